@@ -0,0 +1,3825 @@
+//! C ABI exports for legacy hosts (VB6, VFP9).
+//!
+//! All functions use the `legacybridge_` prefix, take and return
+//! null-terminated UTF-8 strings, and report failure through
+//! [`legacybridge_get_last_error`]. Returned strings must be released with
+//! [`legacybridge_free_string`].
+//!
+//! # Thread safety
+//!
+//! Every export may be called from any thread. Exports are either
+//! stateless (they touch no process-wide state at all) or they go through
+//! the shared slots declared at the top of this file - [`LAST_ERROR`],
+//! [`LAST_ERROR_ENVELOPE`], [`RUNTIME`], [`STARTUP_WARNING`],
+//! [`LAST_FOLDER_REPORT`], [`LAST_STATS`], [`LOG_CALLBACK`] - plus the
+//! [`LIVE_WORKERS`]
+//! counter. All slots
+//! are plain `Mutex`es locked through
+//! [`legacybridge_core::sync::lock_unpoisoned`], so a panic in one call
+//! cannot poison error reporting for the rest of the process. The
+//! last-error slot is per-process, not per-thread: hosts that convert on
+//! several threads should read errors on the thread that saw the failure
+//! before issuing another call. The per-export classification lives in the
+//! `FFI_THREAD_SAFETY` table in this file's tests, which fails when an
+//! export is added without being classified.
+
+use legacybridge_core::config::LegacyBridgeConfig;
+use legacybridge_core::contracts;
+use legacybridge_core::conversion::builder::Conversion;
+use legacybridge_core::conversion::control_words;
+use legacybridge_core::conversion::encoding::{
+    decode_input, safe_write, sanitize_file_stem, InputEncoding, LineEnding, OutputEncoding,
+    SUPPORTED_INPUT_ENCODINGS,
+};
+use legacybridge_core::conversion::environment::ConversionEnvironment;
+use legacybridge_core::conversion::features::FeatureUsage;
+use legacybridge_core::conversion::link_rewrite::LinkRewriteConfig;
+use legacybridge_core::conversion::pipeline::{
+    ConversionStats, PageRange, RecoveryAction, ValidationResult,
+};
+use legacybridge_core::conversion::plain_text::{
+    ConversionDirection, OutputFormat, SUPPORTED_DIRECTIONS,
+};
+use legacybridge_core::conversion::report::{BatchReport, FileReport, FileStatus, ReportFormat};
+use legacybridge_core::conversion::{
+    self, ConversionError, ConversionMode, ConversionPath, PipelineConfig,
+};
+use legacybridge_core::ffi_error_bridge::ErrorEnvelope;
+use legacybridge_core::security::{InputValidator, SanitizationMode, SecurityLimits};
+use legacybridge_core::selftest;
+use legacybridge_core::sync::lock_unpoisoned;
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Error code returned alongside a null pointer; see
+/// [`ConversionError::error_code`] for the full mapping.
+pub const LEGACYBRIDGE_ERROR_INVALID_INPUT: i32 = -1;
+
+static LAST_ERROR: Mutex<String> = Mutex::new(String::new());
+
+/// The last error as a structured [`ErrorEnvelope`], kept in sync with
+/// [`LAST_ERROR`] so hosts can choose the string or the JSON contract.
+static LAST_ERROR_ENVELOPE: Mutex<Option<ErrorEnvelope>> = Mutex::new(None);
+
+/// Process-wide configuration, set by [`legacybridge_initialize`]. `None`
+/// until the first initialize (explicit or automatic).
+static RUNTIME: Mutex<Option<Runtime>> = Mutex::new(None);
+
+/// Folder-conversion workers currently running; [`legacybridge_shutdown`]
+/// waits for this to drain before releasing state.
+static LIVE_WORKERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Set when a conversion export ran before `legacybridge_initialize`.
+static STARTUP_WARNING: Mutex<String> = Mutex::new(String::new());
+
+/// JSON performance counters from the most recent conversion that ran
+/// with `collect_stats` in its options; empty until one does. Read via
+/// [`legacybridge_get_last_stats`].
+static LAST_STATS: Mutex<String> = Mutex::new(String::new());
+
+/// Most verbose level: only errors are delivered to the log callback.
+pub const LEGACYBRIDGE_LOG_ERROR: i32 = 0;
+/// Errors and warnings are delivered to the log callback.
+pub const LEGACYBRIDGE_LOG_WARN: i32 = 1;
+/// Errors, warnings and informational messages are delivered.
+pub const LEGACYBRIDGE_LOG_INFO: i32 = 2;
+/// Everything is delivered. The four levels mirror the `log_level`
+/// values in `legacybridge.toml`, least verbose first.
+pub const LEGACYBRIDGE_LOG_DEBUG: i32 = 3;
+
+/// Messages handed to the log callback are truncated to this many bytes
+/// (on a character boundary) before the NUL terminator is appended, so
+/// fixed-size host buffers have a bound to size against.
+pub const LEGACYBRIDGE_MAX_LOG_MESSAGE_BYTES: usize = 1024;
+
+/// Logging callback for host diagnostics; receives one of the
+/// `LEGACYBRIDGE_LOG_*` levels, a null-terminated UTF-8 message valid
+/// only for the duration of the call, and the `user_data` pointer given
+/// at registration. May be invoked from any thread that calls into the
+/// library.
+pub type LegacyBridgeLogCallback =
+    unsafe extern "C" fn(level: i32, message: *const c_char, user_data: *mut c_void);
+
+/// A registered log callback with its host context and level filter.
+struct LogSink {
+    callback: LegacyBridgeLogCallback,
+    /// Host-owned pointer passed back verbatim and never dereferenced;
+    /// stored as an integer so the slot stays `Send`.
+    user_data: usize,
+    min_level: i32,
+}
+
+/// The installed log callback, set by [`legacybridge_set_log_callback`].
+/// The sink is invoked while this registration guard is held, so a
+/// concurrent [`legacybridge_clear_log_callback`] cannot uninstall it
+/// out from under a call in flight.
+static LOG_CALLBACK: Mutex<Option<LogSink>> = Mutex::new(None);
+
+thread_local! {
+    /// True while the log callback runs on this thread. Conversions
+    /// triggered from inside the callback see it and skip logging, which
+    /// both breaks the recursion and avoids re-locking [`LOG_CALLBACK`].
+    static IN_LOG_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Deliver `message` to the installed log callback, if any, honoring its
+/// level filter. No-op when no callback is installed or when called from
+/// inside the callback itself.
+fn emit_log(level: i32, message: &str) {
+    if IN_LOG_CALLBACK.with(Cell::get) {
+        return;
+    }
+    let guard = lock_unpoisoned(&LOG_CALLBACK);
+    let Some(sink) = guard.as_ref() else {
+        return;
+    };
+    if level > sink.min_level {
+        return;
+    }
+    let mut text = message.replace('\0', " ");
+    if text.len() > LEGACYBRIDGE_MAX_LOG_MESSAGE_BYTES {
+        let mut end = LEGACYBRIDGE_MAX_LOG_MESSAGE_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    let Ok(text) = CString::new(text) else {
+        return;
+    };
+    IN_LOG_CALLBACK.with(|flag| flag.set(true));
+    unsafe { (sink.callback)(level, text.as_ptr(), sink.user_data as *mut c_void) };
+    IN_LOG_CALLBACK.with(|flag| flag.set(false));
+}
+
+#[derive(Default)]
+struct Runtime {
+    limits: SecurityLimits,
+    /// Folder-conversion worker count when the per-call options omit one.
+    default_parallelism: Option<usize>,
+    /// Effective `legacybridge.toml` configuration (defaults when no file
+    /// was found), kept for the capabilities report.
+    config: LegacyBridgeConfig,
+    /// Shared template registry handed to every pipeline, built once at
+    /// initialize and rebuilt by [`legacybridge_reload_templates`].
+    environment: Arc<ConversionEnvironment>,
+}
+
+/// Security limits from the configured runtime, auto-initializing with the
+/// defaults (and recording a warning) when the host skipped
+/// `legacybridge_initialize`.
+fn runtime_limits() -> SecurityLimits {
+    let mut runtime = lock_unpoisoned(&RUNTIME);
+    match &*runtime {
+        Some(r) => r.limits.clone(),
+        None => {
+            let warning = "conversion called before legacybridge_initialize; defaults applied";
+            emit_log(LEGACYBRIDGE_LOG_WARN, warning);
+            *lock_unpoisoned(&STARTUP_WARNING) = warning.to_string();
+            let r = Runtime::default();
+            let limits = r.limits.clone();
+            *runtime = Some(r);
+            limits
+        }
+    }
+}
+
+/// The shared [`ConversionEnvironment`], auto-initializing like
+/// [`runtime_limits`] when the host skipped `legacybridge_initialize`.
+fn runtime_environment() -> Arc<ConversionEnvironment> {
+    runtime_limits(); // ensure the runtime exists
+    lock_unpoisoned(&RUNTIME)
+        .as_ref()
+        .map(|r| Arc::clone(&r.environment))
+        .unwrap_or_default()
+}
+
+fn runtime_default_parallelism() -> Option<usize> {
+    lock_unpoisoned(&RUNTIME)
+        .as_ref()
+        .and_then(|r| r.default_parallelism)
+}
+
+fn set_last_error(message: String) {
+    emit_log(LEGACYBRIDGE_LOG_ERROR, &message);
+    *lock_unpoisoned(&LAST_ERROR_ENVELOPE) = Some(ErrorEnvelope::invalid_input(message.clone()));
+    *lock_unpoisoned(&LAST_ERROR) = message;
+}
+
+fn clear_last_error() {
+    lock_unpoisoned(&LAST_ERROR).clear();
+    lock_unpoisoned(&LAST_ERROR_ENVELOPE).take();
+}
+
+/// Read a C string argument; records an error and returns `None` on NULL or
+/// invalid UTF-8.
+unsafe fn read_input(ptr: *const c_char, what: &str) -> Option<String> {
+    if ptr.is_null() {
+        set_last_error(format!("{what}: null pointer"));
+        return None;
+    }
+    match unsafe { CStr::from_ptr(ptr) }.to_str() {
+        Ok(s) => Some(s.to_string()),
+        Err(_) => {
+            set_last_error(format!("{what}: invalid UTF-8"));
+            None
+        }
+    }
+}
+
+fn into_c_string(s: String) -> *mut c_char {
+    match CString::new(s) {
+        Ok(c) => c.into_raw(),
+        Err(_) => {
+            set_last_error("output contained an interior NUL byte".to_string());
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Record a [`ConversionError`] in both error slots, keeping its
+/// specific code and category (unlike [`set_last_error`], which records
+/// a bridge-level input error).
+fn record_error(err: &ConversionError) {
+    let message = err.to_string();
+    emit_log(LEGACYBRIDGE_LOG_ERROR, &message);
+    *lock_unpoisoned(&LAST_ERROR) = message;
+    *lock_unpoisoned(&LAST_ERROR_ENVELOPE) = Some(ErrorEnvelope::from(err));
+}
+
+fn report(err: ConversionError) -> *mut c_char {
+    record_error(&err);
+    std::ptr::null_mut()
+}
+
+/// Convert RTF to Markdown. Returns a newly allocated string, or NULL on
+/// failure (see `legacybridge_get_last_error`).
+///
+/// # Safety
+/// `rtf` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_rtf_to_markdown(rtf: *const c_char) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(rtf, "rtf input") }) else {
+        return std::ptr::null_mut();
+    };
+    match conversion::secure_rtf_to_markdown(&input, &runtime_limits()) {
+        Ok(markdown) => into_c_string(markdown),
+        Err(e) => report(e),
+    }
+}
+
+/// Conversion options accepted as a JSON document by the `_with_options`
+/// exports, so VB6/VFP9 callers can pass settings without new C structs.
+/// Unknown fields are ignored; missing fields use the defaults. An
+/// optional top-level `v` field names the document's contract major
+/// (missing means version 1); documents from a future major are rejected
+/// with a `contract` error rather than misread.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct LegacyBridgeOptions {
+    /// First page to convert (1-based, inclusive).
+    page_start: Option<usize>,
+    /// Last page to convert (inclusive); defaults to `page_start`.
+    page_end: Option<usize>,
+    /// Worker threads for folder conversion; defaults to the CPU count.
+    max_parallelism: Option<usize>,
+    /// Line ending for written output files: `lf`, `crlf` or `platform`
+    /// (the default).
+    line_ending: Option<LineEnding>,
+    /// Prepend a UTF-8 BOM to written Markdown files. Written RTF never
+    /// carries one regardless.
+    bom: Option<bool>,
+    /// Ensure written files end with exactly one line ending.
+    trailing_newline: Option<bool>,
+    /// Character encoding of input files, overriding BOM/UTF-8
+    /// auto-detection - "everything in this folder is cp866". An
+    /// unsupported name fails the call before any file is processed.
+    input_encoding: Option<String>,
+    /// Per-file overrides of `input_encoding` for folder conversion,
+    /// keyed by file name within the input folder.
+    input_encodings: Option<std::collections::HashMap<String, String>>,
+    /// What a folder run converts: `rtf_to_markdown` (the default),
+    /// `rtf_to_plain_text` or `plain_text_to_rtf`. The direction decides
+    /// which extension the folder scan picks up (`.rtf` or `.txt`) and
+    /// what the outputs are named; an unsupported name fails the call
+    /// before any file is processed.
+    direction: Option<String>,
+    /// Retry files that fail the RTF->Markdown conversion with the
+    /// fallback chain - progressively more permissive configurations
+    /// tried in order - before declaring them failed. Off by default;
+    /// uses the built-in chain (tolerant validation and sanitization,
+    /// then legacy mode with fragment acceptance) unless
+    /// `fallback_chain` names one.
+    retry_with_fallbacks: Option<bool>,
+    /// Explicit fallback chain, tried in order after the base
+    /// configuration; setting it implies `retry_with_fallbacks`. See
+    /// [`FallbackOverlay`] for the fields an entry may override.
+    fallback_chain: Option<Vec<FallbackOverlay>>,
+    /// Total per-file time budget across fallback attempts, in
+    /// milliseconds; a file still unconverted when it runs out is failed
+    /// with its last error. Defaults to 20000.
+    fallback_budget_ms: Option<u64>,
+    /// Cap on the summed input sizes of conversions in flight during a
+    /// folder run; workers wait for budget before starting a file.
+    /// Unset means no budget.
+    max_inflight_bytes: Option<usize>,
+    /// Process folder files in name order instead of smallest-first.
+    preserve_order: Option<bool>,
+    /// Suffix appended to output names whose stem is a Windows-reserved
+    /// device name (CON, PRN, COM1...); defaults to `_file`.
+    reserved_name_suffix: Option<String>,
+    /// Name outputs after the document's `\info` title, or its first
+    /// heading, instead of the input stem: the title is slugified,
+    /// truncated and deduplicated with `-2`/`-3`... suffixes within the
+    /// batch. Files without a title keep the stem-based name. Off by
+    /// default.
+    name_from_title: Option<bool>,
+    /// With `name_from_title`, maximum length of the title-derived stem
+    /// in characters; longer slugs are truncated. Defaults to 60.
+    title_name_length: Option<usize>,
+    /// Accept RTF fragments without the outer `{\rtf1 ...}` wrapper, as
+    /// VFP9 memo fields store them. Off by default.
+    allow_fragment: Option<bool>,
+    /// Shift heading levels by this many steps (positive demotes,
+    /// negative promotes), clamping to 1..=6, for embedding converted
+    /// content under an existing document's hierarchy. Defaults to 0.
+    heading_offset: Option<i8>,
+    /// Execution path: `auto`, `simple` or `pipeline` (the default here:
+    /// options like page ranges need the pipeline, and `simple` or an
+    /// `auto` simple resolution ignores them).
+    conversion_mode: Option<ConversionMode>,
+    /// Hyperlink rewrite rules applied after parsing: an inline
+    /// [`LinkRewriteConfig`](conversion::link_rewrite::LinkRewriteConfig)
+    /// object (`{"rules": [...], "unmatched": "warn"}`). Pipeline path
+    /// only; unset means no rewriting.
+    link_rewrite: Option<LinkRewriteConfig>,
+    /// Collect performance counters for the conversion (input size, token
+    /// and node counts, SIMD level, per-stage durations) and store them
+    /// as JSON readable via `legacybridge_get_last_stats`. Pipeline path
+    /// only; off by default.
+    collect_stats: Option<bool>,
+    /// Keep a state file in the output folder and skip inputs whose
+    /// content and options are unchanged since the recorded run, with the
+    /// output still present. Off by default.
+    incremental: Option<bool>,
+    /// With `incremental`, reconvert everything regardless of the state
+    /// file, which is still refreshed for the next run.
+    force: Option<bool>,
+    /// With `incremental`, delete outputs whose recorded input no longer
+    /// exists in the input folder.
+    clean_removed: Option<bool>,
+    /// Stage folder outputs in a temporary directory inside the
+    /// destination and only move them into place when the run meets the
+    /// publish threshold, so a half-failed batch leaves the destination
+    /// exactly as it was. Off by default.
+    transactional: Option<bool>,
+    /// With `transactional`, minimum percentage of scheduled files that
+    /// must convert for the outputs to be published. Defaults to 100.
+    publish_threshold: Option<f64>,
+    /// Write a machine-readable report of the folder run to this path for
+    /// CI artifacts; a write failure fails the run so the pipeline
+    /// notices the missing artifact.
+    output_report_path: Option<String>,
+    /// Format of that report: `json` (the default, versioned via its
+    /// `report_version` field) or `junit_xml` (one test case per file).
+    report_format: Option<ReportFormat>,
+    /// Contract major to emit the folder and batch reports under;
+    /// defaults to the current major. Version 1 reproduces the shape
+    /// written before contract versioning (no `v` field), for consumers
+    /// that reject unknown fields.
+    contract_version: Option<u32>,
+}
+
+impl LegacyBridgeOptions {
+    fn into_config(self) -> PipelineConfig {
+        let page_range = self.page_start.map(|start| PageRange {
+            start,
+            end: self.page_end.unwrap_or(start),
+        });
+        PipelineConfig {
+            page_range,
+            output_encoding: self.output_encoding(),
+            allow_fragment: self.allow_fragment.unwrap_or(false),
+            heading_offset: self.heading_offset.unwrap_or(0),
+            link_rewrite: self.link_rewrite,
+            collect_stats: self.collect_stats.unwrap_or(false),
+            ..Default::default()
+        }
+    }
+
+    fn output_encoding(&self) -> OutputEncoding {
+        OutputEncoding {
+            line_ending: self.line_ending.unwrap_or_default(),
+            bom: self.bom.unwrap_or(false),
+            trailing_newline: self.trailing_newline.unwrap_or(false),
+        }
+    }
+}
+
+/// Parse an options JSON argument; NULL or empty means the defaults.
+/// Records an error and returns `None` on invalid UTF-8 or invalid JSON.
+unsafe fn read_options<T>(options_json: *const c_char) -> Option<T>
+where
+    T: Default + serde::de::DeserializeOwned,
+{
+    if options_json.is_null() {
+        return Some(T::default());
+    }
+    let json = unsafe { read_input(options_json, "options") }?;
+    if json.trim().is_empty() {
+        return Some(T::default());
+    }
+    // A payload from the future is a specific error, not a JSON error; a
+    // missing `v` field is contract version 1 (the pre-`v` shape).
+    if let Err(e) = contracts::check_payload_version(&json) {
+        record_error(&e);
+        return None;
+    }
+    match serde_json::from_str(&json) {
+        Ok(options) => Some(options),
+        Err(e) => {
+            set_last_error(format!("options: invalid JSON: {e}"));
+            None
+        }
+    }
+}
+
+/// Convert RTF to Markdown with options (see [`LegacyBridgeOptions`]).
+/// `options_json` may be NULL or empty for the defaults. Returns a newly
+/// allocated string, or NULL on failure.
+///
+/// # Safety
+/// `rtf` must be a valid null-terminated string or NULL; `options_json`
+/// must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_rtf_to_markdown_with_options(
+    rtf: *const c_char,
+    options_json: *const c_char,
+) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(rtf, "rtf input") }) else {
+        return std::ptr::null_mut();
+    };
+    let Some(options) = (unsafe { read_options::<LegacyBridgeOptions>(options_json) }) else {
+        return std::ptr::null_mut();
+    };
+    if let Err(reason) = InputValidator::new(runtime_limits()).validate_rtf_input(&input) {
+        return report(ConversionError::validation(reason));
+    }
+    let mode = options.conversion_mode.unwrap_or(ConversionMode::Pipeline);
+    match conversion::resolve_conversion_path(&input, mode) {
+        Ok(ConversionPath::Pipeline) => {
+            match Conversion::rtf_to_markdown(&input)
+                .config(options.into_config())
+                .environment(runtime_environment())
+                .run_full()
+            {
+                Ok(output) => {
+                    record_stats(output.stats.as_ref());
+                    into_c_string(output.markdown)
+                }
+                Err(e) => report(e),
+            }
+        }
+        Ok(ConversionPath::Simple) => {
+            match conversion::rtf_to_markdown_with_mode(&input, ConversionMode::Simple) {
+                Ok((markdown, _)) => into_c_string(markdown),
+                Err(e) => report(e),
+            }
+        }
+        Err(e) => report(e),
+    }
+}
+
+/// Store a `collect_stats` run's counters in the last-stats slot; runs
+/// that don't collect leave the slot as it was.
+fn record_stats(stats: Option<&ConversionStats>) {
+    let Some(stats) = stats else { return };
+    match serde_json::to_string(stats) {
+        Ok(json) => *lock_unpoisoned(&LAST_STATS) = json,
+        Err(e) => set_last_error(format!("cannot serialize conversion stats: {e}")),
+    }
+}
+
+/// Convert Markdown to RTF. Returns a newly allocated string, or NULL on
+/// failure.
+///
+/// # Safety
+/// `markdown` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_markdown_to_rtf(markdown: *const c_char) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(markdown, "markdown input") }) else {
+        return std::ptr::null_mut();
+    };
+    match conversion::secure_markdown_to_rtf(&input, &runtime_limits()) {
+        Ok(rtf) => into_c_string(rtf),
+        Err(e) => report(e),
+    }
+}
+
+/// Convert Markdown to RTF with an embedded `{\*\lbsig ...}` integrity
+/// signature (input and output hashes, converter version, options
+/// fingerprint, timestamp), which [`legacybridge_verify_integrity`]
+/// later checks. Returns a newly allocated string, or NULL on failure.
+///
+/// # Safety
+/// `markdown` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_markdown_to_rtf_with_integrity(
+    markdown: *const c_char,
+) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(markdown, "markdown input") }) else {
+        return std::ptr::null_mut();
+    };
+    let rtf = match conversion::secure_markdown_to_rtf(&input, &runtime_limits()) {
+        Ok(rtf) => rtf,
+        Err(e) => return report(e),
+    };
+    let fingerprint = PipelineConfig::default().fingerprint(&SecurityLimits::default());
+    match conversion::integrity::sign_rtf(&rtf, &input, &fingerprint) {
+        Ok(signed) => into_c_string(signed),
+        Err(e) => report(ConversionError::generation(e)),
+    }
+}
+
+/// Verdict of [`legacybridge_verify_integrity`], serialized as JSON.
+#[derive(Serialize)]
+struct IntegrityVerdict {
+    /// The signature block was found and the content still hashes to
+    /// what it records.
+    valid: bool,
+    error: Option<String>,
+    block: Option<legacybridge_core::conversion::integrity::IntegrityBlock>,
+}
+
+/// Verify the integrity signature embedded in converted content (a
+/// Markdown trailer or an RTF `{\*\lbsig ...}` block): re-hashes the
+/// content minus the block and compares against the recorded output
+/// hash. Returns a newly allocated JSON verdict (`valid`, `error`,
+/// `block`); tampered or unsigned content is a `valid: false` verdict,
+/// not a NULL return.
+///
+/// # Safety
+/// `content` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_verify_integrity(content: *const c_char) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(content, "signed content") }) else {
+        return std::ptr::null_mut();
+    };
+    let verdict = match conversion::integrity::verify_integrity(&input) {
+        Ok(block) => IntegrityVerdict {
+            valid: true,
+            error: None,
+            block: Some(block),
+        },
+        Err(e) => IntegrityVerdict {
+            valid: false,
+            error: Some(e),
+            block: None,
+        },
+    };
+    match serde_json::to_string(&verdict) {
+        Ok(json) => into_c_string(json),
+        Err(e) => report(ConversionError::generation(e.to_string())),
+    }
+}
+
+/// Extract form fields from a legacy RTF form. Returns a newly allocated
+/// JSON array of field objects (`type`, `name`, `default`, `value`), or
+/// NULL on failure.
+///
+/// # Safety
+/// `rtf` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_extract_form_fields(rtf: *const c_char) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(rtf, "rtf input") }) else {
+        return std::ptr::null_mut();
+    };
+    if let Err(reason) = InputValidator::new(runtime_limits())
+        .with_form_fields_allowed(true)
+        .validate_rtf_input(&input)
+    {
+        return report(ConversionError::validation(reason));
+    }
+    let config = PipelineConfig {
+        extract_form_fields: true,
+        ..Default::default()
+    };
+    match Conversion::rtf_to_markdown(&input)
+        .config(config)
+        .environment(runtime_environment())
+        .run_full()
+    {
+        Ok(output) => match serde_json::to_string(&output.form_fields) {
+            Ok(json) => into_c_string(json),
+            Err(e) => report(ConversionError::generation(e.to_string())),
+        },
+        Err(e) => report(e),
+    }
+}
+
+/// Verdict of [`legacybridge_validate_deep`], serialized as JSON.
+#[derive(Serialize)]
+struct DeepValidationReport {
+    /// Whether a full conversion of this document would succeed.
+    valid: bool,
+    error: Option<String>,
+    error_code: i32,
+    validation_results: Vec<legacybridge_core::conversion::pipeline::ValidationResult>,
+    recovery_actions: Vec<legacybridge_core::conversion::pipeline::RecoveryAction>,
+    feature_usage: FeatureUsage,
+}
+
+/// Deep-validate an RTF document without generating output: runs the
+/// pipeline's validation and parsing stages only, so a host can estimate
+/// batch failure rates at a fraction of conversion cost. Returns a newly
+/// allocated JSON report (`valid`, `error`, `error_code`,
+/// `validation_results`, `recovery_actions`, `feature_usage`); an invalid
+/// document is a `valid: false` report, not a NULL return.
+///
+/// # Safety
+/// `rtf` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_validate_deep(rtf: *const c_char) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(rtf, "rtf input") }) else {
+        return std::ptr::null_mut();
+    };
+    let verdict = match conversion::validate_rtf_deep(&input, &runtime_limits()) {
+        Ok(output) => DeepValidationReport {
+            valid: true,
+            error: None,
+            error_code: 0,
+            validation_results: output.validation_results,
+            recovery_actions: output.recovery_actions,
+            feature_usage: output.feature_usage,
+        },
+        Err(e) => DeepValidationReport {
+            valid: false,
+            error: Some(e.to_string()),
+            error_code: e.error_code(),
+            validation_results: Vec::new(),
+            recovery_actions: Vec::new(),
+            feature_usage: FeatureUsage::default(),
+        },
+    };
+    match serde_json::to_string(&verdict) {
+        Ok(json) => into_c_string(json),
+        Err(e) => report(ConversionError::generation(e.to_string())),
+    }
+}
+
+/// Analyze a Markdown document without converting it: heading outline,
+/// code block languages, table dimensions, link/image counts, footnote
+/// and definition-list usage, HTML tags, the predicted RTF output size
+/// and which features have no native RTF mapping. Returns a newly
+/// allocated JSON object, or NULL on failure (see
+/// `legacybridge_get_last_error`).
+///
+/// # Safety
+/// `markdown` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_analyze_markdown(markdown: *const c_char) -> *mut c_char {
+    clear_last_error();
+    let Some(input) = (unsafe { read_input(markdown, "markdown input") }) else {
+        return std::ptr::null_mut();
+    };
+    match legacybridge_core::conversion::markdown_analysis::analyze_markdown(&input) {
+        Ok(analysis) => match serde_json::to_string(&analysis) {
+            Ok(json) => into_c_string(json),
+            Err(e) => report(ConversionError::generation(e.to_string())),
+        },
+        Err(e) => report(e),
+    }
+}
+
+/// Look up conversion support for an RTF control word (without the
+/// backslash). Returns a newly allocated JSON object (`name`, `category`,
+/// `support_level`, `since_version`, `notes`), the full table as a JSON
+/// array when `word` is NULL or empty, or NULL with a last-error message
+/// for a word LegacyBridge does not recognize.
+///
+/// # Safety
+/// `word` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_control_word_info(word: *const c_char) -> *mut c_char {
+    clear_last_error();
+    let queried = if word.is_null() {
+        String::new()
+    } else {
+        let Some(queried) = (unsafe { read_input(word, "control word") }) else {
+            return std::ptr::null_mut();
+        };
+        queried
+    };
+    let queried = queried.trim().trim_start_matches('\\');
+    let json = if queried.is_empty() {
+        serde_json::to_string(control_words::control_word_table())
+    } else {
+        match control_words::control_word_info(queried) {
+            Some(info) => serde_json::to_string(&info),
+            None => {
+                set_last_error(format!("\\{queried} is not a recognized control word"));
+                return std::ptr::null_mut();
+            }
+        }
+    };
+    match json {
+        Ok(json) => into_c_string(json),
+        Err(e) => report(ConversionError::generation(e.to_string())),
+    }
+}
+
+/// Convert an RTF file to a Markdown file. Returns 1 on success, a negative
+/// error code on failure.
+///
+/// # Safety
+/// Both paths must be valid null-terminated strings or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_convert_rtf_file_to_md(
+    input_path: *const c_char,
+    output_path: *const c_char,
+) -> i32 {
+    clear_last_error();
+    let (Some(input_path), Some(output_path)) = (unsafe { read_input(input_path, "input path") }, unsafe {
+        read_input(output_path, "output path")
+    }) else {
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    };
+    let rtf = match std::fs::read(&input_path) {
+        Ok(bytes) => decode_input(&bytes, None).0,
+        Err(e) => {
+            set_last_error(format!("cannot read {input_path}: {e}"));
+            return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+        }
+    };
+    match conversion::secure_rtf_to_markdown(&rtf, &runtime_limits()) {
+        Ok(markdown) => match safe_write(&output_path, &markdown, &OutputEncoding::default()) {
+            Ok(()) => 1,
+            Err(e) => {
+                set_last_error(format!("cannot write {output_path}: {e}"));
+                LEGACYBRIDGE_ERROR_INVALID_INPUT
+            }
+        },
+        Err(e) => {
+            let code = e.error_code();
+            set_last_error(e.to_string());
+            code
+        }
+    }
+}
+
+/// [`legacybridge_convert_rtf_file_to_md`] with options JSON (see
+/// [`LegacyBridgeOptions`]): `input_encoding` overrides the input
+/// auto-detection when the host knows the file's code page, and the
+/// output-encoding fields control the written file. An unsupported
+/// encoding name fails before the file is read.
+///
+/// # Safety
+/// All pointers must be valid null-terminated strings or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_convert_rtf_file_to_md_with_options(
+    input_path: *const c_char,
+    output_path: *const c_char,
+    options_json: *const c_char,
+) -> i32 {
+    clear_last_error();
+    let (Some(input_path), Some(output_path)) = (unsafe { read_input(input_path, "input path") }, unsafe {
+        read_input(output_path, "output path")
+    }) else {
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    };
+    let Some(options) = (unsafe { read_options::<LegacyBridgeOptions>(options_json) }) else {
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    };
+    let forced = match input_encoding_overrides(&options) {
+        Ok((global, _)) => global,
+        Err(message) => {
+            set_last_error(message);
+            return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+        }
+    };
+    let rtf = match std::fs::read(&input_path) {
+        Ok(bytes) => decode_input(&bytes, forced).0,
+        Err(e) => {
+            set_last_error(format!("cannot read {input_path}: {e}"));
+            return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+        }
+    };
+    match conversion::secure_rtf_to_markdown(&rtf, &runtime_limits()) {
+        Ok(markdown) => match safe_write(&output_path, &markdown, &options.output_encoding()) {
+            Ok(()) => 1,
+            Err(e) => {
+                set_last_error(format!("cannot write {output_path}: {e}"));
+                LEGACYBRIDGE_ERROR_INVALID_INPUT
+            }
+        },
+        Err(e) => {
+            let code = e.error_code();
+            set_last_error(e.to_string());
+            code
+        }
+    }
+}
+
+/// Convert a Markdown file to an RTF file. Returns 1 on success, a negative
+/// error code on failure.
+///
+/// # Safety
+/// Both paths must be valid null-terminated strings or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_convert_md_file_to_rtf(
+    input_path: *const c_char,
+    output_path: *const c_char,
+) -> i32 {
+    clear_last_error();
+    let (Some(input_path), Some(output_path)) = (unsafe { read_input(input_path, "input path") }, unsafe {
+        read_input(output_path, "output path")
+    }) else {
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    };
+    let markdown = match std::fs::read_to_string(&input_path) {
+        Ok(content) => content,
+        Err(e) => {
+            set_last_error(format!("cannot read {input_path}: {e}"));
+            return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+        }
+    };
+    match conversion::secure_markdown_to_rtf(&markdown, &runtime_limits()) {
+        // RTF is always written CRLF without a BOM; see OutputEncoding::rtf.
+        Ok(rtf) => match safe_write(&output_path, &rtf, &OutputEncoding::rtf()) {
+            Ok(()) => 1,
+            Err(e) => {
+                set_last_error(format!("cannot write {output_path}: {e}"));
+                LEGACYBRIDGE_ERROR_INVALID_INPUT
+            }
+        },
+        Err(e) => {
+            let code = e.error_code();
+            set_last_error(e.to_string());
+            code
+        }
+    }
+}
+
+/// Progress callback for folder conversion; receives the number of files
+/// finished so far and the total. Calls are serialized, so `current` is
+/// strictly increasing even when conversion runs on multiple workers.
+pub type LegacyBridgeProgressCallback = unsafe extern "C" fn(current: u32, total: u32);
+
+static LAST_FOLDER_REPORT: Mutex<String> = Mutex::new(String::new());
+
+#[derive(Serialize)]
+struct FolderFailure {
+    file: String,
+    code: i32,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct FolderWait {
+    file: String,
+    wait_ms: u64,
+}
+
+#[derive(Serialize)]
+struct FolderRename {
+    file: String,
+    output: String,
+}
+
+#[derive(Serialize)]
+struct FolderReport {
+    /// Contract major of this payload; omitted when emitting version 1.
+    #[serde(skip_serializing_if = "contracts::is_version_one")]
+    v: u32,
+    total: usize,
+    converted: usize,
+    /// Whether the outputs landed in the destination. `false` only for a
+    /// `transactional` run that missed its publish threshold; such a run
+    /// leaves the destination untouched.
+    published: bool,
+    failures: Vec<FolderFailure>,
+    /// Unsupported constructs across all converted files, merged, so the
+    /// host can scope what a batch migration will lose.
+    feature_usage: FeatureUsage,
+    /// Files that waited for the in-flight byte budget, with the wait in
+    /// milliseconds; empty when no budget is configured or nothing waited.
+    budget_waits: Vec<FolderWait>,
+    /// Highest sum of input sizes in flight at once, in bytes; 0 when no
+    /// budget is configured.
+    inflight_high_water: usize,
+    /// Files whose output name was adjusted for Windows (reserved device
+    /// stem, trailing dots/spaces), with the name actually written.
+    adjusted_names: Vec<FolderRename>,
+    /// With `name_from_title`, files whose output was renamed to a
+    /// title-derived slug, with the name actually written; empty when the
+    /// option is off or every title matched its stem.
+    renamed_from_titles: Vec<FolderRename>,
+    /// Files skipped as up to date under `incremental`: content and
+    /// options unchanged since the recorded run, output still present.
+    skipped: Vec<String>,
+    /// Outputs deleted under `clean_removed` because their input is gone.
+    removed_outputs: Vec<String>,
+}
+
+/// Name of the incremental state file kept in the output folder.
+const FOLDER_STATE_FILE: &str = ".legacybridge-state.json";
+
+/// Per-run record for incremental folder conversion: what each input
+/// hashed to, where its output went, and when.
+#[derive(Default, Serialize, Deserialize)]
+struct FolderState {
+    /// Fingerprint of the output-affecting options; a mismatch
+    /// invalidates every entry, so an options change reconverts.
+    options_fingerprint: u64,
+    files: std::collections::BTreeMap<String, FolderStateEntry>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct FolderStateEntry {
+    hash: u64,
+    output: String,
+    /// Seconds since the Unix epoch at conversion time.
+    timestamp: u64,
+}
+
+/// FNV-1a over the input bytes: stable across runs and toolchain
+/// upgrades, unlike `DefaultHasher`, so old state files stay usable.
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Fingerprint of the options that change what a folder run writes
+/// (direction, output encoding, name sanitization, input-encoding
+/// overrides, fallback chain - an overlay can change the written
+/// Markdown); the rest only affect scheduling and can differ between
+/// runs without invalidating outputs. The direction and chain arrive
+/// resolved so `rtf_to_md` and `rtf_to_markdown` fingerprint
+/// identically.
+fn options_fingerprint(
+    options: &LegacyBridgeOptions,
+    direction: ConversionDirection,
+    fallbacks: &[FallbackOverlay],
+) -> u64 {
+    let encoding = options.output_encoding();
+    let mut summary = format!(
+        "{}|{:?}|{}|{}|{}|{}|{}|{}",
+        direction.name(),
+        encoding.line_ending,
+        encoding.bom,
+        encoding.trailing_newline,
+        options.reserved_name_suffix.as_deref().unwrap_or("_file"),
+        options.input_encoding.as_deref().unwrap_or(""),
+        options.name_from_title.unwrap_or(false),
+        options.title_name_length.unwrap_or(DEFAULT_TITLE_NAME_LENGTH),
+    );
+    if let Some(overrides) = &options.input_encodings {
+        let mut entries: Vec<_> = overrides.iter().collect();
+        entries.sort();
+        for (file, name) in entries {
+            summary.push_str(&format!("|{file}={name}"));
+        }
+    }
+    if !fallbacks.is_empty() {
+        summary.push_str(&format!("|{fallbacks:?}"));
+    }
+    fnv1a_64(summary.as_bytes())
+}
+
+/// Resolve the input-encoding overrides from the options, rejecting any
+/// unsupported name so a typo surfaces before the first file is read.
+fn input_encoding_overrides(
+    options: &LegacyBridgeOptions,
+) -> Result<(Option<InputEncoding>, std::collections::HashMap<String, InputEncoding>), String> {
+    let parse = |name: &str| {
+        InputEncoding::from_name(name).ok_or_else(|| {
+            format!(
+                "unsupported input encoding {name:?}; supported: {}",
+                SUPPORTED_INPUT_ENCODINGS.join(", ")
+            )
+        })
+    };
+    let global = options.input_encoding.as_deref().map(parse).transpose()?;
+    let mut per_file = std::collections::HashMap::new();
+    if let Some(overrides) = &options.input_encodings {
+        for (file, name) in overrides {
+            per_file.insert(file.clone(), parse(name)?);
+        }
+    }
+    Ok((global, per_file))
+}
+
+/// Read the state file from a previous run. A missing or unreadable file
+/// or a different options fingerprint means nothing can be skipped.
+fn load_folder_state(path: &Path, fingerprint: u64) -> FolderState {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|json| serde_json::from_str::<FolderState>(&json).ok())
+        .filter(|state| state.options_fingerprint == fingerprint)
+        .unwrap_or_default()
+}
+
+/// Global in-flight byte budget for a folder run: workers block in
+/// [`acquire`](Self::acquire) until the file fits under the cap. Files
+/// larger than the cap are charged the whole cap so they run alone
+/// rather than deadlocking.
+struct ByteBudget {
+    cap: usize,
+    /// (bytes in flight, high-water mark).
+    state: Mutex<(usize, usize)>,
+    available: std::sync::Condvar,
+}
+
+impl ByteBudget {
+    fn new(cap: usize) -> Self {
+        ByteBudget {
+            cap: cap.max(1),
+            state: Mutex::new((0, 0)),
+            available: std::sync::Condvar::new(),
+        }
+    }
+
+    /// Block until `bytes` fit under the cap, returning the wait time.
+    fn acquire(&self, bytes: usize) -> std::time::Duration {
+        let bytes = bytes.min(self.cap);
+        let start = std::time::Instant::now();
+        let mut state = lock_unpoisoned(&self.state);
+        while state.0 + bytes > self.cap {
+            state = self
+                .available
+                .wait(state)
+                .unwrap_or_else(std::sync::PoisonError::into_inner);
+        }
+        state.0 += bytes;
+        state.1 = state.1.max(state.0);
+        start.elapsed()
+    }
+
+    fn release(&self, bytes: usize) {
+        let bytes = bytes.min(self.cap);
+        lock_unpoisoned(&self.state).0 -= bytes;
+        self.available.notify_all();
+    }
+
+    fn high_water(&self) -> usize {
+        lock_unpoisoned(&self.state).1
+    }
+}
+
+/// Output file name for a folder input, with the stem sanitized for
+/// Windows and the extension of the run's output format. Built as a
+/// full name: `with_extension` would truncate stems that themselves
+/// contain a dot (`report.v2` -> `report.md`).
+fn output_file_name(input: &Path, reserved_suffix: &str, format: OutputFormat) -> String {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    format!(
+        "{}.{}",
+        sanitize_file_stem(&stem, reserved_suffix),
+        format.extension()
+    )
+}
+
+/// Default for `title_name_length` in the folder options.
+const DEFAULT_TITLE_NAME_LENGTH: usize = 60;
+
+/// Slug for a title-derived output stem: alphanumerics lowercased, every
+/// other run of characters - including the ones illegal in Windows file
+/// names - collapsed to a single `-`, then truncated to `max_length`
+/// characters. Empty for a title with no usable characters. The result
+/// still goes through [`sanitize_file_stem`], so a title that is
+/// literally a reserved device name gets the suffix like any other stem.
+fn slugify_title(title: &str, max_length: usize) -> String {
+    let mut slug = String::new();
+    let mut pending_dash = false;
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            if pending_dash && !slug.is_empty() {
+                slug.push('-');
+            }
+            pending_dash = false;
+            slug.extend(c.to_lowercase());
+        } else {
+            pending_dash = true;
+        }
+    }
+    let truncated: String = slug.chars().take(max_length.max(1)).collect();
+    truncated.trim_end_matches('-').to_string()
+}
+
+/// One entry of the fallback chain: a partial overlay of permissive
+/// settings applied over the base configuration for a retry. Unset
+/// fields keep the base value.
+#[derive(Debug, Clone, Default, Deserialize)]
+struct FallbackOverlay {
+    /// Name recorded in the report when this overlay converts the file;
+    /// entries without one are reported by position.
+    label: Option<String>,
+    strict_validation: Option<bool>,
+    auto_recovery: Option<bool>,
+    legacy_mode: Option<bool>,
+    allow_fragment: Option<bool>,
+    sanitization_mode: Option<SanitizationMode>,
+}
+
+impl FallbackOverlay {
+    fn apply(&self, mut config: PipelineConfig) -> PipelineConfig {
+        if let Some(strict_validation) = self.strict_validation {
+            config.strict_validation = strict_validation;
+        }
+        if let Some(auto_recovery) = self.auto_recovery {
+            config.auto_recovery = auto_recovery;
+        }
+        if let Some(legacy_mode) = self.legacy_mode {
+            config.legacy_mode = legacy_mode;
+        }
+        if let Some(allow_fragment) = self.allow_fragment {
+            config.allow_fragment = allow_fragment;
+        }
+        if let Some(sanitization_mode) = self.sanitization_mode {
+            config.sanitization_mode = sanitization_mode;
+        }
+        config
+    }
+}
+
+/// The built-in chain used when `retry_with_fallbacks` is set without an
+/// explicit `fallback_chain`: first everything tolerant (lenient
+/// validation, recovery on, oversized payloads sanitized to
+/// placeholders), then the legacy reading that also accepts headerless
+/// memo-field fragments.
+fn default_fallback_chain() -> Vec<FallbackOverlay> {
+    vec![
+        FallbackOverlay {
+            label: Some("tolerant".to_string()),
+            strict_validation: Some(false),
+            auto_recovery: Some(true),
+            sanitization_mode: Some(SanitizationMode::Placeholder),
+            ..Default::default()
+        },
+        FallbackOverlay {
+            label: Some("legacy_fragment".to_string()),
+            legacy_mode: Some(true),
+            allow_fragment: Some(true),
+            ..Default::default()
+        },
+    ]
+}
+
+/// Per-file time budget across fallback attempts when the options do
+/// not set one.
+const DEFAULT_FALLBACK_BUDGET_MS: u64 = 20_000;
+
+/// The resolved retry policy of a folder run: the overlays to try after
+/// the base configuration and the per-file time budget across attempts.
+struct FallbackPolicy {
+    chain: Vec<FallbackOverlay>,
+    budget: std::time::Duration,
+}
+
+impl FallbackPolicy {
+    /// Resolve the policy from the run options: an explicit chain
+    /// implies the feature, `retry_with_fallbacks` alone picks the
+    /// built-in one, and neither leaves the chain empty.
+    fn resolve(options: &LegacyBridgeOptions) -> FallbackPolicy {
+        let chain = match &options.fallback_chain {
+            Some(chain) => chain.clone(),
+            None if options.retry_with_fallbacks.unwrap_or(false) => default_fallback_chain(),
+            None => Vec::new(),
+        };
+        FallbackPolicy {
+            chain,
+            budget: std::time::Duration::from_millis(
+                options.fallback_budget_ms.unwrap_or(DEFAULT_FALLBACK_BUDGET_MS),
+            ),
+        }
+    }
+}
+
+/// Why one folder file failed: error code, message, and how many
+/// configurations were tried before giving up.
+struct FileFailure {
+    code: i32,
+    message: String,
+    attempts: u32,
+}
+
+/// What one successfully converted folder file yields for the run report.
+struct FileOutcome {
+    usage: FeatureUsage,
+    /// Output file name when it had to be sanitized for Windows (reserved
+    /// stem, trailing dots/spaces).
+    adjusted: Option<String>,
+    warnings: Vec<ValidationResult>,
+    recovery_actions: Vec<RecoveryAction>,
+    /// Detected-or-forced encoding the input was read under.
+    input_encoding: InputEncoding,
+    /// The document's `\info` title, or its first heading; feeds the
+    /// `name_from_title` naming pass.
+    title: Option<String>,
+    /// Peak heap bytes of the conversion; only set in builds with the
+    /// core's `memory-accounting` feature.
+    peak_memory_bytes: Option<u64>,
+    /// Configurations tried under the fallback chain; 1 when the base
+    /// configuration converted the file.
+    attempts: u32,
+    /// Label of the overlay that converted the file; `None` for the
+    /// base configuration.
+    fallback: Option<String>,
+}
+
+/// Convert one file for the folder run; errors become report entries
+/// rather than failing the whole operation. The RTF->Markdown direction
+/// runs the full pipeline - retrying under each overlay of the fallback
+/// policy within its budget when the base configuration fails - and
+/// yields the complete outcome; the plain text directions take the
+/// simple paths, so their outcomes carry no warnings, feature usage or
+/// title, and config overlays cannot help them (always one attempt).
+fn convert_folder_file(
+    input: &Path,
+    output_dir: &Path,
+    encoding: &OutputEncoding,
+    reserved_suffix: &str,
+    forced: Option<InputEncoding>,
+    direction: ConversionDirection,
+    fallbacks: &FallbackPolicy,
+) -> Result<FileOutcome, FileFailure> {
+    let fail = |e: ConversionError, attempts: u32| FileFailure {
+        code: e.error_code(),
+        message: e.to_string(),
+        attempts,
+    };
+    let bytes = std::fs::read(input).map_err(|e| FileFailure {
+        code: LEGACYBRIDGE_ERROR_INVALID_INPUT,
+        message: format!("cannot read file: {e}"),
+        attempts: 1,
+    })?;
+    let (content, input_encoding) = decode_input(&bytes, forced);
+    let mut outcome = FileOutcome {
+        usage: FeatureUsage::default(),
+        adjusted: None,
+        warnings: Vec::new(),
+        recovery_actions: Vec::new(),
+        input_encoding,
+        title: None,
+        peak_memory_bytes: None,
+        attempts: 1,
+        fallback: None,
+    };
+    let limits = runtime_limits();
+    let written = match direction {
+        ConversionDirection::RtfToMarkdown => {
+            // The security verdict does not depend on the pipeline
+            // configuration, so a rejection here fails the file without
+            // spending the fallback budget.
+            InputValidator::new(limits)
+                .validate_rtf_input(&content)
+                .map_err(|m| fail(ConversionError::validation(m), 1))?;
+            let started = std::time::Instant::now();
+            let mut converted = None;
+            let mut last_error = None;
+            for (position, overlay) in std::iter::once(None)
+                .chain(fallbacks.chain.iter().map(Some))
+                .enumerate()
+            {
+                if overlay.is_some() && started.elapsed() >= fallbacks.budget {
+                    break;
+                }
+                outcome.attempts = position as u32 + 1;
+                let mut attempt = Conversion::rtf_to_markdown(&content)
+                    .environment(runtime_environment());
+                if let Some(overlay) = overlay {
+                    attempt = attempt.config(overlay.apply(PipelineConfig::default()));
+                }
+                if !fallbacks.chain.is_empty() {
+                    // A hanging attempt must not eat the budget of the
+                    // remaining ones.
+                    attempt =
+                        attempt.timeout(fallbacks.budget.saturating_sub(started.elapsed()));
+                }
+                match attempt.run_full() {
+                    Ok(output) => {
+                        outcome.fallback = overlay.map(|overlay| {
+                            overlay
+                                .label
+                                .clone()
+                                .unwrap_or_else(|| format!("fallback {position}"))
+                        });
+                        converted = Some(output);
+                        break;
+                    }
+                    Err(e) => last_error = Some(e),
+                }
+            }
+            let Some(output) = converted else {
+                let e = last_error.expect("at least the base configuration ran");
+                return Err(fail(e, outcome.attempts));
+            };
+            outcome.usage = output.feature_usage;
+            outcome.warnings = output.validation_results;
+            outcome.recovery_actions = output.recovery_actions;
+            outcome.title = output
+                .metadata
+                .title
+                .clone()
+                .or_else(|| output.metadata.outline.first().map(|h| h.text.clone()));
+            outcome.peak_memory_bytes = output.peak_memory_bytes.map(|bytes| bytes as u64);
+            output.markdown
+        }
+        ConversionDirection::RtfToPlainText => {
+            InputValidator::new(limits)
+                .validate_rtf_input(&content)
+                .map_err(|m| fail(ConversionError::validation(m), 1))?;
+            conversion::rtf_to_plain_text(&content).map_err(|e| fail(e, 1))?
+        }
+        ConversionDirection::PlainTextToRtf => {
+            if content.len() > limits.max_input_size {
+                return Err(fail(
+                    ConversionError::validation(format!(
+                        "input exceeds maximum size ({} > {} bytes)",
+                        content.len(),
+                        limits.max_input_size
+                    )),
+                    1,
+                ));
+            }
+            conversion::plain_text_to_rtf(&content).map_err(|e| fail(e, 1))?
+        }
+    };
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let format = direction.output();
+    let name = output_file_name(input, reserved_suffix, format);
+    outcome.adjusted = (name != format!("{stem}.{}", format.extension())).then(|| name.clone());
+    let path = output_dir.join(&name);
+    // Written RTF is always CRLF without a BOM; the configured encoding
+    // options apply to the text formats.
+    let write_encoding = if format == OutputFormat::Rtf {
+        OutputEncoding::rtf()
+    } else {
+        *encoding
+    };
+    safe_write(&path, &written, &write_encoding).map_err(|e| FileFailure {
+        code: LEGACYBRIDGE_ERROR_INVALID_INPUT,
+        message: format!("cannot write output: {e}"),
+        attempts: outcome.attempts,
+    })?;
+    Ok(outcome)
+}
+
+/// Move every staged output into the destination, one rename per file.
+/// When a rename fails, the files already moved are moved back and the
+/// staging directory is removed, so the destination is never left
+/// half-updated: either every output is published or none is.
+fn publish_staged_outputs(staging: &Path, output_dir: &Path) -> Result<(), String> {
+    let entries: Vec<PathBuf> = std::fs::read_dir(staging)
+        .map_err(|e| format!("cannot read {}: {e}", staging.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for from in &entries {
+        let Some(name) = from.file_name() else {
+            continue;
+        };
+        let to = output_dir.join(name);
+        if let Err(e) = std::fs::rename(from, &to) {
+            for (from, to) in moved.iter().rev() {
+                let _ = std::fs::rename(to, from);
+            }
+            let _ = std::fs::remove_dir_all(staging);
+            return Err(format!(
+                "cannot publish {}: {e}; no outputs were published",
+                to.display()
+            ));
+        }
+        moved.push((from.clone(), to));
+    }
+    let _ = std::fs::remove_dir_all(staging);
+    Ok(())
+}
+
+fn convert_folder(
+    input_dir: &Path,
+    output_dir: &Path,
+    options: &LegacyBridgeOptions,
+    callback: Option<LegacyBridgeProgressCallback>,
+) -> Result<FolderReport, String> {
+    let run_start = std::time::Instant::now();
+    // Like the encoding overrides below, an unsupported report contract
+    // version fails the whole run before anything is read or written.
+    let report_contract =
+        contracts::negotiate_emit_version(options.contract_version).map_err(|e| e.to_string())?;
+    // Encoding overrides are validated before anything is read or
+    // written, so a typo fails the whole run fast instead of half-way.
+    let (global_input_encoding, per_file_encodings) = input_encoding_overrides(options)?;
+    // So is the direction; it decides which extension the scan picks up,
+    // so a folder mixing .rtf and .txt files only routes the matching
+    // ones through this run.
+    let direction = match options.direction.as_deref() {
+        Some(name) => ConversionDirection::from_name(name).ok_or_else(|| {
+            format!(
+                "unsupported direction {name:?}; supported: {}",
+                SUPPORTED_DIRECTIONS.join(", ")
+            )
+        })?,
+        None => ConversionDirection::default(),
+    };
+    let input_extension = direction.input().extension();
+    let mut files: Vec<(PathBuf, usize)> = std::fs::read_dir(input_dir)
+        .map_err(|e| format!("cannot read {}: {e}", input_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(input_extension))
+        })
+        .map(|p| {
+            let size = std::fs::metadata(&p).map(|m| m.len() as usize).unwrap_or(0);
+            (p, size)
+        })
+        .collect();
+    // Smallest first so users see progress quickly and big files don't
+    // monopolize the byte budget early; name order within a size tie and
+    // under `preserve_order`.
+    if options.preserve_order.unwrap_or(false) {
+        files.sort();
+    } else {
+        files.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+    }
+    std::fs::create_dir_all(output_dir)
+        .map_err(|e| format!("cannot create {}: {e}", output_dir.display()))?;
+    // Transactional runs write into a staging directory inside the
+    // destination - same filesystem, so publishing is a rename per file -
+    // and publish only on overall success.
+    let staging_dir = options.transactional.unwrap_or(false).then(|| {
+        output_dir.join(format!(".legacybridge-staging-{}", std::process::id()))
+    });
+    if let Some(staging) = &staging_dir {
+        // A leftover from a crashed run must not leak stale outputs into
+        // this one.
+        let _ = std::fs::remove_dir_all(staging);
+        std::fs::create_dir_all(staging)
+            .map_err(|e| format!("cannot create {}: {e}", staging.display()))?;
+    }
+    let write_dir: &Path = staging_dir.as_deref().unwrap_or(output_dir);
+
+    let discovered = files.len();
+    let reserved_suffix = options.reserved_name_suffix.as_deref().unwrap_or("_file");
+    let incremental = options.incremental.unwrap_or(false);
+    let state_path = output_dir.join(FOLDER_STATE_FILE);
+    // Only the RTF->Markdown pipeline consults the fallback policy, but
+    // resolving it here keeps the fingerprint honest for every direction.
+    let fallbacks = FallbackPolicy::resolve(options);
+    let fingerprint = options_fingerprint(options, direction, &fallbacks.chain);
+    let previous = if incremental {
+        load_folder_state(&state_path, fingerprint)
+    } else {
+        FolderState::default()
+    };
+
+    // Under incremental mode, hash every input once up front; the hash
+    // decides the skip and is recorded in the refreshed state file.
+    // `hashes` stays aligned with the kept `files`.
+    let mut skipped: Vec<String> = Vec::new();
+    let mut hashes: Vec<u64> = Vec::new();
+    if incremental {
+        let force = options.force.unwrap_or(false);
+        let mut kept = Vec::with_capacity(files.len());
+        for (file, size) in files {
+            let name = file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let hash = std::fs::read(&file).ok().map(|bytes| fnv1a_64(&bytes));
+            let up_to_date = !force
+                && hash.is_some_and(|hash| {
+                    previous.files.get(&name).is_some_and(|entry| {
+                        entry.hash == hash && output_dir.join(&entry.output).exists()
+                    })
+                });
+            if up_to_date {
+                skipped.push(name);
+            } else {
+                // An unreadable file stays scheduled: the worker turns
+                // the read error into a report entry.
+                hashes.push(hash.unwrap_or(0));
+                kept.push((file, size));
+            }
+        }
+        files = kept;
+    }
+
+    let total = files.len();
+    let encoding = options.output_encoding();
+    let workers = options
+        .max_parallelism
+        .or_else(runtime_default_parallelism)
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+        .clamp(1, total.max(1));
+
+    // Shared work queue; workers pull the next index so the pool stays
+    // bounded without per-file thread spawns.
+    let next = AtomicUsize::new(0);
+    // Completion counter behind a lock so callback invocations see
+    // consistent, strictly increasing current/total pairs.
+    let progress = Mutex::new(0usize);
+
+    let budget = options.max_inflight_bytes.map(ByteBudget::new);
+
+    let (mut failures, mut waits, mut renames, mut file_reports, mut titles, feature_usage) =
+        std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    LIVE_WORKERS.fetch_add(1, Ordering::SeqCst);
+                    let mut local = Vec::new();
+                    let mut local_waits = Vec::new();
+                    let mut local_renames = Vec::new();
+                    let mut local_reports = Vec::new();
+                    let mut local_titles = Vec::new();
+                    let mut usage = FeatureUsage::default();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((file, size)) = files.get(index) else {
+                            LIVE_WORKERS.fetch_sub(1, Ordering::SeqCst);
+                            return (
+                                local,
+                                local_waits,
+                                local_renames,
+                                local_reports,
+                                local_titles,
+                                usage,
+                            );
+                        };
+                        let name = || {
+                            file.file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .into_owned()
+                        };
+                        if let Some(budget) = &budget {
+                            let waited = budget.acquire(*size);
+                            if !waited.is_zero() {
+                                local_waits.push((
+                                    index,
+                                    FolderWait {
+                                        file: name(),
+                                        wait_ms: waited.as_millis() as u64,
+                                    },
+                                ));
+                            }
+                        }
+                        let file_start = std::time::Instant::now();
+                        let forced = per_file_encodings
+                            .get(&name())
+                            .copied()
+                            .or(global_input_encoding);
+                        let result = convert_folder_file(
+                            file,
+                            write_dir,
+                            &encoding,
+                            reserved_suffix,
+                            forced,
+                            direction,
+                            &fallbacks,
+                        );
+                        let duration_ms = file_start.elapsed().as_millis() as u64;
+                        if let Some(budget) = &budget {
+                            budget.release(*size);
+                        }
+                        match result {
+                            Ok(outcome) => {
+                                usage.merge(&outcome.usage);
+                                let peak_memory_bytes = outcome.peak_memory_bytes;
+                                if let Some(title) = outcome.title {
+                                    local_titles.push((index, title));
+                                }
+                                if let Some(output) = outcome.adjusted {
+                                    local_renames.push((
+                                        index,
+                                        FolderRename {
+                                            file: name(),
+                                            output,
+                                        },
+                                    ));
+                                }
+                                local_reports.push((
+                                    index,
+                                    FileReport {
+                                        file: name(),
+                                        status: FileStatus::Converted,
+                                        duration_ms,
+                                        error: None,
+                                        warnings: outcome.warnings,
+                                        recovery_actions: outcome.recovery_actions,
+                                        fidelity: None,
+                                        encoding: Some(
+                                            outcome.input_encoding.name().to_string(),
+                                        ),
+                                        // Filled in after the naming pass.
+                                        output: None,
+                                        peak_memory_bytes,
+                                        attempts: outcome.attempts,
+                                        fallback: outcome.fallback,
+                                    },
+                                ));
+                            }
+                            Err(failure) => {
+                                local_reports.push((
+                                    index,
+                                    FileReport {
+                                        file: name(),
+                                        status: FileStatus::Failed,
+                                        duration_ms,
+                                        error: Some(failure.message.clone()),
+                                        warnings: Vec::new(),
+                                        recovery_actions: Vec::new(),
+                                        fidelity: None,
+                                        encoding: None,
+                                        output: None,
+                                        peak_memory_bytes: None,
+                                        attempts: failure.attempts,
+                                        fallback: None,
+                                    },
+                                ));
+                                local.push((
+                                    index,
+                                    FolderFailure {
+                                        file: name(),
+                                        code: failure.code,
+                                        message: failure.message,
+                                    },
+                                ));
+                            }
+                        }
+                        let mut done = lock_unpoisoned(&progress);
+                        *done += 1;
+                        if let Some(callback) = callback {
+                            unsafe { callback(*done as u32, total as u32) };
+                        }
+                    }
+                })
+            })
+            .collect();
+        let mut failures = Vec::new();
+        let mut waits = Vec::new();
+        let mut renames = Vec::new();
+        let mut reports = Vec::new();
+        let mut titles = Vec::new();
+        let mut feature_usage = FeatureUsage::default();
+        for handle in handles {
+            let (local, local_waits, local_renames, local_reports, local_titles, usage) =
+                handle.join().unwrap();
+            failures.extend(local);
+            waits.extend(local_waits);
+            renames.extend(local_renames);
+            reports.extend(local_reports);
+            titles.extend(local_titles);
+            feature_usage.merge(&usage);
+        }
+        (failures, waits, renames, reports, titles, feature_usage)
+    });
+    // Workers finish out of order; report entries follow the file order.
+    failures.sort_by_key(|(index, _)| *index);
+    waits.sort_by_key(|(index, _)| *index);
+    let budget_waits: Vec<FolderWait> = waits.into_iter().map(|(_, w)| w).collect();
+    renames.sort_by_key(|(index, _)| *index);
+    let adjusted_names: Vec<FolderRename> = renames.into_iter().map(|(_, r)| r).collect();
+    file_reports.sort_by_key(|(index, _)| *index);
+    let failed: std::collections::HashSet<usize> =
+        failures.iter().map(|(index, _)| *index).collect();
+
+    // With `name_from_title`, outputs were first written under the
+    // stem-based name; rename them here, in file order, so the numeric
+    // dedup suffixes are deterministic regardless of worker scheduling.
+    let mut renamed_from_titles: Vec<FolderRename> = Vec::new();
+    let mut final_outputs: std::collections::HashMap<usize, String> =
+        std::collections::HashMap::new();
+    if options.name_from_title.unwrap_or(false) {
+        let titles: std::collections::HashMap<usize, String> = titles.drain(..).collect();
+        let max_length = options
+            .title_name_length
+            .unwrap_or(DEFAULT_TITLE_NAME_LENGTH);
+        // Everything currently on disk: stem-based outputs not yet
+        // renamed and, under `incremental`, the outputs of skipped files.
+        // A title-derived name must not clobber any of them.
+        let mut taken: std::collections::HashSet<String> = files
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| !failed.contains(index))
+            .map(|(_, (file, _))| output_file_name(file, reserved_suffix, direction.output()))
+            .collect();
+        for name in &skipped {
+            if let Some(entry) = previous.files.get(name) {
+                taken.insert(entry.output.clone());
+            }
+        }
+        for (index, (file, _)) in files.iter().enumerate() {
+            if failed.contains(&index) {
+                continue;
+            }
+            let current = output_file_name(file, reserved_suffix, direction.output());
+            let Some(stem) = titles
+                .get(&index)
+                .map(|title| slugify_title(title, max_length))
+                .filter(|slug| !slug.is_empty())
+                .map(|slug| sanitize_file_stem(&slug, reserved_suffix))
+            else {
+                continue; // no title: the stem-based name stands
+            };
+            taken.remove(&current);
+            let extension = direction.output().extension();
+            let mut name = format!("{stem}.{extension}");
+            let mut counter = 1usize;
+            while taken.contains(&name) {
+                counter += 1;
+                name = format!("{stem}-{counter}.{extension}");
+            }
+            if name != current {
+                if std::fs::rename(write_dir.join(&current), write_dir.join(&name)).is_err() {
+                    // Keep the stem-based name so the report stays
+                    // truthful about what is on disk.
+                    taken.insert(current);
+                    continue;
+                }
+                renamed_from_titles.push(FolderRename {
+                    file: file
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    output: name.clone(),
+                });
+            }
+            taken.insert(name.clone());
+            final_outputs.insert(index, name);
+        }
+    }
+    // The per-file report entries carry where each output actually went,
+    // title-derived or not.
+    for (index, report) in &mut file_reports {
+        if report.status == FileStatus::Converted {
+            report.output = Some(final_outputs.get(index).cloned().unwrap_or_else(|| {
+                output_file_name(&files[*index].0, reserved_suffix, direction.output())
+            }));
+        }
+    }
+
+    // Transactional publish: the staged outputs move into the destination
+    // only when enough of the batch converted; otherwise the staging
+    // directory is removed and the destination stays exactly as it was.
+    let mut published = true;
+    if let Some(staging) = &staging_dir {
+        let threshold = options.publish_threshold.unwrap_or(100.0);
+        let succeeded = total - failures.len();
+        let rate = if total == 0 {
+            100.0
+        } else {
+            succeeded as f64 * 100.0 / total as f64
+        };
+        if rate >= threshold {
+            publish_staged_outputs(staging, output_dir)?;
+        } else {
+            published = false;
+            let _ = std::fs::remove_dir_all(staging);
+        }
+    }
+
+    let mut removed_outputs = Vec::new();
+    // An unpublished run wrote nothing into the destination, so the state
+    // file must not record its conversions and no stale outputs may be
+    // cleaned.
+    if incremental && published {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let mut state = FolderState {
+            options_fingerprint: fingerprint,
+            files: std::collections::BTreeMap::new(),
+        };
+        for name in &skipped {
+            if let Some(entry) = previous.files.get(name) {
+                state.files.insert(name.clone(), entry.clone());
+            }
+        }
+        for (index, (file, _)) in files.iter().enumerate() {
+            if failed.contains(&index) {
+                continue;
+            }
+            let name = file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            state.files.insert(
+                name,
+                FolderStateEntry {
+                    hash: hashes[index],
+                    output: final_outputs.get(&index).cloned().unwrap_or_else(|| {
+                        output_file_name(file, reserved_suffix, direction.output())
+                    }),
+                    timestamp,
+                },
+            );
+        }
+        if options.clean_removed.unwrap_or(false) {
+            for (name, entry) in &previous.files {
+                if !input_dir.join(name).exists() {
+                    let _ = std::fs::remove_file(output_dir.join(&entry.output));
+                    removed_outputs.push(entry.output.clone());
+                }
+            }
+        }
+        // Best effort: losing the state file only costs the next run its
+        // skips, so a write failure does not fail a completed batch.
+        if let Ok(json) = serde_json::to_string(&state) {
+            let _ = std::fs::write(&state_path, json);
+        }
+    }
+
+    if let Some(report_path) = &options.output_report_path {
+        let mut entries: Vec<FileReport> =
+            file_reports.into_iter().map(|(_, r)| r).collect();
+        entries.extend(skipped.iter().cloned().map(FileReport::skipped));
+        BatchReport::new(entries, run_start.elapsed())
+            .with_published(published)
+            .with_contract_version(report_contract)
+            .write(
+                Path::new(report_path),
+                options.report_format.unwrap_or_default(),
+            )?;
+    }
+
+    let failures: Vec<FolderFailure> = failures.into_iter().map(|(_, f)| f).collect();
+    Ok(FolderReport {
+        v: report_contract,
+        total: discovered,
+        converted: total - failures.len(),
+        published,
+        failures,
+        feature_usage,
+        budget_waits,
+        inflight_high_water: budget.as_ref().map(ByteBudget::high_water).unwrap_or(0),
+        adjusted_names,
+        renamed_from_titles,
+        skipped,
+        removed_outputs,
+    })
+}
+
+unsafe fn folder_export(
+    input_dir: *const c_char,
+    output_dir: *const c_char,
+    options_json: *const c_char,
+    callback: Option<LegacyBridgeProgressCallback>,
+) -> i32 {
+    clear_last_error();
+    lock_unpoisoned(&LAST_FOLDER_REPORT).clear();
+    let (Some(input_dir), Some(output_dir)) = (unsafe { read_input(input_dir, "input dir") }, unsafe {
+        read_input(output_dir, "output dir")
+    }) else {
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    };
+    let Some(options) = (unsafe { read_options(options_json) }) else {
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    };
+    match convert_folder(
+        Path::new(&input_dir),
+        Path::new(&output_dir),
+        &options,
+        callback,
+    ) {
+        Ok(report) => {
+            let converted = report.converted;
+            match serde_json::to_string(&report) {
+                Ok(json) => *lock_unpoisoned(&LAST_FOLDER_REPORT) = json,
+                Err(e) => set_last_error(format!("cannot serialize folder report: {e}")),
+            }
+            converted as i32
+        }
+        Err(message) => {
+            set_last_error(message);
+            LEGACYBRIDGE_ERROR_INVALID_INPUT
+        }
+    }
+}
+
+/// Convert every `.rtf` file in `input_dir` to Markdown in `output_dir`,
+/// using a bounded worker pool (`max_parallelism` in the options JSON;
+/// defaults to the CPU count). A `direction` in the options changes what
+/// the run converts - `rtf_to_plain_text` writes `.txt` files, and
+/// `plain_text_to_rtf` picks up `.txt` inputs instead, so a folder
+/// mixing both extensions only routes the matching files through each
+/// run. Returns the number of files converted, or a
+/// negative error code when the folders themselves are unusable. Per-file
+/// failures do not abort the run; retrieve them with
+/// [`legacybridge_get_last_folder_report`]. With `incremental` in the
+/// options, files unchanged since the previous run are skipped, listed in
+/// the report, and not counted in the return value. With
+/// `name_from_title`, outputs are named after the document's `\info`
+/// title or first heading instead of the input stem, deduplicated within
+/// the batch; the old-to-new mapping is in the report. With
+/// `output_report_path`, a machine-readable run report is additionally
+/// written there for CI publishing, as versioned JSON or as JUnit-style
+/// XML under `report_format`. With `transactional`, outputs are staged
+/// inside the destination and moved into place only when at least
+/// `publish_threshold` percent of the scheduled files converted (default
+/// 100); otherwise the destination is left untouched and the report's
+/// `published` field says so.
+///
+/// # Safety
+/// Both paths must be valid null-terminated strings or NULL; `options_json`
+/// must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_convert_folder_rtf_to_md(
+    input_dir: *const c_char,
+    output_dir: *const c_char,
+    options_json: *const c_char,
+) -> i32 {
+    unsafe { folder_export(input_dir, output_dir, options_json, None) }
+}
+
+/// [`legacybridge_convert_folder_rtf_to_md`] with a progress callback,
+/// invoked once per finished file with (current, total).
+///
+/// # Safety
+/// Pointer arguments as for `legacybridge_convert_folder_rtf_to_md`;
+/// `callback` must be a valid function pointer or NULL, and must tolerate
+/// being called from a worker thread.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_convert_folder_rtf_to_md_with_progress(
+    input_dir: *const c_char,
+    output_dir: *const c_char,
+    options_json: *const c_char,
+    callback: Option<LegacyBridgeProgressCallback>,
+) -> i32 {
+    unsafe { folder_export(input_dir, output_dir, options_json, callback) }
+}
+
+/// Retrieve the JSON report of the last folder conversion: total and
+/// converted counts, a `failures` array of file, error code and message,
+/// a `feature_usage` object of unsupported constructs merged across all
+/// converted files, plus `budget_waits` and `inflight_high_water` when a
+/// byte budget was configured, an `adjusted_names` array for outputs
+/// renamed to stay writable on Windows, and a `renamed_from_titles` array
+/// for outputs renamed after their document title under
+/// `name_from_title`. The `published` field records whether the outputs
+/// landed in the destination (always true outside `transactional` runs).
+/// Empty when no folder conversion has run.
+/// Must be freed with `legacybridge_free_string`.
+#[no_mangle]
+pub extern "C" fn legacybridge_get_last_folder_report() -> *mut c_char {
+    let report = lock_unpoisoned(&LAST_FOLDER_REPORT).clone();
+    into_c_string(report)
+}
+
+/// Options accepted by [`legacybridge_initialize`] as a JSON document.
+/// Unknown fields are ignored; missing fields use the defaults.
+#[derive(Default, Deserialize)]
+struct InitializeOptions {
+    /// Maximum accepted input size in bytes.
+    max_input_size: Option<usize>,
+    /// Default worker count for folder conversion.
+    max_parallelism: Option<usize>,
+    /// Explicit `legacybridge.toml` path. Unset searches next to the host
+    /// executable and the current directory; a file named here must load.
+    config_path: Option<String>,
+}
+
+/// Configure the library explicitly: security limits and the folder
+/// conversion worker count. A `legacybridge.toml` (explicit `config_path`
+/// or found next to the host executable / in the current directory) is
+/// loaded first, `LEGACYBRIDGE_*` environment variables override it, and
+/// the JSON options here override both - they are the host's own code.
+/// Idempotent; calling again replaces the configuration. Returns 1 on
+/// success, a negative error code on invalid options or an unloadable
+/// configuration file (the parse error, with line and column, is in
+/// `legacybridge_get_last_error`). Conversion exports called first
+/// auto-initialize with the defaults and record a startup warning.
+///
+/// # Safety
+/// `options_json` must be a valid null-terminated string or NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_initialize(options_json: *const c_char) -> i32 {
+    clear_last_error();
+    let Some(options) = (unsafe { read_options::<InitializeOptions>(options_json) }) else {
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    };
+    let config = match &options.config_path {
+        Some(path) => LegacyBridgeConfig::from_file(path),
+        None => LegacyBridgeConfig::load_default(),
+    };
+    let config = match config {
+        Ok(config) => config,
+        Err(e) => {
+            set_last_error(e);
+            return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+        }
+    };
+    let mut limits = config.limits.clone();
+    if let Some(size) = options.max_input_size {
+        limits.max_input_size = size;
+    }
+    let environment = match &config.template_directory {
+        Some(dir) => match ConversionEnvironment::with_template_directory(dir.clone()) {
+            Ok(environment) => Arc::new(environment),
+            Err(e) => {
+                set_last_error(e);
+                return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+            }
+        },
+        None => Arc::new(ConversionEnvironment::new()),
+    };
+    *lock_unpoisoned(&RUNTIME) = Some(Runtime {
+        limits,
+        default_parallelism: options.max_parallelism.or(config.max_parallelism),
+        config,
+        environment,
+    });
+    // An explicit initialize supersedes any earlier auto-initialize.
+    lock_unpoisoned(&STARTUP_WARNING).clear();
+    1
+}
+
+/// Retrieve a JSON report of what this build produces and how it is
+/// configured: the pipeline [`Capabilities`](PipelineConfig::capabilities)
+/// under the configured defaults, plus a `configuration` object with the
+/// effective settings, a `sources` map naming where each value came from
+/// (`default`, `file` or `environment`) and any configuration `warnings`
+/// (unknown keys, ignored overrides). Auto-initializes like the
+/// conversion exports when called first.
+/// Must be freed with `legacybridge_free_string`.
+#[no_mangle]
+pub extern "C" fn legacybridge_get_capabilities() -> *mut c_char {
+    clear_last_error();
+    runtime_limits(); // ensure the runtime exists
+    let config = lock_unpoisoned(&RUNTIME)
+        .as_ref()
+        .map(|r| r.config.clone())
+        .unwrap_or_default();
+    let capabilities = PipelineConfig {
+        legacy_mode: config.legacy_mode,
+        strict_validation: config.strict_validation,
+        preserve_formatting: config.preserve_formatting,
+        ..PipelineConfig::default()
+    }
+    .capabilities();
+    let report = serde_json::json!({
+        "v": contracts::CONTRACT_VERSION,
+        "capabilities": capabilities,
+        "configuration": config,
+    });
+    match serde_json::to_string(&report) {
+        Ok(json) => into_c_string(json),
+        Err(e) => {
+            set_last_error(format!("cannot serialize capabilities: {e}"));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Install a process-wide logging callback so the host can capture
+/// diagnostics. `min_level` is the most verbose `LEGACYBRIDGE_LOG_*`
+/// level to deliver (`LEGACYBRIDGE_LOG_ERROR` for errors only through
+/// `LEGACYBRIDGE_LOG_DEBUG` for everything); `user_data` is passed back
+/// verbatim on every call and never dereferenced by the library.
+/// Messages are null-terminated UTF-8, truncated to
+/// [`LEGACYBRIDGE_MAX_LOG_MESSAGE_BYTES`], and valid only for the
+/// duration of the call. A NULL `callback` uninstalls, like
+/// [`legacybridge_clear_log_callback`]. Calls into the library from
+/// inside the callback run without logging, so a conversion triggered
+/// there cannot recurse; the callback itself cannot be replaced from
+/// inside the callback. Returns 1 on success, 0 on an out-of-range level
+/// or a re-entrant call.
+///
+/// # Safety
+/// `callback` must be a valid function pointer or NULL and must remain
+/// callable until it is uninstalled (explicitly, or by
+/// [`legacybridge_shutdown`]); it may be invoked from any thread that
+/// calls into the library. `user_data` must stay valid for the same
+/// span. The callback is invoked while the registration lock is held, so
+/// uninstalling never races a delivery in flight.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_set_log_callback(
+    callback: Option<LegacyBridgeLogCallback>,
+    user_data: *mut c_void,
+    min_level: i32,
+) -> i32 {
+    if IN_LOG_CALLBACK.with(Cell::get) {
+        set_last_error("log callback cannot be changed from inside the log callback".to_string());
+        return 0;
+    }
+    let Some(callback) = callback else {
+        lock_unpoisoned(&LOG_CALLBACK).take();
+        return 1;
+    };
+    if !(LEGACYBRIDGE_LOG_ERROR..=LEGACYBRIDGE_LOG_DEBUG).contains(&min_level) {
+        set_last_error(format!(
+            "log level out of range: {min_level} (expected {LEGACYBRIDGE_LOG_ERROR}..={LEGACYBRIDGE_LOG_DEBUG})"
+        ));
+        return 0;
+    }
+    *lock_unpoisoned(&LOG_CALLBACK) = Some(LogSink {
+        callback,
+        user_data: user_data as usize,
+        min_level,
+    });
+    1
+}
+
+/// Uninstall the logging callback installed by
+/// [`legacybridge_set_log_callback`]. No messages are delivered after
+/// this returns. A no-op when no callback is installed. Returns 1 on
+/// success, 0 when called from inside the callback itself.
+#[no_mangle]
+pub extern "C" fn legacybridge_clear_log_callback() -> i32 {
+    if IN_LOG_CALLBACK.with(Cell::get) {
+        set_last_error("log callback cannot be changed from inside the log callback".to_string());
+        return 0;
+    }
+    lock_unpoisoned(&LOG_CALLBACK).take();
+    1
+}
+
+/// Rebuild the shared template registry - the built-ins plus the
+/// configured `template_directory` - and swap it in atomically.
+/// Conversions already running finish on the set they started with;
+/// ones started after the call see the new set. Returns the number of
+/// templates loaded from the directory (0 when none is configured), or
+/// a negative error code leaving the old set live (the reason is in
+/// `legacybridge_get_last_error`). Auto-initializes like the conversion
+/// exports when called first.
+#[no_mangle]
+pub extern "C" fn legacybridge_reload_templates() -> i32 {
+    clear_last_error();
+    match runtime_environment().reload_templates() {
+        Ok(names) => names.len() as i32,
+        Err(e) => {
+            set_last_error(e);
+            LEGACYBRIDGE_ERROR_INVALID_INPUT
+        }
+    }
+}
+
+/// Release all process-wide state so the host can unload the DLL: waits
+/// for in-flight folder workers to drain, then clears the configuration,
+/// the last error, the folder report, the last conversion stats and the
+/// logging callback.
+/// Returns 1 on success, 0 when
+/// workers failed to drain within five seconds. A later call to any export
+/// re-initializes with the defaults.
+#[no_mangle]
+pub extern "C" fn legacybridge_shutdown() -> i32 {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while LIVE_WORKERS.load(Ordering::SeqCst) != 0 {
+        if std::time::Instant::now() > deadline {
+            set_last_error("shutdown timed out waiting for folder workers".to_string());
+            return 0;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    *lock_unpoisoned(&RUNTIME) = None;
+    lock_unpoisoned(&LAST_ERROR).clear();
+    lock_unpoisoned(&LAST_ERROR_ENVELOPE).take();
+    lock_unpoisoned(&LAST_FOLDER_REPORT).clear();
+    lock_unpoisoned(&LAST_STATS).clear();
+    lock_unpoisoned(&STARTUP_WARNING).clear();
+    lock_unpoisoned(&LOG_CALLBACK).take();
+    1
+}
+
+/// Drop the calling thread's cached conversion state (recycled token
+/// buffer, template registry). Conversions never need this for
+/// correctness - the cache is cleared or cloned on every use - but a
+/// host returning a thread to a pool can call it to release the held
+/// allocations. Per-thread by design: call it on the thread to clean.
+#[no_mangle]
+pub extern "C" fn legacybridge_reset_thread_state() {
+    legacybridge_core::reset_thread_state();
+}
+
+/// Retrieve the last error message. Returns an empty string when the last
+/// call succeeded. The returned string must be freed with
+/// `legacybridge_free_string`.
+#[no_mangle]
+pub extern "C" fn legacybridge_get_last_error() -> *mut c_char {
+    let message = lock_unpoisoned(&LAST_ERROR).clone();
+    into_c_string(message)
+}
+
+/// Retrieve the last error as an [`ErrorEnvelope`] JSON document - the
+/// same schema the Tauri commands embed in their responses - or NULL when
+/// the last call succeeded. The returned string must be freed with
+/// `legacybridge_free_string`.
+#[no_mangle]
+pub extern "C" fn legacybridge_get_last_error_json() -> *mut c_char {
+    // Clone out of the lock: a serialization failure would re-enter
+    // `set_last_error`, which takes the same lock.
+    let envelope = lock_unpoisoned(&LAST_ERROR_ENVELOPE).clone();
+    let Some(envelope) = envelope else {
+        return std::ptr::null_mut();
+    };
+    match serde_json::to_string(&envelope) {
+        Ok(json) => into_c_string(json),
+        Err(e) => {
+            set_last_error(format!("cannot serialize error envelope: {e}"));
+            std::ptr::null_mut()
+        }
+    }
+}
+
+/// Retrieve the JSON performance counters of the most recent conversion
+/// that ran with `collect_stats` in its options: input bytes, token and
+/// node counts, tokens per second, the SIMD level the tokenizer's byte
+/// scanner selects (`simd_level`), recovery attempts, per-stage
+/// durations in microseconds (`stage_timings`) and the total duration.
+/// Empty when no such conversion has run. The returned string must be
+/// freed with `legacybridge_free_string`.
+#[no_mangle]
+pub extern "C" fn legacybridge_get_last_stats() -> *mut c_char {
+    let stats = lock_unpoisoned(&LAST_STATS).clone();
+    into_c_string(stats)
+}
+
+/// Returns 1 when the library is loaded and functional.
+#[no_mangle]
+pub extern "C" fn legacybridge_test_connection() -> i32 {
+    match conversion::rtf_to_markdown("{\\rtf1 ping\\par}") {
+        Ok(md) if md.contains("ping") => 1,
+        _ => 0,
+    }
+}
+
+/// Returns the library version string. Must be freed with
+/// `legacybridge_free_string`.
+#[no_mangle]
+pub extern "C" fn legacybridge_get_version_info() -> *mut c_char {
+    into_c_string(format!("LegacyBridge {}", env!("CARGO_PKG_VERSION")))
+}
+
+/// Run the in-process deployment self-test and write its JSON report
+/// into `output_buffer`. `flags` is a bit set of the check-selection
+/// constants in `legacybridge_core::selftest`; 0 runs the full battery.
+/// Returns 0 when every selected check passed, 1 when at least one
+/// failed (the report says which), and
+/// [`LEGACYBRIDGE_ERROR_INVALID_INPUT`] when the buffer is NULL or too
+/// small — the required size, including the NUL terminator, is then
+/// reported through `legacybridge_get_last_error`. 8 KiB is enough for
+/// the full battery. The battery writes only inside a private directory
+/// under the system temp dir and finishes well under two seconds.
+///
+/// # Safety
+/// `output_buffer` must point to at least `output_length` writable bytes
+/// or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_run_selftest(
+    flags: u32,
+    output_buffer: *mut c_char,
+    output_length: i32,
+) -> i32 {
+    clear_last_error();
+    if output_buffer.is_null() || output_length <= 0 {
+        set_last_error("selftest output buffer: null pointer or non-positive length".to_string());
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    }
+    let report = selftest::run_selftest(flags);
+    let json = match serde_json::to_string(&report) {
+        Ok(json) => json,
+        Err(e) => {
+            set_last_error(format!("cannot serialize self-test report: {e}"));
+            return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+        }
+    };
+    let needed = json.len() + 1;
+    if (output_length as usize) < needed {
+        set_last_error(format!(
+            "selftest output buffer too small: need {needed} bytes, got {output_length}"
+        ));
+        return LEGACYBRIDGE_ERROR_INVALID_INPUT;
+    }
+    unsafe {
+        std::ptr::copy_nonoverlapping(json.as_ptr(), output_buffer.cast::<u8>(), json.len());
+        *output_buffer.add(json.len()) = 0;
+    }
+    i32::from(!report.passed)
+}
+
+/// Release a string previously returned by this library.
+///
+/// # Safety
+/// `ptr` must have been returned by a `legacybridge_*` function and not
+/// already freed; NULL is accepted and ignored.
+#[no_mangle]
+pub unsafe extern "C" fn legacybridge_free_string(ptr: *mut c_char) {
+    if !ptr.is_null() {
+        drop(unsafe { CString::from_raw(ptr) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+
+    /// `legacybridge_shutdown` clears process-wide state (last error,
+    /// folder report, runtime); tests that read that state hold this lock
+    /// so the lifecycle test cannot clear it from under them.
+    static GLOBAL_STATE: Mutex<()> = Mutex::new(());
+
+    fn call_str(f: unsafe extern "C" fn(*const c_char) -> *mut c_char, input: &str) -> Option<String> {
+        let c_input = CString::new(input).unwrap();
+        let out = unsafe { f(c_input.as_ptr()) };
+        if out.is_null() {
+            return None;
+        }
+        let s = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(out) };
+        Some(s)
+    }
+
+    #[test]
+    fn rtf_to_markdown_export_works() {
+        let md = call_str(legacybridge_rtf_to_markdown, "{\\rtf1 Hello \\b World\\b0\\par}")
+            .unwrap();
+        assert!(md.contains("Hello **World**"));
+    }
+
+    #[test]
+    fn markdown_to_rtf_export_works() {
+        let rtf = call_str(legacybridge_markdown_to_rtf, "# Title\n\nBody").unwrap();
+        assert!(rtf.starts_with("{\\rtf1"));
+        assert!(rtf.contains("Title"));
+    }
+
+    #[test]
+    fn form_field_export_returns_json_fields() {
+        let rtf = "{\\rtf1 Name: {\\field{\\*\\formfield{\\fftype0\
+                   {\\*\\ffname first_name}}}{\\fldrslt Jane}} City: \
+                   {\\field{\\*\\formfield{\\fftype0{\\*\\ffname city}}}{\\fldrslt }} \
+                   Subscribed: {\\field{\\*\\formfield{\\fftype1\\ffres1\
+                   {\\*\\ffname subscribed}}}}\\par}";
+        let json = call_str(legacybridge_extract_form_fields, rtf).unwrap();
+        let fields: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let fields = fields.as_array().unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0]["name"], "first_name");
+        assert_eq!(fields[0]["value"], "Jane");
+        assert_eq!(fields[2]["type"], "checkbox");
+        assert_eq!(fields[2]["value"], "true");
+    }
+
+    #[test]
+    fn deep_validation_reports_verdicts_without_output() {
+        let json = call_str(legacybridge_validate_deep, "{\\rtf1 before}} after\\par}").unwrap();
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(report["valid"], true);
+        assert!(!report["recovery_actions"].as_array().unwrap().is_empty());
+
+        // A broken document is a valid:false report, not a NULL return.
+        let json = call_str(legacybridge_validate_deep, "not rtf at all").unwrap();
+        let report: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(report["valid"], false);
+        assert!(report["error_code"].as_i64().unwrap() < 0);
+    }
+
+    #[test]
+    fn markdown_analysis_export_returns_feature_counts() {
+        let md = "# Title\n\nA [link](https://example.com) and\n\n```rust\nlet x = 1;\n```\n";
+        let json = call_str(legacybridge_analyze_markdown, md).unwrap();
+        let analysis: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(analysis["outline"][0]["slug"], "title");
+        assert_eq!(analysis["links"]["external"], 1);
+        assert_eq!(analysis["code_block_languages"]["rust"], 1);
+        assert!(analysis["estimated_rtf_size"]["expected"].as_u64().unwrap() > 0);
+        let extensions = analysis["required_extensions"].as_array().unwrap();
+        assert!(extensions.contains(&serde_json::Value::from("links")), "{extensions:?}");
+    }
+
+    #[test]
+    fn control_word_support_is_queryable_over_ffi() {
+        let json = call_str(legacybridge_control_word_info, "pict").unwrap();
+        let info: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(info["name"], "pict");
+        assert_eq!(info["support_level"], "ignored");
+        assert_eq!(info["category"], "pictures");
+
+        // A leading backslash is tolerated: hosts paste words verbatim.
+        let json = call_str(legacybridge_control_word_info, "\\b").unwrap();
+        let info: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(info["support_level"], "full");
+
+        // NULL means the full table.
+        let out = unsafe { legacybridge_control_word_info(std::ptr::null()) };
+        assert!(!out.is_null());
+        let json = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(out) };
+        let table: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert!(table.as_array().unwrap().len() > 50);
+
+        // Unknown words are a NULL return with a last-error message.
+        assert!(call_str(legacybridge_control_word_info, "nosuchword").is_none());
+        let err = legacybridge_get_last_error();
+        let message = unsafe { CStr::from_ptr(err) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(err) };
+        assert!(message.contains("nosuchword"), "{message}");
+    }
+
+    #[test]
+    fn options_export_selects_a_page_range() {
+        let rtf = CString::new("{\\rtf1 P1\\par\\page P2\\par\\page P3\\par}").unwrap();
+        let options = CString::new("{\"page_start\": 2, \"page_end\": 2}").unwrap();
+        let out =
+            unsafe { legacybridge_rtf_to_markdown_with_options(rtf.as_ptr(), options.as_ptr()) };
+        assert!(!out.is_null());
+        let md = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(out) };
+        assert!(md.contains("P2"), "{md}");
+        assert!(!md.contains("P1"), "{md}");
+
+        // NULL options mean defaults: the whole document.
+        let out =
+            unsafe { legacybridge_rtf_to_markdown_with_options(rtf.as_ptr(), std::ptr::null()) };
+        assert!(!out.is_null());
+        let md = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(out) };
+        assert!(md.contains("P1") && md.contains("P3"), "{md}");
+    }
+
+    #[test]
+    fn future_contract_versions_in_options_are_rejected() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let rtf = CString::new("{\\rtf1 Hello\\par}").unwrap();
+
+        // A version-1 document (no "v") and the current major both work.
+        for options in ["{\"page_start\": 1}", "{\"v\": 2, \"page_start\": 1}"] {
+            let options = CString::new(options).unwrap();
+            let out = unsafe {
+                legacybridge_rtf_to_markdown_with_options(rtf.as_ptr(), options.as_ptr())
+            };
+            assert!(!out.is_null());
+            unsafe { legacybridge_free_string(out) };
+        }
+
+        // A payload from the future is the specific contract error.
+        let options = CString::new("{\"v\": 99, \"page_start\": 1}").unwrap();
+        let out =
+            unsafe { legacybridge_rtf_to_markdown_with_options(rtf.as_ptr(), options.as_ptr()) };
+        assert!(out.is_null());
+        let ptr = legacybridge_get_last_error_json();
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let envelope: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(envelope["code"], -7);
+        assert_eq!(envelope["category"], "contract");
+        assert!(
+            envelope["message"]
+                .as_str()
+                .unwrap()
+                .contains("unsupported contract version 99"),
+            "{envelope}"
+        );
+        clear_last_error();
+    }
+
+    #[test]
+    fn collect_stats_option_fills_the_last_stats_slot() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        lock_unpoisoned(&LAST_STATS).clear();
+        let source = "{\\rtf1 Stats \\b here\\b0\\par}";
+        let rtf = CString::new(source).unwrap();
+
+        // Runs without the option leave the slot alone.
+        let out =
+            unsafe { legacybridge_rtf_to_markdown_with_options(rtf.as_ptr(), std::ptr::null()) };
+        assert!(!out.is_null());
+        unsafe { legacybridge_free_string(out) };
+        let ptr = legacybridge_get_last_stats();
+        let empty = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        assert!(empty.is_empty(), "{empty}");
+
+        let options = CString::new("{\"collect_stats\": true}").unwrap();
+        let out =
+            unsafe { legacybridge_rtf_to_markdown_with_options(rtf.as_ptr(), options.as_ptr()) };
+        assert!(!out.is_null());
+        unsafe { legacybridge_free_string(out) };
+        let ptr = legacybridge_get_last_stats();
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let stats: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(stats["input_bytes"], source.len() as u64);
+        assert!(stats["token_count"].as_u64().unwrap() > 0, "{stats}");
+        let detected = legacybridge_core::conversion::simd_lexer::CpuFeatures::detect()
+            .simd_level
+            .to_string();
+        assert_eq!(stats["simd_level"], detected);
+        let stages: Vec<&str> = stats["stage_timings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["stage"].as_str().unwrap())
+            .collect();
+        assert!(stages.contains(&"tokenize"), "{stats}");
+    }
+
+    #[test]
+    fn folder_conversion_handles_200_files_in_parallel() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        static LAST_CURRENT: AtomicUsize = AtomicUsize::new(0);
+        unsafe extern "C" fn on_progress(current: u32, total: u32) {
+            assert_eq!(total, 200);
+            // Serialized invocation: current must be strictly increasing.
+            assert!(current as usize > LAST_CURRENT.swap(current as usize, Ordering::SeqCst));
+            CALLS.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let root = std::env::temp_dir().join(format!("lb-folder-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        for i in 0..200 {
+            // Every 50th file trips the security validator and must land
+            // in the report.
+            let content = if i % 50 == 0 {
+                "{\\rtf1{\\object\\objdata 0102}}".to_string()
+            } else if i % 40 == 1 {
+                // Converts, but with a drawing object to degrade.
+                format!("{{\\rtf1 {{\\*\\do\\dpline}}file {i}\\par}}")
+            } else {
+                format!("{{\\rtf1 file {i}\\par}}")
+            };
+            std::fs::write(input.join(format!("doc{i:03}.rtf")), content).unwrap();
+        }
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let options = CString::new("{\"max_parallelism\": 4}").unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md_with_progress(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+                Some(on_progress),
+            )
+        };
+        assert_eq!(converted, 196);
+        assert_eq!(CALLS.load(Ordering::SeqCst), 200);
+        assert_eq!(LAST_CURRENT.load(Ordering::SeqCst), 200);
+
+        for i in 0..200 {
+            let exists = output.join(format!("doc{i:03}.md")).exists();
+            assert_eq!(exists, i % 50 != 0, "doc{i:03}");
+        }
+
+        let ptr = legacybridge_get_last_folder_report();
+        let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        assert_eq!(report["total"], 200);
+        assert_eq!(report["converted"], 196);
+        let failures = report["failures"].as_array().unwrap();
+        assert_eq!(failures.len(), 4);
+        assert_eq!(failures[0]["file"], "doc000.rtf");
+        assert!(failures[0]["code"].as_i64().unwrap() < 0);
+        assert!(!failures[0]["message"].as_str().unwrap().is_empty());
+        // Files 1, 41, 81, 121, 161 each carry one \do and one \dpline.
+        assert_eq!(report["feature_usage"]["do"]["count"], 5);
+        assert_eq!(report["feature_usage"]["do"]["category"], "drawing_objects");
+        assert_eq!(report["feature_usage"]["do"]["severity"], "lossy");
+        assert_eq!(report["feature_usage"]["dpline"]["count"], 5);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn folder_direction_routes_mixed_extensions() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-direction-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(
+            input.join("report.rtf"),
+            "{\\rtf1 {\\b Quarterly} report\\par First point\\line second line\\par\\par \
+             Next paragraph\\par}",
+        )
+        .unwrap();
+        std::fs::write(
+            input.join("notes.txt"),
+            "alpha line\r\nbeta line\r\n\r\nsecond paragraph\r\n",
+        )
+        .unwrap();
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+
+        // RTF -> plain text schedules only the .rtf input; the encoding
+        // options apply to the written text.
+        let options =
+            CString::new("{\"direction\": \"rtf_to_plain_text\", \"line_ending\": \"lf\"}")
+                .unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 1);
+        assert_eq!(
+            std::fs::read(output.join("report.txt")).unwrap(),
+            b"Quarterly report\n\nFirst point\nsecond line\n\nNext paragraph"
+        );
+
+        // Plain text -> RTF on the same folder picks up only the .txt
+        // input (via the extension-based alias) and writes RTF that
+        // round-trips to the original paragraphs.
+        let options = CString::new("{\"direction\": \"txt_to_rtf\"}").unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 1);
+        let rtf = std::fs::read_to_string(output.join("notes.rtf")).unwrap();
+        assert!(rtf.starts_with("{\\rtf1"), "{rtf}");
+        assert_eq!(
+            conversion::rtf_to_plain_text(&rtf).unwrap(),
+            "alpha line\nbeta line\n\nsecond paragraph"
+        );
+        // Neither run touched the other extension's namesake output.
+        assert!(!output.join("notes.md").exists());
+        assert!(!output.join("report.md").exists());
+
+        // An unsupported direction fails before any file is processed.
+        let options = CString::new("{\"direction\": \"rtf_to_html\"}").unwrap();
+        let failed = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(failed, LEGACYBRIDGE_ERROR_INVALID_INPUT);
+        let ptr = legacybridge_get_last_error();
+        let message = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        assert!(message.contains("unsupported direction"), "{message}");
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn fallback_chain_retries_failed_files_and_reports_attempts() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-fallback-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        // Converts under the base configuration: one attempt, no fallback.
+        std::fs::write(input.join("ok.rtf"), "{\\rtf1 fine\\par}").unwrap();
+        // A headerless memo-field fragment: the base configuration and
+        // the "tolerant" overlay both reject the missing header, the
+        // "legacy_fragment" overlay accepts it on the third attempt.
+        std::fs::write(input.join("fragment.rtf"), "\\b memo fragment\\b0\\par").unwrap();
+        // Empty input fails every configuration in the chain.
+        std::fs::write(input.join("empty.rtf"), "").unwrap();
+
+        let report_path = root.join("report.json");
+        let options = serde_json::json!({
+            "retry_with_fallbacks": true,
+            "output_report_path": report_path,
+        })
+        .to_string();
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let options = CString::new(options).unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 2);
+        assert!(output.join("fragment.md").exists());
+
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+        let entry = |name: &str| {
+            report["files"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|f| f["file"] == name)
+                .unwrap_or_else(|| panic!("no report entry for {name}"))
+                .clone()
+        };
+        let ok = entry("ok.rtf");
+        assert_eq!(ok["status"], "converted");
+        assert_eq!(ok["attempts"], 1);
+        assert_eq!(ok["fallback"], serde_json::Value::Null);
+        let fragment = entry("fragment.rtf");
+        assert_eq!(fragment["status"], "converted");
+        assert_eq!(fragment["attempts"], 3);
+        assert_eq!(fragment["fallback"], "legacy_fragment");
+        let empty = entry("empty.rtf");
+        assert_eq!(empty["status"], "failed");
+        assert_eq!(empty["attempts"], 3);
+        assert_eq!(empty["fallback"], serde_json::Value::Null);
+
+        // Without the chain the fragment stays failed after one attempt.
+        let plain = CString::new(
+            serde_json::json!({"output_report_path": report_path}).to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            unsafe {
+                legacybridge_convert_folder_rtf_to_md(
+                    c_input.as_ptr(),
+                    c_output.as_ptr(),
+                    plain.as_ptr(),
+                )
+            },
+            1
+        );
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&report_path).unwrap()).unwrap();
+        let fragment = report["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|f| f["file"] == "fragment.rtf")
+            .unwrap()
+            .clone();
+        assert_eq!(fragment["status"], "failed");
+        assert_eq!(fragment["attempts"], 1);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn folder_conversion_respects_the_byte_budget() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-budget-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        for i in 0..12 {
+            // ~2KB each, with one file bigger than the whole budget: it
+            // must be clamped and run alone rather than deadlock.
+            let padding = if i == 11 { 6000 } else { 2000 };
+            let content = format!("{{\\rtf1 {}\\par}}", "x".repeat(padding));
+            std::fs::write(input.join(format!("doc{i:02}.rtf")), content).unwrap();
+        }
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let options =
+            CString::new("{\"max_parallelism\": 4, \"max_inflight_bytes\": 4096}").unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 12);
+
+        let ptr = legacybridge_get_last_folder_report();
+        let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        let high_water = report["inflight_high_water"].as_u64().unwrap();
+        assert!(high_water > 0, "budget never tracked");
+        assert!(high_water <= 4096, "high water {high_water} exceeds the budget");
+        assert!(report["budget_waits"].is_array());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn folder_conversion_honors_output_encoding_options() {
+        let root = std::env::temp_dir().join(format!("lb-enc-folder-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("doc.rtf"), "{\\rtf1 A\\par B\\par}").unwrap();
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let options =
+            CString::new("{\"line_ending\": \"crlf\", \"bom\": true, \"trailing_newline\": true}")
+                .unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 1);
+
+        let bytes = std::fs::read(output.join("doc.md")).unwrap();
+        assert!(bytes.starts_with(&[0xEF, 0xBB, 0xBF]), "missing BOM");
+        assert!(bytes.ends_with(b"\r\n"), "missing trailing CRLF");
+        assert!(!bytes.windows(2).any(|w| w[1] == b'\n' && w[0] != b'\r'));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn folder_conversion_sanitizes_windows_hostile_output_names() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-reserved-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("CON.rtf"), "{\\rtf1 device\\par}").unwrap();
+        std::fs::write(input.join("notes .rtf"), "{\\rtf1 trailing\\par}").unwrap();
+        std::fs::write(input.join("plain.rtf"), "{\\rtf1 fine\\par}").unwrap();
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let options = CString::new("{\"reserved_name_suffix\": \"_doc\"}").unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 3);
+        assert!(output.join("CON_doc.md").exists());
+        assert!(output.join("notes.md").exists());
+        assert!(output.join("plain.md").exists());
+
+        let ptr = legacybridge_get_last_folder_report();
+        let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        let adjusted = report["adjusted_names"].as_array().unwrap();
+        assert_eq!(adjusted.len(), 2);
+        assert!(adjusted
+            .iter()
+            .any(|r| r["file"] == "CON.rtf" && r["output"] == "CON_doc.md"));
+        assert!(adjusted
+            .iter()
+            .any(|r| r["file"] == "notes .rtf" && r["output"] == "notes.md"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn title_slugs_strip_illegal_characters_and_truncate() {
+        assert_eq!(
+            slugify_title("Q3: Report / Results (final)", 60),
+            "q3-report-results-final"
+        );
+        // Truncation never leaves a trailing dash.
+        assert_eq!(slugify_title("alpha beta", 7), "alpha-b");
+        assert_eq!(slugify_title("alpha beta", 6), "alpha");
+        // A title with nothing usable yields no slug at all.
+        assert_eq!(slugify_title(" *** ", 60), "");
+    }
+
+    #[test]
+    fn title_named_outputs_are_unique_and_sanitized() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-titles-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(
+            input.join("REPORT0001.rtf"),
+            "{\\rtf1{\\info{\\title Quarterly Report}}Body one\\par}",
+        )
+        .unwrap();
+        // Same title up to punctuation: same slug, deduplicated.
+        std::fs::write(
+            input.join("REPORT0002.rtf"),
+            "{\\rtf1{\\info{\\title Quarterly Report!}}Body two\\par}",
+        )
+        .unwrap();
+        // No \info title: the first heading names the file, and its slug
+        // is a reserved device name the sanitizer must still catch.
+        std::fs::write(
+            input.join("REPORT0003.rtf"),
+            "{\\rtf1 \\outlinelevel0 CON\\par \\pard Body three\\par}",
+        )
+        .unwrap();
+        // No title at all: the stem-based name stands.
+        std::fs::write(input.join("REPORT0004.rtf"), "{\\rtf1 Body four\\par}").unwrap();
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let options =
+            CString::new("{\"name_from_title\": true, \"preserve_order\": true}").unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 4);
+        assert!(output.join("quarterly-report.md").exists());
+        assert!(output.join("quarterly-report-2.md").exists());
+        assert!(output.join("con_file.md").exists());
+        assert!(output.join("REPORT0004.md").exists());
+        assert!(!output.join("REPORT0001.md").exists());
+
+        let ptr = legacybridge_get_last_folder_report();
+        let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        let renamed = report["renamed_from_titles"].as_array().unwrap();
+        assert_eq!(renamed.len(), 3);
+        assert!(renamed
+            .iter()
+            .any(|r| r["file"] == "REPORT0001.rtf" && r["output"] == "quarterly-report.md"));
+        assert!(renamed
+            .iter()
+            .any(|r| r["file"] == "REPORT0002.rtf" && r["output"] == "quarterly-report-2.md"));
+        assert!(renamed
+            .iter()
+            .any(|r| r["file"] == "REPORT0003.rtf" && r["output"] == "con_file.md"));
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn folder_input_encoding_overrides_apply_per_file() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-cp866-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("plain.rtf"), "{\\rtf1 ascii\\par}").unwrap();
+        // "Привет" in cp866, inside an otherwise ASCII RTF shell.
+        let mut rtf = b"{\\rtf1 ".to_vec();
+        rtf.extend_from_slice(&[0x8F, 0xE0, 0xA8, 0xA2, 0xA5, 0xE2]);
+        rtf.extend_from_slice(b"\\par}");
+        std::fs::write(input.join("cyrillic.rtf"), &rtf).unwrap();
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let run = |options: &str| {
+            let options = CString::new(options).unwrap();
+            unsafe {
+                legacybridge_convert_folder_rtf_to_md(
+                    c_input.as_ptr(),
+                    c_output.as_ptr(),
+                    options.as_ptr(),
+                )
+            }
+        };
+
+        // Without an override, the cp866 bytes fall back to windows-1252
+        // and the text comes out garbled.
+        assert_eq!(run("{}"), 2);
+        let md = std::fs::read_to_string(output.join("cyrillic.md")).unwrap();
+        assert!(!md.contains("Привет"), "{md}");
+
+        // The per-file override fixes exactly that file; the report
+        // records what each input was read under.
+        assert_eq!(run("{\"input_encodings\": {\"cyrillic.rtf\": \"cp866\"}}"), 2);
+        let md = std::fs::read_to_string(output.join("cyrillic.md")).unwrap();
+        assert!(md.contains("Привет"), "{md}");
+        let ptr = legacybridge_get_last_folder_report();
+        let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        assert!(report.contains("\"converted\":2"), "{report}");
+
+        // An unsupported name fails fast, before any file is processed.
+        let fresh = root.join("fresh");
+        let c_fresh = CString::new(fresh.to_str().unwrap()).unwrap();
+        let options = CString::new("{\"input_encoding\": \"klingon\"}").unwrap();
+        let code = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_fresh.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(code, LEGACYBRIDGE_ERROR_INVALID_INPUT);
+        let err = legacybridge_get_last_error();
+        let error = unsafe { CStr::from_ptr(err) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(err) };
+        assert!(error.contains("unsupported input encoding"), "{error}");
+        assert!(!fresh.exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn incremental_folder_runs_skip_up_to_date_files() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-incr-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        for i in 0..5 {
+            let content = format!("{{\\rtf1 file {i}\\par}}");
+            std::fs::write(input.join(format!("doc{i}.rtf")), content).unwrap();
+        }
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let run = |options: &str| {
+            let options = CString::new(options).unwrap();
+            let converted = unsafe {
+                legacybridge_convert_folder_rtf_to_md(
+                    c_input.as_ptr(),
+                    c_output.as_ptr(),
+                    options.as_ptr(),
+                )
+            };
+            let ptr = legacybridge_get_last_folder_report();
+            let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { legacybridge_free_string(ptr) };
+            (converted, serde_json::from_str::<serde_json::Value>(&report).unwrap())
+        };
+
+        let (converted, report) = run("{\"incremental\": true}");
+        assert_eq!(converted, 5);
+        assert_eq!(report["skipped"].as_array().unwrap().len(), 0);
+        assert!(output.join(FOLDER_STATE_FILE).exists());
+
+        // Second pass: nothing changed, nothing converts.
+        let (converted, report) = run("{\"incremental\": true}");
+        assert_eq!(converted, 0);
+        assert_eq!(report["total"], 5);
+        assert_eq!(report["skipped"].as_array().unwrap().len(), 5);
+
+        // Touch one file: exactly one conversion.
+        std::fs::write(input.join("doc2.rtf"), "{\\rtf1 file 2 edited\\par}").unwrap();
+        let (converted, report) = run("{\"incremental\": true}");
+        assert_eq!(converted, 1);
+        assert_eq!(report["skipped"].as_array().unwrap().len(), 4);
+        let md = std::fs::read_to_string(output.join("doc2.md")).unwrap();
+        assert!(md.contains("edited"), "{md}");
+
+        // An options change that alters outputs invalidates the state.
+        let (converted, _) = run("{\"incremental\": true, \"bom\": true}");
+        assert_eq!(converted, 5);
+
+        // Force reconverts everything despite a matching state.
+        let (converted, _) = run("{\"incremental\": true, \"bom\": true, \"force\": true}");
+        assert_eq!(converted, 5);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn transactional_folder_run_publishes_only_above_the_threshold() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-txn-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        for i in 0..4 {
+            let content = format!("{{\\rtf1 file {i}\\par}}");
+            std::fs::write(input.join(format!("doc{i}.rtf")), content).unwrap();
+        }
+        std::fs::write(input.join("broken.rtf"), "not rtf at all").unwrap();
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let run = |options: &str| {
+            let options = CString::new(options).unwrap();
+            let converted = unsafe {
+                legacybridge_convert_folder_rtf_to_md(
+                    c_input.as_ptr(),
+                    c_output.as_ptr(),
+                    options.as_ptr(),
+                )
+            };
+            let ptr = legacybridge_get_last_folder_report();
+            let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+            unsafe { legacybridge_free_string(ptr) };
+            (converted, serde_json::from_str::<serde_json::Value>(&report).unwrap())
+        };
+
+        // One of five files fails: below the default 100% threshold
+        // nothing is published and the destination is left empty -
+        // indistinguishable from a run that never started.
+        let (converted, report) = run("{\"transactional\": true}");
+        assert_eq!(converted, 4);
+        assert_eq!(report["published"], false);
+        assert_eq!(report["failures"].as_array().unwrap().len(), 1);
+        assert!(
+            std::fs::read_dir(&output).unwrap().next().is_none(),
+            "destination must stay untouched"
+        );
+
+        // The same run clears an 80% threshold (4 of 5) and publishes,
+        // leaving no staging directory behind.
+        let (converted, report) =
+            run("{\"transactional\": true, \"publish_threshold\": 80.0}");
+        assert_eq!(converted, 4);
+        assert_eq!(report["published"], true);
+        for i in 0..4 {
+            assert!(output.join(format!("doc{i}.md")).exists());
+        }
+        assert!(std::fs::read_dir(&output).unwrap().all(|entry| {
+            let name = entry.unwrap().file_name();
+            !name.to_string_lossy().starts_with(".legacybridge-staging")
+        }));
+
+        // With the broken file gone, the default threshold publishes too.
+        std::fs::remove_file(input.join("broken.rtf")).unwrap();
+        let (converted, report) = run("{\"transactional\": true}");
+        assert_eq!(converted, 4);
+        assert_eq!(report["published"], true);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn clean_removed_deletes_outputs_for_missing_inputs() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-clean-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        for name in ["a", "b", "c"] {
+            std::fs::write(input.join(format!("{name}.rtf")), "{\\rtf1 text\\par}").unwrap();
+        }
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let options = CString::new("{\"incremental\": true}").unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 3);
+
+        std::fs::remove_file(input.join("b.rtf")).unwrap();
+        let options = CString::new("{\"incremental\": true, \"clean_removed\": true}").unwrap();
+        let converted = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                c_input.as_ptr(),
+                c_output.as_ptr(),
+                options.as_ptr(),
+            )
+        };
+        assert_eq!(converted, 0);
+
+        let ptr = legacybridge_get_last_folder_report();
+        let report = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let report: serde_json::Value = serde_json::from_str(&report).unwrap();
+        assert_eq!(report["skipped"].as_array().unwrap().len(), 2);
+        let removed = report["removed_outputs"].as_array().unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0], "b.md");
+        assert!(!output.join("b.md").exists());
+        assert!(output.join("a.md").exists() && output.join("c.md").exists());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn folder_runs_publish_ci_report_artifacts() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-report-{}", std::process::id()));
+        let input = root.join("in");
+        let output = root.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("good.rtf"), "{\\rtf1 fine\\par}").unwrap();
+        std::fs::write(input.join("bad.rtf"), "{\\rtf1{\\object\\objdata 0102}}").unwrap();
+
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(output.to_str().unwrap()).unwrap();
+        let run = |report_path: &Path, format: &str| {
+            let options = serde_json::json!({
+                "output_report_path": report_path,
+                "report_format": format,
+            })
+            .to_string();
+            let options = CString::new(options).unwrap();
+            unsafe {
+                legacybridge_convert_folder_rtf_to_md(
+                    c_input.as_ptr(),
+                    c_output.as_ptr(),
+                    options.as_ptr(),
+                )
+            }
+        };
+
+        let json_path = root.join("report.json");
+        assert_eq!(run(&json_path, "json"), 1);
+        let report: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+        assert_eq!(report["report_version"], 6);
+        assert_eq!(report["total"], 2);
+        assert_eq!(report["converted"], 1);
+        assert_eq!(report["failed"], 1);
+        assert_eq!(report["published"], true);
+        let files = report["files"].as_array().unwrap();
+        let bad = files.iter().find(|f| f["file"] == "bad.rtf").unwrap();
+        assert_eq!(bad["status"], "failed");
+        assert!(bad["error"].as_str().unwrap().contains("objdata"));
+        let good = files.iter().find(|f| f["file"] == "good.rtf").unwrap();
+        assert_eq!(good["status"], "converted");
+        assert_eq!(good["output"], "good.md");
+
+        let xml_path = root.join("report.xml");
+        assert_eq!(run(&xml_path, "junit_xml"), 1);
+        let xml = std::fs::read_to_string(&xml_path).unwrap();
+        assert!(
+            xml.contains("<testsuite name=\"legacybridge\" tests=\"2\" failures=\"1\""),
+            "{xml}"
+        );
+        assert!(xml.contains("name=\"good.rtf\""), "{xml}");
+        assert!(xml.contains("<failure message="), "{xml}");
+
+        // An unwritable report path fails the run so CI notices.
+        let code = run(&root.join("no-such-dir").join("report.json"), "json");
+        assert_eq!(code, LEGACYBRIDGE_ERROR_INVALID_INPUT);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn missing_input_folder_is_an_error() {
+        let input = CString::new("/nonexistent/lb-input").unwrap();
+        let output = CString::new(std::env::temp_dir().to_str().unwrap()).unwrap();
+        let code = unsafe {
+            legacybridge_convert_folder_rtf_to_md(
+                input.as_ptr(),
+                output.as_ptr(),
+                std::ptr::null(),
+            )
+        };
+        assert_eq!(code, LEGACYBRIDGE_ERROR_INVALID_INPUT);
+    }
+
+    #[test]
+    fn configuration_file_settings_reach_the_runtime() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let path = std::env::temp_dir().join(format!("lb-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "[limits]\nmax_input_size = 8\n\n[conversion]\nlegacy_mode = true\n\n\
+             [telemetry]\nendpoint = \"x\"\n",
+        )
+        .unwrap();
+        let options =
+            CString::new(format!("{{\"config_path\": {:?}}}", path.to_str().unwrap())).unwrap();
+        assert_eq!(unsafe { legacybridge_initialize(options.as_ptr()) }, 1);
+
+        // The file's input-size limit applies to conversions.
+        assert!(call_str(legacybridge_rtf_to_markdown, "{\\rtf1 too big\\par}").is_none());
+
+        // The capabilities report carries the dialect, the provenance of
+        // each setting and the unknown-key warning.
+        let ptr = legacybridge_get_capabilities();
+        assert!(!ptr.is_null());
+        let json: serde_json::Value =
+            serde_json::from_str(unsafe { CStr::from_ptr(ptr) }.to_str().unwrap()).unwrap();
+        unsafe { legacybridge_free_string(ptr) };
+        assert_eq!(json["capabilities"]["rtf_spec"], "1.5");
+        let sources = &json["configuration"]["sources"];
+        assert_eq!(sources["limits.max_input_size"], "file");
+        assert_eq!(sources["conversion.legacy_mode"], "file");
+        assert_eq!(sources["runtime.max_parallelism"], "default");
+        assert!(json["configuration"]["warnings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|w| w.as_str().unwrap().contains("telemetry")));
+        assert_eq!(legacybridge_shutdown(), 1);
+
+        // A type error in the file fails initialize and names the line.
+        std::fs::write(&path, "[limits]\nmax_input_size = \"big\"\n").unwrap();
+        assert_eq!(
+            unsafe { legacybridge_initialize(options.as_ptr()) },
+            LEGACYBRIDGE_ERROR_INVALID_INPUT
+        );
+        assert!(lock_unpoisoned(&LAST_ERROR).contains("line 2"));
+        std::fs::remove_file(&path).unwrap();
+        legacybridge_shutdown();
+    }
+
+    #[test]
+    fn reload_templates_rereads_the_configured_directory() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let dir = std::env::temp_dir().join(format!("lb-dll-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("memo2.json"), r#"{"name": "memo2"}"#).unwrap();
+        let toml = std::env::temp_dir().join(format!("lb-dll-tpl-{}.toml", std::process::id()));
+        std::fs::write(
+            &toml,
+            format!("template_directory = {:?}\n", dir.to_str().unwrap()),
+        )
+        .unwrap();
+        let options =
+            CString::new(format!("{{\"config_path\": {:?}}}", toml.to_str().unwrap())).unwrap();
+        assert_eq!(unsafe { legacybridge_initialize(options.as_ptr()) }, 1);
+
+        // A new file on disk is picked up by the reload, atomically.
+        std::fs::write(dir.join("report2.json"), r#"{"name": "report2"}"#).unwrap();
+        assert_eq!(legacybridge_reload_templates(), 2);
+
+        // A broken file fails the reload and leaves the old set live.
+        std::fs::write(dir.join("broken.json"), "not json").unwrap();
+        assert_eq!(
+            legacybridge_reload_templates(),
+            LEGACYBRIDGE_ERROR_INVALID_INPUT
+        );
+        assert!(!lock_unpoisoned(&LAST_ERROR).is_empty());
+
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::remove_file(&toml).ok();
+        legacybridge_shutdown();
+
+        // Without a configured directory the reload is a no-op success.
+        assert_eq!(legacybridge_reload_templates(), 0);
+        legacybridge_shutdown();
+    }
+
+    #[test]
+    fn initialize_and_shutdown_cycle_without_leaking_workers() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let root = std::env::temp_dir().join(format!("lb-lifecycle-{}", std::process::id()));
+        let input = root.join("in");
+        std::fs::create_dir_all(&input).unwrap();
+        for i in 0..8 {
+            std::fs::write(input.join(format!("f{i}.rtf")), "{\\rtf1 Hi\\par}").unwrap();
+        }
+        let c_input = CString::new(input.to_str().unwrap()).unwrap();
+        let c_output = CString::new(root.join("out").to_str().unwrap()).unwrap();
+
+        for _ in 0..5 {
+            let options = CString::new("{\"max_parallelism\": 2}").unwrap();
+            assert_eq!(unsafe { legacybridge_initialize(options.as_ptr()) }, 1);
+            assert!(lock_unpoisoned(&STARTUP_WARNING).is_empty());
+
+            let md = call_str(legacybridge_rtf_to_markdown, "{\\rtf1 Hi\\par}").unwrap();
+            assert!(md.contains("Hi"));
+            let converted = unsafe {
+                legacybridge_convert_folder_rtf_to_md(
+                    c_input.as_ptr(),
+                    c_output.as_ptr(),
+                    std::ptr::null(),
+                )
+            };
+            assert_eq!(converted, 8);
+
+            assert_eq!(legacybridge_shutdown(), 1);
+            assert_eq!(LIVE_WORKERS.load(Ordering::SeqCst), 0);
+            assert!(lock_unpoisoned(&RUNTIME).is_none());
+            assert!(lock_unpoisoned(&LAST_FOLDER_REPORT).is_empty());
+        }
+
+        // Converting before initialize auto-initializes with the defaults
+        // and records a startup warning.
+        let md = call_str(legacybridge_rtf_to_markdown, "{\\rtf1 Late\\par}").unwrap();
+        assert!(md.contains("Late"));
+        assert!(lock_unpoisoned(&STARTUP_WARNING).contains("before legacybridge_initialize"));
+        assert_eq!(legacybridge_shutdown(), 1);
+
+        // Initialize applies the configured security limits.
+        let options = CString::new("{\"max_input_size\": 8}").unwrap();
+        assert_eq!(unsafe { legacybridge_initialize(options.as_ptr()) }, 1);
+        assert!(call_str(legacybridge_rtf_to_markdown, "{\\rtf1 too big\\par}").is_none());
+        assert_eq!(legacybridge_shutdown(), 1);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn null_input_sets_last_error() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let out = unsafe { legacybridge_rtf_to_markdown(std::ptr::null()) };
+        assert!(out.is_null());
+        let err = legacybridge_get_last_error();
+        let message = unsafe { CStr::from_ptr(err) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(err) };
+        assert!(message.contains("null pointer"));
+    }
+
+    #[test]
+    fn error_envelope_json_matches_the_shared_bridge() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let denied = "{\\rtf1{\\object\\objdata 0102}}";
+        assert!(call_str(legacybridge_rtf_to_markdown, denied).is_none());
+        let ptr = legacybridge_get_last_error_json();
+        assert!(!ptr.is_null());
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let envelope: ErrorEnvelope = serde_json::from_str(&json).unwrap();
+        // Identical to what the same failure produces everywhere else.
+        let expected = ErrorEnvelope::from(
+            &conversion::secure_rtf_to_markdown(denied, &SecurityLimits::default()).unwrap_err(),
+        );
+        assert_eq!(envelope, expected);
+
+        // Bridge-level input errors get the `input` category.
+        let out = unsafe { legacybridge_rtf_to_markdown(std::ptr::null()) };
+        assert!(out.is_null());
+        let ptr = legacybridge_get_last_error_json();
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        let envelope: ErrorEnvelope = serde_json::from_str(&json).unwrap();
+        assert_eq!(envelope.category, "input");
+        assert_eq!(envelope.code, -1);
+
+        // A successful call clears the envelope along with the string.
+        call_str(legacybridge_rtf_to_markdown, "{\\rtf1 fine\\par}").unwrap();
+        assert!(legacybridge_get_last_error_json().is_null());
+    }
+
+    #[test]
+    fn test_connection_reports_healthy() {
+        assert_eq!(legacybridge_test_connection(), 1);
+    }
+
+    #[test]
+    fn version_info_contains_version() {
+        let ptr = legacybridge_get_version_info();
+        let version = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        assert!(version.contains(env!("CARGO_PKG_VERSION")));
+    }
+
+    #[test]
+    fn selftest_fills_the_buffer_or_reports_the_needed_size() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let mut buffer = vec![0 as c_char; 8192];
+        let code = unsafe {
+            legacybridge_run_selftest(0, buffer.as_mut_ptr(), buffer.len() as i32)
+        };
+        assert_eq!(code, 0);
+        let json = unsafe { CStr::from_ptr(buffer.as_ptr()) }.to_str().unwrap();
+        let report: legacybridge_core::selftest::SelftestReport =
+            serde_json::from_str(json).unwrap();
+        assert!(report.passed, "{report:#?}");
+        assert_eq!(report.checks.len(), 7);
+
+        // Too-small buffers fail cleanly with the required size on record.
+        let mut tiny = [0 as c_char; 4];
+        let code = unsafe { legacybridge_run_selftest(0, tiny.as_mut_ptr(), tiny.len() as i32) };
+        assert_eq!(code, LEGACYBRIDGE_ERROR_INVALID_INPUT);
+        let err = legacybridge_get_last_error();
+        let message = unsafe { CStr::from_ptr(err) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(err) };
+        assert!(message.contains("too small"), "{message}");
+
+        let code = unsafe { legacybridge_run_selftest(0, std::ptr::null_mut(), 0) };
+        assert_eq!(code, LEGACYBRIDGE_ERROR_INVALID_INPUT);
+    }
+
+    #[test]
+    fn secure_path_rejects_denied_control_words() {
+        let out = call_str(
+            legacybridge_rtf_to_markdown,
+            "{\\rtf1{\\object\\objdata 0102}}",
+        );
+        assert!(out.is_none());
+    }
+
+    /// What an export touches besides its own arguments; the crate doc's
+    /// "Thread safety" section summarizes this table.
+    #[derive(Debug, PartialEq)]
+    enum ThreadSafety {
+        /// Touches no process-wide state at all.
+        Stateless,
+        /// Reads or writes the audited shared slots (last error, runtime,
+        /// folder report, worker counter) through `lock_unpoisoned`.
+        SharedSlots,
+    }
+
+    /// Every C export, classified. The companion test scans this file for
+    /// `extern "C" fn legacybridge_*` definitions, so adding an export
+    /// without a row here fails the build.
+    const FFI_THREAD_SAFETY: &[(&str, ThreadSafety)] = &[
+        ("legacybridge_rtf_to_markdown", ThreadSafety::SharedSlots),
+        (
+            "legacybridge_rtf_to_markdown_with_options",
+            ThreadSafety::SharedSlots,
+        ),
+        ("legacybridge_markdown_to_rtf", ThreadSafety::SharedSlots),
+        (
+            "legacybridge_markdown_to_rtf_with_integrity",
+            ThreadSafety::SharedSlots,
+        ),
+        ("legacybridge_verify_integrity", ThreadSafety::SharedSlots),
+        ("legacybridge_extract_form_fields", ThreadSafety::SharedSlots),
+        ("legacybridge_validate_deep", ThreadSafety::SharedSlots),
+        ("legacybridge_analyze_markdown", ThreadSafety::SharedSlots),
+        ("legacybridge_control_word_info", ThreadSafety::SharedSlots),
+        (
+            "legacybridge_convert_rtf_file_to_md",
+            ThreadSafety::SharedSlots,
+        ),
+        (
+            "legacybridge_convert_rtf_file_to_md_with_options",
+            ThreadSafety::SharedSlots,
+        ),
+        (
+            "legacybridge_convert_md_file_to_rtf",
+            ThreadSafety::SharedSlots,
+        ),
+        (
+            "legacybridge_convert_folder_rtf_to_md",
+            ThreadSafety::SharedSlots,
+        ),
+        (
+            "legacybridge_convert_folder_rtf_to_md_with_progress",
+            ThreadSafety::SharedSlots,
+        ),
+        (
+            "legacybridge_get_last_folder_report",
+            ThreadSafety::SharedSlots,
+        ),
+        ("legacybridge_initialize", ThreadSafety::SharedSlots),
+        ("legacybridge_reload_templates", ThreadSafety::SharedSlots),
+        ("legacybridge_get_capabilities", ThreadSafety::SharedSlots),
+        ("legacybridge_shutdown", ThreadSafety::SharedSlots),
+        ("legacybridge_set_log_callback", ThreadSafety::SharedSlots),
+        ("legacybridge_clear_log_callback", ThreadSafety::SharedSlots),
+        // Thread-local only; no shared slots involved.
+        ("legacybridge_reset_thread_state", ThreadSafety::Stateless),
+        ("legacybridge_get_last_error", ThreadSafety::SharedSlots),
+        ("legacybridge_get_last_error_json", ThreadSafety::SharedSlots),
+        ("legacybridge_get_last_stats", ThreadSafety::SharedSlots),
+        ("legacybridge_test_connection", ThreadSafety::Stateless),
+        // Writes the last-error slot when the version string ever carries
+        // an interior NUL; classified shared to stay honest.
+        ("legacybridge_get_version_info", ThreadSafety::SharedSlots),
+        ("legacybridge_run_selftest", ThreadSafety::SharedSlots),
+        ("legacybridge_free_string", ThreadSafety::Stateless),
+    ];
+
+    #[test]
+    fn every_export_is_classified_for_thread_safety() {
+        let source = include_str!("lib.rs");
+        let mut exports = Vec::new();
+        for line in source.lines() {
+            if !line.starts_with("pub ") {
+                continue;
+            }
+            let Some(rest) = line.split("extern \"C\" fn ").nth(1) else {
+                continue;
+            };
+            let name: String = rest
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                .collect();
+            if name.starts_with("legacybridge_") {
+                exports.push(name);
+            }
+        }
+        assert!(exports.len() >= 18, "export scan broke: {exports:?}");
+        for name in &exports {
+            assert!(
+                FFI_THREAD_SAFETY.iter().any(|(n, _)| n == name),
+                "export {name} has no thread-safety classification"
+            );
+        }
+        for (name, _) in FFI_THREAD_SAFETY {
+            assert!(
+                exports.iter().any(|e| e == name),
+                "classified export {name} no longer exists"
+            );
+        }
+        // Stateless really means stateless: only the exports that never
+        // reach a shared slot carry the classification (thread-local
+        // state does not count as shared).
+        let stateless: Vec<_> = FFI_THREAD_SAFETY
+            .iter()
+            .filter(|(_, s)| *s == ThreadSafety::Stateless)
+            .map(|(n, _)| *n)
+            .collect();
+        assert_eq!(
+            stateless,
+            [
+                "legacybridge_reset_thread_state",
+                "legacybridge_test_connection",
+                "legacybridge_free_string"
+            ]
+        );
+    }
+
+    /// Hammer the shared slots from several threads at once, then poison
+    /// one deliberately and check error reporting keeps working. Holds
+    /// `GLOBAL_STATE` throughout so the lifecycle test cannot interleave.
+    #[test]
+    fn shared_slots_survive_concurrent_use_and_poisoning() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        let budget = ByteBudget::new(64);
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let budget = &budget;
+                scope.spawn(move || {
+                    for j in 0..50 {
+                        set_last_error(format!("thread {i} iteration {j}"));
+                        let ptr = legacybridge_get_last_error();
+                        assert!(!ptr.is_null());
+                        unsafe { legacybridge_free_string(ptr) };
+                        unsafe { legacybridge_free_string(legacybridge_get_last_folder_report()) };
+                        budget.acquire(16);
+                        budget.release(16);
+                    }
+                });
+            }
+        });
+        assert!(budget.high_water() <= 64);
+
+        // Poison the last-error slot the way a panicking conversion would.
+        let result = std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _held = LAST_ERROR.lock().unwrap();
+                    panic!("poison LAST_ERROR");
+                })
+                .join()
+        });
+        assert!(result.is_err());
+        assert!(LAST_ERROR.is_poisoned());
+
+        // Error reporting still works after the poisoning.
+        set_last_error("still reachable".to_string());
+        let ptr = legacybridge_get_last_error();
+        let message = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { legacybridge_free_string(ptr) };
+        assert_eq!(message, "still reachable");
+        clear_last_error();
+    }
+
+    /// Lines captured by the test log callbacks: (level, user_data,
+    /// message). Shared across the log tests, which serialize on
+    /// `GLOBAL_STATE` and clear it on entry.
+    static LOG_LINES: Mutex<Vec<(i32, usize, String)>> = Mutex::new(Vec::new());
+
+    unsafe extern "C" fn collect_log(level: i32, message: *const c_char, user_data: *mut c_void) {
+        let text = unsafe { CStr::from_ptr(message) }.to_str().unwrap().to_string();
+        lock_unpoisoned(&LOG_LINES).push((level, user_data as usize, text));
+    }
+
+    /// A hostile callback that tries to reconfigure logging and run a
+    /// failing conversion from inside the delivery.
+    unsafe extern "C" fn reentrant_log(level: i32, message: *const c_char, user_data: *mut c_void) {
+        assert_eq!(legacybridge_clear_log_callback(), 0);
+        unsafe { legacybridge_free_string(legacybridge_rtf_to_markdown(std::ptr::null())) };
+        unsafe { collect_log(level, message, user_data) };
+    }
+
+    #[test]
+    fn log_callback_receives_errors_and_stops_after_clearing() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        lock_unpoisoned(&LOG_LINES).clear();
+        let user_data = 0x5A as *mut c_void;
+        assert_eq!(
+            unsafe {
+                legacybridge_set_log_callback(Some(collect_log), user_data, LEGACYBRIDGE_LOG_DEBUG)
+            },
+            1
+        );
+
+        // A failing conversion reaches the callback with its error text.
+        assert!(unsafe { legacybridge_rtf_to_markdown(std::ptr::null()) }.is_null());
+        {
+            let lines = lock_unpoisoned(&LOG_LINES);
+            assert_eq!(lines.len(), 1);
+            assert_eq!(lines[0].0, LEGACYBRIDGE_LOG_ERROR);
+            assert_eq!(lines[0].1, 0x5A);
+            assert!(lines[0].2.contains("null pointer"), "{}", lines[0].2);
+        }
+
+        // Nothing is delivered once the callback is cleared.
+        assert_eq!(legacybridge_clear_log_callback(), 1);
+        assert!(unsafe { legacybridge_rtf_to_markdown(std::ptr::null()) }.is_null());
+        assert_eq!(lock_unpoisoned(&LOG_LINES).len(), 1);
+        clear_last_error();
+    }
+
+    #[test]
+    fn log_callback_honors_min_level_and_truncates_long_messages() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        lock_unpoisoned(&LOG_LINES).clear();
+
+        // An out-of-range level is rejected up front.
+        assert_eq!(
+            unsafe {
+                legacybridge_set_log_callback(
+                    Some(collect_log),
+                    std::ptr::null_mut(),
+                    LEGACYBRIDGE_LOG_DEBUG + 1,
+                )
+            },
+            0
+        );
+
+        // At errors-only, the startup warning is filtered out.
+        assert_eq!(
+            unsafe {
+                legacybridge_set_log_callback(
+                    Some(collect_log),
+                    std::ptr::null_mut(),
+                    LEGACYBRIDGE_LOG_ERROR,
+                )
+            },
+            1
+        );
+        assert_eq!(legacybridge_shutdown(), 1); // shutdown also uninstalls
+        assert_eq!(
+            unsafe {
+                legacybridge_set_log_callback(
+                    Some(collect_log),
+                    std::ptr::null_mut(),
+                    LEGACYBRIDGE_LOG_ERROR,
+                )
+            },
+            1
+        );
+        assert!(call_str(legacybridge_rtf_to_markdown, "{\\rtf1 ping\\par}").is_some());
+        assert!(lock_unpoisoned(&LOG_LINES).is_empty());
+
+        // At warnings, the same startup path is delivered.
+        assert_eq!(legacybridge_shutdown(), 1);
+        assert_eq!(
+            unsafe {
+                legacybridge_set_log_callback(
+                    Some(collect_log),
+                    std::ptr::null_mut(),
+                    LEGACYBRIDGE_LOG_WARN,
+                )
+            },
+            1
+        );
+        assert!(call_str(legacybridge_rtf_to_markdown, "{\\rtf1 ping\\par}").is_some());
+        {
+            let lines = lock_unpoisoned(&LOG_LINES);
+            assert_eq!(lines.len(), 1);
+            assert_eq!(lines[0].0, LEGACYBRIDGE_LOG_WARN);
+            assert!(lines[0].2.contains("before legacybridge_initialize"));
+        }
+
+        // Long messages are cut on a character boundary, never mid-char.
+        set_last_error("€".repeat(400));
+        {
+            let lines = lock_unpoisoned(&LOG_LINES);
+            let message = &lines.last().unwrap().2;
+            assert!(message.len() <= LEGACYBRIDGE_MAX_LOG_MESSAGE_BYTES);
+            assert_eq!(message.len() % 3, 0, "truncation split a character");
+        }
+        assert_eq!(legacybridge_clear_log_callback(), 1);
+        clear_last_error();
+    }
+
+    #[test]
+    fn log_callback_is_not_reentered_from_inside_a_delivery() {
+        let _guard = lock_unpoisoned(&GLOBAL_STATE);
+        lock_unpoisoned(&LOG_LINES).clear();
+        assert_eq!(
+            unsafe {
+                legacybridge_set_log_callback(
+                    Some(reentrant_log),
+                    std::ptr::null_mut(),
+                    LEGACYBRIDGE_LOG_DEBUG,
+                )
+            },
+            1
+        );
+
+        // The callback's own failing conversion produces no nested
+        // delivery, and its clear attempt is refused, so the callback
+        // stays installed for the next error.
+        assert!(unsafe { legacybridge_rtf_to_markdown(std::ptr::null()) }.is_null());
+        assert_eq!(lock_unpoisoned(&LOG_LINES).len(), 1);
+        assert!(unsafe { legacybridge_rtf_to_markdown(std::ptr::null()) }.is_null());
+        assert_eq!(lock_unpoisoned(&LOG_LINES).len(), 2);
+
+        // A NULL callback uninstalls, same as the explicit clear.
+        assert_eq!(
+            unsafe { legacybridge_set_log_callback(None, std::ptr::null_mut(), 0) },
+            1
+        );
+        assert!(unsafe { legacybridge_rtf_to_markdown(std::ptr::null()) }.is_null());
+        assert_eq!(lock_unpoisoned(&LOG_LINES).len(), 2);
+        clear_last_error();
+    }
+}
@@ -0,0 +1,11 @@
+//! Fuzz the Markdown parser: no panics on arbitrary input.
+#![no_main]
+
+use legacybridge_core::conversion::markdown_parser::MarkdownParser;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = MarkdownParser::new().parse(input);
+    }
+});
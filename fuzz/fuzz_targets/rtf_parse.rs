@@ -0,0 +1,16 @@
+//! Fuzz the token stream -> document tree parser: no panics, bounded output.
+#![no_main]
+
+use legacybridge_core::conversion::{lexer, rtf_parser::RtfParser};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        if let Ok(tokens) = lexer::tokenize(input) {
+            if let Ok(document) = RtfParser::new(tokens).parse() {
+                // Plain text can never be grossly larger than the input.
+                assert!(document.plain_text().len() <= input.len() * 4 + 1024);
+            }
+        }
+    }
+});
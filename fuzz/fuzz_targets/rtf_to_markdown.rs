@@ -0,0 +1,15 @@
+//! Fuzz the end-to-end secure conversion path with limits enforced.
+#![no_main]
+
+use legacybridge_core::conversion::secure_rtf_to_markdown;
+use legacybridge_core::security::SecurityLimits;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let limits = SecurityLimits::default();
+        if let Ok(markdown) = secure_rtf_to_markdown(input, &limits) {
+            assert!(markdown.len() <= limits.max_output_size);
+        }
+    }
+});
@@ -0,0 +1,18 @@
+//! Fuzz the lexers: neither may panic, and when both succeed the scalar and
+//! SIMD token streams must be identical.
+#![no_main]
+
+use legacybridge_core::conversion::{lexer, simd_lexer};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let scalar = lexer::tokenize(input);
+        let simd = simd_lexer::tokenize_simd(input);
+        match (&scalar, &simd) {
+            (Ok(a), Ok(b)) => assert_eq!(a, b, "scalar and SIMD lexers diverged"),
+            (Err(_), Err(_)) => {}
+            _ => panic!("one lexer failed where the other succeeded"),
+        }
+    }
+});
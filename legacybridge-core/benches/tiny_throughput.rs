@@ -0,0 +1,66 @@
+//! Per-call overhead on tiny documents.
+//!
+//! At batch rates (tens of thousands of memo-field conversions per
+//! second) the fixed setup cost of a conversion dominates tiny
+//! documents. This guards the thread-local reuse in
+//! [`conversion::thread_state`]: the lexer's recycled token buffer and
+//! the template stage's cached registry. The 10k-document batch is run
+//! as one measured unit so the per-call overhead, not the conversion
+//! itself, is what shifts the number.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use legacybridge_core::conversion;
+use legacybridge_core::conversion::environment::ConversionEnvironment;
+use legacybridge_core::conversion::pipeline::{DocumentPipeline, PipelineConfig};
+use legacybridge_core::conversion::template::TemplateSystem;
+use std::hint::black_box;
+use std::sync::Arc;
+
+fn bench_tiny_documents(c: &mut Criterion) {
+    let rtf = "{\\rtf1 \\b memo\\b0  field contents\\par}";
+    c.bench_function("rtf_to_markdown_10k_tiny", |b| {
+        b.iter(|| {
+            for _ in 0..10_000 {
+                black_box(conversion::rtf_to_markdown(black_box(rtf)).unwrap());
+            }
+        })
+    });
+
+    let config = PipelineConfig {
+        template: Some("memo".to_string()),
+        ..Default::default()
+    };
+    c.bench_function("pipeline_with_template_10k_tiny", |b| {
+        b.iter(|| {
+            for _ in 0..10_000 {
+                let pipeline = DocumentPipeline::new(config.clone());
+                black_box(pipeline.process(black_box(rtf)).unwrap());
+            }
+        })
+    });
+
+    // Same batch through a shared environment: template setup becomes an
+    // Arc clone of the application-level registry.
+    let environment = Arc::new(ConversionEnvironment::new());
+    c.bench_function("pipeline_with_template_10k_tiny_shared_env", |b| {
+        b.iter(|| {
+            for _ in 0..10_000 {
+                let pipeline = DocumentPipeline::new(config.clone())
+                    .with_environment(Arc::clone(&environment));
+                black_box(pipeline.process(black_box(rtf)).unwrap());
+            }
+        })
+    });
+
+    // The setup costs in isolation: rebuilding the registry (validating
+    // every built-in) against taking an environment snapshot.
+    c.bench_function("template_registry_rebuild", |b| {
+        b.iter(|| black_box(TemplateSystem::new()))
+    });
+    c.bench_function("template_environment_snapshot", |b| {
+        b.iter(|| black_box(environment.templates()))
+    });
+}
+
+criterion_group!(benches, bench_tiny_documents);
+criterion_main!(benches);
@@ -0,0 +1,41 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+/**
+ * A conversion failure as every bridge reports it.
+ */
+export type ErrorEnvelope = { 
+/**
+ * Contract major of this payload; see [`contracts`](crate::contracts).
+ * Envelopes recorded before contract versioning are version 1 and
+ * omit the field.
+ */
+v?: number, 
+/**
+ * Stable numeric code matching [`ConversionError::error_code`];
+ * `-1` for bridge-level input errors (null pointer, bad JSON).
+ */
+code: number, 
+/**
+ * Stable category: `parse`, `validation`, `generation`,
+ * `resource_limit`, `cancelled`, `contract`, or `input` for
+ * bridge-level errors.
+ */
+category: string, message: string, 
+/**
+ * Validation code (`RTF003`, ...) when the error carries one.
+ */
+validation_code: string | null, 
+/**
+ * Byte offset of the failure, when the failing stage reports one.
+ */
+position: number | null, 
+/**
+ * Actionable suggestions for the user, in display order.
+ */
+hints: Array<string>, 
+/**
+ * Whether retrying can succeed without editing the document:
+ * cancellations and resource limits are transient, and parse errors
+ * may yield to `auto_recovery`.
+ */
+recoverable: boolean, };
@@ -0,0 +1,571 @@
+//! Optional `legacybridge.toml` configuration shared by the library and
+//! the DLL.
+//!
+//! Desktop users have the settings UI; DLL integrators would otherwise
+//! need code changes in their VB6/VFP9 host to configure anything. A
+//! configuration file next to the DLL (or at an explicit path) covers
+//! security limits, the template directory, the font map, link rewrite
+//! rules, logging, the default conversion options and the
+//! folder-conversion worker count:
+//!
+//! ```toml
+//! template_directory = "templates"
+//! font_map_path = "fonts.json"
+//! link_rewrite_path = "links.json"
+//!
+//! [limits]
+//! max_input_size = 1048576
+//!
+//! [logging]
+//! level = "info"
+//! file = "legacybridge.log"
+//!
+//! [conversion]
+//! legacy_mode = true
+//!
+//! [runtime]
+//! max_parallelism = 4
+//! ```
+//!
+//! `LEGACYBRIDGE_*` environment variables override the file (e.g.
+//! `LEGACYBRIDGE_MAX_INPUT_SIZE`, `LEGACYBRIDGE_LEGACY_MODE`), so an
+//! operator can adjust a deployment without touching the file the
+//! installer shipped. Every setting records where its effective value
+//! came from in [`LegacyBridgeConfig::sources`]; unknown keys and
+//! unparsable overrides land in [`LegacyBridgeConfig::warnings`] instead
+//! of failing the load, while TOML syntax and type errors fail with the
+//! line and column.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::security::SecurityLimits;
+
+/// File name searched for when no explicit path is given.
+pub const CONFIG_FILE_NAME: &str = "legacybridge.toml";
+
+/// Where a setting's effective value came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConfigSource {
+    Default,
+    File,
+    Environment,
+}
+
+/// Log verbosity accepted by the `[logging]` section. The core does not
+/// log itself; the value is validated here and surfaced to hosts through
+/// the capabilities report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+/// Effective configuration after merging defaults, the file and the
+/// environment, with per-setting provenance.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LegacyBridgeConfig {
+    /// Security limits applied to every conversion.
+    pub limits: SecurityLimits,
+    /// Directory holding user templates, for hosts that load them.
+    pub template_directory: Option<String>,
+    /// JSON [`FontMap`](crate::conversion::font_map::FontMap) overriding
+    /// the built-in font substitutions.
+    pub font_map_path: Option<String>,
+    /// JSON [`LinkRewriteConfig`](crate::conversion::link_rewrite::LinkRewriteConfig)
+    /// with hyperlink rewrite rules, for hosts that wire it into
+    /// [`PipelineConfig::link_rewrite_path`](crate::PipelineConfig::link_rewrite_path).
+    pub link_rewrite_path: Option<String>,
+    pub log_level: Option<LogLevel>,
+    pub log_file: Option<String>,
+    /// Default conversion options (see [`crate::PipelineConfig`]).
+    pub legacy_mode: bool,
+    pub strict_validation: bool,
+    pub preserve_formatting: bool,
+    /// Folder-conversion worker count; `None` means the CPU count.
+    pub max_parallelism: Option<usize>,
+    /// Where each setting's effective value came from, keyed by its TOML
+    /// path (`limits.max_input_size`, `conversion.legacy_mode`, ...).
+    pub sources: BTreeMap<String, ConfigSource>,
+    /// Unknown keys and ignored overrides; never fatal.
+    pub warnings: Vec<String>,
+}
+
+impl Default for LegacyBridgeConfig {
+    fn default() -> Self {
+        let mut sources = BTreeMap::new();
+        for key in ALL_KEYS {
+            sources.insert(key.to_string(), ConfigSource::Default);
+        }
+        LegacyBridgeConfig {
+            limits: SecurityLimits::default(),
+            template_directory: None,
+            font_map_path: None,
+            link_rewrite_path: None,
+            log_level: None,
+            log_file: None,
+            legacy_mode: false,
+            strict_validation: false,
+            preserve_formatting: true,
+            max_parallelism: None,
+            sources,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Every configurable setting, by its TOML path. Drives the provenance
+/// map and the unknown-key check, so a new setting added here is covered
+/// by both.
+const ALL_KEYS: &[&str] = &[
+    "template_directory",
+    "font_map_path",
+    "link_rewrite_path",
+    "limits.max_input_size",
+    "limits.max_nesting_depth",
+    "limits.max_token_count",
+    "limits.max_output_size",
+    "limits.max_format_runs",
+    "limits.max_embedded_object_size",
+    "limits.max_total_embedded_size",
+    "limits.max_image_count",
+    "logging.level",
+    "logging.file",
+    "conversion.legacy_mode",
+    "conversion.strict_validation",
+    "conversion.preserve_formatting",
+    "runtime.max_parallelism",
+];
+
+/// The file's schema: every field optional so partial files work. Kept
+/// separate from [`LegacyBridgeConfig`] so absent keys stay
+/// distinguishable from explicit defaults.
+#[derive(Default, Deserialize)]
+struct ConfigFile {
+    template_directory: Option<String>,
+    font_map_path: Option<String>,
+    link_rewrite_path: Option<String>,
+    #[serde(default)]
+    limits: LimitsSection,
+    #[serde(default)]
+    logging: LoggingSection,
+    #[serde(default)]
+    conversion: ConversionSection,
+    #[serde(default)]
+    runtime: RuntimeSection,
+}
+
+#[derive(Default, Deserialize)]
+struct LimitsSection {
+    max_input_size: Option<usize>,
+    max_nesting_depth: Option<usize>,
+    max_token_count: Option<usize>,
+    max_output_size: Option<usize>,
+    max_format_runs: Option<usize>,
+    max_embedded_object_size: Option<usize>,
+    max_total_embedded_size: Option<usize>,
+    max_image_count: Option<usize>,
+}
+
+#[derive(Default, Deserialize)]
+struct LoggingSection {
+    level: Option<LogLevel>,
+    file: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct ConversionSection {
+    legacy_mode: Option<bool>,
+    strict_validation: Option<bool>,
+    preserve_formatting: Option<bool>,
+}
+
+#[derive(Default, Deserialize)]
+struct RuntimeSection {
+    max_parallelism: Option<usize>,
+}
+
+impl LegacyBridgeConfig {
+    /// Load from an explicit file path, then apply environment overrides.
+    /// A missing or invalid file is an error here - the caller asked for
+    /// this file specifically.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read configuration {}: {e}", path.display()))?;
+        let mut config = Self::from_toml(&text)
+            .map_err(|e| format!("configuration {}: {e}", path.display()))?;
+        config.apply_env();
+        Ok(config)
+    }
+
+    /// Load the configuration the way the DLL does: `legacybridge.toml`
+    /// next to the host executable, then in the current directory; when
+    /// neither exists, the defaults. Environment overrides apply in every
+    /// case. A file that exists but fails to parse is still an error -
+    /// silently ignoring a present config is worse than failing loudly.
+    pub fn load_default() -> Result<Self, String> {
+        let mut candidates = Vec::new();
+        if let Some(dir) = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        {
+            candidates.push(dir.join(CONFIG_FILE_NAME));
+        }
+        candidates.push(CONFIG_FILE_NAME.into());
+        for candidate in candidates {
+            if candidate.is_file() {
+                return Self::from_file(candidate);
+            }
+        }
+        let mut config = Self::default();
+        config.apply_env();
+        Ok(config)
+    }
+
+    /// Parse the TOML text. Syntax and type errors fail with the line and
+    /// column from the TOML parser; unknown keys only warn.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let file: ConfigFile = toml::from_str(text).map_err(|e| e.to_string().trim().to_string())?;
+        let mut config = LegacyBridgeConfig {
+            warnings: unknown_keys(text),
+            ..Self::default()
+        };
+
+        let mut set = |key: &str, applied: bool| {
+            if applied {
+                config.sources.insert(key.to_string(), ConfigSource::File);
+            }
+        };
+        set("template_directory", file.template_directory.is_some());
+        set("font_map_path", file.font_map_path.is_some());
+        set("link_rewrite_path", file.link_rewrite_path.is_some());
+        set("limits.max_input_size", file.limits.max_input_size.is_some());
+        set("limits.max_nesting_depth", file.limits.max_nesting_depth.is_some());
+        set("limits.max_token_count", file.limits.max_token_count.is_some());
+        set("limits.max_output_size", file.limits.max_output_size.is_some());
+        set("limits.max_format_runs", file.limits.max_format_runs.is_some());
+        set(
+            "limits.max_embedded_object_size",
+            file.limits.max_embedded_object_size.is_some(),
+        );
+        set(
+            "limits.max_total_embedded_size",
+            file.limits.max_total_embedded_size.is_some(),
+        );
+        set("limits.max_image_count", file.limits.max_image_count.is_some());
+        set("logging.level", file.logging.level.is_some());
+        set("logging.file", file.logging.file.is_some());
+        set("conversion.legacy_mode", file.conversion.legacy_mode.is_some());
+        set(
+            "conversion.strict_validation",
+            file.conversion.strict_validation.is_some(),
+        );
+        set(
+            "conversion.preserve_formatting",
+            file.conversion.preserve_formatting.is_some(),
+        );
+        set("runtime.max_parallelism", file.runtime.max_parallelism.is_some());
+
+        let limits = &mut config.limits;
+        let l = file.limits;
+        limits.max_input_size = l.max_input_size.unwrap_or(limits.max_input_size);
+        limits.max_nesting_depth = l.max_nesting_depth.unwrap_or(limits.max_nesting_depth);
+        limits.max_token_count = l.max_token_count.unwrap_or(limits.max_token_count);
+        limits.max_output_size = l.max_output_size.unwrap_or(limits.max_output_size);
+        limits.max_format_runs = l.max_format_runs.unwrap_or(limits.max_format_runs);
+        limits.max_embedded_object_size = l
+            .max_embedded_object_size
+            .unwrap_or(limits.max_embedded_object_size);
+        limits.max_total_embedded_size = l
+            .max_total_embedded_size
+            .unwrap_or(limits.max_total_embedded_size);
+        limits.max_image_count = l.max_image_count.unwrap_or(limits.max_image_count);
+
+        config.template_directory = file.template_directory;
+        config.font_map_path = file.font_map_path;
+        config.link_rewrite_path = file.link_rewrite_path;
+        config.log_level = file.logging.level;
+        config.log_file = file.logging.file;
+        if let Some(value) = file.conversion.legacy_mode {
+            config.legacy_mode = value;
+        }
+        if let Some(value) = file.conversion.strict_validation {
+            config.strict_validation = value;
+        }
+        if let Some(value) = file.conversion.preserve_formatting {
+            config.preserve_formatting = value;
+        }
+        config.max_parallelism = file.runtime.max_parallelism;
+        Ok(config)
+    }
+
+    /// Apply `LEGACYBRIDGE_*` overrides from the process environment.
+    pub fn apply_env(&mut self) {
+        self.apply_env_from(std::env::vars());
+    }
+
+    /// Apply overrides from an explicit variable list, so tests (and
+    /// hosts with their own configuration store) can inject values
+    /// without mutating the process environment.
+    pub fn apply_env_from(&mut self, vars: impl IntoIterator<Item = (String, String)>) {
+        for (name, value) in vars {
+            let Some(setting) = name.strip_prefix("LEGACYBRIDGE_") else {
+                continue;
+            };
+            match self.apply_override(setting, &value) {
+                Ok(Some(key)) => {
+                    self.sources.insert(key.to_string(), ConfigSource::Environment);
+                }
+                Ok(None) => self
+                    .warnings
+                    .push(format!("unknown environment override {name} ignored")),
+                Err(e) => self
+                    .warnings
+                    .push(format!("{name}={value} ignored: {e}")),
+            }
+        }
+    }
+
+    /// Apply one override; returns the setting's TOML path, `None` for an
+    /// unrecognized name, or an error for an unparsable value.
+    fn apply_override(&mut self, setting: &str, value: &str) -> Result<Option<&'static str>, String> {
+        fn parse<T: std::str::FromStr>(value: &str, what: &str) -> Result<T, String> {
+            value.parse().map_err(|_| format!("expected {what}"))
+        }
+        let key = match setting {
+            "MAX_INPUT_SIZE" => {
+                self.limits.max_input_size = parse(value, "a byte count")?;
+                "limits.max_input_size"
+            }
+            "MAX_NESTING_DEPTH" => {
+                self.limits.max_nesting_depth = parse(value, "a number")?;
+                "limits.max_nesting_depth"
+            }
+            "MAX_TOKEN_COUNT" => {
+                self.limits.max_token_count = parse(value, "a number")?;
+                "limits.max_token_count"
+            }
+            "MAX_OUTPUT_SIZE" => {
+                self.limits.max_output_size = parse(value, "a byte count")?;
+                "limits.max_output_size"
+            }
+            "MAX_FORMAT_RUNS" => {
+                self.limits.max_format_runs = parse(value, "a number")?;
+                "limits.max_format_runs"
+            }
+            "MAX_EMBEDDED_OBJECT_SIZE" => {
+                self.limits.max_embedded_object_size = parse(value, "a byte count")?;
+                "limits.max_embedded_object_size"
+            }
+            "MAX_TOTAL_EMBEDDED_SIZE" => {
+                self.limits.max_total_embedded_size = parse(value, "a byte count")?;
+                "limits.max_total_embedded_size"
+            }
+            "MAX_IMAGE_COUNT" => {
+                self.limits.max_image_count = parse(value, "a number")?;
+                "limits.max_image_count"
+            }
+            "TEMPLATE_DIRECTORY" => {
+                self.template_directory = Some(value.to_string());
+                "template_directory"
+            }
+            "FONT_MAP_PATH" => {
+                self.font_map_path = Some(value.to_string());
+                "font_map_path"
+            }
+            "LINK_REWRITE_PATH" => {
+                self.link_rewrite_path = Some(value.to_string());
+                "link_rewrite_path"
+            }
+            "LOG_LEVEL" => {
+                self.log_level = Some(match value.to_ascii_lowercase().as_str() {
+                    "error" => LogLevel::Error,
+                    "warn" => LogLevel::Warn,
+                    "info" => LogLevel::Info,
+                    "debug" => LogLevel::Debug,
+                    _ => return Err("expected error, warn, info or debug".to_string()),
+                });
+                "logging.level"
+            }
+            "LOG_FILE" => {
+                self.log_file = Some(value.to_string());
+                "logging.file"
+            }
+            "LEGACY_MODE" => {
+                self.legacy_mode = parse(value, "true or false")?;
+                "conversion.legacy_mode"
+            }
+            "STRICT_VALIDATION" => {
+                self.strict_validation = parse(value, "true or false")?;
+                "conversion.strict_validation"
+            }
+            "PRESERVE_FORMATTING" => {
+                self.preserve_formatting = parse(value, "true or false")?;
+                "conversion.preserve_formatting"
+            }
+            "MAX_PARALLELISM" => {
+                self.max_parallelism = Some(parse(value, "a worker count")?);
+                "runtime.max_parallelism"
+            }
+            _ => return Ok(None),
+        };
+        Ok(Some(key))
+    }
+}
+
+/// Warnings for keys the schema does not know. A separate lenient parse:
+/// the typed deserialization above skips unknown fields silently, this
+/// walk names them so typos do not go unnoticed.
+fn unknown_keys(text: &str) -> Vec<String> {
+    let Ok(table) = text.parse::<toml::Table>() else {
+        return Vec::new(); // syntax errors already failed the typed parse
+    };
+    let mut warnings = Vec::new();
+    for (key, value) in &table {
+        match value {
+            toml::Value::Table(section) => {
+                for inner in section.keys() {
+                    let path = format!("{key}.{inner}");
+                    if !ALL_KEYS.contains(&path.as_str()) {
+                        warnings.push(format!("unknown configuration key '{path}' ignored"));
+                    }
+                }
+                if !ALL_KEYS.iter().any(|k| k.starts_with(&format!("{key}."))) {
+                    warnings.push(format!("unknown configuration section '{key}' ignored"));
+                }
+            }
+            _ if ALL_KEYS.contains(&key.as_str()) => {}
+            _ => warnings.push(format!("unknown configuration key '{key}' ignored")),
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIXTURE: &str = r#"
+template_directory = "templates"
+font_map_path = "fonts.json"
+link_rewrite_path = "links.json"
+
+[limits]
+max_input_size = 1048576
+max_image_count = 5
+
+[logging]
+level = "debug"
+file = "legacybridge.log"
+
+[conversion]
+legacy_mode = true
+strict_validation = true
+preserve_formatting = false
+
+[runtime]
+max_parallelism = 3
+"#;
+
+    #[test]
+    fn fixture_settings_all_take_effect() {
+        let config = LegacyBridgeConfig::from_toml(FIXTURE).unwrap();
+        assert!(config.warnings.is_empty(), "{:?}", config.warnings);
+        assert_eq!(config.template_directory.as_deref(), Some("templates"));
+        assert_eq!(config.font_map_path.as_deref(), Some("fonts.json"));
+        assert_eq!(config.link_rewrite_path.as_deref(), Some("links.json"));
+        assert_eq!(config.limits.max_input_size, 1_048_576);
+        assert_eq!(config.limits.max_image_count, 5);
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(config.log_file.as_deref(), Some("legacybridge.log"));
+        assert!(config.legacy_mode);
+        assert!(config.strict_validation);
+        assert!(!config.preserve_formatting);
+        assert_eq!(config.max_parallelism, Some(3));
+
+        // Provenance: set keys come from the file, untouched ones stay
+        // at their defaults.
+        assert_eq!(config.sources["limits.max_input_size"], ConfigSource::File);
+        assert_eq!(config.sources["logging.level"], ConfigSource::File);
+        assert_eq!(
+            config.sources["limits.max_nesting_depth"],
+            ConfigSource::Default
+        );
+        assert_eq!(
+            config.limits.max_nesting_depth,
+            crate::security::SecurityLimits::default().max_nesting_depth
+        );
+    }
+
+    #[test]
+    fn environment_overrides_beat_the_file() {
+        let mut config = LegacyBridgeConfig::from_toml(FIXTURE).unwrap();
+        config.apply_env_from([
+            ("LEGACYBRIDGE_MAX_INPUT_SIZE".to_string(), "2048".to_string()),
+            ("LEGACYBRIDGE_LEGACY_MODE".to_string(), "false".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ]);
+        assert_eq!(config.limits.max_input_size, 2048);
+        assert!(!config.legacy_mode);
+        assert_eq!(
+            config.sources["limits.max_input_size"],
+            ConfigSource::Environment
+        );
+        assert_eq!(
+            config.sources["conversion.legacy_mode"],
+            ConfigSource::Environment
+        );
+        // The rest of the file is untouched.
+        assert_eq!(config.max_parallelism, Some(3));
+    }
+
+    #[test]
+    fn bad_overrides_warn_and_keep_the_previous_value() {
+        let mut config = LegacyBridgeConfig::from_toml(FIXTURE).unwrap();
+        config.apply_env_from([
+            ("LEGACYBRIDGE_MAX_INPUT_SIZE".to_string(), "huge".to_string()),
+            ("LEGACYBRIDGE_FROBNICATE".to_string(), "1".to_string()),
+        ]);
+        assert_eq!(config.limits.max_input_size, 1_048_576);
+        assert_eq!(config.sources["limits.max_input_size"], ConfigSource::File);
+        assert!(config.warnings.iter().any(|w| w.contains("expected a byte count")));
+        assert!(config
+            .warnings
+            .iter()
+            .any(|w| w.contains("LEGACYBRIDGE_FROBNICATE")));
+    }
+
+    #[test]
+    fn unknown_keys_warn_but_do_not_fail() {
+        let config = LegacyBridgeConfig::from_toml(
+            "max_input_sise = 5\n[limits]\nmax_inputs = 7\n[telemetry]\nendpoint = \"x\"\n",
+        )
+        .unwrap();
+        assert!(config
+            .warnings
+            .iter()
+            .any(|w| w.contains("'max_input_sise'")));
+        assert!(config.warnings.iter().any(|w| w.contains("'limits.max_inputs'")));
+        assert!(config.warnings.iter().any(|w| w.contains("'telemetry'")));
+    }
+
+    #[test]
+    fn type_errors_carry_line_and_column() {
+        let err = LegacyBridgeConfig::from_toml("[limits]\nmax_input_size = \"big\"\n")
+            .unwrap_err();
+        assert!(err.contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn missing_explicit_file_is_an_error() {
+        let err = LegacyBridgeConfig::from_file("/nonexistent/legacybridge.toml").unwrap_err();
+        assert!(err.contains("cannot read configuration"), "{err}");
+    }
+}
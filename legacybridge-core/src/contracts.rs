@@ -0,0 +1,114 @@
+//! Versioning for the JSON contracts shared with embedders.
+//!
+//! The options documents the DLL accepts, the batch and folder reports,
+//! the capabilities document and the error envelope all evolve, while
+//! VB6/VFP9 hosts compiled years ago keep calling. Every such payload
+//! carries a `v` field naming its contract major; payloads written
+//! before the field existed are contract version 1 and parse with the
+//! documented field defaults. Parsers accept the current and all
+//! previous majors and reject future ones with
+//! [`ConversionError::UnsupportedContractVersion`], so a host talking to
+//! a library older than itself gets a specific error instead of a
+//! silently misread payload. Emitters default to the current major;
+//! callers can ask for an older one (the version-1 rendering simply
+//! omits `v`, which is exactly the shape old consumers parse).
+
+use crate::conversion::ConversionError;
+use serde::Deserialize;
+
+/// The contract major this build emits by default.
+pub const CONTRACT_VERSION: u32 = 2;
+
+/// The oldest contract major this build accepts; version 1 is the
+/// pre-`v` shape of every payload.
+pub const MIN_CONTRACT_VERSION: u32 = 1;
+
+/// Resolve the `v` field of an accepted payload: missing means version
+/// 1, future majors are rejected.
+pub fn accept_version(v: Option<u32>) -> Result<u32, ConversionError> {
+    let v = v.unwrap_or(MIN_CONTRACT_VERSION);
+    if (MIN_CONTRACT_VERSION..=CONTRACT_VERSION).contains(&v) {
+        Ok(v)
+    } else {
+        Err(ConversionError::UnsupportedContractVersion { found: v })
+    }
+}
+
+/// Resolve the version an emitter should write: `None` means current,
+/// and asking for a version this build does not speak is the same error
+/// as receiving one.
+pub fn negotiate_emit_version(requested: Option<u32>) -> Result<u32, ConversionError> {
+    accept_version(requested.or(Some(CONTRACT_VERSION)))
+}
+
+/// The probe shared by every JSON parser: reads only `v`.
+#[derive(Deserialize)]
+struct VersionProbe {
+    v: Option<u32>,
+}
+
+/// Check the contract version of a raw JSON payload without parsing the
+/// rest of it. Malformed JSON passes as version 1 here so the caller's
+/// own parse reports the real syntax error.
+pub fn check_payload_version(json: &str) -> Result<u32, ConversionError> {
+    let v = serde_json::from_str::<VersionProbe>(json)
+        .map(|probe| probe.v)
+        .unwrap_or(None);
+    accept_version(v)
+}
+
+/// Serde default for `v` fields: a payload without one is version 1.
+pub fn version_one() -> u32 {
+    MIN_CONTRACT_VERSION
+}
+
+/// Serde skip for `v` fields: version 1 payloads omit the field, so an
+/// emitter asked for version 1 reproduces the historical shape.
+pub fn is_version_one(v: &u32) -> bool {
+    *v == MIN_CONTRACT_VERSION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_v_means_version_one() {
+        assert_eq!(accept_version(None).unwrap(), 1);
+        assert_eq!(check_payload_version("{\"page_start\": 2}").unwrap(), 1);
+        // Syntax errors are the caller's parse to report, not ours.
+        assert_eq!(check_payload_version("not json").unwrap(), 1);
+    }
+
+    #[test]
+    fn current_and_previous_majors_are_accepted() {
+        for v in MIN_CONTRACT_VERSION..=CONTRACT_VERSION {
+            assert_eq!(accept_version(Some(v)).unwrap(), v);
+        }
+        assert_eq!(
+            check_payload_version(&format!("{{\"v\": {CONTRACT_VERSION}}}")).unwrap(),
+            CONTRACT_VERSION
+        );
+    }
+
+    #[test]
+    fn future_majors_are_rejected_with_the_specific_code() {
+        let err = accept_version(Some(CONTRACT_VERSION + 1)).unwrap_err();
+        assert!(matches!(
+            err,
+            ConversionError::UnsupportedContractVersion { found } if found == CONTRACT_VERSION + 1
+        ));
+        assert_eq!(err.category(), "contract");
+        assert_eq!(err.error_code(), -7);
+        assert!(err.to_string().contains("unsupported contract version"), "{err}");
+        // Version 0 never existed; it is just as unsupported.
+        assert!(accept_version(Some(0)).is_err());
+    }
+
+    #[test]
+    fn emitters_default_to_current_and_honor_explicit_requests() {
+        assert_eq!(negotiate_emit_version(None).unwrap(), CONTRACT_VERSION);
+        assert_eq!(negotiate_emit_version(Some(1)).unwrap(), 1);
+        assert!(negotiate_emit_version(Some(CONTRACT_VERSION + 1)).is_err());
+    }
+}
@@ -0,0 +1,107 @@
+//! Thread-local record of the conversion in flight.
+//!
+//! The pipeline keeps this up to date as it moves through its stages so a
+//! panic hook (the desktop app installs one for crash reporting) can say
+//! what the process was doing when it died. Only the input size, a
+//! content hash and the stage name are recorded - never document content.
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+
+/// What the current thread is converting and how far it got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breadcrumb {
+    /// Input length in bytes.
+    pub input_bytes: usize,
+    /// FNV-1a hash of the input, for correlating a crash with a document
+    /// without storing its content.
+    pub input_hash: u64,
+    /// The stage the pipeline was in: `validate`, `tokenize`, `parse`,
+    /// `transform`, `generate` or `verify`.
+    pub stage: String,
+}
+
+thread_local! {
+    static ACTIVE: RefCell<Option<Breadcrumb>> = const { RefCell::new(None) };
+}
+
+/// Clears the thread's breadcrumb when the conversion leaves
+/// `process_impl` - normally or through `?`. A panic hook still sees the
+/// breadcrumb: hooks run before unwinding drops this guard.
+pub(crate) struct ActiveConversion;
+
+impl Drop for ActiveConversion {
+    fn drop(&mut self) {
+        ACTIVE.with(|slot| slot.borrow_mut().take());
+    }
+}
+
+/// Record the start of a conversion on this thread.
+pub(crate) fn start(input: &str) -> ActiveConversion {
+    ACTIVE.with(|slot| {
+        *slot.borrow_mut() = Some(Breadcrumb {
+            input_bytes: input.len(),
+            input_hash: fnv1a_64(input.as_bytes()),
+            stage: "validate".to_string(),
+        });
+    });
+    ActiveConversion
+}
+
+/// Advance the stage of the conversion on this thread.
+pub(crate) fn stage(stage: &'static str) {
+    ACTIVE.with(|slot| {
+        if let Some(active) = slot.borrow_mut().as_mut() {
+            active.stage = stage.to_string();
+        }
+    });
+}
+
+/// The conversion in flight on the current thread, if any.
+pub fn active() -> Option<Breadcrumb> {
+    ACTIVE.with(|slot| slot.borrow().clone())
+}
+
+/// FNV-1a: stable across runs and toolchain upgrades, so hashes in crash
+/// reports stay comparable over time.
+fn fnv1a_64(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for &byte in bytes {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::pipeline::DocumentPipeline;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn pipeline_tracks_the_conversion_in_flight() {
+        let input = "{\\rtf1 tracked text\\par}";
+        let seen = Arc::new(Mutex::new(None));
+        let captured = Arc::clone(&seen);
+        let pipeline =
+            DocumentPipeline::with_defaults().register_pre_generate("probe", move |_, _| {
+                *captured.lock().unwrap() = active();
+                Ok(())
+            });
+        pipeline.process(input).unwrap();
+
+        let crumb = seen.lock().unwrap().clone().expect("breadcrumb set");
+        assert_eq!(crumb.input_bytes, input.len());
+        assert_eq!(crumb.input_hash, fnv1a_64(input.as_bytes()));
+        assert_eq!(crumb.stage, "transform");
+        // Cleared once the conversion is over.
+        assert!(active().is_none());
+    }
+
+    #[test]
+    fn failed_conversions_clear_the_breadcrumb_too() {
+        assert!(DocumentPipeline::with_defaults().process("not rtf").is_err());
+        assert!(active().is_none());
+    }
+}
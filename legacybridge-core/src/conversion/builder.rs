@@ -0,0 +1,502 @@
+//! Fluent conversion API for library consumers.
+//!
+//! Free functions like [`rtf_to_markdown`](super::rtf_to_markdown) cover
+//! one-line conversions, but anything configurable meant assembling a
+//! [`PipelineConfig`] struct literal, which gains a field most releases.
+//! The builder is the stable surface: name the options you care about,
+//! never mention the rest, and get a [`ConversionOutcome`] carrying the
+//! diagnostics a library caller actually wants.
+//!
+//! ```
+//! use legacybridge_core::conversion::builder::Conversion;
+//!
+//! let outcome = Conversion::rtf_to_markdown("{\\rtf1 \\b Deadline\\b0  Friday\\par}")
+//!     .preserve_formatting(true)
+//!     .run()?;
+//! assert!(outcome.output.contains("**Deadline**"));
+//!
+//! let reverse = Conversion::markdown_to_rtf("# Status\n\nAll green.").run()?;
+//! assert!(reverse.output.starts_with("{\\rtf1"));
+//! # Ok::<(), legacybridge_core::conversion::ConversionError>(())
+//! ```
+
+use super::cancel::CancellationToken;
+use super::clock::ConversionClock;
+use super::environment::ConversionEnvironment;
+use super::features::FeatureUsage;
+use super::integrity;
+use super::markdown_parser::MarkdownParser;
+use super::pipeline::{
+    ConversionError, ConversionResult, ConversionStats, DocumentPipeline, PipelineConfig,
+    PipelineMetadata, PipelineOutput, RecoveryAction, Stage, ValidationLevel, ValidationResult,
+    Validator,
+};
+use super::rtf_generator::RtfGenerator;
+use super::rtf_parser::Annotation;
+use crate::security::{InputValidator, SecurityLimits};
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+
+/// Entry point of the fluent API; see the module docs.
+pub struct Conversion;
+
+impl Conversion {
+    /// Start building an RTF -> Markdown conversion of `input`.
+    pub fn rtf_to_markdown(input: impl Into<String>) -> ConversionBuilder {
+        ConversionBuilder::new(Direction::RtfToMarkdown, input.into())
+    }
+
+    /// Start building a Markdown -> RTF conversion of `input`.
+    pub fn markdown_to_rtf(input: impl Into<String>) -> ConversionBuilder {
+        ConversionBuilder::new(Direction::MarkdownToRtf, input.into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Direction {
+    RtfToMarkdown,
+    MarkdownToRtf,
+}
+
+/// What a built conversion produced: the output plus the diagnostics
+/// that accumulate along the way. The Markdown -> RTF direction runs
+/// without the staged pipeline, so its outcomes carry no metadata,
+/// stats or recovery actions.
+#[derive(Debug, Clone, Default)]
+pub struct ConversionOutcome {
+    /// The converted document (empty for
+    /// [`validate_only`](ConversionBuilder::validate_only)).
+    pub output: String,
+    /// Validation findings that did not stop the conversion.
+    pub warnings: Vec<ValidationResult>,
+    /// Document metadata (title, outline, fragment-ness).
+    pub metadata: Option<PipelineMetadata>,
+    /// Performance counters, when [`collect_stats`](ConversionBuilder::collect_stats) asked for them.
+    pub stats: Option<ConversionStats>,
+    /// Structural repairs auto-recovery applied.
+    pub recovery_actions: Vec<RecoveryAction>,
+    /// Unsupported constructs the document uses.
+    pub feature_usage: FeatureUsage,
+    /// Reviewer comments extracted from the document.
+    pub annotations: Vec<Annotation>,
+    /// Peak heap bytes; `None` without the `memory-accounting` feature.
+    pub peak_memory_bytes: Option<usize>,
+}
+
+impl From<PipelineOutput> for ConversionOutcome {
+    fn from(output: PipelineOutput) -> Self {
+        ConversionOutcome {
+            output: output.markdown,
+            warnings: output.validation_results,
+            metadata: Some(output.metadata),
+            stats: output.stats,
+            recovery_actions: output.recovery_actions,
+            feature_usage: output.feature_usage,
+            annotations: output.annotations,
+            peak_memory_bytes: output.peak_memory_bytes,
+        }
+    }
+}
+
+/// A conversion being configured; terminals are
+/// [`run`](Self::run), [`run_full`](Self::run_full) and
+/// [`validate_only`](Self::validate_only). Construct via
+/// [`Conversion::rtf_to_markdown`] / [`Conversion::markdown_to_rtf`].
+pub struct ConversionBuilder {
+    direction: Direction,
+    input: String,
+    config: PipelineConfig,
+    limits: Option<SecurityLimits>,
+    timeout: Option<Duration>,
+    environment: Option<std::sync::Arc<ConversionEnvironment>>,
+    cancel: Option<CancellationToken>,
+}
+
+impl ConversionBuilder {
+    fn new(direction: Direction, input: String) -> Self {
+        ConversionBuilder {
+            direction,
+            input,
+            config: PipelineConfig::default(),
+            limits: None,
+            timeout: None,
+            environment: None,
+            cancel: None,
+        }
+    }
+
+    /// Keep bold/italic/underline formatting in the output.
+    pub fn preserve_formatting(mut self, preserve: bool) -> Self {
+        self.config.preserve_formatting = preserve;
+        self
+    }
+
+    /// Treat validation warnings as errors.
+    pub fn strict_validation(mut self, strict: bool) -> Self {
+        self.config.strict_validation = strict;
+        self
+    }
+
+    /// Restrict generated RTF to the 1.5 spec for legacy readers.
+    pub fn legacy_mode(mut self, legacy: bool) -> Self {
+        self.config.legacy_mode = legacy;
+        self
+    }
+
+    /// Apply a registered template by name.
+    pub fn template(mut self, name: impl Into<String>) -> Self {
+        self.config.template = Some(name.into());
+        self
+    }
+
+    /// Set the template's `{{variables}}` wholesale.
+    pub fn variables(mut self, variables: HashMap<String, String>) -> Self {
+        self.config.template_variables = variables;
+        self
+    }
+
+    /// Set one template `{{variable}}`.
+    pub fn variable(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config
+            .template_variables
+            .insert(key.into(), value.into());
+        self
+    }
+
+    /// Accept headerless RTF fragments, as database memo fields store
+    /// them.
+    pub fn allow_fragment(mut self, allow: bool) -> Self {
+        self.config.allow_fragment = allow;
+        self
+    }
+
+    /// Shift heading levels; positive demotes, negative promotes.
+    pub fn heading_offset(mut self, offset: i8) -> Self {
+        self.config.heading_offset = offset;
+        self
+    }
+
+    /// Append an integrity signature to the output.
+    pub fn integrity(mut self, integrity: bool) -> Self {
+        self.config.integrity = integrity;
+        self
+    }
+
+    /// Freeze the conversion's clock for reproducible output; see
+    /// [`PipelineConfig::fixed_timestamp`].
+    pub fn fixed_timestamp(mut self, timestamp: impl Into<String>) -> Self {
+        self.config.fixed_timestamp = Some(timestamp.into());
+        self
+    }
+
+    /// Collect performance counters into [`ConversionOutcome::stats`].
+    pub fn collect_stats(mut self, collect: bool) -> Self {
+        self.config.collect_stats = collect;
+        self
+    }
+
+    /// Adjust settings with no dedicated setter, without giving up the
+    /// builder's forward compatibility for the rest:
+    ///
+    /// ```
+    /// use legacybridge_core::conversion::builder::Conversion;
+    ///
+    /// let outcome = Conversion::rtf_to_markdown("{\\rtf1 wrapped text\\par}")
+    ///     .configure(|config| config.wrap_width = Some(40))
+    ///     .run()?;
+    /// # assert!(outcome.output.contains("wrapped"));
+    /// # Ok::<(), legacybridge_core::conversion::ConversionError>(())
+    /// ```
+    pub fn configure(mut self, adjust: impl FnOnce(&mut PipelineConfig)) -> Self {
+        adjust(&mut self.config);
+        self
+    }
+
+    /// Replace the configuration wholesale, for hosts that already carry
+    /// a [`PipelineConfig`]; the Tauri commands and the C FFI route
+    /// through this, so every integration builds conversions the same
+    /// way.
+    pub fn config(mut self, config: PipelineConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Enforce security limits on the raw input before converting.
+    pub fn limits(mut self, limits: SecurityLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    /// Cancel the conversion if it runs longer than `duration`. Only the
+    /// RTF -> Markdown pipeline has cancellation points; the reverse
+    /// direction ignores this.
+    pub fn timeout(mut self, duration: Duration) -> Self {
+        self.timeout = Some(duration);
+        self
+    }
+
+    /// Convert against a shared [`ConversionEnvironment`] instead of the
+    /// per-thread template cache.
+    pub fn environment(mut self, environment: std::sync::Arc<ConversionEnvironment>) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Attach a cancellation token the caller can trip from another
+    /// thread.
+    pub fn cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Run the conversion.
+    pub fn run(self) -> ConversionResult<ConversionOutcome> {
+        match self.direction {
+            Direction::RtfToMarkdown => self.run_full().map(ConversionOutcome::from),
+            Direction::MarkdownToRtf => self.run_reverse(),
+        }
+    }
+
+    /// Run an RTF -> Markdown conversion and return the raw
+    /// [`PipelineOutput`], for hosts that surface every diagnostic
+    /// channel; [`run`](Self::run) distills it into a
+    /// [`ConversionOutcome`]. Fails on the Markdown -> RTF direction,
+    /// which produces no pipeline output.
+    pub fn run_full(self) -> ConversionResult<PipelineOutput> {
+        if self.direction != Direction::RtfToMarkdown {
+            return Err(ConversionError::generation(
+                "run_full applies to the RTF->Markdown direction only; use run()",
+            ));
+        }
+        if let Some(limits) = &self.limits {
+            InputValidator::new(limits.clone())
+                .with_form_fields_allowed(self.config.extract_form_fields)
+                .validate_rtf_input(&self.input)
+                .map_err(ConversionError::validation)?;
+        }
+        let (pipeline, _watchdog) = self.pipeline();
+        pipeline.process(&self.input)
+    }
+
+    /// Validate without generating output: the RTF direction runs the
+    /// pipeline's validation and parsing stages only, the Markdown
+    /// direction runs the Markdown validators. The outcome's `output` is
+    /// empty; findings land in `warnings`, and findings at error level
+    /// fail the call as a full conversion would.
+    pub fn validate_only(mut self) -> ConversionResult<ConversionOutcome> {
+        match self.direction {
+            Direction::RtfToMarkdown => {
+                self.config.stop_after = Stage::Parse;
+                self.run_full().map(ConversionOutcome::from)
+            }
+            Direction::MarkdownToRtf => {
+                let warnings = self.validate_markdown()?;
+                Ok(ConversionOutcome {
+                    warnings,
+                    ..ConversionOutcome::default()
+                })
+            }
+        }
+    }
+
+    /// Build the pipeline this conversion runs through, plus the guard
+    /// keeping an optional timeout watchdog alive for its duration.
+    fn pipeline(&self) -> (DocumentPipeline, Option<WatchdogGuard>) {
+        let mut pipeline = DocumentPipeline::new(self.config.clone());
+        if let Some(environment) = &self.environment {
+            pipeline = pipeline.with_environment(std::sync::Arc::clone(environment));
+        }
+        let mut watchdog = None;
+        match (self.cancel.clone(), self.timeout) {
+            (Some(cancel), timeout) => {
+                if let Some(duration) = timeout {
+                    watchdog = Some(WatchdogGuard::spawn(cancel.clone(), duration));
+                }
+                pipeline = pipeline.with_cancellation(cancel);
+            }
+            (None, Some(duration)) => {
+                let cancel = CancellationToken::new();
+                watchdog = Some(WatchdogGuard::spawn(cancel.clone(), duration));
+                pipeline = pipeline.with_cancellation(cancel);
+            }
+            (None, None) => {}
+        }
+        (pipeline, watchdog)
+    }
+
+    fn run_reverse(self) -> ConversionResult<ConversionOutcome> {
+        let warnings = self.validate_markdown()?;
+        if let Some(limits) = &self.limits {
+            if self.input.len() > limits.max_input_size {
+                return Err(ConversionError::validation(format!(
+                    "input exceeds maximum size ({} > {} bytes)",
+                    self.input.len(),
+                    limits.max_input_size
+                )));
+            }
+        }
+        let mut parser = MarkdownParser::new().with_heading_offset(self.config.heading_offset);
+        if let Some(limits) = &self.limits {
+            parser = parser.with_unicode_hygiene(limits.unicode_hygiene.clone());
+        }
+        let mut document = parser.parse(&self.input).map_err(ConversionError::parse)?;
+        if let Some(rewrites) = &self.config.link_rewrite {
+            let compiled = rewrites.compile().map_err(ConversionError::validation)?;
+            compiled.apply(&mut document.content);
+        }
+        let mut rtf = RtfGenerator::new()
+            .with_legacy_mode(self.config.legacy_mode)
+            .generate(&document)
+            .map_err(ConversionError::generation)?;
+        if self.config.integrity {
+            let fingerprint = self.config.fingerprint(&SecurityLimits::default());
+            let clock = ConversionClock::resolve(
+                self.config.fixed_timestamp.as_deref(),
+                self.config.timezone.clone(),
+            )
+            .map_err(ConversionError::validation)?;
+            rtf = integrity::sign_rtf_at(&rtf, &self.input, &fingerprint, &clock.timestamp_rfc3339())
+                .map_err(ConversionError::generation)?;
+        }
+        Ok(ConversionOutcome {
+            output: rtf,
+            warnings,
+            ..ConversionOutcome::default()
+        })
+    }
+
+    /// Run the Markdown validators, failing on error-level findings and
+    /// returning the rest as warnings.
+    fn validate_markdown(&self) -> ConversionResult<Vec<ValidationResult>> {
+        let results = Validator::new(self.config.strict_validation).validate_markdown(&self.input);
+        if let Some(error) = results.iter().find(|r| r.level == ValidationLevel::Error) {
+            return Err(ConversionError::validation_with_code(
+                error.code.clone(),
+                error.message.clone(),
+            ));
+        }
+        Ok(results)
+    }
+}
+
+/// Cancels a conversion's token after a deadline, unless dropped first.
+/// The watchdog thread blocks on a channel with the timeout, so a
+/// conversion that finishes early releases the thread immediately
+/// instead of leaving it sleeping out the full duration.
+struct WatchdogGuard {
+    done: Option<mpsc::Sender<()>>,
+}
+
+impl WatchdogGuard {
+    fn spawn(cancel: CancellationToken, duration: Duration) -> Self {
+        let (done, finished) = mpsc::channel();
+        std::thread::spawn(move || {
+            if finished.recv_timeout(duration).is_err() {
+                cancel.cancel();
+            }
+        });
+        WatchdogGuard { done: Some(done) }
+    }
+}
+
+impl Drop for WatchdogGuard {
+    fn drop(&mut self) {
+        // Closing the channel wakes the watchdog with a disconnect.
+        self.done.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_builder_converts_in_both_directions() {
+        let outcome = Conversion::rtf_to_markdown("{\\rtf1 \\b bold\\b0  tail\\par}")
+            .run()
+            .unwrap();
+        assert!(outcome.output.contains("**bold**"), "{}", outcome.output);
+        assert!(outcome.metadata.is_some());
+
+        let outcome = Conversion::markdown_to_rtf("# Title\n\nbody").run().unwrap();
+        assert!(outcome.output.starts_with("{\\rtf1"), "{}", outcome.output);
+        assert!(outcome.metadata.is_none());
+    }
+
+    #[test]
+    fn templates_and_variables_flow_through() {
+        let outcome = Conversion::rtf_to_markdown("{\\rtf1 Agenda\\par}")
+            .template("memo")
+            .variable("author", "Pat")
+            .fixed_timestamp("2026-01-15T09:30:00")
+            .run()
+            .unwrap();
+        assert!(!outcome.output.is_empty());
+    }
+
+    #[test]
+    fn limits_are_enforced_on_the_raw_input() {
+        let limits = SecurityLimits {
+            max_input_size: 8,
+            ..Default::default()
+        };
+        let err = Conversion::rtf_to_markdown("{\\rtf1 well beyond eight bytes\\par}")
+            .limits(limits.clone())
+            .run()
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+
+        let err = Conversion::markdown_to_rtf("well beyond eight bytes")
+            .limits(limits)
+            .run()
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+    }
+
+    #[test]
+    fn timeouts_cancel_a_runaway_conversion() {
+        // A large document with a zero timeout: the watchdog fires
+        // before (or during) the conversion, which must surface as the
+        // cancelled category rather than hanging or succeeding.
+        let mut rtf = String::from("{\\rtf1 ");
+        for _ in 0..200_000 {
+            rtf.push_str("paragraph body text\\par ");
+        }
+        rtf.push('}');
+        let err = Conversion::rtf_to_markdown(rtf)
+            .timeout(Duration::from_millis(0))
+            .run()
+            .unwrap_err();
+        assert_eq!(err.category(), "cancelled");
+    }
+
+    #[test]
+    fn validate_only_reports_without_generating() {
+        let outcome = Conversion::rtf_to_markdown("{\\rtf1 {unclosed\\par}")
+            .validate_only()
+            .unwrap();
+        assert!(outcome.output.is_empty());
+        assert!(outcome.warnings.iter().any(|r| r.code == "RTF004"));
+
+        let outcome = Conversion::markdown_to_rtf("![](missing-alt.png)")
+            .validate_only()
+            .unwrap();
+        assert!(outcome.output.is_empty());
+        assert!(outcome.warnings.iter().any(|r| r.code == "MD003"));
+
+        let err = Conversion::markdown_to_rtf("").validate_only().unwrap_err();
+        assert_eq!(err.category(), "validation");
+    }
+
+    #[test]
+    fn run_full_is_the_pipeline_direction_only() {
+        let output = Conversion::rtf_to_markdown("{\\rtf1 Hi\\par}")
+            .run_full()
+            .unwrap();
+        assert!(output.markdown.contains("Hi"));
+
+        let err = Conversion::markdown_to_rtf("Hi").run_full().unwrap_err();
+        assert_eq!(err.category(), "generation");
+    }
+}
@@ -0,0 +1,54 @@
+//! Cooperative cancellation for long-running conversions.
+//!
+//! A [`CancellationToken`] is shared between the caller and a conversion:
+//! the caller flips it, and the lexer, parser and pipeline stage
+//! boundaries observe it within their next check, so even a single large
+//! document stops promptly instead of running to completion.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Message carried by the `Result<_, String>` stages when a conversion is
+/// cancelled; the pipeline maps it to
+/// [`ConversionError::Cancelled`](super::pipeline::ConversionError::Cancelled)
+/// the same way other sentinel messages are mapped to their codes.
+pub const CANCELLED_MESSAGE: &str = "conversion cancelled";
+
+/// A cancellation flag shared between a conversion and its caller.
+///
+/// Cloning is cheap and every clone observes the same flag. Cancellation
+/// is cooperative and one-way: once cancelled, a token stays cancelled.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation; in-flight conversions holding a clone return
+    /// `Cancelled` at their next check.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_flag() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!clone.is_cancelled());
+        token.cancel();
+        assert!(clone.is_cancelled());
+    }
+}
@@ -0,0 +1,254 @@
+//! Injectable time source for reproducible conversions.
+//!
+//! Templates stamp `{{date}}`/`{{time}}` and the integrity block records
+//! its signing time, so two otherwise identical runs differ
+//! byte-for-byte, and every templated document looks changed to a
+//! diff-based regression pipeline. A [`ConversionClock`] makes the time
+//! explicit:
+//! real local time by default, a fixed instant when
+//! [`PipelineConfig::fixed_timestamp`](super::pipeline::PipelineConfig::fixed_timestamp)
+//! or the [`FIXED_TIMESTAMP_VAR`] environment variable says so, and a
+//! selectable timezone for the real-time case.
+
+use chrono::{DateTime, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+/// Environment variable holding a fixed timestamp for CI runs, in the
+/// same formats [`PipelineConfig::fixed_timestamp`](super::pipeline::PipelineConfig::fixed_timestamp)
+/// accepts. An explicit config setting wins over it.
+pub const FIXED_TIMESTAMP_VAR: &str = "LEGACYBRIDGE_FIXED_TIMESTAMP";
+
+/// Which wall clock the real-time case reads. Irrelevant once a fixed
+/// timestamp is set: fixed values are used verbatim.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClockTimezone {
+    /// The machine's local timezone, as the template stage always used.
+    #[default]
+    Local,
+    Utc,
+    /// An IANA timezone name (e.g. `Europe/Berlin`), so a server farm
+    /// spread across regions stamps documents consistently. Requires the
+    /// `named-timezones` feature (chrono-tz); without it the name is a
+    /// configuration error.
+    Named(String),
+}
+
+/// The time source a conversion stamps documents with; see the module
+/// docs. `Default` is real local time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConversionClock {
+    /// Wall-clock instant every read returns; `None` reads real time.
+    fixed: Option<NaiveDateTime>,
+    timezone: ClockTimezone,
+}
+
+impl ConversionClock {
+    /// A clock reading real time in the machine's local timezone - the
+    /// behavior before clocks were injectable.
+    pub fn real() -> Self {
+        ConversionClock::default()
+    }
+
+    /// A clock frozen at `at`; every read returns it verbatim, in any
+    /// timezone, on any machine.
+    pub fn fixed(at: NaiveDateTime) -> Self {
+        ConversionClock {
+            fixed: Some(at),
+            timezone: ClockTimezone::default(),
+        }
+    }
+
+    /// Select the timezone real-time reads use; no-op on a fixed clock.
+    pub fn with_timezone(mut self, timezone: ClockTimezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Build the clock a conversion runs under: an explicit `fixed`
+    /// setting wins, then [`FIXED_TIMESTAMP_VAR`] from the environment,
+    /// then real time in `timezone`. Fails on an unparseable timestamp
+    /// or, without the `named-timezones` feature, a named timezone.
+    pub fn resolve(fixed: Option<&str>, timezone: ClockTimezone) -> Result<Self, String> {
+        Self::resolve_with(fixed, timezone, std::env::var(FIXED_TIMESTAMP_VAR).ok())
+    }
+
+    /// [`resolve`](Self::resolve) with the environment value explicit,
+    /// so tests stay hermetic (the same split [`crate::config`] uses for
+    /// its `LEGACYBRIDGE_*` overrides).
+    fn resolve_with(
+        fixed: Option<&str>,
+        timezone: ClockTimezone,
+        env_value: Option<String>,
+    ) -> Result<Self, String> {
+        if let ClockTimezone::Named(name) = &timezone {
+            #[cfg(feature = "named-timezones")]
+            name.parse::<chrono_tz::Tz>()
+                .map_err(|_| format!("unknown timezone {name:?}"))?;
+            #[cfg(not(feature = "named-timezones"))]
+            return Err(format!(
+                "timezone {name:?} needs the named-timezones feature; \
+                 use local or utc"
+            ));
+        }
+        let fixed = match (fixed, env_value) {
+            (Some(value), _) => Some(parse_timestamp(value)?),
+            (None, Some(value)) => {
+                Some(parse_timestamp(&value).map_err(|e| format!("{FIXED_TIMESTAMP_VAR}: {e}"))?)
+            }
+            (None, None) => None,
+        };
+        Ok(ConversionClock { fixed, timezone })
+    }
+
+    /// The wall-clock time templates stamp into `{{date}}`/`{{time}}`.
+    pub fn now(&self) -> NaiveDateTime {
+        if let Some(at) = self.fixed {
+            return at;
+        }
+        match &self.timezone {
+            ClockTimezone::Local => chrono::Local::now().naive_local(),
+            ClockTimezone::Utc => chrono::Utc::now().naive_utc(),
+            #[cfg(feature = "named-timezones")]
+            ClockTimezone::Named(name) => match name.parse::<chrono_tz::Tz>() {
+                // Validated in resolve(); an unresolved name here means
+                // the clock was built directly - fall back to UTC rather
+                // than panic mid-conversion.
+                Ok(tz) => chrono::Utc::now().with_timezone(&tz).naive_local(),
+                Err(_) => chrono::Utc::now().naive_utc(),
+            },
+            #[cfg(not(feature = "named-timezones"))]
+            ClockTimezone::Named(_) => chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    /// RFC 3339 signing timestamp for the integrity block. A fixed
+    /// clock's value is stamped as UTC so the trailer, too, is identical
+    /// across machines; a real clock reads UTC directly, as the block
+    /// always has.
+    pub fn timestamp_rfc3339(&self) -> String {
+        match self.fixed {
+            Some(at) => at
+                .and_utc()
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+            None => chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        }
+    }
+}
+
+/// Accept RFC 3339 (`2026-01-15T09:30:00Z`, offsets honored by
+/// converting to UTC) or a plain `2026-01-15T09:30:00` wall-clock
+/// value.
+fn parse_timestamp(value: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(instant) = DateTime::parse_from_rfc3339(value) {
+        return Ok(instant.naive_utc());
+    }
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S")
+        .map_err(|_| format!("invalid timestamp {value:?}; use RFC 3339 or YYYY-MM-DDTHH:MM:SS"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naive(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()
+    }
+
+    #[test]
+    fn fixed_clocks_return_the_instant_verbatim() {
+        let clock = ConversionClock::fixed(naive("2026-01-15T09:30:00"));
+        assert_eq!(clock.now(), naive("2026-01-15T09:30:00"));
+        assert_eq!(clock.timestamp_rfc3339(), "2026-01-15T09:30:00Z");
+        // The timezone is irrelevant once the value is fixed.
+        let utc = ConversionClock::fixed(naive("2026-01-15T09:30:00"))
+            .with_timezone(ClockTimezone::Utc);
+        assert_eq!(utc.now(), clock.now());
+    }
+
+    #[test]
+    fn timestamps_parse_in_both_accepted_forms() {
+        assert_eq!(
+            parse_timestamp("2026-01-15T09:30:00").unwrap(),
+            naive("2026-01-15T09:30:00")
+        );
+        // RFC 3339 offsets are honored by converting to UTC.
+        assert_eq!(
+            parse_timestamp("2026-01-15T09:30:00Z").unwrap(),
+            naive("2026-01-15T09:30:00")
+        );
+        assert_eq!(
+            parse_timestamp("2026-01-15T09:30:00+02:00").unwrap(),
+            naive("2026-01-15T07:30:00")
+        );
+        assert!(parse_timestamp("15.01.2026").is_err());
+    }
+
+    #[test]
+    fn explicit_setting_wins_over_the_environment() {
+        let env = Some("2026-06-01T00:00:00".to_string());
+        let clock = ConversionClock::resolve_with(
+            Some("2026-01-15T09:30:00"),
+            ClockTimezone::default(),
+            env.clone(),
+        )
+        .unwrap();
+        assert_eq!(clock.now(), naive("2026-01-15T09:30:00"));
+
+        let clock =
+            ConversionClock::resolve_with(None, ClockTimezone::default(), env).unwrap();
+        assert_eq!(clock.now(), naive("2026-06-01T00:00:00"));
+
+        assert!(
+            ConversionClock::resolve_with(Some("nonsense"), ClockTimezone::default(), None)
+                .is_err()
+        );
+        let err =
+            ConversionClock::resolve_with(None, ClockTimezone::default(), Some("junk".into()))
+                .unwrap_err();
+        assert!(err.contains(FIXED_TIMESTAMP_VAR), "{err}");
+    }
+
+    #[test]
+    fn utc_and_local_real_clocks_both_tick() {
+        // No fixed value: both read real time; assert only that the
+        // reads are sane, not what the machine's clock says.
+        let local = ConversionClock::real().now();
+        let utc = ConversionClock::real()
+            .with_timezone(ClockTimezone::Utc)
+            .now();
+        assert!((local.and_utc().timestamp() - utc.and_utc().timestamp()).abs() < 24 * 3600 + 60);
+    }
+
+    #[cfg(feature = "named-timezones")]
+    #[test]
+    fn named_timezones_resolve_and_shift_the_wall_clock() {
+        let clock =
+            ConversionClock::resolve_with(None, ClockTimezone::Named("Etc/GMT-2".to_string()), None)
+                .unwrap();
+        let shifted = clock.now();
+        let utc = ConversionClock::real()
+            .with_timezone(ClockTimezone::Utc)
+            .now();
+        let delta = shifted.and_utc().timestamp() - utc.and_utc().timestamp();
+        assert!((delta - 2 * 3600).abs() < 60, "delta was {delta}s");
+        assert!(ConversionClock::resolve_with(
+            None,
+            ClockTimezone::Named("Mars/Olympus_Mons".to_string()),
+            None
+        )
+        .is_err());
+    }
+
+    #[cfg(not(feature = "named-timezones"))]
+    #[test]
+    fn named_timezones_are_rejected_without_the_feature() {
+        let err = ConversionClock::resolve_with(
+            None,
+            ClockTimezone::Named("Europe/Berlin".to_string()),
+            None,
+        )
+        .unwrap_err();
+        assert!(err.contains("named-timezones"), "{err}");
+    }
+}
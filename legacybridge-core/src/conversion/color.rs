@@ -0,0 +1,208 @@
+//! Color utilities shared by the RTF parser, the RTF generator and template
+//! style validation.
+//!
+//! RTF stores colors in a `\colortbl` destination and references them by
+//! index (`\cfN`); templates describe them as CSS-style strings (`#RRGGBB`
+//! or a named color). This module owns the conversions between the three.
+
+use super::lexer::RtfToken;
+
+/// An RGB color.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Color { r, g, b }
+    }
+
+    /// `#rrggbb` (lowercase hex).
+    pub fn to_hex(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+
+    /// Squared Euclidean distance in RGB space; used for nearest-color
+    /// lookups where exact matches are not expected.
+    fn distance_squared(self, other: Color) -> u32 {
+        let d = |a: u8, b: u8| {
+            let d = i32::from(a) - i32::from(b);
+            (d * d) as u32
+        };
+        d(self.r, other.r) + d(self.g, other.g) + d(self.b, other.b)
+    }
+}
+
+/// The 16 standard named colors (CSS level 1), which is what legacy RTF
+/// writers and the template styles actually use.
+pub const NAMED_COLORS: &[(&str, Color)] = &[
+    ("black", Color::new(0x00, 0x00, 0x00)),
+    ("silver", Color::new(0xc0, 0xc0, 0xc0)),
+    ("gray", Color::new(0x80, 0x80, 0x80)),
+    ("white", Color::new(0xff, 0xff, 0xff)),
+    ("maroon", Color::new(0x80, 0x00, 0x00)),
+    ("red", Color::new(0xff, 0x00, 0x00)),
+    ("purple", Color::new(0x80, 0x00, 0x80)),
+    ("fuchsia", Color::new(0xff, 0x00, 0xff)),
+    ("green", Color::new(0x00, 0x80, 0x00)),
+    ("lime", Color::new(0x00, 0xff, 0x00)),
+    ("olive", Color::new(0x80, 0x80, 0x00)),
+    ("yellow", Color::new(0xff, 0xff, 0x00)),
+    ("navy", Color::new(0x00, 0x00, 0x80)),
+    ("blue", Color::new(0x00, 0x00, 0xff)),
+    ("teal", Color::new(0x00, 0x80, 0x80)),
+    ("aqua", Color::new(0x00, 0xff, 0xff)),
+];
+
+/// Parse a template color string: `#RRGGBB` or one of [`NAMED_COLORS`].
+pub fn parse_color(s: &str) -> Result<Color, String> {
+    let trimmed = s.trim();
+    if let Some(hex) = trimmed.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!(
+                "invalid color '{trimmed}': expected #RRGGBB with hex digits"
+            ));
+        }
+        let component = |range: std::ops::Range<usize>| {
+            u8::from_str_radix(&hex[range], 16).expect("validated hex digits")
+        };
+        return Ok(Color::new(component(0..2), component(2..4), component(4..6)));
+    }
+    let lower = trimmed.to_ascii_lowercase();
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, color)| *color)
+        .ok_or_else(|| {
+            format!("invalid color '{trimmed}': expected #RRGGBB or a named color")
+        })
+}
+
+/// The name of the closest entry in [`NAMED_COLORS`].
+pub fn nearest_named(color: Color) -> &'static str {
+    NAMED_COLORS
+        .iter()
+        .min_by_key(|(_, named)| color.distance_squared(*named))
+        .map(|(name, _)| *name)
+        .expect("NAMED_COLORS is non-empty")
+}
+
+/// Parse the tokens of a `\colortbl` destination into the color table.
+///
+/// Entries are `;`-terminated; an entry with no components (the leading
+/// "auto" slot most writers emit) parses as black, matching how readers
+/// render it.
+pub fn parse_colortbl(tokens: &[RtfToken]) -> Vec<Color> {
+    let mut colors = Vec::new();
+    let mut current = Color::default();
+    for token in tokens {
+        match token {
+            RtfToken::ControlWord { name, parameter, .. } => {
+                let value = parameter.unwrap_or(0).clamp(0, 255) as u8;
+                match name.as_str() {
+                    "red" => current.r = value,
+                    "green" => current.g = value,
+                    "blue" => current.b = value,
+                    _ => {}
+                }
+            }
+            RtfToken::Text(text) => {
+                for c in text.chars() {
+                    if c == ';' {
+                        colors.push(current);
+                        current = Color::default();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    colors
+}
+
+/// Emit a `\colortbl` destination for the given table.
+///
+/// Entries are emitted exactly as given (including the auto slot, if the
+/// caller has one), so a parsed table round-trips index-for-index.
+pub fn emit_colortbl(colors: &[Color]) -> String {
+    let mut out = String::from("{\\colortbl");
+    for color in colors {
+        out.push_str(&format!(
+            "\\red{}\\green{}\\blue{};",
+            color.r, color.g, color.b
+        ));
+    }
+    out.push('}');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::lexer::tokenize;
+
+    #[test]
+    fn parses_hex_colors() {
+        assert_eq!(parse_color("#000080").unwrap(), Color::new(0, 0, 0x80));
+        assert_eq!(parse_color("#FF8000").unwrap(), Color::new(0xff, 0x80, 0));
+    }
+
+    #[test]
+    fn parses_named_colors() {
+        assert_eq!(parse_color("navy").unwrap(), Color::new(0, 0, 0x80));
+        assert_eq!(parse_color("Navy").unwrap(), Color::new(0, 0, 0x80));
+    }
+
+    #[test]
+    fn rejects_bad_hex_with_clear_message() {
+        let err = parse_color("#GGGGGG").unwrap_err();
+        assert!(err.contains("#GGGGGG"), "{err}");
+        assert!(err.contains("hex digits"), "{err}");
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        let err = parse_color("blurple").unwrap_err();
+        assert!(err.contains("blurple"), "{err}");
+    }
+
+    #[test]
+    fn hex_round_trips() {
+        let color = Color::new(0x12, 0xab, 0xef);
+        assert_eq!(parse_color(&color.to_hex()).unwrap(), color);
+    }
+
+    #[test]
+    fn finds_nearest_named_color() {
+        assert_eq!(nearest_named(Color::new(0x01, 0x02, 0x7f)), "navy");
+        assert_eq!(nearest_named(Color::new(0xfe, 0x01, 0x00)), "red");
+    }
+
+    #[test]
+    fn parses_sixteen_entry_colortbl() {
+        let mut src = String::from("{\\colortbl ;");
+        for (_, color) in NAMED_COLORS {
+            src.push_str(&format!(
+                "\\red{}\\green{}\\blue{};",
+                color.r, color.g, color.b
+            ));
+        }
+        src.push('}');
+        let tokens = tokenize(&src).unwrap();
+        let colors = parse_colortbl(&tokens);
+        // The leading auto entry plus the 16 named ones.
+        assert_eq!(colors.len(), 17);
+        assert_eq!(colors[0], Color::default());
+        assert_eq!(colors[13], NAMED_COLORS[12].1); // navy
+    }
+
+    #[test]
+    fn emitted_colortbl_parses_back() {
+        let colors = vec![Color::new(1, 2, 3), Color::new(0xff, 0, 0x80)];
+        let tokens = tokenize(&emit_colortbl(&colors)).unwrap();
+        assert_eq!(parse_colortbl(&tokens), colors);
+    }
+}
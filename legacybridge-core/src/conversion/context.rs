@@ -0,0 +1,441 @@
+//! Per-tenant isolation for server-style embedding.
+//!
+//! Services embedding the core crate serve many tenants from one process.
+//! A [`ConversionContext`] carries the tenant id, per-tenant
+//! [`SecurityLimits`] overrides, a template namespace and metrics labels;
+//! the `_with_context` pipeline entry points enforce per-tenant in-flight
+//! caps through a process-wide limiter and count conversions per tenant in
+//! Prometheus exposition format (see [`render_metrics`]).
+
+use super::template::Template;
+use crate::security::SecurityLimits;
+use crate::sync::lock_unpoisoned;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Everything a server needs to isolate one tenant's conversion.
+#[derive(Debug, Clone, Default)]
+pub struct ConversionContext {
+    /// Tenant id; keys the in-flight cap and the metrics labels.
+    pub tenant: String,
+    /// Overrides the default security limits for this tenant.
+    pub limits: Option<SecurityLimits>,
+    /// Template lookups try `<namespace>/<name>` before the global name.
+    pub template_namespace: Option<String>,
+    /// Tenant-specific templates, registered (under their own, typically
+    /// namespaced, names) before template lookup.
+    pub templates: Vec<Template>,
+    /// Extra labels attached to this conversion's metrics.
+    pub metrics_labels: Vec<(String, String)>,
+}
+
+impl ConversionContext {
+    pub fn new(tenant: impl Into<String>) -> Self {
+        ConversionContext {
+            tenant: tenant.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_limits(mut self, limits: SecurityLimits) -> Self {
+        self.limits = Some(limits);
+        self
+    }
+
+    pub fn with_template_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.template_namespace = Some(namespace.into());
+        self
+    }
+
+    pub fn with_template(mut self, template: Template) -> Self {
+        self.templates.push(template);
+        self
+    }
+
+    pub fn with_label(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metrics_labels.push((key.into(), value.into()));
+        self
+    }
+}
+
+/// Per-tenant in-flight slot; permits decrement it on drop.
+#[derive(Debug, Default)]
+struct TenantSlot {
+    cap: AtomicUsize,
+    in_flight: AtomicUsize,
+}
+
+/// Holds one in-flight slot for a tenant until dropped.
+#[derive(Debug)]
+pub struct TenantPermit {
+    slot: Arc<TenantSlot>,
+}
+
+impl Drop for TenantPermit {
+    fn drop(&mut self) {
+        self.slot.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Registry of per-tenant concurrency caps. Tenants without an explicit
+/// cap are unlimited.
+#[derive(Debug, Default)]
+pub struct TenantLimiter {
+    slots: Mutex<HashMap<String, Arc<TenantSlot>>>,
+}
+
+impl TenantLimiter {
+    fn slot(&self, tenant: &str) -> Arc<TenantSlot> {
+        let mut slots = lock_unpoisoned(&self.slots);
+        match slots.get(tenant) {
+            Some(slot) => Arc::clone(slot),
+            None => {
+                let slot = Arc::new(TenantSlot {
+                    cap: AtomicUsize::new(usize::MAX),
+                    in_flight: AtomicUsize::new(0),
+                });
+                slots.insert(tenant.to_string(), Arc::clone(&slot));
+                slot
+            }
+        }
+    }
+
+    /// Set the maximum in-flight conversions for a tenant.
+    pub fn set_cap(&self, tenant: &str, cap: usize) {
+        self.slot(tenant).cap.store(cap, Ordering::SeqCst);
+    }
+
+    /// Take an in-flight slot, failing immediately when the tenant is at
+    /// its cap - servers want fast rejection, not queueing.
+    pub fn acquire(&self, tenant: &str) -> Result<TenantPermit, String> {
+        let slot = self.slot(tenant);
+        let mut current = slot.in_flight.load(Ordering::SeqCst);
+        loop {
+            if current >= slot.cap.load(Ordering::SeqCst) {
+                return Err(format!(
+                    "tenant '{tenant}' is at its concurrent conversion limit"
+                ));
+            }
+            match slot.in_flight.compare_exchange(
+                current,
+                current + 1,
+                Ordering::SeqCst,
+                Ordering::SeqCst,
+            ) {
+                Ok(_) => return Ok(TenantPermit { slot }),
+                Err(actual) => current = actual,
+            }
+        }
+    }
+}
+
+/// The process-wide limiter used by the `_with_context` entry points.
+pub fn tenant_limiter() -> &'static TenantLimiter {
+    static LIMITER: OnceLock<TenantLimiter> = OnceLock::new();
+    LIMITER.get_or_init(TenantLimiter::default)
+}
+
+/// Monotonic counters keyed by rendered label set, e.g.
+/// `conversions_total{tenant="acme"}`.
+fn counters() -> &'static Mutex<BTreeMap<String, u64>> {
+    static COUNTERS: OnceLock<Mutex<BTreeMap<String, u64>>> = OnceLock::new();
+    COUNTERS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Render a label list as Prometheus exposition syntax, without braces.
+fn render_labels(labels: &[(String, String)]) -> String {
+    labels
+        .iter()
+        .map(|(k, v)| format!("{k}=\"{}\"", v.replace('"', "\\\"")))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Count one conversion for a tenant, with the context's extra labels.
+pub(crate) fn count_conversion(ctx: &ConversionContext, outcome: &str) {
+    let mut labels = vec![
+        ("tenant".to_string(), ctx.tenant.clone()),
+        ("outcome".to_string(), outcome.to_string()),
+    ];
+    labels.extend(ctx.metrics_labels.iter().cloned());
+    let key = format!("conversions_total{{{}}}", render_labels(&labels));
+    *lock_unpoisoned(counters()).entry(key).or_insert(0) += 1;
+}
+
+/// Operation-scoped counters keyed by rendered label set, separate from
+/// the per-tenant map so each family renders under its own TYPE header.
+fn operation_counters() -> &'static Mutex<BTreeMap<String, u64>> {
+    static COUNTERS: OnceLock<Mutex<BTreeMap<String, u64>>> = OnceLock::new();
+    COUNTERS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// The `template` label value for a conversion: a built-in template's own
+/// name, `"custom"` for anything user-registered and `"none"` without a
+/// template - so the label's cardinality is bounded no matter what names
+/// tenants register.
+fn template_label(template: Option<&str>) -> &'static str {
+    match template {
+        None => "none",
+        Some("memo") => "memo",
+        Some("report") => "report",
+        Some("letter") => "letter",
+        Some("invoice") => "invoice",
+        Some("contract") => "contract",
+        Some(name) => {
+            debug_assert!(
+                !super::template::is_builtin_name(name),
+                "template_label is missing a builtin arm for {name}"
+            );
+            "custom"
+        }
+    }
+}
+
+/// Count one conversion operation on the
+/// `conversion_operations_total{direction,mode,template,outcome}` series.
+/// Recorded by the core entry points themselves, so every host - Tauri,
+/// the DLL, batch - feeds the same series; the per-tenant
+/// `conversions_total` stays untouched as the aggregate dashboards read.
+pub(crate) fn count_operation(
+    direction: &str,
+    mode: &str,
+    template: Option<&str>,
+    outcome: &str,
+) {
+    let labels = vec![
+        ("direction".to_string(), direction.to_string()),
+        ("mode".to_string(), mode.to_string()),
+        ("template".to_string(), template_label(template).to_string()),
+        ("outcome".to_string(), outcome.to_string()),
+    ];
+    let key = format!("conversion_operations_total{{{}}}", render_labels(&labels));
+    *lock_unpoisoned(operation_counters()).entry(key).or_insert(0) += 1;
+}
+
+/// Bucket upper bounds for `legacybridge_conversion_peak_bytes`, in
+/// bytes: 64 KiB to 64 MiB, roughly the range between a trivial document
+/// and the 10 MiB input cap with room for expansion.
+const PEAK_BYTES_BUCKETS: [u64; 6] = [
+    64 << 10,
+    256 << 10,
+    1 << 20,
+    4 << 20,
+    16 << 20,
+    64 << 20,
+];
+
+/// One Prometheus histogram: cumulative bucket counts aligned with
+/// [`PEAK_BYTES_BUCKETS`], plus the observation sum and count.
+#[derive(Default, Clone)]
+struct Histogram {
+    buckets: [u64; PEAK_BYTES_BUCKETS.len()],
+    sum: u64,
+    count: u64,
+}
+
+/// Peak-memory histograms keyed by rendered label set.
+fn peak_histograms() -> &'static Mutex<BTreeMap<String, Histogram>> {
+    static HISTOGRAMS: OnceLock<Mutex<BTreeMap<String, Histogram>>> = OnceLock::new();
+    HISTOGRAMS.get_or_init(|| Mutex::new(BTreeMap::new()))
+}
+
+/// Record one conversion's peak heap bytes for a tenant. Only called
+/// when the pipeline actually measured a peak, i.e. in builds with the
+/// `memory-accounting` feature.
+pub(crate) fn observe_peak_bytes(ctx: &ConversionContext, bytes: u64) {
+    let mut labels = vec![("tenant".to_string(), ctx.tenant.clone())];
+    labels.extend(ctx.metrics_labels.iter().cloned());
+    let mut histograms = lock_unpoisoned(peak_histograms());
+    let histogram = histograms.entry(render_labels(&labels)).or_default();
+    for (bucket, bound) in histogram.buckets.iter_mut().zip(PEAK_BYTES_BUCKETS) {
+        if bytes <= bound {
+            *bucket += 1;
+        }
+    }
+    histogram.sum += bytes;
+    histogram.count += 1;
+}
+
+/// Render all counters and histograms in Prometheus text exposition
+/// format, sorted.
+pub fn render_metrics() -> String {
+    let counters = lock_unpoisoned(counters());
+    let mut out = String::from("# TYPE conversions_total counter\n");
+    for (key, value) in counters.iter() {
+        out.push_str(&format!("{key} {value}\n"));
+    }
+    let operations = lock_unpoisoned(operation_counters());
+    if !operations.is_empty() {
+        out.push_str("# TYPE conversion_operations_total counter\n");
+        for (key, value) in operations.iter() {
+            out.push_str(&format!("{key} {value}\n"));
+        }
+    }
+    let histograms = lock_unpoisoned(peak_histograms());
+    if !histograms.is_empty() {
+        out.push_str("# TYPE legacybridge_conversion_peak_bytes histogram\n");
+        for (labels, histogram) in histograms.iter() {
+            for (count, bound) in histogram.buckets.iter().zip(PEAK_BYTES_BUCKETS) {
+                out.push_str(&format!(
+                    "legacybridge_conversion_peak_bytes_bucket{{{labels},le=\"{bound}\"}} {count}\n"
+                ));
+            }
+            out.push_str(&format!(
+                "legacybridge_conversion_peak_bytes_bucket{{{labels},le=\"+Inf\"}} {}\n",
+                histogram.count
+            ));
+            out.push_str(&format!(
+                "legacybridge_conversion_peak_bytes_sum{{{labels}}} {}\n",
+                histogram.sum
+            ));
+            out.push_str(&format!(
+                "legacybridge_conversion_peak_bytes_count{{{labels}}} {}\n",
+                histogram.count
+            ));
+        }
+    }
+    out
+}
+
+/// Every counter series as a JSON object keyed by rendered series name,
+/// for hosts that poll metrics over an RPC bridge rather than scraping
+/// [`render_metrics`].
+pub fn metrics_snapshot() -> serde_json::Value {
+    let counters = lock_unpoisoned(counters());
+    let operations = lock_unpoisoned(operation_counters());
+    serde_json::json!({
+        "conversions_total": &*counters,
+        "conversion_operations_total": &*operations,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_are_isolated_per_tenant() {
+        let limiter = TenantLimiter::default();
+        limiter.set_cap("a", 1);
+        limiter.set_cap("b", 2);
+
+        let a1 = limiter.acquire("a").unwrap();
+        assert!(limiter.acquire("a").is_err(), "tenant a is at its cap");
+        let _b1 = limiter.acquire("b").unwrap();
+        let _b2 = limiter.acquire("b").unwrap();
+        assert!(limiter.acquire("b").is_err(), "tenant b is at its cap");
+
+        // Releasing a permit frees the slot.
+        drop(a1);
+        let _a2 = limiter.acquire("a").unwrap();
+    }
+
+    #[test]
+    fn unconfigured_tenants_are_unlimited() {
+        let limiter = TenantLimiter::default();
+        let _permits: Vec<_> = (0..32).map(|_| limiter.acquire("free").unwrap()).collect();
+    }
+
+    /// The metrics and limiter paths run from every worker thread of a
+    /// host at once; hammer them concurrently and check nothing is lost.
+    #[test]
+    fn counters_and_limiter_survive_concurrent_use() {
+        let threads = 8;
+        let per_thread = 100;
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(|| {
+                    let ctx = ConversionContext::new("stress-test");
+                    for _ in 0..per_thread {
+                        count_conversion(&ctx, "success");
+                        let permit = tenant_limiter().acquire("stress-test").unwrap();
+                        drop(permit);
+                    }
+                });
+            }
+        });
+        let rendered = render_metrics();
+        assert!(
+            rendered.contains(&format!(
+                "conversions_total{{tenant=\"stress-test\",outcome=\"success\"}} {}",
+                threads * per_thread
+            )),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn peak_byte_observations_render_as_a_histogram() {
+        let ctx = ConversionContext::new("histogram-test");
+        observe_peak_bytes(&ctx, 32 << 10);
+        observe_peak_bytes(&ctx, 2 << 20);
+        let rendered = render_metrics();
+        // 32 KiB lands in every bucket, 2 MiB only from 4 MiB up.
+        assert!(
+            rendered.contains(
+                "legacybridge_conversion_peak_bytes_bucket{tenant=\"histogram-test\",le=\"65536\"} 1"
+            ),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains(
+                "legacybridge_conversion_peak_bytes_bucket{tenant=\"histogram-test\",le=\"4194304\"} 2"
+            ),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains(
+                "legacybridge_conversion_peak_bytes_bucket{tenant=\"histogram-test\",le=\"+Inf\"} 2"
+            ),
+            "{rendered}"
+        );
+        assert!(
+            rendered
+                .contains("legacybridge_conversion_peak_bytes_count{tenant=\"histogram-test\"} 2"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn operation_series_bound_the_template_label() {
+        count_operation("rtf_to_md", "pipeline", Some("letter"), "success");
+        count_operation("rtf_to_md", "pipeline", Some("quarterly-report-v2"), "success");
+        count_operation("md_to_rtf", "simple", None, "error");
+        let operations = metrics_snapshot()["conversion_operations_total"].clone();
+        let value = |template: &str, rest: &str| {
+            operations[format!(
+                "conversion_operations_total{{{rest},template=\"{template}\",outcome=\"success\"}}"
+            )]
+            .as_u64()
+            .unwrap_or(0)
+        };
+        assert!(value("letter", "direction=\"rtf_to_md\",mode=\"pipeline\"") >= 1);
+        // The registered name never becomes a label value.
+        assert!(value("custom", "direction=\"rtf_to_md\",mode=\"pipeline\"") >= 1);
+        assert!(
+            !serde_json::to_string(&operations)
+                .unwrap()
+                .contains("quarterly-report-v2")
+        );
+        let rendered = render_metrics();
+        assert!(
+            rendered.contains("# TYPE conversion_operations_total counter"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn metrics_render_with_tenant_labels() {
+        let ctx = ConversionContext::new("metrics-test").with_label("region", "eu");
+        count_conversion(&ctx, "success");
+        count_conversion(&ctx, "success");
+        let rendered = render_metrics();
+        assert!(
+            rendered.contains(
+                "conversions_total{tenant=\"metrics-test\",outcome=\"success\",region=\"eu\"} 2"
+            ),
+            "{rendered}"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Aliases for non-standard control words from niche RTF writers.
+//!
+//! Our VFP9 report writer emits control words no spec knows (`\fpxhdr`,
+//! `\fpxfoot`) that carry meaning for us. The
+//! [`ControlWordExtensions`] map assigns each such word a handling
+//! [`ExtensionRule`], consulted by the
+//! [`RtfParser`](super::rtf_parser::RtfParser) before its default
+//! unknown-word behavior. The mechanism ships empty; deployments supply
+//! a JSON file via
+//! [`PipelineConfig::control_word_extensions_path`](super::pipeline::PipelineConfig::control_word_extensions_path).
+//! Standard control words cannot be aliased, so spec behavior is never
+//! overridden.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How an aliased control word is handled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "treat_as", rename_all = "kebab-case")]
+pub enum ExtensionRule {
+    /// Skip the rest of the containing group, like an ignorable
+    /// destination.
+    IgnoreGroup,
+    /// Flush the current paragraph, like `\par`.
+    ParagraphBreak,
+    /// Turn bold on for the rest of the group, like `\b`.
+    BoldOn,
+    /// Turn bold off, like `\b0`.
+    BoldOff,
+    /// Skip the containing group but capture its text under `name` in
+    /// [`DocumentMetadata::custom_destinations`](super::rtf_parser::DocumentMetadata::custom_destinations).
+    CustomDestination { name: String },
+}
+
+/// A custom destination captured under an [`ExtensionRule::CustomDestination`]
+/// alias, kept on the document metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomDestination {
+    pub name: String,
+    pub text: String,
+}
+
+/// Configurable control-word alias table; empty by default.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ControlWordExtensions {
+    rules: HashMap<String, ExtensionRule>,
+}
+
+impl ControlWordExtensions {
+    /// Load a map from its JSON form:
+    /// `{"fpxhdr": {"treat_as": "custom-destination", "name": "header"},
+    ///   "fpxpar": {"treat_as": "paragraph-break"}}`.
+    ///
+    /// Rules referencing standard control words are rejected.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let map: ControlWordExtensions = serde_json::from_str(json)
+            .map_err(|e| format!("invalid control word extensions JSON: {e}"))?;
+        for word in map.rules.keys() {
+            if word.is_empty() || !word.chars().all(|c| c.is_ascii_lowercase()) {
+                return Err(format!(
+                    "invalid control word {word:?}: expected lowercase ASCII letters"
+                ));
+            }
+            if super::control_words::control_word_info(word).is_some() {
+                return Err(format!(
+                    "cannot alias standard control word \\{word}: spec behavior is not overridable"
+                ));
+            }
+        }
+        Ok(map)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read control word extensions {path}: {e}"))?;
+        Self::from_json(&json)
+    }
+
+    /// The rule for `name`, or `None` when the word is not aliased.
+    pub fn rule(&self, name: &str) -> Option<&ExtensionRule> {
+        self.rules.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loads_rules_from_json() {
+        let map = ControlWordExtensions::from_json(
+            r#"{"fpxhdr": {"treat_as": "custom-destination", "name": "header"},
+                "fpxbold": {"treat_as": "bold-on"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            map.rule("fpxhdr"),
+            Some(&ExtensionRule::CustomDestination {
+                name: "header".to_string()
+            })
+        );
+        assert_eq!(map.rule("fpxbold"), Some(&ExtensionRule::BoldOn));
+        assert_eq!(map.rule("b"), None);
+    }
+
+    #[test]
+    fn rejects_standard_control_words() {
+        let err =
+            ControlWordExtensions::from_json(r#"{"par": {"treat_as": "ignore-group"}}"#)
+                .unwrap_err();
+        assert!(err.contains("standard control word \\par"), "{err}");
+    }
+
+    #[test]
+    fn rejects_malformed_words_and_json() {
+        let err = ControlWordExtensions::from_json(r#"{"Fpx1": {"treat_as": "bold-on"}}"#)
+            .unwrap_err();
+        assert!(err.contains("lowercase ASCII"), "{err}");
+
+        let err = ControlWordExtensions::from_json("{not json").unwrap_err();
+        assert!(err.contains("invalid control word extensions JSON"), "{err}");
+    }
+}
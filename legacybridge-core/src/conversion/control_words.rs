@@ -0,0 +1,260 @@
+//! Queryable support table for RTF control words.
+//!
+//! Support engineers need to answer "does LegacyBridge handle `\xyz`?"
+//! without reading parser source. This module holds the single
+//! declarative table behind that answer: the degradation report
+//! ([`super::features`]) classifies control words through it, so the
+//! documented support level cannot drift from what conversion actually
+//! does. Queried through [`control_word_info`] in-process, the
+//! `control_word_support` Tauri command, and the
+//! `legacybridge_control_word_info` FFI export.
+
+use super::features::FeatureCategory;
+use serde::Serialize;
+
+/// Functional grouping of a control word, for filtering the table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlWordCategory {
+    Annotations,
+    CharacterFormatting,
+    DocumentStructure,
+    DrawingObjects,
+    EmbeddedObjects,
+    Equations,
+    Fields,
+    FormFields,
+    HeaderTables,
+    Metadata,
+    ParagraphFormatting,
+    Pictures,
+    Revisions,
+    SpecialCharacters,
+    Tables,
+}
+
+/// How completely conversion handles a control word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SupportLevel {
+    /// Converted with its full meaning.
+    Full,
+    /// Converted with reduced fidelity (placeholder, cached text, ...).
+    Partial,
+    /// Recognized and skipped without affecting surrounding text.
+    Ignored,
+    /// Rejected by the security validator.
+    Blocked,
+}
+
+/// One row of the support table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ControlWordInfo {
+    /// Control word without the leading backslash.
+    pub name: &'static str,
+    pub category: ControlWordCategory,
+    pub support_level: SupportLevel,
+    /// Release that first handled the word at this level.
+    pub since_version: &'static str,
+    pub notes: &'static str,
+    /// Degradation category tallied into the feature-usage report, for
+    /// words whose handling loses something.
+    #[serde(skip)]
+    pub(crate) feature: Option<FeatureCategory>,
+}
+
+/// Builds the table from one entry per line; `degrades X` marks the word
+/// for the feature-usage report with [`FeatureCategory`] `X`.
+macro_rules! control_words {
+    ($($name:literal => $category:ident, $support:ident, $since:literal,
+        $notes:literal $(, degrades $feature:ident)? ;)+) => {
+        &[$(ControlWordInfo {
+            name: $name,
+            category: ControlWordCategory::$category,
+            support_level: SupportLevel::$support,
+            since_version: $since,
+            notes: $notes,
+            feature: control_words!(@feature $($feature)?),
+        },)+]
+    };
+    (@feature) => { None };
+    (@feature $feature:ident) => { Some(FeatureCategory::$feature) };
+}
+
+/// The support table, sorted by control word for binary search. Extend it
+/// whenever the parser learns or special-cases a word; the coverage test
+/// below fails otherwise.
+const TABLE: &[ControlWordInfo] = control_words![
+    "annotation" => Annotations, Partial, "0.1", "comment body extracted per the annotation mode";
+    "atnauthor" => Annotations, Partial, "0.1", "comment author captured for the next annotation";
+    "atnicn" => Annotations, Ignored, "0.1", "comment icon presentation is dropped", degrades Annotations;
+    "atnid" => Annotations, Partial, "0.1", "comment initials captured for the next annotation";
+    "atrfend" => Annotations, Partial, "0.1", "closes a comment anchor range";
+    "atrfstart" => Annotations, Partial, "0.1", "opens a comment anchor range";
+    "author" => Metadata, Full, "0.1", "captured into document metadata";
+    "b" => CharacterFormatting, Full, "0.1", "bold on/off";
+    "bkmkend" => Fields, Partial, "0.1", "bookmark range end; skipped (the start carries the anchor)";
+    "bkmkstart" => Fields, Partial, "0.1", "named anchor for REF/PAGEREF resolution when cross-references are on; skipped otherwise";
+    "bullet" => SpecialCharacters, Full, "0.1", "emitted as U+2022";
+    "cell" => Tables, Full, "0.1", "ends the current table cell";
+    "cellx" => Tables, Full, "0.1", "closes a cell definition; the boundary lands on the table model";
+    "cf" => CharacterFormatting, Full, "0.1", "foreground color from the color table";
+    "clmgf" => Tables, Full, "0.1", "first cell of a horizontal merge";
+    "clmrg" => Tables, Full, "0.1", "cell merged into the one to its left";
+    "clvmgf" => Tables, Full, "0.1", "first cell of a vertical merge";
+    "clvmrg" => Tables, Full, "0.1", "cell merged into the one above it";
+    "colortbl" => HeaderTables, Full, "0.1", "color table parsed for \\cfN lookups";
+    "cs" => CharacterFormatting, Full, "0.1", "character style applied from the stylesheet";
+    "dde" => EmbeddedObjects, Blocked, "0.1", "rejected by the input validator";
+    "deflang" => Metadata, Full, "0.1", "document language mapped to the lang front-matter key";
+    "deleted" => Revisions, Full, "0.1", "deleted runs rendered per the revision mode";
+    "do" => DrawingObjects, Partial, "0.1", "replaced with the drawing placeholder", degrades DrawingObjects;
+    "dpellipse" => DrawingObjects, Ignored, "0.1", "drawing primitive is dropped", degrades DrawingObjects;
+    "dpline" => DrawingObjects, Ignored, "0.1", "drawing primitive is dropped", degrades DrawingObjects;
+    "dprect" => DrawingObjects, Ignored, "0.1", "drawing primitive is dropped", degrades DrawingObjects;
+    "dptxbx" => DrawingObjects, Ignored, "0.1", "text box drawing is dropped", degrades DrawingObjects;
+    "emdash" => SpecialCharacters, Full, "0.1", "emitted as U+2014";
+    "endash" => SpecialCharacters, Full, "0.1", "emitted as U+2013";
+    "f" => CharacterFormatting, Full, "0.1", "font selection from the font table";
+    "fftype" => FormFields, Ignored, "0.1", "form field type is dropped", degrades FormFields;
+    "field" => Fields, Partial, "0.1", "HYPERLINK fields become links; others keep their cached result text", degrades Fields;
+    "fldinst" => Fields, Partial, "0.1", "HYPERLINK instructions parsed for target and tooltip; others dropped", degrades Fields;
+    "fldrslt" => Fields, Full, "0.1", "field result text becomes the link text or stays in the flow";
+    "fonttbl" => HeaderTables, Full, "0.1", "font table parsed, font map applied";
+    "footer" => DocumentStructure, Ignored, "0.1", "footer content is dropped";
+    "footerl" => DocumentStructure, Ignored, "0.1", "footer content is dropped";
+    "footerr" => DocumentStructure, Ignored, "0.1", "footer content is dropped";
+    "formfield" => FormFields, Partial, "0.1", "extracted as structured data when form extraction is on", degrades FormFields;
+    "fs" => CharacterFormatting, Full, "0.1", "font size in half-points";
+    "generator" => Metadata, Ignored, "0.1", "producing application is dropped";
+    "header" => DocumentStructure, Ignored, "0.1", "header content is dropped";
+    "headerl" => DocumentStructure, Ignored, "0.1", "header content is dropped";
+    "headerr" => DocumentStructure, Ignored, "0.1", "header content is dropped";
+    "i" => CharacterFormatting, Full, "0.1", "italic on/off";
+    "info" => Metadata, Full, "0.1", "title, author and subject captured";
+    "intbl" => Tables, Full, "0.1", "marks the paragraph as a table row";
+    "keep" => ParagraphFormatting, Partial, "0.1", "parsed but not modeled; re-emitted on table cells per generator config";
+    "keepn" => ParagraphFormatting, Full, "0.1", "keep with next, surfaced as spacing comments";
+    "lang" => CharacterFormatting, Ignored, "0.1", "per-run language is dropped; only the \\deflang default round-trips";
+    "lbimage" => Pictures, Full, "0.1", "LegacyBridge's own image-reference destination; src/alt/title re-read on round trips";
+    "lbimgalt" => Pictures, Full, "0.1", "image alt text, kept for accessibility";
+    "lbimgsrc" => Pictures, Full, "0.1", "image source path or URL";
+    "lbimgtitle" => Pictures, Full, "0.1", "image title, the Markdown tooltip";
+    "lbrawend" => DocumentStructure, Full, "0.1", "closes a raw passthrough region, re-emitted as an rtf-raw fence";
+    "lbrawstart" => DocumentStructure, Full, "0.1", "LegacyBridge's own raw passthrough region marker";
+    "lbsig" => DocumentStructure, Full, "0.1", "LegacyBridge's integrity signature block, skipped on re-parse";
+    "ldblquote" => SpecialCharacters, Full, "0.1", "emitted as U+201C";
+    "line" => DocumentStructure, Full, "0.1", "line break within a paragraph";
+    "listoverridetable" => HeaderTables, Ignored, "0.1", "list overrides are dropped";
+    "listtable" => HeaderTables, Ignored, "0.1", "list definitions are dropped";
+    "lquote" => SpecialCharacters, Full, "0.1", "emitted as U+2018";
+    "ltrch" => CharacterFormatting, Full, "0.1", "left-to-right run direction";
+    "ltrpar" => ParagraphFormatting, Full, "0.1", "left-to-right paragraph direction";
+    "mmath" => Equations, Partial, "0.1", "replaced with the equation placeholder", degrades Equations;
+    "mmathpr" => Equations, Ignored, "0.1", "math properties are dropped", degrades Equations;
+    "objautlink" => EmbeddedObjects, Blocked, "0.1", "rejected by the input validator";
+    "objclass" => EmbeddedObjects, Blocked, "0.1", "rejected by the input validator", degrades EmbeddedObjects;
+    "objdata" => EmbeddedObjects, Blocked, "0.1", "rejected by the input validator", degrades EmbeddedObjects;
+    "object" => EmbeddedObjects, Blocked, "0.1", "embedded object cannot be carried over", degrades EmbeddedObjects;
+    "objemb" => EmbeddedObjects, Blocked, "0.1", "embedded object cannot be carried over", degrades EmbeddedObjects;
+    "outlinelevel" => ParagraphFormatting, Full, "0.1", "mapped to Markdown heading level";
+    "page" => DocumentStructure, Full, "0.1", "page boundary, honored by page ranges";
+    "pagebb" => ParagraphFormatting, Full, "0.1", "page break before, surfaced as spacing comments";
+    "par" => DocumentStructure, Full, "0.1", "paragraph boundary";
+    "pard" => ParagraphFormatting, Full, "0.1", "resets paragraph properties";
+    "pict" => Pictures, Ignored, "0.1", "embedded image is dropped", degrades Pictures;
+    "plain" => CharacterFormatting, Full, "0.1", "resets character formatting";
+    "qc" => ParagraphFormatting, Partial, "0.1", "centering kept on table cells only";
+    "ql" => ParagraphFormatting, Partial, "0.1", "left alignment kept on table cells only";
+    "qr" => ParagraphFormatting, Partial, "0.1", "right alignment kept on table cells only";
+    "rdblquote" => SpecialCharacters, Full, "0.1", "emitted as U+201D";
+    "revauth" => Revisions, Full, "0.1", "revision author resolved from \\revtbl";
+    "revauthdel" => Revisions, Full, "0.1", "deletion author resolved from \\revtbl";
+    "revbar" => Revisions, Ignored, "0.1", "change bar presentation is dropped", degrades RevisionMarks;
+    "revdttm" => Revisions, Full, "0.1", "revision timestamp decoded";
+    "revdttmdel" => Revisions, Full, "0.1", "deletion timestamp decoded";
+    "revised" => Revisions, Full, "0.1", "inserted runs rendered per the revision mode";
+    "revisions" => Revisions, Ignored, "0.1", "track-changes flag is dropped", degrades RevisionMarks;
+    "revprop" => Revisions, Ignored, "0.1", "revision display property is dropped", degrades RevisionMarks;
+    "revtbl" => HeaderTables, Full, "0.1", "reviewer names resolved for \\revauthN";
+    "row" => Tables, Full, "0.1", "ends the current table row";
+    "rquote" => SpecialCharacters, Full, "0.1", "emitted as U+2019";
+    "rtlch" => CharacterFormatting, Full, "0.1", "right-to-left run direction";
+    "rtlpar" => ParagraphFormatting, Full, "0.1", "right-to-left paragraph direction";
+    "sa" => ParagraphFormatting, Full, "0.1", "space after, surfaced as spacing comments";
+    "sb" => ParagraphFormatting, Full, "0.1", "space before, surfaced as spacing comments";
+    "sect" => DocumentStructure, Full, "0.1", "section boundary, treated as a page boundary";
+    "shp" => DrawingObjects, Ignored, "0.1", "shape is dropped", degrades DrawingObjects;
+    "shpinst" => DrawingObjects, Ignored, "0.1", "shape instance is dropped", degrades DrawingObjects;
+    "shppict" => Pictures, Ignored, "0.1", "shape-hosted image is dropped", degrades Pictures;
+    "sl" => ParagraphFormatting, Full, "0.1", "line spacing, surfaced as spacing comments";
+    "slmult" => ParagraphFormatting, Full, "0.1", "marks \\sl as a multiple";
+    "strike" => CharacterFormatting, Full, "0.1", "strikethrough on/off";
+    "stylesheet" => HeaderTables, Full, "0.1", "character styles parsed for \\csN lookups";
+    "subject" => Metadata, Full, "0.1", "captured into document metadata";
+    "tab" => SpecialCharacters, Full, "0.1", "emitted as a tab character";
+    "themedata" => HeaderTables, Ignored, "0.1", "theme payload is dropped";
+    "title" => Metadata, Full, "0.1", "captured into document metadata";
+    "trowd" => Tables, Full, "0.1", "starts a table row definition";
+    "u" => SpecialCharacters, Full, "0.1", "Unicode code point emitted, fallback swallowed";
+    "ul" => CharacterFormatting, Full, "0.1", "underline on/off";
+    "ulnone" => CharacterFormatting, Full, "0.1", "underline off";
+];
+
+/// Look up the support entry for a control word (without the backslash).
+/// `None` means the word is not recognized: it is skipped by the parser's
+/// unknown-word rule and does not appear in degradation reports.
+pub fn control_word_info(name: &str) -> Option<ControlWordInfo> {
+    TABLE
+        .binary_search_by_key(&name, |info| info.name)
+        .ok()
+        .map(|i| TABLE[i])
+}
+
+/// The full support table, sorted by control word.
+pub fn control_word_table() -> &'static [ControlWordInfo] {
+    TABLE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::rtf_parser::HANDLED_CONTROL_WORDS;
+    use super::*;
+
+    #[test]
+    fn table_is_sorted_for_binary_search() {
+        assert!(TABLE.windows(2).all(|w| w[0].name < w[1].name));
+    }
+
+    #[test]
+    fn every_parser_handled_word_is_documented_as_converted() {
+        for word in HANDLED_CONTROL_WORDS {
+            let info = control_word_info(word)
+                .unwrap_or_else(|| panic!("\\{word} is parser-handled but not in the table"));
+            assert!(
+                matches!(
+                    info.support_level,
+                    SupportLevel::Full | SupportLevel::Partial
+                ),
+                "\\{word} is parser-handled but documented as {:?}",
+                info.support_level
+            );
+        }
+    }
+
+    #[test]
+    fn lookups_hit_and_miss_as_expected() {
+        let info = control_word_info("pict").unwrap();
+        assert_eq!(info.support_level, SupportLevel::Ignored);
+        assert_eq!(info.category, ControlWordCategory::Pictures);
+        assert!(control_word_info("nosuchword").is_none());
+    }
+
+    #[test]
+    fn entries_serialize_with_snake_case_levels() {
+        let json = serde_json::to_value(control_word_info("formfield").unwrap()).unwrap();
+        assert_eq!(json["support_level"], "partial");
+        assert_eq!(json["category"], "form_fields");
+        assert_eq!(json["since_version"], "0.1");
+    }
+}
@@ -0,0 +1,294 @@
+//! Intra-document cross-reference resolution.
+//!
+//! Legacy manuals say "see Section 4.2" with a `{\*\bkmkstart}` bookmark
+//! on the target and a `REF`/`PAGEREF` field on the reference. Markdown
+//! has no bookmarks, but every heading already gets a slug anchor, so
+//! under the parser's opt-in cross-reference mode a bookmark stands for
+//! its nearest enclosing heading and each reference becomes an
+//! intra-document link to that heading's slug. `PAGEREF` resolves the
+//! same way: Markdown has no page numbers, and the link is what the
+//! page reference was for.
+//!
+//! Slugs are computed here with the default [`Slugger`] flavor, so the
+//! resolved links match what [`MarkdownGenerator`] emits unless it is
+//! configured for ASCII transliteration.
+//!
+//! [`MarkdownGenerator`]: super::markdown_generator::MarkdownGenerator
+
+use super::markdown_generator::{heading_text, Slugger};
+use super::rtf_parser::{RtfDocument, RtfNode};
+use std::collections::{HashMap, HashSet};
+
+/// Field instruction types recognized as cross-references. This is the
+/// whole allowlist: any other instruction keeps the existing behavior
+/// (its cached `\fldrslt` text stays in the flow), and even these two are
+/// only consulted when cross-reference mode is on.
+pub(crate) const CROSS_REFERENCE_FIELDS: &[&str] = &["REF", "PAGEREF"];
+
+/// The bookmark a field instruction targets, or `None` when the
+/// instruction is not an allowlisted cross-reference. Word quotes names
+/// containing spaces; switches such as `\h` follow the name.
+pub(crate) fn reference_target(instruction: &str) -> Option<String> {
+    let instruction = instruction.trim_start();
+    let keyword = instruction.split_whitespace().next()?;
+    if !CROSS_REFERENCE_FIELDS.contains(&keyword) {
+        return None;
+    }
+    let rest = instruction[keyword.len()..].trim_start();
+    let name = match rest.strip_prefix('"') {
+        Some(quoted) => quoted.split('"').next()?,
+        None => rest.split_whitespace().next()?,
+    };
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Resolve the `#bkmk:` sentinel links the parser left for `REF`/
+/// `PAGEREF` fields into `#slug` links on the nearest enclosing heading.
+///
+/// `bookmarks` pairs each bookmark name with the index of the top-level
+/// block it sits in (the parser records the block count at the
+/// `\*\bkmkstart`): a bookmark inside a heading targets that heading,
+/// one in body text targets the closest heading above it. References to
+/// unknown bookmarks, or to bookmarks with no heading to land on, are
+/// unwrapped to their result text with a warning.
+pub(crate) fn resolve(
+    document: &mut RtfDocument,
+    bookmarks: &[(String, usize)],
+    warnings: &mut Vec<String>,
+) {
+    let mut slugger = Slugger::new(false);
+    let headings: Vec<(usize, String)> = document
+        .content
+        .iter()
+        .enumerate()
+        .filter_map(|(index, node)| match node {
+            RtfNode::Heading { content, .. } => {
+                Some((index, slugger.slug(&heading_text(content))))
+            }
+            _ => None,
+        })
+        .collect();
+    let mut anchors: HashMap<&str, Option<&str>> = HashMap::new();
+    for (name, block) in bookmarks {
+        let slug = headings
+            .iter()
+            .take_while(|(index, _)| index <= block)
+            .last()
+            .map(|(_, slug)| slug.as_str());
+        // Word requires unique bookmark names; on duplicates the first
+        // definition wins, like Word's own Go To.
+        anchors.entry(name).or_insert(slug);
+    }
+
+    let mut work: Vec<&mut Vec<RtfNode>> = vec![&mut document.content];
+    while let Some(content) = work.pop() {
+        rewrite_references(content, &anchors, warnings);
+        for node in content.iter_mut() {
+            match node {
+                RtfNode::Formatted { content, .. }
+                | RtfNode::Hyperlink { content, .. }
+                | RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => work.push(content),
+                RtfNode::Table(table) => {
+                    for row in &mut table.rows {
+                        for cell in &mut row.cells {
+                            work.push(&mut cell.content);
+                        }
+                    }
+                }
+                RtfNode::Text(_)
+                | RtfNode::Image { .. }
+                | RtfNode::RawRtf { .. }
+                | RtfNode::LineBreak
+                | RtfNode::PageBreak => {}
+            }
+        }
+    }
+}
+
+/// Rewrite the `#bkmk:` hyperlinks among `content`'s immediate children:
+/// resolved ones get their `#slug` URL, unresolved ones are unwrapped to
+/// their result text with a warning.
+fn rewrite_references(
+    content: &mut Vec<RtfNode>,
+    anchors: &HashMap<&str, Option<&str>>,
+    warnings: &mut Vec<String>,
+) {
+    let mut i = 0;
+    while i < content.len() {
+        if let RtfNode::Hyperlink { url, content: text, .. } = &mut content[i] {
+            if let Some(name) = url.strip_prefix("#bkmk:") {
+                match anchors.get(name) {
+                    Some(Some(slug)) => *url = format!("#{slug}"),
+                    Some(None) => {
+                        warnings.push(format!(
+                            "cross-reference to bookmark '{name}' with no enclosing \
+                             heading; keeping its result text"
+                        ));
+                        let text = std::mem::take(text);
+                        content.splice(i..=i, text);
+                        continue;
+                    }
+                    None => {
+                        warnings.push(format!(
+                            "cross-reference to unknown bookmark '{name}'; keeping \
+                             its result text"
+                        ));
+                        let text = std::mem::take(text);
+                        content.splice(i..=i, text);
+                        continue;
+                    }
+                }
+            }
+        }
+        i += 1;
+    }
+}
+
+/// What the RTF generator's cross-reference mode plants where: one
+/// bookmark per heading whose slug is the target of an intra-document
+/// link, built per `generate` call so the plan tracks the document.
+#[derive(Default)]
+pub(crate) struct XrefPlan {
+    /// Per top-level heading, in order: the bookmark name to plant
+    /// (its slug), or `None` when nothing links to it.
+    marks: Vec<Option<String>>,
+    /// Heading slugs some intra-document link targets; only links into
+    /// this set become `REF` fields.
+    targets: HashSet<String>,
+    /// Headings emitted so far in the current `generate` call.
+    next_heading: usize,
+}
+
+impl XrefPlan {
+    pub(crate) fn build(document: &RtfDocument) -> Self {
+        let mut slugger = Slugger::new(false);
+        let slugs: Vec<String> = document
+            .content
+            .iter()
+            .filter_map(|node| match node {
+                RtfNode::Heading { content, .. } => Some(slugger.slug(&heading_text(content))),
+                _ => None,
+            })
+            .collect();
+        let mut linked = HashSet::new();
+        let mut work: Vec<&RtfNode> = document.content.iter().collect();
+        while let Some(node) = work.pop() {
+            match node {
+                RtfNode::Hyperlink { url, content, .. } => {
+                    if let Some(name) = url.strip_prefix('#') {
+                        linked.insert(name.to_string());
+                    }
+                    work.extend(content.iter());
+                }
+                RtfNode::Formatted { content, .. }
+                | RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => work.extend(content.iter()),
+                RtfNode::Table(table) => {
+                    for row in &table.rows {
+                        for cell in &row.cells {
+                            work.extend(cell.content.iter());
+                        }
+                    }
+                }
+                RtfNode::Text(_)
+                | RtfNode::Image { .. }
+                | RtfNode::RawRtf { .. }
+                | RtfNode::LineBreak
+                | RtfNode::PageBreak => {}
+            }
+        }
+        let targets: HashSet<String> = slugs
+            .iter()
+            .filter(|slug| linked.contains(*slug))
+            .cloned()
+            .collect();
+        let marks = slugs
+            .into_iter()
+            .map(|slug| targets.contains(&slug).then_some(slug))
+            .collect();
+        XrefPlan {
+            marks,
+            targets,
+            next_heading: 0,
+        }
+    }
+
+    /// The bookmark name for the next heading, advancing the count.
+    pub(crate) fn next_mark(&mut self) -> Option<String> {
+        let mark = self.marks.get(self.next_heading).cloned().flatten();
+        self.next_heading += 1;
+        mark
+    }
+
+    /// Whether an intra-document link target (the URL without its `#`)
+    /// has a bookmark planted for it.
+    pub(crate) fn is_target(&self, name: &str) -> bool {
+        self.targets.contains(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reference_target_recognizes_only_the_allowlisted_fields() {
+        assert_eq!(reference_target("REF sec1 \\h").as_deref(), Some("sec1"));
+        assert_eq!(
+            reference_target(" PAGEREF \"two words\" \\h").as_deref(),
+            Some("two words")
+        );
+        assert_eq!(reference_target("HYPERLINK \"https://x\""), None);
+        assert_eq!(reference_target("TOC \\o"), None);
+        assert_eq!(reference_target("REF"), None);
+    }
+
+    #[test]
+    fn bookmarks_resolve_to_the_nearest_enclosing_heading() {
+        let heading = |text: &str| RtfNode::Heading {
+            level: 1,
+            spacing: Default::default(),
+            content: vec![RtfNode::Text(text.to_string())],
+        };
+        let reference = |name: &str| RtfNode::Hyperlink {
+            url: format!("#bkmk:{name}"),
+            title: None,
+            content: vec![RtfNode::Text("see".to_string())],
+        };
+        let mut document = RtfDocument {
+            content: vec![
+                heading("Overview"),
+                heading("Details"),
+                RtfNode::Paragraph {
+                    direction: Default::default(),
+                    spacing: Default::default(),
+                    content: vec![reference("in_heading"), reference("in_body")],
+                },
+            ],
+            ..Default::default()
+        };
+        // One bookmark inside the first heading, one in the body under
+        // the second.
+        let bookmarks = vec![
+            ("in_heading".to_string(), 0),
+            ("in_body".to_string(), 2),
+        ];
+        let mut warnings = Vec::new();
+        resolve(&mut document, &bookmarks, &mut warnings);
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let RtfNode::Paragraph { content, .. } = &document.content[2] else {
+            panic!("paragraph");
+        };
+        let urls: Vec<&str> = content
+            .iter()
+            .map(|node| match node {
+                RtfNode::Hyperlink { url, .. } => url.as_str(),
+                _ => panic!("hyperlink"),
+            })
+            .collect();
+        assert_eq!(urls, ["#overview", "#details"]);
+    }
+}
@@ -0,0 +1,436 @@
+//! Character encoding of files read from and written to disk.
+//!
+//! On the way out: legacy Windows consumers expect CRLF line endings and
+//! sometimes a UTF-8 BOM; git-based consumers want LF and no BOM. The
+//! generators produce plain strings and stay encoding-agnostic; an
+//! [`OutputEncoding`] is applied at write time by [`safe_write`].
+//!
+//! On the way in: files from legacy systems are often not UTF-8.
+//! [`decode_input`] detects the encoding (BOM, then a UTF-8 validity
+//! check, then a windows-1252 fallback) and callers can force a specific
+//! [`InputEncoding`] when detection would guess wrong - "everything in
+//! this folder is cp866".
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+
+/// File stems Windows reserves for devices: a file named `CON.md` cannot
+/// be created and fails with the opaque "os error 123".
+const RESERVED_STEMS: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Make a generated file stem safe to create on Windows: trailing dots
+/// and spaces are stripped (Windows silently drops them, so the file the
+/// caller looks for later would not exist), and reserved device names
+/// get `reserved_suffix` appended. Pure string logic, applied on every
+/// platform so a batch run on Linux produces the same names the same run
+/// on Windows would.
+pub fn sanitize_file_stem(stem: &str, reserved_suffix: &str) -> String {
+    let trimmed = stem.trim_end_matches(['.', ' ']);
+    if trimmed.is_empty() {
+        return format!("unnamed{reserved_suffix}");
+    }
+    // Windows reserves the portion before the first dot, so `CON.backup`
+    // is just as unusable as `CON`.
+    let base = trimmed.split('.').next().unwrap_or(trimmed);
+    if RESERVED_STEMS.iter().any(|r| r.eq_ignore_ascii_case(base)) {
+        format!("{trimmed}{reserved_suffix}")
+    } else {
+        trimmed.to_string()
+    }
+}
+
+/// Rewrite `path` into a form Windows can actually open: absolute paths
+/// at or beyond the 260-character `MAX_PATH` limit get the `\\?\`
+/// extended-length prefix (`\\?\UNC\` for network shares). A no-op on
+/// other platforms and for paths that already fit or carry the prefix.
+pub fn writable_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        const MAX_PATH: usize = 260;
+        let raw = path.as_os_str();
+        if path.is_absolute() && raw.len() >= MAX_PATH {
+            let text = raw.to_string_lossy();
+            if let Some(share) = text.strip_prefix(r"\\").filter(|_| !text.starts_with(r"\\?\")) {
+                return PathBuf::from(format!(r"\\?\UNC\{share}"));
+            }
+            if !text.starts_with(r"\\?\") {
+                return PathBuf::from(format!(r"\\?\{text}"));
+            }
+        }
+    }
+    path.to_path_buf()
+}
+
+/// Line ending convention for written files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    /// CRLF on Windows, LF elsewhere.
+    #[default]
+    Platform,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Platform => {
+                if cfg!(windows) {
+                    "\r\n"
+                } else {
+                    "\n"
+                }
+            }
+        }
+    }
+}
+
+/// How a generated document is encoded when written to disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutputEncoding {
+    pub line_ending: LineEnding,
+    /// Prepend a UTF-8 byte order mark.
+    pub bom: bool,
+    /// Ensure the file ends with exactly one line ending (default off:
+    /// the content ends however the generator left it).
+    pub trailing_newline: bool,
+}
+
+impl OutputEncoding {
+    /// The encoding for written RTF files: always CRLF and never a BOM,
+    /// regardless of configuration. Readers tolerate both endings, but a
+    /// BOM breaks the `{\rtf1` header and mixed endings confuse
+    /// line-buffered legacy readers.
+    pub fn rtf() -> Self {
+        OutputEncoding {
+            line_ending: LineEnding::CrLf,
+            bom: false,
+            trailing_newline: false,
+        }
+    }
+
+    /// Encode `content` to the bytes to write: line breaks normalized to
+    /// the configured ending, optional trailing newline, optional BOM.
+    pub fn encode(&self, content: &str) -> Vec<u8> {
+        let ending = self.line_ending.as_str();
+        let mut text = String::with_capacity(content.len() + ending.len());
+        let mut chars = content.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\r' => {
+                    if chars.peek() == Some(&'\n') {
+                        chars.next();
+                    }
+                    text.push_str(ending);
+                }
+                '\n' => text.push_str(ending),
+                _ => text.push(c),
+            }
+        }
+        if self.trailing_newline && !text.ends_with(ending) {
+            text.push_str(ending);
+        }
+        let mut bytes = Vec::with_capacity(text.len() + BOM.len());
+        if self.bom {
+            bytes.extend_from_slice(BOM);
+        }
+        bytes.extend_from_slice(text.as_bytes());
+        bytes
+    }
+}
+
+/// Write `content` to `path` under the given encoding, going through a
+/// temporary file in the same directory and renaming it into place so a
+/// crash mid-write never leaves a truncated output file.
+pub fn safe_write(
+    path: impl AsRef<Path>,
+    content: &str,
+    encoding: &OutputEncoding,
+) -> std::io::Result<()> {
+    let path = writable_path(path.as_ref());
+    let tmp = staging_path(&path);
+    let mut file = std::fs::File::create(&tmp)?;
+    file.write_all(&encoding.encode(content))?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&tmp, path)
+}
+
+/// The `.tmp` sibling [`safe_write`] stages into before renaming.
+/// Exposed so incremental writers (the chunked file-transfer commands)
+/// can stream into the same staging file and finish with the same
+/// sync-then-rename, keeping the commit-or-nothing property.
+pub fn staging_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut tmp = writable_path(path.as_ref()).into_os_string();
+    tmp.push(".tmp");
+    PathBuf::from(tmp)
+}
+
+/// Canonical names of the encodings [`InputEncoding::from_name`]
+/// accepts, surfaced through the capabilities report so frontends can
+/// populate a picker and validate overrides up front.
+pub const SUPPORTED_INPUT_ENCODINGS: &[&str] = &["utf-8", "windows-1252", "cp866", "iso-8859-1"];
+
+/// Character encoding of an input file read from disk. Decoding a
+/// single-byte page never fails; every byte maps to some character, so
+/// forcing the wrong encoding yields mojibake rather than an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// UTF-8, tolerating (and stripping) a leading BOM; invalid
+    /// sequences become U+FFFD.
+    Utf8,
+    /// cp1252, the Windows western single-byte default.
+    Windows1252,
+    /// cp866, the DOS Cyrillic page still common in VFP9 shops.
+    Cp866,
+    /// ISO 8859-1: bytes map straight to the first 256 codepoints.
+    Latin1,
+}
+
+/// The 32 cp1252 codepoints that differ from Latin-1 (bytes 0x80-0x9F).
+/// The five unassigned bytes decode to their C1 control codepoint, as
+/// Windows itself does.
+const CP1252_C1: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// cp866 upper half (bytes 0x80-0xFF): the Cyrillic alphabet at
+/// 0x80-0xAF and 0xE0-0xEF, box drawing at 0xB0-0xDF, extras at the top.
+const CP866_HIGH: [char; 128] = [
+    'А', 'Б', 'В', 'Г', 'Д', 'Е', 'Ж', 'З', 'И', 'Й', 'К', 'Л', 'М', 'Н', 'О', 'П', // 0x80
+    'Р', 'С', 'Т', 'У', 'Ф', 'Х', 'Ц', 'Ч', 'Ш', 'Щ', 'Ъ', 'Ы', 'Ь', 'Э', 'Ю', 'Я', // 0x90
+    'а', 'б', 'в', 'г', 'д', 'е', 'ж', 'з', 'и', 'й', 'к', 'л', 'м', 'н', 'о', 'п', // 0xA0
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐', // 0xB0
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧', // 0xC0
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀', // 0xD0
+    'р', 'с', 'т', 'у', 'ф', 'х', 'ц', 'ч', 'ш', 'щ', 'ъ', 'ы', 'ь', 'э', 'ю', 'я', // 0xE0
+    'Ё', 'ё', 'Є', 'є', 'Ї', 'ї', 'Ў', 'ў', '°', '∙', '·', '√', '№', '¤', '■',
+    '\u{A0}', // 0xF0
+];
+
+impl InputEncoding {
+    /// Look up an encoding by name, accepting the aliases legacy tooling
+    /// uses (`cp1252`, `ibm866`, `latin1`, ...). `None` means the name
+    /// is unsupported; see [`SUPPORTED_INPUT_ENCODINGS`].
+    pub fn from_name(name: &str) -> Option<InputEncoding> {
+        match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+            "utf-8" | "utf8" => Some(InputEncoding::Utf8),
+            "windows-1252" | "cp1252" | "ansi" => Some(InputEncoding::Windows1252),
+            "cp866" | "ibm866" | "dos-866" | "866" => Some(InputEncoding::Cp866),
+            "iso-8859-1" | "latin1" | "latin-1" => Some(InputEncoding::Latin1),
+            _ => None,
+        }
+    }
+
+    /// The canonical name, as listed in [`SUPPORTED_INPUT_ENCODINGS`].
+    pub fn name(self) -> &'static str {
+        match self {
+            InputEncoding::Utf8 => "utf-8",
+            InputEncoding::Windows1252 => "windows-1252",
+            InputEncoding::Cp866 => "cp866",
+            InputEncoding::Latin1 => "iso-8859-1",
+        }
+    }
+
+    /// Decode file bytes under this encoding.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            InputEncoding::Utf8 => {
+                let bytes = bytes.strip_prefix(BOM).unwrap_or(bytes);
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+            InputEncoding::Windows1252 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => CP1252_C1[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect(),
+            InputEncoding::Cp866 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0xFF => CP866_HIGH[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect(),
+            InputEncoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        }
+    }
+}
+
+/// Decode input-file bytes into text. A forced encoding wins; otherwise
+/// a UTF-8 BOM decides, then anything that validates as UTF-8 is UTF-8,
+/// and the remainder falls back to windows-1252 (the single-byte default
+/// of the legacy systems this tool serves). Returns the text together
+/// with the encoding actually used, for per-file reporting.
+pub fn decode_input(bytes: &[u8], forced: Option<InputEncoding>) -> (String, InputEncoding) {
+    let encoding = forced.unwrap_or_else(|| {
+        if bytes.starts_with(BOM) || std::str::from_utf8(bytes).is_ok() {
+            InputEncoding::Utf8
+        } else {
+            InputEncoding::Windows1252
+        }
+    });
+    (encoding.decode(bytes), encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lf_without_bom_matches_git_expectations() {
+        let encoding = OutputEncoding {
+            line_ending: LineEnding::Lf,
+            bom: false,
+            trailing_newline: false,
+        };
+        assert_eq!(encoding.encode("a\r\nb\rc\nd"), b"a\nb\nc\nd");
+    }
+
+    #[test]
+    fn crlf_with_bom_matches_legacy_expectations() {
+        let encoding = OutputEncoding {
+            line_ending: LineEnding::CrLf,
+            bom: true,
+            trailing_newline: true,
+        };
+        assert_eq!(encoding.encode("a\nb"), b"\xEF\xBB\xBFa\r\nb\r\n");
+    }
+
+    #[test]
+    fn trailing_newline_is_not_duplicated() {
+        let encoding = OutputEncoding {
+            line_ending: LineEnding::Lf,
+            bom: false,
+            trailing_newline: true,
+        };
+        assert_eq!(encoding.encode("a\n"), b"a\n");
+    }
+
+    #[test]
+    fn rtf_encoding_is_crlf_without_bom() {
+        let bytes = OutputEncoding::rtf().encode("{\\rtf1 line\nbreak}");
+        assert_eq!(bytes, b"{\\rtf1 line\r\nbreak}");
+    }
+
+    #[test]
+    fn reserved_stems_get_the_configured_suffix() {
+        assert_eq!(sanitize_file_stem("CON", "_file"), "CON_file");
+        assert_eq!(sanitize_file_stem("con", "_file"), "con_file");
+        assert_eq!(sanitize_file_stem("COM7", "_file"), "COM7_file");
+        assert_eq!(sanitize_file_stem("CON.backup", "_file"), "CON.backup_file");
+        // Not reserved: prefix match only counts up to the first dot.
+        assert_eq!(sanitize_file_stem("CONTRACT", "_file"), "CONTRACT");
+        assert_eq!(sanitize_file_stem("COM10", "_file"), "COM10");
+    }
+
+    #[test]
+    fn trailing_dots_and_spaces_are_stripped() {
+        assert_eq!(sanitize_file_stem("notes. ", "_file"), "notes");
+        assert_eq!(sanitize_file_stem("report...", "_file"), "report");
+        assert_eq!(sanitize_file_stem("...", "_file"), "unnamed_file");
+        assert_eq!(sanitize_file_stem("plain", "_file"), "plain");
+    }
+
+    #[cfg(not(windows))]
+    #[test]
+    fn writable_path_is_identity_off_windows() {
+        let long = Path::new("/tmp").join("x".repeat(300));
+        assert_eq!(writable_path(&long), long);
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_paths_get_the_extended_length_prefix() {
+        let dir = std::env::temp_dir().join(format!("lb-longpath-{}", std::process::id()));
+        let deep = dir.join("d".repeat(200)).join("e".repeat(100));
+        std::fs::create_dir_all(writable_path(&deep)).unwrap();
+        let target = deep.join("out.md");
+        assert!(target.as_os_str().len() > 260);
+        assert!(writable_path(&target).starts_with(r"\\?\"));
+        safe_write(&target, "long path", &OutputEncoding::default()).unwrap();
+        assert_eq!(
+            std::fs::read(writable_path(&target)).unwrap(),
+            b"long path"
+        );
+        std::fs::remove_dir_all(writable_path(&dir)).unwrap();
+    }
+
+    #[test]
+    fn forced_cp866_decodes_what_the_fallback_garbles() {
+        // "Привет" in cp866.
+        let bytes = [0x8F, 0xE0, 0xA8, 0xA2, 0xA5, 0xE2];
+        let (forced, used) = decode_input(&bytes, Some(InputEncoding::Cp866));
+        assert_eq!(forced, "Привет");
+        assert_eq!(used.name(), "cp866");
+        // Without the override the bytes are not valid UTF-8, so the
+        // windows-1252 fallback produces mojibake.
+        let (detected, used) = decode_input(&bytes, None);
+        assert_eq!(used, InputEncoding::Windows1252);
+        assert_ne!(detected, "Привет");
+    }
+
+    #[test]
+    fn detection_prefers_bom_then_utf8_then_cp1252() {
+        let (text, used) = decode_input("\u{FEFF}héllo".as_bytes(), None);
+        assert_eq!((text.as_str(), used), ("héllo", InputEncoding::Utf8));
+        let (text, used) = decode_input("héllo".as_bytes(), None);
+        assert_eq!((text.as_str(), used), ("héllo", InputEncoding::Utf8));
+        // 0x93/0x94 are cp1252 curly quotes and invalid UTF-8.
+        let (text, used) = decode_input(b"\x93quoted\x94", None);
+        assert_eq!(
+            (text.as_str(), used),
+            ("\u{201C}quoted\u{201D}", InputEncoding::Windows1252)
+        );
+    }
+
+    #[test]
+    fn encoding_names_and_aliases_resolve() {
+        for name in SUPPORTED_INPUT_ENCODINGS {
+            let encoding = InputEncoding::from_name(name).unwrap();
+            assert_eq!(encoding.name(), *name);
+        }
+        assert_eq!(
+            InputEncoding::from_name("CP1252"),
+            Some(InputEncoding::Windows1252)
+        );
+        assert_eq!(InputEncoding::from_name("IBM866"), Some(InputEncoding::Cp866));
+        assert_eq!(InputEncoding::from_name("latin_1"), Some(InputEncoding::Latin1));
+        assert_eq!(InputEncoding::from_name("ebcdic"), None);
+    }
+
+    #[test]
+    fn safe_write_round_trips_exact_bytes() {
+        let dir = std::env::temp_dir();
+        let cases = [
+            (LineEnding::Lf, false, b"one\ntwo".to_vec()),
+            (LineEnding::CrLf, false, b"one\r\ntwo".to_vec()),
+            (LineEnding::Lf, true, b"\xEF\xBB\xBFone\ntwo".to_vec()),
+            (LineEnding::CrLf, true, b"\xEF\xBB\xBFone\r\ntwo".to_vec()),
+        ];
+        for (i, (line_ending, bom, expected)) in cases.into_iter().enumerate() {
+            let path = dir.join(format!("lb-encoding-{}-{i}.md", std::process::id()));
+            let encoding = OutputEncoding {
+                line_ending,
+                bom,
+                trailing_newline: false,
+            };
+            safe_write(&path, "one\ntwo", &encoding).unwrap();
+            assert_eq!(std::fs::read(&path).unwrap(), expected);
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,227 @@
+//! Shared, reloadable conversion state for long-running hosts.
+//!
+//! A one-shot CLI run can afford to build its template registry per
+//! conversion; a desktop app or DLL host converting thousands of memo
+//! fields cannot, and it also wants template edits on disk to take
+//! effect without a restart. A [`ConversionEnvironment`] owns the
+//! [`TemplateSystem`] at application level: each conversion takes a
+//! cheap snapshot (an [`Arc`] clone), and
+//! [`reload_templates`](ConversionEnvironment::reload_templates) builds
+//! a whole new registry off to the side and swaps it in atomically - a
+//! conversion in flight keeps the set it started with, so a reload can
+//! never tear it between old and new templates. Inject one via
+//! [`DocumentPipeline::with_environment`](super::pipeline::DocumentPipeline::with_environment);
+//! pipelines without one fall back to the per-thread cache in
+//! [`thread_state`](super::thread_state).
+
+use super::template::TemplateSystem;
+use crate::sync::{read_unpoisoned, write_unpoisoned};
+use std::sync::{Arc, RwLock};
+
+/// Application-level home of shared conversion state, held by the host
+/// in an [`Arc`] and handed to every pipeline it builds.
+pub struct ConversionEnvironment {
+    /// The current template set. Readers clone the inner [`Arc`]; reload
+    /// replaces it whole, so no reader ever observes a half-built set.
+    templates: RwLock<Arc<TemplateSystem>>,
+    /// Directory reloads re-read on top of the built-ins; `None` reloads
+    /// the built-ins alone.
+    template_directory: Option<String>,
+}
+
+impl ConversionEnvironment {
+    /// An environment serving the built-in templates only.
+    pub fn new() -> Self {
+        ConversionEnvironment {
+            templates: RwLock::new(Arc::new(TemplateSystem::new())),
+            template_directory: None,
+        }
+    }
+
+    /// An environment loading user templates from `dir` on top of the
+    /// built-ins, both now and on every
+    /// [`reload_templates`](Self::reload_templates).
+    pub fn with_template_directory(dir: impl Into<String>) -> Result<Self, String> {
+        let dir = dir.into();
+        let mut system = TemplateSystem::new();
+        system.load_directory(&dir)?;
+        Ok(ConversionEnvironment {
+            templates: RwLock::new(Arc::new(system)),
+            template_directory: Some(dir),
+        })
+    }
+
+    /// Snapshot of the current template set: two atomic operations, so
+    /// taking one per conversion costs nothing, and the snapshot is
+    /// immune to concurrent reloads for as long as the caller holds it.
+    pub fn templates(&self) -> Arc<TemplateSystem> {
+        read_unpoisoned(&self.templates).clone()
+    }
+
+    /// Rebuild the template set - built-ins plus the configured
+    /// directory - and swap it in atomically, returning the names loaded
+    /// from the directory. Conversions started before the swap finish on
+    /// their snapshot; ones started after see the new set. On error
+    /// nothing is swapped and the old set stays live.
+    pub fn reload_templates(&self) -> Result<Vec<String>, String> {
+        let mut system = TemplateSystem::new();
+        let names = match &self.template_directory {
+            Some(dir) => system.load_directory(dir)?,
+            None => Vec::new(),
+        };
+        *write_unpoisoned(&self.templates) = Arc::new(system);
+        Ok(names)
+    }
+
+    /// Swap in an externally assembled template set, for hosts that
+    /// register templates programmatically instead of loading a
+    /// directory. The same atomicity as a reload: snapshots already
+    /// taken are unaffected.
+    pub fn install_templates(&self, system: TemplateSystem) {
+        *write_unpoisoned(&self.templates) = Arc::new(system);
+    }
+}
+
+impl Default for ConversionEnvironment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::pipeline::{DocumentPipeline, PipelineConfig};
+    use crate::conversion::template::{
+        ReplacePattern, Template, TemplateType, Transformation,
+    };
+
+    /// A template set where both probes carry the same `version`, so a
+    /// torn read (one probe old, one new) is detectable.
+    fn probe_set(version: &str) -> TemplateSystem {
+        let mut system = TemplateSystem::new();
+        for name in ["probe-left", "probe-right"] {
+            let mut template = Template::new(name, TemplateType::Manual);
+            template
+                .transformations
+                .push(Transformation::ReplacePattern(ReplacePattern {
+                    find: "MARKER".to_string(),
+                    replace: format!("version {version}"),
+                    regex: false,
+                }));
+            system.register(template).unwrap();
+        }
+        system
+    }
+
+    /// What `probe_set` version a template in `system` stamps, from its
+    /// replacement text.
+    fn version_of(system: &TemplateSystem, name: &str) -> String {
+        let template = system.get(name).expect("probe template registered");
+        let Transformation::ReplacePattern(pattern) = &template.transformations[0] else {
+            panic!("probe template carries a replace pattern");
+        };
+        pattern.replace.clone()
+    }
+
+    #[test]
+    fn snapshots_survive_a_swap() {
+        let environment = ConversionEnvironment::new();
+        let before = environment.templates();
+        environment.install_templates(probe_set("A"));
+        assert!(before.get("probe-left").is_none());
+        assert!(environment.templates().get("probe-left").is_some());
+        // Built-ins are part of every rebuilt set.
+        assert!(environment.templates().get("invoice").is_some());
+    }
+
+    #[test]
+    fn reload_rereads_the_template_directory() {
+        let dir = std::env::temp_dir().join(format!("lb-environment-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("greeting.json"),
+            r#"{"name": "greeting", "header": "HELLO"}"#,
+        )
+        .unwrap();
+        let environment =
+            ConversionEnvironment::with_template_directory(dir.to_string_lossy().to_string())
+                .unwrap();
+        assert!(environment.templates().get("greeting").is_some());
+
+        std::fs::write(
+            dir.join("farewell.json"),
+            r#"{"name": "farewell", "header": "GOODBYE"}"#,
+        )
+        .unwrap();
+        let names = environment.reload_templates().unwrap();
+        assert_eq!(names, ["farewell", "greeting"]);
+        assert!(environment.templates().get("farewell").is_some());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn reload_failure_keeps_the_old_set_live() {
+        let dir = std::env::temp_dir().join(format!("lb-environment-bad-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ok.json"), r#"{"name": "ok"}"#).unwrap();
+        let environment =
+            ConversionEnvironment::with_template_directory(dir.to_string_lossy().to_string())
+                .unwrap();
+        std::fs::write(dir.join("broken.json"), "not json").unwrap();
+        assert!(environment.reload_templates().is_err());
+        assert!(environment.templates().get("ok").is_some());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn concurrent_reloads_never_tear_a_snapshot() {
+        let environment = Arc::new(ConversionEnvironment::new());
+        environment.install_templates(probe_set("A"));
+        let config = PipelineConfig {
+            template: Some("probe-left".to_string()),
+            ..Default::default()
+        };
+        let done = std::sync::atomic::AtomicBool::new(false);
+        let done = &done;
+        std::thread::scope(|scope| {
+            {
+                let environment = Arc::clone(&environment);
+                scope.spawn(move || {
+                    for round in 0..200 {
+                        let version = if round % 2 == 0 { "B" } else { "A" };
+                        environment.install_templates(probe_set(version));
+                    }
+                    done.store(true, std::sync::atomic::Ordering::Release);
+                });
+            }
+            for _ in 0..2 {
+                let environment = Arc::clone(&environment);
+                let config = config.clone();
+                scope.spawn(move || {
+                    while !done.load(std::sync::atomic::Ordering::Acquire) {
+                        // Both probes must come from the same set.
+                        let snapshot = environment.templates();
+                        assert_eq!(
+                            version_of(&snapshot, "probe-left"),
+                            version_of(&snapshot, "probe-right"),
+                            "snapshot mixed two template sets"
+                        );
+                        // A conversion through the environment lands on
+                        // one set or the other, never neither.
+                        let output = DocumentPipeline::new(config.clone())
+                            .with_environment(Arc::clone(&environment))
+                            .process("{\\rtf1 MARKER\\par}")
+                            .unwrap();
+                        assert!(
+                            output.markdown.contains("version A")
+                                || output.markdown.contains("version B"),
+                            "{}",
+                            output.markdown
+                        );
+                    }
+                });
+            }
+        });
+    }
+}
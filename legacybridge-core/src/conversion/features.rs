@@ -0,0 +1,203 @@
+//! Degradation reporting for RTF constructs we cannot fully convert.
+//!
+//! Legacy documents lean on features - drawing objects, embedded OLE
+//! objects, revision marks - that have no Markdown equivalent. Rather than
+//! failing or silently dropping them, the parser tallies every occurrence
+//! into a [`FeatureUsage`] map so callers can answer "what does this
+//! document use that won't survive conversion?" before committing to a
+//! migration.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Category of an unsupported RTF construct.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureCategory {
+    /// Comment presentation machinery the converter does not resolve
+    /// (`\atnicn`, ...); `\annotation` bodies themselves are extracted
+    /// and rendered per the annotation mode.
+    Annotations,
+    /// Vector drawing objects and shapes (`\do`, `\shp`, ...).
+    DrawingObjects,
+    /// Embedded OLE objects (`\object`, `\objdata`, ...).
+    EmbeddedObjects,
+    /// Math zones (`\mmath*`).
+    Equations,
+    /// Field codes - hyperlinks, page numbers, cross references.
+    Fields,
+    /// Interactive form fields (`\formfield`, ...).
+    FormFields,
+    /// Embedded images (`\pict`, ...).
+    Pictures,
+    /// Track-changes machinery the converter does not resolve
+    /// (`\revisions`, `\revbar`, ...); the `\revised`/`\deleted` runs
+    /// themselves are handled by the revision mode.
+    RevisionMarks,
+}
+
+impl FeatureCategory {
+    /// How losing this category shows up in converted output.
+    pub fn severity(self) -> FeatureSeverity {
+        match self {
+            // The surrounding text converts; only the markup is dropped.
+            FeatureCategory::Annotations | FeatureCategory::RevisionMarks => {
+                FeatureSeverity::Ignorable
+            }
+            // Visible content is lost or flattened to its cached text.
+            FeatureCategory::DrawingObjects
+            | FeatureCategory::Equations
+            | FeatureCategory::Fields
+            | FeatureCategory::FormFields
+            | FeatureCategory::Pictures => FeatureSeverity::Lossy,
+            // Embedded payloads cannot be carried over at all, and the
+            // security validator rejects object data outright.
+            FeatureCategory::EmbeddedObjects => FeatureSeverity::Blocking,
+        }
+    }
+}
+
+/// Severity of losing a construct, ordered least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeatureSeverity {
+    /// Dropped without affecting the converted text.
+    Ignorable,
+    /// Converted output loses visible content or behavior.
+    Lossy,
+    /// The construct prevents a faithful conversion entirely.
+    Blocking,
+}
+
+/// Classification of known-unsupported control words, read from the
+/// support table in [`super::control_words`] so the degradation report
+/// and the documented support levels cannot drift apart.
+fn classify(name: &str) -> Option<FeatureCategory> {
+    super::control_words::control_word_info(name).and_then(|info| info.feature)
+}
+
+/// Usage of one unsupported control word.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureUse {
+    pub count: usize,
+    pub category: FeatureCategory,
+    pub severity: FeatureSeverity,
+}
+
+/// Unsupported constructs encountered in one document, keyed by control
+/// word. Serializes as a plain JSON object so reports stay greppable.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FeatureUsage {
+    features: BTreeMap<String, FeatureUse>,
+}
+
+impl FeatureUsage {
+    /// Tally one occurrence of `name` if the classification table knows it;
+    /// unclassified control words are not usage worth reporting.
+    pub(crate) fn record(&mut self, name: &str) {
+        let Some(category) = classify(name) else {
+            return;
+        };
+        self.features
+            .entry(name.to_string())
+            .and_modify(|usage| usage.count += 1)
+            .or_insert(FeatureUse {
+                count: 1,
+                category,
+                severity: category.severity(),
+            });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.features.is_empty()
+    }
+
+    pub fn get(&self, control_word: &str) -> Option<&FeatureUse> {
+        self.features.get(control_word)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FeatureUse)> {
+        self.features.iter().map(|(word, usage)| (word.as_str(), usage))
+    }
+
+    /// Total occurrences per category, for contract-scoping summaries.
+    pub fn category_counts(&self) -> BTreeMap<FeatureCategory, usize> {
+        let mut counts = BTreeMap::new();
+        for usage in self.features.values() {
+            *counts.entry(usage.category).or_insert(0) += usage.count;
+        }
+        counts
+    }
+
+    /// The most severe loss in the document, if any construct was recorded.
+    pub fn worst_severity(&self) -> Option<FeatureSeverity> {
+        self.features.values().map(|usage| usage.severity).max()
+    }
+
+    /// Fold another document's usage into this one, for batch manifests.
+    pub fn merge(&mut self, other: &FeatureUsage) {
+        for (word, usage) in &other.features {
+            self.features
+                .entry(word.clone())
+                .and_modify(|existing| existing.count += usage.count)
+                .or_insert_with(|| usage.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_classified_control_words_are_recorded() {
+        let mut usage = FeatureUsage::default();
+        usage.record("do");
+        usage.record("do");
+        usage.record("b"); // supported, not a degradation
+        usage.record("nosuchword");
+        assert_eq!(usage.get("do").unwrap().count, 2);
+        assert_eq!(
+            usage.get("do").unwrap().category,
+            FeatureCategory::DrawingObjects
+        );
+        assert!(usage.get("b").is_none());
+        assert!(usage.get("nosuchword").is_none());
+    }
+
+    #[test]
+    fn worst_severity_orders_blocking_over_lossy_over_ignorable() {
+        let mut usage = FeatureUsage::default();
+        assert_eq!(usage.worst_severity(), None);
+        usage.record("atnicn");
+        assert_eq!(usage.worst_severity(), Some(FeatureSeverity::Ignorable));
+        usage.record("pict");
+        assert_eq!(usage.worst_severity(), Some(FeatureSeverity::Lossy));
+        usage.record("objdata");
+        assert_eq!(usage.worst_severity(), Some(FeatureSeverity::Blocking));
+    }
+
+    #[test]
+    fn merge_sums_counts_across_documents() {
+        let mut a = FeatureUsage::default();
+        a.record("do");
+        let mut b = FeatureUsage::default();
+        b.record("do");
+        b.record("atnicn");
+        a.merge(&b);
+        assert_eq!(a.get("do").unwrap().count, 2);
+        assert_eq!(a.get("atnicn").unwrap().count, 1);
+    }
+
+    #[test]
+    fn serializes_as_a_plain_object() {
+        let mut usage = FeatureUsage::default();
+        usage.record("revisions");
+        let json = serde_json::to_string(&usage).unwrap();
+        assert_eq!(
+            json,
+            "{\"revisions\":{\"count\":1,\"category\":\"revision_marks\",\"severity\":\"ignorable\"}}"
+        );
+    }
+}
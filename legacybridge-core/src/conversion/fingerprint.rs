@@ -0,0 +1,206 @@
+//! Stable fingerprinting of conversion options.
+//!
+//! Caching, incremental batch skipping and history re-runs all need to
+//! decide whether two conversions used "the same options", and they need
+//! that answer to hold across releases - hashing a struct's in-memory
+//! layout or its default serde output breaks as soon as a field is
+//! added. An [`OptionsFingerprint`] is SHA-256 over a canonical JSON
+//! form instead: a schema version, sorted keys, and only the fields
+//! that differ from their defaults. Leaving default-valued fields out
+//! is what keeps the scheme extensible - a field added in a later
+//! release serializes to nothing while it holds its default, so
+//! fingerprints recorded before the field existed stay valid.
+//!
+//! The invariants, enumerated by the tests:
+//!
+//! - changing any semantically relevant option changes the fingerprint;
+//! - a new field at its default does not (it is simply absent);
+//! - the encoding itself is guarded by a golden fingerprint, so
+//!   accidental format drift fails a test instead of silently
+//!   invalidating every recorded key.
+
+use super::pipeline::PipelineConfig;
+use crate::security::SecurityLimits;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+/// Version of the canonical encoding; bump only when the format itself
+/// changes (which invalidates all recorded fingerprints by design).
+pub const FINGERPRINT_SCHEMA_VERSION: u32 = 1;
+
+/// SHA-256 of the canonical serialization of a conversion's options,
+/// as lowercase hex. Equal fingerprints mean the conversions ran with
+/// semantically identical settings.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OptionsFingerprint(String);
+
+impl OptionsFingerprint {
+    pub fn as_hex(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for OptionsFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PipelineConfig {
+    /// Fingerprint this configuration together with the security limits
+    /// it runs under; see the [module docs](self) for the guarantees.
+    pub fn fingerprint(&self, limits: &SecurityLimits) -> OptionsFingerprint {
+        let canonical = canonical_json(self, limits);
+        let digest = Sha256::digest(canonical.as_bytes());
+        let mut hex = String::with_capacity(64);
+        for byte in digest {
+            hex.push_str(&format!("{byte:02x}"));
+        }
+        OptionsFingerprint(hex)
+    }
+}
+
+/// The canonical form: `{"limits":{...},"options":{...},"schema":1}`
+/// with keys sorted (serde_json's map is ordered) and default-valued
+/// fields dropped.
+fn canonical_json(config: &PipelineConfig, limits: &SecurityLimits) -> String {
+    let mut root = Map::new();
+    root.insert(
+        "limits".to_string(),
+        without_defaults(
+            serde_json::to_value(limits).expect("limits serialize"),
+            serde_json::to_value(SecurityLimits::default()).expect("limits serialize"),
+        ),
+    );
+    root.insert(
+        "options".to_string(),
+        without_defaults(
+            serde_json::to_value(config).expect("config serialize"),
+            serde_json::to_value(PipelineConfig::default()).expect("config serialize"),
+        ),
+    );
+    root.insert(
+        "schema".to_string(),
+        Value::from(FINGERPRINT_SCHEMA_VERSION),
+    );
+    Value::Object(root).to_string()
+}
+
+/// Drop top-level fields whose serialized value equals the default's.
+fn without_defaults(value: Value, defaults: Value) -> Value {
+    match (value, &defaults) {
+        (Value::Object(map), Value::Object(defaults)) => Value::Object(
+            map.into_iter()
+                .filter(|(key, value)| defaults.get(key) != Some(value))
+                .collect(),
+        ),
+        (value, _) => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::pipeline::PageRange;
+
+    fn defaults() -> OptionsFingerprint {
+        PipelineConfig::default().fingerprint(&SecurityLimits::default())
+    }
+
+    #[test]
+    fn default_fingerprint_matches_the_golden_value() {
+        // Guards the canonical encoding itself: if this changes, every
+        // cache key, batch state file and history entry recorded so far
+        // is invalidated. Bump FINGERPRINT_SCHEMA_VERSION deliberately
+        // instead of letting the format drift.
+        assert_eq!(
+            canonical_json(&PipelineConfig::default(), &SecurityLimits::default()),
+            r#"{"limits":{},"options":{},"schema":1}"#
+        );
+        assert_eq!(
+            defaults().as_hex(),
+            "85b8a5f8618d97e057304b46d1ebf39dfd549b5e16333ed84c6fdeee9540698f"
+        );
+    }
+
+    #[test]
+    fn every_semantically_relevant_field_changes_the_fingerprint() {
+        let baseline = defaults();
+        let variants = [
+            PipelineConfig {
+                strict_validation: true,
+                ..Default::default()
+            },
+            PipelineConfig {
+                legacy_mode: true,
+                ..Default::default()
+            },
+            PipelineConfig {
+                page_range: Some(PageRange { start: 1, end: 2 }),
+                ..Default::default()
+            },
+            PipelineConfig {
+                template: Some("contract".to_string()),
+                ..Default::default()
+            },
+            PipelineConfig {
+                wrap_width: Some(80),
+                ..Default::default()
+            },
+            PipelineConfig {
+                width_comments: true,
+                ..Default::default()
+            },
+        ];
+        let mut seen = vec![baseline];
+        for config in variants {
+            let fingerprint = config.fingerprint(&SecurityLimits::default());
+            assert!(!seen.contains(&fingerprint), "collision for {config:?}");
+            seen.push(fingerprint);
+        }
+
+        let tighter = SecurityLimits {
+            max_input_size: 1024,
+            ..Default::default()
+        };
+        let fingerprint = PipelineConfig::default().fingerprint(&tighter);
+        assert!(!seen.contains(&fingerprint), "limits must be covered");
+    }
+
+    #[test]
+    fn default_valued_fields_do_not_affect_the_fingerprint() {
+        // Spelling out a default explicitly is the same as omitting it -
+        // the property that keeps old fingerprints valid when a release
+        // adds a new option.
+        let explicit = PipelineConfig {
+            strict_validation: false,
+            template_variables: std::collections::HashMap::new(),
+            ..Default::default()
+        };
+        assert_eq!(explicit.fingerprint(&SecurityLimits::default()), defaults());
+    }
+
+    #[test]
+    fn template_variables_hash_independently_of_insertion_order() {
+        let mut forward = std::collections::HashMap::new();
+        forward.insert("a".to_string(), "1".to_string());
+        forward.insert("b".to_string(), "2".to_string());
+        let mut reverse = std::collections::HashMap::new();
+        reverse.insert("b".to_string(), "2".to_string());
+        reverse.insert("a".to_string(), "1".to_string());
+        let forward = PipelineConfig {
+            template_variables: forward,
+            ..Default::default()
+        };
+        let reverse = PipelineConfig {
+            template_variables: reverse,
+            ..Default::default()
+        };
+        assert_eq!(
+            forward.fingerprint(&SecurityLimits::default()),
+            reverse.fingerprint(&SecurityLimits::default())
+        );
+    }
+}
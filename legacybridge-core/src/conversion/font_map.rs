@@ -0,0 +1,252 @@
+//! Legacy-to-modern font substitution.
+//!
+//! VB6/VFP9 documents reference fonts that no longer ship with Windows
+//! ("MS Sans Serif", "FoxPrint", ...). The [`FontMap`] rewrites those to
+//! modern equivalents during parsing, falling back by font family
+//! (`\froman` -> serif, `\fswiss` -> sans, ...) for names it has never
+//! heard of. Substitutions are recorded in the document metadata so the
+//! original names stay auditable.
+
+use super::lexer::RtfToken;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// RTF font family, from the `\fnil`/`\froman`/... control words.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FontFamily {
+    #[default]
+    Nil,
+    Roman,
+    Swiss,
+    Modern,
+    Script,
+    Decor,
+    Tech,
+}
+
+/// One entry of a parsed `\fonttbl`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FontEntry {
+    pub index: i32,
+    pub name: String,
+    pub family: FontFamily,
+}
+
+/// A substitution applied by the [`FontMap`], kept for auditability.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FontSubstitution {
+    pub index: i32,
+    pub original: String,
+    pub replacement: String,
+}
+
+/// Configurable font substitution table.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FontMap {
+    /// Exact (case-insensitive) name substitutions.
+    exact: HashMap<String, String>,
+    /// Fallback per font family for names not otherwise known.
+    families: HashMap<FontFamily, String>,
+}
+
+/// Modern fonts passed through untouched even when a family fallback exists.
+const KNOWN_MODERN_FONTS: &[&str] = &[
+    "arial",
+    "calibri",
+    "cambria",
+    "consolas",
+    "courier new",
+    "georgia",
+    "segoe ui",
+    "symbol",
+    "tahoma",
+    "times new roman",
+    "verdana",
+    "wingdings",
+];
+
+impl FontMap {
+    /// The default substitutions for the legacy fonts we see in the wild.
+    pub fn with_defaults() -> Self {
+        let exact = [
+            ("ms sans serif", "Segoe UI"),
+            ("ms serif", "Times New Roman"),
+            ("system", "Segoe UI"),
+            ("helv", "Arial"),
+            ("tms rmn", "Times New Roman"),
+            ("courier", "Courier New"),
+            ("foxprint", "Courier New"),
+            ("fixedsys", "Consolas"),
+            ("terminal", "Consolas"),
+        ];
+        let families = [
+            (FontFamily::Roman, "Times New Roman"),
+            (FontFamily::Swiss, "Arial"),
+            (FontFamily::Modern, "Courier New"),
+        ];
+        FontMap {
+            exact: exact
+                .into_iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect(),
+            families: families
+                .into_iter()
+                .map(|(family, to)| (family, to.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Load a map from its JSON form:
+    /// `{"exact": {"MS Sans Serif": "Segoe UI"}, "families": {"roman": "Times New Roman"}}`.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let mut map: FontMap =
+            serde_json::from_str(json).map_err(|e| format!("invalid font map JSON: {e}"))?;
+        map.exact = map
+            .exact
+            .into_iter()
+            .map(|(from, to)| (from.to_ascii_lowercase(), to))
+            .collect();
+        Ok(map)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read font map {path}: {e}"))?;
+        Self::from_json(&json)
+    }
+
+    /// The replacement for `name`, or `None` when it passes through.
+    pub fn substitute(&self, name: &str, family: FontFamily) -> Option<&str> {
+        let lower = name.trim().to_ascii_lowercase();
+        if KNOWN_MODERN_FONTS.contains(&lower.as_str()) {
+            return None;
+        }
+        if let Some(replacement) = self.exact.get(&lower) {
+            return Some(replacement);
+        }
+        self.families.get(&family).map(String::as_str)
+    }
+}
+
+impl Default for FontMap {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+/// Parse the tokens of a `\fonttbl` destination into font entries.
+pub fn parse_fonttbl(tokens: &[RtfToken]) -> Vec<FontEntry> {
+    let mut fonts = Vec::new();
+    let mut current: Option<FontEntry> = None;
+    for token in tokens {
+        match token {
+            RtfToken::ControlWord { name, parameter, .. } => match name.as_str() {
+                "f" => {
+                    current = Some(FontEntry {
+                        index: parameter.unwrap_or(0),
+                        ..Default::default()
+                    });
+                }
+                "fnil" | "froman" | "fswiss" | "fmodern" | "fscript" | "fdecor" | "ftech" => {
+                    if let Some(entry) = current.as_mut() {
+                        entry.family = match name.as_str() {
+                            "froman" => FontFamily::Roman,
+                            "fswiss" => FontFamily::Swiss,
+                            "fmodern" => FontFamily::Modern,
+                            "fscript" => FontFamily::Script,
+                            "fdecor" => FontFamily::Decor,
+                            "ftech" => FontFamily::Tech,
+                            _ => FontFamily::Nil,
+                        };
+                    }
+                }
+                _ => {}
+            },
+            RtfToken::Text(text) => {
+                let Some(entry) = current.as_mut() else {
+                    continue;
+                };
+                match text.split_once(';') {
+                    Some((head, _)) => {
+                        entry.name.push_str(head);
+                        entry.name = entry.name.trim().to_string();
+                        fonts.push(current.take().expect("entry is set"));
+                    }
+                    None => entry.name.push_str(text),
+                }
+            }
+            _ => {}
+        }
+    }
+    fonts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::lexer::tokenize;
+
+    #[test]
+    fn substitutes_legacy_fonts() {
+        let map = FontMap::with_defaults();
+        assert_eq!(
+            map.substitute("MS Sans Serif", FontFamily::Swiss),
+            Some("Segoe UI")
+        );
+        assert_eq!(
+            map.substitute("FoxPrint", FontFamily::Modern),
+            Some("Courier New")
+        );
+    }
+
+    #[test]
+    fn unknown_fonts_fall_back_by_family() {
+        let map = FontMap::with_defaults();
+        assert_eq!(
+            map.substitute("Olde Print Shoppe", FontFamily::Roman),
+            Some("Times New Roman")
+        );
+        assert_eq!(map.substitute("Mystery Face", FontFamily::Nil), None);
+    }
+
+    #[test]
+    fn modern_fonts_pass_through() {
+        let map = FontMap::with_defaults();
+        assert_eq!(map.substitute("Segoe UI", FontFamily::Swiss), None);
+        assert_eq!(map.substitute("Times New Roman", FontFamily::Roman), None);
+    }
+
+    #[test]
+    fn loads_overrides_from_json() {
+        let map = FontMap::from_json(
+            r#"{"exact": {"FoxPrint": "Cascadia Mono"}, "families": {"swiss": "Inter"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            map.substitute("foxprint", FontFamily::Modern),
+            Some("Cascadia Mono")
+        );
+        assert_eq!(map.substitute("Helv", FontFamily::Swiss), Some("Inter"));
+    }
+
+    #[test]
+    fn rejects_invalid_json() {
+        let err = FontMap::from_json("{not json").unwrap_err();
+        assert!(err.contains("invalid font map JSON"), "{err}");
+    }
+
+    #[test]
+    fn parses_fonttbl_entries() {
+        let tokens = tokenize(
+            "{\\fonttbl{\\f0\\froman Times New Roman;}{\\f1\\fswiss MS Sans Serif;}}",
+        )
+        .unwrap();
+        let fonts = parse_fonttbl(&tokens);
+        assert_eq!(fonts.len(), 2);
+        assert_eq!(fonts[0].name, "Times New Roman");
+        assert_eq!(fonts[0].family, FontFamily::Roman);
+        assert_eq!(fonts[1].index, 1);
+        assert_eq!(fonts[1].name, "MS Sans Serif");
+    }
+}
@@ -0,0 +1,197 @@
+//! Legacy RTF form field extraction.
+//!
+//! The VB6-era apps we migrate produced RTF "forms": `\field` groups
+//! wrapping a `{\*\formfield ...}` destination that carries the field
+//! type, name and default, with the current value in `{\fldrslt ...}`.
+//! [`parse_field`] turns one such group into a structured [`FormField`]
+//! so batch migrations can lift the filled-in values out of the prose.
+
+use super::lexer::RtfToken;
+use serde::{Deserialize, Serialize};
+
+/// Form field type from `\fftypeN` (0 = text, 1 = checkbox, 2 = dropdown).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FormFieldType {
+    #[default]
+    Text,
+    Checkbox,
+    DropDown,
+}
+
+/// One form field lifted out of the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FormField {
+    #[serde(rename = "type")]
+    pub field_type: FormFieldType,
+    /// Field name from `{\*\ffname ...}`.
+    pub name: Option<String>,
+    /// Default value from `{\*\ffdeftext ...}`.
+    pub default: Option<String>,
+    /// Resolved current value: the `\fldrslt` text (falling back to the
+    /// default) for text inputs, `true`/`false` for checkboxes.
+    pub value: String,
+}
+
+impl FormField {
+    /// The text that replaces the field in the document flow: the value,
+    /// a `____` placeholder for empty text inputs, or a `[x]`/`[ ]`
+    /// checkbox marker.
+    pub fn replacement_text(&self) -> String {
+        match self.field_type {
+            FormFieldType::Checkbox => {
+                if self.value == "true" { "[x]" } else { "[ ]" }.to_string()
+            }
+            _ if self.value.is_empty() => "____".to_string(),
+            _ => self.value.clone(),
+        }
+    }
+}
+
+/// Parse one `\field` group (its `GroupStart` already consumed) into a
+/// [`FormField`]. Returns `None` for ordinary fields - hyperlinks, page
+/// numbers - that carry no `\formfield` destination.
+pub fn parse_field(tokens: &[RtfToken]) -> Option<FormField> {
+    if !tokens.iter().any(
+        |t| matches!(t, RtfToken::ControlWord { name, .. } if name == "formfield"),
+    ) {
+        return None;
+    }
+
+    let mut field_type = FormFieldType::Text;
+    let mut name = String::new();
+    let mut default = String::new();
+    let mut result = String::new();
+    let mut checked: Option<i32> = None;
+    let mut default_checked: Option<i32> = None;
+    // Text-collecting destination currently open, with the depth it was
+    // entered at so the matching GroupEnd closes it.
+    let mut current: Option<(&str, usize)> = None;
+    let mut depth = 0usize;
+
+    for token in tokens {
+        match token {
+            RtfToken::GroupStart => depth += 1,
+            RtfToken::GroupEnd => {
+                depth = depth.saturating_sub(1);
+                if let Some((_, entered)) = current {
+                    if depth < entered {
+                        current = None;
+                    }
+                }
+            }
+            RtfToken::ControlWord { name: word, parameter, .. } => match word.as_str() {
+                "fftype" => {
+                    field_type = match parameter {
+                        Some(1) => FormFieldType::Checkbox,
+                        Some(2) => FormFieldType::DropDown,
+                        _ => FormFieldType::Text,
+                    };
+                }
+                "ffres" => checked = *parameter,
+                "ffdefres" => default_checked = *parameter,
+                "ffname" => current = Some(("name", depth)),
+                "ffdeftext" => current = Some(("default", depth)),
+                "fldrslt" => current = Some(("result", depth)),
+                _ => {}
+            },
+            RtfToken::Text(text) => match current {
+                Some(("name", _)) => name.push_str(text),
+                Some(("default", _)) => default.push_str(text),
+                Some(("result", _)) => result.push_str(text),
+                _ => {}
+            },
+            RtfToken::ControlSymbol(_) => {}
+        }
+    }
+
+    let value = match field_type {
+        FormFieldType::Checkbox => {
+            (checked.or(default_checked).unwrap_or(0) != 0).to_string()
+        }
+        _ => {
+            let result = result.trim();
+            if result.is_empty() {
+                default.trim().to_string()
+            } else {
+                result.to_string()
+            }
+        }
+    };
+    Some(FormField {
+        field_type,
+        name: non_empty(name),
+        default: non_empty(default),
+        value,
+    })
+}
+
+fn non_empty(s: String) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::lexer::tokenize;
+
+    fn parse(rtf: &str) -> Option<FormField> {
+        let tokens = tokenize(rtf).unwrap();
+        // Drop the outer GroupStart, as the parser does before handing the
+        // field group over.
+        parse_field(&tokens[1..])
+    }
+
+    #[test]
+    fn parses_text_field_with_result() {
+        let field = parse(
+            "{\\field{\\*\\formfield{\\fftype0{\\*\\ffname first_name}\
+             {\\*\\ffdeftext unknown}}}{\\fldrslt Jane}}",
+        )
+        .unwrap();
+        assert_eq!(field.field_type, FormFieldType::Text);
+        assert_eq!(field.name.as_deref(), Some("first_name"));
+        assert_eq!(field.default.as_deref(), Some("unknown"));
+        assert_eq!(field.value, "Jane");
+        assert_eq!(field.replacement_text(), "Jane");
+    }
+
+    #[test]
+    fn empty_text_field_falls_back_to_default_then_placeholder() {
+        let field = parse(
+            "{\\field{\\*\\formfield{\\fftype0{\\*\\ffdeftext N/A}}}{\\fldrslt }}",
+        )
+        .unwrap();
+        assert_eq!(field.value, "N/A");
+
+        let field =
+            parse("{\\field{\\*\\formfield{\\fftype0}}{\\fldrslt }}").unwrap();
+        assert_eq!(field.value, "");
+        assert_eq!(field.replacement_text(), "____");
+    }
+
+    #[test]
+    fn checkbox_state_resolves_from_ffres_over_default() {
+        let field = parse(
+            "{\\field{\\*\\formfield{\\fftype1\\ffdefres0\\ffres1{\\*\\ffname subscribed}}}}",
+        )
+        .unwrap();
+        assert_eq!(field.field_type, FormFieldType::Checkbox);
+        assert_eq!(field.value, "true");
+        assert_eq!(field.replacement_text(), "[x]");
+
+        let field =
+            parse("{\\field{\\*\\formfield{\\fftype1\\ffdefres0}}}").unwrap();
+        assert_eq!(field.value, "false");
+        assert_eq!(field.replacement_text(), "[ ]");
+    }
+
+    #[test]
+    fn ordinary_fields_are_not_form_fields() {
+        assert!(parse(
+            "{\\field{\\*\\fldinst HYPERLINK \"https://example.com\"}{\\fldrslt link}}"
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,245 @@
+//! Output integrity signatures.
+//!
+//! Regulated deployments need to prove that an output file corresponds to
+//! a specific input and converter version. When enabled, a conversion
+//! appends a signature block to its output - an HTML comment trailer in
+//! Markdown, a `{\*\lbsig ...}` ignorable destination in RTF - carrying
+//! SHA-256 hashes of the input and of the output (excluding the block
+//! itself), the crate version, the options fingerprint and a timestamp.
+//! [`verify_integrity`] re-computes the output hash and fails on any
+//! post-signing modification; the recorded input hash lets an auditor
+//! with the original input check provenance via [`sha256_hex`].
+//!
+//! The block deliberately carries no secret: it detects accidental or
+//! careless modification and pins the converter version, not a hostile
+//! forger who re-signs. Payloads are `key=value` pairs in both carriers
+//! so the RTF form needs no brace escaping.
+
+use super::fingerprint::OptionsFingerprint;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Opens the Markdown signature trailer; the payload runs to ` -->`.
+const MARKDOWN_MARKER: &str = "<!-- legacybridge-integrity ";
+
+/// Opens the RTF signature group; the payload runs to the closing brace.
+/// `\*` makes readers that don't know `\lbsig` skip the group.
+const RTF_MARKER: &str = "{\\*\\lbsig ";
+
+/// The decoded contents of a signature block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntegrityBlock {
+    /// SHA-256 of the conversion's input, as lowercase hex.
+    pub input_sha256: String,
+    /// SHA-256 of the output excluding the signature block itself.
+    pub output_sha256: String,
+    /// Crate version of the converter that produced the output.
+    pub version: String,
+    /// [`OptionsFingerprint`] of the configuration the conversion ran
+    /// under, as lowercase hex.
+    pub options_fingerprint: String,
+    /// RFC 3339 UTC timestamp of signing.
+    pub timestamp: String,
+}
+
+/// SHA-256 of `content`, as lowercase hex - the hash every field of an
+/// [`IntegrityBlock`] uses.
+pub fn sha256_hex(content: &str) -> String {
+    let digest = Sha256::digest(content.as_bytes());
+    let mut hex = String::with_capacity(64);
+    for byte in digest {
+        hex.push_str(&format!("{byte:02x}"));
+    }
+    hex
+}
+
+/// The `key=value` payload shared by both carriers. No value contains
+/// spaces: hashes and fingerprints are hex, versions are dotted numbers,
+/// and RFC 3339 timestamps use `T`.
+fn payload(input: &str, output: &str, fingerprint: &OptionsFingerprint, timestamp: &str) -> String {
+    format!(
+        "input={} output={} version={} options={} time={}",
+        sha256_hex(input),
+        sha256_hex(output),
+        env!("CARGO_PKG_VERSION"),
+        fingerprint.as_hex(),
+        timestamp,
+    )
+}
+
+/// Append a signature trailer to Markdown output. The output hash covers
+/// everything before the trailer, including the newline this adds to
+/// unterminated content.
+pub fn sign_markdown(markdown: &str, input: &str, fingerprint: &OptionsFingerprint) -> String {
+    sign_markdown_at(
+        markdown,
+        input,
+        fingerprint,
+        &super::clock::ConversionClock::real().timestamp_rfc3339(),
+    )
+}
+
+/// [`sign_markdown`] with an explicit signing timestamp, as a fixed
+/// [`ConversionClock`](super::clock::ConversionClock) provides for
+/// reproducible output; the public entry point stamps real UTC time.
+pub fn sign_markdown_at(
+    markdown: &str,
+    input: &str,
+    fingerprint: &OptionsFingerprint,
+    timestamp: &str,
+) -> String {
+    let mut out = markdown.to_string();
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    let payload = payload(input, &out, fingerprint, timestamp);
+    out.push_str(MARKDOWN_MARKER);
+    out.push_str(&payload);
+    out.push_str(" -->\n");
+    out
+}
+
+/// Insert a `{\*\lbsig ...}` group before the document's closing brace.
+/// The output hash covers the RTF exactly as passed, which is what
+/// removing the group restores. Fails on input with no closing brace,
+/// which no generated RTF document lacks.
+pub fn sign_rtf(rtf: &str, input: &str, fingerprint: &OptionsFingerprint) -> Result<String, String> {
+    sign_rtf_at(
+        rtf,
+        input,
+        fingerprint,
+        &super::clock::ConversionClock::real().timestamp_rfc3339(),
+    )
+}
+
+/// [`sign_rtf`] with an explicit signing timestamp; see
+/// [`sign_markdown_at`].
+pub fn sign_rtf_at(
+    rtf: &str,
+    input: &str,
+    fingerprint: &OptionsFingerprint,
+    timestamp: &str,
+) -> Result<String, String> {
+    let close = rtf
+        .rfind('}')
+        .ok_or_else(|| "cannot sign RTF with no closing brace".to_string())?;
+    let payload = payload(input, rtf, fingerprint, timestamp);
+    let mut out = String::with_capacity(rtf.len() + RTF_MARKER.len() + payload.len() + 1);
+    out.push_str(&rtf[..close]);
+    out.push_str(RTF_MARKER);
+    out.push_str(&payload);
+    out.push('}');
+    out.push_str(&rtf[close..]);
+    Ok(out)
+}
+
+/// Locate the signature block in signed content of either carrier,
+/// returning the decoded block and the content with the block removed.
+fn extract(content: &str) -> Result<(IntegrityBlock, String), String> {
+    if let Some(start) = content.rfind(MARKDOWN_MARKER) {
+        let after = &content[start + MARKDOWN_MARKER.len()..];
+        let end = after
+            .find(" -->")
+            .ok_or_else(|| "malformed integrity block: unterminated trailer".to_string())?;
+        return Ok((parse_payload(&after[..end])?, content[..start].to_string()));
+    }
+    if let Some(start) = content.rfind(RTF_MARKER) {
+        let after = &content[start + RTF_MARKER.len()..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| "malformed integrity block: unterminated group".to_string())?;
+        let mut unsigned = String::with_capacity(content.len());
+        unsigned.push_str(&content[..start]);
+        unsigned.push_str(&after[end + 1..]);
+        return Ok((parse_payload(&after[..end])?, unsigned));
+    }
+    Err("no integrity block found".to_string())
+}
+
+fn parse_payload(payload: &str) -> Result<IntegrityBlock, String> {
+    let field = |key: &str| {
+        payload
+            .split_whitespace()
+            .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+            .map(str::to_string)
+            .ok_or_else(|| format!("malformed integrity block: missing {key}"))
+    };
+    Ok(IntegrityBlock {
+        input_sha256: field("input")?,
+        output_sha256: field("output")?,
+        version: field("version")?,
+        options_fingerprint: field("options")?,
+        timestamp: field("time")?,
+    })
+}
+
+/// Verify signed content of either carrier: locate the signature block,
+/// re-hash the content with the block removed, and compare against the
+/// recorded output hash. Returns the decoded block so callers can check
+/// the input hash, version and options against their records.
+pub fn verify_integrity(content: &str) -> Result<IntegrityBlock, String> {
+    let (block, unsigned) = extract(content)?;
+    let actual = sha256_hex(&unsigned);
+    if actual != block.output_sha256 {
+        return Err(format!(
+            "output hash mismatch: content hashes to {actual} but was signed as {}",
+            block.output_sha256
+        ));
+    }
+    Ok(block)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::pipeline::PipelineConfig;
+    use crate::security::SecurityLimits;
+
+    fn fingerprint() -> OptionsFingerprint {
+        PipelineConfig::default().fingerprint(&SecurityLimits::default())
+    }
+
+    #[test]
+    fn signed_markdown_verifies_and_records_the_input() {
+        let signed = sign_markdown("# Title\n\nBody\n", "{\\rtf1 input}", &fingerprint());
+        let block = verify_integrity(&signed).unwrap();
+        assert_eq!(block.input_sha256, sha256_hex("{\\rtf1 input}"));
+        assert_eq!(block.version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(block.options_fingerprint, fingerprint().as_hex());
+    }
+
+    #[test]
+    fn signed_rtf_verifies_and_still_parses() {
+        let rtf = "{\\rtf1\\ansi Hello}";
+        let signed = sign_rtf(rtf, "# Hello\n", &fingerprint()).unwrap();
+        verify_integrity(&signed).unwrap();
+        // The `\*` destination must not leak into the converted text.
+        let markdown = crate::conversion::rtf_to_markdown(&signed).unwrap();
+        assert!(!markdown.contains("lbsig"), "{markdown}");
+        assert!(!markdown.contains("output="), "{markdown}");
+    }
+
+    #[test]
+    fn any_single_character_edit_fails_verification() {
+        for signed in [
+            sign_markdown("body\n", "input", &fingerprint()),
+            sign_rtf("{\\rtf1 body}", "input", &fingerprint()).unwrap(),
+        ] {
+            // Flip one character outside the block (so it stays parsable).
+            let tampered = signed.replacen("body", "bodY", 1);
+            assert_ne!(tampered, signed);
+            let err = verify_integrity(&tampered).unwrap_err();
+            assert!(err.contains("output hash mismatch"), "{err}");
+        }
+    }
+
+    #[test]
+    fn unsigned_and_mangled_content_are_reported_as_such() {
+        assert_eq!(
+            verify_integrity("just markdown").unwrap_err(),
+            "no integrity block found"
+        );
+        let err = verify_integrity("x <!-- legacybridge-integrity input=aa -->\n").unwrap_err();
+        assert!(err.contains("missing output"), "{err}");
+    }
+}
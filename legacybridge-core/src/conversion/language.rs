@@ -0,0 +1,95 @@
+//! Language tags and Windows LCIDs.
+//!
+//! Word's spell checker picks its dictionary from `\deflang`/`\lang`
+//! codes, so generated RTF without them gets every word of a Spanish
+//! document flagged against the English dictionary. This module maps
+//! between the BCP 47 tags our metadata and front matter carry (e.g.
+//! `es-ES`) and the Windows LCIDs RTF wants (e.g. 3082). The table
+//! covers the languages the legacy document bases actually contain;
+//! unknown tags simply emit no language words.
+
+/// BCP 47 tag to Windows LCID, sorted by tag. Where Windows defines
+/// several sorts for one language (Spanish), the modern LCID is used.
+const LANGUAGES: &[(&str, u16)] = &[
+    ("ar-SA", 1025),
+    ("cs-CZ", 1029),
+    ("da-DK", 1030),
+    ("de-DE", 1031),
+    ("el-GR", 1032),
+    ("en-GB", 2057),
+    ("en-US", 1033),
+    ("es-ES", 3082),
+    ("fi-FI", 1035),
+    ("fr-FR", 1036),
+    ("he-IL", 1037),
+    ("hu-HU", 1038),
+    ("it-IT", 1040),
+    ("ja-JP", 1041),
+    ("ko-KR", 1042),
+    ("nb-NO", 1044),
+    ("nl-NL", 1043),
+    ("pl-PL", 1045),
+    ("pt-BR", 1046),
+    ("pt-PT", 2070),
+    ("ru-RU", 1049),
+    ("sv-SE", 1053),
+    ("tr-TR", 1055),
+    ("zh-CN", 2052),
+    ("zh-TW", 1028),
+];
+
+/// The LCID for a language tag, matched case-insensitively. A bare
+/// primary subtag (`es`) resolves to its first regional entry (`es-ES`),
+/// so front matter does not need to spell out a region.
+pub fn lcid_for_tag(tag: &str) -> Option<u16> {
+    let tag = tag.trim();
+    LANGUAGES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(tag))
+        .or_else(|| {
+            LANGUAGES.iter().find(|(known, _)| {
+                known.len() > tag.len()
+                    && known.as_bytes()[tag.len()] == b'-'
+                    && known[..tag.len()].eq_ignore_ascii_case(tag)
+            })
+        })
+        .map(|(_, lcid)| *lcid)
+}
+
+/// The tag for a Windows LCID, for reading `\deflang` back out.
+pub fn tag_for_lcid(lcid: i32) -> Option<&'static str> {
+    LANGUAGES
+        .iter()
+        .find(|(_, known)| i32::from(*known) == lcid)
+        .map(|(tag, _)| *tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_map_to_lcids_case_insensitively() {
+        assert_eq!(lcid_for_tag("es-ES"), Some(3082));
+        assert_eq!(lcid_for_tag("ES-es"), Some(3082));
+        assert_eq!(lcid_for_tag("tlh"), None);
+    }
+
+    #[test]
+    fn bare_primary_subtags_resolve_to_a_region() {
+        assert_eq!(lcid_for_tag("es"), Some(3082));
+        // First regional entry in tag order.
+        assert_eq!(lcid_for_tag("en"), lcid_for_tag("en-GB"));
+        // A prefix is not a primary subtag.
+        assert_eq!(lcid_for_tag("e"), None);
+    }
+
+    #[test]
+    fn lcids_round_trip_to_tags() {
+        for (tag, lcid) in LANGUAGES {
+            assert_eq!(tag_for_lcid(i32::from(*lcid)), Some(*tag));
+            assert_eq!(lcid_for_tag(tag), Some(*lcid));
+        }
+        assert_eq!(tag_for_lcid(9999), None);
+    }
+}
@@ -0,0 +1,424 @@
+//! RTF tokenizer.
+//!
+//! Splits raw RTF input into a flat token stream that the parser turns into
+//! an [`RtfDocument`](super::rtf_parser::RtfDocument) tree.
+
+use super::cancel::{self, CancellationToken};
+
+/// A single lexical unit of an RTF document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtfToken {
+    /// `{` - opens a group.
+    GroupStart,
+    /// `}` - closes a group.
+    GroupEnd,
+    /// `\word` or `\word123` - a control word with optional numeric parameter.
+    ControlWord {
+        name: String,
+        parameter: Option<i32>,
+        /// Whether the word was terminated by a delimiter space the lexer
+        /// consumed. The parser uses it to restore word boundaries that
+        /// legacy writers express as that single space (see
+        /// [`RtfParser`](super::rtf_parser::RtfParser)).
+        space: bool,
+    },
+    /// `\*`, `\~`, `\-` etc. - single-character control symbols.
+    ControlSymbol(char),
+    /// A run of plain text.
+    Text(String),
+}
+
+/// A token together with the byte span of the source that produced it,
+/// for consumers that need to map tokens back to the input - syntax
+/// highlighting in particular.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken {
+    pub token: RtfToken,
+    /// Byte offset of the first input byte this token covers.
+    pub start: usize,
+    /// Byte offset just past the last covered byte.
+    pub end: usize,
+}
+
+/// Bytes scanned between cancellation checks; small enough that even a
+/// pathological input stops within a few milliseconds of the request.
+const CANCEL_CHECK_BYTES: usize = 64 * 1024;
+
+/// Tokenize RTF source into a token stream.
+///
+/// The lexer is intentionally tolerant: it never fails on unknown control
+/// words and treats stray bytes as text, leaving structural judgement to the
+/// parser and validators.
+pub fn tokenize(input: &str) -> Result<Vec<RtfToken>, String> {
+    tokenize_impl(input, None)
+}
+
+/// [`tokenize`] observing a [`CancellationToken`] every
+/// [`CANCEL_CHECK_BYTES`] of input; a cancelled token fails tokenization
+/// with [`CANCELLED_MESSAGE`](cancel::CANCELLED_MESSAGE).
+pub fn tokenize_with_cancellation(
+    input: &str,
+    cancel: &CancellationToken,
+) -> Result<Vec<RtfToken>, String> {
+    tokenize_impl(input, Some(cancel))
+}
+
+/// Tokenize RTF source, annotating every token with the byte span it was
+/// produced from.
+///
+/// The spans tile the input: the first token starts at 0, each token starts
+/// where the previous one ended, and the last token ends at `input.len()`.
+/// Bytes that produce no token of their own - raw newlines, control-word
+/// delimiter spaces, dropped `\-` hyphens - are charged to the nearest
+/// token so coverage has no gaps. Stripping the spans yields exactly the
+/// token stream [`tokenize`] returns.
+pub fn tokenize_spanned(input: &str) -> Result<Vec<SpannedToken>, String> {
+    let mut tokens: Vec<SpannedToken> = Vec::new();
+    let bytes = input.as_bytes();
+    let mut i = 0;
+    let mut text = String::new();
+    // End of the last pushed token; the next token's span starts here so
+    // skipped bytes in between stay covered.
+    let mut prev_end = 0;
+
+    macro_rules! push_token {
+        ($token:expr, $end:expr) => {{
+            tokens.push(SpannedToken {
+                token: $token,
+                start: prev_end,
+                end: $end,
+            });
+            prev_end = $end;
+        }};
+    }
+
+    macro_rules! flush_text {
+        ($end:expr) => {
+            if !text.is_empty() {
+                push_token!(RtfToken::Text(std::mem::take(&mut text)), $end);
+            }
+        };
+    }
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' => {
+                flush_text!(i);
+                i += 1;
+                push_token!(RtfToken::GroupStart, i);
+            }
+            b'}' => {
+                flush_text!(i);
+                i += 1;
+                push_token!(RtfToken::GroupEnd, i);
+            }
+            b'\\' => {
+                // The backslash belongs to whatever token it introduces.
+                let escape_start = i;
+                i += 1;
+                if i >= bytes.len() {
+                    return Err("unexpected end of input after backslash".to_string());
+                }
+                let c = bytes[i];
+                if c.is_ascii_alphabetic() {
+                    flush_text!(escape_start);
+                    let start = i;
+                    while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let name = input[start..i].to_string();
+                    let mut parameter = None;
+                    let num_start = i;
+                    if i < bytes.len() && (bytes[i] == b'-' || bytes[i].is_ascii_digit()) {
+                        i += 1;
+                        while i < bytes.len() && bytes[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        parameter = input[num_start..i].parse::<i32>().ok();
+                    }
+                    // A single space after a control word is a delimiter, not text.
+                    let space = i < bytes.len() && bytes[i] == b' ';
+                    if space {
+                        i += 1;
+                    }
+                    push_token!(RtfToken::ControlWord { name, parameter, space }, i);
+                } else if c == b'\'' {
+                    // \'hh - an 8-bit escaped character (cp1252 in practice).
+                    if i + 2 < bytes.len() {
+                        let hex = &input[i + 1..i + 3];
+                        if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                            text.push(cp1252_to_char(byte));
+                            i += 3;
+                        } else {
+                            i += 1;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                } else {
+                    // Control symbol: \\ \{ \} \~ \- \_ \* etc.
+                    match c {
+                        b'\\' | b'{' | b'}' => text.push(c as char),
+                        b'~' => text.push('\u{a0}'),
+                        b'-' => {} // optional hyphen: drop
+                        b'_' => text.push('-'),
+                        _ => {
+                            flush_text!(escape_start);
+                            push_token!(RtfToken::ControlSymbol(c as char), i + 1);
+                        }
+                    }
+                    i += 1;
+                }
+            }
+            b'\r' | b'\n' => {
+                // Raw newlines in RTF source are insignificant.
+                i += 1;
+            }
+            _ => {
+                // Accumulate a UTF-8 code point worth of text.
+                let ch_len = utf8_len(bytes[i]);
+                let end = (i + ch_len).min(bytes.len());
+                if let Some(s) = input.get(i..end) {
+                    text.push_str(s);
+                }
+                i = end;
+            }
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(SpannedToken {
+            token: RtfToken::Text(text),
+            start: prev_end,
+            end: bytes.len(),
+        });
+    }
+    // Trailing skipped bytes (a final newline, say) extend the last span.
+    if let Some(last) = tokens.last_mut() {
+        last.end = bytes.len();
+    }
+    Ok(tokens)
+}
+
+fn tokenize_impl(input: &str, cancel: Option<&CancellationToken>) -> Result<Vec<RtfToken>, String> {
+    // Start from the thread's recycled buffer; the parser hands it back
+    // when it is done (see thread_state).
+    let mut tokens = super::thread_state::take_token_buffer();
+    let bytes = input.as_bytes();
+    let mut i = 0;
+    let mut next_cancel_check = CANCEL_CHECK_BYTES;
+    let mut text = String::new();
+
+    macro_rules! flush_text {
+        () => {
+            if !text.is_empty() {
+                tokens.push(RtfToken::Text(std::mem::take(&mut text)));
+            }
+        };
+    }
+
+    while i < bytes.len() {
+        if i >= next_cancel_check {
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return Err(cancel::CANCELLED_MESSAGE.to_string());
+            }
+            next_cancel_check = i + CANCEL_CHECK_BYTES;
+        }
+        match bytes[i] {
+            b'{' => {
+                flush_text!();
+                tokens.push(RtfToken::GroupStart);
+                i += 1;
+            }
+            b'}' => {
+                flush_text!();
+                tokens.push(RtfToken::GroupEnd);
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                if i >= bytes.len() {
+                    return Err("unexpected end of input after backslash".to_string());
+                }
+                let c = bytes[i];
+                if c.is_ascii_alphabetic() {
+                    flush_text!();
+                    let start = i;
+                    while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let name = input[start..i].to_string();
+                    let mut parameter = None;
+                    let num_start = i;
+                    if i < bytes.len() && (bytes[i] == b'-' || bytes[i].is_ascii_digit()) {
+                        i += 1;
+                        while i < bytes.len() && bytes[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        parameter = input[num_start..i].parse::<i32>().ok();
+                    }
+                    // A single space after a control word is a delimiter, not
+                    // text; the token remembers it was there.
+                    let space = i < bytes.len() && bytes[i] == b' ';
+                    if space {
+                        i += 1;
+                    }
+                    tokens.push(RtfToken::ControlWord { name, parameter, space });
+                } else if c == b'\'' {
+                    // \'hh - an 8-bit escaped character (cp1252 in practice).
+                    if i + 2 < bytes.len() {
+                        let hex = &input[i + 1..i + 3];
+                        if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                            text.push(cp1252_to_char(byte));
+                            i += 3;
+                        } else {
+                            i += 1;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                } else {
+                    // Control symbol: \\ \{ \} \~ \- \_ \* etc.
+                    match c {
+                        b'\\' | b'{' | b'}' => text.push(c as char),
+                        b'~' => text.push('\u{a0}'),
+                        b'-' => {} // optional hyphen: drop
+                        b'_' => text.push('-'),
+                        _ => {
+                            flush_text!();
+                            tokens.push(RtfToken::ControlSymbol(c as char));
+                        }
+                    }
+                    i += 1;
+                }
+            }
+            b'\r' | b'\n' => {
+                // Raw newlines in RTF source are insignificant.
+                i += 1;
+            }
+            _ => {
+                // Accumulate a UTF-8 code point worth of text.
+                let ch_len = utf8_len(bytes[i]);
+                let end = (i + ch_len).min(bytes.len());
+                if let Some(s) = input.get(i..end) {
+                    text.push_str(s);
+                }
+                i = end;
+            }
+        }
+    }
+    flush_text!();
+    Ok(tokens)
+}
+
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}
+
+/// Map a cp1252 byte to its Unicode character.
+pub(crate) fn cp1252_to_char(byte: u8) -> char {
+    // 0x80..=0x9f differ from Latin-1; everything else maps directly.
+    const HIGH: [char; 32] = [
+        '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}',
+        '\u{2021}', '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}',
+        '\u{17d}', '\u{8f}', '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}',
+        '\u{2022}', '\u{2013}', '\u{2014}', '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}',
+        '\u{153}', '\u{9d}', '\u{17e}', '\u{178}',
+    ];
+    match byte {
+        0x80..=0x9f => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokenizes_basic_document() {
+        let tokens = tokenize("{\\rtf1 Hello \\b World\\b0}").unwrap();
+        assert_eq!(tokens[0], RtfToken::GroupStart);
+        assert_eq!(
+            tokens[1],
+            RtfToken::ControlWord {
+                name: "rtf".to_string(),
+                parameter: Some(1),
+                space: true
+            }
+        );
+        assert!(tokens.contains(&RtfToken::Text("Hello ".to_string())));
+        assert_eq!(*tokens.last().unwrap(), RtfToken::GroupEnd);
+    }
+
+    #[test]
+    fn handles_escaped_characters() {
+        let tokens = tokenize("\\{text\\}").unwrap();
+        assert_eq!(tokens, vec![RtfToken::Text("{text}".to_string())]);
+    }
+
+    #[test]
+    fn handles_hex_escapes() {
+        let tokens = tokenize("caf\\'e9").unwrap();
+        assert_eq!(tokens, vec![RtfToken::Text("café".to_string())]);
+    }
+
+    #[test]
+    fn spans_tile_the_input() {
+        let input = "{\\rtf1 Hello\r\n\\b World}\n";
+        let spanned = tokenize_spanned(input).unwrap();
+        assert_eq!(spanned[0].start, 0);
+        for pair in spanned.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        assert_eq!(spanned.last().unwrap().end, input.len());
+        // The control word's span takes in its delimiter space.
+        assert_eq!(&input[spanned[1].start..spanned[1].end], "\\rtf1 ");
+    }
+
+    #[test]
+    fn stripping_spans_matches_tokenize() {
+        let input = "caf\\'e9 \\{x\\} \\*\\li-720 done";
+        let spanned = tokenize_spanned(input).unwrap();
+        let stripped: Vec<RtfToken> = spanned.into_iter().map(|s| s.token).collect();
+        assert_eq!(stripped, tokenize(input).unwrap());
+    }
+
+    #[test]
+    fn records_consumed_delimiter_spaces() {
+        let tokens = tokenize("\\b0 joined\\i0text").unwrap();
+        assert_eq!(
+            tokens[0],
+            RtfToken::ControlWord {
+                name: "b".to_string(),
+                parameter: Some(0),
+                space: true
+            }
+        );
+        assert_eq!(
+            tokens[2],
+            RtfToken::ControlWord {
+                name: "i".to_string(),
+                parameter: Some(0),
+                space: false
+            }
+        );
+    }
+
+    #[test]
+    fn negative_parameters() {
+        let tokens = tokenize("\\li-720").unwrap();
+        assert_eq!(
+            tokens[0],
+            RtfToken::ControlWord {
+                name: "li".to_string(),
+                parameter: Some(-720),
+                space: false
+            }
+        );
+    }
+}
@@ -0,0 +1,472 @@
+//! Hyperlink URL rewriting for migrated documents.
+//!
+//! Legacy documents link into worlds that no longer exist - intranet
+//! hosts, mapped drives, SharePoint 2003 paths. A [`LinkRewriteConfig`]
+//! carries an ordered list of rules mapping those URLs onto their
+//! modern homes (a literal prefix swap or a regex with capture groups),
+//! plus a policy for absolute links no rule recognizes. The rewrites
+//! operate on the parsed document tree, so they apply in either
+//! conversion direction: the pipeline runs them under
+//! [`PipelineConfig::link_rewrite`](super::pipeline::PipelineConfig::link_rewrite),
+//! and
+//! [`markdown_to_rtf_with_link_rewrites`](super::markdown_to_rtf_with_link_rewrites)
+//! runs the same rules on the way back. Relative links pass through
+//! untouched unless a rule explicitly targets them.
+
+use super::rtf_parser::{RtfNode, TextFormat};
+use serde::{Deserialize, Serialize};
+
+/// Most rewrites and unmatched links listed individually in validation
+/// results; beyond this an overflow line carries the remaining count.
+pub const MAX_LISTED_LINKS: usize = 20;
+
+/// Rule-count cap; a config beyond this is almost certainly generated
+/// wrong, and every URL probes every rule until one matches.
+const MAX_RULES: usize = 64;
+
+/// What happens to an absolute link (one carrying a `scheme://`) that no
+/// rule matched. Relative links are never subject to the policy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmatchedLinkPolicy {
+    /// Leave the link as written.
+    #[default]
+    Keep,
+    /// Leave the link as written, but report it as an `RTF117` warning.
+    Warn,
+    /// Unwrap the link to its display text and report an `RTF117`
+    /// warning; the dead URL is gone but the words survive.
+    Strip,
+}
+
+/// One rewrite rule: exactly one matcher plus its replacement.
+///
+/// A `prefix` rule swaps the matched prefix for the replacement and
+/// keeps the remainder of the URL. A `pattern` rule is a regex tried
+/// against the whole URL; the replacement is a template where `$1`,
+/// `$2`, ... splice in capture groups.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkRewriteRule {
+    /// Literal prefix matcher, mutually exclusive with `pattern`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// Regex matcher, mutually exclusive with `prefix`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Replacement text (prefix rules) or template (regex rules).
+    pub replacement: String,
+}
+
+/// Ordered rewrite rules plus the unmatched-link policy; first matching
+/// rule wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LinkRewriteConfig {
+    #[serde(default)]
+    pub rules: Vec<LinkRewriteRule>,
+    #[serde(default)]
+    pub unmatched: UnmatchedLinkPolicy,
+}
+
+impl LinkRewriteConfig {
+    /// Load a config from its JSON form:
+    /// `{"rules": [{"prefix": "http://intranet/", "replacement": "https://wiki.example.com/"}], "unmatched": "warn"}`.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid link rewrite JSON: {e}"))
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read link rewrite config {path}: {e}"))?;
+        Self::from_json(&json)
+    }
+
+    /// Validate and compile the rules for repeated use across documents.
+    pub fn compile(&self) -> Result<CompiledLinkRewrites, String> {
+        if self.rules.len() > MAX_RULES {
+            return Err(format!(
+                "link rewrite config has {} rules; the limit is {MAX_RULES}",
+                self.rules.len()
+            ));
+        }
+        let mut rules = Vec::with_capacity(self.rules.len());
+        for rule in &self.rules {
+            let matcher = match (&rule.prefix, &rule.pattern) {
+                (Some(prefix), None) => {
+                    if prefix.is_empty() {
+                        return Err("link rewrite rule with empty prefix".to_string());
+                    }
+                    CompiledMatcher::Prefix(prefix.clone())
+                }
+                (None, Some(pattern)) => regex::RegexBuilder::new(pattern)
+                    .size_limit(1 << 20)
+                    .build()
+                    .map(CompiledMatcher::Regex)
+                    .map_err(|e| format!("invalid link rewrite pattern '{pattern}': {e}"))?,
+                _ => {
+                    return Err(
+                        "link rewrite rule must set exactly one of 'prefix' or 'pattern'"
+                            .to_string(),
+                    );
+                }
+            };
+            rules.push((matcher, rule.replacement.clone()));
+        }
+        Ok(CompiledLinkRewrites {
+            rules,
+            unmatched: self.unmatched,
+        })
+    }
+}
+
+/// A rule's matcher compiled for repeated probing.
+#[derive(Debug)]
+enum CompiledMatcher {
+    Prefix(String),
+    Regex(regex::Regex),
+}
+
+/// A [`LinkRewriteConfig`] validated and ready to apply.
+#[derive(Debug)]
+pub struct CompiledLinkRewrites {
+    rules: Vec<(CompiledMatcher, String)>,
+    unmatched: UnmatchedLinkPolicy,
+}
+
+/// What [`CompiledLinkRewrites::apply`] did, for validation reporting.
+/// The listing vectors cap at [`MAX_LISTED_LINKS`]; the counts don't.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LinkRewriteOutcome {
+    /// `(original, new)` per rewritten link, capped.
+    pub rewrites: Vec<(String, String)>,
+    /// Every rewrite, including those past the listing cap.
+    pub rewrite_count: usize,
+    /// Unmatched absolute links under [`UnmatchedLinkPolicy::Warn`] or
+    /// [`UnmatchedLinkPolicy::Strip`], capped.
+    pub unmatched: Vec<String>,
+    /// Every unmatched absolute link, including those past the cap.
+    pub unmatched_count: usize,
+    /// Links unwrapped to their display text under
+    /// [`UnmatchedLinkPolicy::Strip`].
+    pub stripped: usize,
+}
+
+/// An absolute link carries an explicit scheme; everything else is
+/// relative and outside the unmatched-link policy's reach.
+fn is_absolute(url: &str) -> bool {
+    url.contains("://") || url.starts_with("mailto:")
+}
+
+impl CompiledLinkRewrites {
+    /// The rewritten form of `url`, or `None` when no rule matches.
+    /// Public so generators and hosts can probe a single URL without
+    /// building a document around it.
+    pub fn rewrite_url(&self, url: &str) -> Option<String> {
+        for (matcher, replacement) in &self.rules {
+            match matcher {
+                CompiledMatcher::Prefix(prefix) => {
+                    if let Some(rest) = url.strip_prefix(prefix.as_str()) {
+                        return Some(format!("{replacement}{rest}"));
+                    }
+                }
+                CompiledMatcher::Regex(re) => {
+                    if re.is_match(url) {
+                        return Some(re.replace(url, replacement.as_str()).into_owned());
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Rewrite every hyperlink in the tree, applying the unmatched-link
+    /// policy to absolute links no rule recognized. Iterative, like every
+    /// other tree walk in the crate.
+    pub fn apply(&self, nodes: &mut [RtfNode]) -> LinkRewriteOutcome {
+        let mut outcome = LinkRewriteOutcome::default();
+        let mut stack: Vec<&mut RtfNode> = nodes.iter_mut().collect();
+        while let Some(node) = stack.pop() {
+            match node {
+                RtfNode::Hyperlink { url, content, .. } => {
+                    let mut strip = false;
+                    match self.rewrite_url(url) {
+                        Some(new_url) if new_url != *url => {
+                            outcome.rewrite_count += 1;
+                            if outcome.rewrites.len() < MAX_LISTED_LINKS {
+                                outcome.rewrites.push((url.clone(), new_url.clone()));
+                            }
+                            *url = new_url;
+                        }
+                        Some(_) => {}
+                        None if is_absolute(url) => match self.unmatched {
+                            UnmatchedLinkPolicy::Keep => {}
+                            UnmatchedLinkPolicy::Warn | UnmatchedLinkPolicy::Strip => {
+                                outcome.unmatched_count += 1;
+                                if outcome.unmatched.len() < MAX_LISTED_LINKS {
+                                    outcome.unmatched.push(url.clone());
+                                }
+                                strip = self.unmatched == UnmatchedLinkPolicy::Strip;
+                            }
+                        },
+                        None => {}
+                    }
+                    if strip {
+                        outcome.stripped += 1;
+                        let inner = std::mem::take(content);
+                        *node = RtfNode::Formatted {
+                            format: TextFormat::default(),
+                            content: inner,
+                        };
+                    }
+                    if let RtfNode::Hyperlink { content, .. }
+                    | RtfNode::Formatted { content, .. } = node
+                    {
+                        stack.extend(content.iter_mut());
+                    }
+                }
+                RtfNode::Formatted { content, .. }
+                | RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => stack.extend(content.iter_mut()),
+                RtfNode::Table(table) => {
+                    for row in &mut table.rows {
+                        for cell in &mut row.cells {
+                            stack.extend(cell.content.iter_mut());
+                        }
+                    }
+                }
+                RtfNode::Text(_)
+                | RtfNode::Image { .. }
+                | RtfNode::RawRtf { .. }
+                | RtfNode::LineBreak
+                | RtfNode::PageBreak => {}
+            }
+        }
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(url: &str) -> RtfNode {
+        RtfNode::Paragraph {
+            direction: Default::default(),
+            spacing: Default::default(),
+            content: vec![RtfNode::Hyperlink {
+                url: url.to_string(),
+                title: None,
+                content: vec![RtfNode::Text("here".to_string())],
+            }],
+        }
+    }
+
+    fn first_url(nodes: &[RtfNode]) -> Option<&str> {
+        let RtfNode::Paragraph { content, .. } = &nodes[0] else {
+            return None;
+        };
+        match &content[0] {
+            RtfNode::Hyperlink { url, .. } => Some(url),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn prefix_rule_swaps_the_prefix_and_keeps_the_remainder() {
+        let config = LinkRewriteConfig {
+            rules: vec![LinkRewriteRule {
+                prefix: Some("http://intranet/".to_string()),
+                replacement: "https://wiki.example.com/".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut nodes = vec![link("http://intranet/hr/leave.doc")];
+        let outcome = config.compile().unwrap().apply(&mut nodes);
+        assert_eq!(
+            first_url(&nodes),
+            Some("https://wiki.example.com/hr/leave.doc")
+        );
+        assert_eq!(outcome.rewrite_count, 1);
+        assert_eq!(
+            outcome.rewrites,
+            vec![(
+                "http://intranet/hr/leave.doc".to_string(),
+                "https://wiki.example.com/hr/leave.doc".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn regex_rule_splices_capture_groups_into_the_template() {
+        let config = LinkRewriteConfig {
+            rules: vec![LinkRewriteRule {
+                pattern: Some(r"^file://S:/(\w+)/(.+)$".to_string()),
+                replacement: "https://files.example.com/$1/$2".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut nodes = vec![link("file://S:/sales/q3.xls")];
+        config.compile().unwrap().apply(&mut nodes);
+        assert_eq!(first_url(&nodes), Some("https://files.example.com/sales/q3.xls"));
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let config = LinkRewriteConfig {
+            rules: vec![
+                LinkRewriteRule {
+                    prefix: Some("http://intranet/hr/".to_string()),
+                    replacement: "https://people.example.com/".to_string(),
+                    ..Default::default()
+                },
+                LinkRewriteRule {
+                    prefix: Some("http://intranet/".to_string()),
+                    replacement: "https://wiki.example.com/".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut nodes = vec![link("http://intranet/hr/holidays")];
+        config.compile().unwrap().apply(&mut nodes);
+        assert_eq!(first_url(&nodes), Some("https://people.example.com/holidays"));
+    }
+
+    #[test]
+    fn unmatched_absolute_links_follow_the_policy() {
+        let rules = vec![LinkRewriteRule {
+            prefix: Some("http://intranet/".to_string()),
+            replacement: "https://wiki.example.com/".to_string(),
+            ..Default::default()
+        }];
+        let mut kept = vec![link("http://dead.example.net/page")];
+        let outcome = LinkRewriteConfig {
+            rules: rules.clone(),
+            unmatched: UnmatchedLinkPolicy::Keep,
+        }
+        .compile()
+        .unwrap()
+        .apply(&mut kept);
+        assert_eq!(first_url(&kept), Some("http://dead.example.net/page"));
+        assert_eq!(outcome.unmatched_count, 0);
+
+        let mut warned = vec![link("http://dead.example.net/page")];
+        let outcome = LinkRewriteConfig {
+            rules: rules.clone(),
+            unmatched: UnmatchedLinkPolicy::Warn,
+        }
+        .compile()
+        .unwrap()
+        .apply(&mut warned);
+        assert_eq!(first_url(&warned), Some("http://dead.example.net/page"));
+        assert_eq!(outcome.unmatched, vec!["http://dead.example.net/page"]);
+        assert_eq!(outcome.stripped, 0);
+
+        let mut stripped = vec![link("http://dead.example.net/page")];
+        let outcome = LinkRewriteConfig {
+            rules,
+            unmatched: UnmatchedLinkPolicy::Strip,
+        }
+        .compile()
+        .unwrap()
+        .apply(&mut stripped);
+        assert_eq!(first_url(&stripped), None, "link should be unwrapped");
+        let RtfNode::Paragraph { content, .. } = &stripped[0] else {
+            panic!("paragraph survives stripping");
+        };
+        assert_eq!(
+            content[0],
+            RtfNode::Formatted {
+                format: TextFormat::default(),
+                content: vec![RtfNode::Text("here".to_string())],
+            }
+        );
+        assert_eq!(outcome.stripped, 1);
+        assert_eq!(outcome.unmatched_count, 1);
+    }
+
+    #[test]
+    fn relative_links_pass_through_unless_a_rule_targets_them() {
+        let config = LinkRewriteConfig {
+            rules: Vec::new(),
+            unmatched: UnmatchedLinkPolicy::Strip,
+        };
+        let mut nodes = vec![link("docs/readme.md"), link("#section")];
+        let outcome = config.compile().unwrap().apply(&mut nodes);
+        assert_eq!(first_url(&nodes), Some("docs/readme.md"));
+        assert_eq!(outcome.unmatched_count, 0);
+        assert_eq!(outcome.stripped, 0);
+
+        // A prefix rule aimed at relative URLs still applies.
+        let config = LinkRewriteConfig {
+            rules: vec![LinkRewriteRule {
+                prefix: Some("docs/".to_string()),
+                replacement: "manual/".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        config.compile().unwrap().apply(&mut nodes);
+        assert_eq!(first_url(&nodes), Some("manual/readme.md"));
+    }
+
+    #[test]
+    fn listing_caps_at_the_limit_but_counts_keep_going() {
+        let config = LinkRewriteConfig {
+            rules: vec![LinkRewriteRule {
+                prefix: Some("http://old/".to_string()),
+                replacement: "https://new/".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let mut nodes: Vec<RtfNode> = (0..MAX_LISTED_LINKS + 5)
+            .map(|i| link(&format!("http://old/{i}")))
+            .collect();
+        let outcome = config.compile().unwrap().apply(&mut nodes);
+        assert_eq!(outcome.rewrites.len(), MAX_LISTED_LINKS);
+        assert_eq!(outcome.rewrite_count, MAX_LISTED_LINKS + 5);
+    }
+
+    #[test]
+    fn compile_rejects_malformed_rules() {
+        let both = LinkRewriteConfig {
+            rules: vec![LinkRewriteRule {
+                prefix: Some("a".to_string()),
+                pattern: Some("b".to_string()),
+                replacement: "c".to_string(),
+            }],
+            ..Default::default()
+        };
+        assert!(both.compile().unwrap_err().contains("exactly one"));
+
+        let neither = LinkRewriteConfig {
+            rules: vec![LinkRewriteRule::default()],
+            ..Default::default()
+        };
+        assert!(neither.compile().unwrap_err().contains("exactly one"));
+
+        let bad_regex = LinkRewriteConfig {
+            rules: vec![LinkRewriteRule {
+                pattern: Some("(unclosed".to_string()),
+                replacement: "x".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(bad_regex.compile().unwrap_err().contains("invalid link rewrite pattern"));
+    }
+
+    #[test]
+    fn json_round_trip_reads_policy_in_snake_case() {
+        let config = LinkRewriteConfig::from_json(
+            r#"{"rules": [{"prefix": "http://a/", "replacement": "https://b/"}], "unmatched": "warn"}"#,
+        )
+        .unwrap();
+        assert_eq!(config.unmatched, UnmatchedLinkPolicy::Warn);
+        assert_eq!(config.rules.len(), 1);
+        assert!(LinkRewriteConfig::from_json("not json").unwrap_err().contains("invalid link rewrite JSON"));
+    }
+}
@@ -0,0 +1,308 @@
+//! Pre-flight analysis of Markdown inputs.
+//!
+//! The MD->RTF direction counterpart of the RTF analysis surface: before
+//! converting, callers can see which Markdown features a file uses, what
+//! the converter supports natively, and what would be dropped. The
+//! counts come from a lexical scan (fence-aware, so `#` inside a code
+//! block is not a heading), while the outline and the size estimate go
+//! through the real parser and generator, so they match what a
+//! conversion would produce.
+
+use super::markdown_generator::{resolve_outline_offsets, OutlineEntry, Slugger};
+use super::markdown_parser::{is_table_separator, parse_heading, split_table_row, MarkdownParser};
+use super::rtf_generator::{RtfGenerator, SizeEstimate};
+use super::{ConversionError, ConversionResult};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// Rows and columns of one table, in document order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableDimensions {
+    /// Data rows, not counting the header or separator line.
+    pub rows: usize,
+    pub columns: usize,
+}
+
+/// Link or image occurrences, split by target kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TargetCounts {
+    /// Absolute targets (`http:`, `https:`, `mailto:`, protocol-relative).
+    pub external: usize,
+    /// Everything else: relative paths and fragment references.
+    pub relative: usize,
+}
+
+/// What a Markdown file uses, from [`analyze_markdown`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MarkdownAnalysis {
+    /// Heading outline; offsets index the analyzed source text.
+    pub outline: Vec<OutlineEntry>,
+    /// Fenced code block count per info-string language; unlabeled
+    /// blocks count under `""`.
+    pub code_block_languages: BTreeMap<String, usize>,
+    /// Table sizes, in document order.
+    pub tables: Vec<TableDimensions>,
+    pub links: TargetCounts,
+    pub images: TargetCounts,
+    /// Footnote references and definitions (`[^label]`).
+    pub footnotes: usize,
+    /// Definition-list items (a `: ` continuation under a term line).
+    pub definition_list_items: usize,
+    /// Inline HTML usage per tag name, lowercased.
+    pub html_tags: BTreeMap<String, usize>,
+    /// Predicted RTF output size, from [`RtfGenerator::estimate`].
+    pub estimated_rtf_size: SizeEstimate,
+    /// Features the converter has no native mapping for; their content
+    /// survives as plain text at best. Sorted.
+    pub required_extensions: Vec<String>,
+}
+
+/// Analyze Markdown content without converting it; see the
+/// [module docs](self).
+pub fn analyze_markdown(content: &str) -> ConversionResult<MarkdownAnalysis> {
+    let document = MarkdownParser::new()
+        .parse(content)
+        .map_err(ConversionError::parse)?;
+    let estimated_rtf_size = RtfGenerator::new().estimate(&document);
+
+    let mut analysis = MarkdownAnalysis {
+        outline: Vec::new(),
+        code_block_languages: BTreeMap::new(),
+        tables: Vec::new(),
+        links: TargetCounts::default(),
+        images: TargetCounts::default(),
+        footnotes: 0,
+        definition_list_items: 0,
+        html_tags: BTreeMap::new(),
+        estimated_rtf_size,
+        required_extensions: Vec::new(),
+    };
+
+    let mut slugger = Slugger::new(false);
+    let mut in_fence = false;
+    let mut previous_blank = true;
+    let mut table: Option<TableDimensions> = None;
+    let mut byte_offset = 0usize;
+    for line in content.split_inclusive('\n') {
+        let start = byte_offset;
+        byte_offset += line.len();
+        let line = line.trim_end_matches(['\n', '\r']);
+        let trimmed = line.trim_start();
+
+        if let Some(info) = trimmed.strip_prefix("```") {
+            if in_fence {
+                in_fence = false;
+            } else {
+                in_fence = true;
+                let language = info.split_whitespace().next().unwrap_or("").to_string();
+                *analysis.code_block_languages.entry(language).or_insert(0) += 1;
+            }
+            previous_blank = false;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        if is_table_separator(line) {
+            // The row above the separator was counted as a data row;
+            // reclassify it as the header.
+            if let Some(table) = &mut table {
+                table.rows = table.rows.saturating_sub(1);
+            }
+        } else if line.trim_start().starts_with('|') {
+            let columns = split_table_row(line).len();
+            let table = table.get_or_insert(TableDimensions { rows: 0, columns });
+            table.rows += 1;
+            table.columns = table.columns.max(columns);
+        } else if let Some(finished) = table.take() {
+            analysis.tables.push(finished);
+        }
+
+        if let Some((level, text)) = parse_heading(line) {
+            let text = text.to_string();
+            let slug = slugger.slug(&text);
+            analysis.outline.push(OutlineEntry {
+                text,
+                level,
+                slug,
+                // Bytes until resolve_outline_offsets runs below.
+                char_offset: start + (line.len() - trimmed.len()),
+                utf16_offset: 0,
+            });
+        }
+
+        scan_inline(trimmed, &mut analysis);
+
+        if trimmed.starts_with(": ") && !previous_blank {
+            analysis.definition_list_items += 1;
+        }
+        previous_blank = trimmed.is_empty();
+    }
+    if let Some(finished) = table.take() {
+        analysis.tables.push(finished);
+    }
+    resolve_outline_offsets(content, &mut analysis.outline);
+
+    let mut required = Vec::new();
+    if !analysis.code_block_languages.is_empty() {
+        required.push("fenced_code".to_string());
+    }
+    if analysis.links.external + analysis.links.relative > 0 {
+        required.push("links".to_string());
+    }
+    if analysis.images.external + analysis.images.relative > 0 {
+        required.push("images".to_string());
+    }
+    if analysis.footnotes > 0 {
+        required.push("footnotes".to_string());
+    }
+    if analysis.definition_list_items > 0 {
+        required.push("definition_lists".to_string());
+    }
+    if !analysis.html_tags.is_empty() {
+        required.push("inline_html".to_string());
+    }
+    required.sort();
+    analysis.required_extensions = required;
+    Ok(analysis)
+}
+
+/// Count links, images, footnotes and HTML tags on one non-fence line.
+fn scan_inline(line: &str, analysis: &mut MarkdownAnalysis) {
+    static LINK: OnceLock<Regex> = OnceLock::new();
+    static FOOTNOTE: OnceLock<Regex> = OnceLock::new();
+    static HTML_TAG: OnceLock<Regex> = OnceLock::new();
+    let link = LINK.get_or_init(|| Regex::new(r"(!?)\[[^\]]*\]\(([^)]*)\)").unwrap());
+    let footnote = FOOTNOTE.get_or_init(|| Regex::new(r"\[\^[^\]]+\]").unwrap());
+    let html_tag =
+        HTML_TAG.get_or_init(|| Regex::new(r"</?([a-zA-Z][a-zA-Z0-9-]*)[^>]*>").unwrap());
+
+    for capture in link.captures_iter(line) {
+        let target = capture.get(2).map_or("", |m| m.as_str());
+        let external = target.starts_with("http://")
+            || target.starts_with("https://")
+            || target.starts_with("mailto:")
+            || target.starts_with("//");
+        let counts = if capture.get(1).is_some_and(|m| !m.as_str().is_empty()) {
+            &mut analysis.images
+        } else {
+            &mut analysis.links
+        };
+        if external {
+            counts.external += 1;
+        } else {
+            counts.relative += 1;
+        }
+    }
+    analysis.footnotes += footnote.find_iter(line).count();
+    for capture in html_tag.captures_iter(line) {
+        let tag = capture[1].to_ascii_lowercase();
+        *analysis.html_tags.entry(tag).or_insert(0) += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One fixture exercising every counted feature, with the counts
+    /// pinned.
+    #[test]
+    fn fixture_counts_are_pinned() {
+        let md = "# Café Title\n\n\
+                  Intro with a [site](https://example.com) link, a\n\
+                  [local](./notes.md) link and an inline <b>bold</b> tag.\n\n\
+                  ## Details\n\n\
+                  ![logo](https://example.com/logo.png) and ![icon](img/icon.png)\n\n\
+                  A claim[^1] with a footnote.\n\n\
+                  [^1]: The footnote text.\n\n\
+                  Term\n\
+                  : its definition\n\n\
+                  ```rust\n\
+                  let x = 1; // # not a heading, [not](a-link)\n\
+                  ```\n\n\
+                  ```\n\
+                  plain block\n\
+                  ```\n\n\
+                  | A | B | C |\n\
+                  | --- | --- | --- |\n\
+                  | 1 | 2 | 3 |\n\
+                  | 4 | 5 | 6 |\n\n\
+                  <div class=\"x\">html</div>\n";
+        let analysis = analyze_markdown(md).unwrap();
+
+        assert_eq!(analysis.outline.len(), 2);
+        assert_eq!(analysis.outline[0].slug, "café-title");
+        assert_eq!(analysis.outline[0].char_offset, 0);
+        assert_eq!(analysis.outline[1].level, 2);
+        assert_eq!(
+            md.chars().nth(analysis.outline[1].char_offset),
+            Some('#')
+        );
+
+        assert_eq!(
+            analysis.code_block_languages,
+            BTreeMap::from([("rust".to_string(), 1), (String::new(), 1)])
+        );
+        assert_eq!(
+            analysis.tables,
+            vec![TableDimensions {
+                rows: 2,
+                columns: 3
+            }]
+        );
+        assert_eq!(
+            analysis.links,
+            TargetCounts {
+                external: 1,
+                relative: 1
+            }
+        );
+        assert_eq!(
+            analysis.images,
+            TargetCounts {
+                external: 1,
+                relative: 1
+            }
+        );
+        // One reference plus one definition.
+        assert_eq!(analysis.footnotes, 2);
+        assert_eq!(analysis.definition_list_items, 1);
+        assert_eq!(
+            analysis.html_tags,
+            BTreeMap::from([("b".to_string(), 2), ("div".to_string(), 2)])
+        );
+        assert!(analysis.estimated_rtf_size.expected > 0);
+        assert_eq!(
+            analysis.required_extensions,
+            vec![
+                "definition_lists",
+                "fenced_code",
+                "footnotes",
+                "images",
+                "inline_html",
+                "links"
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_documents_require_no_extensions() {
+        let analysis =
+            analyze_markdown("# Title\n\nJust **bold** text and\n\n- a list\n").unwrap();
+        assert!(analysis.required_extensions.is_empty());
+        assert_eq!(analysis.outline.len(), 1);
+        assert!(analysis.tables.is_empty());
+    }
+
+    #[test]
+    fn fences_suppress_feature_detection() {
+        let analysis = analyze_markdown("```\n# nope\n[x](y)\n| a |\n```\n").unwrap();
+        assert!(analysis.outline.is_empty());
+        assert_eq!(analysis.links, TargetCounts::default());
+        assert!(analysis.tables.is_empty());
+    }
+}
@@ -0,0 +1,1842 @@
+//! Markdown generator.
+//!
+//! Walks an [`RtfDocument`] and emits GitHub-flavored Markdown. Text content
+//! is escaped context-sensitively so literal characters from the source
+//! document never turn into accidental Markdown syntax.
+
+use super::rtf_parser::{
+    CellAlignment, CellMerge, Direction, ParagraphSpacing, Revision, RevisionKind, RtfDocument,
+    RtfNode, Table, TextFormat,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Flavor of Markdown emitted for constructs core CommonMark lacks,
+/// currently just heading anchors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFlavor {
+    /// Plain GFM; no explicit anchors (GitHub derives its own).
+    #[default]
+    Gfm,
+    /// kramdown/Pandoc attribute lists: `## Title {#slug}`.
+    AttrList,
+    /// An `<a id="slug"></a>` line before each heading, for renderers
+    /// without anchor support.
+    HtmlAnchors,
+}
+
+/// What to do with tracked changes (`\revised`/`\deleted` runs).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionMode {
+    /// Keep inserted text, drop deleted text - the document as the
+    /// reviewers left it.
+    #[default]
+    AcceptAll,
+    /// Drop inserted text, keep deleted text - the document before review.
+    RejectAll,
+    /// Keep both, marked up: CriticMarkup (`{++ins++}`/`{--del--}`) for
+    /// Markdown flavors, `<ins>`/`<del>` with the author and date in a
+    /// `title` attribute for [`OutputFlavor::HtmlAnchors`].
+    Annotate,
+}
+
+/// One heading of the document outline, in document order.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OutlineEntry {
+    pub text: String,
+    pub level: u8,
+    pub slug: String,
+    /// Position of the heading's `#` marker in the generated Markdown,
+    /// in Unicode scalar values.
+    pub char_offset: usize,
+    /// The same position in UTF-16 code units, for JS editors whose
+    /// cursor positions count those.
+    pub utf16_offset: usize,
+}
+
+/// GitHub-compatible slug generation with duplicate handling: repeated
+/// headings get `-1`/`-2`... suffixes in document order.
+pub struct Slugger {
+    counts: HashMap<String, usize>,
+    ascii: bool,
+}
+
+impl Slugger {
+    pub fn new(ascii: bool) -> Self {
+        Slugger {
+            counts: HashMap::new(),
+            ascii,
+        }
+    }
+
+    pub fn slug(&mut self, text: &str) -> String {
+        let mut base = String::with_capacity(text.len());
+        for c in text.trim().chars() {
+            if c.is_whitespace() {
+                base.push('-');
+            } else if c.is_alphanumeric() || c == '-' || c == '_' {
+                if !self.ascii || c.is_ascii() {
+                    base.extend(c.to_lowercase());
+                } else {
+                    base.push_str(transliterate(c));
+                }
+            }
+            // Everything else (punctuation) is stripped, as GitHub does.
+        }
+        if base.is_empty() {
+            // Punctuation-only headings still need a usable anchor.
+            base.push_str("section");
+        }
+        let seen = self.counts.entry(base.clone()).or_insert(0);
+        let slug = if *seen == 0 {
+            base.clone()
+        } else {
+            format!("{base}-{seen}")
+        };
+        *seen += 1;
+        slug
+    }
+}
+
+/// Transliterate a Latin letter with diacritics to its ASCII base form;
+/// characters without an obvious fold are dropped.
+fn transliterate(c: char) -> &'static str {
+    match c.to_lowercase().next().unwrap_or(c) {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'č' => "c",
+        'è'..='ë' | 'ē' | 'ė' | 'ę' => "e",
+        'ì'..='ï' | 'ī' | 'į' => "i",
+        'ð' | 'ď' => "d",
+        'ñ' | 'ń' | 'ň' => "n",
+        'ò'..='ö' | 'ø' | 'ō' => "o",
+        'œ' => "oe",
+        'ß' => "ss",
+        'š' | 'ś' => "s",
+        'ù'..='ü' | 'ū' | 'ů' => "u",
+        'ý' | 'ÿ' => "y",
+        'ž' | 'ź' | 'ż' => "z",
+        'þ' => "th",
+        _ => "",
+    }
+}
+
+/// How right-to-left content is marked in the generated Markdown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RtlStyle {
+    /// Unicode directional isolates (U+2066/U+2067...U+2069); renders
+    /// correctly in any Unicode-aware viewer.
+    #[default]
+    Isolates,
+    /// `<div dir="rtl">` blocks, for HTML-capable Markdown renderers.
+    HtmlDiv,
+}
+
+/// How tables containing merged cells (`\clmgf`/`\clvmgf` spans) are
+/// emitted. Tables without merges always use the GFM grid.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MergedTableStyle {
+    /// GFM grid; the format cannot express spans, so a span keeps its
+    /// content in the first cell and the continuation cells are left
+    /// empty. Lossy: the column alignment survives but the merge does
+    /// not (the pipeline reports this as an `RTF111` warning).
+    #[default]
+    GfmGrid,
+    /// HTML `<table>` with `colspan`/`rowspan` attributes, for
+    /// HTML-capable Markdown renderers.
+    Html,
+}
+
+/// First Strong Isolate characters. Runs use LRI/RLI; PDI closes either.
+const LRI: char = '\u{2066}';
+const RLI: char = '\u{2067}';
+const PDI: char = '\u{2069}';
+
+/// Where a piece of text will land in the generated output, which determines
+/// which characters must be escaped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeContext {
+    /// Regular block flow (paragraphs, headings).
+    Block,
+    /// Inside a GFM table cell: pipes additionally need escaping.
+    TableCell,
+    /// Inside a code span or fenced block we generated: no escaping at all.
+    Code,
+}
+
+pub struct MarkdownGenerator {
+    rtl_style: RtlStyle,
+    output_flavor: OutputFlavor,
+    /// Transliterate slugs to ASCII instead of preserving Unicode.
+    ascii_slugs: bool,
+    revision_mode: RevisionMode,
+    /// Re-flow paragraph lines at this column (default off).
+    wrap_width: Option<usize>,
+    /// Record explicit paragraph spacing as HTML comments (default off:
+    /// spacing is dropped, since Markdown has no way to express it).
+    spacing_comments: bool,
+    /// Record table column widths as an HTML comment before each table
+    /// (default off: widths are dropped).
+    width_comments: bool,
+    /// How tables with merged cells are emitted (default: GFM grid).
+    merged_table_style: MergedTableStyle,
+    /// Cap on flattened GFM cell length in characters (default off); see
+    /// [`with_max_cell_length`](Self::with_max_cell_length).
+    max_cell_length: Option<usize>,
+    /// Emit tables with an over-cap cell as HTML instead of truncating;
+    /// see [`with_fallback_html_tables`](Self::with_fallback_html_tables).
+    fallback_html_tables: bool,
+    /// Cells the last `generate` truncated; a `Cell` because generation
+    /// borrows the generator shared. Callers surface this as a warning.
+    truncated_cells: std::cell::Cell<usize>,
+    /// Emit only core Markdown a legacy viewer renders as plain text; see
+    /// [`with_legacy_mode`](Self::with_legacy_mode).
+    legacy_mode: bool,
+}
+
+impl MarkdownGenerator {
+    pub fn new() -> Self {
+        MarkdownGenerator {
+            rtl_style: RtlStyle::default(),
+            output_flavor: OutputFlavor::default(),
+            ascii_slugs: false,
+            revision_mode: RevisionMode::default(),
+            wrap_width: None,
+            spacing_comments: false,
+            width_comments: false,
+            merged_table_style: MergedTableStyle::default(),
+            max_cell_length: None,
+            fallback_html_tables: false,
+            truncated_cells: std::cell::Cell::new(0),
+            legacy_mode: false,
+        }
+    }
+
+    /// Choose how right-to-left content is marked (default: isolates).
+    pub fn with_rtl_style(mut self, rtl_style: RtlStyle) -> Self {
+        self.rtl_style = rtl_style;
+        self
+    }
+
+    /// Choose how heading anchors are emitted (default: none).
+    pub fn with_output_flavor(mut self, output_flavor: OutputFlavor) -> Self {
+        self.output_flavor = output_flavor;
+        self
+    }
+
+    /// Transliterate heading slugs to ASCII (default: Unicode preserved).
+    pub fn with_ascii_slugs(mut self, ascii_slugs: bool) -> Self {
+        self.ascii_slugs = ascii_slugs;
+        self
+    }
+
+    /// Choose how tracked changes are resolved (default: accept all).
+    pub fn with_revision_mode(mut self, revision_mode: RevisionMode) -> Self {
+        self.revision_mode = revision_mode;
+        self
+    }
+
+    /// Re-flow paragraph lines at `width` columns (default: off, one line
+    /// per paragraph). See [`wrap_markdown`] for the breaking rules.
+    pub fn with_wrap_width(mut self, wrap_width: Option<usize>) -> Self {
+        self.wrap_width = wrap_width;
+        self
+    }
+
+    /// Encode explicit paragraph spacing as an HTML comment before each
+    /// block (default: spacing is dropped).
+    pub fn with_spacing_comments(mut self, spacing_comments: bool) -> Self {
+        self.spacing_comments = spacing_comments;
+        self
+    }
+
+    /// Record table column boundaries as a `<!-- widths: 2400,4800 -->`
+    /// comment before each table that has them, so the Markdown->RTF
+    /// direction can regenerate the original `\cellx` positions
+    /// (default off: widths are dropped).
+    pub fn with_width_comments(mut self, width_comments: bool) -> Self {
+        self.width_comments = width_comments;
+        self
+    }
+
+    /// Choose how tables with merged cells are emitted (default: GFM
+    /// grid with continuation cells left empty).
+    pub fn with_merged_table_style(mut self, merged_table_style: MergedTableStyle) -> Self {
+        self.merged_table_style = merged_table_style;
+        self
+    }
+
+    /// Truncate flattened GFM table cells longer than `max` characters,
+    /// appending an ellipsis (default: off, cells keep their full
+    /// length). Truncated cells are counted in
+    /// [`truncated_cells`](Self::truncated_cells) so callers can warn
+    /// about the loss; extraction APIs working from the document tree
+    /// are unaffected.
+    pub fn with_max_cell_length(mut self, max_cell_length: Option<usize>) -> Self {
+        self.max_cell_length = max_cell_length;
+        self
+    }
+
+    /// With a cell-length cap set, emit any table containing an over-cap
+    /// cell as an HTML `<table>` instead of truncating (default: off).
+    /// Keeps the full content at the cost of requiring an HTML-capable
+    /// renderer; suppressed in legacy mode, where truncation applies.
+    pub fn with_fallback_html_tables(mut self, fallback_html_tables: bool) -> Self {
+        self.fallback_html_tables = fallback_html_tables;
+        self
+    }
+
+    /// How many table cells the last [`generate`](Self::generate) call
+    /// truncated under the cell-length cap.
+    pub fn truncated_cells(&self) -> usize {
+        self.truncated_cells.get()
+    }
+
+    /// Avoid HTML and extended Markdown syntax, for viewers that render
+    /// only the core constructs (default: off). In legacy mode:
+    ///
+    /// - no `<a id>` anchor lines or `{#slug}` attribute lists, whatever
+    ///   the output flavor says;
+    /// - right-to-left paragraphs use Unicode isolates, never
+    ///   `<div dir="rtl">`;
+    /// - annotated revisions use CriticMarkup, never `<ins>`/`<del>`;
+    /// - struck text is emitted plain instead of GFM `~~strikethrough~~`;
+    /// - merged tables use the GFM grid, never an HTML `<table>`;
+    /// - spacing comments are suppressed.
+    pub fn with_legacy_mode(mut self, legacy_mode: bool) -> Self {
+        self.legacy_mode = legacy_mode;
+        self
+    }
+
+    pub fn generate(&self, document: &RtfDocument) -> String {
+        self.generate_with_outline(document).0
+    }
+
+    /// Generate the Markdown and the document outline in one pass, so the
+    /// outline's offsets index the string actually returned - anchor
+    /// lines, wrapping and all.
+    pub fn generate_with_outline(&self, document: &RtfDocument) -> (String, Vec<OutlineEntry>) {
+        self.truncated_cells.set(0);
+        let mut out = String::new();
+        // A document language round-trips as a front-matter key, mirroring
+        // what the Markdown parser reads back into the metadata.
+        if let Some(language) = &document.metadata.language {
+            out.push_str("---\nlang: ");
+            out.push_str(language);
+            out.push_str("\n---\n\n");
+        }
+        let mut slugger = Slugger::new(self.ascii_slugs);
+        let mut outline = Vec::new();
+        for node in &document.content {
+            self.generate_block(node, &mut out, &mut slugger, &mut outline);
+        }
+        // Normalize trailing blank lines to a single newline.
+        while out.ends_with("\n\n") {
+            out.pop();
+        }
+        let out = match self.wrap_width {
+            // Heading lines are exempt from wrapping and the stashed
+            // offsets all point at line starts, so remapping line starts
+            // keeps them exact.
+            Some(width) => wrap_markdown_remapping(&out, width, &mut outline),
+            None => out,
+        };
+        resolve_outline_offsets(&out, &mut outline);
+        (out, outline)
+    }
+
+    /// Compute the document outline (heading text, level, slug, offsets)
+    /// alone. Delegates to
+    /// [`generate_with_outline`](Self::generate_with_outline), so slugs
+    /// and offsets match the emitted Markdown exactly.
+    pub fn outline(&self, document: &RtfDocument) -> Vec<OutlineEntry> {
+        self.generate_with_outline(document).1
+    }
+
+    fn generate_block(
+        &self,
+        node: &RtfNode,
+        out: &mut String,
+        slugger: &mut Slugger,
+        outline: &mut Vec<OutlineEntry>,
+    ) {
+        match node {
+            RtfNode::Heading {
+                level,
+                spacing,
+                content,
+            } => {
+                self.push_spacing_comment(spacing, out);
+                let text = heading_text(content);
+                let slug = slugger.slug(&text);
+                if self.output_flavor == OutputFlavor::HtmlAnchors && !self.legacy_mode {
+                    out.push_str(&format!("<a id=\"{slug}\"></a>\n"));
+                }
+                // The offset is in bytes until the output is final;
+                // `resolve_outline_offsets` converts it.
+                outline.push(OutlineEntry {
+                    text,
+                    level: *level,
+                    slug: slug.clone(),
+                    char_offset: out.len(),
+                    utf16_offset: 0,
+                });
+                out.push_str(&"#".repeat(*level as usize));
+                out.push(' ');
+                // Headings are implicitly bold; dropping the redundant bold
+                // flag avoids `# **Title**` on round trips.
+                let content = strip_bold(content);
+                // Heading text follows the marker, so it is not at line start.
+                out.push_str(&self.render_inline(&content, EscapeContext::Block, false));
+                if self.output_flavor == OutputFlavor::AttrList && !self.legacy_mode {
+                    out.push_str(&format!(" {{#{slug}}}"));
+                }
+                out.push_str("\n\n");
+            }
+            RtfNode::Paragraph {
+                direction,
+                spacing,
+                content,
+            } => {
+                // An empty paragraph (a kept `\par` run) is one line of
+                // vertical spacing; whitespace-only text still vanishes.
+                if content.is_empty() {
+                    out.push('\n');
+                    return;
+                }
+                let text = self.render_inline_directed(
+                    content,
+                    EscapeContext::Block,
+                    true,
+                    *direction,
+                );
+                if !text.trim().is_empty() {
+                    self.push_spacing_comment(spacing, out);
+                    // Legacy viewers show <div> tags as text; isolates
+                    // degrade invisibly, so they win in legacy mode.
+                    let rtl_style = if self.legacy_mode {
+                        RtlStyle::Isolates
+                    } else {
+                        self.rtl_style
+                    };
+                    match (direction, rtl_style) {
+                        (Direction::LeftToRight, _) => out.push_str(&text),
+                        (Direction::RightToLeft, RtlStyle::Isolates) => {
+                            out.push(RLI);
+                            out.push_str(&text);
+                            out.push(PDI);
+                        }
+                        (Direction::RightToLeft, RtlStyle::HtmlDiv) => {
+                            out.push_str("<div dir=\"rtl\">");
+                            out.push_str(&text);
+                            out.push_str("</div>");
+                        }
+                    }
+                    out.push_str("\n\n");
+                }
+            }
+            RtfNode::ListItem {
+                ordered,
+                level,
+                content,
+            } => {
+                out.push_str(&"  ".repeat(*level as usize));
+                out.push_str(if *ordered { "1. " } else { "- " });
+                out.push_str(&self.render_inline(content, EscapeContext::Block, false));
+                out.push('\n');
+            }
+            RtfNode::Table(table) => {
+                self.generate_table(table, out);
+            }
+            RtfNode::PageBreak => {
+                out.push_str("---\n\n");
+            }
+            RtfNode::RawRtf { content } => {
+                out.push_str("```rtf-raw\n");
+                out.push_str(content);
+                out.push_str("\n```\n\n");
+            }
+            // Stray inline nodes at block level: render as their own line.
+            other => {
+                let text = self.render_inline(std::slice::from_ref(other), EscapeContext::Block, true);
+                if !text.trim().is_empty() {
+                    out.push_str(&text);
+                    out.push_str("\n\n");
+                }
+            }
+        }
+    }
+
+    fn generate_table(&self, table: &Table, out: &mut String) {
+        if table.rows.is_empty() {
+            return;
+        }
+        let width = table.rows.iter().map(|r| r.cells.len()).max().unwrap_or(0);
+        if width == 0 {
+            return;
+        }
+        if self.width_comments && !self.legacy_mode {
+            let bounds = table.col_bounds();
+            if !bounds.is_empty() {
+                let widths: Vec<String> = bounds.iter().map(i32::to_string).collect();
+                out.push_str(&format!("<!-- widths: {} -->\n", widths.join(",")));
+            }
+        }
+        if table.has_merges()
+            && self.merged_table_style == MergedTableStyle::Html
+            && !self.legacy_mode
+        {
+            self.generate_html_table(table, out);
+            return;
+        }
+        // With the fallback enabled, a single over-cap cell switches the
+        // whole table to HTML: mixing a truncated grid with full HTML
+        // cells would make the loss depend on neighboring cells.
+        if self.fallback_html_tables && !self.legacy_mode && self.table_exceeds_cell_cap(table) {
+            self.generate_html_table(table, out);
+            return;
+        }
+        let alignments = table.column_alignments();
+        for (i, row) in table.rows.iter().enumerate() {
+            out.push('|');
+            for col in 0..width {
+                // Continuation cells of a merged span stay empty: GFM has
+                // no spans, so the content lives in the span's first cell
+                // and the grid keeps its column alignment.
+                let cell = row
+                    .cells
+                    .get(col)
+                    .filter(|c| c.h_merge != CellMerge::Merged && c.v_merge != CellMerge::Merged)
+                    .map(|c| self.render_cell(&c.content, EscapeContext::TableCell))
+                    .unwrap_or_default();
+                out.push(' ');
+                out.push_str(&self.truncate_cell(cell));
+                out.push_str(" |");
+            }
+            out.push('\n');
+            if i == 0 {
+                out.push('|');
+                for col in 0..width {
+                    out.push_str(match alignments.get(col) {
+                        Some(CellAlignment::Center) => " :---: |",
+                        Some(CellAlignment::Right) => " ---: |",
+                        _ => " --- |",
+                    });
+                }
+                out.push('\n');
+            }
+        }
+        out.push('\n');
+    }
+
+    /// Emit a table as an HTML `<table>`, the only way Markdown output can
+    /// express `colspan`/`rowspan`. Continuation cells are covered by
+    /// their span's first cell and produce no element.
+    fn generate_html_table(&self, table: &Table, out: &mut String) {
+        out.push_str("<table>\n");
+        for (i, row) in table.rows.iter().enumerate() {
+            out.push_str("  <tr>");
+            for (col, cell) in row.cells.iter().enumerate() {
+                if cell.h_merge == CellMerge::Merged || cell.v_merge == CellMerge::Merged {
+                    continue;
+                }
+                out.push_str("<td");
+                let col_span = table.col_span(i, col);
+                if col_span > 1 {
+                    out.push_str(&format!(" colspan=\"{col_span}\""));
+                }
+                let row_span = table.row_span(i, col);
+                if row_span > 1 {
+                    out.push_str(&format!(" rowspan=\"{row_span}\""));
+                }
+                out.push('>');
+                out.push_str(&self.render_cell(&cell.content, EscapeContext::Block));
+                out.push_str("</td>");
+            }
+            out.push_str("</tr>\n");
+        }
+        out.push_str("</table>\n\n");
+    }
+
+    /// Flatten one cell's content to a single line: top-level paragraph
+    /// breaks become `<br>` elements (a plain space in legacy mode, which
+    /// bans HTML), empty paragraphs are dropped and the result is
+    /// trimmed. A raw newline would break the row across lines and
+    /// corrupt the grid.
+    fn render_cell(&self, content: &[RtfNode], ctx: EscapeContext) -> String {
+        let separator = if self.legacy_mode { " " } else { "<br>" };
+        let mut segments: Vec<String> = Vec::new();
+        let mut run_start = 0;
+        let flush = |segments: &mut Vec<String>, nodes: &[RtfNode]| {
+            // In block context line breaks render as raw hard breaks,
+            // which a cell cannot hold; fold them into the separator.
+            let segment = self.render_inline(nodes, ctx, false).replace("  \n", separator);
+            let segment = segment.trim();
+            if !segment.is_empty() {
+                segments.push(segment.to_string());
+            }
+        };
+        for (i, node) in content.iter().enumerate() {
+            if let RtfNode::Paragraph { content: inner, .. }
+            | RtfNode::Heading { content: inner, .. }
+            | RtfNode::ListItem { content: inner, .. } = node
+            {
+                flush(&mut segments, &content[run_start..i]);
+                flush(&mut segments, inner);
+                run_start = i + 1;
+            }
+        }
+        flush(&mut segments, &content[run_start..]);
+        let mut cell = segments.join(separator);
+        // A \par directly before \cell leaves a trailing break carrying
+        // no content; drop dangling breaks at either end.
+        while let Some(stripped) = cell.strip_suffix("<br>") {
+            cell = stripped.trim_end().to_string();
+        }
+        while let Some(stripped) = cell.strip_prefix("<br>") {
+            cell = stripped.trim_start().to_string();
+        }
+        cell
+    }
+
+    /// Enforce the cell-length cap on a flattened cell, appending an
+    /// ellipsis and counting the loss for the caller's warning.
+    fn truncate_cell(&self, cell: String) -> String {
+        let Some(max) = self.max_cell_length else {
+            return cell;
+        };
+        let Some((boundary, _)) = cell.char_indices().nth(max) else {
+            return cell;
+        };
+        self.truncated_cells.set(self.truncated_cells.get() + 1);
+        let mut truncated = cell[..boundary].to_string();
+        truncated.push('…');
+        truncated
+    }
+
+    /// Whether any cell's flattened form exceeds the cell-length cap,
+    /// which under the HTML fallback switches the whole table to HTML.
+    fn table_exceeds_cell_cap(&self, table: &Table) -> bool {
+        let Some(max) = self.max_cell_length else {
+            return false;
+        };
+        table.rows.iter().any(|row| {
+            row.cells.iter().any(|cell| {
+                self.render_cell(&cell.content, EscapeContext::TableCell)
+                    .chars()
+                    .count()
+                    > max
+            })
+        })
+    }
+
+    /// Record a block's explicit spacing as an HTML comment, when enabled.
+    /// Only fields the source set are listed, in raw RTF units.
+    fn push_spacing_comment(&self, spacing: &ParagraphSpacing, out: &mut String) {
+        if !self.spacing_comments || self.legacy_mode || spacing.is_default() {
+            return;
+        }
+        let mut parts = Vec::new();
+        if let Some(twips) = spacing.space_before {
+            parts.push(format!("sb={twips}"));
+        }
+        if let Some(twips) = spacing.space_after {
+            parts.push(format!("sa={twips}"));
+        }
+        if let Some(value) = spacing.line_spacing {
+            parts.push(format!("sl={value}"));
+            parts.push(format!("slmult={}", spacing.line_spacing_multiple as i32));
+        }
+        if spacing.page_break_before {
+            parts.push("pagebb=1".to_string());
+        }
+        if spacing.keep_with_next {
+            parts.push("keepn=1".to_string());
+        }
+        out.push_str(&format!("<!-- spacing: {} -->\n", parts.join(" ")));
+    }
+
+    fn render_inline(&self, nodes: &[RtfNode], ctx: EscapeContext, at_line_start: bool) -> String {
+        self.render_inline_directed(nodes, ctx, at_line_start, Direction::LeftToRight)
+    }
+
+    /// Render inline content using an explicit frame stack, so formatting
+    /// nesting depth is limited only by memory - not by the native stack.
+    ///
+    /// Runs whose explicit direction differs from `base` (Latin product
+    /// codes inside an Arabic sentence, and vice versa) are wrapped in
+    /// directional isolates so the bidi algorithm keeps their ordering.
+    fn render_inline_directed(
+        &self,
+        nodes: &[RtfNode],
+        ctx: EscapeContext,
+        at_line_start: bool,
+        base: Direction,
+    ) -> String {
+        /// One partially rendered node list; `wrap` is set for the content
+        /// of a `Formatted` node and applied when the frame completes.
+        /// `ctx` is per-frame so a "Code" style can suspend escaping for
+        /// exactly its own content.
+        struct Frame<'a> {
+            nodes: std::slice::Iter<'a, RtfNode>,
+            buf: String,
+            wrap: Option<&'a TextFormat>,
+            /// Set for the content of a `Hyperlink` node: destination and
+            /// title, wrapped around the rendered text when the frame
+            /// completes.
+            link: Option<(&'a str, Option<&'a str>)>,
+            ctx: EscapeContext,
+        }
+
+        let mut line_start = at_line_start;
+        let mut stack = vec![Frame {
+            nodes: nodes.iter(),
+            buf: String::new(),
+            wrap: None,
+            link: None,
+            ctx,
+        }];
+        loop {
+            let top = stack.last_mut().expect("render stack never empties");
+            let ctx = top.ctx;
+            let Some(node) = top.nodes.next() else {
+                let frame = stack.pop().expect("render stack never empties");
+                let mut rendered = match frame.wrap {
+                    // GFM strikethrough is an extension; legacy viewers
+                    // show the tildes, so struck text goes out plain.
+                    Some(format) if self.legacy_mode && format.strikethrough => {
+                        let format = TextFormat {
+                            strikethrough: false,
+                            ..format.clone()
+                        };
+                        wrap_formatting(&frame.buf, &format)
+                    }
+                    Some(format) => wrap_formatting(&frame.buf, format),
+                    None => frame.buf,
+                };
+                if let Some(revision) = frame.wrap.and_then(|f| f.revision.as_ref()) {
+                    if self.revision_mode == RevisionMode::Annotate
+                        && !rendered.trim().is_empty()
+                    {
+                        rendered = self.annotate_revision(&rendered, revision);
+                    }
+                }
+                if let Some(direction) = frame.wrap.and_then(|f| f.direction) {
+                    if direction != base && !rendered.trim().is_empty() {
+                        let isolate = match direction {
+                            Direction::LeftToRight => LRI,
+                            Direction::RightToLeft => RLI,
+                        };
+                        rendered = format!("{isolate}{rendered}{PDI}");
+                    }
+                }
+                if let Some((url, title)) = frame.link {
+                    rendered = format_inline_link(&rendered, url, title);
+                }
+                match stack.last_mut() {
+                    Some(parent) => {
+                        parent.buf.push_str(&rendered);
+                        line_start = false;
+                    }
+                    None => return rendered,
+                }
+                continue;
+            };
+            match node {
+                RtfNode::Text(text) => {
+                    top.buf.push_str(&escape_markdown(text, ctx, line_start));
+                    line_start = false;
+                }
+                RtfNode::Formatted { format, content } => {
+                    if let Some(revision) = &format.revision {
+                        let keep = match (self.revision_mode, revision.kind) {
+                            (RevisionMode::Annotate, _) => true,
+                            (RevisionMode::AcceptAll, kind) => kind == RevisionKind::Inserted,
+                            (RevisionMode::RejectAll, kind) => kind == RevisionKind::Deleted,
+                        };
+                        if !keep {
+                            continue;
+                        }
+                    }
+                    line_start = false;
+                    let child_ctx = if format.style_name.as_deref() == Some("Code") {
+                        EscapeContext::Code
+                    } else {
+                        ctx
+                    };
+                    stack.push(Frame {
+                        nodes: content.iter(),
+                        buf: String::new(),
+                        wrap: Some(format),
+                        link: None,
+                        ctx: child_ctx,
+                    });
+                }
+                RtfNode::Hyperlink { url, title, content } => {
+                    line_start = false;
+                    stack.push(Frame {
+                        nodes: content.iter(),
+                        buf: String::new(),
+                        wrap: None,
+                        link: Some((url, title.as_deref())),
+                        ctx,
+                    });
+                }
+                RtfNode::Image { src, alt, title } => {
+                    top.buf
+                        .push_str(&format_inline_image(alt, src, title.as_deref()));
+                    line_start = false;
+                }
+                RtfNode::LineBreak => {
+                    match ctx {
+                        // A hard break inside a table cell would corrupt
+                        // the row; an HTML break keeps the boundary (a
+                        // plain space in legacy mode, which bans HTML).
+                        EscapeContext::TableCell => {
+                            top.buf
+                                .push_str(if self.legacy_mode { " " } else { "<br>" })
+                        }
+                        _ => top.buf.push_str("  \n"),
+                    }
+                    line_start = ctx != EscapeContext::TableCell;
+                }
+                RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => {
+                    stack.push(Frame {
+                        nodes: content.iter(),
+                        buf: String::new(),
+                        wrap: None,
+                        link: None,
+                        ctx,
+                    });
+                }
+                RtfNode::RawRtf { content } => {
+                    // Always its own fenced block, even when the node sits
+                    // in an inline position.
+                    if !line_start {
+                        top.buf.push('\n');
+                    }
+                    top.buf.push_str("```rtf-raw\n");
+                    top.buf.push_str(content);
+                    top.buf.push_str("\n```\n");
+                    line_start = true;
+                }
+                RtfNode::Table(_) | RtfNode::PageBreak => {}
+            }
+        }
+    }
+
+    /// Mark up a rendered revision run, keeping surrounding whitespace
+    /// outside the markers as [`wrap_formatting`] does.
+    fn annotate_revision(&self, inner: &str, revision: &Revision) -> String {
+        let leading: String = inner.chars().take_while(|c| c.is_whitespace()).collect();
+        let trailing: String = inner
+            .chars()
+            .rev()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        let core = inner.trim();
+        let wrapped = if self.output_flavor == OutputFlavor::HtmlAnchors && !self.legacy_mode {
+            let tag = match revision.kind {
+                RevisionKind::Inserted => "ins",
+                RevisionKind::Deleted => "del",
+            };
+            let title = [revision.author.as_deref(), revision.date.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(", ");
+            if title.is_empty() {
+                format!("<{tag}>{core}</{tag}>")
+            } else {
+                format!("<{tag} title=\"{title}\">{core}</{tag}>")
+            }
+        } else {
+            match revision.kind {
+                RevisionKind::Inserted => format!("{{++{core}++}}"),
+                RevisionKind::Deleted => format!("{{--{core}--}}"),
+            }
+        };
+        format!("{leading}{wrapped}{trailing}")
+    }
+}
+
+impl Default for MarkdownGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Plain text of heading content, for slug computation. Iterative for the
+/// same stack-safety reasons as the render walk.
+pub(crate) fn heading_text(nodes: &[RtfNode]) -> String {
+    let mut out = String::new();
+    let mut work: Vec<&RtfNode> = nodes.iter().rev().collect();
+    while let Some(node) = work.pop() {
+        match node {
+            RtfNode::Text(t) => out.push_str(t),
+            RtfNode::Formatted { content, .. }
+            | RtfNode::Hyperlink { content, .. }
+            | RtfNode::Paragraph { content, .. }
+            | RtfNode::Heading { content, .. }
+            | RtfNode::ListItem { content, .. } => work.extend(content.iter().rev()),
+            RtfNode::LineBreak => out.push(' '),
+            RtfNode::Image { .. } | RtfNode::RawRtf { .. } | RtfNode::Table(_)
+            | RtfNode::PageBreak => {}
+        }
+    }
+    out
+}
+
+/// Clear the bold flag on formatted runs (at any depth) for heading content.
+fn strip_bold(nodes: &[RtfNode]) -> Vec<RtfNode> {
+    let mut nodes = nodes.to_vec();
+    let mut work: Vec<&mut RtfNode> = nodes.iter_mut().collect();
+    while let Some(node) = work.pop() {
+        if let RtfNode::Formatted { format, content } = node {
+            format.bold = false;
+            work.extend(content.iter_mut());
+        }
+    }
+    nodes
+}
+
+/// Emit a link in the inline form, which round-trips without needing a
+/// definition block; destinations that would break the syntax go in
+/// angle brackets.
+fn format_inline_link(text: &str, url: &str, title: Option<&str>) -> String {
+    let dest = if url.is_empty() || url.chars().any(|c| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{url}>")
+    } else {
+        url.to_string()
+    };
+    match title {
+        Some(title) => format!("[{text}]({dest} \"{}\")", title.replace('"', "\\\"")),
+        None => format!("[{text}]({dest})"),
+    }
+}
+
+/// Emit an image in the inline form. Alt text lives in brackets, so
+/// brackets - and the escaping backslash itself - are escaped before the
+/// link formatting handles the destination and title.
+fn format_inline_image(alt: &str, src: &str, title: Option<&str>) -> String {
+    let alt = alt
+        .replace('\\', "\\\\")
+        .replace('[', "\\[")
+        .replace(']', "\\]");
+    format!("!{}", format_inline_link(&alt, src, title))
+}
+
+fn wrap_formatting(inner: &str, format: &TextFormat) -> String {
+    if inner.trim().is_empty() {
+        return inner.to_string();
+    }
+    // Emphasis delimiters must hug non-whitespace; keep surrounding spaces
+    // outside the markers.
+    let leading: String = inner.chars().take_while(|c| c.is_whitespace()).collect();
+    let trailing: String = inner
+        .chars()
+        .rev()
+        .take_while(|c| c.is_whitespace())
+        .collect();
+    let core = inner.trim();
+    // Known character styles map to Markdown idioms; unknown style names
+    // fall back to the resolved formatting below.
+    match format.style_name.as_deref() {
+        Some("Code") => return format!("{leading}`{core}`{trailing}"),
+        Some("Emphasis") if !format.bold && !format.italic => {
+            return format!("{leading}*{core}*{trailing}");
+        }
+        _ => {}
+    }
+    let mut wrapped = core.to_string();
+    if format.strikethrough {
+        wrapped = format!("~~{wrapped}~~");
+    }
+    if format.bold && format.italic {
+        wrapped = format!("***{wrapped}***");
+    } else if format.bold {
+        wrapped = format!("**{wrapped}**");
+    } else if format.italic {
+        wrapped = format!("*{wrapped}*");
+    }
+    if format.underline && !format.bold && !format.italic {
+        // Markdown has no underline; italics is the conventional fallback.
+        wrapped = format!("*{wrapped}*");
+    }
+    format!("{leading}{wrapped}{trailing}")
+}
+
+/// Re-flow paragraph lines at `width` columns for line-oriented diffing.
+///
+/// Breaks happen at spaces - preferring sentence boundaries - and never
+/// inside inline code spans or links. Table rows, headings, anchor lines
+/// and fenced code blocks pass through untouched, and a break is never
+/// placed where the continuation would read as a block marker. Because
+/// the Markdown parser joins paragraph lines with a space, wrapping is
+/// render-neutral: the wrapped output parses to the same document.
+pub fn wrap_markdown(markdown: &str, width: usize) -> String {
+    wrap_markdown_remapping(markdown, width, &mut [])
+}
+
+/// [`wrap_markdown`], additionally rewriting outline offsets (byte
+/// offsets at this point, all pointing at line starts of `markdown`) to
+/// the matching line starts of the wrapped text.
+fn wrap_markdown_remapping(markdown: &str, width: usize, outline: &mut [OutlineEntry]) -> String {
+    let mut out = String::with_capacity(markdown.len() + 64);
+    let mut in_fence = false;
+    let mut old_start = 0usize;
+    let mut next = 0usize;
+    for line in markdown.split_inclusive('\n') {
+        while next < outline.len() && outline[next].char_offset == old_start {
+            outline[next].char_offset = out.len();
+            next += 1;
+        }
+        old_start += line.len();
+        let (body, newline) = match line.strip_suffix('\n') {
+            Some(body) => (body, true),
+            None => (line, false),
+        };
+        let trimmed = body.trim_start();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        }
+        let exempt = in_fence
+            || trimmed.starts_with("```")
+            || trimmed.starts_with('|')
+            || trimmed.starts_with('#')
+            || trimmed.starts_with("<a id=")
+            || trimmed.starts_with("<!--");
+        if exempt || body.chars().count() <= width {
+            out.push_str(body);
+        } else {
+            out.push_str(&wrap_line(body, width));
+        }
+        if newline {
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Convert the byte offsets stashed in `char_offset` to char and UTF-16
+/// code-unit positions, with one pass over the final output.
+pub(crate) fn resolve_outline_offsets(markdown: &str, outline: &mut [OutlineEntry]) {
+    let mut next = 0usize;
+    let mut chars = 0usize;
+    let mut utf16 = 0usize;
+    for (byte_pos, c) in markdown.char_indices() {
+        while next < outline.len() && outline[next].char_offset == byte_pos {
+            outline[next].char_offset = chars;
+            outline[next].utf16_offset = utf16;
+            next += 1;
+        }
+        chars += 1;
+        utf16 += c.len_utf16();
+    }
+    for entry in &mut outline[next..] {
+        entry.char_offset = chars;
+        entry.utf16_offset = utf16;
+    }
+}
+
+/// Wrap one over-long line, breaking at the spaces [`find_break`] picks.
+fn wrap_line(line: &str, width: usize) -> String {
+    let protected = protected_ranges(line);
+    let chars: Vec<char> = line.chars().collect();
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut start = 0usize;
+    while chars.len() - start > width {
+        let Some(break_at) = find_break(&chars, start, width, &protected) else {
+            break;
+        };
+        out.extend(&chars[start..break_at]);
+        out.push('\n');
+        start = break_at + 1; // the break replaces the space
+    }
+    out.extend(&chars[start..]);
+    out
+}
+
+/// Pick the space to break at for the segment starting at `start`: the
+/// latest sentence boundary within the column, then the latest space
+/// within it, then the earliest space beyond it (for unbreakable-prefix
+/// lines). `None` when the rest of the line offers no safe break.
+fn find_break(
+    chars: &[char],
+    start: usize,
+    width: usize,
+    protected: &[(usize, usize)],
+) -> Option<usize> {
+    let in_protected =
+        |i: usize| protected.iter().any(|&(from, to)| i >= from && i <= to);
+    let is_candidate = |i: usize| {
+        chars[i] == ' '
+            && i > start
+            && chars[i - 1] != ' '
+            && chars.get(i + 1).is_some_and(|c| *c != ' ')
+            && !in_protected(i)
+            && !starts_block_marker(&chars[i + 1..])
+    };
+    let is_sentence_end = |i: usize| matches!(chars[i - 1], '.' | '!' | '?');
+
+    let limit = (start + width).min(chars.len() - 1);
+    let within = (start..=limit).rev().filter(|&i| is_candidate(i));
+    let mut latest_space = None;
+    for i in within {
+        if is_sentence_end(i) {
+            return Some(i);
+        }
+        latest_space.get_or_insert(i);
+    }
+    latest_space.or_else(|| (limit + 1..chars.len()).find(|&i| is_candidate(i)))
+}
+
+/// Character index ranges (inclusive) that must not be broken: inline
+/// code spans and link constructs.
+fn protected_ranges(line: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut ranges = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let escaped = i > 0 && chars[i - 1] == '\\';
+        match chars[i] {
+            '`' if !escaped => {
+                // Code span: to the matching unescaped backtick.
+                let mut j = i + 1;
+                while j < chars.len() && !(chars[j] == '`' && chars[j - 1] != '\\') {
+                    j += 1;
+                }
+                if j < chars.len() {
+                    ranges.push((i, j));
+                    i = j;
+                }
+            }
+            '[' if !escaped => {
+                // Link: `[text](target)` or `[text][ref]`.
+                let mut j = i + 1;
+                while j < chars.len() && chars[j] != ']' {
+                    j += 1;
+                }
+                if matches!(chars.get(j + 1), Some('(') | Some('[')) {
+                    let close = match chars[j + 1] {
+                        '(' => ')',
+                        _ => ']',
+                    };
+                    let mut k = j + 2;
+                    while k < chars.len() && chars[k] != close {
+                        k += 1;
+                    }
+                    if k < chars.len() {
+                        ranges.push((i, k));
+                        i = k;
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    ranges
+}
+
+/// Would a line starting with `rest` read as a block construct? Breaking
+/// there would change how the continuation parses.
+fn starts_block_marker(rest: &[char]) -> bool {
+    match rest.first() {
+        Some('#') | Some('>') | Some('|') => true,
+        Some(c @ '-') | Some(c @ '+') | Some(c @ '*') => {
+            matches!(rest.get(1), None | Some(' ') | Some('\t'))
+                || (*c == '-' && rest.get(1) == Some(&'-'))
+        }
+        Some('0'..='9') => {
+            let digits = rest.iter().take_while(|c| c.is_ascii_digit()).count();
+            matches!(rest.get(digits), Some('.') | Some(')'))
+        }
+        _ => false,
+    }
+}
+
+/// Escape Markdown-significant characters in literal text.
+///
+/// The escaping is context-aware rather than blanket:
+/// - emphasis markers (`*`, `_`) are escaped only where CommonMark would
+///   treat them as delimiters (intraword `_` never forms emphasis and is
+///   left alone);
+/// - block-introducing characters (`#`, `>`, `-`, `+`, `N.`) are escaped
+///   only at the start of a line;
+/// - pipes are escaped only inside table cells;
+/// - `[` is escaped only when the text could complete a link (`](`);
+/// - nothing is escaped in code context.
+pub fn escape_markdown(text: &str, ctx: EscapeContext, at_line_start: bool) -> String {
+    if ctx == EscapeContext::Code {
+        return text.to_string();
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() + 8);
+    let mut i = 0;
+    let mut line_start = at_line_start;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let prev = if i > 0 { Some(chars[i - 1]) } else { None };
+        let next = chars.get(i + 1).copied();
+
+        if line_start && !c.is_whitespace() {
+            if let Some((replacement, consumed)) = block_marker_escape(&chars[i..]) {
+                out.push_str(&replacement);
+                i += consumed;
+                line_start = false;
+                continue;
+            }
+            line_start = false;
+        }
+
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '*' => {
+                if emphasis_would_delimit(prev, next) {
+                    out.push_str("\\*");
+                } else {
+                    out.push('*');
+                }
+            }
+            '_' => {
+                // Intraword underscores cannot open or close emphasis.
+                let intraword = prev.is_some_and(|p| p.is_alphanumeric())
+                    && next.is_some_and(|n| n.is_alphanumeric());
+                if !intraword && emphasis_would_delimit(prev, next) {
+                    out.push_str("\\_");
+                } else {
+                    out.push('_');
+                }
+            }
+            '`' => out.push_str("\\`"),
+            '|' if ctx == EscapeContext::TableCell => out.push_str("\\|"),
+            '[' => {
+                // Only dangerous when the remainder can complete `[...](...)`.
+                let rest: String = chars[i + 1..].iter().collect();
+                if could_complete_link(&rest) {
+                    out.push_str("\\[");
+                } else {
+                    out.push('[');
+                }
+            }
+            '\n' => {
+                out.push('\n');
+                line_start = true;
+            }
+            _ => out.push(c),
+        }
+        i += 1;
+    }
+    out
+}
+
+/// Escape a block-introducing marker at line start, returning the
+/// replacement text and how many source characters it consumed.
+fn block_marker_escape(rest: &[char]) -> Option<(String, usize)> {
+    let c = rest[0];
+    match c {
+        '#' | '>' => Some((format!("\\{c}"), 1)),
+        '-' | '+' => {
+            // Only a list/thematic marker when followed by a space or EOL.
+            match rest.get(1) {
+                None | Some(' ') | Some('\t') | Some('\n') => Some((format!("\\{c}"), 1)),
+                Some('-') if c == '-' => Some(("\\-".to_string(), 1)),
+                _ => None,
+            }
+        }
+        '0'..='9' => {
+            // `1.` or `1)` at line start opens an ordered list.
+            let digits = rest.iter().take_while(|ch| ch.is_ascii_digit()).count();
+            match rest.get(digits) {
+                Some(p @ '.') | Some(p @ ')') => {
+                    let mut s: String = rest[..digits].iter().collect();
+                    s.push('\\');
+                    s.push(*p);
+                    Some((s, digits + 1))
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn emphasis_would_delimit(prev: Option<char>, next: Option<char>) -> bool {
+    let prev_ws = prev.is_none_or(|c| c.is_whitespace());
+    let next_ws = next.is_none_or(|c| c.is_whitespace());
+    // A run surrounded by whitespace on both sides can never open or close.
+    !(prev_ws && next_ws)
+}
+
+fn could_complete_link(rest: &str) -> bool {
+    if let Some(close) = rest.find(']') {
+        rest[close..].starts_with("](") || rest[close..].starts_with("][")
+    } else {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::lexer::tokenize;
+    use crate::conversion::rtf_parser::RtfParser;
+
+    fn convert(input: &str) -> String {
+        let doc = RtfParser::new(tokenize(input).unwrap()).parse().unwrap();
+        MarkdownGenerator::new().generate(&doc)
+    }
+
+    #[test]
+    fn generates_heading_and_paragraph() {
+        let md = convert("{\\rtf1 \\outlinelevel0 Title\\par \\pard Body text\\par}");
+        assert_eq!(md, "# Title\n\nBody text\n");
+    }
+
+    #[test]
+    fn generates_bold_and_italic() {
+        let md = convert("{\\rtf1 plain {\\b bold} {\\i italic}\\par}");
+        assert_eq!(md, "plain **bold** *italic*\n");
+    }
+
+    #[test]
+    fn generates_table() {
+        let md = convert("{\\rtf1 \\trowd\\intbl A\\cell B\\cell\\row}");
+        assert!(md.contains("| A | B |"));
+        assert!(md.contains("| --- | --- |"));
+    }
+
+    /// A three-paragraph first cell and a literal pipe in the second.
+    const MULTILINE_CELL_TABLE: &str =
+        "{\\rtf1 \\trowd\\intbl One\\par Two\\par Three\\cell a|b\\cell\\row}";
+
+    #[test]
+    fn multi_paragraph_cells_flatten_with_br_and_pipes_escape() {
+        let md = convert(MULTILINE_CELL_TABLE);
+        assert!(md.contains("| One<br>Two<br>Three | a\\|b |"), "got: {md}");
+        // One line per row: a raw break would corrupt the grid.
+        assert!(!md.contains("One\n"), "got: {md}");
+
+        // Legacy mode has no HTML; the boundaries degrade to spaces.
+        let doc = RtfParser::new(tokenize(MULTILINE_CELL_TABLE).unwrap())
+            .parse()
+            .unwrap();
+        let legacy = MarkdownGenerator::new().with_legacy_mode(true).generate(&doc);
+        assert!(legacy.contains("| One Two Three | a\\|b |"), "got: {legacy}");
+    }
+
+    #[test]
+    fn over_cap_cells_truncate_with_an_ellipsis() {
+        let doc = RtfParser::new(tokenize(MULTILINE_CELL_TABLE).unwrap())
+            .parse()
+            .unwrap();
+        let generator = MarkdownGenerator::new().with_max_cell_length(Some(8));
+        let md = generator.generate(&doc);
+        assert!(md.contains("| One<br>T… | a\\|b |"), "got: {md}");
+        assert_eq!(generator.truncated_cells(), 1);
+
+        // A fresh generate resets the count.
+        let generator = generator.with_max_cell_length(None);
+        generator.generate(&doc);
+        assert_eq!(generator.truncated_cells(), 0);
+    }
+
+    #[test]
+    fn fallback_html_tables_keeps_over_cap_cells_whole() {
+        let doc = RtfParser::new(tokenize(MULTILINE_CELL_TABLE).unwrap())
+            .parse()
+            .unwrap();
+        let generator = MarkdownGenerator::new()
+            .with_max_cell_length(Some(8))
+            .with_fallback_html_tables(true);
+        let md = generator.generate(&doc);
+        assert!(md.contains("<td>One<br>Two<br>Three</td>"), "got: {md}");
+        assert_eq!(generator.truncated_cells(), 0);
+
+        // Under the cap the grid stays GFM.
+        let generator = generator.with_max_cell_length(Some(80));
+        let md = generator.generate(&doc);
+        assert!(md.contains("| One<br>Two<br>Three | a\\|b |"), "got: {md}");
+    }
+
+    /// A 2x-colspan header row over a rowspan first column.
+    const MERGED_TABLE: &str =
+        "{\\rtf1 \\trowd\\clmgf\\cellx2400\\clmrg\\cellx4800 \\intbl Invoice\\cell\\cell\\row \
+         \\trowd\\clvmgf\\cellx2400\\cellx4800 \\intbl Acme\\cell 10\\cell\\row \
+         \\trowd\\clvmrg\\cellx2400\\cellx4800 \\intbl\\cell 20\\cell\\row}";
+
+    #[test]
+    fn merged_cells_flatten_to_an_empty_gfm_grid_cell() {
+        let md = convert(MERGED_TABLE);
+        // The span's content stays in its first cell; continuations are
+        // empty, keeping the column count uniform.
+        assert!(md.contains("| Invoice |  |"), "got: {md}");
+        assert!(md.contains("| Acme | 10 |"), "got: {md}");
+        assert!(md.contains("|  | 20 |"), "got: {md}");
+    }
+
+    #[test]
+    fn html_merged_table_style_emits_colspan_and_rowspan() {
+        let doc = RtfParser::new(tokenize(MERGED_TABLE).unwrap()).parse().unwrap();
+        let md = MarkdownGenerator::new()
+            .with_merged_table_style(MergedTableStyle::Html)
+            .generate(&doc);
+        assert!(md.contains("<td colspan=\"2\">Invoice</td>"), "got: {md}");
+        assert!(md.contains("<td rowspan=\"2\">Acme</td>"), "got: {md}");
+        // Continuation cells produce no element of their own.
+        assert_eq!(md.matches("<td").count(), 4, "got: {md}");
+
+        // Legacy mode never emits HTML, whatever the style says.
+        let legacy = MarkdownGenerator::new()
+            .with_merged_table_style(MergedTableStyle::Html)
+            .with_legacy_mode(true)
+            .generate(&doc);
+        assert!(!legacy.contains("<table>"), "got: {legacy}");
+    }
+
+    #[test]
+    fn column_alignment_maps_to_gfm_colon_syntax() {
+        let md = convert(
+            "{\\rtf1 \\trowd\\cellx2400\\cellx5000 \
+             \\intbl\\qc Item\\cell\\qr 10\\cell\\row}",
+        );
+        assert!(md.contains("| :---: | ---: |"), "got: {md}");
+    }
+
+    #[test]
+    fn width_comments_record_column_bounds() {
+        let src = "{\\rtf1 \\trowd\\cellx2400\\cellx5000 \\intbl A\\cell B\\cell\\row}";
+        let doc = RtfParser::new(tokenize(src).unwrap()).parse().unwrap();
+        let md = MarkdownGenerator::new()
+            .with_width_comments(true)
+            .generate(&doc);
+        assert!(md.contains("<!-- widths: 2400,5000 -->"), "got: {md}");
+
+        // Off by default, and suppressed in legacy mode.
+        assert!(!convert(src).contains("widths"), "got: {}", convert(src));
+        let legacy = MarkdownGenerator::new()
+            .with_width_comments(true)
+            .with_legacy_mode(true)
+            .generate(&doc);
+        assert!(!legacy.contains("widths"), "got: {legacy}");
+    }
+
+    #[test]
+    fn escapes_leading_block_markers() {
+        let md = convert("{\\rtf1 # not a heading\\par}");
+        assert!(md.starts_with("\\# not a heading"), "got: {md}");
+        let md = convert("{\\rtf1 > not a quote\\par}");
+        assert!(md.starts_with("\\> not a quote"), "got: {md}");
+        let md = convert("{\\rtf1 - not a list\\par}");
+        assert!(md.starts_with("\\- not a list"), "got: {md}");
+    }
+
+    #[test]
+    fn escapes_ordered_list_marker() {
+        let md = convert("{\\rtf1 1. not a list\\par}");
+        assert!(md.starts_with("1\\. not a list"), "got: {md}");
+    }
+
+    #[test]
+    fn leaves_mid_line_digits_alone() {
+        let md = convert("{\\rtf1 version 2. is out\\par}");
+        assert!(md.contains("version 2. is out"), "got: {md}");
+    }
+
+    #[test]
+    fn escapes_emphasis_markers_that_would_delimit() {
+        let md = convert("{\\rtf1 a *starred* word\\par}");
+        assert!(md.contains("a \\*starred\\* word"), "got: {md}");
+    }
+
+    #[test]
+    fn keeps_intraword_underscores() {
+        let md = convert("{\\rtf1 snake_case_name\\par}");
+        assert!(md.contains("snake_case_name"), "got: {md}");
+    }
+
+    #[test]
+    fn escapes_boundary_underscores() {
+        let md = convert("{\\rtf1 an _emphasized_ word\\par}");
+        assert!(md.contains("an \\_emphasized\\_ word"), "got: {md}");
+    }
+
+    #[test]
+    fn escapes_pipes_in_table_cells() {
+        let md = convert("{\\rtf1 \\trowd\\intbl a|b\\cell c\\cell\\row}");
+        assert!(md.contains("a\\|b"), "got: {md}");
+    }
+
+    #[test]
+    fn pipes_outside_tables_untouched() {
+        let md = convert("{\\rtf1 a|b in prose\\par}");
+        assert!(md.contains("a|b in prose"), "got: {md}");
+    }
+
+    #[test]
+    fn escapes_link_looking_brackets() {
+        let md = convert("{\\rtf1 [click here](danger)\\par}");
+        assert!(md.contains("\\[click here](danger)"), "got: {md}");
+    }
+
+    #[test]
+    fn plain_brackets_untouched() {
+        let md = convert("{\\rtf1 array[0] access\\par}");
+        assert!(md.contains("array[0] access"), "got: {md}");
+    }
+
+    #[test]
+    fn code_context_escapes_nothing() {
+        assert_eq!(
+            escape_markdown("*raw* _text_ # | [x](y)", EscapeContext::Code, true),
+            "*raw* _text_ # | [x](y)"
+        );
+    }
+
+    /// Mixed-direction fixture: an Arabic sentence with an embedded Latin
+    /// product code. Expected output captured from manual verification in a
+    /// bidi-aware terminal: the paragraph is isolated RTL and the product
+    /// code is isolated LTR inside it.
+    #[test]
+    fn rtl_paragraph_with_ltr_run_uses_isolates() {
+        let md = convert(
+            "{\\rtf1 \\rtlpar \\u1575?\\u1604?\\u1605?\\u1606?\\u1578?\\u1580? \
+             {\\ltrch ABC-123} \\u1580?\\u1575?\\u1607?\\u1586?\\par}",
+        );
+        assert_eq!(
+            md.trim_end(),
+            "\u{2067}\u{627}\u{644}\u{645}\u{646}\u{62a}\u{62c} \u{2066}ABC-123\u{2069} \u{62c}\u{627}\u{647}\u{632}\u{2069}"
+        );
+    }
+
+    #[test]
+    fn rtl_html_div_style() {
+        let rtf = "{\\rtf1 \\rtlpar \\u1588?\\u1576?\\u1603?\\u1577?\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let md = MarkdownGenerator::new()
+            .with_rtl_style(RtlStyle::HtmlDiv)
+            .generate(&doc);
+        assert_eq!(md.trim_end(), "<div dir=\"rtl\">\u{634}\u{628}\u{643}\u{629}</div>");
+    }
+
+    #[test]
+    fn ltr_documents_stay_free_of_direction_marks() {
+        let md = convert("{\\rtf1 plain text\\par}");
+        assert!(!md.contains('\u{2066}'));
+        assert!(!md.contains('\u{2067}'));
+    }
+
+    #[test]
+    fn slugs_are_github_compatible() {
+        let mut slugger = Slugger::new(false);
+        assert_eq!(slugger.slug("Getting Started"), "getting-started");
+        assert_eq!(slugger.slug("What's new?"), "whats-new");
+        assert_eq!(slugger.slug("snake_case and-dashes"), "snake_case-and-dashes");
+    }
+
+    #[test]
+    fn duplicate_headings_get_numbered_slugs() {
+        let mut slugger = Slugger::new(false);
+        assert_eq!(slugger.slug("Setup"), "setup");
+        assert_eq!(slugger.slug("Setup"), "setup-1");
+        assert_eq!(slugger.slug("Setup"), "setup-2");
+    }
+
+    #[test]
+    fn punctuation_only_headings_get_a_fallback_slug() {
+        let mut slugger = Slugger::new(false);
+        assert_eq!(slugger.slug("???"), "section");
+        assert_eq!(slugger.slug("!!!"), "section-1");
+    }
+
+    #[test]
+    fn slugs_preserve_or_transliterate_unicode() {
+        assert_eq!(Slugger::new(false).slug("Café Menü"), "café-menü");
+        assert_eq!(Slugger::new(true).slug("Café Menü"), "cafe-menu");
+    }
+
+    #[test]
+    fn attr_list_flavor_emits_slug_attributes() {
+        let rtf = "{\\rtf1 \\outlinelevel0 Setup\\par \\pard \\outlinelevel0 Setup\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let md = MarkdownGenerator::new()
+            .with_output_flavor(OutputFlavor::AttrList)
+            .generate(&doc);
+        assert!(md.contains("# Setup {#setup}"), "got: {md}");
+        assert!(md.contains("# Setup {#setup-1}"), "got: {md}");
+    }
+
+    #[test]
+    fn html_anchor_flavor_emits_anchor_elements() {
+        let rtf = "{\\rtf1 \\outlinelevel0 Getting Started\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let md = MarkdownGenerator::new()
+            .with_output_flavor(OutputFlavor::HtmlAnchors)
+            .generate(&doc);
+        assert!(
+            md.contains("<a id=\"getting-started\"></a>\n# Getting Started"),
+            "got: {md}"
+        );
+    }
+
+    #[test]
+    fn outline_matches_generated_anchors() {
+        let rtf = "{\\rtf1 \\outlinelevel0 Intro\\par \\pard \\outlinelevel1 Intro\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let outline = MarkdownGenerator::new().outline(&doc);
+        assert_eq!(
+            outline,
+            vec![
+                OutlineEntry {
+                    text: "Intro".to_string(),
+                    level: 1,
+                    slug: "intro".to_string(),
+                    char_offset: 0,
+                    utf16_offset: 0,
+                },
+                OutlineEntry {
+                    text: "Intro".to_string(),
+                    level: 2,
+                    slug: "intro-1".to_string(),
+                    // After "# Intro\n\n".
+                    char_offset: 9,
+                    utf16_offset: 9,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn outline_offsets_count_chars_and_utf16_units() {
+        // The clef is outside the BMP (2 UTF-16 units), the e-acute
+        // inside it, so all three counts (bytes, chars, UTF-16) differ.
+        let rtf = "{\\rtf1 \\outlinelevel0 \u{1d11e} caf\\u233?\\par \
+                   \\pard \\outlinelevel1 Next\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let (md, outline) = MarkdownGenerator::new().generate_with_outline(&doc);
+        assert!(md.starts_with("# \u{1d11e} café\n\n## Next"), "got: {md}");
+        assert_eq!(outline.len(), 2);
+        // "# X café\n\n" is 10 chars but 11 UTF-16 units.
+        assert_eq!(outline[1].char_offset, 10);
+        assert_eq!(outline[1].utf16_offset, 11);
+        assert_eq!(md.chars().nth(outline[1].char_offset), Some('#'));
+    }
+
+    #[test]
+    fn outline_offsets_survive_wrapping() {
+        let rtf = "{\\rtf1 \\outlinelevel0 Intro\\par \\pard a long opening \
+                   paragraph that will certainly be wrapped at the narrow width \
+                   chosen below\\par \\outlinelevel1 Details\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let (md, outline) = MarkdownGenerator::new()
+            .with_wrap_width(Some(20))
+            .generate_with_outline(&doc);
+        assert_eq!(outline.len(), 2);
+        let offset = outline[1].char_offset;
+        assert_eq!(md.chars().nth(offset), Some('#'), "got: {md}");
+        assert!(md[..offset].matches('\n').count() > 3, "paragraph wrapped");
+    }
+
+    #[test]
+    fn outline_ignores_hash_text_that_is_not_a_heading() {
+        // A paragraph starting with '#' is escaped text, not a heading;
+        // the outline must come from the document model, not a scrape.
+        let rtf = "{\\rtf1 \\outlinelevel0 Real\\par \\pard # fake heading\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let outline = MarkdownGenerator::new().outline(&doc);
+        assert_eq!(outline.len(), 1);
+        assert_eq!(outline[0].text, "Real");
+    }
+
+    #[test]
+    fn code_character_style_becomes_backticks() {
+        // Word-exported fixture with a custom "Code" character style; the
+        // styled run must come through verbatim inside a code span.
+        let md = convert(
+            "{\\rtf1{\\stylesheet{\\*\\cs16\\f1\\fs20 Code;}}\
+             Call {\\cs16 ptr->next_item} here\\par}",
+        );
+        assert!(md.contains("Call `ptr->next_item` here"), "got: {md}");
+    }
+
+    #[test]
+    fn unknown_character_style_falls_back_to_formatting() {
+        let md = convert(
+            "{\\rtf1{\\stylesheet{\\*\\cs20\\b Strong;}}a {\\cs20 loud} word\\par}",
+        );
+        assert!(md.contains("a **loud** word"), "got: {md}");
+    }
+
+    /// Word tracked-changes export: one insertion with author and date,
+    /// one deletion by the same reviewer.
+    const TRACKED: &str = "{\\rtf1{\\*\\revtbl {Unknown;}{Alice Smith;}}\
+         Base {\\revised\\revauth1\\revdttm130235269 inserted}\
+         {\\deleted\\revauthdel1 removed} tail\\par}";
+
+    fn convert_revisions(mode: RevisionMode, flavor: OutputFlavor) -> String {
+        let doc = RtfParser::new(tokenize(TRACKED).unwrap()).parse().unwrap();
+        MarkdownGenerator::new()
+            .with_revision_mode(mode)
+            .with_output_flavor(flavor)
+            .generate(&doc)
+    }
+
+    #[test]
+    fn accept_all_keeps_insertions_and_drops_deletions() {
+        // AcceptAll is the default: plain conversion sees the document as
+        // the reviewers left it.
+        assert_eq!(convert(TRACKED).trim_end(), "Base inserted tail");
+    }
+
+    #[test]
+    fn reject_all_restores_the_pre_review_text() {
+        let md = convert_revisions(RevisionMode::RejectAll, OutputFlavor::Gfm);
+        assert_eq!(md.trim_end(), "Base removed tail");
+    }
+
+    #[test]
+    fn annotate_emits_critic_markup() {
+        let md = convert_revisions(RevisionMode::Annotate, OutputFlavor::Gfm);
+        assert_eq!(md.trim_end(), "Base {++inserted++}{--removed--} tail");
+    }
+
+    #[test]
+    fn annotate_html_flavor_carries_author_and_date() {
+        let md = convert_revisions(RevisionMode::Annotate, OutputFlavor::HtmlAnchors);
+        assert_eq!(
+            md.trim_end(),
+            "Base <ins title=\"Alice Smith, 2024-03-07 14:05\">inserted</ins>\
+             <del title=\"Alice Smith\">removed</del> tail"
+        );
+    }
+
+    #[test]
+    fn legacy_mode_avoids_html_and_extended_syntax() {
+        let src = "{\\rtf1 \\outlinelevel0 Title\\par \\pard \
+                   \\strike old\\strike0  stays\\par}";
+        let doc = RtfParser::new(tokenize(src).unwrap()).parse().unwrap();
+
+        let modern = MarkdownGenerator::new()
+            .with_output_flavor(OutputFlavor::HtmlAnchors)
+            .generate(&doc);
+        assert!(modern.contains("<a id=\"title\"></a>"), "{modern}");
+        assert!(modern.contains("~~old~~"), "{modern}");
+
+        let legacy = MarkdownGenerator::new()
+            .with_output_flavor(OutputFlavor::HtmlAnchors)
+            .with_legacy_mode(true)
+            .generate(&doc);
+        assert!(!legacy.contains('<'), "no HTML in legacy mode: {legacy}");
+        assert!(!legacy.contains("~~"), "no GFM strike in legacy mode: {legacy}");
+        assert!(legacy.contains("old stays"), "struck text stays plain: {legacy}");
+    }
+
+    #[test]
+    fn spacing_comments_are_opt_in() {
+        let src = "{\\rtf1 \\sb240\\sa120\\sl276\\slmult1 spaced\\par \\pard plain\\par}";
+        let doc = RtfParser::new(tokenize(src).unwrap()).parse().unwrap();
+
+        let md = MarkdownGenerator::new().generate(&doc);
+        assert!(!md.contains("<!--"), "dropped by default: {md}");
+
+        let md = MarkdownGenerator::new()
+            .with_spacing_comments(true)
+            .generate(&doc);
+        assert!(
+            md.contains("<!-- spacing: sb=240 sa=120 sl=276 slmult=1 -->\nspaced"),
+            "{md}"
+        );
+        // Only explicitly spaced blocks get a comment.
+        assert_eq!(md.matches("<!--").count(), 1, "{md}");
+    }
+
+    #[test]
+    fn spacing_comments_record_break_and_keep_flags() {
+        let src = "{\\rtf1 \\pagebb\\keepn Section\\par \\pard body\\par}";
+        let doc = RtfParser::new(tokenize(src).unwrap()).parse().unwrap();
+        let md = MarkdownGenerator::new()
+            .with_spacing_comments(true)
+            .generate(&doc);
+        assert!(md.contains("<!-- spacing: pagebb=1 keepn=1 -->\nSection"), "{md}");
+        assert_eq!(md.matches("<!--").count(), 1, "{md}");
+    }
+
+    #[test]
+    fn wrapping_prefers_sentence_boundaries() {
+        let wrapped = wrap_markdown(
+            "First sentence ends here. Second part continues with more words beyond.\n",
+            40,
+        );
+        let lines: Vec<&str> = wrapped.lines().collect();
+        assert_eq!(lines[0], "First sentence ends here.");
+        assert!(lines.iter().all(|l| l.chars().count() <= 40), "{wrapped}");
+    }
+
+    #[test]
+    fn wrapping_never_breaks_code_spans_or_table_rows() {
+        let wrapped = wrap_markdown("pre `a very long code span here` post\n", 15);
+        assert!(
+            wrapped.contains("`a very long code span here`"),
+            "{wrapped}"
+        );
+
+        let row = "| a very long cell | another very long cell |\n";
+        assert_eq!(wrap_markdown(row, 15), row);
+    }
+
+    #[test]
+    fn wrapping_does_not_create_block_markers() {
+        // The break before "- not" must be rejected: the continuation
+        // would otherwise parse as a list item.
+        let wrapped = wrap_markdown("a phrase with a dash - not a list item at all\n", 21);
+        assert!(
+            wrapped.lines().all(|l| !l.starts_with("- ")),
+            "{wrapped}"
+        );
+    }
+
+    #[test]
+    fn wrapping_is_render_neutral() {
+        use crate::conversion::markdown_parser::MarkdownParser;
+
+        let rtf = "{\\rtf1 \\outlinelevel0 Quarterly Report\\par \\pard \
+                   The first quarter closed above plan. Revenue grew in \
+                   every region, with the {\\b largest gains} in the \
+                   legacy product line that we had planned to retire.\\par}";
+        let doc = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        let plain = MarkdownGenerator::new().generate(&doc);
+        let wrapped = MarkdownGenerator::new()
+            .with_wrap_width(Some(40))
+            .generate(&doc);
+        assert!(wrapped.lines().count() > plain.lines().count(), "{wrapped}");
+        assert_eq!(
+            MarkdownParser::new().parse(&wrapped).unwrap(),
+            MarkdownParser::new().parse(&plain).unwrap()
+        );
+    }
+
+    /// A fixture whose text is literally a Markdown tutorial: every character
+    /// must survive as literal text after conversion.
+    #[test]
+    fn markdown_tutorial_fixture_renders_literally() {
+        let rtf = "{\\rtf1 # Headings use hashes\\par \
+                   Use *asterisks* or _underscores_ for emphasis\\par \
+                   - dashes make lists\\par \
+                   1. numbers make ordered lists\\par \
+                   > greater-than quotes text\\par \
+                   [links](look) like this\\par}";
+        let md = convert(rtf);
+        assert!(md.contains("\\# Headings use hashes"));
+        assert!(md.contains("\\*asterisks\\*"));
+        assert!(md.contains("\\_underscores\\_"));
+        assert!(md.contains("\\- dashes make lists"));
+        assert!(md.contains("1\\. numbers make ordered lists"));
+        assert!(md.contains("\\> greater-than quotes text"));
+        assert!(md.contains("\\[links](look) like this"));
+    }
+}
@@ -0,0 +1,1405 @@
+//! Markdown parser.
+//!
+//! Parses GitHub-flavored Markdown into the same [`RtfDocument`] tree the
+//! RTF parser produces, so both generators share one document model.
+
+use super::language;
+use super::rtf_parser::{
+    CellAlignment, Direction, DocumentMetadata, ParagraphSpacing, RtfDocument, RtfNode, Table,
+    TableCell, TableRow, TextFormat,
+};
+use super::normalization::{self, NormalizationForm};
+use super::unicode_hygiene;
+use crate::security::{InputValidator, UnicodeHygiene};
+use std::collections::HashMap;
+
+/// Collected link reference definitions: normalized label to destination
+/// and optional title.
+type LinkDefs = HashMap<String, (String, Option<String>)>;
+
+pub struct MarkdownParser {
+    /// Base paragraph direction; front matter `direction: rtl` overrides it.
+    direction: Direction,
+    /// Document language as a BCP 47 tag; front matter `lang: es-ES`
+    /// overrides it. Carried into [`DocumentMetadata::language`] so the
+    /// RTF generator can emit `\deflang`.
+    language: Option<String>,
+    /// Accept ```` ```rtf-raw ```` fenced blocks as verbatim RTF
+    /// passthrough regions. Off by default: raw RTF is an escape hatch
+    /// the caller must opt into, not something untrusted Markdown gets
+    /// for free.
+    allow_raw_rtf: bool,
+    /// Shift parsed heading levels by this many steps, clamping to
+    /// 1..=6 with a warning when clamping flattens structure; see
+    /// [`with_heading_offset`](Self::with_heading_offset).
+    heading_offset: i8,
+    /// Per-class policies for zero-width and bidi control characters,
+    /// applied to the finished document's text nodes.
+    hygiene: UnicodeHygiene,
+    /// Normalization form the finished document's text nodes are brought
+    /// into, after the hygiene scrub.
+    normalization: NormalizationForm,
+    /// Normalize `"Code"`-styled runs too (default: off).
+    normalize_code: bool,
+}
+
+impl MarkdownParser {
+    pub fn new() -> Self {
+        MarkdownParser {
+            direction: Direction::default(),
+            language: None,
+            allow_raw_rtf: false,
+            heading_offset: 0,
+            hygiene: UnicodeHygiene::default(),
+            normalization: NormalizationForm::default(),
+            normalize_code: false,
+        }
+    }
+
+    /// Set the base paragraph direction for documents without front matter.
+    pub fn with_direction(mut self, direction: Direction) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Set the document language (a BCP 47 tag such as `es-ES`) for
+    /// documents without a `lang:` front-matter key. A tag with no known
+    /// LCID is kept in the metadata but draws a warning, since the RTF
+    /// generator will not be able to turn it into `\deflang`.
+    pub fn with_language(mut self, tag: impl Into<String>) -> Self {
+        self.language = Some(tag.into());
+        self
+    }
+
+    /// Accept ```` ```rtf-raw ```` fences as raw RTF passthrough blocks.
+    /// Their contents are validated (balanced braces, the security deny
+    /// list) and then emitted verbatim by the RTF generator. Without the
+    /// opt-in such fences are ordinary paragraph text.
+    pub fn with_raw_rtf(mut self, allow: bool) -> Self {
+        self.allow_raw_rtf = allow;
+        self
+    }
+
+    /// Shift heading levels by `offset` steps, for embedding the
+    /// generated RTF under an existing document's hierarchy: positive
+    /// demotes (`#` becomes `##` under `+1`), negative promotes. Levels
+    /// are clamped to 1..=6, with a warning per clamped heading. The
+    /// mirror option for the RTF-to-Markdown direction is
+    /// [`PipelineConfig::heading_offset`](super::pipeline::PipelineConfig::heading_offset).
+    pub fn with_heading_offset(mut self, offset: i8) -> Self {
+        self.heading_offset = offset;
+        self
+    }
+
+    /// Replace the default [`UnicodeHygiene`] policy applied to text
+    /// nodes (default from
+    /// [`SecurityLimits`](crate::security::SecurityLimits): strip bidi
+    /// overrides and zero-width characters outside emoji sequences).
+    pub fn with_unicode_hygiene(mut self, hygiene: UnicodeHygiene) -> Self {
+        self.hygiene = hygiene;
+        self
+    }
+
+    /// Select the [`NormalizationForm`] text nodes are brought into
+    /// (default: NFC, so decomposed and composed sources produce
+    /// byte-identical output for identical text).
+    pub fn with_normalization(mut self, form: NormalizationForm) -> Self {
+        self.normalization = form;
+        self
+    }
+
+    /// Normalize runs carrying the `"Code"` character style too
+    /// (default: off; normalization can change what quoted source code
+    /// means, so code is left exactly as written).
+    pub fn with_code_normalization(mut self, normalize: bool) -> Self {
+        self.normalize_code = normalize;
+        self
+    }
+
+    pub fn parse(&self, input: &str) -> Result<RtfDocument, String> {
+        self.parse_with_warnings(input).map(|(document, _)| document)
+    }
+
+    /// Parse, returning the document together with warnings for
+    /// annotations that were recognized but ignored as invalid.
+    pub fn parse_with_warnings(&self, input: &str) -> Result<(RtfDocument, Vec<String>), String> {
+        let (input, front) = match parse_front_matter(input) {
+            Some((rest, front)) => (rest, front),
+            None => (input, FrontMatter::default()),
+        };
+        let direction = front.direction.unwrap_or(self.direction);
+        let language = front.language.or_else(|| self.language.clone());
+        let mut content = Vec::new();
+        let mut warnings = Vec::new();
+        if let Some(tag) = &language {
+            if language::lcid_for_tag(tag).is_none() {
+                warnings.push(format!(
+                    "language '{tag}' has no known Windows LCID; \\deflang will be omitted"
+                ));
+            }
+        }
+        let mut paragraph_lines: Vec<&str> = Vec::new();
+        let mut table_lines: Vec<&str> = Vec::new();
+        // Column boundaries from a `<!-- widths: ... -->` annotation,
+        // consumed by the next table.
+        let mut pending_widths: Option<Vec<i32>> = None;
+        // A `<!-- page-break -->` annotation, consumed by the next
+        // paragraph or heading as `\pagebb`. A `Cell` so the
+        // `flush_paragraph` closure below can take it through a shared
+        // borrow.
+        let pending_page_break = std::cell::Cell::new(false);
+        // Lines of an open ```rtf-raw fence; `Some` while inside one.
+        let mut raw_lines: Option<Vec<&str>> = None;
+
+        // Link reference definitions are collected up front and removed
+        // from the content flow, so references resolve regardless of
+        // where in the document their definition sits.
+        let mut link_defs = LinkDefs::new();
+        for line in input.lines() {
+            if let Some((label, url, title)) = parse_link_definition(line) {
+                // The first definition of a label wins, per CommonMark.
+                link_defs.entry(label).or_insert((url, title));
+            }
+        }
+
+        let flush_paragraph =
+            |lines: &mut Vec<&str>, content: &mut Vec<RtfNode>, warnings: &mut Vec<String>| {
+                if lines.is_empty() {
+                    return;
+                }
+                let text = lines.join(" ");
+                lines.clear();
+                let inline = parse_inline(&text, &link_defs, warnings);
+                if !inline.is_empty() {
+                    content.push(RtfNode::Paragraph {
+                        direction,
+                        spacing: ParagraphSpacing {
+                            page_break_before: pending_page_break.take(),
+                            ..Default::default()
+                        },
+                        content: inline,
+                    });
+                }
+            };
+
+        for line in input.lines() {
+            let trimmed = line.trim_end();
+            if let Some(lines) = raw_lines.as_mut() {
+                if trimmed.trim() == "```" {
+                    let raw = lines.join("\n");
+                    raw_lines = None;
+                    validate_raw_rtf(&raw)?;
+                    content.push(RtfNode::RawRtf { content: raw });
+                } else {
+                    // Verbatim: no trimming inside the fence.
+                    lines.push(line);
+                }
+                continue;
+            }
+            if self.allow_raw_rtf && trimmed.trim() == "```rtf-raw" {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                flush_table(
+                    &mut table_lines,
+                    &mut content,
+                    &mut pending_widths,
+                    &link_defs,
+                    &mut warnings,
+                );
+                raw_lines = Some(Vec::new());
+                continue;
+            }
+            if trimmed.trim_start().starts_with('|') {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                table_lines.push(trimmed);
+                continue;
+            }
+            flush_table(
+                &mut table_lines,
+                &mut content,
+                &mut pending_widths,
+                &link_defs,
+                &mut warnings,
+            );
+            if parse_link_definition(trimmed).is_some() {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                continue;
+            }
+            if let Some(result) = parse_widths_annotation(trimmed) {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                match result {
+                    Ok(widths) => pending_widths = Some(widths),
+                    Err(reason) => {
+                        warnings.push(format!("widths annotation ignored: {reason}"));
+                    }
+                }
+                continue;
+            }
+            if is_page_break_annotation(trimmed) {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                pending_page_break.set(true);
+                continue;
+            }
+            if trimmed.trim().is_empty() {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                continue;
+            }
+            if let Some((level, text)) = parse_heading(trimmed) {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                let shifted = level as i32 + self.heading_offset as i32;
+                let bounded = shifted.clamp(1, 6);
+                if bounded != shifted {
+                    warnings.push(format!(
+                        "heading level {level} clamped to {bounded} under heading offset {:+}",
+                        self.heading_offset
+                    ));
+                }
+                content.push(RtfNode::Heading {
+                    level: bounded as u8,
+                    spacing: ParagraphSpacing {
+                        page_break_before: pending_page_break.take(),
+                        ..Default::default()
+                    },
+                    content: parse_inline(text, &link_defs, &mut warnings),
+                });
+                continue;
+            }
+            if let Some((ordered, text)) = parse_list_item(trimmed) {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                content.push(RtfNode::ListItem {
+                    ordered,
+                    level: 0,
+                    content: parse_inline(text, &link_defs, &mut warnings),
+                });
+                continue;
+            }
+            if is_thematic_break(trimmed) {
+                flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+                content.push(RtfNode::PageBreak);
+                continue;
+            }
+            paragraph_lines.push(trimmed);
+        }
+        if raw_lines.is_some() {
+            return Err("unterminated ```rtf-raw block".to_string());
+        }
+        flush_paragraph(&mut paragraph_lines, &mut content, &mut warnings);
+        flush_table(
+            &mut table_lines,
+            &mut content,
+            &mut pending_widths,
+            &link_defs,
+            &mut warnings,
+        );
+        if pending_page_break.get() {
+            warnings
+                .push("page-break annotation ignored: no following paragraph or heading".to_string());
+        }
+
+        let mut document = RtfDocument {
+            metadata: DocumentMetadata {
+                language,
+                ..Default::default()
+            },
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content,
+        };
+        unicode_hygiene::scrub(&mut document, &self.hygiene, &mut warnings)?;
+        normalization::normalize(&mut document, self.normalization, self.normalize_code);
+
+        Ok((
+            document,
+            warnings,
+        ))
+    }
+}
+
+impl Default for MarkdownParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Validate one raw RTF block before it passes through verbatim: braces
+/// must balance (respecting `\{`/`\}` escapes), and the content must
+/// clear the default security validator - deny-listed control words,
+/// nesting depth, input size. Raw passthrough bypasses the RTF parser,
+/// so this is the only gate the content crosses.
+fn validate_raw_rtf(content: &str) -> Result<(), String> {
+    let mut depth = 0i64;
+    let bytes = content.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        if depth < 0 {
+            break;
+        }
+        i += 1;
+    }
+    if depth != 0 {
+        return Err("rtf-raw block rejected: unbalanced braces".to_string());
+    }
+    InputValidator::with_defaults()
+        .validate_rtf_input(content)
+        .map_err(|reason| format!("rtf-raw block rejected: {reason}"))
+}
+
+/// Keys a front matter block can declare.
+#[derive(Default)]
+struct FrontMatter {
+    direction: Option<Direction>,
+    language: Option<String>,
+}
+
+/// Recognize a minimal YAML front matter block and extract the keys we
+/// understand (`direction`, `lang`). Returns the remaining input and the
+/// declared keys, or `None` when there is no front matter (or it declares
+/// no key we understand), in which case the block stays in the content.
+fn parse_front_matter(input: &str) -> Option<(&str, FrontMatter)> {
+    let rest = input.strip_prefix("---\n")?;
+    let end = rest.find("\n---")?;
+    let (block, mut remainder) = rest.split_at(end);
+    remainder = remainder.strip_prefix("\n---")?;
+    remainder = remainder.strip_prefix('\n').unwrap_or(remainder);
+    let mut front = FrontMatter::default();
+    for line in block.lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            match key.trim() {
+                "direction" => {
+                    front.direction = match value.trim() {
+                        "rtl" => Some(Direction::RightToLeft),
+                        "ltr" => Some(Direction::LeftToRight),
+                        _ => continue,
+                    };
+                }
+                "lang" => {
+                    let value = value.trim();
+                    if !value.is_empty() {
+                        front.language = Some(value.to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    if front.direction.is_none() && front.language.is_none() {
+        return None;
+    }
+    Some((remainder, front))
+}
+
+/// Collect accumulated `|`-prefixed lines into a table node. The header
+/// separator row (`| --- |`) contributes per-column alignment from its
+/// colon syntax, applied to every cell in the column; a pending widths
+/// annotation becomes the rows' column boundaries.
+fn flush_table(
+    lines: &mut Vec<&str>,
+    content: &mut Vec<RtfNode>,
+    widths: &mut Option<Vec<i32>>,
+    defs: &LinkDefs,
+    warnings: &mut Vec<String>,
+) {
+    if lines.is_empty() {
+        return;
+    }
+    // The separator follows the header row but aligns the whole column,
+    // header included, so resolve it before building any cells.
+    let alignments: Vec<CellAlignment> = lines
+        .iter()
+        .find(|line| is_table_separator(line))
+        .map(|line| {
+            split_table_row(line)
+                .iter()
+                .map(|cell| separator_alignment(cell))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut rows = Vec::new();
+    for line in lines.drain(..) {
+        if is_table_separator(line) {
+            continue;
+        }
+        let cells = split_table_row(line)
+            .into_iter()
+            .enumerate()
+            .map(|(col, cell)| TableCell {
+                content: parse_cell(cell.trim(), defs, warnings),
+                alignment: alignments.get(col).copied().unwrap_or_default(),
+                ..TableCell::default()
+            })
+            .collect();
+        rows.push(TableRow {
+            cells,
+            col_bounds: widths.clone().unwrap_or_default(),
+        });
+    }
+    if !rows.is_empty() {
+        *widths = None;
+        content.push(RtfNode::Table(Table { rows }));
+    }
+}
+
+/// One cell's inline content. `<br>` separators - how the generator
+/// flattens multi-paragraph cells - become line breaks inside the cell,
+/// so the RTF direction regenerates the intra-cell boundaries.
+fn parse_cell(cell: &str, defs: &LinkDefs, warnings: &mut Vec<String>) -> Vec<RtfNode> {
+    let mut content = Vec::new();
+    for (i, segment) in cell.split("<br>").enumerate() {
+        if i > 0 {
+            content.push(RtfNode::LineBreak);
+        }
+        content.extend(parse_inline(segment.trim(), defs, warnings));
+    }
+    content
+}
+
+/// Alignment declared by one separator cell: `:---:` centers, `---:`
+/// right-aligns, anything else is left.
+fn separator_alignment(cell: &str) -> CellAlignment {
+    let trimmed = cell.trim();
+    match (trimmed.starts_with(':'), trimmed.ends_with(':')) {
+        (true, true) => CellAlignment::Center,
+        (false, true) => CellAlignment::Right,
+        _ => CellAlignment::Left,
+    }
+}
+
+/// Recognize a `<!-- widths: 2400,4800 -->` annotation line. Returns
+/// `None` for anything else, and `Err` when the annotation is present
+/// but malformed: non-numeric, non-positive, or not strictly increasing
+/// (`\cellx` positions are cumulative).
+fn parse_widths_annotation(line: &str) -> Option<Result<Vec<i32>, String>> {
+    let inner = line
+        .trim()
+        .strip_prefix("<!-- widths:")?
+        .strip_suffix("-->")?
+        .trim();
+    let mut widths = Vec::new();
+    for part in inner.split(',') {
+        match part.trim().parse::<i32>() {
+            Ok(value) if value > 0 => widths.push(value),
+            _ => return Some(Err(format!("'{}' is not a positive twip count", part.trim()))),
+        }
+    }
+    if widths.windows(2).any(|w| w[0] >= w[1]) {
+        return Some(Err("boundaries must be strictly increasing".to_string()));
+    }
+    Some(Ok(widths))
+}
+
+/// Recognize a `<!-- page-break -->` annotation line; the next paragraph
+/// or heading starts on a new page (`\pagebb`). Distinct from a `---`
+/// thematic break, which becomes a free-standing `\page` boundary.
+fn is_page_break_annotation(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed
+        .strip_prefix("<!--")
+        .and_then(|rest| rest.strip_suffix("-->"))
+        .is_some_and(|inner| inner.trim() == "page-break")
+}
+
+pub(crate) fn is_table_separator(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.contains('-')
+        && trimmed
+            .chars()
+            .all(|c| matches!(c, '|' | '-' | ':' | ' '))
+}
+
+/// Split a table row on unescaped pipes; `\|` stays inside its cell for
+/// [`parse_inline`] to unescape.
+pub(crate) fn split_table_row(line: &str) -> Vec<String> {
+    let inner = line.trim().trim_start_matches('|');
+    let inner = inner.strip_suffix('|').unwrap_or(inner);
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                cell.push(c);
+                if let Some(next) = chars.next() {
+                    cell.push(next);
+                }
+            }
+            '|' => cells.push(std::mem::take(&mut cell)),
+            _ => cell.push(c),
+        }
+    }
+    cells.push(cell);
+    cells
+}
+
+pub(crate) fn parse_heading(line: &str) -> Option<(u8, &str)> {
+    let hashes = line.bytes().take_while(|&b| b == b'#').count();
+    if (1..=6).contains(&hashes) {
+        let rest = &line[hashes..];
+        if let Some(text) = rest.strip_prefix(' ') {
+            return Some((hashes as u8, text.trim_start()));
+        }
+        if rest.is_empty() {
+            return Some((hashes as u8, ""));
+        }
+    }
+    None
+}
+
+fn parse_list_item(line: &str) -> Option<(bool, &str)> {
+    let trimmed = line.trim_start();
+    for marker in ["- ", "* ", "+ "] {
+        if let Some(rest) = trimmed.strip_prefix(marker) {
+            return Some((false, rest));
+        }
+    }
+    let digits = trimmed.bytes().take_while(|b| b.is_ascii_digit()).count();
+    if digits > 0 {
+        let rest = &trimmed[digits..];
+        if let Some(text) = rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") ")) {
+            return Some((true, text));
+        }
+    }
+    None
+}
+
+fn is_thematic_break(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.len() >= 3
+        && (trimmed.chars().all(|c| c == '-')
+            || trimmed.chars().all(|c| c == '*')
+            || trimmed.chars().all(|c| c == '_'))
+}
+
+/// Recognize a link reference definition line: `[label]: destination`
+/// with an optional quoted title. Returns the normalized label, the
+/// destination and the title.
+fn parse_link_definition(line: &str) -> Option<(String, String, Option<String>)> {
+    let rest = line.trim().strip_prefix('[')?;
+    let close = rest.find(']')?;
+    let label = &rest[..close];
+    if label.trim().is_empty() {
+        return None;
+    }
+    let rest = rest[close + 1..].strip_prefix(':')?;
+    let (url, title) = parse_destination(rest)?;
+    Some((normalize_label(label), url, title))
+}
+
+/// Reference labels match case-insensitively with internal whitespace
+/// collapsed, per CommonMark.
+fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Split destination text into `url` and optional quoted title:
+/// `url "title"`, `<url> "title"`, titles also in `'...'` or `(...)`.
+fn parse_destination(dest: &str) -> Option<(String, Option<String>)> {
+    let dest = dest.trim();
+    let (url, rest) = if let Some(rest) = dest.strip_prefix('<') {
+        let end = rest.find('>')?;
+        (rest[..end].to_string(), rest[end + 1..].trim())
+    } else {
+        match dest.find(char::is_whitespace) {
+            Some(end) => (dest[..end].to_string(), dest[end..].trim()),
+            None => (dest.to_string(), ""),
+        }
+    };
+    if url.is_empty() {
+        return None;
+    }
+    if rest.is_empty() {
+        return Some((url, None));
+    }
+    quoted_title(rest).map(|title| (url, Some(title)))
+}
+
+/// The title portion of a destination, when the remainder is exactly one
+/// quoted run.
+fn quoted_title(text: &str) -> Option<String> {
+    let mut chars = text.chars();
+    let close = match chars.next()? {
+        '"' => '"',
+        '\'' => '\'',
+        '(' => ')',
+        _ => return None,
+    };
+    chars.as_str().strip_suffix(close).map(str::to_string)
+}
+
+/// How the bracket run starting at one `[` reads; `end` is the index just
+/// past the construct.
+enum LinkOutcome {
+    Link {
+        text: String,
+        url: String,
+        title: Option<String>,
+        end: usize,
+    },
+    /// An explicit reference (`[text][id]` or `[text][]`) whose label has
+    /// no definition.
+    Unresolved { label: String, end: usize },
+    NotALink,
+}
+
+/// Interpret a link at the `[` at `from`: inline `[text](url "title")`,
+/// full `[text][id]`, collapsed `[text][]` and shortcut `[text]`
+/// reference forms. A shortcut bracket without a matching definition is
+/// ordinary text, not an error; the explicit reference forms surface as
+/// [`LinkOutcome::Unresolved`] so the caller can warn.
+fn parse_link(chars: &[char], index: &InlineIndex, from: usize, defs: &LinkDefs) -> LinkOutcome {
+    let Some(close) = index.next_bracket_close(from + 1) else {
+        return LinkOutcome::NotALink;
+    };
+    let text: String = chars[from + 1..close].iter().collect();
+    if text.trim().is_empty() {
+        return LinkOutcome::NotALink;
+    }
+    match chars.get(close + 1) {
+        Some('(') => {
+            let Some(end) = index.next_paren_close(close + 2) else {
+                return LinkOutcome::NotALink;
+            };
+            let dest: String = chars[close + 2..end].iter().collect();
+            match parse_destination(&dest) {
+                Some((url, title)) => LinkOutcome::Link {
+                    text,
+                    url,
+                    title,
+                    end: end + 1,
+                },
+                None => LinkOutcome::NotALink,
+            }
+        }
+        Some('[') => {
+            let Some(end) = index.next_bracket_close(close + 2) else {
+                return LinkOutcome::NotALink;
+            };
+            let label: String = chars[close + 2..end].iter().collect();
+            // The collapsed form `[text][]` uses the text as its label.
+            let label = if label.trim().is_empty() { text.clone() } else { label };
+            match defs.get(&normalize_label(&label)) {
+                Some((url, title)) => LinkOutcome::Link {
+                    text,
+                    url: url.clone(),
+                    title: title.clone(),
+                    end: end + 1,
+                },
+                None => LinkOutcome::Unresolved {
+                    label,
+                    end: end + 1,
+                },
+            }
+        }
+        _ => match defs.get(&normalize_label(&text)) {
+            Some((url, title)) => LinkOutcome::Link {
+                url: url.clone(),
+                title: title.clone(),
+                text,
+                end: close + 1,
+            },
+            None => LinkOutcome::NotALink,
+        },
+    }
+}
+
+/// Interpret an image at the `!` at `from`: the inline form
+/// `![alt](src "title")` only. Unlike links, an empty alt is still an
+/// image - it is exactly the case the accessibility validation flags.
+/// Returns `(alt, src, title, end)` with `end` just past the construct.
+fn parse_image(
+    chars: &[char],
+    index: &InlineIndex,
+    from: usize,
+) -> Option<(String, String, Option<String>, usize)> {
+    let close = index.next_bracket_close(from + 2)?;
+    if chars.get(close + 1) != Some(&'(') {
+        return None;
+    }
+    let end = index.next_paren_close(close + 2)?;
+    let dest: String = chars[close + 2..end].iter().collect();
+    let (src, title) = parse_destination(&dest)?;
+    let alt = unescape_punctuation(&chars[from + 2..close]);
+    Some((alt, src, title, end + 1))
+}
+
+/// Resolve backslash escapes the way the inline walk does, for text that
+/// is captured as-is rather than re-parsed.
+fn unescape_punctuation(chars: &[char]) -> String {
+    let mut out = String::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] == '\\' && chars.get(i + 1).is_some_and(|c| c.is_ascii_punctuation()) {
+            i += 1;
+        }
+        out.push(chars[i]);
+        i += 1;
+    }
+    out
+}
+
+/// The emphasis delimiter strings the inline walk can search for, as
+/// (character, run length) pairs; the order indexes
+/// [`InlineIndex::closers`].
+const DELIM_KINDS: [(char, usize); 7] = [
+    ('*', 1),
+    ('*', 2),
+    ('*', 3),
+    ('_', 1),
+    ('_', 2),
+    ('_', 3),
+    ('~', 2),
+];
+
+/// Inline constructs nested more deeply than this are taken as literal
+/// text. Real documents stay in single digits; a crafted line of
+/// alternating delimiters could otherwise recurse once per level and
+/// overflow the stack.
+const MAX_INLINE_DEPTH: usize = 32;
+
+/// Candidate positions for every closing construct in one inline run,
+/// collected in a single pass so the emphasis and bracket searches never
+/// rescan the line. The old on-demand scans walked to the end of the
+/// line for every delimiter that never closes, which made a megabytes-
+/// long single line (a minified export) quadratic; each search is now a
+/// binary probe of a sorted position list, keeping the whole inline pass
+/// O(n log n) in the worst case.
+struct InlineIndex {
+    /// Valid emphasis closer positions per entry of [`DELIM_KINDS`],
+    /// ascending. A position qualifies when the delimiter run starts
+    /// there and the preceding character is not whitespace.
+    closers: [Vec<usize>; 7],
+    /// Unescaped `]` positions, ascending, under the same
+    /// backslash-consumes-one-character rule as the inline walk.
+    brackets: Vec<usize>,
+    /// Unescaped `)` positions, ascending.
+    parens: Vec<usize>,
+}
+
+impl InlineIndex {
+    fn new(chars: &[char]) -> Self {
+        let n = chars.len();
+        // Run lengths measured forward: run[i] copies of chars[i] start
+        // at i. Built right to left in one pass.
+        let mut run = vec![0usize; n];
+        for i in (0..n).rev() {
+            run[i] = 1 + if chars.get(i + 1) == Some(&chars[i]) { run[i + 1] } else { 0 };
+        }
+        let mut closers: [Vec<usize>; 7] = Default::default();
+        for i in 1..n {
+            let c = chars[i];
+            if matches!(c, '*' | '_' | '~') && !chars[i - 1].is_whitespace() {
+                for (kind, &(delim, len)) in DELIM_KINDS.iter().enumerate() {
+                    if delim == c && run[i] >= len {
+                        closers[kind].push(i);
+                    }
+                }
+            }
+        }
+        let mut brackets = Vec::new();
+        let mut parens = Vec::new();
+        let mut i = 0;
+        while i < n {
+            match chars[i] {
+                '\\' => i += 2,
+                c => {
+                    match c {
+                        ']' => brackets.push(i),
+                        ')' => parens.push(i),
+                        _ => {}
+                    }
+                    i += 1;
+                }
+            }
+        }
+        InlineIndex {
+            closers,
+            brackets,
+            parens,
+        }
+    }
+
+    /// First position at or after `from` where `delim_len` copies of `c`
+    /// can close an emphasis span.
+    fn next_closer(&self, c: char, delim_len: usize, from: usize) -> Option<usize> {
+        let kind = DELIM_KINDS
+            .iter()
+            .position(|&(delim, len)| delim == c && len == delim_len)?;
+        Self::first_at_or_after(&self.closers[kind], from)
+    }
+
+    /// First unescaped `]` at or after `from`.
+    fn next_bracket_close(&self, from: usize) -> Option<usize> {
+        Self::first_at_or_after(&self.brackets, from)
+    }
+
+    /// First unescaped `)` at or after `from`.
+    fn next_paren_close(&self, from: usize) -> Option<usize> {
+        Self::first_at_or_after(&self.parens, from)
+    }
+
+    fn first_at_or_after(positions: &[usize], from: usize) -> Option<usize> {
+        positions
+            .get(positions.partition_point(|&p| p < from))
+            .copied()
+    }
+}
+
+/// Parse inline emphasis (`**bold**`, `*italic*`, `~~strike~~`), links
+/// (inline and reference forms, resolved against `defs`), images and
+/// backslash escapes into formatted runs.
+fn parse_inline(text: &str, defs: &LinkDefs, warnings: &mut Vec<String>) -> Vec<RtfNode> {
+    parse_inline_at(text, defs, warnings, 0)
+}
+
+fn parse_inline_at(
+    text: &str,
+    defs: &LinkDefs,
+    warnings: &mut Vec<String>,
+    depth: usize,
+) -> Vec<RtfNode> {
+    if depth > MAX_INLINE_DEPTH {
+        return vec![RtfNode::Text(text.to_string())];
+    }
+    let mut nodes = Vec::new();
+    let mut plain = String::new();
+    let chars: Vec<char> = text.chars().collect();
+    let index = InlineIndex::new(&chars);
+    let mut i = 0;
+
+    let flush = |plain: &mut String, nodes: &mut Vec<RtfNode>| {
+        if !plain.is_empty() {
+            nodes.push(RtfNode::Text(std::mem::take(plain)));
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\\' if i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() => {
+                plain.push(chars[i + 1]);
+                i += 2;
+            }
+            '!' if chars.get(i + 1) == Some(&'[') => match parse_image(&chars, &index, i) {
+                Some((alt, src, title, end)) => {
+                    flush(&mut plain, &mut nodes);
+                    nodes.push(RtfNode::Image { src, alt, title });
+                    i = end;
+                }
+                // Not an image; the `[` takes the normal link path next.
+                None => {
+                    plain.push('!');
+                    i += 1;
+                }
+            },
+            '[' => match parse_link(&chars, &index, i, defs) {
+                LinkOutcome::Link {
+                    text,
+                    url,
+                    title,
+                    end,
+                } => {
+                    flush(&mut plain, &mut nodes);
+                    nodes.push(RtfNode::Hyperlink {
+                        url,
+                        title,
+                        content: parse_inline_at(&text, defs, warnings, depth + 1),
+                    });
+                    i = end;
+                }
+                LinkOutcome::Unresolved { label, end } => {
+                    warnings.push(format!(
+                        "unresolved link reference '[{label}]' kept as literal text"
+                    ));
+                    plain.extend(chars[i..end].iter());
+                    i = end;
+                }
+                LinkOutcome::NotALink => {
+                    plain.push('[');
+                    i += 1;
+                }
+            },
+            '*' | '_' | '~' => {
+                let run = chars[i..].iter().take_while(|&&x| x == c).count();
+                let (delim_len, format) = match (c, run) {
+                    ('~', r) if r >= 2 => (2, strike_format()),
+                    ('*' | '_', r) if r >= 3 => (3, bold_italic_format()),
+                    ('*' | '_', 2) => (2, bold_format()),
+                    ('*' | '_', 1) => (1, italic_format()),
+                    _ => {
+                        plain.push(c);
+                        i += 1;
+                        continue;
+                    }
+                };
+                if let Some(end) = index.next_closer(c, delim_len, i + delim_len) {
+                    let inner: String = chars[i + delim_len..end].iter().collect();
+                    if !inner.is_empty() {
+                        flush(&mut plain, &mut nodes);
+                        nodes.push(RtfNode::Formatted {
+                            format,
+                            content: parse_inline_at(&inner, defs, warnings, depth + 1),
+                        });
+                        i = end + delim_len;
+                        continue;
+                    }
+                }
+                plain.push(c);
+                i += 1;
+            }
+            _ => {
+                plain.push(c);
+                i += 1;
+            }
+        }
+    }
+    flush(&mut plain, &mut nodes);
+    nodes
+}
+
+fn bold_format() -> TextFormat {
+    TextFormat {
+        bold: true,
+        ..Default::default()
+    }
+}
+
+fn italic_format() -> TextFormat {
+    TextFormat {
+        italic: true,
+        ..Default::default()
+    }
+}
+
+fn bold_italic_format() -> TextFormat {
+    TextFormat {
+        bold: true,
+        italic: true,
+        ..Default::default()
+    }
+}
+
+fn strike_format() -> TextFormat {
+    TextFormat {
+        strikethrough: true,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> RtfDocument {
+        MarkdownParser::new().parse(input).unwrap()
+    }
+
+    #[test]
+    fn parses_heading_and_paragraph() {
+        let doc = parse("# Title\n\nBody text");
+        assert!(matches!(doc.content[0], RtfNode::Heading { level: 1, .. }));
+        assert!(matches!(doc.content[1], RtfNode::Paragraph { .. }));
+    }
+
+    #[test]
+    fn parses_bold_and_italic() {
+        let doc = parse("plain **bold** and *italic*");
+        let RtfNode::Paragraph { ref content, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(content.iter().any(|n| matches!(
+            n,
+            RtfNode::Formatted { format, .. } if format.bold && !format.italic
+        )));
+        assert!(content.iter().any(|n| matches!(
+            n,
+            RtfNode::Formatted { format, .. } if format.italic && !format.bold
+        )));
+    }
+
+    #[test]
+    fn page_break_annotation_marks_the_next_block_only() {
+        let doc = parse("Intro\n\n<!-- page-break -->\n\n# Appendix\n\nDetails");
+        let RtfNode::Paragraph { spacing, .. } = &doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert!(!spacing.page_break_before);
+        let RtfNode::Heading { spacing, .. } = &doc.content[1] else {
+            panic!("expected heading, got {:?}", doc.content);
+        };
+        assert!(spacing.page_break_before, "annotation marks the heading");
+        let RtfNode::Paragraph { spacing, .. } = &doc.content[2] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert!(!spacing.page_break_before, "the flag is consumed once");
+    }
+
+    #[test]
+    fn trailing_page_break_annotation_warns() {
+        let (doc, warnings) = MarkdownParser::new()
+            .parse_with_warnings("Only paragraph\n\n<!-- page-break -->")
+            .unwrap();
+        assert_eq!(doc.content.len(), 1);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("page-break annotation ignored"), "{warnings:?}");
+    }
+
+    #[test]
+    fn pathological_single_line_input_parses_within_a_time_budget() {
+        // Four megabytes of delimiters that never close, on one line. The
+        // old closer search rescanned the rest of the line for every one
+        // of them, which at this size effectively hung the parser; the
+        // indexed walk finishes in well under the budget even in debug
+        // builds.
+        let unit = "*a _b ~~c [d ";
+        let line = unit.repeat(4 * 1024 * 1024 / unit.len());
+        let started = std::time::Instant::now();
+        let doc = parse(&line);
+        assert!(!doc.content.is_empty());
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(5),
+            "inline pass took {:?}",
+            started.elapsed()
+        );
+    }
+
+    #[test]
+    fn absurdly_nested_emphasis_stops_at_the_depth_cap() {
+        // Thousands of emphasis spans opened and closed around one
+        // character; without MAX_INLINE_DEPTH each level is a recursive
+        // call and a line like this overflows the stack. Past the cap
+        // the remainder is taken as literal text, so the payload
+        // survives either way.
+        let line = format!("{}x{}", "*a _a ".repeat(10_000), " a_ a*".repeat(10_000));
+        let doc = parse(&line);
+        assert!(doc.plain_text().contains('x'));
+    }
+
+    #[test]
+    fn parses_lists() {
+        let doc = parse("- first\n- second\n\n1. one\n2. two");
+        let unordered = doc
+            .content
+            .iter()
+            .filter(|n| matches!(n, RtfNode::ListItem { ordered: false, .. }))
+            .count();
+        let ordered = doc
+            .content
+            .iter()
+            .filter(|n| matches!(n, RtfNode::ListItem { ordered: true, .. }))
+            .count();
+        assert_eq!(unordered, 2);
+        assert_eq!(ordered, 2);
+    }
+
+    #[test]
+    fn parses_tables_without_the_separator_row() {
+        let doc = parse("| a | b |\n| --- | --- |\n| c | d |");
+        let RtfNode::Table(ref table) = doc.content[0] else {
+            panic!("expected table");
+        };
+        assert_eq!(table.rows.len(), 2);
+        assert_eq!(table.rows[0].cells.len(), 2);
+        assert_eq!(doc.plain_text().trim(), "a\tb\t\nc\td");
+    }
+
+    #[test]
+    fn separator_colons_set_column_alignment() {
+        let doc = parse("| Item | Qty | Price |\n| :---: | --- | ---: |\n| a | b | c |");
+        let RtfNode::Table(ref table) = doc.content[0] else {
+            panic!("expected table");
+        };
+        // Alignment applies to the whole column, header row included.
+        for row in &table.rows {
+            assert_eq!(row.cells[0].alignment, CellAlignment::Center);
+            assert_eq!(row.cells[1].alignment, CellAlignment::Left);
+            assert_eq!(row.cells[2].alignment, CellAlignment::Right);
+        }
+    }
+
+    #[test]
+    fn widths_annotation_becomes_column_bounds() {
+        let doc = parse("<!-- widths: 2400,4800,6960 -->\n| a | b | c |\n| --- | --- | --- |");
+        let RtfNode::Table(ref table) = doc.content[0] else {
+            panic!("expected table");
+        };
+        assert_eq!(table.rows[0].col_bounds, vec![2400, 4800, 6960]);
+        // The annotation itself never becomes content.
+        assert!(!doc.plain_text().contains("widths"));
+
+        // And it only applies to the table that follows it.
+        let doc = parse("<!-- widths: 100,200 -->\n| a |\n| --- |\n\n| b |\n| --- |");
+        let RtfNode::Table(ref second) = doc.content[1] else {
+            panic!("expected a second table");
+        };
+        assert!(second.rows[0].col_bounds.is_empty());
+    }
+
+    #[test]
+    fn invalid_widths_annotations_warn_and_are_ignored() {
+        for (input, reason) in [
+            ("<!-- widths: 2400,banana -->\n| a |\n| --- |", "banana"),
+            ("<!-- widths: 4800,2400 -->\n| a |\n| --- |", "increasing"),
+            ("<!-- widths: -5 -->\n| a |\n| --- |", "-5"),
+        ] {
+            let (doc, warnings) = MarkdownParser::new().parse_with_warnings(input).unwrap();
+            assert_eq!(warnings.len(), 1, "{input}");
+            assert!(warnings[0].contains(reason), "{}", warnings[0]);
+            let RtfNode::Table(ref table) = doc.content[0] else {
+                panic!("expected table");
+            };
+            assert!(table.rows[0].col_bounds.is_empty());
+        }
+    }
+
+    #[test]
+    fn escaped_pipes_stay_inside_their_cell() {
+        let doc = parse("| a \\| b | c |\n| --- | --- |");
+        let RtfNode::Table(ref table) = doc.content[0] else {
+            panic!("expected table");
+        };
+        assert_eq!(table.rows[0].cells.len(), 2);
+        assert_eq!(doc.plain_text().trim(), "a | b\tc");
+    }
+
+    #[test]
+    fn backslash_escapes_are_literal() {
+        let doc = parse("\\*not emphasis\\*");
+        assert_eq!(doc.plain_text().trim(), "*not emphasis*");
+    }
+
+    #[test]
+    fn front_matter_declares_direction() {
+        let doc = parse("---\ndirection: rtl\n---\n\u{645}\u{631}\u{62d}\u{628}\u{627}");
+        assert!(matches!(
+            doc.content[0],
+            RtfNode::Paragraph {
+                direction: Direction::RightToLeft,
+                ..
+            }
+        ));
+        // The front matter itself does not become content.
+        assert!(!doc.plain_text().contains("direction"));
+    }
+
+    #[test]
+    fn front_matter_declares_language() {
+        let doc = parse("---\nlang: es-ES\ndirection: ltr\n---\nHola");
+        assert_eq!(doc.metadata.language.as_deref(), Some("es-ES"));
+        assert!(!doc.plain_text().contains("lang"));
+    }
+
+    #[test]
+    fn unknown_language_tag_is_kept_with_a_warning() {
+        let (doc, warnings) = MarkdownParser::new()
+            .with_language("tlh")
+            .parse_with_warnings("Hello")
+            .unwrap();
+        assert_eq!(doc.metadata.language.as_deref(), Some("tlh"));
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("'tlh'"), "{warnings:?}");
+    }
+
+    #[test]
+    fn configured_direction_applies_without_front_matter() {
+        let doc = MarkdownParser::new()
+            .with_direction(Direction::RightToLeft)
+            .parse("\u{645}\u{631}\u{62d}\u{628}\u{627}")
+            .unwrap();
+        assert!(matches!(
+            doc.content[0],
+            RtfNode::Paragraph {
+                direction: Direction::RightToLeft,
+                ..
+            }
+        ));
+    }
+
+    /// The first hyperlink node anywhere in the document.
+    fn first_link(doc: &RtfDocument) -> (String, Option<String>, String) {
+        fn find(nodes: &[RtfNode]) -> Option<(String, Option<String>, String)> {
+            for node in nodes {
+                match node {
+                    RtfNode::Hyperlink { url, title, content } => {
+                        let mut text = String::new();
+                        for inner in content {
+                            if let RtfNode::Text(t) = inner {
+                                text.push_str(t);
+                            }
+                        }
+                        return Some((url.clone(), title.clone(), text));
+                    }
+                    RtfNode::Formatted { content, .. }
+                    | RtfNode::Paragraph { content, .. }
+                    | RtfNode::Heading { content, .. }
+                    | RtfNode::ListItem { content, .. } => {
+                        if let Some(found) = find(content) {
+                            return Some(found);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            None
+        }
+        find(&doc.content).expect("expected a hyperlink node")
+    }
+
+    #[test]
+    fn inline_links_carry_url_and_title() {
+        let doc = parse("See [the docs](https://example.com/d \"Docs\") here.");
+        let (url, title, text) = first_link(&doc);
+        assert_eq!(url, "https://example.com/d");
+        assert_eq!(title.as_deref(), Some("Docs"));
+        assert_eq!(text, "the docs");
+        // The surrounding prose stays in the flow.
+        assert_eq!(doc.plain_text().trim(), "See the docs here.");
+    }
+
+    #[test]
+    fn reference_links_resolve_in_all_three_forms() {
+        // CommonMark's core reference cases: full, collapsed and shortcut
+        // forms against one definition, matched case-insensitively.
+        let full = parse("[foo][Bar]\n\n[BAR]: /url \"title\"");
+        let (url, title, text) = first_link(&full);
+        assert_eq!((url.as_str(), title.as_deref(), text.as_str()),
+            ("/url", Some("title"), "foo"));
+
+        let collapsed = parse("[foo][]\n\n[foo]: /url \"title\"");
+        assert_eq!(first_link(&collapsed).0, "/url");
+
+        let shortcut = parse("[foo]\n\n[foo]: /url \"title\"");
+        assert_eq!(first_link(&shortcut).0, "/url");
+
+        // The definition itself never becomes content, wherever it sits.
+        let before = parse("[one]: /first\n\nuse [one] now");
+        assert_eq!(first_link(&before).0, "/first");
+        assert!(!before.plain_text().contains("/first"));
+    }
+
+    #[test]
+    fn unresolved_references_warn_and_stay_literal() {
+        let (doc, warnings) = MarkdownParser::new()
+            .parse_with_warnings("see [text][missing] here")
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("missing"), "{}", warnings[0]);
+        assert_eq!(doc.plain_text().trim(), "see [text][missing] here");
+
+        // A bare bracket with no definition is ordinary prose, not an
+        // unresolved reference - no warning.
+        let (doc, warnings) = MarkdownParser::new()
+            .parse_with_warnings("an [aside] in brackets")
+            .unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        assert_eq!(doc.plain_text().trim(), "an [aside] in brackets");
+    }
+
+    #[test]
+    fn link_text_keeps_inline_formatting() {
+        let doc = parse("[**bold** label](https://example.com)");
+        let RtfNode::Paragraph { ref content, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        let RtfNode::Hyperlink { ref content, .. } = content[0] else {
+            panic!("expected hyperlink");
+        };
+        assert!(content.iter().any(|n| matches!(
+            n,
+            RtfNode::Formatted { format, .. } if format.bold
+        )));
+    }
+
+    #[test]
+    fn inline_images_carry_alt_src_and_title() {
+        let doc = parse("See ![a chart](img/chart.png \"Q3\") here.");
+        let RtfNode::Paragraph { ref content, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(
+            content[1],
+            RtfNode::Image {
+                src: "img/chart.png".to_string(),
+                alt: "a chart".to_string(),
+                title: Some("Q3".to_string()),
+            }
+        );
+        // An empty alt still parses as an image; a bare `!` stays prose.
+        let doc = parse("![](x.png) costs $5!");
+        let RtfNode::Paragraph { ref content, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(&content[0], RtfNode::Image { alt, .. } if alt.is_empty()));
+    }
+
+    #[test]
+    fn image_alt_resolves_backslash_escapes() {
+        let doc = parse("![a \\[bracketed\\] chart](x.png)");
+        let RtfNode::Paragraph { ref content, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(
+            &content[0],
+            RtfNode::Image { alt, .. } if alt == "a [bracketed] chart"
+        ));
+    }
+
+    #[test]
+    fn raw_rtf_fences_require_the_opt_in() {
+        let md = "```rtf-raw\n{\\pard}\n```";
+        // Without the opt-in the fence is ordinary paragraph text.
+        let doc = parse(md);
+        assert!(
+            doc.content
+                .iter()
+                .all(|n| !matches!(n, RtfNode::RawRtf { .. })),
+            "{:?}",
+            doc.content
+        );
+
+        let doc = MarkdownParser::new().with_raw_rtf(true).parse(md).unwrap();
+        assert_eq!(
+            doc.content[0],
+            RtfNode::RawRtf {
+                content: "{\\pard}".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn denied_control_words_are_rejected_inside_raw_blocks() {
+        let md = "```rtf-raw\n{\\object\\objdata 0102}\n```";
+        let err = MarkdownParser::new().with_raw_rtf(true).parse(md).unwrap_err();
+        assert!(err.contains("rtf-raw block rejected"), "{err}");
+        assert!(err.contains("objdata"), "{err}");
+    }
+
+    #[test]
+    fn unbalanced_braces_are_rejected_inside_raw_blocks() {
+        let parser = || MarkdownParser::new().with_raw_rtf(true);
+        let err = parser().parse("```rtf-raw\n{\\pard\n```").unwrap_err();
+        assert!(err.contains("unbalanced braces"), "{err}");
+
+        // Escaped braces do not count toward the balance.
+        let doc = parser().parse("```rtf-raw\n{\\{ and \\}}\n```").unwrap();
+        assert!(matches!(doc.content[0], RtfNode::RawRtf { .. }));
+
+        // An unterminated fence is an error, not a silent paragraph.
+        let err = parser().parse("```rtf-raw\n{\\pard}").unwrap_err();
+        assert!(err.contains("unterminated"), "{err}");
+    }
+
+    #[test]
+    fn multi_line_paragraphs_join() {
+        let doc = parse("line one\nline two");
+        assert_eq!(doc.plain_text().trim(), "line one line two");
+    }
+}
@@ -0,0 +1,210 @@
+//! Opt-in peak-memory accounting for conversions.
+//!
+//! Capacity planning needs real peak numbers; deriving them from input
+//! plus output size undercounts the parse tree and every intermediate
+//! buffer. Under the `memory-accounting` feature, [`CountingAllocator`]
+//! wraps the system allocator and keeps per-thread counters of live heap
+//! bytes, their high-water mark and the number of allocations made; the
+//! pipeline opens a span per
+//! conversion and reports the span's peak in
+//! [`PipelineOutput::peak_memory_bytes`](super::pipeline::PipelineOutput::peak_memory_bytes)
+//! and the `legacybridge_conversion_peak_bytes` histogram. Without the
+//! feature the span type is a zero-sized no-op and conversions pay
+//! nothing.
+//!
+//! The counters are thread-local, so they only see allocations made by
+//! the converting thread itself - exactly the pipeline's behavior, which
+//! never spawns - and concurrent conversions never pollute each other.
+
+#[cfg(feature = "memory-accounting")]
+use std::alloc::{GlobalAlloc, Layout, System};
+#[cfg(feature = "memory-accounting")]
+use std::cell::Cell;
+
+#[cfg(feature = "memory-accounting")]
+thread_local! {
+    /// Live heap bytes allocated by this thread through the wrapper.
+    static LIVE: Cell<usize> = const { Cell::new(0) };
+    /// High-water mark of `LIVE` since the current span opened.
+    static PEAK: Cell<usize> = const { Cell::new(0) };
+    /// Running count of allocations made by this thread; reallocations
+    /// don't count, so the number tracks distinct allocation calls.
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// System-allocator wrapper that maintains the thread-local counters.
+/// Instrumented builds install it in the binary crate:
+///
+/// ```ignore
+/// #[global_allocator]
+/// static ALLOCATOR: CountingAllocator = CountingAllocator;
+/// ```
+#[cfg(feature = "memory-accounting")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "memory-accounting")]
+impl CountingAllocator {
+    /// `try_with`, not `with`: the allocator runs during thread-local
+    /// destruction too, when the counters may already be gone.
+    fn charge(bytes: usize) {
+        let _ = LIVE.try_with(|live| {
+            let now = live.get() + bytes;
+            live.set(now);
+            let _ = PEAK.try_with(|peak| peak.set(peak.get().max(now)));
+        });
+    }
+
+    fn count_allocation() {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+    }
+
+    fn release(bytes: usize) {
+        let _ = LIVE.try_with(|live| live.set(live.get().saturating_sub(bytes)));
+    }
+}
+
+#[cfg(feature = "memory-accounting")]
+// SAFETY: every call delegates to `System`; the counters are plain
+// thread-local cells and never allocate themselves.
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            Self::charge(layout.size());
+            Self::count_allocation();
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            Self::charge(layout.size());
+            Self::count_allocation();
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        Self::release(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            if new_size >= layout.size() {
+                Self::charge(new_size - layout.size());
+            } else {
+                Self::release(layout.size() - new_size);
+            }
+        }
+        new_ptr
+    }
+}
+
+/// One conversion's accounting window; see [`start_span`].
+#[cfg(feature = "memory-accounting")]
+pub(crate) struct MemorySpan {
+    /// Live bytes when the span opened; the span's peak is measured
+    /// above this, so allocations predating the conversion don't count.
+    baseline: usize,
+    /// Allocation count when the span opened, for the same reason.
+    allocation_baseline: u64,
+}
+
+/// Open an accounting span for the conversion starting on this thread.
+#[cfg(feature = "memory-accounting")]
+pub(crate) fn start_span() -> MemorySpan {
+    let baseline = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(baseline));
+    MemorySpan {
+        baseline,
+        allocation_baseline: ALLOCATIONS.with(Cell::get),
+    }
+}
+
+#[cfg(feature = "memory-accounting")]
+impl MemorySpan {
+    /// Peak heap bytes above the span's baseline so far.
+    pub(crate) fn peak_bytes(&self) -> Option<usize> {
+        Some(PEAK.with(Cell::get).saturating_sub(self.baseline))
+    }
+
+    /// Allocations this thread made since the span opened.
+    pub(crate) fn allocation_count(&self) -> Option<u64> {
+        Some(ALLOCATIONS.with(Cell::get).saturating_sub(self.allocation_baseline))
+    }
+}
+
+/// No-op span without the `memory-accounting` feature.
+#[cfg(not(feature = "memory-accounting"))]
+pub(crate) struct MemorySpan;
+
+#[cfg(not(feature = "memory-accounting"))]
+#[inline]
+pub(crate) fn start_span() -> MemorySpan {
+    MemorySpan
+}
+
+#[cfg(not(feature = "memory-accounting"))]
+impl MemorySpan {
+    #[inline]
+    pub(crate) fn peak_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    pub(crate) fn allocation_count(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// The crate's own instrumented test binary measures through this; host
+/// binaries opting in declare the same in their crate root.
+#[cfg(all(test, feature = "memory-accounting"))]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+#[cfg(all(test, feature = "memory-accounting"))]
+mod tests {
+    use crate::conversion::pipeline::DocumentPipeline;
+
+    /// Peaks are not exact multiples of the input size - allocator
+    /// rounding, reuse and the fixed pipeline overhead all blur them -
+    /// but a 50x larger document must peak far higher than a small one.
+    #[test]
+    fn peak_scales_roughly_with_input_size() {
+        let small = format!("{{\\rtf1 {}\\par}}", "word ".repeat(200));
+        let large = format!("{{\\rtf1 {}\\par}}", "word ".repeat(10_000));
+        let pipeline = DocumentPipeline::with_defaults();
+        let small_peak = pipeline
+            .process(&small)
+            .unwrap()
+            .peak_memory_bytes
+            .expect("instrumented build reports a peak");
+        let large_peak = pipeline
+            .process(&large)
+            .unwrap()
+            .peak_memory_bytes
+            .expect("instrumented build reports a peak");
+        assert!(small_peak > small.len(), "{small_peak} vs {}", small.len());
+        assert!(
+            large_peak > small_peak * 4,
+            "large {large_peak} should dwarf small {small_peak}"
+        );
+    }
+}
+
+#[cfg(all(test, not(feature = "memory-accounting")))]
+mod tests {
+    use crate::conversion::pipeline::DocumentPipeline;
+
+    #[test]
+    fn peak_is_absent_without_the_feature() {
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 Hello\\par}")
+            .unwrap();
+        assert_eq!(output.peak_memory_bytes, None);
+    }
+}
@@ -0,0 +1,889 @@
+//! RTF <-> Markdown conversion core.
+
+pub mod breadcrumb;
+pub mod builder;
+pub mod cancel;
+pub mod clock;
+pub mod color;
+pub mod context;
+pub mod control_word_extensions;
+pub mod control_words;
+pub mod cross_references;
+pub mod encoding;
+pub mod environment;
+pub mod features;
+pub mod fingerprint;
+pub mod font_map;
+pub mod forms;
+pub mod integrity;
+pub mod language;
+pub mod lexer;
+pub mod link_rewrite;
+pub mod markdown_analysis;
+pub mod markdown_generator;
+pub mod markdown_parser;
+pub mod memory;
+pub mod normalization;
+pub mod pipeline;
+pub mod plain_text;
+pub mod report;
+pub mod rtf_generator;
+pub mod rtf_parser;
+pub mod session;
+pub mod simd_lexer;
+pub mod styles;
+pub mod template;
+pub mod terminology;
+pub mod thread_state;
+pub mod unicode_hygiene;
+
+pub use pipeline::{ConversionError, ConversionResult, DocumentPipeline, PipelineConfig};
+
+use crate::security::{InputValidator, SecurityLimits};
+use lexer::RtfToken;
+use link_rewrite::LinkRewriteConfig;
+use markdown_generator::MarkdownGenerator;
+use markdown_parser::MarkdownParser;
+use rtf_generator::{ConformanceProfile, RtfGenerator};
+use rtf_parser::RtfParser;
+use serde::{Deserialize, Serialize};
+
+/// How [`rtf_to_markdown_with_mode`] picks its execution path.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionMode {
+    /// Decide per document: simple documents take the direct path,
+    /// heavyweight ones the pipeline.
+    #[default]
+    Auto,
+    /// Force the direct tokenize/parse/generate path.
+    Simple,
+    /// Force the full [`DocumentPipeline`].
+    Pipeline,
+}
+
+/// Which execution path a conversion actually took;
+/// [`ConversionMode::Auto`] resolves to one of these.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionPath {
+    Simple,
+    Pipeline,
+}
+
+/// Convert an RTF document to Markdown.
+///
+/// Simple documents go through a direct tokenize/parse/generate path;
+/// documents with heavyweight constructs are routed through the full
+/// [`DocumentPipeline`] for validation and diagnostics. Use
+/// [`rtf_to_markdown_with_mode`] to force a path or observe the choice.
+pub fn rtf_to_markdown(rtf: &str) -> ConversionResult<String> {
+    rtf_to_markdown_with_mode(rtf, ConversionMode::Auto).map(|(markdown, _)| markdown)
+}
+
+/// [`rtf_to_markdown`] with an explicit [`ConversionMode`], returning the
+/// Markdown together with the [`ConversionPath`] that produced it.
+pub fn rtf_to_markdown_with_mode(
+    rtf: &str,
+    mode: ConversionMode,
+) -> ConversionResult<(String, ConversionPath)> {
+    match resolve_conversion_path(rtf, mode)? {
+        ConversionPath::Pipeline => {
+            let output = DocumentPipeline::with_defaults().process(rtf)?;
+            Ok((output.markdown, ConversionPath::Pipeline))
+        }
+        ConversionPath::Simple => {
+            let result = lexer::tokenize(rtf)
+                .map_err(ConversionError::parse)
+                .and_then(|tokens| {
+                    RtfParser::new(tokens).parse().map_err(ConversionError::parse)
+                })
+                .map(|document| MarkdownGenerator::new().generate(&document));
+            context::count_operation(
+                "rtf_to_md",
+                "simple",
+                None,
+                if result.is_ok() { "success" } else { "error" },
+            );
+            result.map(|markdown| (markdown, ConversionPath::Simple))
+        }
+    }
+}
+
+/// Resolve a [`ConversionMode`] against a document. `Simple` and
+/// `Pipeline` pass through; `Auto` tokenizes and scans for heavyweight
+/// constructs, so a document merely mentioning `\trowd` in its text no
+/// longer takes the pipeline.
+pub fn resolve_conversion_path(rtf: &str, mode: ConversionMode) -> ConversionResult<ConversionPath> {
+    Ok(match mode {
+        ConversionMode::Simple => ConversionPath::Simple,
+        ConversionMode::Pipeline => ConversionPath::Pipeline,
+        ConversionMode::Auto if rtf.len() > 64 * 1024 => ConversionPath::Pipeline,
+        ConversionMode::Auto => {
+            let tokens = lexer::tokenize(rtf).map_err(ConversionError::parse)?;
+            if should_use_pipeline(&tokens) {
+                ConversionPath::Pipeline
+            } else {
+                ConversionPath::Simple
+            }
+        }
+    })
+}
+
+/// [`rtf_to_markdown`] under a tenant [`context::ConversionContext`]:
+/// always routes through the pipeline so the tenant's cap, limits,
+/// templates and metrics apply.
+pub fn rtf_to_markdown_with_context(
+    rtf: &str,
+    ctx: &context::ConversionContext,
+) -> ConversionResult<String> {
+    let output = DocumentPipeline::with_defaults().process_with_context(rtf, ctx)?;
+    Ok(output.markdown)
+}
+
+/// Decide whether a document needs the full pipeline: any table, object,
+/// picture or stylesheet control word qualifies.
+fn should_use_pipeline(tokens: &[RtfToken]) -> bool {
+    tokens.iter().any(|token| {
+        matches!(
+            token,
+            RtfToken::ControlWord { name, .. }
+                if matches!(name.as_str(), "trowd" | "object" | "pict" | "stylesheet")
+        )
+    })
+}
+
+/// Convert a Markdown document to RTF.
+pub fn markdown_to_rtf(markdown: &str) -> ConversionResult<String> {
+    let result = MarkdownParser::new()
+        .parse(markdown)
+        .map_err(ConversionError::parse)
+        .and_then(|document| {
+            RtfGenerator::new()
+                .generate(&document)
+                .map_err(ConversionError::generation)
+        });
+    context::count_operation(
+        "md_to_rtf",
+        "simple",
+        None,
+        if result.is_ok() { "success" } else { "error" },
+    );
+    result
+}
+
+/// [`markdown_to_rtf`] restricted to RTF 1.5 for legacy readers (VB6
+/// RichTextBox, VFP9 report viewer); see
+/// [`RtfGenerator::with_legacy_mode`].
+pub fn markdown_to_rtf_legacy(markdown: &str) -> ConversionResult<String> {
+    let document = MarkdownParser::new()
+        .parse(markdown)
+        .map_err(ConversionError::parse)?;
+    RtfGenerator::new()
+        .with_legacy_mode(true)
+        .generate(&document)
+        .map_err(ConversionError::generation)
+}
+
+/// [`markdown_to_rtf`] with ```` ```rtf-raw ```` passthrough fences
+/// enabled; see [`MarkdownParser::with_raw_rtf`]. Block contents are
+/// validated against the security deny list and emitted into the output
+/// verbatim - an escape hatch for RTF constructs the document model
+/// does not carry.
+pub fn markdown_to_rtf_with_raw_rtf(markdown: &str) -> ConversionResult<String> {
+    let document = MarkdownParser::new()
+        .with_raw_rtf(true)
+        .parse(markdown)
+        .map_err(ConversionError::parse)?;
+    RtfGenerator::new()
+        .generate(&document)
+        .map_err(ConversionError::generation)
+}
+
+/// [`markdown_to_rtf`] emitting a headerless fragment (no `{\rtf1 ...}`
+/// wrapper), for writing back into database memo fields that store
+/// fragments; see [`RtfGenerator::with_fragment`]. The RTF->Markdown
+/// direction reads such fields under
+/// [`PipelineConfig::allow_fragment`](pipeline::PipelineConfig::allow_fragment).
+pub fn markdown_to_rtf_fragment(markdown: &str) -> ConversionResult<String> {
+    let document = MarkdownParser::new()
+        .parse(markdown)
+        .map_err(ConversionError::parse)?;
+    RtfGenerator::new()
+        .with_fragment(true)
+        .generate(&document)
+        .map_err(ConversionError::generation)
+}
+
+/// [`markdown_to_rtf`] with heading levels shifted by `offset`, for
+/// embedding the converted content under an existing document's
+/// hierarchy: positive demotes, negative promotes (an offset of `-2`
+/// turns H3 into H1). Levels are clamped to 1..=6; see
+/// [`MarkdownParser::with_heading_offset`]. The RTF->Markdown direction
+/// shifts under
+/// [`PipelineConfig::heading_offset`](pipeline::PipelineConfig::heading_offset).
+pub fn markdown_to_rtf_with_heading_offset(
+    markdown: &str,
+    offset: i8,
+) -> ConversionResult<String> {
+    let document = MarkdownParser::new()
+        .with_heading_offset(offset)
+        .parse(markdown)
+        .map_err(ConversionError::parse)?;
+    RtfGenerator::new()
+        .generate(&document)
+        .map_err(ConversionError::generation)
+}
+
+/// [`markdown_to_rtf`] with hyperlink URLs rewritten under `config`'s
+/// rules, the reverse direction of
+/// [`PipelineConfig::link_rewrite`](pipeline::PipelineConfig::link_rewrite):
+/// the same rules apply, but unmatched links are not individually
+/// reported - this entry point has no validation-results channel.
+pub fn markdown_to_rtf_with_link_rewrites(
+    markdown: &str,
+    config: &LinkRewriteConfig,
+) -> ConversionResult<String> {
+    let compiled = config.compile().map_err(ConversionError::validation)?;
+    let mut document = MarkdownParser::new()
+        .parse(markdown)
+        .map_err(ConversionError::parse)?;
+    compiled.apply(&mut document.content);
+    RtfGenerator::new()
+        .generate(&document)
+        .map_err(ConversionError::generation)
+}
+
+/// [`markdown_to_rtf`] with an integrity signature: the generated RTF
+/// carries a `{\*\lbsig ...}` block recording input and output hashes,
+/// converter version, options fingerprint and timestamp, which
+/// [`integrity::verify_integrity`] later checks. Signed with the default
+/// configuration's fingerprint, matching what this entry point runs.
+pub fn markdown_to_rtf_with_integrity(markdown: &str) -> ConversionResult<String> {
+    let rtf = markdown_to_rtf(markdown)?;
+    let fingerprint = PipelineConfig::default().fingerprint(&SecurityLimits::default());
+    integrity::sign_rtf(&rtf, markdown, &fingerprint).map_err(ConversionError::generation)
+}
+
+/// [`markdown_to_rtf`] under an explicit [`ConformanceProfile`]. Unlike
+/// [`markdown_to_rtf_legacy`] this only restricts which control words
+/// are emitted (see the profile docs for the fallbacks); it does not
+/// fold source lines.
+pub fn markdown_to_rtf_with_profile(
+    markdown: &str,
+    profile: ConformanceProfile,
+) -> ConversionResult<String> {
+    let document = MarkdownParser::new()
+        .parse(markdown)
+        .map_err(ConversionError::parse)?;
+    RtfGenerator::new()
+        .with_profile(profile)
+        .generate(&document)
+        .map_err(ConversionError::generation)
+}
+
+/// Convert an RTF document to plain text: paragraph-level blocks become
+/// blank-line-separated paragraphs, table cells are tab-separated, all
+/// inline formatting is dropped. The tolerant parser applies, so
+/// everything that converts to Markdown also converts to text.
+pub fn rtf_to_plain_text(rtf: &str) -> ConversionResult<String> {
+    let result = lexer::tokenize(rtf)
+        .map_err(ConversionError::parse)
+        .and_then(|tokens| RtfParser::new(tokens).parse().map_err(ConversionError::parse))
+        .map(|document| plain_text::document_to_text(&document));
+    context::count_operation(
+        "rtf_to_txt",
+        "simple",
+        None,
+        if result.is_ok() { "success" } else { "error" },
+    );
+    result
+}
+
+/// Convert plain text to minimally formatted RTF: paragraphs from
+/// blank-line separation, single line breaks kept as `\line`, no inline
+/// formatting inferred; see [`plain_text::text_to_document`].
+pub fn plain_text_to_rtf(text: &str) -> ConversionResult<String> {
+    let result = RtfGenerator::new()
+        .generate(&plain_text::text_to_document(text))
+        .map_err(ConversionError::generation);
+    context::count_operation(
+        "txt_to_rtf",
+        "simple",
+        None,
+        if result.is_ok() { "success" } else { "error" },
+    );
+    result
+}
+
+/// Validate an RTF document without generating output: runs the
+/// pipeline's validation, tokenization and parsing stages but stops
+/// before generation ([`pipeline::Stage::Parse`]), so pre-flighting a
+/// batch never pays generation cost or produces files. The returned
+/// [`pipeline::PipelineOutput`] carries the full diagnostics -
+/// validation results, feature usage, recovery actions - with an empty
+/// `markdown` body; its verdict matches what a full conversion under the
+/// same limits would return.
+pub fn validate_rtf_deep(
+    rtf: &str,
+    limits: &SecurityLimits,
+) -> ConversionResult<pipeline::PipelineOutput> {
+    InputValidator::new(limits.clone())
+        .validate_rtf_input(rtf)
+        .map_err(ConversionError::validation)?;
+    let config = PipelineConfig {
+        stop_after: pipeline::Stage::Parse,
+        ..Default::default()
+    };
+    DocumentPipeline::new(config).process(rtf)
+}
+
+/// [`rtf_to_markdown`] with security limits enforced on the raw input.
+pub fn secure_rtf_to_markdown(rtf: &str, limits: &SecurityLimits) -> ConversionResult<String> {
+    InputValidator::new(limits.clone())
+        .validate_rtf_input(rtf)
+        .map_err(ConversionError::validation)?;
+    rtf_to_markdown(rtf)
+}
+
+/// [`markdown_to_rtf`] with security limits enforced on the raw input,
+/// and on the output *before* generating it: when even the lower bound
+/// of the [size estimate](RtfGenerator::estimate) exceeds
+/// `max_output_size`, the conversion is rejected without paying the
+/// generation cost.
+pub fn secure_markdown_to_rtf(markdown: &str, limits: &SecurityLimits) -> ConversionResult<String> {
+    if markdown.len() > limits.max_input_size {
+        return Err(ConversionError::validation(format!(
+            "input exceeds maximum size ({} > {} bytes)",
+            markdown.len(),
+            limits.max_input_size
+        )));
+    }
+    if let Some((number, length)) = markdown
+        .lines()
+        .enumerate()
+        .find_map(|(i, line)| (line.len() > limits.max_line_length).then_some((i + 1, line.len())))
+    {
+        return Err(ConversionError::validation(format!(
+            "line {number} exceeds maximum line length ({length} > {} bytes)",
+            limits.max_line_length
+        )));
+    }
+    let document = MarkdownParser::new()
+        .with_unicode_hygiene(limits.unicode_hygiene.clone())
+        .parse(markdown)
+        .map_err(ConversionError::parse)?;
+    let mut generator = RtfGenerator::new();
+    let estimate = generator.estimate(&document);
+    if estimate.min > limits.max_output_size {
+        return Err(ConversionError::resource_limit(format!(
+            "output would exceed maximum size (at least {} > {} bytes)",
+            estimate.min, limits.max_output_size
+        )));
+    }
+    let result = generator
+        .generate(&document)
+        .map_err(ConversionError::generation);
+    context::count_operation(
+        "md_to_rtf",
+        "simple",
+        None,
+        if result.is_ok() { "success" } else { "error" },
+    );
+    result
+}
+
+/// Re-parse third-party RTF and re-emit it in our canonical form.
+///
+/// The generator's output is deterministic (sorted header tables, fixed
+/// control-word order), so canonicalized files diff cleanly and hash
+/// stably regardless of which tool produced the original.
+pub fn canonicalize_rtf(rtf: &str) -> ConversionResult<String> {
+    let tokens = lexer::tokenize(rtf).map_err(ConversionError::parse)?;
+    let document = RtfParser::new(tokens)
+        .parse()
+        .map_err(ConversionError::parse)?;
+    RtfGenerator::new()
+        .generate(&document)
+        .map_err(ConversionError::generation)
+}
+
+/// Extract the heading outline of a parsed document, for navigation
+/// panels. Offsets index the Markdown a default [`MarkdownGenerator`]
+/// emits for the same document; callers with a configured generator
+/// should use
+/// [`MarkdownGenerator::generate_with_outline`] instead so the offsets
+/// match their actual output.
+pub fn extract_outline(
+    document: &rtf_parser::RtfDocument,
+) -> Vec<markdown_generator::OutlineEntry> {
+    MarkdownGenerator::new().outline(document)
+}
+
+/// Extract plain text from an RTF document, discarding all formatting.
+pub fn extract_plain_text(rtf: &str) -> ConversionResult<String> {
+    let tokens = lexer::tokenize(rtf).map_err(ConversionError::parse)?;
+    let document = RtfParser::new(tokens)
+        .parse()
+        .map_err(ConversionError::parse)?;
+    Ok(document.plain_text())
+}
+
+/// One table's cell text in document order, serializable for JSON
+/// export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtractedTable {
+    /// Rows of cell text; intra-cell paragraph breaks are newlines.
+    pub rows: Vec<Vec<String>>,
+}
+
+/// Extract every table's cell text as structured data, for feeding
+/// spreadsheets or JSON consumers. Works from the document tree, so
+/// cells keep their full untruncated text whatever Markdown cell policy
+/// ([`PipelineConfig::max_cell_length`](pipeline::PipelineConfig::max_cell_length))
+/// a conversion of the same document runs under.
+pub fn extract_tables(rtf: &str) -> ConversionResult<Vec<ExtractedTable>> {
+    let tokens = lexer::tokenize(rtf).map_err(ConversionError::parse)?;
+    let document = RtfParser::new(tokens)
+        .parse()
+        .map_err(ConversionError::parse)?;
+    Ok(document
+        .content
+        .iter()
+        .filter_map(|node| match node {
+            rtf_parser::RtfNode::Table(table) => Some(ExtractedTable {
+                rows: table
+                    .rows
+                    .iter()
+                    .map(|row| row.cells.iter().map(|cell| cell_text(&cell.content)).collect())
+                    .collect(),
+            }),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Plain text of one cell, intra-cell boundaries (line breaks, nested
+/// paragraphs) preserved as newlines. Iterative for the same
+/// stack-safety reasons as [`RtfDocument::plain_text`](rtf_parser::RtfDocument::plain_text).
+fn cell_text(nodes: &[rtf_parser::RtfNode]) -> String {
+    use rtf_parser::RtfNode;
+    enum Step<'a> {
+        Node(&'a RtfNode),
+        Newline,
+    }
+    let mut out = String::new();
+    let mut work: Vec<Step> = nodes.iter().rev().map(Step::Node).collect();
+    while let Some(step) = work.pop() {
+        let node = match step {
+            Step::Newline => {
+                out.push('\n');
+                continue;
+            }
+            Step::Node(node) => node,
+        };
+        match node {
+            RtfNode::Text(t) => out.push_str(t),
+            RtfNode::Formatted { content, .. } | RtfNode::Hyperlink { content, .. } => {
+                work.extend(content.iter().rev().map(Step::Node));
+            }
+            RtfNode::Paragraph { content, .. }
+            | RtfNode::Heading { content, .. }
+            | RtfNode::ListItem { content, .. } => {
+                work.push(Step::Newline);
+                work.extend(content.iter().rev().map(Step::Node));
+            }
+            RtfNode::LineBreak => out.push('\n'),
+            RtfNode::Image { .. }
+            | RtfNode::RawRtf { .. }
+            | RtfNode::Table(_)
+            | RtfNode::PageBreak => {}
+        }
+    }
+    out.trim_end_matches('\n').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_output_is_rejected_before_generation() {
+        let limits = SecurityLimits {
+            max_output_size: 64,
+            ..Default::default()
+        };
+        let md = "# Title\n\nA paragraph that cannot possibly fit in 64 bytes of RTF output.";
+        let err = secure_markdown_to_rtf(md, &limits).unwrap_err();
+        assert_eq!(err.category(), "resource_limit");
+        assert!(err.to_string().contains("at least"), "{err}");
+
+        // The same document passes under the default cap.
+        assert!(secure_markdown_to_rtf(md, &SecurityLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn overlong_markdown_lines_are_rejected_with_the_line_number() {
+        let limits = SecurityLimits {
+            max_line_length: 64,
+            ..Default::default()
+        };
+        let markdown = format!("short line\n{}\n", "x".repeat(65));
+        let err = secure_markdown_to_rtf(&markdown, &limits).unwrap_err();
+        assert_eq!(err.category(), "validation");
+        assert!(err.to_string().contains("line 2"), "{err}");
+
+        // At the limit exactly is still accepted.
+        let markdown = "x".repeat(64);
+        assert!(secure_markdown_to_rtf(&markdown, &limits).is_ok());
+    }
+
+    #[test]
+    fn simple_path_round_trip() {
+        let md = rtf_to_markdown("{\\rtf1 Hello \\b World\\b0\\par}").unwrap();
+        assert_eq!(md.trim(), "Hello **World**");
+    }
+
+    #[test]
+    fn reference_links_round_trip_as_inline_links() {
+        let md = "Read [the spec][cm] first.\n\n[cm]: https://spec.example/cm \"CommonMark\"\n";
+        let rtf = markdown_to_rtf(md).unwrap();
+        assert!(
+            rtf.contains("{\\field{\\*\\fldinst{HYPERLINK \"https://spec.example/cm\""),
+            "{rtf}"
+        );
+        assert!(rtf.contains("\\\\o \"CommonMark\""), "{rtf}");
+        // Coming back, the inline form wins - it needs no definition block.
+        let back = rtf_to_markdown(&rtf).unwrap();
+        assert!(
+            back.contains("[the spec](https://spec.example/cm \"CommonMark\")"),
+            "{back}"
+        );
+    }
+
+    #[test]
+    fn image_alt_and_title_survive_the_round_trip() {
+        // Brackets in the alt text exercise Markdown escaping; quotes pass
+        // through the RTF destination untouched because the metadata lives
+        // in subgroups, not quoted arguments.
+        let md = "![Logo \\[2019\\] \"classic\"](img/logo.png \"The old logo\")\n";
+        let rtf = markdown_to_rtf(md).unwrap();
+        assert!(
+            rtf.contains(
+                "{\\*\\lbimage{\\*\\lbimgsrc img/logo.png}\
+                 {\\*\\lbimgalt Logo [2019] \"classic\"}\
+                 {\\*\\lbimgtitle The old logo}}"
+            ),
+            "{rtf}"
+        );
+        let back = rtf_to_markdown(&rtf).unwrap();
+        assert!(
+            back.contains("![Logo \\[2019\\] \"classic\"](img/logo.png \"The old logo\")"),
+            "{back}"
+        );
+    }
+
+    #[test]
+    fn simple_path_strips_bidi_overrides_too() {
+        let (markdown, path) =
+            rtf_to_markdown_with_mode("{\\rtf1 file\\u8238?cod.rtf\\par}", ConversionMode::Simple)
+                .unwrap();
+        assert_eq!(path, ConversionPath::Simple);
+        assert!(markdown.contains("filecod.rtf"), "{markdown}");
+    }
+
+    #[test]
+    fn nfd_source_produces_nfc_output_in_both_directions() {
+        // "café" with a combining acute (U+0301), as Mac tooling writes
+        // it; output carries the composed U+00E9 instead.
+        let rtf = markdown_to_rtf("cafe\u{0301}").unwrap();
+        assert!(rtf.contains("caf\\u233"), "{rtf}");
+        assert!(!rtf.contains("\\u769"), "{rtf}");
+
+        let markdown = rtf_to_markdown("{\\rtf1 cafe\\u769?\\par}").unwrap();
+        assert!(markdown.contains("caf\u{E9}"), "{markdown}");
+    }
+
+    #[test]
+    fn code_styled_runs_keep_their_decomposed_characters() {
+        let markdown = rtf_to_markdown(
+            "{\\rtf1{\\stylesheet{\\*\\cs16\\f1\\fs20 Code;}}\
+             caffe\\u769? {\\cs16 ide\\u769?e_fixe} here\\par}",
+        )
+        .unwrap();
+        // Prose is recomposed; the code span is quoted verbatim.
+        assert!(markdown.contains("caff\u{E9}"), "{markdown}");
+        assert!(markdown.contains("`ide\u{0301}e_fixe`"), "{markdown}");
+    }
+
+    #[test]
+    fn operation_metrics_label_mode_and_template() {
+        let series = |key: &str| {
+            context::metrics_snapshot()["conversion_operations_total"][key]
+                .as_u64()
+                .unwrap_or(0)
+        };
+        let simple_key = "conversion_operations_total{direction=\"rtf_to_md\",\
+                          mode=\"simple\",template=\"none\",outcome=\"success\"}";
+        let templated_key = "conversion_operations_total{direction=\"rtf_to_md\",\
+                             mode=\"pipeline\",template=\"memo\",outcome=\"success\"}";
+        let (simple_before, templated_before) = (series(simple_key), series(templated_key));
+
+        rtf_to_markdown_with_mode("{\\rtf1 Hello\\par}", ConversionMode::Simple).unwrap();
+        let config = PipelineConfig {
+            template: Some("memo".to_string()),
+            ..Default::default()
+        };
+        DocumentPipeline::new(config).process("{\\rtf1 Hello\\par}").unwrap();
+
+        // Other tests convert in parallel, so the counts only move up -
+        // but each series must have moved at least by our conversion.
+        assert!(series(simple_key) > simple_before);
+        assert!(series(templated_key) > templated_before);
+    }
+
+    #[test]
+    fn intra_document_links_round_trip_through_bookmarks() {
+        let md = "# Overview\n\nSee [Details](#details).\n\n\
+                  # Details\n\nBack to [Overview](#overview).\n";
+        let document = MarkdownParser::new().parse(md).unwrap();
+        let rtf = RtfGenerator::new()
+            .with_cross_references(true)
+            .generate(&document)
+            .unwrap();
+        assert!(
+            rtf.contains("{\\*\\bkmkstart details}{\\*\\bkmkend details}"),
+            "{rtf}"
+        );
+        assert!(
+            rtf.contains("{\\field{\\*\\fldinst{REF details \\\\h}}{\\fldrslt Details}}"),
+            "{rtf}"
+        );
+        // Coming back, each bookmark stands for its heading again, so the
+        // links land on the same slugs they started with.
+        let tokens = lexer::tokenize(&rtf).unwrap();
+        let (document, warnings) = rtf_parser::RtfParser::new(tokens)
+            .with_cross_references(true)
+            .parse_with_warnings()
+            .unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        let back = MarkdownGenerator::new().generate(&document);
+        assert!(back.contains("[Details](#details)"), "{back}");
+        assert!(back.contains("[Overview](#overview)"), "{back}");
+    }
+
+    #[test]
+    fn document_language_round_trips_through_front_matter() {
+        let md = "---\nlang: es-ES\n---\n\n# Hola\n\nBuenos dias.\n";
+        let document = MarkdownParser::new().parse(md).unwrap();
+        assert_eq!(document.metadata.language.as_deref(), Some("es-ES"));
+        let rtf = RtfGenerator::new().generate(&document).unwrap();
+        assert!(rtf.contains("\\deflang3082"), "{rtf}");
+        // Coming back, \deflang lands on the metadata and the Markdown
+        // generator re-emits the front-matter key.
+        let tokens = lexer::tokenize(&rtf).unwrap();
+        let document = rtf_parser::RtfParser::new(tokens).parse().unwrap();
+        assert_eq!(document.metadata.language.as_deref(), Some("es-ES"));
+        let back = MarkdownGenerator::new().generate(&document);
+        assert!(back.starts_with("---\nlang: es-ES\n---\n\n# Hola"), "{back}");
+    }
+
+    #[test]
+    fn secure_markdown_to_rtf_honors_the_hygiene_policy() {
+        use crate::security::{UnicodeHygiene, UnicodePolicy};
+
+        let limits = SecurityLimits {
+            unicode_hygiene: UnicodeHygiene {
+                bidi_overrides: UnicodePolicy::Reject,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let err = secure_markdown_to_rtf("file\u{202E}cod.rtf", &limits).unwrap_err();
+        assert!(err.to_string().contains("U+202E"), "{err}");
+    }
+
+    #[test]
+    fn heading_offset_promotes_h3_to_h1() {
+        let rtf = markdown_to_rtf_with_heading_offset("### Deep Title\n\nBody\n", -2).unwrap();
+        assert!(rtf.contains("\\outlinelevel0"), "{rtf}");
+        assert!(!rtf.contains("\\outlinelevel2"), "{rtf}");
+        // Promoting past H1 clamps, with a parser warning per heading.
+        let (document, warnings) = MarkdownParser::new()
+            .with_heading_offset(-2)
+            .parse_with_warnings("## Too High\n")
+            .unwrap();
+        assert!(matches!(
+            document.content[0],
+            crate::conversion::rtf_parser::RtfNode::Heading { level: 1, .. }
+        ));
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("clamped to 1"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn extracted_tables_keep_full_cell_text_despite_the_markdown_cap() {
+        let rtf = "{\\rtf1 \\trowd\\intbl One\\par Two\\par Three\\cell a|b\\cell\\row}";
+        let tables = extract_tables(rtf).unwrap();
+        assert_eq!(
+            tables,
+            vec![ExtractedTable {
+                rows: vec![vec!["One\nTwo\nThree".to_string(), "a|b".to_string()]],
+            }]
+        );
+        // The same document converted under a cell cap truncates the
+        // Markdown, but the extraction above is untouched by that policy.
+        let config = PipelineConfig {
+            max_cell_length: Some(8),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert!(output.markdown.contains('…'), "{}", output.markdown);
+    }
+
+    #[test]
+    fn link_rewrites_apply_on_the_markdown_to_rtf_direction() {
+        let config = LinkRewriteConfig {
+            rules: vec![link_rewrite::LinkRewriteRule {
+                prefix: Some("http://intranet/".to_string()),
+                replacement: "https://wiki.example.com/".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let rtf =
+            markdown_to_rtf_with_link_rewrites("[HR](http://intranet/hr)\n", &config).unwrap();
+        assert!(rtf.contains("HYPERLINK \"https://wiki.example.com/hr\""), "{rtf}");
+        assert!(!rtf.contains("intranet"), "{rtf}");
+    }
+
+    #[test]
+    fn raw_rtf_blocks_survive_the_round_trip() {
+        // A benign \field the document model does not carry verbatim;
+        // the raw block keeps its exact RTF across the round trip (up to
+        // the canonical control-word delimiter spaces).
+        let md = "before\n\n```rtf-raw\n{\\field{\\*\\fldinst HYPERLINK \
+                  \"https://example.com\"}{\\fldrslt example}}\n```\n\nafter\n";
+        let rtf = markdown_to_rtf_with_raw_rtf(md).unwrap();
+        assert!(rtf.contains("{\\*\\lbrawstart}"), "{rtf}");
+        assert!(
+            rtf.contains("HYPERLINK \"https://example.com\""),
+            "{rtf}"
+        );
+        let back = rtf_to_markdown(&rtf).unwrap();
+        assert!(back.contains("```rtf-raw\n"), "{back}");
+        assert!(back.contains("HYPERLINK \"https://example.com\""), "{back}");
+        assert!(back.contains("{\\fldrslt example}"), "{back}");
+        assert!(back.contains("before"), "{back}");
+        assert!(back.contains("after"), "{back}");
+    }
+
+    #[test]
+    fn table_widths_and_alignment_round_trip_through_markdown() {
+        // RTF -> Markdown with width comments -> RTF must restore the
+        // original \cellx positions exactly and keep column alignment.
+        let src = "{\\rtf1 \\trowd\\cellx2415\\cellx5003\\cellx7160 \
+                   \\intbl\\qc Item\\cell\\ql Qty\\cell\\qr Price\\cell\\row}";
+        let document = rtf_parser::RtfParser::new(lexer::tokenize(src).unwrap())
+            .parse()
+            .unwrap();
+        let md = markdown_generator::MarkdownGenerator::new()
+            .with_width_comments(true)
+            .generate(&document);
+        let rtf = markdown_to_rtf(&md).unwrap();
+        assert!(
+            rtf.contains("\\cellx2415\\cellx5003\\cellx7160"),
+            "widths drifted: {rtf}"
+        );
+        assert!(rtf.contains("\\qc Item"), "{rtf}");
+        assert!(rtf.contains("\\qr Price"), "{rtf}");
+    }
+
+    #[test]
+    fn table_documents_take_the_pipeline() {
+        let path = |rtf| resolve_conversion_path(rtf, ConversionMode::Auto).unwrap();
+        assert_eq!(
+            path("{\\rtf1 \\trowd\\intbl A\\cell\\row}"),
+            ConversionPath::Pipeline
+        );
+        assert_eq!(path("{\\rtf1 plain}"), ConversionPath::Simple);
+        // Merely mentioning \trowd in text (escaped backslash) is not a
+        // table; the old substring heuristic fired on this.
+        assert_eq!(
+            path("{\\rtf1 the \\\\trowd word\\par}"),
+            ConversionPath::Simple
+        );
+    }
+
+    #[test]
+    fn explicit_modes_override_the_heuristic_and_are_reported() {
+        let rtf = "{\\rtf1 \\trowd\\intbl A\\cell B\\cell\\row}";
+        let (simple, path) = rtf_to_markdown_with_mode(rtf, ConversionMode::Simple).unwrap();
+        assert_eq!(path, ConversionPath::Simple);
+        let (auto, path) = rtf_to_markdown_with_mode(rtf, ConversionMode::Auto).unwrap();
+        assert_eq!(path, ConversionPath::Pipeline);
+        // Both paths share the parser and generator, so forcing the
+        // simple path changes diagnostics, not output.
+        assert_eq!(simple, auto);
+
+        let (_, path) =
+            rtf_to_markdown_with_mode("{\\rtf1 plain\\par}", ConversionMode::Pipeline).unwrap();
+        assert_eq!(path, ConversionPath::Pipeline);
+    }
+
+    #[test]
+    fn canonicalize_erases_third_party_header_ordering() {
+        // The same document with its fonttbl entries shuffled must
+        // canonicalize to identical bytes, and stay stable from there.
+        let a = "{\\rtf1{\\fonttbl{\\f1 Courier New;}{\\f0 Arial;}}Hi {\\f1 mono}\\par}";
+        let b = "{\\rtf1{\\fonttbl{\\f0 Arial;}{\\f1 Courier New;}}Hi {\\f1 mono}\\par}";
+        assert_eq!(canonicalize_rtf(a).unwrap(), canonicalize_rtf(b).unwrap());
+
+        let canonical = canonicalize_rtf(&canonicalize_rtf(a).unwrap()).unwrap();
+        assert_eq!(canonicalize_rtf(&canonical).unwrap(), canonical);
+    }
+
+    #[test]
+    fn legacy_rtf_uses_cp1252_escapes() {
+        let modern = markdown_to_rtf("café").unwrap();
+        assert!(modern.contains("\\u233?"), "{modern}");
+
+        let legacy = markdown_to_rtf_legacy("café").unwrap();
+        assert!(legacy.contains("\\'e9"), "{legacy}");
+        assert!(!legacy.contains("\\u233"), "{legacy}");
+    }
+
+    #[test]
+    fn deep_validation_verdicts_match_full_conversions() {
+        // A mixed corpus: clean, recoverable two ways, and broken two ways.
+        let corpus = [
+            "{\\rtf1 Hello \\b World\\b0\\par}",
+            "{\\rtf1 before}} after\\par}",
+            "{\\rtf1{\\fonttbl",
+            "{\\rtf1 broken\\",
+            "just some text",
+        ];
+        let limits = SecurityLimits::default();
+        for rtf in corpus {
+            let full = DocumentPipeline::with_defaults().process(rtf);
+            let deep = validate_rtf_deep(rtf, &limits);
+            assert_eq!(full.is_ok(), deep.is_ok(), "verdict mismatch for {rtf:?}");
+        }
+    }
+
+    #[test]
+    fn deep_validation_reports_diagnostics_without_output() {
+        let output =
+            validate_rtf_deep("{\\rtf1 before}} after\\par}", &SecurityLimits::default())
+                .unwrap();
+        assert!(output.markdown.is_empty());
+        assert!(output.validation_results.iter().any(|r| r.code == "RTF104"));
+        assert!(!output.recovery_actions.is_empty());
+    }
+
+    #[test]
+    fn plain_text_extraction() {
+        let text = extract_plain_text("{\\rtf1 Hello \\b World\\b0\\par}").unwrap();
+        assert_eq!(text.trim(), "Hello World");
+    }
+}
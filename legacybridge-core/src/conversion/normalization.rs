@@ -0,0 +1,213 @@
+//! Unicode normalization for converted text.
+//!
+//! Mac-authored RTF stores accented characters decomposed (NFD) while
+//! Windows sources compose them (NFC), so converted output mixes forms
+//! depending on where a document came from and downstream diff tools
+//! report phantom changes. This pass walks a parsed document's text
+//! nodes and brings them into one [`NormalizationForm`]. Both parsers
+//! run it after the hygiene scrub, so both conversion directions and
+//! plain-text extraction see the same form.
+//!
+//! Runs carrying the `"Code"` character style are exempt unless the
+//! caller opts in: normalization can change what source code means
+//! (decomposed identifiers stop matching their composed spelling), and
+//! code is quoted, not prose.
+
+use super::rtf_parser::{RtfDocument, RtfNode};
+use serde::{Deserialize, Serialize};
+use unicode_normalization::{is_nfc_quick, is_nfd_quick, IsNormalized, UnicodeNormalization};
+
+/// Which Unicode normalization form converted text is brought into.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizationForm {
+    /// Canonical composition, the form Windows tooling writes; the
+    /// default, so output is diff-stable regardless of the source.
+    #[default]
+    Nfc,
+    /// Canonical decomposition, for consumers that expect Mac-style
+    /// decomposed text.
+    Nfd,
+    /// Leave text exactly as the source document wrote it.
+    None,
+}
+
+/// Bring every text node in `document` into `form`, skipping
+/// `"Code"`-styled runs unless `normalize_code` is set. The applied form
+/// is recorded in the document metadata for auditability.
+///
+/// Adjacent text siblings are normalized as one string (and coalesced
+/// into one node): the RTF parser emits a separate node per `\uN`
+/// escape, so a combining mark often sits in its own node right after
+/// the base character it modifies.
+pub fn normalize(document: &mut RtfDocument, form: NormalizationForm, normalize_code: bool) {
+    document.metadata.normalization = Some(form);
+    if form == NormalizationForm::None {
+        return;
+    }
+    let mut work: Vec<&mut Vec<RtfNode>> = vec![&mut document.content];
+    while let Some(content) = work.pop() {
+        normalize_siblings(content, form);
+        for node in content.iter_mut() {
+            match node {
+                RtfNode::Formatted { format, content } => {
+                    if normalize_code || format.style_name.as_deref() != Some("Code") {
+                        work.push(content);
+                    }
+                }
+                RtfNode::Hyperlink { content, .. }
+                | RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => work.push(content),
+                RtfNode::Table(table) => {
+                    for row in &mut table.rows {
+                        for cell in &mut row.cells {
+                            work.push(&mut cell.content);
+                        }
+                    }
+                }
+                // Raw RTF is a verbatim passthrough region by contract.
+                RtfNode::Text(_)
+                | RtfNode::Image { .. }
+                | RtfNode::RawRtf { .. }
+                | RtfNode::LineBreak
+                | RtfNode::PageBreak => {}
+            }
+        }
+    }
+}
+
+/// Normalize each maximal run of adjacent `Text` siblings in `content`
+/// as one string, replacing the run with a single node.
+fn normalize_siblings(content: &mut Vec<RtfNode>, form: NormalizationForm) {
+    let mut i = 0;
+    while i < content.len() {
+        if !matches!(content[i], RtfNode::Text(_)) {
+            i += 1;
+            continue;
+        }
+        let mut j = i + 1;
+        while j < content.len() && matches!(content[j], RtfNode::Text(_)) {
+            j += 1;
+        }
+        if j == i + 1 {
+            if let RtfNode::Text(text) = &mut content[i] {
+                if let Some(normalized) = normalize_text(text, form) {
+                    *text = normalized;
+                }
+            }
+        } else {
+            let joined: String = content[i..j]
+                .iter()
+                .map(|node| match node {
+                    RtfNode::Text(text) => text.as_str(),
+                    _ => unreachable!("run contains only text nodes"),
+                })
+                .collect();
+            let normalized = normalize_text(&joined, form).unwrap_or(joined);
+            content.splice(i..j, std::iter::once(RtfNode::Text(normalized)));
+        }
+        i += 1;
+    }
+}
+
+/// Normalize one text run; `None` means it already was in form, so
+/// callers skip the reallocation on the overwhelmingly common path.
+fn normalize_text(text: &str, form: NormalizationForm) -> Option<String> {
+    match form {
+        NormalizationForm::Nfc => match is_nfc_quick(text.chars()) {
+            IsNormalized::Yes => None,
+            _ => Some(text.nfc().collect()),
+        },
+        NormalizationForm::Nfd => match is_nfd_quick(text.chars()) {
+            IsNormalized::Yes => None,
+            _ => Some(text.nfd().collect()),
+        },
+        NormalizationForm::None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::rtf_parser::{DocumentMetadata, TextFormat};
+
+    fn document_with(nodes: Vec<RtfNode>) -> RtfDocument {
+        RtfDocument {
+            metadata: DocumentMetadata::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content: vec![RtfNode::Paragraph {
+                direction: Default::default(),
+                spacing: Default::default(),
+                content: nodes,
+            }],
+        }
+    }
+
+    fn first_text(document: &RtfDocument) -> &str {
+        let RtfNode::Paragraph { content, .. } = &document.content[0] else {
+            panic!("paragraph");
+        };
+        let RtfNode::Text(text) = &content[0] else {
+            panic!("text");
+        };
+        text
+    }
+
+    #[test]
+    fn nfc_recomposes_decomposed_accents() {
+        // "café" with a combining acute, as Mac RTF writes it.
+        let mut document = document_with(vec![RtfNode::Text("cafe\u{0301}".to_string())]);
+        normalize(&mut document, NormalizationForm::Nfc, false);
+        assert_eq!(first_text(&document), "caf\u{E9}");
+        assert_eq!(document.metadata.normalization, Some(NormalizationForm::Nfc));
+    }
+
+    #[test]
+    fn nfd_decomposes_composed_accents() {
+        let mut document = document_with(vec![RtfNode::Text("caf\u{E9}".to_string())]);
+        normalize(&mut document, NormalizationForm::Nfd, false);
+        assert_eq!(first_text(&document), "cafe\u{0301}");
+    }
+
+    #[test]
+    fn none_leaves_text_alone_but_still_records_the_form() {
+        let mut document = document_with(vec![RtfNode::Text("cafe\u{0301}".to_string())]);
+        normalize(&mut document, NormalizationForm::None, false);
+        assert_eq!(first_text(&document), "cafe\u{0301}");
+        assert_eq!(
+            document.metadata.normalization,
+            Some(NormalizationForm::None)
+        );
+    }
+
+    #[test]
+    fn code_styled_runs_are_exempt_by_default() {
+        let code_run = RtfNode::Formatted {
+            format: TextFormat {
+                style_name: Some("Code".to_string()),
+                ..Default::default()
+            },
+            content: vec![RtfNode::Text("ide\u{0301}e_fixe".to_string())],
+        };
+        let mut document = document_with(vec![code_run.clone()]);
+        normalize(&mut document, NormalizationForm::Nfc, false);
+        assert_eq!(document.content[0], RtfNode::Paragraph {
+            direction: Default::default(),
+            spacing: Default::default(),
+            content: vec![code_run],
+        });
+
+        // Opting in normalizes the run like any other text.
+        normalize(&mut document, NormalizationForm::Nfc, true);
+        let RtfNode::Paragraph { content, .. } = &document.content[0] else {
+            panic!("paragraph");
+        };
+        let RtfNode::Formatted { content, .. } = &content[0] else {
+            panic!("formatted run");
+        };
+        assert_eq!(content[0], RtfNode::Text("id\u{E9}e_fixe".to_string()));
+    }
+}
@@ -0,0 +1,268 @@
+//! Post-parse document cleanup.
+//!
+//! Converted legacy documents lean on runs of empty paragraphs
+//! (`\par \par \par`) for vertical spacing and leave trailing whitespace
+//! on paragraph text, which reads as an accidental hard break in
+//! Markdown. This pass runs right after parsing: it collapses long empty
+//! runs, drops paragraphs that carry only formatting toggles, and trims
+//! paragraph tails, reporting what it changed so callers can audit the
+//! edits. The [`CleanupPolicy`] tunes or disables it.
+
+use super::super::rtf_parser::{RtfDocument, RtfNode};
+use serde::{Deserialize, Serialize};
+
+/// Tunables for the post-parse cleanup pass; the defaults clean up
+/// legacy spacing habits without touching deliberate short gaps.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CleanupPolicy {
+    /// Run the pass at all; off keeps the parser's output untouched.
+    pub enabled: bool,
+    /// Runs of more than this many consecutive empty paragraphs collapse
+    /// to a single one.
+    pub max_empty_paragraphs: usize,
+    /// The run limit used instead under
+    /// [`preserve_formatting`](super::PipelineConfig::preserve_formatting):
+    /// vertical rhythm is formatting too, so preserving it raises the
+    /// threshold rather than disabling cleanup.
+    pub preserve_formatting_max: usize,
+}
+
+impl Default for CleanupPolicy {
+    fn default() -> Self {
+        CleanupPolicy {
+            enabled: true,
+            max_empty_paragraphs: 1,
+            preserve_formatting_max: 3,
+        }
+    }
+}
+
+/// What one cleanup run changed, for the `RTF112` Info results.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CleanupCounts {
+    /// Empty paragraphs removed when collapsing runs over the limit.
+    pub empty_paragraphs_removed: usize,
+    /// Paragraphs dropped for carrying only formatting toggles.
+    pub toggle_only_paragraphs_removed: usize,
+    /// Paragraphs whose trailing whitespace was trimmed.
+    pub paragraphs_trimmed: usize,
+}
+
+impl CleanupCounts {
+    pub fn is_clean(&self) -> bool {
+        *self == CleanupCounts::default()
+    }
+}
+
+/// How a top-level node takes part in run collapsing.
+enum Kind {
+    /// A paragraph whose subtree holds only whitespace text.
+    Empty,
+    /// Like [`Kind::Empty`] but with at least one formatting toggle;
+    /// dropped outright rather than collapsed.
+    ToggleOnly,
+    /// Anything else; ends the current empty run.
+    Content,
+}
+
+/// Clean the document in place: trim paragraph tails, drop toggle-only
+/// paragraphs, and collapse runs of more than `max_empty` consecutive
+/// empty paragraphs down to the single blank line ordinary paragraph
+/// separation already provides. Returns what changed.
+pub fn clean(document: &mut RtfDocument, max_empty: usize) -> CleanupCounts {
+    let mut counts = CleanupCounts::default();
+    let content = std::mem::take(&mut document.content);
+    let mut out = Vec::with_capacity(content.len());
+    // Empty paragraphs of the current run, buffered until something else
+    // ends it and the collapse decision can be made.
+    let mut run: Vec<RtfNode> = Vec::new();
+
+    let flush_run = |run: &mut Vec<RtfNode>, out: &mut Vec<RtfNode>, counts: &mut CleanupCounts| {
+        // Short runs are deliberate spacing; long ones are the legacy
+        // vertical-spacing idiom and go entirely.
+        if run.len() > max_empty {
+            counts.empty_paragraphs_removed += run.len();
+            run.clear();
+        }
+        out.append(run);
+    };
+
+    for mut node in content {
+        match classify(&node) {
+            Kind::Empty => run.push(node),
+            // A toggle-only paragraph never earns a blank line, and
+            // dropping it joins the empty runs around it.
+            Kind::ToggleOnly => counts.toggle_only_paragraphs_removed += 1,
+            Kind::Content => {
+                flush_run(&mut run, &mut out, &mut counts);
+                if trim_paragraph_tail(&mut node) {
+                    counts.paragraphs_trimmed += 1;
+                }
+                out.push(node);
+            }
+        }
+    }
+    flush_run(&mut run, &mut out, &mut counts);
+    document.content = out;
+    counts
+}
+
+/// Classify a top-level node for run collapsing. Only paragraphs can be
+/// empty; anything carrying an image, a link, a raw block or real text
+/// is content.
+fn classify(node: &RtfNode) -> Kind {
+    let RtfNode::Paragraph { content, .. } = node else {
+        return Kind::Content;
+    };
+    let mut toggles = false;
+    let mut work: Vec<&RtfNode> = content.iter().collect();
+    while let Some(node) = work.pop() {
+        match node {
+            RtfNode::Text(text) => {
+                if !text.trim().is_empty() {
+                    return Kind::Content;
+                }
+            }
+            RtfNode::Formatted { content, .. } => {
+                toggles = true;
+                work.extend(content.iter());
+            }
+            _ => return Kind::Content,
+        }
+    }
+    if toggles {
+        Kind::ToggleOnly
+    } else {
+        Kind::Empty
+    }
+}
+
+/// Trim trailing whitespace from a paragraph's text: whitespace-only
+/// trailing runs go entirely, and the last remaining direct text run
+/// loses its tail. Returns whether anything changed.
+fn trim_paragraph_tail(node: &mut RtfNode) -> bool {
+    let RtfNode::Paragraph { content, .. } = node else {
+        return false;
+    };
+    let mut changed = false;
+    while matches!(content.last(), Some(RtfNode::Text(text)) if text.trim().is_empty()) {
+        content.pop();
+        changed = true;
+    }
+    if let Some(RtfNode::Text(text)) = content.last_mut() {
+        let trimmed = text.trim_end();
+        if trimmed.len() != text.len() {
+            *text = trimmed.to_string();
+            changed = true;
+        }
+    }
+    changed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::rtf_parser::{Direction, ParagraphSpacing, TextFormat};
+
+    fn paragraph(content: Vec<RtfNode>) -> RtfNode {
+        RtfNode::Paragraph {
+            direction: Direction::default(),
+            spacing: ParagraphSpacing::default(),
+            content,
+        }
+    }
+
+    fn text_paragraph(text: &str) -> RtfNode {
+        paragraph(vec![RtfNode::Text(text.to_string())])
+    }
+
+    fn document(content: Vec<RtfNode>) -> RtfDocument {
+        RtfDocument {
+            metadata: Default::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content,
+        }
+    }
+
+    #[test]
+    fn long_empty_runs_are_removed() {
+        let mut doc = document(vec![
+            text_paragraph("one"),
+            paragraph(Vec::new()),
+            paragraph(Vec::new()),
+            paragraph(Vec::new()),
+            text_paragraph("two"),
+        ]);
+        let counts = clean(&mut doc, 1);
+        assert_eq!(counts.empty_paragraphs_removed, 3);
+        assert_eq!(doc.content.len(), 2);
+
+        // Runs at or under the limit are deliberate spacing and stay.
+        let mut doc = document(vec![
+            text_paragraph("one"),
+            paragraph(Vec::new()),
+            paragraph(Vec::new()),
+            text_paragraph("two"),
+        ]);
+        let counts = clean(&mut doc, 2);
+        assert!(counts.is_clean());
+        assert_eq!(doc.content.len(), 4);
+    }
+
+    #[test]
+    fn toggle_only_paragraphs_are_dropped_and_join_runs() {
+        // `\par \b \b0 \par` style: a paragraph of toggles around
+        // whitespace splits what is really one empty run.
+        let mut doc = document(vec![
+            text_paragraph("one"),
+            text_paragraph(" "),
+            paragraph(vec![RtfNode::Formatted {
+                format: TextFormat {
+                    bold: true,
+                    ..Default::default()
+                },
+                content: vec![RtfNode::Text(" ".to_string())],
+            }]),
+            text_paragraph(" "),
+            text_paragraph("two"),
+        ]);
+        let counts = clean(&mut doc, 1);
+        assert_eq!(counts.toggle_only_paragraphs_removed, 1);
+        assert_eq!(counts.empty_paragraphs_removed, 2);
+        assert_eq!(doc.content.len(), 2);
+    }
+
+    #[test]
+    fn paragraph_tails_are_trimmed() {
+        // Two trailing spaces would read as a Markdown hard break.
+        let mut doc = document(vec![paragraph(vec![
+            RtfNode::Text("kept  ".to_string()),
+            RtfNode::Text("  ".to_string()),
+        ])]);
+        let counts = clean(&mut doc, 1);
+        assert_eq!(counts.paragraphs_trimmed, 1);
+        assert_eq!(
+            doc.content[0],
+            paragraph(vec![RtfNode::Text("kept".to_string())])
+        );
+    }
+
+    #[test]
+    fn meaningful_paragraphs_are_never_empty() {
+        // An image-only paragraph has no text but is content.
+        let mut doc = document(vec![
+            text_paragraph(" "),
+            paragraph(vec![RtfNode::Image {
+                src: "x.png".to_string(),
+                alt: String::new(),
+                title: None,
+            }]),
+            text_paragraph(" "),
+        ]);
+        let counts = clean(&mut doc, 1);
+        assert!(counts.is_clean());
+        assert_eq!(doc.content.len(), 3);
+    }
+}
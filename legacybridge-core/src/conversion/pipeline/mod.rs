@@ -0,0 +1,2753 @@
+//! Document conversion pipeline.
+//!
+//! The pipeline runs a conversion through discrete stages - validation,
+//! tokenization, parsing, generation, post-validation - collecting
+//! diagnostics along the way. The simple path in [`crate::conversion`] skips
+//! this machinery for trivial documents.
+
+pub mod cleanup;
+pub mod recovery;
+pub mod validation;
+mod verify;
+
+use super::breadcrumb;
+use super::cancel::{self, CancellationToken};
+use super::clock::{ClockTimezone, ConversionClock};
+use super::context::{self, ConversionContext};
+use super::encoding::OutputEncoding;
+use super::environment::ConversionEnvironment;
+use super::features::FeatureUsage;
+use super::control_word_extensions::ControlWordExtensions;
+use super::font_map::FontMap;
+use super::link_rewrite::{LinkRewriteConfig, MAX_LISTED_LINKS, UnmatchedLinkPolicy};
+use super::forms::FormField;
+use super::integrity;
+use super::unicode_hygiene;
+use super::lexer::{tokenize, tokenize_with_cancellation, RtfToken};
+use super::markdown_generator::{MarkdownGenerator, OutlineEntry, RevisionMode};
+use super::memory;
+use super::normalization::NormalizationForm;
+use super::rtf_parser::{
+    Annotation, DocumentMetadata, PlaceholderPolicy, RtfDocument, RtfNode, RtfParser,
+};
+use super::simd_lexer::CpuFeatures;
+use super::template::TemplateDiff;
+use super::terminology::{TerminologyConfig, MAX_LISTED_MATCHES};
+use crate::security::SanitizationMode;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+pub use cleanup::CleanupPolicy;
+pub use recovery::{RecoveryAction, RecoveryKind};
+pub use validation::{ValidationLevel, ValidationResult, Validator};
+
+/// Errors surfaced by any conversion path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConversionError {
+    ParseError(String),
+    /// A validation failure, carrying the [`ValidationResult`] code
+    /// (`RTF003`, ...) that produced it so downstream consumers can
+    /// categorize without string matching.
+    ValidationError { code: String, message: String },
+    GenerationError(String),
+    ResourceLimit(String),
+    /// The caller cancelled the conversion via a
+    /// [`CancellationToken`](super::cancel::CancellationToken).
+    Cancelled,
+    /// A JSON payload (options, report, envelope) declared a contract
+    /// major newer than this build speaks; see
+    /// [`contracts`](crate::contracts).
+    UnsupportedContractVersion { found: u32 },
+}
+
+impl ConversionError {
+    pub fn parse(message: impl Into<String>) -> Self {
+        ConversionError::ParseError(message.into())
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        ConversionError::ValidationError {
+            code: "VAL000".to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn validation_with_code(code: impl Into<String>, message: impl Into<String>) -> Self {
+        ConversionError::ValidationError {
+            code: code.into(),
+            message: message.into(),
+        }
+    }
+
+    pub fn generation(message: impl Into<String>) -> Self {
+        ConversionError::GenerationError(message.into())
+    }
+
+    pub fn resource_limit(message: impl Into<String>) -> Self {
+        ConversionError::ResourceLimit(message.into())
+    }
+
+    /// Stable category name used by metrics, the UI and the FFI bridge.
+    pub fn category(&self) -> &'static str {
+        match self {
+            ConversionError::ParseError(_) => "parse",
+            ConversionError::ValidationError { .. } => "validation",
+            ConversionError::GenerationError(_) => "generation",
+            ConversionError::ResourceLimit(_) => "resource_limit",
+            ConversionError::Cancelled => "cancelled",
+            ConversionError::UnsupportedContractVersion { .. } => "contract",
+        }
+    }
+
+    /// Stable numeric error code for C ABI consumers (0 is success).
+    pub fn error_code(&self) -> i32 {
+        match self {
+            ConversionError::ParseError(_) => -2,
+            ConversionError::ValidationError { .. } => -3,
+            ConversionError::GenerationError(_) => -4,
+            ConversionError::ResourceLimit(_) => -5,
+            ConversionError::Cancelled => -6,
+            ConversionError::UnsupportedContractVersion { .. } => -7,
+        }
+    }
+}
+
+impl std::fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConversionError::ParseError(msg) => write!(f, "parse error: {msg}"),
+            ConversionError::ValidationError { code, message } => {
+                write!(f, "validation error [{code}]: {message}")
+            }
+            ConversionError::GenerationError(msg) => write!(f, "generation error: {msg}"),
+            ConversionError::ResourceLimit(msg) => write!(f, "resource limit: {msg}"),
+            ConversionError::Cancelled => write!(f, "{}", cancel::CANCELLED_MESSAGE),
+            ConversionError::UnsupportedContractVersion { found } => write!(
+                f,
+                "unsupported contract version {found}: this build accepts versions {}..={}",
+                crate::contracts::MIN_CONTRACT_VERSION,
+                crate::contracts::CONTRACT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {}
+
+/// Run one hook with panic isolation: a panicking hook becomes a
+/// generation error naming the hook instead of unwinding into the caller.
+fn run_isolated(name: &str, hook: impl FnOnce() -> ConversionResult<()>) -> ConversionResult<()> {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(hook)) {
+        Ok(result) => result,
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "panic".to_string());
+            Err(ConversionError::generation(format!(
+                "hook '{name}' panicked: {message}"
+            )))
+        }
+    }
+}
+
+/// Map a `Result<_, String>` stage message to its error, recognizing the
+/// cancellation sentinel the lexer and parser return.
+fn map_cancellable_message(message: String) -> ConversionError {
+    if message == cancel::CANCELLED_MESSAGE {
+        ConversionError::Cancelled
+    } else {
+        ConversionError::parse(message)
+    }
+}
+
+pub type ConversionResult<T> = Result<T, ConversionError>;
+
+/// An inclusive 1-based page range, where pages are delimited by `\page`
+/// and `\sect` boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageRange {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// How reviewer comments (`\annotation` groups) show up in the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationMode {
+    /// Drop comments from the output, reporting how many were stripped.
+    #[default]
+    Strip,
+    /// Leave `[note N]` markers at the anchors and append the comments as
+    /// `[note N: AB] text` margin-note lines after the body.
+    Footnotes,
+    /// Leave `[note N]` markers at the anchors and append a trailing
+    /// `## Comments` section referencing them.
+    Section,
+}
+
+/// The last stage the pipeline runs; later stages are skipped.
+///
+/// Stopping after [`Stage::Parse`] yields the full diagnostics -
+/// validation results, feature usage, recovery actions, metadata - at a
+/// fraction of the cost, for pre-flighting documents without producing
+/// output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    /// Stop after parsing and template application; no Markdown is
+    /// generated and [`PipelineOutput::markdown`] is empty.
+    Parse,
+    /// Run the pipeline to completion.
+    #[default]
+    Generate,
+}
+
+/// Tunable pipeline behavior; the defaults match the desktop app.
+///
+/// A field is added most releases, so construct instances with struct
+/// update syntax - `PipelineConfig { strict_validation: true,
+/// ..Default::default() }` - rather than exhaustive literals, or stay on
+/// the [`Conversion`](super::builder::Conversion) builder, which is the
+/// stable surface for library consumers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineConfig {
+    /// Treat validation warnings as errors.
+    pub strict_validation: bool,
+    /// Also run the validators at the strict level and report what a
+    /// `strict_validation` run would have flagged on top of this one, in
+    /// [`PipelineOutput::strict_delta`]. No-op when the run is already
+    /// strict. The validators are cheap relative to parsing, so this does
+    /// not run the pipeline twice.
+    pub compare_validation: bool,
+    /// Attempt to repair structurally broken documents instead of failing.
+    pub auto_recovery: bool,
+    /// Keep character formatting (bold/italic/...) in the output.
+    pub preserve_formatting: bool,
+    /// Emit output compatible with legacy viewers: Markdown restricted to
+    /// core syntax (no HTML, no GFM strikethrough; see
+    /// [`MarkdownGenerator::with_legacy_mode`]), and any RTF generated
+    /// under this config restricted to RTF 1.5 (cp1252 `\'xx` escapes, no
+    /// stylesheet, folded lines; see
+    /// [`RtfGenerator::with_legacy_mode`](super::rtf_generator::RtfGenerator::with_legacy_mode)).
+    /// [`PipelineConfig::capabilities`] reports the resulting dialect.
+    pub legacy_mode: bool,
+    /// Path to a JSON [`FontMap`](super::font_map::FontMap) overriding the
+    /// built-in font substitutions.
+    pub font_map_path: Option<String>,
+    /// Path to a JSON
+    /// [`ControlWordExtensions`](super::control_word_extensions::ControlWordExtensions)
+    /// map aliasing non-standard control words from niche writers.
+    pub control_word_extensions_path: Option<String>,
+    /// Convert only this page range, for paging through large documents.
+    pub page_range: Option<PageRange>,
+    /// Name of a [`TemplateSystem`] template to apply after parsing.
+    pub template: Option<String>,
+    /// `{{variable}}` values overlaid on the template's own.
+    pub template_variables: HashMap<String, String>,
+    /// Report what the template would change instead of applying it: the
+    /// output stays untransformed and the diff lands in
+    /// [`PipelineOutput::template_diff`].
+    pub dry_run: bool,
+    /// How tracked changes (`\revised`/`\deleted`) are resolved.
+    pub revision_mode: RevisionMode,
+    /// Lift `\formfield` groups into [`PipelineOutput::form_fields`],
+    /// replacing them in the flow with their value text.
+    pub extract_form_fields: bool,
+    /// How reviewer comments are rendered.
+    pub annotation_mode: AnnotationMode,
+    /// Placeholder text for equation and drawing object groups.
+    pub placeholders: PlaceholderPolicy,
+    /// Post-parse cleanup of empty-paragraph runs, toggle-only
+    /// paragraphs and trailing paragraph whitespace; on by default, with
+    /// the edits reported as `RTF112` Info results. Under
+    /// [`preserve_formatting`](Self::preserve_formatting) the policy's
+    /// raised run limit applies instead of its base one.
+    pub cleanup: CleanupPolicy,
+    /// Re-flow paragraph lines at this column for line-oriented diffing
+    /// (default off: one line per paragraph).
+    pub wrap_width: Option<usize>,
+    /// Record explicit paragraph spacing as HTML comments in the Markdown
+    /// (default off: spacing is dropped).
+    pub spacing_comments: bool,
+    /// Record table column widths as `<!-- widths: ... -->` comments in
+    /// the Markdown, so converting back to RTF restores the original
+    /// `\cellx` positions (default off: widths are dropped).
+    pub width_comments: bool,
+    /// Truncate flattened GFM table cells longer than this many
+    /// characters, appending an ellipsis and reporting the loss as an
+    /// `RTF118` warning (default off: cells keep their full length).
+    /// Multi-paragraph cells flatten with `<br>` separators either way.
+    pub max_cell_length: Option<usize>,
+    /// With [`max_cell_length`](Self::max_cell_length) set, emit tables
+    /// containing an over-cap cell as HTML `<table>` elements instead of
+    /// truncating, keeping the full content for HTML-capable renderers
+    /// (default off). Suppressed in legacy mode.
+    pub fallback_html_tables: bool,
+    /// Last stage to run; the default runs the whole pipeline.
+    pub stop_after: Stage,
+    /// How output files are encoded when a caller writes them to disk
+    /// (line endings, BOM); the in-memory output is unaffected. Written
+    /// RTF always uses [`OutputEncoding::rtf`] regardless.
+    pub output_encoding: OutputEncoding,
+    /// Re-parse the generated Markdown and check it still matches the
+    /// document (text and heading/list/table structure); mismatches are
+    /// reported as `RTF110` warnings (errors under `strict_validation`).
+    /// Default: on in debug builds, off in release.
+    pub verify_output: bool,
+    /// What happens to embedded payloads (`\pict`/`\object`) that violate
+    /// the [`SecurityLimits`](crate::security::SecurityLimits)
+    /// embedded-object caps: a placeholder plus a warning, or rejection.
+    pub sanitization_mode: SanitizationMode,
+    /// Append an integrity signature trailer to the Markdown (input and
+    /// output hashes, converter version, options fingerprint, timestamp)
+    /// for downstream verification via
+    /// [`integrity::verify_integrity`](super::integrity::verify_integrity).
+    /// Default off: the trailer is audit metadata most consumers don't
+    /// want in their documents.
+    pub integrity: bool,
+    /// Accept RTF fragments without the outer `{\rtf1 ...}` wrapper, as
+    /// VFP9 memo fields store them: the validator reports `RTF113` Info
+    /// instead of the `RTF003` error and parsing runs under a minimal
+    /// synthetic header. [`PipelineMetadata::is_fragment`] records when
+    /// this happened. Default off: a missing header usually means the
+    /// input is not RTF at all.
+    pub allow_fragment: bool,
+    /// Shift heading levels by this many steps before generation, for
+    /// embedding converted content under an existing document's
+    /// hierarchy: positive demotes (`+1` turns H1 into H2), negative
+    /// promotes. The useful range is -2 to +5; levels are clamped to
+    /// 1..=6 either way, with an `RTF114` warning when clamping flattens
+    /// structure. The outline is computed against the shifted levels.
+    /// Default 0: headings pass through unchanged.
+    pub heading_offset: i8,
+    /// Unicode normalization form the parsed text is brought into, so
+    /// output bytes do not depend on whether the source composed its
+    /// accents (Windows, NFC) or decomposed them (Mac, NFD). `"Code"`
+    /// -styled runs are left as written. Default NFC; the applied form
+    /// is recorded in [`PipelineMetadata::normalization`].
+    pub normalization: NormalizationForm,
+    /// Hyperlink rewrite rules applied after parsing; see
+    /// [`LinkRewriteConfig`](super::link_rewrite::LinkRewriteConfig).
+    /// Rewrites are reported as `RTF116` Info results and unmatched
+    /// absolute links as `RTF117` warnings when the config's policy asks
+    /// for them. Takes precedence over
+    /// [`link_rewrite_path`](Self::link_rewrite_path) when both are set.
+    pub link_rewrite: Option<LinkRewriteConfig>,
+    /// Path to a JSON [`LinkRewriteConfig`](super::link_rewrite::LinkRewriteConfig)
+    /// file, for hosts that keep the rules alongside the settings file.
+    pub link_rewrite_path: Option<String>,
+    /// Terminology rules enforced on the generated Markdown; see
+    /// [`TerminologyConfig`](super::terminology::TerminologyConfig).
+    /// Replacements are reported as `RTF119` Info results and flag-only
+    /// (banned) matches as `RTF120` warnings, each carrying the match's
+    /// line and column; code spans, code blocks and URLs are exempt.
+    /// Takes precedence over [`terminology_path`](Self::terminology_path)
+    /// when both are set.
+    pub terminology: Option<TerminologyConfig>,
+    /// Path to a terminology config file - the JSON
+    /// [`TerminologyConfig`](super::terminology::TerminologyConfig) form,
+    /// or `.csv` rows of `pattern,replacement[,case[,boundary]]` - for
+    /// hosts that keep the word list alongside the settings file.
+    pub terminology_path: Option<String>,
+    /// Document size cap enforced by the validators (`RTF002`/`MD002`),
+    /// in bytes. `None` keeps the default
+    /// [`MAX_DOCUMENT_SIZE`](validation::MAX_DOCUMENT_SIZE), which
+    /// protects hosts that move content over IPC; file-backed hosts
+    /// whose inputs never leave disk raise it.
+    pub max_document_size: Option<usize>,
+    /// Freeze the conversion's clock at this instant (RFC 3339 or
+    /// `YYYY-MM-DDTHH:MM:SS`): templates stamp it into
+    /// `{{date}}`/`{{time}}` and the integrity block records it as the
+    /// signing time, so repeated runs are byte-identical for diff-based
+    /// regression pipelines. `None` falls back to the
+    /// [`FIXED_TIMESTAMP_VAR`](super::clock::FIXED_TIMESTAMP_VAR)
+    /// environment variable, then to real time.
+    pub fixed_timestamp: Option<String>,
+    /// Timezone the clock reads real time in; irrelevant under a fixed
+    /// timestamp. Named IANA zones need the `named-timezones` feature.
+    pub timezone: ClockTimezone,
+    /// Collect performance counters - input size, token and node counts,
+    /// tokens per second, the SIMD level the byte scanner selects on
+    /// this CPU, and per-stage wall-clock durations - in
+    /// [`PipelineOutput::stats`], for diagnosing slow documents without
+    /// attaching a profiler. Default off: the counters cost a few clock
+    /// reads and nobody looks at them on routine runs.
+    pub collect_stats: bool,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        PipelineConfig {
+            strict_validation: false,
+            compare_validation: false,
+            auto_recovery: true,
+            preserve_formatting: true,
+            legacy_mode: false,
+            font_map_path: None,
+            control_word_extensions_path: None,
+            page_range: None,
+            template: None,
+            template_variables: HashMap::new(),
+            dry_run: false,
+            revision_mode: RevisionMode::default(),
+            extract_form_fields: false,
+            annotation_mode: AnnotationMode::default(),
+            placeholders: PlaceholderPolicy::default(),
+            cleanup: CleanupPolicy::default(),
+            wrap_width: None,
+            spacing_comments: false,
+            width_comments: false,
+            max_cell_length: None,
+            fallback_html_tables: false,
+            stop_after: Stage::default(),
+            output_encoding: OutputEncoding::default(),
+            verify_output: cfg!(debug_assertions),
+            sanitization_mode: SanitizationMode::default(),
+            integrity: false,
+            allow_fragment: false,
+            heading_offset: 0,
+            normalization: NormalizationForm::default(),
+            link_rewrite: None,
+            link_rewrite_path: None,
+            terminology: None,
+            terminology_path: None,
+            max_document_size: None,
+            fixed_timestamp: None,
+            timezone: ClockTimezone::default(),
+            collect_stats: false,
+        }
+    }
+}
+
+/// What the converter emits under a given configuration, so integrators
+/// can verify which dialect they are getting before shipping output to a
+/// legacy system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// The active compatibility mode.
+    pub legacy_mode: bool,
+    /// Specification level of generated RTF.
+    pub rtf_spec: String,
+    /// Generated RTF escapes non-cp1252 text as `\uN` (false: `\'xx`
+    /// with `?` fallback).
+    pub unicode_escapes: bool,
+    /// Generated RTF carries a `\stylesheet` table.
+    pub stylesheet: bool,
+    /// Generated Markdown may contain raw HTML (anchors, `<div dir>`,
+    /// `<ins>`/`<del>`).
+    pub html_in_markdown: bool,
+    /// Generated Markdown may use extended syntax (GFM strikethrough,
+    /// attribute lists).
+    pub extended_markdown: bool,
+    /// Per-object cap on decoded embedded payload size, in bytes.
+    pub max_embedded_object_size: usize,
+    /// Cap on the summed decoded embedded payload size per document.
+    pub max_total_embedded_size: usize,
+    /// Cap on the number of embedded images per document.
+    pub max_image_count: usize,
+    /// What happens to payloads over those caps.
+    pub sanitization_mode: SanitizationMode,
+    /// Input character encodings the file commands accept as overrides
+    /// of the BOM/UTF-8 auto-detection.
+    pub supported_input_encodings: Vec<String>,
+    /// Conversion directions the batch surfaces route files through;
+    /// see [`ConversionDirection`](super::plain_text::ConversionDirection).
+    pub supported_directions: Vec<String>,
+}
+
+impl PipelineConfig {
+    /// Describe the output dialect this configuration produces.
+    pub fn capabilities(&self) -> Capabilities {
+        let limits = crate::security::SecurityLimits::default();
+        Capabilities {
+            legacy_mode: self.legacy_mode,
+            rtf_spec: if self.legacy_mode { "1.5" } else { "1.9" }.to_string(),
+            unicode_escapes: !self.legacy_mode,
+            stylesheet: !self.legacy_mode,
+            html_in_markdown: !self.legacy_mode,
+            extended_markdown: !self.legacy_mode,
+            max_embedded_object_size: limits.max_embedded_object_size,
+            max_total_embedded_size: limits.max_total_embedded_size,
+            max_image_count: limits.max_image_count,
+            sanitization_mode: self.sanitization_mode,
+            supported_input_encodings: super::encoding::SUPPORTED_INPUT_ENCODINGS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            supported_directions: super::plain_text::SUPPORTED_DIRECTIONS
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Mutable state threaded through the pipeline stages.
+#[derive(Debug, Default)]
+struct PipelineContext {
+    tokens: Option<Vec<RtfToken>>,
+    document: Option<RtfDocument>,
+    output: Option<String>,
+    /// Document outline computed during generation; slugs match the
+    /// anchors the generator would emit.
+    outline: Vec<OutlineEntry>,
+    validation_results: Vec<ValidationResult>,
+    template_diff: Option<TemplateDiff>,
+    /// Unsupported constructs recorded by the parser, lifted out of the
+    /// document metadata for the degradation report.
+    feature_usage: FeatureUsage,
+    /// Form fields extracted by the parser when the config asks for them.
+    form_fields: Vec<FormField>,
+    /// Reviewer comments extracted by the parser, in document order.
+    annotations: Vec<Annotation>,
+    /// Structural repairs applied under auto-recovery, with source
+    /// excerpts; empty when the input was well-formed.
+    recovery_actions: Vec<RecoveryAction>,
+    /// Validation results only a strict run would produce; collected when
+    /// [`PipelineConfig::compare_validation`] is set.
+    strict_delta: Vec<ValidationResult>,
+}
+
+/// The result of a full pipeline run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineOutput {
+    pub markdown: String,
+    pub validation_results: Vec<ValidationResult>,
+    pub metadata: PipelineMetadata,
+    /// What the configured template would change; only set on dry runs.
+    pub template_diff: Option<TemplateDiff>,
+    /// What this document uses that won't fully survive conversion; empty
+    /// for documents that convert faithfully.
+    pub feature_usage: FeatureUsage,
+    /// Form fields lifted out of the document; only populated when
+    /// [`PipelineConfig::extract_form_fields`] is set.
+    pub form_fields: Vec<FormField>,
+    /// Reviewer comments lifted out of the document, in document order,
+    /// regardless of the annotation mode - the UI shows them in a side
+    /// panel even when the output strips them.
+    pub annotations: Vec<Annotation>,
+    /// Structural repairs applied under [`PipelineConfig::auto_recovery`],
+    /// each locating the edit with before/after source excerpts; empty
+    /// when recovery is off or the input was well-formed.
+    pub recovery_actions: Vec<RecoveryAction>,
+    /// Results a `strict_validation` run would have added to
+    /// [`validation_results`](Self::validation_results); only populated
+    /// under [`PipelineConfig::compare_validation`], and empty when the
+    /// run was already strict.
+    pub strict_delta: Vec<ValidationResult>,
+    /// Peak heap bytes this conversion allocated above what was already
+    /// live when it started; `None` unless the crate was built with the
+    /// `memory-accounting` feature and the binary installs the counting
+    /// allocator from [`super::memory`].
+    pub peak_memory_bytes: Option<usize>,
+    /// Performance counters for this run; only populated under
+    /// [`PipelineConfig::collect_stats`].
+    pub stats: Option<ConversionStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipelineMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub token_count: usize,
+    pub node_count: usize,
+    /// Headings (text, level, slug) in document order.
+    pub outline: Vec<OutlineEntry>,
+    /// The input was a headerless fragment parsed under a synthetic
+    /// header; only ever set under [`PipelineConfig::allow_fragment`].
+    /// Callers writing back to the same store should emit a fragment too
+    /// (see [`markdown_to_rtf_fragment`](super::markdown_to_rtf_fragment)).
+    pub is_fragment: bool,
+    /// Unicode normalization form the text was brought into, from
+    /// [`PipelineConfig::normalization`]; recorded so downstream diff
+    /// tooling knows which form to expect.
+    pub normalization: NormalizationForm,
+}
+
+/// Wall-clock time one pipeline stage took, in microseconds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StageTiming {
+    /// Stage name, matching the breadcrumb stages: `validate`,
+    /// `tokenize`, `parse`, `transform`, `generate`, `verify`.
+    pub stage: String,
+    pub duration_us: u64,
+}
+
+/// Performance counters for one conversion, collected under
+/// [`PipelineConfig::collect_stats`] for "why is this file slow"
+/// support questions: whether the SIMD path engaged, how much work each
+/// stage did and where the time went, without attaching a profiler.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversionStats {
+    /// Size of the raw input, in bytes.
+    pub input_bytes: usize,
+    /// Tokens the lexer produced; matches
+    /// [`PipelineMetadata::token_count`].
+    pub token_count: usize,
+    /// Lexer throughput derived from the token count and the tokenize
+    /// stage's duration.
+    pub tokens_per_second: u64,
+    /// Vector instruction set the tokenizer's byte scanner selects on
+    /// this CPU (`none`, `sse2`, `avx2`, `neon`), from
+    /// [`CpuFeatures::detect`](super::simd_lexer::CpuFeatures::detect).
+    pub simd_level: String,
+    /// Top-level parse tree nodes; matches
+    /// [`PipelineMetadata::node_count`].
+    pub node_count: usize,
+    /// Structural repairs attempted under auto-recovery.
+    pub recovery_attempts: usize,
+    /// Per-stage durations, in pipeline order.
+    pub stage_timings: Vec<StageTiming>,
+    /// Wall-clock time of the whole run, in microseconds.
+    pub total_duration_us: u64,
+    /// Heap allocations the conversion made; `None` unless the crate was
+    /// built with the `memory-accounting` feature and the binary installs
+    /// the counting allocator from [`super::memory`].
+    pub allocation_count: Option<u64>,
+}
+
+/// Stopwatch behind [`ConversionStats`]: each `mark` closes the stage
+/// that just ran.
+struct StageClock {
+    started: Instant,
+    last: Instant,
+    timings: Vec<StageTiming>,
+}
+
+impl StageClock {
+    fn start() -> Self {
+        let now = Instant::now();
+        StageClock {
+            started: now,
+            last: now,
+            timings: Vec::new(),
+        }
+    }
+
+    fn mark(&mut self, stage: &str) {
+        let now = Instant::now();
+        self.timings.push(StageTiming {
+            stage: stage.to_string(),
+            duration_us: now.duration_since(self.last).as_micros() as u64,
+        });
+        self.last = now;
+    }
+
+    fn into_stats(
+        self,
+        input_bytes: usize,
+        token_count: usize,
+        node_count: usize,
+        recovery_attempts: usize,
+        allocation_count: Option<u64>,
+    ) -> ConversionStats {
+        let tokenize_us = self
+            .timings
+            .iter()
+            .find(|t| t.stage == "tokenize")
+            .map_or(0, |t| t.duration_us);
+        // A sub-microsecond tokenize rounds to 0; report the rate as if
+        // it took a full microsecond rather than dividing by zero.
+        let tokens_per_second =
+            (token_count as u64).saturating_mul(1_000_000) / tokenize_us.max(1);
+        ConversionStats {
+            input_bytes,
+            token_count,
+            tokens_per_second,
+            simd_level: CpuFeatures::detect().simd_level.to_string(),
+            node_count,
+            recovery_attempts,
+            stage_timings: self.timings,
+            total_duration_us: self.started.elapsed().as_micros() as u64,
+            allocation_count,
+        }
+    }
+}
+
+/// A custom transformation run on the parsed document before generation.
+/// Hooks may push [`ValidationResult`]s to report what they changed.
+pub type PreGenerateHook =
+    Box<dyn Fn(&mut RtfDocument, &mut Vec<ValidationResult>) -> ConversionResult<()> + Send + Sync>;
+
+/// A custom string-level pass run on the generated Markdown.
+pub type PostGenerateHook =
+    Box<dyn Fn(&mut String, &mut Vec<ValidationResult>) -> ConversionResult<()> + Send + Sync>;
+
+pub struct DocumentPipeline {
+    config: PipelineConfig,
+    /// Checked at stage boundaries and inside the lexer/parser loops.
+    cancel: Option<CancellationToken>,
+    /// Shared application state; without one the template stage falls
+    /// back to the per-thread cache.
+    environment: Option<Arc<ConversionEnvironment>>,
+    /// Custom document transformations, run in registration order between
+    /// template application and generation.
+    pre_generate_hooks: Vec<(String, PreGenerateHook)>,
+    /// Custom output passes, run in registration order after generation.
+    post_generate_hooks: Vec<(String, PostGenerateHook)>,
+}
+
+impl DocumentPipeline {
+    pub fn new(config: PipelineConfig) -> Self {
+        DocumentPipeline {
+            config,
+            cancel: None,
+            environment: None,
+            pre_generate_hooks: Vec::new(),
+            post_generate_hooks: Vec::new(),
+        }
+    }
+
+    /// Resolve templates from a shared
+    /// [`ConversionEnvironment`](super::environment::ConversionEnvironment)
+    /// instead of the per-thread cache. The snapshot is taken when the
+    /// template stage runs, so a long conversion is unaffected by a
+    /// concurrent reload.
+    pub fn with_environment(mut self, environment: Arc<ConversionEnvironment>) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Register a named transformation of the parsed document, run before
+    /// generation. Hooks run in registration order; a panicking hook is
+    /// caught and reported as a generation error naming the hook rather
+    /// than tearing down the caller.
+    pub fn register_pre_generate(
+        mut self,
+        name: impl Into<String>,
+        hook: impl Fn(&mut RtfDocument, &mut Vec<ValidationResult>) -> ConversionResult<()>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.pre_generate_hooks.push((name.into(), Box::new(hook)));
+        self
+    }
+
+    /// Register a named string-level pass over the generated Markdown,
+    /// with the same ordering and panic isolation as
+    /// [`register_pre_generate`](Self::register_pre_generate).
+    pub fn register_post_generate(
+        mut self,
+        name: impl Into<String>,
+        hook: impl Fn(&mut String, &mut Vec<ValidationResult>) -> ConversionResult<()>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.post_generate_hooks.push((name.into(), Box::new(hook)));
+        self
+    }
+
+    /// Observe a [`CancellationToken`]: once the caller cancels it, the
+    /// run returns [`ConversionError::Cancelled`] at the next check -
+    /// between stages, or inside the lexer/parser loops for long inputs.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    fn check_cancelled(&self) -> ConversionResult<()> {
+        match &self.cancel {
+            Some(token) if token.is_cancelled() => Err(ConversionError::Cancelled),
+            _ => Ok(()),
+        }
+    }
+
+    fn run_pre_generate_hooks(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        if self.pre_generate_hooks.is_empty() {
+            return Ok(());
+        }
+        let mut document = ctx.document.take().ok_or_else(|| {
+            ConversionError::generation("pipeline stage contract violated: no document before hooks")
+        })?;
+        let mut result = Ok(());
+        for (name, hook) in &self.pre_generate_hooks {
+            result = run_isolated(name, || hook(&mut document, &mut ctx.validation_results));
+            if result.is_err() {
+                break;
+            }
+        }
+        ctx.document = Some(document);
+        result
+    }
+
+    fn run_post_generate_hooks(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        if self.post_generate_hooks.is_empty() {
+            return Ok(());
+        }
+        let mut output = ctx.output.take().ok_or_else(|| {
+            ConversionError::generation("pipeline stage contract violated: no output before hooks")
+        })?;
+        let mut result = Ok(());
+        for (name, hook) in &self.post_generate_hooks {
+            result = run_isolated(name, || hook(&mut output, &mut ctx.validation_results));
+            if result.is_err() {
+                break;
+            }
+        }
+        ctx.output = Some(output);
+        result
+    }
+
+    pub fn with_defaults() -> Self {
+        Self::new(PipelineConfig::default())
+    }
+
+    pub fn config(&self) -> &PipelineConfig {
+        &self.config
+    }
+
+    /// Run an RTF document through the full conversion pipeline.
+    pub fn process(&self, input: &str) -> ConversionResult<PipelineOutput> {
+        let result = self.process_impl(input, None);
+        self.count_operation(&result);
+        result
+    }
+
+    /// Record this run on the labeled operation series; the pipeline is
+    /// the RTF -> Markdown direction by construction.
+    fn count_operation(&self, result: &ConversionResult<PipelineOutput>) {
+        context::count_operation(
+            "rtf_to_md",
+            "pipeline",
+            self.config.template.as_deref(),
+            if result.is_ok() { "success" } else { "error" },
+        );
+    }
+
+    /// [`process`](Self::process) under a tenant context: enforces the
+    /// tenant's in-flight cap and security limits, makes the context's
+    /// templates visible to template lookup, and counts the conversion in
+    /// the per-tenant metrics.
+    pub fn process_with_context(
+        &self,
+        input: &str,
+        context: &ConversionContext,
+    ) -> ConversionResult<PipelineOutput> {
+        let _permit = context::tenant_limiter()
+            .acquire(&context.tenant)
+            .map_err(ConversionError::resource_limit)?;
+        if let Some(limits) = &context.limits {
+            crate::security::InputValidator::new(limits.clone())
+                .with_form_fields_allowed(self.config.extract_form_fields)
+                .validate_rtf_input(input)
+                .map_err(ConversionError::validation)?;
+        }
+        let result = self.process_impl(input, Some(context));
+        context::count_conversion(
+            context,
+            if result.is_ok() { "success" } else { "error" },
+        );
+        self.count_operation(&result);
+        if let Ok(output) = &result {
+            if let Some(peak) = output.peak_memory_bytes {
+                context::observe_peak_bytes(context, peak as u64);
+            }
+        }
+        result
+    }
+
+    fn process_impl(
+        &self,
+        input: &str,
+        conversion_ctx: Option<&ConversionContext>,
+    ) -> ConversionResult<PipelineOutput> {
+        let mut ctx = PipelineContext::default();
+        let _active = breadcrumb::start(input);
+        let memory_span = memory::start_span();
+        let mut clock = self.config.collect_stats.then(StageClock::start);
+
+        // Validation sees the raw input; from tokenization on, a
+        // headerless fragment runs inside a minimal synthetic wrapper.
+        let is_fragment = self.config.allow_fragment && !input.trim_start().starts_with("{\\rtf");
+        let wrapped;
+        let parse_input = if is_fragment {
+            wrapped = format!("{{\\rtf1 {input}}}");
+            wrapped.as_str()
+        } else {
+            input
+        };
+
+        self.pre_validate(input, &mut ctx)?;
+        if self.config.auto_recovery {
+            ctx.recovery_actions = recovery::brace_repairs(parse_input);
+            ctx.recovery_actions
+                .extend(recovery::encoding_fixes(parse_input));
+        }
+        if let Some(clock) = &mut clock {
+            clock.mark("validate");
+        }
+        self.check_cancelled()?;
+        breadcrumb::stage("tokenize");
+        self.tokenize_stage(parse_input, &mut ctx)?;
+        if let Some(clock) = &mut clock {
+            clock.mark("tokenize");
+        }
+        self.check_cancelled()?;
+        breadcrumb::stage("parse");
+        self.parse_stage(&mut ctx, conversion_ctx)?;
+        if let Some(clock) = &mut clock {
+            clock.mark("parse");
+        }
+        self.check_cancelled()?;
+        breadcrumb::stage("transform");
+        self.cleanup_stage(&mut ctx)?;
+        self.apply_page_range(&mut ctx)?;
+        self.apply_link_rewrites(&mut ctx)?;
+        self.apply_template(&mut ctx, conversion_ctx)?;
+        self.run_pre_generate_hooks(&mut ctx)?;
+        self.apply_heading_offset(&mut ctx)?;
+        if let Some(clock) = &mut clock {
+            clock.mark("transform");
+        }
+        if self.config.stop_after == Stage::Generate {
+            self.check_cancelled()?;
+            breadcrumb::stage("generate");
+            self.generate_stage(&mut ctx)?;
+            if let Some(clock) = &mut clock {
+                clock.mark("generate");
+            }
+            breadcrumb::stage("verify");
+            self.verify_stage(&mut ctx)?;
+            self.append_annotations(&mut ctx);
+            self.apply_terminology(&mut ctx)?;
+            self.run_post_generate_hooks(&mut ctx)?;
+            if let Some(clock) = &mut clock {
+                clock.mark("verify");
+            }
+        }
+
+        let metadata = PipelineMetadata {
+            title: ctx
+                .document
+                .as_ref()
+                .and_then(|d| d.metadata.title.clone()),
+            author: ctx
+                .document
+                .as_ref()
+                .and_then(|d| d.metadata.author.clone()),
+            token_count: ctx.tokens.as_ref().map(Vec::len).unwrap_or(0),
+            node_count: ctx
+                .document
+                .as_ref()
+                .map(|d| d.content.len())
+                .unwrap_or(0),
+            outline: std::mem::take(&mut ctx.outline),
+            is_fragment,
+            normalization: self.config.normalization,
+        };
+
+        let mut markdown = match self.config.stop_after {
+            Stage::Parse => String::new(),
+            Stage::Generate => ctx.output.take().ok_or_else(|| {
+                ConversionError::generation("pipeline stage contract violated: no output produced")
+            })?,
+        };
+        if self.config.integrity && self.config.stop_after == Stage::Generate {
+            // Signed last: the output hash must cover everything every
+            // earlier stage and hook contributed.
+            let fingerprint = self
+                .config
+                .fingerprint(&crate::security::SecurityLimits::default());
+            markdown = integrity::sign_markdown_at(
+                &markdown,
+                input,
+                &fingerprint,
+                &self.conversion_clock()?.timestamp_rfc3339(),
+            );
+        }
+        let stats = clock.map(|clock| {
+            clock.into_stats(
+                input.len(),
+                metadata.token_count,
+                metadata.node_count,
+                ctx.recovery_actions.len(),
+                memory_span.allocation_count(),
+            )
+        });
+        Ok(PipelineOutput {
+            markdown,
+            validation_results: ctx.validation_results,
+            metadata,
+            template_diff: ctx.template_diff,
+            feature_usage: ctx.feature_usage,
+            form_fields: ctx.form_fields,
+            annotations: ctx.annotations,
+            recovery_actions: ctx.recovery_actions,
+            strict_delta: ctx.strict_delta,
+            peak_memory_bytes: memory_span.peak_bytes(),
+            stats,
+        })
+    }
+
+    fn pre_validate(&self, input: &str, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        let max_size = self
+            .config
+            .max_document_size
+            .unwrap_or(validation::MAX_DOCUMENT_SIZE);
+        let results = Validator::new(self.config.strict_validation)
+            .with_fragment_allowed(self.config.allow_fragment)
+            .with_max_size(max_size)
+            .validate_rtf(input);
+        if self.compare_strictness() {
+            ctx.strict_delta.extend(
+                Validator::new(true)
+                    .with_fragment_allowed(self.config.allow_fragment)
+                    .with_max_size(max_size)
+                    .validate_rtf(input)
+                    .into_iter()
+                    .filter(|r| !results.contains(r)),
+            );
+        }
+        let first_error = results
+            .iter()
+            .find(|r| r.level == ValidationLevel::Error)
+            .map(|r| (r.code.clone(), r.message.clone()));
+        ctx.validation_results.extend(results);
+        if let Some((code, message)) = first_error {
+            return Err(ConversionError::validation_with_code(code, message));
+        }
+        Ok(())
+    }
+
+    /// Whether this run collects the strict-only validation delta: asked
+    /// for, and not redundant because the run is already strict.
+    fn compare_strictness(&self) -> bool {
+        self.config.compare_validation && !self.config.strict_validation
+    }
+
+    fn tokenize_stage(&self, input: &str, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        let tokens = match &self.cancel {
+            Some(token) => tokenize_with_cancellation(input, token),
+            None => tokenize(input),
+        }
+        .map_err(map_cancellable_message)?;
+        ctx.tokens = Some(tokens);
+        Ok(())
+    }
+
+    fn parse_stage(
+        &self,
+        ctx: &mut PipelineContext,
+        conversion_ctx: Option<&ConversionContext>,
+    ) -> ConversionResult<()> {
+        let tokens = ctx.tokens.clone().ok_or_else(|| {
+            ConversionError::parse("pipeline stage contract violated: no tokens before parse")
+        })?;
+        let font_map = match &self.config.font_map_path {
+            Some(path) => FontMap::from_file(path).map_err(ConversionError::validation)?,
+            None => FontMap::with_defaults(),
+        };
+        let extensions = match &self.config.control_word_extensions_path {
+            Some(path) => {
+                ControlWordExtensions::from_file(path).map_err(ConversionError::validation)?
+            }
+            None => ControlWordExtensions::default(),
+        };
+        let mut parser = RtfParser::new(tokens)
+            .with_tolerance(self.config.auto_recovery)
+            .with_form_extraction(self.config.extract_form_fields)
+            .with_annotation_markers(self.config.annotation_mode != AnnotationMode::Strip)
+            .with_placeholders(self.config.placeholders.clone())
+            .with_font_map(font_map)
+            .with_extensions(extensions)
+            .with_sanitization_mode(self.config.sanitization_mode)
+            // Legacy consumers read the ANSI branch of \upr pairs.
+            .with_unicode_preference(!self.config.legacy_mode)
+            // Keep \par runs so the cleanup stage decides their fate
+            // (or, with cleanup opted out, so they survive as-is).
+            .with_empty_paragraphs(true)
+            .with_normalization(self.config.normalization);
+        if let Some(limits) = conversion_ctx.and_then(|c| c.limits.as_ref()) {
+            parser = parser.with_unicode_hygiene(limits.unicode_hygiene.clone());
+        }
+        if let Some(token) = &self.cancel {
+            parser = parser.with_cancellation(token.clone());
+        }
+        let (document, warnings) = parser.parse_with_warnings().map_err(|message| {
+            // Exceeding the format run limit is a policy rejection of a
+            // well-formed document, not a parse failure.
+            if message.starts_with("format run limit") {
+                ConversionError::validation_with_code("RTF106", message)
+            } else if message.starts_with(unicode_hygiene::WARNING_PREFIX) {
+                // A character class set to Reject is a policy rejection,
+                // not a parse failure.
+                ConversionError::validation_with_code("RTF115", message)
+            } else {
+                map_cancellable_message(message)
+            }
+        })?;
+        for warning in warnings {
+            // Unicode hygiene counts get their own code so integrators
+            // can tell spoofing cleanup from structural recovery.
+            let code = if warning.starts_with(unicode_hygiene::WARNING_PREFIX) {
+                "RTF115"
+            } else {
+                "RTF104"
+            };
+            ctx.validation_results
+                .push(ValidationResult::warning(code, warning));
+        }
+        for substitution in &document.metadata.font_substitutions {
+            ctx.validation_results.push(ValidationResult::info(
+                "RTF105",
+                format!(
+                    "substituted font '{}' with '{}'",
+                    substitution.original, substitution.replacement
+                ),
+            ));
+        }
+        if self.config.annotation_mode == AnnotationMode::Strip
+            && !document.metadata.annotations.is_empty()
+        {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF109",
+                format!(
+                    "{} comment(s) stripped; set an annotation mode to export them",
+                    document.metadata.annotations.len()
+                ),
+            ));
+        }
+        ctx.feature_usage = document.metadata.feature_usage.clone();
+        ctx.form_fields = document.metadata.form_fields.clone();
+        ctx.annotations = document.metadata.annotations.clone();
+        ctx.document = Some(document);
+        Ok(())
+    }
+
+    /// Run the post-parse cleanup pass (see [`cleanup`]), reporting its
+    /// edits as `RTF112` Info results.
+    fn cleanup_stage(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        if !self.config.cleanup.enabled {
+            return Ok(());
+        }
+        let document = ctx.document.as_mut().ok_or_else(|| {
+            ConversionError::generation(
+                "pipeline stage contract violated: no document before cleanup",
+            )
+        })?;
+        let max_empty = if self.config.preserve_formatting {
+            self.config
+                .cleanup
+                .max_empty_paragraphs
+                .max(self.config.cleanup.preserve_formatting_max)
+        } else {
+            self.config.cleanup.max_empty_paragraphs
+        };
+        let counts = cleanup::clean(document, max_empty);
+        let mut report = |count: usize, message: String| {
+            if count > 0 {
+                ctx.validation_results
+                    .push(ValidationResult::info("RTF112", message));
+            }
+        };
+        report(
+            counts.empty_paragraphs_removed,
+            format!(
+                "collapsed {} empty paragraph(s) used as vertical spacing",
+                counts.empty_paragraphs_removed
+            ),
+        );
+        report(
+            counts.toggle_only_paragraphs_removed,
+            format!(
+                "dropped {} paragraph(s) carrying only formatting toggles",
+                counts.toggle_only_paragraphs_removed
+            ),
+        );
+        report(
+            counts.paragraphs_trimmed,
+            format!(
+                "trimmed trailing whitespace from {} paragraph(s)",
+                counts.paragraphs_trimmed
+            ),
+        );
+        Ok(())
+    }
+
+    /// Shift heading levels by the configured offset, clamping to 1..=6.
+    /// Runs last in the transform phase so headings a template or hook
+    /// introduced are shifted too, and before generation so the outline
+    /// reflects the shifted levels.
+    fn apply_heading_offset(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        let offset = self.config.heading_offset;
+        if offset == 0 {
+            return Ok(());
+        }
+        let document = ctx.document.as_mut().ok_or_else(|| {
+            ConversionError::generation(
+                "pipeline stage contract violated: no document before heading offset",
+            )
+        })?;
+        let mut clamped = 0usize;
+        for node in &mut document.content {
+            if let RtfNode::Heading { level, .. } = node {
+                let shifted = *level as i32 + offset as i32;
+                let bounded = shifted.clamp(1, 6);
+                if bounded != shifted {
+                    clamped += 1;
+                }
+                *level = bounded as u8;
+            }
+        }
+        if clamped > 0 {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF114",
+                format!(
+                    "{clamped} heading(s) clamped to the 1-6 range under heading offset {offset:+}"
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Restrict the parsed document to the configured page range, keeping
+    /// metadata and the font/color/style tables intact.
+    fn apply_page_range(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        let Some(range) = self.config.page_range else {
+            return Ok(());
+        };
+        let document = ctx.document.as_mut().ok_or_else(|| {
+            ConversionError::generation(
+                "pipeline stage contract violated: no document before page selection",
+            )
+        })?;
+        let pages = split_pages(std::mem::take(&mut document.content));
+        if range.start < 1 || range.start > range.end || range.end > pages.len() {
+            return Err(ConversionError::validation_with_code(
+                "RTF107",
+                format!(
+                    "page range {}-{} is out of range: document has {} page(s)",
+                    range.start,
+                    range.end,
+                    pages.len()
+                ),
+            ));
+        }
+        let mut content = Vec::new();
+        for (i, page) in pages
+            .into_iter()
+            .enumerate()
+            .skip(range.start - 1)
+            .take(range.end - range.start + 1)
+        {
+            if i + 1 > range.start {
+                content.push(RtfNode::PageBreak);
+            }
+            content.extend(page);
+        }
+        document.content = content;
+        Ok(())
+    }
+
+    /// Rewrite hyperlink URLs under the configured rules, reporting each
+    /// rewrite as an `RTF116` Info result and unmatched absolute links
+    /// as `RTF117` warnings when the policy asks for them. Runs after
+    /// page selection so links on dropped pages are not reported, and
+    /// before template application so templates see the final URLs.
+    fn apply_link_rewrites(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        let loaded;
+        let config = match (&self.config.link_rewrite, &self.config.link_rewrite_path) {
+            (Some(config), _) => config,
+            (None, Some(path)) => {
+                loaded = LinkRewriteConfig::from_file(path).map_err(ConversionError::validation)?;
+                &loaded
+            }
+            (None, None) => return Ok(()),
+        };
+        let compiled = config.compile().map_err(ConversionError::validation)?;
+        let document = ctx.document.as_mut().ok_or_else(|| {
+            ConversionError::generation(
+                "pipeline stage contract violated: no document before link rewriting",
+            )
+        })?;
+        let outcome = compiled.apply(&mut document.content);
+        for (original, new) in &outcome.rewrites {
+            ctx.validation_results.push(ValidationResult::info(
+                "RTF116",
+                format!("rewrote link '{original}' -> '{new}'"),
+            ));
+        }
+        if outcome.rewrite_count > outcome.rewrites.len() {
+            ctx.validation_results.push(ValidationResult::info(
+                "RTF116",
+                format!(
+                    "...and {} more link rewrite(s) past the {MAX_LISTED_LINKS}-entry listing cap",
+                    outcome.rewrite_count - outcome.rewrites.len()
+                ),
+            ));
+        }
+        let verb = match config.unmatched {
+            UnmatchedLinkPolicy::Strip => "stripped to its display text",
+            _ => "kept as written",
+        };
+        for url in &outcome.unmatched {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF117",
+                format!("absolute link '{url}' matched no rewrite rule; {verb}"),
+            ));
+        }
+        if outcome.unmatched_count > outcome.unmatched.len() {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF117",
+                format!(
+                    "...and {} more unmatched absolute link(s)",
+                    outcome.unmatched_count - outcome.unmatched.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Enforce the configured terminology on the generated Markdown,
+    /// reporting each replacement as an `RTF119` Info result and each
+    /// flag-only (banned) match as an `RTF120` warning, with the line
+    /// and column of the match. Runs after output verification - the
+    /// verifier compares the Markdown against the parsed tree, which
+    /// still carries the original terms - and after annotations, so
+    /// reviewer comments are held to the same standard.
+    fn apply_terminology(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        let loaded;
+        let config = match (&self.config.terminology, &self.config.terminology_path) {
+            (Some(config), _) => config,
+            (None, Some(path)) => {
+                loaded = TerminologyConfig::from_file(path).map_err(ConversionError::validation)?;
+                &loaded
+            }
+            (None, None) => return Ok(()),
+        };
+        let compiled = config.compile().map_err(ConversionError::validation)?;
+        let output = ctx.output.as_mut().ok_or_else(|| {
+            ConversionError::generation(
+                "pipeline stage contract violated: no output before terminology enforcement",
+            )
+        })?;
+        let (markdown, outcome) = compiled.apply(output);
+        *output = markdown;
+        for m in &outcome.replacements {
+            ctx.validation_results.push(ValidationResult::info(
+                "RTF119",
+                format!(
+                    "replaced '{}' with '{}' at line {}, column {}",
+                    m.found,
+                    m.replaced_with.as_deref().unwrap_or_default(),
+                    m.line,
+                    m.column
+                ),
+            ));
+        }
+        if outcome.replacement_count > outcome.replacements.len() {
+            ctx.validation_results.push(ValidationResult::info(
+                "RTF119",
+                format!(
+                    "...and {} more terminology replacement(s) past the {MAX_LISTED_MATCHES}-entry listing cap",
+                    outcome.replacement_count - outcome.replacements.len()
+                ),
+            ));
+        }
+        for m in &outcome.flagged {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF120",
+                format!(
+                    "banned term '{}' at line {}, column {}",
+                    m.found, m.line, m.column
+                ),
+            ));
+        }
+        if outcome.flagged_count > outcome.flagged.len() {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF120",
+                format!(
+                    "...and {} more banned-term match(es)",
+                    outcome.flagged_count - outcome.flagged.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Apply (or, on a dry run, preview) the configured template. Under a
+    /// tenant context, the namespaced name (`<namespace>/<name>`) is tried
+    /// before the global one.
+    fn apply_template(
+        &self,
+        ctx: &mut PipelineContext,
+        conversion_ctx: Option<&ConversionContext>,
+    ) -> ConversionResult<()> {
+        let Some(name) = &self.config.template else {
+            return Ok(());
+        };
+        let document = ctx.document.as_mut().ok_or_else(|| {
+            ConversionError::generation(
+                "pipeline stage contract violated: no document before template application",
+            )
+        })?;
+        // The environment's snapshot or the thread's cached registry,
+        // cloned either way: built-ins come pre-validated and per-call
+        // registrations die with the clone. The snapshot is taken once,
+        // here, so a concurrent reload cannot change the set mid-stage.
+        let mut system = match &self.environment {
+            Some(environment) => (*environment.templates()).clone(),
+            None => super::thread_state::template_system(),
+        };
+        if let Some(conversion_ctx) = conversion_ctx {
+            for template in &conversion_ctx.templates {
+                system
+                    .register(template.clone())
+                    .map_err(ConversionError::validation)?;
+            }
+        }
+        let namespaced = conversion_ctx
+            .and_then(|c| c.template_namespace.as_ref())
+            .map(|namespace| format!("{namespace}/{name}"));
+        let name = match namespaced {
+            Some(candidate) if system.get(&candidate).is_some() => candidate,
+            _ => name.clone(),
+        };
+        let mut template = system.get(&name).cloned().ok_or_else(|| {
+            ConversionError::validation_with_code("RTF108", format!("unknown template '{name}'"))
+        })?;
+        template
+            .variables
+            .extend(self.config.template_variables.clone());
+        system.register(template).map_err(ConversionError::validation)?;
+        let now = self.conversion_clock()?.now();
+        if self.config.dry_run {
+            ctx.template_diff = Some(
+                system
+                    .preview_at(&name, document, now)
+                    .map_err(ConversionError::validation)?,
+            );
+        } else {
+            system
+                .apply_at(&name, document, now)
+                .map_err(ConversionError::validation)?;
+        }
+        Ok(())
+    }
+
+    /// The time source this run stamps documents with - `{{date}}` and
+    /// `{{time}}` in templates, the integrity block's signing time; see
+    /// [`ConversionClock`]. A bad `fixed_timestamp` or timezone is a
+    /// validation error.
+    fn conversion_clock(&self) -> ConversionResult<ConversionClock> {
+        ConversionClock::resolve(
+            self.config.fixed_timestamp.as_deref(),
+            self.config.timezone.clone(),
+        )
+        .map_err(ConversionError::validation)
+    }
+
+    fn generate_stage(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        let document = ctx.document.as_ref().ok_or_else(|| {
+            ConversionError::generation(
+                "pipeline stage contract violated: no document before generation",
+            )
+        })?;
+        // The pipeline emits GFM tables, which cannot express merged-cell
+        // spans; flag documents where that loses structure.
+        let flattened = document
+            .content
+            .iter()
+            .filter(|node| matches!(node, RtfNode::Table(table) if table.has_merges()))
+            .count();
+        if flattened > 0 {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF111",
+                format!(
+                    "{flattened} table(s) have merged cells GFM cannot express; \
+                     continuation cells left empty"
+                ),
+            ));
+        }
+        let generator = MarkdownGenerator::new()
+            .with_revision_mode(self.config.revision_mode)
+            .with_wrap_width(self.config.wrap_width)
+            .with_spacing_comments(self.config.spacing_comments)
+            .with_width_comments(self.config.width_comments)
+            .with_max_cell_length(self.config.max_cell_length)
+            .with_fallback_html_tables(self.config.fallback_html_tables)
+            .with_legacy_mode(self.config.legacy_mode);
+        let (output, outline) = generator.generate_with_outline(document);
+        if generator.truncated_cells() > 0 {
+            ctx.validation_results.push(ValidationResult::warning(
+                "RTF118",
+                format!(
+                    "{} table cell(s) truncated at {} characters; the full text survives \
+                     in the document tree and its extraction APIs",
+                    generator.truncated_cells(),
+                    self.config.max_cell_length.unwrap_or(0),
+                ),
+            ));
+        }
+        ctx.outline = outline;
+        ctx.output = Some(output);
+        Ok(())
+    }
+
+    /// Re-parse the generated Markdown and check it still matches the
+    /// document; see [`PipelineConfig::verify_output`]. Runs before
+    /// annotations are appended, since those are additions by design.
+    fn verify_stage(&self, ctx: &mut PipelineContext) -> ConversionResult<()> {
+        if !self.config.verify_output {
+            return Ok(());
+        }
+        let (Some(document), Some(output)) = (ctx.document.as_ref(), ctx.output.as_deref())
+        else {
+            return Ok(());
+        };
+        let mismatches = verify::verify(document, output, self.config.revision_mode);
+        let first = mismatches.first().cloned();
+        if self.compare_strictness() {
+            // Under strict validation these would be errors, not warnings.
+            ctx.strict_delta.extend(
+                mismatches
+                    .iter()
+                    .map(|m| ValidationResult::error("RTF110", m.clone())),
+            );
+        }
+        ctx.validation_results
+            .extend(mismatches.into_iter().map(|m| {
+                if self.config.strict_validation {
+                    ValidationResult::error("RTF110", m)
+                } else {
+                    ValidationResult::warning("RTF110", m)
+                }
+            }));
+        match first {
+            Some(message) if self.config.strict_validation => {
+                Err(ConversionError::validation_with_code("RTF110", message))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Render the extracted comments into the generated Markdown per the
+    /// configured [`AnnotationMode`]; the `[note N]` anchor markers were
+    /// already placed by the parser.
+    fn append_annotations(&self, ctx: &mut PipelineContext) {
+        if ctx.annotations.is_empty() || self.config.annotation_mode == AnnotationMode::Strip {
+            return;
+        }
+        let Some(output) = ctx.output.as_mut() else {
+            return;
+        };
+        if !output.ends_with('\n') {
+            output.push('\n');
+        }
+        if self.config.annotation_mode == AnnotationMode::Section {
+            output.push_str("\n## Comments\n");
+        }
+        output.push('\n');
+        for annotation in &ctx.annotations {
+            let line = match self.config.annotation_mode {
+                AnnotationMode::Footnotes => match annotation.label() {
+                    Some(label) => {
+                        format!("[note {}: {}] {}\n", annotation.index, label, annotation.text)
+                    }
+                    None => format!("[note {}] {}\n", annotation.index, annotation.text),
+                },
+                _ => {
+                    let label = annotation.label().unwrap_or("unknown");
+                    match &annotation.anchor {
+                        Some(anchor) => format!(
+                            "{}. **{}** (note {}, on \"{}\"): {}\n",
+                            annotation.index, label, annotation.index, anchor, annotation.text
+                        ),
+                        None => format!(
+                            "{}. **{}** (note {}): {}\n",
+                            annotation.index, label, annotation.index, annotation.text
+                        ),
+                    }
+                }
+            };
+            output.push_str(&line);
+        }
+    }
+}
+
+/// Split document content into pages at `\page`/`\sect` boundaries. A
+/// document with N page breaks has N+1 pages; the breaks themselves are
+/// not part of any page.
+fn split_pages(content: Vec<RtfNode>) -> Vec<Vec<RtfNode>> {
+    let mut pages = vec![Vec::new()];
+    for node in content {
+        if matches!(node, RtfNode::PageBreak) {
+            pages.push(Vec::new());
+        } else {
+            pages.last_mut().expect("pages is never empty").push(node);
+        }
+    }
+    pages
+}
+
+/// Number of pages a document would be split into for page-range
+/// selection; see [`split_pages`] for the boundary rules.
+pub fn page_count(input: &str) -> ConversionResult<usize> {
+    let tokens = tokenize(input).map_err(ConversionError::parse)?;
+    let document = RtfParser::new(tokens)
+        .parse()
+        .map_err(ConversionError::parse)?;
+    let breaks = document
+        .content
+        .iter()
+        .filter(|n| matches!(n, RtfNode::PageBreak))
+        .count();
+    Ok(breaks + 1)
+}
+
+/// Extract title/author without running a full conversion.
+pub fn extract_metadata(input: &str) -> ConversionResult<DocumentMetadata> {
+    let tokens = tokenize(input).map_err(ConversionError::parse)?;
+    let document = RtfParser::new(tokens)
+        .parse()
+        .map_err(ConversionError::parse)?;
+    Ok(document.metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capabilities_report_tracks_legacy_mode() {
+        let caps = PipelineConfig::default().capabilities();
+        assert!(!caps.legacy_mode);
+        assert_eq!(caps.rtf_spec, "1.9");
+        assert!(caps.unicode_escapes && caps.stylesheet);
+        assert!(caps.html_in_markdown && caps.extended_markdown);
+
+        let caps = PipelineConfig {
+            legacy_mode: true,
+            ..Default::default()
+        }
+        .capabilities();
+        assert!(caps.legacy_mode);
+        assert_eq!(caps.rtf_spec, "1.5");
+        assert!(!caps.unicode_escapes && !caps.stylesheet);
+        assert!(!caps.html_in_markdown && !caps.extended_markdown);
+    }
+
+    #[test]
+    fn pipeline_converts_basic_document() {
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 Hello \\b World\\b0\\par}")
+            .unwrap();
+        assert!(output.markdown.contains("Hello **World**"));
+        assert!(output.metadata.token_count > 0);
+    }
+
+    #[test]
+    fn collect_stats_counters_match_a_direct_tokenizer_run() {
+        let fixture = "{\\rtf1 {\\b Heading}\\par Some body text\\par}";
+        let output = DocumentPipeline::new(PipelineConfig {
+            collect_stats: true,
+            ..Default::default()
+        })
+        .process(fixture)
+        .unwrap();
+        let stats = output.stats.expect("collect_stats populates the counters");
+        assert_eq!(stats.input_bytes, fixture.len());
+        assert_eq!(stats.token_count, tokenize(fixture).unwrap().len());
+        assert_eq!(stats.token_count, output.metadata.token_count);
+        assert_eq!(stats.node_count, output.metadata.node_count);
+        assert_eq!(
+            stats.simd_level,
+            CpuFeatures::detect().simd_level.to_string()
+        );
+        assert!(stats.tokens_per_second > 0, "{stats:?}");
+        assert_eq!(stats.recovery_attempts, 0);
+        let stages: Vec<&str> = stats.stage_timings.iter().map(|t| t.stage.as_str()).collect();
+        assert_eq!(
+            stages,
+            ["validate", "tokenize", "parse", "transform", "generate", "verify"]
+        );
+        assert!(
+            stats.total_duration_us
+                >= stats.stage_timings.iter().map(|t| t.duration_us).sum(),
+            "{stats:?}"
+        );
+    }
+
+    #[test]
+    fn stats_are_absent_unless_asked_for() {
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 Hello\\par}")
+            .unwrap();
+        assert!(output.stats.is_none());
+    }
+
+    #[test]
+    fn verify_accepts_pipes_in_table_cells() {
+        // Regression: the generated `\|` escape used to be dropped by our
+        // own parser on re-parse, splitting the cell. With verification on
+        // (the debug default) this document must come back clean.
+        let output = DocumentPipeline::new(PipelineConfig {
+            verify_output: true,
+            ..Default::default()
+        })
+        .process("{\\rtf1 \\trowd\\intbl a | b\\cell plain\\cell\\row}")
+        .unwrap();
+        assert!(output.markdown.contains("a \\| b"), "{}", output.markdown);
+        assert!(
+            !output.validation_results.iter().any(|r| r.code == "RTF110"),
+            "{:?}",
+            output.validation_results
+        );
+    }
+
+    #[test]
+    fn merged_table_cells_are_flagged_when_flattened_to_gfm() {
+        let output = DocumentPipeline::with_defaults()
+            .process(
+                "{\\rtf1 \\trowd\\clmgf\\cellx2400\\clmrg\\cellx4800 \
+                 \\intbl Invoice\\cell\\cell\\row}",
+            )
+            .unwrap();
+        let warning = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF111")
+            .expect("expected a merged-cells warning");
+        assert!(warning.message.contains("merged cells"), "{}", warning.message);
+        // The grid keeps its shape: the continuation cell is empty.
+        assert!(output.markdown.contains("| Invoice |  |"), "{}", output.markdown);
+
+        // Plain tables stay unflagged.
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 \\trowd\\intbl A\\cell B\\cell\\row}")
+            .unwrap();
+        assert!(!output.validation_results.iter().any(|r| r.code == "RTF111"));
+    }
+
+    #[test]
+    fn par_runs_collapse_to_one_blank_line_by_default() {
+        // Seven consecutive \par: the legacy vertical-spacing idiom.
+        let fixture = "{\\rtf1 one\\par\\par\\par\\par\\par\\par\\par two\\par}";
+        let output = DocumentPipeline::with_defaults().process(fixture).unwrap();
+        assert!(
+            output.markdown.contains("one\n\ntwo"),
+            "{:?}",
+            output.markdown
+        );
+        assert!(!output.markdown.contains("\n\n\n"), "{:?}", output.markdown);
+        let info = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF112")
+            .expect("expected a cleanup info result");
+        assert_eq!(info.level, ValidationLevel::Info);
+        assert!(info.message.contains("6 empty paragraph(s)"), "{}", info.message);
+    }
+
+    #[test]
+    fn cleanup_opt_out_keeps_the_empty_paragraphs() {
+        let fixture = "{\\rtf1 one\\par\\par\\par\\par\\par\\par\\par two\\par}";
+        let output = DocumentPipeline::new(PipelineConfig {
+            cleanup: CleanupPolicy {
+                enabled: false,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .process(fixture)
+        .unwrap();
+        // The \par run survives as blank lines, uncounted.
+        assert!(output.markdown.contains("\n\n\n"), "{:?}", output.markdown);
+        assert!(!output.validation_results.iter().any(|r| r.code == "RTF112"));
+    }
+
+    #[test]
+    fn preserve_formatting_raises_the_collapse_threshold() {
+        // Two empty paragraphs: deliberate spacing under the raised
+        // default limit, collapsed once formatting preservation is off.
+        let fixture = "{\\rtf1 one\\par\\par\\par two\\par}";
+        let output = DocumentPipeline::with_defaults().process(fixture).unwrap();
+        assert!(!output.validation_results.iter().any(|r| r.code == "RTF112"));
+        assert!(output.markdown.contains("\n\n\n"), "{:?}", output.markdown);
+
+        let output = DocumentPipeline::new(PipelineConfig {
+            preserve_formatting: false,
+            ..Default::default()
+        })
+        .process(fixture)
+        .unwrap();
+        assert!(!output.markdown.contains("\n\n\n"), "{:?}", output.markdown);
+        assert!(output.validation_results.iter().any(|r| r.code == "RTF112"));
+    }
+
+    #[test]
+    fn fragments_round_trip_without_gaining_a_header() {
+        // A bold/italic fragment the way a VFP9 memo field stores it.
+        let fragment = "\\b bold\\b0  and \\i italic\\i0 ";
+        let pipeline = DocumentPipeline::new(PipelineConfig {
+            allow_fragment: true,
+            ..Default::default()
+        });
+        let output = pipeline.process(fragment).unwrap();
+        assert!(output.metadata.is_fragment);
+        assert!(output.markdown.contains("**bold**"), "{}", output.markdown);
+        assert!(output.markdown.contains("*italic*"), "{}", output.markdown);
+        assert!(output
+            .validation_results
+            .iter()
+            .any(|r| r.code == "RTF113" && r.level == ValidationLevel::Info));
+
+        // Back to RTF for the same memo field: still headerless, still
+        // a fragment to the next conversion.
+        let rtf = crate::conversion::markdown_to_rtf_fragment(&output.markdown).unwrap();
+        assert!(!rtf.contains("\\rtf1"), "{rtf}");
+        let again = pipeline.process(&rtf).unwrap();
+        assert!(again.metadata.is_fragment);
+        assert!(again.markdown.contains("**bold**"), "{}", again.markdown);
+
+        // Without the flag, fragments stay rejected.
+        let err = DocumentPipeline::with_defaults()
+            .process(fragment)
+            .unwrap_err();
+        assert!(err.to_string().contains("RTF header"), "{err}");
+    }
+
+    #[test]
+    fn integrity_signed_output_verifies_until_tampered_with() {
+        let fixture = "{\\rtf1 Hello \\b World\\b0\\par}";
+        let output = DocumentPipeline::new(PipelineConfig {
+            integrity: true,
+            ..Default::default()
+        })
+        .process(fixture)
+        .unwrap();
+        assert!(
+            output.markdown.contains("<!-- legacybridge-integrity "),
+            "{}",
+            output.markdown
+        );
+        let block = integrity::verify_integrity(&output.markdown).unwrap();
+        assert_eq!(block.input_sha256, integrity::sha256_hex(fixture));
+
+        // One flipped character after signing must fail verification.
+        let tampered = output.markdown.replacen("Hello", "Hallo", 1);
+        assert!(verify_integrity_fails(&tampered));
+
+        // Default runs stay unsigned.
+        let output = DocumentPipeline::with_defaults().process(fixture).unwrap();
+        assert!(!output.markdown.contains("legacybridge-integrity"));
+    }
+
+    fn verify_integrity_fails(content: &str) -> bool {
+        integrity::verify_integrity(content)
+            .unwrap_err()
+            .contains("output hash mismatch")
+    }
+
+    #[test]
+    fn strict_verification_passes_on_well_formed_documents() {
+        // Strict mode turns RTF110 mismatches into hard errors, so a
+        // representative mix of headings, tables and deleted revisions
+        // must still convert cleanly under it.
+        let output = DocumentPipeline::new(PipelineConfig {
+            verify_output: true,
+            strict_validation: true,
+            ..Default::default()
+        })
+        .process(
+            "{\\rtf1 \\outlinelevel0 Title\\par \\pard \
+             kept {\\deleted\\revauthdel1 gone} tail\\par \
+             \\trowd\\intbl A\\cell B\\cell\\row}",
+        )
+        .unwrap();
+        assert!(!output.validation_results.iter().any(|r| r.code == "RTF110"));
+    }
+
+    #[test]
+    fn pipeline_rejects_non_rtf() {
+        let err = DocumentPipeline::with_defaults()
+            .process("just some text")
+            .unwrap_err();
+        let ConversionError::ValidationError { code, .. } = err else {
+            panic!("expected ValidationError, got {err:?}");
+        };
+        assert_eq!(code, "RTF003");
+    }
+
+    #[test]
+    fn parse_failure_is_a_parse_error() {
+        // A trailing backslash fails tokenization even with auto-recovery
+        // and must be reported as a parse error, not a validation error.
+        let err = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 broken\\")
+            .unwrap_err();
+        assert!(matches!(err, ConversionError::ParseError(_)), "{err:?}");
+        assert_eq!(err.category(), "parse");
+    }
+
+    #[test]
+    fn truncated_destination_recovers_under_auto_recovery() {
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1{\\fonttbl")
+            .unwrap();
+        assert!(output
+            .validation_results
+            .iter()
+            .any(|r| r.code == "RTF104"));
+    }
+
+    #[test]
+    fn stray_group_end_retains_following_text() {
+        // A mid-file stray `}` must not truncate the document when
+        // auto_recovery is on, and the recovery must be reported.
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 before}} after\\par}")
+            .unwrap();
+        assert!(output.markdown.contains("after"), "{}", output.markdown);
+        assert!(output
+            .validation_results
+            .iter()
+            .any(|r| r.code == "RTF104"));
+    }
+
+    #[test]
+    fn raw_utf8_under_ansi_converts_and_reports_an_encoding_fix() {
+        // Some export tools write raw UTF-8 bodies into \ansi documents
+        // instead of escaping. The characters must come through intact,
+        // with the acceptance recorded for auditors.
+        let input = "{\\rtf1\\ansi\\ansicpg1252\\deff0 Привет, мир!\\par}";
+        let output = DocumentPipeline::with_defaults().process(input).unwrap();
+        assert!(output.markdown.contains("Привет, мир!"), "{}", output.markdown);
+        assert!(output
+            .recovery_actions
+            .iter()
+            .any(|a| a.kind == RecoveryKind::EncodingFix));
+
+        // Strict validation still flags the malformed charset declaration,
+        // without blocking the conversion.
+        let strict = DocumentPipeline::new(PipelineConfig {
+            strict_validation: true,
+            ..Default::default()
+        })
+        .process(input)
+        .unwrap();
+        assert!(strict
+            .validation_results
+            .iter()
+            .any(|r| r.code == "RTF122" && r.level == ValidationLevel::Warning));
+    }
+
+    #[test]
+    fn hooks_run_in_registration_order() {
+        let output = DocumentPipeline::with_defaults()
+            .register_pre_generate("part-numbers", |document, results| {
+                document
+                    .content
+                    .push(RtfNode::Text("first".to_string()));
+                results.push(ValidationResult::info("HOOK1", "part numbers rewritten"));
+                Ok(())
+            })
+            .register_pre_generate("appendix", |document, _| {
+                document
+                    .content
+                    .push(RtfNode::Text(" second".to_string()));
+                Ok(())
+            })
+            .register_post_generate("stamp", |markdown, _| {
+                markdown.push_str("\n<!-- stamped -->\n");
+                Ok(())
+            })
+            .process("{\\rtf1 body\\par}")
+            .unwrap();
+        let first = output.markdown.find("first").unwrap();
+        let second = output.markdown.find("second").unwrap();
+        assert!(first < second, "{}", output.markdown);
+        assert!(output.markdown.ends_with("<!-- stamped -->\n"));
+        assert!(output.validation_results.iter().any(|r| r.code == "HOOK1"));
+    }
+
+    #[test]
+    fn panicking_hook_is_isolated_and_named() {
+        let err = DocumentPipeline::with_defaults()
+            .register_pre_generate("exploder", |_, _| panic!("boom"))
+            .process("{\\rtf1 body\\par}")
+            .unwrap_err();
+        let ConversionError::GenerationError(message) = err else {
+            panic!("expected GenerationError, got {err:?}");
+        };
+        assert!(message.contains("'exploder'"), "{message}");
+        assert!(message.contains("boom"), "{message}");
+
+        // A hook returning an error surfaces it unchanged.
+        let err = DocumentPipeline::with_defaults()
+            .register_post_generate("rejector", |_, _| {
+                Err(ConversionError::validation("rejected by policy"))
+            })
+            .process("{\\rtf1 body\\par}")
+            .unwrap_err();
+        assert!(matches!(err, ConversionError::ValidationError { .. }), "{err:?}");
+    }
+
+    #[test]
+    fn unbalanced_braces_produce_a_recovery_action_with_excerpts() {
+        let input = "{\\rtf1 left open\\par";
+        let output = DocumentPipeline::with_defaults().process(input).unwrap();
+        let action = output
+            .recovery_actions
+            .iter()
+            .find(|a| a.description.contains("closing brace"))
+            .expect("expected a closing-brace recovery action");
+        assert_eq!(action.offset, input.len());
+        assert!(action.before.ends_with("\\par"), "{}", action.before);
+        assert!(action.after.ends_with("\\par}"), "{}", action.after);
+
+        // Recovery actions are only reported when recovery is on.
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 balanced\\par}")
+            .unwrap();
+        assert!(output.recovery_actions.is_empty());
+    }
+
+    #[test]
+    fn compare_validation_reports_what_strict_would_have_flagged() {
+        // Unclosed group: lenient mode recovers with a warning, strict
+        // mode rejects with an RTF004 error.
+        let input = "{\\rtf1 left open\\par";
+        let config = PipelineConfig {
+            compare_validation: true,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(input).unwrap();
+        assert_eq!(output.strict_delta.len(), 1, "{:?}", output.strict_delta);
+        assert_eq!(output.strict_delta[0].code, "RTF004");
+        assert_eq!(output.strict_delta[0].level, ValidationLevel::Error);
+
+        // The delta is opt-in, and empty when the run is already strict.
+        let output = DocumentPipeline::with_defaults().process(input).unwrap();
+        assert!(output.strict_delta.is_empty());
+        let config = PipelineConfig {
+            compare_validation: true,
+            strict_validation: true,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process("{\\rtf1 balanced\\par}")
+            .unwrap();
+        assert!(output.strict_delta.is_empty());
+    }
+
+    #[test]
+    fn strict_parser_rejects_stray_group_end() {
+        let config = PipelineConfig {
+            auto_recovery: false,
+            ..Default::default()
+        };
+        let err = DocumentPipeline::new(config)
+            .process("{\\rtf1 before}} after\\par}")
+            .unwrap_err();
+        assert!(matches!(err, ConversionError::ParseError(_)), "{err:?}");
+    }
+
+    #[test]
+    fn error_codes_are_stable_per_category() {
+        assert_eq!(ConversionError::parse("x").error_code(), -2);
+        assert_eq!(ConversionError::validation("x").error_code(), -3);
+        assert_eq!(ConversionError::generation("x").error_code(), -4);
+        assert_eq!(ConversionError::Cancelled.error_code(), -6);
+    }
+
+    #[test]
+    fn cancelled_token_stops_the_pipeline_before_work() {
+        let token = CancellationToken::new();
+        token.cancel();
+        let err = DocumentPipeline::with_defaults()
+            .with_cancellation(token)
+            .process("{\\rtf1 Hi\\par}")
+            .unwrap_err();
+        assert!(matches!(err, ConversionError::Cancelled), "{err:?}");
+        assert_eq!(err.category(), "cancelled");
+    }
+
+    #[test]
+    fn cancellation_interrupts_a_large_conversion_promptly() {
+        // Large enough that the parse loop runs for a while; the token is
+        // flipped mid-flight and the run must return promptly. The worker
+        // timestamps the moment `process` returns so the measurement is
+        // the pipeline's cancellation latency, not `join()` and thread
+        // scheduling overhead.
+        let rtf = format!(
+            "{{\\rtf1 {}}}",
+            "some plain paragraph text to chew through\\par ".repeat(200_000)
+        );
+        let token = CancellationToken::new();
+        let worker_token = token.clone();
+        let handle = std::thread::spawn(move || {
+            let result = DocumentPipeline::with_defaults()
+                .with_cancellation(worker_token)
+                .process(&rtf);
+            (result, std::time::Instant::now())
+        });
+        std::thread::sleep(std::time::Duration::from_millis(30));
+        token.cancel();
+        let cancelled_at = std::time::Instant::now();
+        let (result, returned_at) = handle.join().unwrap();
+        assert!(
+            matches!(result, Err(ConversionError::Cancelled)),
+            "conversion finished before it could be cancelled; grow the fixture"
+        );
+        // The product target is <100ms; the assertion leaves a generous
+        // margin so scheduling jitter on loaded CI runners cannot turn
+        // it into an intermittent failure.
+        let latency = returned_at.saturating_duration_since(cancelled_at);
+        assert!(
+            latency < std::time::Duration::from_millis(500),
+            "cancellation took {latency:?}"
+        );
+    }
+
+    #[test]
+    fn font_substitutions_are_reported_as_info() {
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1{\\fonttbl{\\f0\\fswiss MS Sans Serif;}}Hello\\par}")
+            .unwrap();
+        let info = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF105")
+            .expect("expected a font substitution info result");
+        assert_eq!(info.level, ValidationLevel::Info);
+        assert!(info.message.contains("MS Sans Serif"), "{}", info.message);
+        assert!(info.message.contains("Segoe UI"), "{}", info.message);
+    }
+
+    #[test]
+    fn missing_font_map_file_is_a_validation_error() {
+        let config = PipelineConfig {
+            font_map_path: Some("/nonexistent/fonts.json".to_string()),
+            ..Default::default()
+        };
+        let err = DocumentPipeline::new(config)
+            .process("{\\rtf1 Hello\\par}")
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+    }
+
+    #[test]
+    fn link_rewrites_apply_and_are_reported_as_info() {
+        let config = PipelineConfig {
+            link_rewrite: Some(super::super::link_rewrite::LinkRewriteConfig {
+                rules: vec![super::super::link_rewrite::LinkRewriteRule {
+                    pattern: Some(r"^http://intranet/(\w+)$".to_string()),
+                    replacement: "https://wiki.example.com/$1".to_string(),
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process(
+                "{\\rtf1 See {\\field{\\*\\fldinst{HYPERLINK \"http://intranet/hr\"}}\
+                 {\\fldrslt HR}}\\par}",
+            )
+            .unwrap();
+        assert!(
+            output.markdown.contains("[HR](https://wiki.example.com/hr)"),
+            "{}",
+            output.markdown
+        );
+        let info = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF116")
+            .expect("expected a link rewrite info result");
+        assert!(info.message.contains("http://intranet/hr"), "{}", info.message);
+        assert!(info.message.contains("https://wiki.example.com/hr"), "{}", info.message);
+    }
+
+    #[test]
+    fn unmatched_links_warn_under_the_warn_policy() {
+        let config = PipelineConfig {
+            link_rewrite: Some(super::super::link_rewrite::LinkRewriteConfig {
+                rules: Vec::new(),
+                unmatched: super::super::link_rewrite::UnmatchedLinkPolicy::Warn,
+            }),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process(
+                "{\\rtf1 See {\\field{\\*\\fldinst{HYPERLINK \"http://dead.example.net/x\"}}\
+                 {\\fldrslt here}}\\par}",
+            )
+            .unwrap();
+        let warning = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF117")
+            .expect("expected an unmatched-link warning");
+        assert_eq!(warning.level, ValidationLevel::Warning);
+        assert!(
+            warning.message.contains("http://dead.example.net/x"),
+            "{}",
+            warning.message
+        );
+        // The link itself is kept as written.
+        assert!(output.markdown.contains("http://dead.example.net/x"));
+    }
+
+    #[test]
+    fn truncated_table_cells_are_reported_as_a_warning() {
+        let rtf = "{\\rtf1 \\trowd\\intbl One\\par Two\\par Three\\cell B\\cell\\row}";
+        let config = PipelineConfig {
+            max_cell_length: Some(8),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert!(output.markdown.contains('…'), "{}", output.markdown);
+        let warning = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF118")
+            .expect("expected a truncation warning");
+        assert!(warning.message.contains("1 table cell(s)"), "{}", warning.message);
+
+        // The HTML fallback keeps the content whole instead: no warning.
+        let config = PipelineConfig {
+            max_cell_length: Some(8),
+            fallback_html_tables: true,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert!(output.markdown.contains("One<br>Two<br>Three"), "{}", output.markdown);
+        assert!(!output.validation_results.iter().any(|r| r.code == "RTF118"));
+    }
+
+    #[test]
+    fn missing_link_rewrite_file_is_a_validation_error() {
+        let config = PipelineConfig {
+            link_rewrite_path: Some("/nonexistent/links.json".to_string()),
+            ..Default::default()
+        };
+        let err = DocumentPipeline::new(config)
+            .process("{\\rtf1 Hello\\par}")
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+    }
+
+    #[test]
+    fn terminology_replaces_terms_and_reports_positions() {
+        let config = PipelineConfig {
+            terminology: Some(super::super::terminology::TerminologyConfig {
+                rules: vec![super::super::terminology::TerminologyRule {
+                    pattern: "login".to_string(),
+                    replacement: Some("sign in".to_string()),
+                    ..Default::default()
+                }],
+            }),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process("{\\rtf1 Login to the portal.\\par}")
+            .unwrap();
+        assert!(output.markdown.contains("Sign in to the portal."), "{}", output.markdown);
+        let info = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF119")
+            .expect("expected a terminology info result");
+        assert!(info.message.contains("'Login'"), "{}", info.message);
+        assert!(info.message.contains("'Sign in'"), "{}", info.message);
+        assert!(info.message.contains("line 1"), "{}", info.message);
+    }
+
+    #[test]
+    fn banned_terms_are_flagged_without_rewriting() {
+        let config = PipelineConfig {
+            terminology: Some(super::super::terminology::TerminologyConfig {
+                rules: vec![super::super::terminology::TerminologyRule {
+                    pattern: "kill switch".to_string(),
+                    ..Default::default()
+                }],
+            }),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process("{\\rtf1 Flip the kill switch.\\par}")
+            .unwrap();
+        assert!(output.markdown.contains("kill switch"), "{}", output.markdown);
+        let warning = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF120")
+            .expect("expected a banned-term warning");
+        assert_eq!(warning.level, ValidationLevel::Warning);
+        assert!(warning.message.contains("'kill switch'"), "{}", warning.message);
+    }
+
+    #[test]
+    fn missing_terminology_file_is_a_validation_error() {
+        let config = PipelineConfig {
+            terminology_path: Some("/nonexistent/terms.csv".to_string()),
+            ..Default::default()
+        };
+        let err = DocumentPipeline::new(config)
+            .process("{\\rtf1 Hello\\par}")
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+    }
+
+    #[test]
+    fn fixed_timestamps_make_templated_output_reproducible() {
+        let config = PipelineConfig {
+            template: Some("letter".to_string()),
+            fixed_timestamp: Some("2026-01-15T09:30:00".to_string()),
+            ..Default::default()
+        };
+        let input = "{\\rtf1 Dear reader\\par}";
+
+        let first = DocumentPipeline::new(config.clone()).process(input).unwrap();
+        let second = DocumentPipeline::new(config.clone()).process(input).unwrap();
+        assert_eq!(first.markdown, second.markdown);
+        assert!(first.markdown.contains("01/15/2026"), "{}", first.markdown);
+
+        // A fixed clock never reads the machine's timezone, so the run
+        // is byte-identical under any TZ - here demonstrated with the
+        // config's own timezone selection, which only steers real-time
+        // reads.
+        let utc = DocumentPipeline::new(PipelineConfig {
+            timezone: ClockTimezone::Utc,
+            ..config.clone()
+        })
+        .process(input)
+        .unwrap();
+        assert_eq!(utc.markdown, first.markdown);
+
+        // The integrity block signs with the same clock.
+        let signed = DocumentPipeline::new(PipelineConfig {
+            integrity: true,
+            ..config.clone()
+        });
+        let first = signed.process(input).unwrap();
+        assert!(
+            first.markdown.contains("time=2026-01-15T09:30:00Z"),
+            "{}",
+            first.markdown
+        );
+        let second = DocumentPipeline::new(PipelineConfig {
+            integrity: true,
+            ..config
+        })
+        .process(input)
+        .unwrap();
+        assert_eq!(first.markdown, second.markdown);
+    }
+
+    #[test]
+    fn a_bad_fixed_timestamp_is_a_validation_error() {
+        let config = PipelineConfig {
+            template: Some("letter".to_string()),
+            fixed_timestamp: Some("yesterdayish".to_string()),
+            ..Default::default()
+        };
+        let err = DocumentPipeline::new(config)
+            .process("{\\rtf1 Dear reader\\par}")
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+        assert!(err.to_string().contains("timestamp"), "{err}");
+    }
+
+    #[test]
+    fn pipeline_exposes_the_document_outline() {
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 \\outlinelevel0 Intro\\par \\pard Body\\par}")
+            .unwrap();
+        assert_eq!(output.metadata.outline.len(), 1);
+        assert_eq!(output.metadata.outline[0].slug, "intro");
+    }
+
+    #[test]
+    fn heading_offset_demotes_with_a_clamp_warning_at_h6() {
+        let config = PipelineConfig {
+            heading_offset: 1,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process(
+                "{\\rtf1 \\outlinelevel0 Intro\\par \\pard \
+                 \\outlinelevel5 Fine Print\\par \\pard Body\\par}",
+            )
+            .unwrap();
+        assert!(output.markdown.contains("## Intro"));
+        // H6 cannot demote further; it stays H6 and the loss is reported.
+        assert!(output.markdown.contains("###### Fine Print"));
+        let warning = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF114")
+            .expect("clamp warning");
+        assert!(warning.message.contains("1 heading(s)"), "{}", warning.message);
+        // The outline reflects the shifted levels.
+        let levels: Vec<u8> = output.metadata.outline.iter().map(|e| e.level).collect();
+        assert_eq!(levels, vec![2, 6]);
+    }
+
+    #[test]
+    fn bidi_overrides_are_stripped_under_their_own_warning_code() {
+        // \u8238 is U+202E RIGHT-TO-LEFT OVERRIDE, the filename-spoofing
+        // classic.
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 file\\u8238?cod.rtf\\par}")
+            .unwrap();
+        assert!(output.markdown.contains("filecod.rtf"), "{}", output.markdown);
+        let warning = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF115")
+            .expect("hygiene warning");
+        assert!(
+            warning.message.contains("bidi override"),
+            "{}",
+            warning.message
+        );
+    }
+
+    #[test]
+    fn tenant_limits_can_keep_bidi_overrides() {
+        use crate::security::{SecurityLimits, UnicodeHygiene};
+
+        let ctx = crate::conversion::context::ConversionContext::new("tenant").with_limits(
+            SecurityLimits {
+                unicode_hygiene: UnicodeHygiene::keep_all(),
+                ..Default::default()
+            },
+        );
+        let output = DocumentPipeline::with_defaults()
+            .process_with_context("{\\rtf1 file\\u8238?cod.rtf\\par}", &ctx)
+            .unwrap();
+        assert!(output.markdown.contains("file\u{202E}cod.rtf"), "kept");
+        assert!(!output.validation_results.iter().any(|r| r.code == "RTF115"));
+    }
+
+    #[test]
+    fn normalization_form_is_configurable_and_recorded() {
+        // Default: the Mac-style decomposed accent comes out composed.
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 cafe\\u769?\\par}")
+            .unwrap();
+        assert!(output.markdown.contains("caf\u{E9}"), "{}", output.markdown);
+        assert_eq!(output.metadata.normalization, NormalizationForm::Nfc);
+
+        // NFD decomposes the composed source instead.
+        let config = PipelineConfig {
+            normalization: NormalizationForm::Nfd,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process("{\\rtf1 caf\\u233?\\par}")
+            .unwrap();
+        assert!(
+            output.markdown.contains("cafe\u{0301}"),
+            "{}",
+            output.markdown
+        );
+        assert_eq!(output.metadata.normalization, NormalizationForm::Nfd);
+    }
+
+    #[test]
+    fn pipeline_surfaces_the_degradation_report() {
+        use crate::conversion::features::{FeatureCategory, FeatureSeverity};
+
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 {\\*\\do\\dpline} body {\\*\\atnicn2}\\par}")
+            .unwrap();
+        let usage = &output.feature_usage;
+        assert_eq!(usage.get("do").unwrap().count, 1);
+        assert_eq!(
+            usage.category_counts()[&FeatureCategory::Annotations],
+            1
+        );
+        assert_eq!(usage.worst_severity(), Some(FeatureSeverity::Lossy));
+
+        let clean = DocumentPipeline::with_defaults()
+            .process("{\\rtf1 plain\\par}")
+            .unwrap();
+        assert!(clean.feature_usage.is_empty());
+    }
+
+    #[test]
+    fn placeholder_policy_is_configurable() {
+        let rtf = "{\\rtf1 Figure: {\\*\\do\\dprect} end\\par}";
+        let output = DocumentPipeline::with_defaults().process(rtf).unwrap();
+        assert!(
+            output.markdown.contains("Figure: [drawing omitted] end"),
+            "{}",
+            output.markdown
+        );
+
+        let config = PipelineConfig {
+            placeholders: PlaceholderPolicy {
+                drawing: "(figure)".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert!(
+            output.markdown.contains("Figure: (figure) end"),
+            "{}",
+            output.markdown
+        );
+    }
+
+    #[test]
+    fn revision_mode_is_configurable_through_the_pipeline() {
+        let rtf = "{\\rtf1 keep {\\deleted gone}\\par}";
+        let output = DocumentPipeline::with_defaults().process(rtf).unwrap();
+        assert_eq!(output.markdown.trim_end(), "keep");
+
+        let config = PipelineConfig {
+            revision_mode: RevisionMode::Annotate,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert!(output.markdown.contains("{--gone--}"), "{}", output.markdown);
+    }
+
+    #[test]
+    fn form_field_extraction_is_opt_in_through_the_config() {
+        let rtf = "{\\rtf1 Subscribed: {\\field{\\*\\formfield{\\fftype1\\ffres1\
+                   {\\*\\ffname subscribed}}}}\\par}";
+        let config = PipelineConfig {
+            extract_form_fields: true,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert_eq!(output.form_fields.len(), 1);
+        assert_eq!(output.form_fields[0].name.as_deref(), Some("subscribed"));
+        assert_eq!(output.form_fields[0].value, "true");
+        assert!(output.markdown.contains("[x]"), "{}", output.markdown);
+
+        let output = DocumentPipeline::with_defaults().process(rtf).unwrap();
+        assert!(output.form_fields.is_empty());
+    }
+
+    /// Three comments by two reviewers, the first anchored to a range.
+    fn commented_fixture() -> &'static str {
+        "{\\rtf1 The {\\*\\atrfstart1}budget figure{\\*\\atrfend1}\
+         {\\*\\atnid AB}{\\*\\atnauthor Alice Brown}\\chatn\
+         {\\*\\annotation{\\*\\atnref1}Please verify} needs review.\
+         {\\*\\atnid CD}\\chatn{\\*\\annotation Looks wrong} Final copy.\
+         {\\*\\atnid AB}\\chatn{\\*\\annotation Approved}\\par}"
+    }
+
+    #[test]
+    fn stripped_comments_are_counted_and_still_surfaced() {
+        let output = DocumentPipeline::with_defaults()
+            .process(commented_fixture())
+            .unwrap();
+        assert!(!output.markdown.contains("Please verify"), "{}", output.markdown);
+        assert!(!output.markdown.contains("[note"), "{}", output.markdown);
+        assert_eq!(output.annotations.len(), 3);
+        assert_eq!(output.annotations[0].initials.as_deref(), Some("AB"));
+        let warning = output
+            .validation_results
+            .iter()
+            .find(|r| r.code == "RTF109")
+            .expect("expected a stripped-comments warning");
+        assert!(warning.message.contains("3 comment(s)"), "{}", warning.message);
+    }
+
+    #[test]
+    fn footnote_mode_renders_numbered_margin_notes() {
+        let config = PipelineConfig {
+            annotation_mode: AnnotationMode::Footnotes,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process(commented_fixture())
+            .unwrap();
+        assert!(output.markdown.contains("[note 1]"), "{}", output.markdown);
+        assert!(
+            output.markdown.contains("[note 1: AB] Please verify"),
+            "{}",
+            output.markdown
+        );
+        assert!(
+            output.markdown.contains("[note 2: CD] Looks wrong"),
+            "{}",
+            output.markdown
+        );
+        assert!(!output
+            .validation_results
+            .iter()
+            .any(|r| r.code == "RTF109"));
+    }
+
+    #[test]
+    fn comments_section_lists_notes_with_anchors() {
+        let config = PipelineConfig {
+            annotation_mode: AnnotationMode::Section,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config)
+            .process(commented_fixture())
+            .unwrap();
+        assert!(output.markdown.contains("## Comments"), "{}", output.markdown);
+        assert!(
+            output
+                .markdown
+                .contains("1. **AB** (note 1, on \"budget figure\"): Please verify"),
+            "{}",
+            output.markdown
+        );
+        assert!(
+            output.markdown.contains("3. **AB** (note 3): Approved"),
+            "{}",
+            output.markdown
+        );
+    }
+
+    #[test]
+    fn template_dry_run_returns_untransformed_output_with_a_diff() {
+        let rtf = "{\\rtf1 Total 1234.5\\par}";
+        let config = PipelineConfig {
+            template: Some("report".to_string()),
+            dry_run: true,
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert!(output.markdown.contains("1234.5"), "{}", output.markdown);
+        let diff = output.template_diff.unwrap();
+        assert_eq!(diff.transformations[0].touched, 1);
+
+        // Without dry_run the template actually applies and no diff is set.
+        let config = PipelineConfig {
+            template: Some("report".to_string()),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(rtf).unwrap();
+        assert!(output.markdown.contains("1,234.50"), "{}", output.markdown);
+        assert!(output.template_diff.is_none());
+    }
+
+    #[test]
+    fn unknown_template_is_a_validation_error() {
+        let config = PipelineConfig {
+            template: Some("nonexistent".to_string()),
+            ..Default::default()
+        };
+        let err = DocumentPipeline::new(config)
+            .process("{\\rtf1 Hi\\par}")
+            .unwrap_err();
+        let ConversionError::ValidationError { code, .. } = err else {
+            panic!("expected validation error, got {err:?}");
+        };
+        assert_eq!(code, "RTF108");
+    }
+
+    #[test]
+    fn tenant_templates_are_visible_only_in_their_namespace() {
+        use crate::conversion::template::Template;
+
+        let mut letterhead = Template::new(
+            "tenant-a/letterhead",
+            crate::conversion::template::TemplateType::Memo,
+        );
+        letterhead.header_footer.footer = Some("Tenant A Inc.".to_string());
+
+        let config = PipelineConfig {
+            template: Some("letterhead".to_string()),
+            ..Default::default()
+        };
+        let pipeline = DocumentPipeline::new(config);
+
+        let ctx_a = ConversionContext::new("tenant-a")
+            .with_template_namespace("tenant-a")
+            .with_template(letterhead);
+        let output = pipeline
+            .process_with_context("{\\rtf1 Hi\\par}", &ctx_a)
+            .unwrap();
+        assert!(output.markdown.contains("Tenant A Inc."), "{}", output.markdown);
+
+        // Tenant B's namespace has no letterhead and there is no global
+        // one, so the same config fails for them.
+        let ctx_b = ConversionContext::new("tenant-b").with_template_namespace("tenant-b");
+        let err = pipeline
+            .process_with_context("{\\rtf1 Hi\\par}", &ctx_b)
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+    }
+
+    #[test]
+    fn context_limits_and_caps_are_enforced() {
+        use crate::security::SecurityLimits;
+
+        let limits = SecurityLimits {
+            max_input_size: 8,
+            ..Default::default()
+        };
+        let ctx = ConversionContext::new("tiny-tenant").with_limits(limits);
+        let err = DocumentPipeline::with_defaults()
+            .process_with_context("{\\rtf1 too big for the limit\\par}", &ctx)
+            .unwrap_err();
+        assert_eq!(err.category(), "validation");
+
+        context::tenant_limiter().set_cap("capped-tenant", 0);
+        let err = DocumentPipeline::with_defaults()
+            .process_with_context("{\\rtf1 Hi\\par}", &ConversionContext::new("capped-tenant"))
+            .unwrap_err();
+        assert_eq!(err.category(), "resource_limit");
+    }
+
+    /// Six pages (five `\page` breaks), each with its own marker text.
+    fn paged_fixture() -> String {
+        let body = (1..=6)
+            .map(|i| format!("P{i}\\par"))
+            .collect::<Vec<_>>()
+            .join("\\page ");
+        format!("{{\\rtf1 {body}}}")
+    }
+
+    #[test]
+    fn page_range_selects_exactly_the_requested_pages() {
+        let config = PipelineConfig {
+            page_range: Some(PageRange { start: 2, end: 3 }),
+            ..Default::default()
+        };
+        let output = DocumentPipeline::new(config).process(&paged_fixture()).unwrap();
+        assert!(output.markdown.contains("P2"), "{}", output.markdown);
+        assert!(output.markdown.contains("P3"), "{}", output.markdown);
+        assert!(!output.markdown.contains("P1"), "{}", output.markdown);
+        assert!(!output.markdown.contains("P4"), "{}", output.markdown);
+    }
+
+    #[test]
+    fn out_of_range_pages_are_a_validation_error() {
+        let config = PipelineConfig {
+            page_range: Some(PageRange { start: 5, end: 9 }),
+            ..Default::default()
+        };
+        let err = DocumentPipeline::new(config)
+            .process(&paged_fixture())
+            .unwrap_err();
+        let ConversionError::ValidationError { code, message } = err else {
+            panic!("expected ValidationError, got {err:?}");
+        };
+        assert_eq!(code, "RTF107");
+        assert!(message.contains("6 page(s)"), "{message}");
+    }
+
+    #[test]
+    fn page_count_counts_break_delimited_pages() {
+        assert_eq!(page_count(&paged_fixture()).unwrap(), 6);
+        assert_eq!(page_count("{\\rtf1 single\\par}").unwrap(), 1);
+    }
+
+    #[test]
+    fn pipeline_surfaces_metadata() {
+        let output = DocumentPipeline::with_defaults()
+            .process("{\\rtf1{\\info{\\title Spec}}Body\\par}")
+            .unwrap();
+        assert_eq!(output.metadata.title.as_deref(), Some("Spec"));
+    }
+}
@@ -0,0 +1,213 @@
+//! Structured reporting of auto-recovery edits.
+//!
+//! The tolerant parser describes what it repaired in prose; auditors also
+//! need the where and the exact change. A [`RecoveryAction`] pairs each
+//! structural repair with the byte offset of the edit and before/after
+//! excerpts of the source around it.
+
+use serde::{Deserialize, Serialize};
+
+/// Total excerpt window around an edit, in characters.
+const EXCERPT_WINDOW: usize = 120;
+
+/// The classes of repair auto-recovery can apply. Auditors filter on
+/// this rather than parsing the prose description.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryKind {
+    /// A brace-balance repair: stray terminator ignored or closing
+    /// brace implied. The default, so reports serialized before the
+    /// field existed (all brace repairs) deserialize unchanged.
+    #[default]
+    BraceRepair,
+    /// Raw UTF-8 multibyte text accepted in a document that declared a
+    /// single-byte charset.
+    EncodingFix,
+}
+
+/// One structural repair applied during auto-recovery.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryAction {
+    /// Machine-readable class of the repair.
+    #[serde(default)]
+    pub kind: RecoveryKind,
+    /// What was repaired, in prose.
+    pub description: String,
+    /// Byte offset of the edit in the original input.
+    pub offset: usize,
+    /// Source around the edit before the repair, clamped to
+    /// [`EXCERPT_WINDOW`] characters.
+    pub before: String,
+    /// The same window after the repair.
+    pub after: String,
+}
+
+/// Describe the brace repairs a tolerant parse applies to `input`: stray
+/// group terminators ignored, and closing braces implied at end of input
+/// for groups left open. Pure reporting - the tolerant parser performs
+/// the actual repairs on the token stream.
+pub fn brace_repairs(input: &str) -> Vec<RecoveryAction> {
+    let mut actions = Vec::new();
+    let bytes = input.as_bytes();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            // Skip the escaped character so \{ and \} don't count.
+            b'\\' => i += 1,
+            b'{' => depth += 1,
+            b'}' if depth == 0 => {
+                let mut repaired = input.to_string();
+                repaired.remove(i);
+                actions.push(RecoveryAction {
+                    kind: RecoveryKind::BraceRepair,
+                    description: "ignored stray group terminator".to_string(),
+                    offset: i,
+                    before: excerpt(input, i),
+                    after: excerpt(&repaired, i),
+                });
+            }
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    if depth > 0 {
+        let mut repaired = input.to_string();
+        repaired.extend(std::iter::repeat_n('}', depth));
+        actions.push(RecoveryAction {
+            kind: RecoveryKind::BraceRepair,
+            description: format!("inserted {depth} closing brace(s) at end of input"),
+            offset: input.len(),
+            before: excerpt(input, input.len()),
+            after: excerpt(&repaired, input.len()),
+        });
+    }
+    actions
+}
+
+/// Describe the encoding fix applied when a document that declares
+/// `\ansi` carries raw UTF-8 multibyte text instead of `\'xx` or `\uN`
+/// escapes. Some export tools write bodies this way; a strict cp1252
+/// reading turns every such character into mojibake, so the pipeline
+/// accepts the text as the UTF-8 it validated as on decode. Genuinely
+/// invalid bytes never reach here - [`decode_input`] already routed
+/// them through the windows-1252 fallback. The `before` excerpt shows
+/// what a strict cp1252 reading would have produced, `after` the text
+/// as accepted.
+///
+/// [`decode_input`]: crate::conversion::encoding::decode_input
+pub fn encoding_fixes(input: &str) -> Vec<RecoveryAction> {
+    if !declares_ansi(input) {
+        return Vec::new();
+    }
+    let Some((offset, _)) = input.char_indices().find(|(_, c)| !c.is_ascii()) else {
+        return Vec::new();
+    };
+    let count = input.chars().filter(|c| !c.is_ascii()).count();
+    let window = excerpt(input, offset);
+    vec![RecoveryAction {
+        kind: RecoveryKind::EncodingFix,
+        description: format!(
+            "accepted {count} raw UTF-8 character(s) in a document declaring \\ansi"
+        ),
+        offset,
+        before: crate::conversion::encoding::InputEncoding::Windows1252.decode(window.as_bytes()),
+        after: window,
+    }]
+}
+
+/// Whether the input carries the `\ansi` charset control word (not a
+/// longer word such as `\ansicpg` that merely shares the prefix).
+pub(crate) fn declares_ansi(input: &str) -> bool {
+    let bytes = input.as_bytes();
+    let mut from = 0;
+    while let Some(at) = input[from..].find("\\ansi") {
+        let end = from + at + "\\ansi".len();
+        if bytes.get(end).is_none_or(|b| !b.is_ascii_alphabetic()) {
+            return true;
+        }
+        from = end;
+    }
+    false
+}
+
+/// A window of up to [`EXCERPT_WINDOW`] characters centered on the byte
+/// `offset`, clamped to the text and to char boundaries.
+fn excerpt(text: &str, offset: usize) -> String {
+    let half = EXCERPT_WINDOW / 2;
+    let mut start = offset.saturating_sub(half).min(text.len());
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = offset.saturating_add(half).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+    text[start..end].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unbalanced_open_reports_the_implied_closing_braces() {
+        let input = "{\\rtf1 {\\b bold";
+        let actions = brace_repairs(input);
+        assert_eq!(actions.len(), 1);
+        let action = &actions[0];
+        assert_eq!(action.offset, input.len());
+        assert!(action.description.contains("2 closing brace(s)"));
+        assert!(action.before.ends_with("bold"), "{}", action.before);
+        assert!(action.after.ends_with("bold}}"), "{}", action.after);
+    }
+
+    #[test]
+    fn stray_terminator_reports_its_location() {
+        let input = "{\\rtf1 a}} b";
+        let actions = brace_repairs(input);
+        assert_eq!(actions.len(), 1);
+        let action = &actions[0];
+        assert_eq!(action.offset, 9);
+        assert_eq!(action.before, "{\\rtf1 a}} b");
+        assert_eq!(action.after, "{\\rtf1 a} b");
+    }
+
+    #[test]
+    fn escaped_braces_are_not_repairs() {
+        assert!(brace_repairs("{\\rtf1 \\{literal\\} text}").is_empty());
+    }
+
+    #[test]
+    fn raw_utf8_in_an_ansi_document_is_an_encoding_fix() {
+        let input = "{\\rtf1\\ansi\\ansicpg1252\\deff0 Привет\\par}";
+        let actions = encoding_fixes(input);
+        assert_eq!(actions.len(), 1);
+        let action = &actions[0];
+        assert_eq!(action.kind, RecoveryKind::EncodingFix);
+        assert!(action.description.contains("6 raw UTF-8 character(s)"));
+        assert_eq!(action.offset, input.find('П').unwrap());
+        // The before excerpt shows the mojibake a strict cp1252 reading
+        // would produce; the after excerpt keeps the accepted text.
+        assert!(action.after.contains("Привет"));
+        assert!(!action.before.contains("Привет"));
+    }
+
+    #[test]
+    fn escaped_and_ascii_ansi_documents_need_no_encoding_fix() {
+        // Escaped Cyrillic is well-formed, not a candidate for repair.
+        assert!(encoding_fixes("{\\rtf1\\ansi \\u1055?\\u1088?\\par}").is_empty());
+        assert!(encoding_fixes("{\\rtf1\\ansi plain\\par}").is_empty());
+        // No \ansi declaration: \ansicpg alone shares only the prefix.
+        assert!(encoding_fixes("{\\rtf1\\ansicpg1252 café\\par}").is_empty());
+    }
+
+    #[test]
+    fn excerpts_are_clamped_and_boundary_safe() {
+        let text = format!("{}é{}", "a".repeat(200), "b".repeat(200));
+        let window = excerpt(&text, 200);
+        assert!(window.chars().count() <= EXCERPT_WINDOW + 1);
+        assert!(window.contains('é'));
+    }
+}
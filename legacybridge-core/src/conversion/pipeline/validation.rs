@@ -0,0 +1,351 @@
+//! Input validation for the conversion pipeline.
+
+use serde::{Deserialize, Serialize};
+
+/// Maximum accepted document size for in-memory conversion.
+pub const MAX_DOCUMENT_SIZE: usize = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ValidationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A single finding produced by a validator.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidationResult {
+    pub level: ValidationLevel,
+    /// Stable machine-readable code, e.g. `RTF001`.
+    pub code: String,
+    pub message: String,
+}
+
+impl ValidationResult {
+    pub fn new(level: ValidationLevel, code: &str, message: impl Into<String>) -> Self {
+        ValidationResult {
+            level,
+            code: code.to_string(),
+            message: message.into(),
+        }
+    }
+
+    pub fn error(code: &str, message: impl Into<String>) -> Self {
+        Self::new(ValidationLevel::Error, code, message)
+    }
+
+    pub fn warning(code: &str, message: impl Into<String>) -> Self {
+        Self::new(ValidationLevel::Warning, code, message)
+    }
+
+    pub fn info(code: &str, message: impl Into<String>) -> Self {
+        Self::new(ValidationLevel::Info, code, message)
+    }
+}
+
+pub struct Validator {
+    strict: bool,
+    /// Severity of the missing-alt-text accessibility finding (`MD003`).
+    missing_alt_level: ValidationLevel,
+    /// Accept content without the `{\rtf1 ...}` wrapper (`RTF113` info
+    /// instead of the `RTF003` error).
+    allow_fragment: bool,
+    /// Size cap for the `RTF002`/`MD002` check; [`MAX_DOCUMENT_SIZE`]
+    /// unless a file-backed host raised it.
+    max_size: usize,
+}
+
+impl Validator {
+    pub fn new(strict: bool) -> Self {
+        Validator {
+            strict,
+            missing_alt_level: ValidationLevel::Warning,
+            allow_fragment: false,
+            max_size: MAX_DOCUMENT_SIZE,
+        }
+    }
+
+    /// Raise (or lower) the document size cap. The default
+    /// [`MAX_DOCUMENT_SIZE`] protects the in-memory IPC paths; hosts
+    /// converting straight from disk can afford more.
+    pub fn with_max_size(mut self, max_size: usize) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Accept headerless RTF fragments, as stored in database memo
+    /// fields; everything else - size caps, brace balance - still
+    /// applies to them.
+    pub fn with_fragment_allowed(mut self, allow_fragment: bool) -> Self {
+        self.allow_fragment = allow_fragment;
+        self
+    }
+
+    /// Report images without alt text at `level` instead of the default
+    /// [`ValidationLevel::Warning`]; accessibility-conscious deployments
+    /// raise it to `Error`, archives that only mirror content lower it to
+    /// `Info`.
+    pub fn with_missing_alt_level(mut self, level: ValidationLevel) -> Self {
+        self.missing_alt_level = level;
+        self
+    }
+
+    /// Validate raw RTF input before any parsing happens.
+    pub fn validate_rtf(&self, input: &str) -> Vec<ValidationResult> {
+        let mut results = Vec::new();
+
+        if input.is_empty() {
+            results.push(ValidationResult::error("RTF001", "document is empty"));
+            return results;
+        }
+        if input.len() > self.max_size {
+            results.push(ValidationResult::error(
+                "RTF002",
+                format!(
+                    "document exceeds maximum size ({} > {} bytes)",
+                    input.len(),
+                    self.max_size
+                ),
+            ));
+            return results;
+        }
+        if !input.trim_start().starts_with("{\\rtf") {
+            if self.allow_fragment {
+                results.push(ValidationResult::info(
+                    "RTF113",
+                    "no RTF header; treated as a fragment",
+                ));
+            } else {
+                results.push(ValidationResult::error(
+                    "RTF003",
+                    "missing RTF header ({\\rtf1 ...)",
+                ));
+            }
+        }
+
+        // Some export tools write raw UTF-8 bodies into documents that
+        // declare \ansi; the pipeline accepts the text as UTF-8 (it
+        // validated as such on decode), so this never blocks, but strict
+        // runs surface the malformed charset declaration as a warning.
+        if super::recovery::declares_ansi(input) {
+            let count = input.chars().filter(|c| !c.is_ascii()).count();
+            if count > 0 {
+                let level = if self.strict {
+                    ValidationLevel::Warning
+                } else {
+                    ValidationLevel::Info
+                };
+                results.push(ValidationResult::new(
+                    level,
+                    "RTF122",
+                    format!(
+                        "{count} raw UTF-8 character(s) in a document declaring \
+                         \\ansi; accepted as UTF-8"
+                    ),
+                ));
+            }
+        }
+
+        // Unbalanced groups are recoverable by the tolerant parser, so they
+        // only block the conversion under strict validation.
+        let level = if self.strict {
+            ValidationLevel::Error
+        } else {
+            ValidationLevel::Warning
+        };
+        match brace_balance(input) {
+            0 => {}
+            n if n > 0 => {
+                results.push(ValidationResult::new(
+                    level,
+                    "RTF004",
+                    format!("{n} unclosed group(s)"),
+                ));
+            }
+            n => {
+                results.push(ValidationResult::new(
+                    level,
+                    "RTF005",
+                    format!("{} extra group terminator(s)", -n),
+                ));
+            }
+        }
+
+        results
+    }
+
+    /// Validate Markdown input before the MD->RTF direction runs.
+    pub fn validate_markdown(&self, input: &str) -> Vec<ValidationResult> {
+        let mut results = Vec::new();
+        if input.is_empty() {
+            results.push(ValidationResult::error("MD001", "document is empty"));
+        }
+        if input.len() > self.max_size {
+            results.push(ValidationResult::error(
+                "MD002",
+                format!(
+                    "document exceeds maximum size ({} > {} bytes)",
+                    input.len(),
+                    self.max_size
+                ),
+            ));
+        }
+        let missing = images_without_alt(input);
+        if missing > 0 {
+            results.push(ValidationResult::new(
+                self.missing_alt_level,
+                "MD003",
+                format!(
+                    "{missing} image(s) have no alt text; screen readers will \
+                     announce nothing for them"
+                ),
+            ));
+        }
+        results
+    }
+}
+
+/// Count inline images (`![alt](src)`) whose alt text is empty or
+/// whitespace. A syntactic scan in the spirit of [`brace_balance`]; the
+/// parser keeps such images, this only surfaces the accessibility gap.
+fn images_without_alt(input: &str) -> usize {
+    let chars: Vec<char> = input.chars().collect();
+    let mut count = 0;
+    let mut i = 0;
+    while i + 1 < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+            continue;
+        }
+        if chars[i] != '!' || chars[i + 1] != '[' {
+            i += 1;
+            continue;
+        }
+        let alt_start = i + 2;
+        let Some(close) = (alt_start..chars.len()).find(|&j| chars[j] == ']') else {
+            break;
+        };
+        if chars.get(close + 1) == Some(&'(')
+            && chars[alt_start..close].iter().all(|c| c.is_whitespace())
+        {
+            count += 1;
+        }
+        i = close + 1;
+    }
+    count
+}
+
+/// Net brace balance, ignoring escaped `\{`/`\}`.
+fn brace_balance(input: &str) -> i64 {
+    let mut balance = 0i64;
+    let bytes = input.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1, // skip the escaped character
+            b'{' => balance += 1,
+            b'}' => balance -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    balance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_document_passes() {
+        let results = Validator::new(false).validate_rtf("{\\rtf1 Hello}");
+        assert!(results.iter().all(|r| r.level != ValidationLevel::Error));
+    }
+
+    #[test]
+    fn missing_header_is_an_error() {
+        let results = Validator::new(false).validate_rtf("hello world");
+        assert!(results.iter().any(|r| r.code == "RTF003"));
+    }
+
+    #[test]
+    fn fragments_pass_when_allowed_but_keep_the_other_checks() {
+        let validator = Validator::new(false).with_fragment_allowed(true);
+        let results = validator.validate_rtf("\\b bold\\b0");
+        assert!(results.iter().all(|r| r.level != ValidationLevel::Error));
+        assert!(results
+            .iter()
+            .any(|r| r.code == "RTF113" && r.level == ValidationLevel::Info));
+
+        // Brace balance still applies to fragments.
+        let results = validator.validate_rtf("{\\b unclosed");
+        assert!(results.iter().any(|r| r.code == "RTF004"));
+    }
+
+    #[test]
+    fn missing_alt_text_is_flagged_at_the_configured_level() {
+        let md = "![](x.png) next to ![a chart](y.png)";
+        let results = Validator::new(false).validate_markdown(md);
+        assert!(results
+            .iter()
+            .any(|r| r.code == "MD003" && r.level == ValidationLevel::Warning));
+
+        let raised = Validator::new(false)
+            .with_missing_alt_level(ValidationLevel::Error)
+            .validate_markdown(md);
+        assert!(raised
+            .iter()
+            .any(|r| r.code == "MD003" && r.level == ValidationLevel::Error));
+
+        let clean = Validator::new(false).validate_markdown("![a chart](y.png)");
+        assert!(clean.iter().all(|r| r.code != "MD003"));
+    }
+
+    #[test]
+    fn raised_size_cap_admits_oversized_documents() {
+        let mut big = String::from("{\\rtf1 ");
+        big.push_str(&"x".repeat(MAX_DOCUMENT_SIZE));
+        big.push('}');
+
+        let default_cap = Validator::new(false).validate_rtf(&big);
+        assert!(default_cap.iter().any(|r| r.code == "RTF002"));
+
+        let raised = Validator::new(false)
+            .with_max_size(big.len())
+            .validate_rtf(&big);
+        assert!(raised.iter().all(|r| r.code != "RTF002"));
+    }
+
+    #[test]
+    fn raw_utf8_under_ansi_is_info_in_lenient_warning_in_strict() {
+        let input = "{\\rtf1\\ansi\\deff0 Привет\\par}";
+        let lenient = Validator::new(false).validate_rtf(input);
+        assert!(lenient
+            .iter()
+            .any(|r| r.code == "RTF122" && r.level == ValidationLevel::Info));
+        let strict = Validator::new(true).validate_rtf(input);
+        assert!(strict
+            .iter()
+            .any(|r| r.code == "RTF122" && r.level == ValidationLevel::Warning));
+        // Escaped characters under \ansi are well-formed.
+        let escaped = Validator::new(true).validate_rtf("{\\rtf1\\ansi \\'e9\\u1055?\\par}");
+        assert!(escaped.iter().all(|r| r.code != "RTF122"));
+    }
+
+    #[test]
+    fn escaped_braces_do_not_count() {
+        assert_eq!(brace_balance("{\\rtf1 \\{ \\} }"), 0);
+    }
+
+    #[test]
+    fn unbalanced_is_warning_in_lenient_error_in_strict() {
+        let lenient = Validator::new(false).validate_rtf("{\\rtf1 {unclosed}");
+        assert!(lenient
+            .iter()
+            .any(|r| r.code == "RTF004" && r.level == ValidationLevel::Warning));
+        let strict = Validator::new(true).validate_rtf("{\\rtf1 {unclosed}");
+        assert!(strict
+            .iter()
+            .any(|r| r.code == "RTF004" && r.level == ValidationLevel::Error));
+    }
+}
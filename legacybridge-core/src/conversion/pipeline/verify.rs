@@ -0,0 +1,240 @@
+//! Output verification: checks generated Markdown still says what the
+//! document says.
+//!
+//! The generator has shipped Markdown our own [`MarkdownParser`] could
+//! not round-trip (unescaped pipes in table cells, emphasis bleeding
+//! across lines) and nothing caught it. [`verify`] re-parses the
+//! generated output and compares the plain-text projection and the
+//! heading/list/table structure against the source document; each
+//! mismatch is returned with the node path it concerns.
+
+use super::super::markdown_generator::RevisionMode;
+use super::super::markdown_parser::MarkdownParser;
+use super::super::rtf_parser::{RevisionKind, RtfDocument, RtfNode};
+
+/// Compare `markdown` against the document it was generated from,
+/// returning one message per mismatch. `mode` must be the revision mode
+/// the generator ran under, so dropped `\revised`/`\deleted` runs are not
+/// reported as lost text.
+pub(super) fn verify(document: &RtfDocument, markdown: &str, mode: RevisionMode) -> Vec<String> {
+    // Intentional non-content lines (spacing comments, anchor lines)
+    // would re-parse as paragraph text; drop them before comparing.
+    let cleaned: Vec<&str> = markdown
+        .lines()
+        .filter(|line| {
+            let trimmed = line.trim();
+            let comment = trimmed.starts_with("<!--") && trimmed.ends_with("-->");
+            let anchor = trimmed.starts_with("<a id=") && trimmed.ends_with("</a>");
+            !comment && !anchor
+        })
+        .collect();
+    // Keep everything on the re-parse: the output already went through
+    // the Unicode hygiene and normalization passes, and running either
+    // again under a different policy would report deliberately kept or
+    // differently-normalized characters as mismatches.
+    let reparsed = match MarkdownParser::new()
+        .with_unicode_hygiene(crate::security::UnicodeHygiene::keep_all())
+        .with_normalization(crate::conversion::normalization::NormalizationForm::None)
+        .parse(&cleaned.join("\n"))
+    {
+        Ok(reparsed) => reparsed,
+        Err(e) => return vec![format!("generated Markdown does not re-parse: {e}")],
+    };
+
+    let mut mismatches = Vec::new();
+    // Under Annotate both revision sides appear with CriticMarkup/HTML
+    // marks around them, which no projection of the source matches;
+    // structure is still checked.
+    if mode != RevisionMode::Annotate {
+        let expected = projection(&document.content, mode);
+        let actual = projection(&reparsed.content, mode);
+        if expected != actual {
+            mismatches.push(text_divergence(&expected, &actual));
+        }
+    }
+
+    let source = signature(&document.content);
+    let output = signature(&reparsed.content);
+    for i in 0..source.len().max(output.len()) {
+        match (source.get(i), output.get(i)) {
+            (Some((path, kind)), Some((_, reparsed_kind))) if kind != reparsed_kind => {
+                mismatches.push(format!("{path}: {kind} re-parsed as {reparsed_kind}"));
+            }
+            (Some((path, kind)), None) => {
+                mismatches.push(format!("{path}: {kind} missing from generated output"));
+            }
+            (None, Some((path, kind))) => {
+                mismatches.push(format!("{path}: {kind} not present in the source document"));
+            }
+            _ => {}
+        }
+    }
+    mismatches
+}
+
+/// Whitespace-normalized plain text with revisions resolved the way the
+/// generator resolves them.
+fn projection(nodes: &[RtfNode], mode: RevisionMode) -> String {
+    let mut out = String::new();
+    project_nodes(nodes, mode, &mut out);
+    // Directional isolates wrap RTL paragraphs in the output but are not
+    // document text.
+    out.retain(|c| !matches!(c, '\u{2066}'..='\u{2069}'));
+    out.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn project_nodes(nodes: &[RtfNode], mode: RevisionMode, out: &mut String) {
+    for node in nodes {
+        match node {
+            RtfNode::Text(text) => out.push_str(text),
+            RtfNode::Formatted { format, content } => {
+                let dropped = match (&format.revision, mode) {
+                    (Some(revision), RevisionMode::AcceptAll) => {
+                        revision.kind == RevisionKind::Deleted
+                    }
+                    (Some(revision), RevisionMode::RejectAll) => {
+                        revision.kind == RevisionKind::Inserted
+                    }
+                    _ => false,
+                };
+                if !dropped {
+                    project_nodes(content, mode, out);
+                }
+            }
+            RtfNode::Hyperlink { content, .. } => project_nodes(content, mode, out),
+            // Alt text is the image's textual content; projecting it
+            // catches a reference dropped on either side.
+            RtfNode::Image { alt, .. } => out.push_str(alt),
+            RtfNode::Paragraph { content, .. }
+            | RtfNode::Heading { content, .. }
+            | RtfNode::ListItem { content, .. } => {
+                project_nodes(content, mode, out);
+                out.push(' ');
+            }
+            RtfNode::Table(table) => {
+                for row in &table.rows {
+                    for cell in &row.cells {
+                        project_nodes(&cell.content, mode, out);
+                        out.push(' ');
+                    }
+                }
+            }
+            RtfNode::LineBreak => out.push(' '),
+            // Raw passthrough carries RTF source, not prose.
+            RtfNode::RawRtf { .. } | RtfNode::PageBreak => {}
+        }
+    }
+}
+
+/// Heading/list/table outline of a content tree, with node paths.
+fn signature(content: &[RtfNode]) -> Vec<(String, String)> {
+    content
+        .iter()
+        .enumerate()
+        .filter_map(|(i, node)| {
+            let kind = match node {
+                RtfNode::Heading { level, .. } => format!("heading (level {level})"),
+                RtfNode::ListItem { ordered: true, .. } => "ordered list item".to_string(),
+                RtfNode::ListItem { ordered: false, .. } => "unordered list item".to_string(),
+                RtfNode::Table(table) => format!("table ({} row(s))", table.rows.len()),
+                _ => return None,
+            };
+            Some((format!("content[{i}]"), kind))
+        })
+        .collect()
+}
+
+/// Describe where two projections first diverge, with short excerpts.
+fn text_divergence(expected: &str, actual: &str) -> String {
+    let at = expected
+        .chars()
+        .zip(actual.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let excerpt = |text: &str| -> String {
+        let tail: String = text.chars().skip(at).take(40).collect();
+        if tail.is_empty() {
+            "<end of text>".to_string()
+        } else {
+            format!("\"{tail}\"")
+        }
+    };
+    format!(
+        "output text diverges from the document at character {at}: expected {}, got {}",
+        excerpt(expected),
+        excerpt(actual)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::rtf_parser::{Table, TableCell, TableRow};
+    use super::*;
+
+    fn table_document(cell_text: &str) -> RtfDocument {
+        RtfDocument {
+            metadata: Default::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content: vec![RtfNode::Table(Table {
+                rows: vec![TableRow {
+                    cells: vec![
+                        TableCell {
+                            content: vec![RtfNode::Text(cell_text.to_string())],
+                            ..TableCell::default()
+                        },
+                        TableCell {
+                            content: vec![RtfNode::Text("plain".to_string())],
+                            ..TableCell::default()
+                        },
+                    ],
+                    ..TableRow::default()
+                }],
+            })],
+        }
+    }
+
+    #[test]
+    fn matching_output_verifies_cleanly() {
+        let document = table_document("a | b");
+        let markdown = "| a \\| b | plain |\n| --- | --- |\n";
+        assert_eq!(verify(&document, markdown, RevisionMode::AcceptAll), Vec::<String>::new());
+    }
+
+    #[test]
+    fn lost_text_is_reported_with_a_divergence_point() {
+        let document = table_document("a | b");
+        // An unescaped pipe splits the cell, shifting every cell after it.
+        let markdown = "| a | b | plain |\n| --- | --- | --- |\n";
+        let mismatches = verify(&document, markdown, RevisionMode::AcceptAll);
+        assert!(!mismatches.is_empty());
+        assert!(
+            mismatches[0].contains("diverges"),
+            "{mismatches:?}"
+        );
+    }
+
+    #[test]
+    fn structure_drift_is_reported_with_node_paths() {
+        let document = RtfDocument {
+            metadata: Default::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content: vec![RtfNode::Heading {
+                level: 2,
+                spacing: Default::default(),
+                content: vec![RtfNode::Text("Title".to_string())],
+            }],
+        };
+        // The heading came out as a plain paragraph.
+        let mismatches = verify(&document, "Title\n", RevisionMode::AcceptAll);
+        assert!(
+            mismatches.iter().any(|m| m.contains("content[0]")
+                && m.contains("heading (level 2)")
+                && m.contains("missing")),
+            "{mismatches:?}"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! Plain text as a first-class conversion format.
+//!
+//! Migration tickets ask for more than Markdown: "RTF -> plain text
+//! batch" feeds search indexers and diff tooling, and "plain text ->
+//! minimally formatted RTF" seeds legacy viewers from logs and notes.
+//! This module carries the format-level pieces: the
+//! [`ConversionDirection`] the batch surfaces route files on, and the
+//! splitting/joining between raw text and the document model. The free
+//! functions [`rtf_to_plain_text`](super::rtf_to_plain_text) and
+//! [`plain_text_to_rtf`](super::plain_text_to_rtf) sit next to their
+//! Markdown siblings in the crate root.
+
+use super::rtf_parser::{Direction, ParagraphSpacing, RtfDocument, RtfNode};
+use serde::{Deserialize, Serialize};
+
+/// What a batch input file is read as, decided by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputFormat {
+    Rtf,
+    PlainText,
+}
+
+impl InputFormat {
+    /// The file extension a folder scan matches, case-insensitively.
+    pub fn extension(self) -> &'static str {
+        match self {
+            InputFormat::Rtf => "rtf",
+            InputFormat::PlainText => "txt",
+        }
+    }
+}
+
+/// What a batch run writes for each converted input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    Markdown,
+    Rtf,
+    PlainText,
+}
+
+impl OutputFormat {
+    /// The extension output files are written under.
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "md",
+            OutputFormat::Rtf => "rtf",
+            OutputFormat::PlainText => "txt",
+        }
+    }
+}
+
+/// Canonical names of the directions [`ConversionDirection::from_name`]
+/// accepts, surfaced through the capabilities report so hosts can
+/// validate a requested direction up front, like
+/// [`SUPPORTED_INPUT_ENCODINGS`](super::encoding::SUPPORTED_INPUT_ENCODINGS)
+/// does for encodings.
+pub const SUPPORTED_DIRECTIONS: &[&str] =
+    &["rtf_to_markdown", "rtf_to_plain_text", "plain_text_to_rtf"];
+
+/// The conversion a batch surface performs on each file it routes: an
+/// input format picked up by extension and the output format written
+/// next to it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversionDirection {
+    #[default]
+    RtfToMarkdown,
+    RtfToPlainText,
+    PlainTextToRtf,
+}
+
+impl ConversionDirection {
+    /// Look up a direction by name, accepting the extension-based
+    /// shorthands (`rtf_to_md`, `txt_to_rtf`, ...). `None` means the
+    /// name is unsupported; see [`SUPPORTED_DIRECTIONS`].
+    pub fn from_name(name: &str) -> Option<ConversionDirection> {
+        match name.trim().to_ascii_lowercase().replace('-', "_").as_str() {
+            "rtf_to_markdown" | "rtf_to_md" => Some(ConversionDirection::RtfToMarkdown),
+            "rtf_to_plain_text" | "rtf_to_txt" => Some(ConversionDirection::RtfToPlainText),
+            "plain_text_to_rtf" | "txt_to_rtf" => Some(ConversionDirection::PlainTextToRtf),
+            _ => None,
+        }
+    }
+
+    /// The canonical name, as listed in [`SUPPORTED_DIRECTIONS`].
+    pub fn name(self) -> &'static str {
+        match self {
+            ConversionDirection::RtfToMarkdown => "rtf_to_markdown",
+            ConversionDirection::RtfToPlainText => "rtf_to_plain_text",
+            ConversionDirection::PlainTextToRtf => "plain_text_to_rtf",
+        }
+    }
+
+    pub fn input(self) -> InputFormat {
+        match self {
+            ConversionDirection::RtfToMarkdown | ConversionDirection::RtfToPlainText => {
+                InputFormat::Rtf
+            }
+            ConversionDirection::PlainTextToRtf => InputFormat::PlainText,
+        }
+    }
+
+    pub fn output(self) -> OutputFormat {
+        match self {
+            ConversionDirection::RtfToMarkdown => OutputFormat::Markdown,
+            ConversionDirection::RtfToPlainText => OutputFormat::PlainText,
+            ConversionDirection::PlainTextToRtf => OutputFormat::Rtf,
+        }
+    }
+}
+
+/// Build a document from plain text: paragraphs separated by one or
+/// more blank lines, single line breaks kept as line breaks within
+/// their paragraph, no inline formatting. Line endings are accepted in
+/// either convention; the output side re-normalizes on write anyway.
+pub fn text_to_document(text: &str) -> RtfDocument {
+    let normalized = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut document = RtfDocument::default();
+    for paragraph in normalized.split("\n\n") {
+        let lines: Vec<&str> = paragraph
+            .lines()
+            .map(str::trim_end)
+            .skip_while(|line| line.is_empty())
+            .collect();
+        if lines.is_empty() {
+            continue;
+        }
+        let mut content = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                content.push(RtfNode::LineBreak);
+            }
+            content.push(RtfNode::Text((*line).to_string()));
+        }
+        document.content.push(RtfNode::Paragraph {
+            direction: Direction::default(),
+            spacing: ParagraphSpacing::default(),
+            content,
+        });
+    }
+    document
+}
+
+/// Render a document as plain text: one blank line between
+/// paragraph-level blocks, table cells tab-separated, all inline
+/// formatting dropped. The inverse of [`text_to_document`] for the
+/// documents it produces. Distinct from
+/// [`RtfDocument::plain_text`], which is a single-`\n` concatenation
+/// for title extraction and tests, not an output format.
+pub fn document_to_text(document: &RtfDocument) -> String {
+    let mut blocks: Vec<String> = Vec::new();
+    for node in &document.content {
+        let block = match node {
+            RtfNode::Table(table) => table
+                .rows
+                .iter()
+                .map(|row| {
+                    row.cells
+                        .iter()
+                        .map(|cell| inline_text(&cell.content))
+                        .collect::<Vec<_>>()
+                        .join("\t")
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => inline_text(std::slice::from_ref(node)),
+        };
+        if !block.trim().is_empty() {
+            blocks.push(block.trim_end().to_string());
+        }
+    }
+    blocks.join("\n\n")
+}
+
+/// Concatenate the text of `nodes`, flattening block structure into the
+/// current block. Iterative (explicit work list) like
+/// [`RtfDocument::plain_text`], so adversarially deep trees cannot
+/// exhaust the native stack.
+fn inline_text(nodes: &[RtfNode]) -> String {
+    let mut out = String::new();
+    let mut work: Vec<&RtfNode> = nodes.iter().rev().collect();
+    while let Some(node) = work.pop() {
+        match node {
+            RtfNode::Text(t) => out.push_str(t),
+            RtfNode::Formatted { content, .. }
+            | RtfNode::Hyperlink { content, .. }
+            | RtfNode::Paragraph { content, .. }
+            | RtfNode::Heading { content, .. }
+            | RtfNode::ListItem { content, .. } => work.extend(content.iter().rev()),
+            RtfNode::LineBreak => out.push('\n'),
+            RtfNode::Table(_)
+            | RtfNode::Image { .. }
+            | RtfNode::RawRtf { .. }
+            | RtfNode::PageBreak => {}
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_lines_separate_paragraphs_and_single_breaks_stay() {
+        let document = text_to_document("first line\nsecond line\r\n\r\nnext paragraph\n\n\n");
+        assert_eq!(document.content.len(), 2);
+        let RtfNode::Paragraph { content, .. } = &document.content[0] else {
+            panic!("expected a paragraph");
+        };
+        assert_eq!(
+            content,
+            &[
+                RtfNode::Text("first line".to_string()),
+                RtfNode::LineBreak,
+                RtfNode::Text("second line".to_string()),
+            ]
+        );
+        // The round trip is byte-exact for what text_to_document keeps.
+        assert_eq!(
+            document_to_text(&document),
+            "first line\nsecond line\n\nnext paragraph"
+        );
+    }
+
+    #[test]
+    fn formatting_flattens_and_blocks_get_blank_line_separation() {
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(
+                "{\\rtf1 {\\b Report}\\par First \\i item\\i0 text\\par\\par}",
+            )
+            .unwrap(),
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(document_to_text(&doc), "Report\n\nFirst item text");
+    }
+
+    #[test]
+    fn directions_resolve_names_and_extensions() {
+        for name in SUPPORTED_DIRECTIONS {
+            let direction = ConversionDirection::from_name(name).unwrap();
+            assert_eq!(direction.name(), *name);
+        }
+        assert_eq!(
+            ConversionDirection::from_name("RTF-to-TXT"),
+            Some(ConversionDirection::RtfToPlainText)
+        );
+        assert_eq!(ConversionDirection::from_name("rtf_to_html"), None);
+
+        let direction = ConversionDirection::RtfToPlainText;
+        assert_eq!(direction.input().extension(), "rtf");
+        assert_eq!(direction.output().extension(), "txt");
+        let reverse = ConversionDirection::PlainTextToRtf;
+        assert_eq!(reverse.input().extension(), "txt");
+        assert_eq!(reverse.output().extension(), "rtf");
+    }
+}
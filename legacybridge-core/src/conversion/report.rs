@@ -0,0 +1,329 @@
+//! Machine-readable batch run reports for CI pipelines.
+//!
+//! A folder conversion can publish its outcome as a versioned JSON
+//! artifact or as JUnit-style XML, where each file is a test case so
+//! existing CI dashboards render pass/fail without custom tooling. The
+//! JSON schema is guarded by [`REPORT_VERSION`]; any change to the
+//! serialized shape must bump it so consumers can detect the format.
+
+use super::pipeline::{RecoveryAction, ValidationResult};
+use crate::contracts;
+use serde::{Deserialize, Serialize};
+
+/// Version of the JSON report schema; bumped on any shape change.
+/// Version 2 added the per-file `encoding` field.
+/// Version 3 added the per-file `output` field.
+/// Version 4 added the per-file `peak_memory_bytes` field.
+/// Version 5 added the run-level `published` field.
+/// Version 6 added the per-file `attempts` and `fallback` fields.
+pub const REPORT_VERSION: u32 = 6;
+
+/// Artifact format for a written batch report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    /// JUnit-style XML: one `<testcase>` per file.
+    JunitXml,
+}
+
+/// How one file fared in the batch run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    Converted,
+    Failed,
+    /// Skipped as up to date under incremental mode.
+    Skipped,
+}
+
+/// One file's outcome in a [`BatchReport`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileReport {
+    pub file: String,
+    pub status: FileStatus,
+    /// Wall-clock conversion time; 0 for skipped files.
+    pub duration_ms: u64,
+    /// Failure message; only set under [`FileStatus::Failed`].
+    pub error: Option<String>,
+    /// Validation findings from the conversion, in pipeline order.
+    pub warnings: Vec<ValidationResult>,
+    /// Structural repairs applied under auto-recovery.
+    pub recovery_actions: Vec<RecoveryAction>,
+    /// Round-trip fidelity score; reserved for the fidelity-report
+    /// feature and absent until it lands.
+    pub fidelity: Option<f64>,
+    /// Detected-or-forced character encoding the input was read under;
+    /// absent for skips and failed files.
+    pub encoding: Option<String>,
+    /// Name of the file actually written, which can differ from the input
+    /// stem (Windows sanitization, title-derived naming); only set under
+    /// [`FileStatus::Converted`].
+    pub output: Option<String>,
+    /// Peak heap bytes the conversion allocated; only set in builds with
+    /// the `memory-accounting` feature.
+    pub peak_memory_bytes: Option<u64>,
+    /// How many configurations were tried under the run's fallback
+    /// chain before this outcome; 1 for runs without a chain and for
+    /// files the first configuration decided. Defaults to 1 when
+    /// reading pre-version-6 reports.
+    #[serde(default = "attempts_default")]
+    pub attempts: u32,
+    /// Label of the fallback overlay that produced the conversion;
+    /// absent when the base configuration succeeded or the file failed
+    /// every attempt.
+    #[serde(default)]
+    pub fallback: Option<String>,
+}
+
+fn attempts_default() -> u32 {
+    1
+}
+
+impl FileReport {
+    pub fn skipped(file: impl Into<String>) -> Self {
+        FileReport {
+            file: file.into(),
+            status: FileStatus::Skipped,
+            duration_ms: 0,
+            error: None,
+            warnings: Vec::new(),
+            recovery_actions: Vec::new(),
+            fidelity: None,
+            encoding: None,
+            output: None,
+            peak_memory_bytes: None,
+            attempts: 1,
+            fallback: None,
+        }
+    }
+}
+
+/// A whole batch run, serializable as JSON or JUnit XML.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchReport {
+    /// Contract major of this payload; see [`contracts`](crate::contracts).
+    /// Reports written before contract versioning are version 1 and omit
+    /// the field, as does a report explicitly emitted at version 1.
+    #[serde(
+        default = "contracts::version_one",
+        skip_serializing_if = "contracts::is_version_one"
+    )]
+    pub v: u32,
+    /// Schema version; see [`REPORT_VERSION`].
+    pub report_version: u32,
+    pub total: usize,
+    pub converted: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Wall-clock time of the whole run.
+    pub duration_ms: u64,
+    /// Whether the outputs were actually published to the destination.
+    /// `false` only for a transactional run that missed its publish
+    /// threshold; non-transactional runs write outputs in place and are
+    /// always published. Defaults to `true` when reading pre-version-5
+    /// reports.
+    #[serde(default = "published_default")]
+    pub published: bool,
+    pub files: Vec<FileReport>,
+}
+
+fn published_default() -> bool {
+    true
+}
+
+impl BatchReport {
+    /// Assemble a report from per-file outcomes, deriving the summary
+    /// counts from their statuses.
+    pub fn new(files: Vec<FileReport>, duration: std::time::Duration) -> Self {
+        let count = |status| files.iter().filter(|f| f.status == status).count();
+        BatchReport {
+            v: contracts::CONTRACT_VERSION,
+            report_version: REPORT_VERSION,
+            total: files.len(),
+            converted: count(FileStatus::Converted),
+            failed: count(FileStatus::Failed),
+            skipped: count(FileStatus::Skipped),
+            duration_ms: duration.as_millis() as u64,
+            published: true,
+            files,
+        }
+    }
+
+    /// Record whether the outputs were published; see
+    /// [`published`](Self::published).
+    pub fn with_published(mut self, published: bool) -> Self {
+        self.published = published;
+        self
+    }
+
+    /// Emit this report under an explicit contract major, for consumers
+    /// that only parse an older shape; see
+    /// [`contracts`](crate::contracts).
+    pub fn with_contract_version(mut self, v: u32) -> Self {
+        self.v = v;
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    /// Render the run as JUnit-style XML: one `<testsuite>` with a
+    /// `<testcase>` per file, failures as `<failure>` elements and
+    /// incremental skips as `<skipped/>`, so CI dashboards built for
+    /// test results render the batch as pass/fail.
+    pub fn to_junit_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuite name=\"legacybridge\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+            self.total,
+            self.failed,
+            self.skipped,
+            self.duration_ms as f64 / 1000.0,
+        ));
+        for file in &self.files {
+            xml.push_str(&format!(
+                "  <testcase classname=\"conversion\" name=\"{}\" time=\"{:.3}\"",
+                escape_xml(&file.file),
+                file.duration_ms as f64 / 1000.0,
+            ));
+            match file.status {
+                FileStatus::Converted => xml.push_str("/>\n"),
+                FileStatus::Failed => {
+                    let message = file.error.as_deref().unwrap_or("conversion failed");
+                    xml.push_str(&format!(
+                        ">\n    <failure message=\"{}\"/>\n  </testcase>\n",
+                        escape_xml(message)
+                    ));
+                }
+                FileStatus::Skipped => xml.push_str(">\n    <skipped/>\n  </testcase>\n"),
+            }
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+
+    /// Write the report to `path` in the given format.
+    pub fn write(&self, path: &std::path::Path, format: ReportFormat) -> Result<(), String> {
+        let content = match format {
+            ReportFormat::Json => self.to_json()?,
+            ReportFormat::JunitXml => self.to_junit_xml(),
+        };
+        std::fs::write(path, content).map_err(|e| format!("cannot write {}: {e}", path.display()))
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> BatchReport {
+        BatchReport::new(
+            vec![
+                FileReport {
+                    file: "clean.rtf".to_string(),
+                    status: FileStatus::Converted,
+                    duration_ms: 12,
+                    error: None,
+                    warnings: vec![ValidationResult::warning("RTF004", "1 unclosed group(s)")],
+                    recovery_actions: Vec::new(),
+                    fidelity: None,
+                    encoding: Some("utf-8".to_string()),
+                    output: Some("clean.md".to_string()),
+                    peak_memory_bytes: None,
+                    attempts: 1,
+                    fallback: None,
+                },
+                FileReport {
+                    file: "broken <2>.rtf".to_string(),
+                    status: FileStatus::Failed,
+                    duration_ms: 3,
+                    error: Some("missing RTF header (\"{\\rtf1\" ...)".to_string()),
+                    warnings: Vec::new(),
+                    recovery_actions: Vec::new(),
+                    fidelity: None,
+                    encoding: None,
+                    output: None,
+                    peak_memory_bytes: None,
+                    attempts: 2,
+                    fallback: None,
+                },
+                FileReport::skipped("stale.rtf"),
+            ],
+            std::time::Duration::from_millis(1500),
+        )
+    }
+
+    #[test]
+    fn json_report_round_trips_through_serde() {
+        let report = sample();
+        assert_eq!(report.report_version, REPORT_VERSION);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.converted, 1);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.skipped, 1);
+        let json = report.to_json().unwrap();
+        let parsed: BatchReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    #[test]
+    fn version_one_reports_still_parse_and_re_emit_the_old_shape() {
+        // A report as written before contract versioning: no "v" field.
+        let json = r#"{
+            "report_version": 5,
+            "total": 1, "converted": 1, "failed": 0, "skipped": 0,
+            "duration_ms": 10, "published": true,
+            "files": [{
+                "file": "a.rtf", "status": "converted", "duration_ms": 10,
+                "error": null, "warnings": [], "recovery_actions": [],
+                "fidelity": null, "encoding": null, "output": null,
+                "peak_memory_bytes": null
+            }]
+        }"#;
+        let report: BatchReport = serde_json::from_str(json).unwrap();
+        assert_eq!(report.v, 1);
+        assert!(!report.to_json().unwrap().contains("\"v\""), "v1 shape keeps no v");
+
+        // A fresh report carries the current major; asking for version 1
+        // reproduces the historical shape.
+        let report = sample();
+        assert_eq!(report.v, crate::contracts::CONTRACT_VERSION);
+        assert!(report.to_json().unwrap().contains("\"v\""));
+        let downgraded = report.with_contract_version(1);
+        assert!(!downgraded.to_json().unwrap().contains("\"v\""));
+    }
+
+    #[test]
+    fn junit_xml_has_a_testcase_per_file_with_escaping() {
+        let xml = sample().to_junit_xml();
+        assert!(xml.starts_with("<?xml version=\"1.0\""), "{xml}");
+        assert!(
+            xml.contains("<testsuite name=\"legacybridge\" tests=\"3\" failures=\"1\" skipped=\"1\" time=\"1.500\">"),
+            "{xml}"
+        );
+        assert!(xml.contains("<testcase classname=\"conversion\" name=\"clean.rtf\" time=\"0.012\"/>"), "{xml}");
+        // Names and messages are XML-escaped.
+        assert!(xml.contains("name=\"broken &lt;2&gt;.rtf\""), "{xml}");
+        assert!(xml.contains("<failure message=\"missing RTF header (&quot;{\\rtf1&quot; ...)\"/>"), "{xml}");
+        assert!(xml.contains("<skipped/>"), "{xml}");
+        assert!(xml.ends_with("</testsuite>\n"), "{xml}");
+    }
+}
@@ -0,0 +1,1509 @@
+//! RTF generator.
+//!
+//! Walks an [`RtfDocument`] and emits RTF suitable for legacy readers
+//! (VB6 RichTextBox, VFP9 report viewer) as well as modern Word.
+//!
+//! Output is deterministic: identical documents produce byte-identical
+//! RTF across runs and threads. Header tables are emitted in sorted order
+//! (fonts by index then name; colors and styles in document order), so
+//! generated files are safe for content-addressed storage and diffing.
+
+use super::color;
+use super::cross_references::XrefPlan;
+use super::language;
+use super::rtf_parser::{
+    CellAlignment, CellMerge, Direction, ParagraphSpacing, RtfDocument, RtfNode, Table, TextFormat,
+};
+use super::styles::{self, CharacterStyle};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Default font size for body text, in half-points.
+const BODY_FONT_SIZE: i32 = 22;
+/// Heading sizes by level (half-points), index 0 = H1.
+const HEADING_SIZES: [i32; 6] = [48, 40, 36, 32, 28, 24];
+
+/// Maximum source line length in legacy mode; VB6-era RTF readers buffer
+/// input line by line and truncate past roughly 255 characters.
+const LEGACY_LINE_WIDTH: usize = 128;
+
+/// Which RTF specification level generated output may use.
+///
+/// [`Rtf15`](Self::Rtf15) restricts emission to control words readers of
+/// that era (VB6 RichTextBox, the VFP9 report viewer) accept, with these
+/// fallbacks, each counted in [`RtfGenerator::downgrades`]:
+///
+/// - non-ASCII text: `\uN` is unavailable, so characters become cp1252
+///   `\'xx` escapes or `?` where cp1252 has no slot (`unicode_text`);
+/// - character styles: no `\stylesheet` and no `\csN` references; runs
+///   keep their resolved toggles, so they render the same without the
+///   named style (`character_styles`);
+/// - heading outline levels: `\outlinelevelN` is dropped; headings keep
+///   their bold and font-size treatment (`outline_level`).
+///
+/// Neither profile ever emits list tables or `\highlight`: bullets are
+/// literal `\bullet\tab` runs and the Markdown model has no highlight,
+/// so there is nothing to downgrade for those.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConformanceProfile {
+    /// RTF 1.5, the last level the legacy readers fully accept.
+    Rtf15,
+    /// RTF 1.9, what modern Word reads best.
+    #[default]
+    Rtf19,
+}
+
+/// Predicted [`RtfGenerator::generate`] output size in bytes, from node
+/// counts and text lengths with per-node-type cost factors. `min` never
+/// exceeds and `max` never undershoots the actual size, so `min` can
+/// reject over-quota conversions before any generation work, and
+/// `expected` is close enough to pre-reserve output buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeEstimate {
+    pub min: usize,
+    pub expected: usize,
+    pub max: usize,
+}
+
+/// Accumulates the three bounds; `span(min, expected, max)` adds a
+/// per-node cost range, `flat` a cost that is exact for all three.
+#[derive(Default)]
+struct Estimator {
+    min: usize,
+    expected: usize,
+    max: usize,
+}
+
+impl Estimator {
+    fn flat(&mut self, bytes: usize) {
+        self.span(bytes, bytes, bytes);
+    }
+
+    fn span(&mut self, min: usize, expected: usize, max: usize) {
+        self.min += min;
+        self.expected += expected;
+        self.max += max;
+    }
+}
+
+pub struct RtfGenerator {
+    /// Font name -> font table index.
+    fonts: HashMap<String, i32>,
+    /// Character styles carried over from the document, so runs with a
+    /// recorded style name round-trip their `\csN` reference.
+    styles: Vec<CharacterStyle>,
+    /// Emit RTF 1.5 a VB6 RichTextBox accepts; see
+    /// [`with_legacy_mode`](Self::with_legacy_mode).
+    legacy_mode: bool,
+    /// Specification level the output may use; legacy mode implies
+    /// [`ConformanceProfile::Rtf15`] regardless.
+    profile: ConformanceProfile,
+    /// Emit only the document body, without the `{\rtf1 ...}` wrapper;
+    /// see [`with_fragment`](Self::with_fragment).
+    fragment: bool,
+    /// Emit `\keepn` on every heading; see
+    /// [`with_keep_headings`](Self::with_keep_headings).
+    keep_headings: bool,
+    /// Emit `\keep` on every table cell; see
+    /// [`with_keep_tables`](Self::with_keep_tables).
+    keep_tables: bool,
+    /// Convert intra-document links into bookmark + `REF` field pairs;
+    /// see [`with_cross_references`](Self::with_cross_references).
+    cross_references: bool,
+    /// Which headings get bookmarks and which link targets become `REF`
+    /// fields in the current `generate` call; empty when the mode is off.
+    xrefs: XrefPlan,
+    /// Document language override (a BCP 47 tag); see
+    /// [`with_language`](Self::with_language).
+    language: Option<String>,
+    /// LCID of the effective language for the current `generate` call;
+    /// `None` when no language is set or its tag has no known LCID.
+    lang: Option<u16>,
+    /// Constructs the last `generate` downgraded to stay in profile,
+    /// keyed by kind with occurrence counts.
+    downgrades: BTreeMap<&'static str, usize>,
+}
+
+impl RtfGenerator {
+    pub fn new() -> Self {
+        let mut fonts = HashMap::new();
+        fonts.insert("Calibri".to_string(), 0);
+        RtfGenerator {
+            fonts,
+            styles: Vec::new(),
+            legacy_mode: false,
+            profile: ConformanceProfile::default(),
+            fragment: false,
+            keep_headings: false,
+            keep_tables: false,
+            cross_references: false,
+            xrefs: XrefPlan::default(),
+            language: None,
+            lang: None,
+            downgrades: BTreeMap::new(),
+        }
+    }
+
+    /// Emit a headerless fragment - the body without the `{\rtf1 ...}`
+    /// wrapper, font table or stylesheet - for writing back into
+    /// database memo fields that store fragments (default: off). Such
+    /// output only renders inside a host that supplies the header, which
+    /// is exactly what those fields' readers do.
+    pub fn with_fragment(mut self, fragment: bool) -> Self {
+        self.fragment = fragment;
+        self
+    }
+
+    /// Restrict output to what VB6 RichTextBox and the VFP9 report viewer
+    /// render correctly (default: off). Legacy mode implies the
+    /// [`ConformanceProfile::Rtf15`] control-word restrictions and
+    /// additionally folds source lines at [`LEGACY_LINE_WIDTH`] columns,
+    /// since line-buffered readers truncate very long lines.
+    pub fn with_legacy_mode(mut self, legacy_mode: bool) -> Self {
+        self.legacy_mode = legacy_mode;
+        self
+    }
+
+    /// Mark every heading keep-with-next (`\keepn`) so Word never leaves
+    /// one stranded at the bottom of a page (default: off). Headings
+    /// whose spacing already carries the flag - from a template or a
+    /// round-tripped document - emit it regardless. `\keepn` dates back
+    /// to RTF 1.0, so both conformance profiles may emit it.
+    pub fn with_keep_headings(mut self, keep: bool) -> Self {
+        self.keep_headings = keep;
+        self
+    }
+
+    /// Mark table cells keep-lines-together (`\keep`) so Word avoids
+    /// splitting a row's contents across a page boundary (default: off).
+    pub fn with_keep_tables(mut self, keep: bool) -> Self {
+        self.keep_tables = keep;
+        self
+    }
+
+    /// Convert intra-document links whose target matches a heading slug
+    /// into bookmark + `REF` field pairs (default: off): the heading
+    /// gains `{\*\bkmkstart slug}{\*\bkmkend slug}` and the link becomes
+    /// a `REF` field, so Word navigates the reference the way a Markdown
+    /// reader follows the anchor. Links into no heading keep the usual
+    /// `HYPERLINK` emission. Slugs use the default (non-transliterated)
+    /// flavor, matching the Markdown generator's default.
+    pub fn with_cross_references(mut self, convert: bool) -> Self {
+        self.cross_references = convert;
+        self
+    }
+
+    /// Set the document language as a BCP 47 tag (e.g. `es-ES`), emitted
+    /// as `\deflang` in the header plus `\lang` on each paragraph so
+    /// Word's spell checker picks the right dictionary (default: none).
+    /// Overrides
+    /// [`DocumentMetadata::language`](super::rtf_parser::DocumentMetadata::language)
+    /// when both are set. A tag
+    /// with no known LCID emits no language words and is recorded as an
+    /// `unknown_language` downgrade.
+    pub fn with_language(mut self, tag: impl Into<String>) -> Self {
+        self.language = Some(tag.into());
+        self
+    }
+
+    /// Select the [`ConformanceProfile`] for generated output (default:
+    /// [`Rtf19`](ConformanceProfile::Rtf19)). Unlike legacy mode this
+    /// only restricts control words; lines are not folded.
+    pub fn with_profile(mut self, profile: ConformanceProfile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// The profile actually applied: legacy mode implies RTF 1.5.
+    fn effective_profile(&self) -> ConformanceProfile {
+        if self.legacy_mode {
+            ConformanceProfile::Rtf15
+        } else {
+            self.profile
+        }
+    }
+
+    /// What the last [`generate`](Self::generate) downgraded to stay
+    /// within the profile, keyed by construct (`unicode_text`,
+    /// `character_styles`, `outline_level`) with occurrence counts.
+    /// Empty under [`ConformanceProfile::Rtf19`]; callers surface these
+    /// as warnings.
+    pub fn downgrades(&self) -> &BTreeMap<&'static str, usize> {
+        &self.downgrades
+    }
+
+    fn downgrade(&mut self, kind: &'static str, count: usize) {
+        if count > 0 {
+            *self.downgrades.entry(kind).or_insert(0) += count;
+        }
+    }
+
+    /// Predict the output size of [`generate`](Self::generate) for this
+    /// document without generating, for progress reporting and quota
+    /// pre-checks. Costs per node type were calibrated against actual
+    /// output on the test fixtures (see the calibration test).
+    pub fn estimate(&self, document: &RtfDocument) -> SizeEstimate {
+        let mut est = Estimator::default();
+        // Prologue, table braces, trailing "\r\n" and "}".
+        est.flat(30);
+        // Font table; the built-in Calibri entry is always present.
+        est.flat("{\\f0 Calibri;}".len());
+        for entry in &document.fonts {
+            if entry.name != "Calibri" {
+                est.flat(8 + entry.name.len());
+            }
+        }
+        if !document.colors.is_empty() {
+            est.flat(11 + 20 * document.colors.len());
+        }
+        if self.effective_profile() == ConformanceProfile::Rtf19 {
+            if !document.styles.is_empty() {
+                est.flat(13);
+            }
+            for style in &document.styles {
+                est.span(
+                    8 + style.name.len(),
+                    12 + style.name.len(),
+                    40 + style.name.len(),
+                );
+            }
+        }
+        if let Some(title) = &document.metadata.title {
+            est.flat(17);
+            self.estimate_text(title, &mut est);
+        }
+        // Walk the tree with an explicit work list - like the generator
+        // itself, depth must not be bounded by the native stack.
+        let mut work: Vec<&RtfNode> = document.content.iter().rev().collect();
+        while let Some(node) = work.pop() {
+            match node {
+                RtfNode::Text(text) => self.estimate_text(text, &mut est),
+                RtfNode::Formatted { content, .. } => {
+                    // Open plus close toggles; complex runs (color, font,
+                    // style, size) cost more than a plain bold flip.
+                    est.span(0, 10, 48);
+                    work.extend(content.iter().rev());
+                }
+                RtfNode::Hyperlink { url, title, content } => {
+                    // The field wrapper around the instruction and result.
+                    est.flat(45);
+                    self.estimate_text(url, &mut est);
+                    if let Some(title) = title {
+                        // ` \\o ""` around the tooltip.
+                        est.flat(7);
+                        self.estimate_text(title, &mut est);
+                    }
+                    work.extend(content.iter().rev());
+                }
+                RtfNode::Image { src, alt, title } => {
+                    // The \lbimage destination wrapper and its subgroups.
+                    est.flat(40);
+                    self.estimate_text(src, &mut est);
+                    self.estimate_text(alt, &mut est);
+                    if let Some(title) = title {
+                        est.flat(16);
+                        self.estimate_text(title, &mut est);
+                    }
+                }
+                RtfNode::Heading { content, .. } => {
+                    // RTF 1.5 headings lack the 14-byte \outlinelevelN.
+                    // Max leaves room for \pagebb\keepn flags.
+                    if self.effective_profile() == ConformanceProfile::Rtf15 {
+                        est.span(31, 39, 79);
+                    } else {
+                        est.span(45, 53, 93);
+                    }
+                    work.extend(content.iter().rev());
+                }
+                RtfNode::Paragraph { content, .. } => {
+                    est.span(17, 20, 77);
+                    work.extend(content.iter().rev());
+                }
+                RtfNode::ListItem { content, .. } => {
+                    est.span(30, 38, 42);
+                    work.extend(content.iter().rev());
+                }
+                RtfNode::Table(table) => {
+                    est.flat(7);
+                    for row in &table.rows {
+                        est.flat(13);
+                        // Max leaves room for \keep under keep_tables.
+                        est.span(
+                            20 * row.cells.len(),
+                            28 * row.cells.len(),
+                            49 * row.cells.len(),
+                        );
+                        for cell in &row.cells {
+                            work.extend(cell.content.iter().rev());
+                        }
+                    }
+                }
+                RtfNode::RawRtf { content } => {
+                    // The two passthrough region markers plus the content,
+                    // which passes through without re-encoding.
+                    est.flat(28 + content.len());
+                }
+                RtfNode::PageBreak => est.flat(8),
+                RtfNode::LineBreak => est.flat(6),
+            }
+        }
+        if self.legacy_mode {
+            // Folding inserts "\r\n" roughly every LEGACY_LINE_WIDTH
+            // columns.
+            est.expected += est.expected / LEGACY_LINE_WIDTH * 2;
+            est.max += est.max / LEGACY_LINE_WIDTH * 2 + 4;
+        }
+        SizeEstimate {
+            min: est.min,
+            expected: est.expected,
+            max: est.max,
+        }
+    }
+
+    /// Escaped-size bounds for a text run: ASCII is mostly 1:1, RTF
+    /// specials double, and non-ASCII expands to `\uN?` (or `\'xx`/`?` in
+    /// legacy mode, which can also shrink multi-byte input).
+    fn estimate_text(&self, text: &str, est: &mut Estimator) {
+        for c in text.chars() {
+            match c {
+                '\\' | '{' | '}' => est.span(2, 2, 2),
+                c if c.is_ascii() => est.flat(1),
+                _ if self.effective_profile() == ConformanceProfile::Rtf15 => est.span(1, 4, 4),
+                _ => est.span(1, 7, 9),
+            }
+        }
+    }
+
+    pub fn generate(&mut self, document: &RtfDocument) -> Result<String, String> {
+        self.downgrades.clear();
+        self.lang = match self
+            .language
+            .as_deref()
+            .or(document.metadata.language.as_deref())
+        {
+            Some(tag) => {
+                let lcid = language::lcid_for_tag(tag);
+                if lcid.is_none() {
+                    self.downgrade("unknown_language", 1);
+                }
+                lcid
+            }
+            None => None,
+        };
+        self.xrefs = if self.cross_references {
+            XrefPlan::build(document)
+        } else {
+            XrefPlan::default()
+        };
+        // Carry over the document's font table (already substituted by the
+        // parser's FontMap) so \fN references keep meaning.
+        for entry in &document.fonts {
+            self.fonts.insert(entry.name.clone(), entry.index);
+        }
+        // RTF 1.5 readers mis-render \cs references; drop the style table
+        // and rely on the resolved toggles each run carries anyway.
+        self.styles = if self.effective_profile() == ConformanceProfile::Rtf15 {
+            self.downgrade("character_styles", document.styles.len());
+            Vec::new()
+        } else {
+            document.styles.clone()
+        };
+        // The estimate covers header and body; as a body-buffer capacity
+        // it over-reserves slightly, which beats reallocation churn.
+        let mut body = String::with_capacity(self.estimate(document).expected);
+        for node in &document.content {
+            self.generate_block(node, &mut body)?;
+        }
+
+        if self.fragment {
+            if self.legacy_mode {
+                body = fold_legacy_lines(&body);
+            }
+            return Ok(body);
+        }
+
+        let mut out = String::with_capacity(body.len() + 128);
+        out.push_str("{\\rtf1\\ansi\\deff0");
+        if let Some(lcid) = self.lang {
+            out.push_str(&format!("\\deflang{lcid}"));
+        }
+        out.push_str("{\\fonttbl");
+        // Sorted emission: HashMap iteration order would make identical
+        // inputs produce byte-different files between runs.
+        let mut fonts: Vec<(&String, &i32)> = self.fonts.iter().collect();
+        fonts.sort_by(|a, b| a.1.cmp(b.1).then_with(|| a.0.cmp(b.0)));
+        for (name, index) in fonts {
+            out.push_str(&format!("{{\\f{index} {name};}}"));
+        }
+        out.push('}');
+        if !document.colors.is_empty() {
+            out.push_str(&color::emit_colortbl(&document.colors));
+        }
+        if !self.styles.is_empty() {
+            out.push_str("{\\stylesheet");
+            for style in &self.styles {
+                out.push_str(&format!(
+                    "{{\\*\\cs{}{} {};}}",
+                    style.index,
+                    styles::format_words(&style.format),
+                    style.name
+                ));
+            }
+            out.push('}');
+        }
+        if let Some(title) = &document.metadata.title {
+            out.push_str(&format!("{{\\info{{\\title {}}}}}", self.escape(title)));
+        }
+        out.push_str("\r\n");
+        out.push_str(&body);
+        out.push('}');
+        if self.legacy_mode {
+            out = fold_legacy_lines(&out);
+        }
+        Ok(out)
+    }
+
+    /// The `\langN` word paragraph prefixes carry, or nothing when no
+    /// language is in effect. `\deflang` alone would do for Word, but
+    /// legacy readers only honor the paragraph-level word.
+    fn lang_word(&self) -> String {
+        self.lang
+            .map(|lcid| format!("\\lang{lcid}"))
+            .unwrap_or_default()
+    }
+
+    fn escape(&mut self, text: &str) -> String {
+        if self.effective_profile() == ConformanceProfile::Rtf15 {
+            self.downgrade("unicode_text", text.chars().filter(|c| !c.is_ascii()).count());
+            escape_rtf_text_legacy(text)
+        } else {
+            escape_rtf_text(text)
+        }
+    }
+
+    fn generate_block(&mut self, node: &RtfNode, out: &mut String) -> Result<(), String> {
+        match node {
+            RtfNode::Heading {
+                level,
+                spacing,
+                content,
+            } => {
+                let size = HEADING_SIZES[(*level as usize).clamp(1, 6) - 1];
+                // RTF 1.5 predates \outlinelevel; bold and font size
+                // still convey the heading.
+                let outline = if self.effective_profile() == ConformanceProfile::Rtf15 {
+                    self.downgrade("outline_level", 1);
+                    String::new()
+                } else {
+                    format!("\\outlinelevel{}", (*level as i32).clamp(1, 6) - 1)
+                };
+                // Explicit spacing wins; otherwise the house heading style.
+                let space = if spacing.has_spacing() {
+                    spacing_words(spacing)
+                } else {
+                    "\\sb240\\sa120".to_string()
+                };
+                let flags = break_keep_words(spacing, self.keep_headings);
+                let lang = self.lang_word();
+                out.push_str(&format!("\\pard{flags}{space}{outline}\\b\\fs{size}{lang} "));
+                // An empty bookmark range right before the heading text;
+                // `REF` fields elsewhere in the document target it.
+                if let Some(name) = self.xrefs.next_mark() {
+                    out.push_str(&format!(
+                        "{{\\*\\bkmkstart {name}}}{{\\*\\bkmkend {name}}}"
+                    ));
+                }
+                self.generate_inline(content, out)?;
+                out.push_str("\\b0\\fs22\\par\r\n");
+            }
+            RtfNode::Paragraph {
+                direction,
+                spacing,
+                content,
+            } => {
+                let dir = match direction {
+                    Direction::RightToLeft => "\\rtlpar",
+                    Direction::LeftToRight => "",
+                };
+                let space = spacing_words(spacing);
+                let flags = break_keep_words(spacing, false);
+                let lang = self.lang_word();
+                out.push_str(&format!(
+                    "\\pard{dir}{flags}{space}\\fs{BODY_FONT_SIZE}{lang} "
+                ));
+                self.generate_inline_directed(content, out, *direction)?;
+                out.push_str("\\par\r\n");
+            }
+            RtfNode::ListItem {
+                ordered, content, ..
+            } => {
+                let bullet = if *ordered { "" } else { "\\bullet\\tab " };
+                let lang = self.lang_word();
+                out.push_str(&format!(
+                    "\\pard\\fi-360\\li720\\fs{BODY_FONT_SIZE}{lang} {bullet}"
+                ));
+                self.generate_inline(content, out)?;
+                out.push_str("\\par\r\n");
+            }
+            RtfNode::Table(table) => self.generate_table(table, out)?,
+            // Region markers, not a wrapping destination: a `\*` group
+            // would tell readers to skip the content, and the whole point
+            // is that they interpret it.
+            RtfNode::RawRtf { content } => {
+                out.push_str("{\\*\\lbrawstart}");
+                out.push_str(content);
+                out.push_str("{\\*\\lbrawend}\r\n");
+            }
+            RtfNode::PageBreak => out.push_str("\\page\r\n"),
+            RtfNode::LineBreak => out.push_str("\\line "),
+            other => {
+                let lang = self.lang_word();
+                out.push_str(&format!("\\pard\\fs{BODY_FONT_SIZE}{lang} "));
+                self.generate_inline(std::slice::from_ref(other), out)?;
+                out.push_str("\\par\r\n");
+            }
+        }
+        Ok(())
+    }
+
+    fn generate_table(&mut self, table: &Table, out: &mut String) -> Result<(), String> {
+        const CELL_WIDTH: i32 = 2400;
+        // Alignment persists across \cell boundaries when re-parsed, so
+        // emit a toggle only where it changes - as format_toggles does.
+        let mut alignment = CellAlignment::Left;
+        for row in &table.rows {
+            out.push_str("\\trowd");
+            // Recorded boundaries win; cells past them (or rows without
+            // any) get uniform default widths. \cellx positions must stay
+            // cumulative and monotonic whatever the model carries.
+            let mut last = 0;
+            for (i, cell) in row.cells.iter().enumerate() {
+                match cell.h_merge {
+                    CellMerge::First => out.push_str("\\clmgf"),
+                    CellMerge::Merged => out.push_str("\\clmrg"),
+                    CellMerge::None => {}
+                }
+                match cell.v_merge {
+                    CellMerge::First => out.push_str("\\clvmgf"),
+                    CellMerge::Merged => out.push_str("\\clvmrg"),
+                    CellMerge::None => {}
+                }
+                let bound = match row.col_bounds.get(i) {
+                    Some(&b) if b > last => b,
+                    _ => last + CELL_WIDTH,
+                };
+                out.push_str(&format!("\\cellx{bound}"));
+                last = bound;
+            }
+            out.push(' ');
+            for cell in &row.cells {
+                out.push_str(if self.keep_tables {
+                    "\\intbl\\keep "
+                } else {
+                    "\\intbl "
+                });
+                if cell.alignment != alignment {
+                    out.push_str(match cell.alignment {
+                        CellAlignment::Left => "\\ql ",
+                        CellAlignment::Center => "\\qc ",
+                        CellAlignment::Right => "\\qr ",
+                    });
+                    alignment = cell.alignment;
+                }
+                self.generate_inline(&cell.content, out)?;
+                out.push_str("\\cell ");
+            }
+            out.push_str("\\row\r\n");
+        }
+        out.push_str("\\pard\r\n");
+        Ok(())
+    }
+
+    fn generate_inline(&mut self, nodes: &[RtfNode], out: &mut String) -> Result<(), String> {
+        self.generate_inline_directed(nodes, out, Direction::LeftToRight)
+    }
+
+    /// Emit inline content using an explicit frame stack (node iterator plus
+    /// the toggles to close when the frame completes), so formatting nesting
+    /// depth is limited only by memory - not by the native stack.
+    fn generate_inline_directed(
+        &mut self,
+        nodes: &[RtfNode],
+        out: &mut String,
+        base: Direction,
+    ) -> Result<(), String> {
+        let mut stack: Vec<(std::slice::Iter<'_, RtfNode>, String)> =
+            vec![(nodes.iter(), String::new())];
+        // Set while `out` ends with a toggle run's delimiter space, which
+        // the parser's word-join repair would read as a lost word boundary
+        // if body text followed with a word character on each side; the
+        // left side is tracked in `last_text_char`.
+        let mut open_delimiter = false;
+        let mut last_text_char: Option<char> = None;
+        while let Some((iter, _)) = stack.last_mut() {
+            let Some(node) = iter.next() else {
+                let (_, close) = stack.pop().expect("generate stack never empties");
+                out.push_str(&close);
+                if !close.is_empty() {
+                    open_delimiter = true;
+                }
+                continue;
+            };
+            match node {
+                RtfNode::Text(text) => {
+                    // Terminate the toggle with `{}` instead of its
+                    // delimiter space so deliberate adjacency ("bold" hard
+                    // against "text") survives a round trip.
+                    if open_delimiter
+                        && out.ends_with(' ')
+                        && last_text_char.is_some_and(char::is_alphanumeric)
+                        && text.chars().next().is_some_and(char::is_alphanumeric)
+                    {
+                        out.pop();
+                        out.push_str("{}");
+                    }
+                    open_delimiter = false;
+                    last_text_char = text.chars().next_back().or(last_text_char);
+                    out.push_str(&self.escape(text));
+                }
+                RtfNode::Formatted { format, content } => {
+                    let (open, close) = format_toggles(format, base, &self.styles);
+                    out.push_str(&open);
+                    if !open.is_empty() {
+                        open_delimiter = true;
+                    }
+                    stack.push((content.iter(), close));
+                }
+                RtfNode::Hyperlink { url, title, content } => {
+                    // `\field` and HYPERLINK date back to Word 97, so both
+                    // conformance profiles may emit them. The instruction's
+                    // `\o` switch carries the title as the tooltip.
+                    let target = url
+                        .strip_prefix('#')
+                        .filter(|name| self.xrefs.is_target(name));
+                    if let Some(name) = target {
+                        // A bookmark was planted on the matching heading;
+                        // `\h` makes the field navigate like a hyperlink.
+                        out.push_str(&format!(
+                            "{{\\field{{\\*\\fldinst{{REF {} \\\\h",
+                            self.escape(name)
+                        ));
+                    } else {
+                        out.push_str(&format!(
+                            "{{\\field{{\\*\\fldinst{{HYPERLINK \"{}\"",
+                            self.escape(url)
+                        ));
+                        if let Some(title) = title {
+                            out.push_str(&format!(" \\\\o \"{}\"", self.escape(title)));
+                        }
+                    }
+                    out.push_str("}}{\\fldrslt ");
+                    open_delimiter = false;
+                    last_text_char = None;
+                    stack.push((content.iter(), "}}".to_string()));
+                }
+                RtfNode::Image { src, alt, title } => {
+                    // Our own ignorable destination: readers that predate
+                    // it skip the whole group, ours re-reads the metadata
+                    // from the nested subgroups. Subgroups instead of
+                    // quoted arguments mean alt text with quotes needs no
+                    // escaping beyond the RTF specials.
+                    out.push_str(&format!(
+                        "{{\\*\\lbimage{{\\*\\lbimgsrc {}}}{{\\*\\lbimgalt {}}}",
+                        self.escape(src),
+                        self.escape(alt)
+                    ));
+                    if let Some(title) = title {
+                        out.push_str(&format!("{{\\*\\lbimgtitle {}}}", self.escape(title)));
+                    }
+                    out.push('}');
+                    open_delimiter = false;
+                    last_text_char = None;
+                }
+                RtfNode::RawRtf { content } => {
+                    out.push_str("{\\*\\lbrawstart}");
+                    out.push_str(content);
+                    out.push_str("{\\*\\lbrawend}");
+                    open_delimiter = false;
+                    last_text_char = None;
+                }
+                RtfNode::LineBreak => {
+                    out.push_str("\\line ");
+                    open_delimiter = false;
+                    last_text_char = None;
+                }
+                RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => {
+                    stack.push((content.iter(), String::new()));
+                }
+                RtfNode::Table(_) | RtfNode::PageBreak => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for RtfGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Emit the spacing control words for a block: `\sbN\saN\slN\slmult1`.
+/// Empty when the node carries no explicit spacing.
+fn spacing_words(spacing: &ParagraphSpacing) -> String {
+    let mut out = String::new();
+    if let Some(twips) = spacing.space_before {
+        out.push_str(&format!("\\sb{twips}"));
+    }
+    if let Some(twips) = spacing.space_after {
+        out.push_str(&format!("\\sa{twips}"));
+    }
+    if let Some(value) = spacing.line_spacing {
+        let multiple = spacing.line_spacing_multiple as i32;
+        out.push_str(&format!("\\sl{value}\\slmult{multiple}"));
+    }
+    out
+}
+
+/// Emit the page-break and keep flags for a block: `\pagebb` when the
+/// block starts a new page, `\keepn` when it stays with the next block
+/// (from the node's own spacing or forced by the generator's
+/// configuration). Both predate RTF 1.5, so no profile downgrades them.
+fn break_keep_words(spacing: &ParagraphSpacing, force_keep_with_next: bool) -> String {
+    let mut out = String::new();
+    if spacing.page_break_before {
+        out.push_str("\\pagebb");
+    }
+    if spacing.keep_with_next || force_keep_with_next {
+        out.push_str("\\keepn");
+    }
+    out
+}
+
+fn format_toggles(
+    format: &TextFormat,
+    base: Direction,
+    styles: &[CharacterStyle],
+) -> (String, String) {
+    let mut open = String::new();
+    let mut close = String::new();
+    // A recorded style name becomes a \csN reference when the style table
+    // still has it; the resolved toggles below are emitted regardless, so
+    // readers that ignore \cs render the run the same way.
+    if let Some(name) = &format.style_name {
+        if let Some(style) = styles.iter().find(|s| s.name == *name) {
+            open.push_str(&format!("\\cs{} ", style.index));
+            close.push_str("\\cs0 ");
+        }
+    }
+    // Only emit direction toggles when the run differs from the paragraph
+    // base, so plain LTR documents stay free of \ltrch noise.
+    if let Some(direction) = format.direction {
+        if direction != base {
+            let (enter, leave) = match direction {
+                Direction::RightToLeft => ("\\rtlch ", "\\ltrch "),
+                Direction::LeftToRight => ("\\ltrch ", "\\rtlch "),
+            };
+            open.push_str(enter);
+            close.insert_str(0, leave);
+        }
+    }
+    if format.bold {
+        open.push_str("\\b ");
+        close.insert_str(0, "\\b0 ");
+    }
+    if format.italic {
+        open.push_str("\\i ");
+        close.insert_str(0, "\\i0 ");
+    }
+    if format.underline {
+        open.push_str("\\ul ");
+        close.insert_str(0, "\\ulnone ");
+    }
+    if format.strikethrough {
+        open.push_str("\\strike ");
+        close.insert_str(0, "\\strike0 ");
+    }
+    if let Some(size) = format.font_size {
+        open.push_str(&format!("\\fs{size} "));
+        close.insert_str(0, &format!("\\fs{BODY_FONT_SIZE} "));
+    }
+    if let Some(index) = format.color_index {
+        open.push_str(&format!("\\cf{index} "));
+        close.insert_str(0, "\\cf0 ");
+    }
+    (open, close)
+}
+
+/// Escape text for inclusion in an RTF body.
+pub fn escape_rtf_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '{' => out.push_str("\\{"),
+            '}' => out.push_str("\\}"),
+            '\n' => out.push_str("\\line "),
+            c if (c as u32) < 128 => out.push(c),
+            c => {
+                // Non-ASCII: emit as signed 16-bit \uN with '?' fallback.
+                let code = c as u32;
+                if code <= 0xffff {
+                    let signed = code as i32;
+                    let value = if signed > 32767 { signed - 65536 } else { signed };
+                    out.push_str(&format!("\\u{value}?"));
+                } else {
+                    // Astral plane: surrogate pair, as Word does.
+                    let v = code - 0x10000;
+                    let high = 0xd800 + (v >> 10);
+                    let low = 0xdc00 + (v & 0x3ff);
+                    out.push_str(&format!(
+                        "\\u{}?\\u{}?",
+                        high as i32 - 65536,
+                        low as i32 - 65536
+                    ));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Escape text for an RTF 1.5 body: cp1252 `\'xx` escapes instead of
+/// `\uN`, with `?` for characters cp1252 cannot express. The inverse of
+/// the lexer's cp1252 decoding, so legacy output re-parses losslessly for
+/// the cp1252 repertoire.
+pub fn escape_rtf_text_legacy(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '{' => out.push_str("\\{"),
+            '}' => out.push_str("\\}"),
+            '\n' => out.push_str("\\line "),
+            c if (c as u32) < 128 => out.push(c),
+            c => match cp1252_byte(c) {
+                Some(byte) => out.push_str(&format!("\\'{byte:02x}")),
+                None => out.push('?'),
+            },
+        }
+    }
+    out
+}
+
+/// Map a Unicode character to its cp1252 byte, when it has one.
+fn cp1252_byte(c: char) -> Option<u8> {
+    // 0x80..=0x9f differ from Latin-1; everything else maps directly.
+    const HIGH: [(char, u8); 27] = [
+        ('\u{20ac}', 0x80),
+        ('\u{201a}', 0x82),
+        ('\u{192}', 0x83),
+        ('\u{201e}', 0x84),
+        ('\u{2026}', 0x85),
+        ('\u{2020}', 0x86),
+        ('\u{2021}', 0x87),
+        ('\u{2c6}', 0x88),
+        ('\u{2030}', 0x89),
+        ('\u{160}', 0x8a),
+        ('\u{2039}', 0x8b),
+        ('\u{152}', 0x8c),
+        ('\u{17d}', 0x8e),
+        ('\u{2018}', 0x91),
+        ('\u{2019}', 0x92),
+        ('\u{201c}', 0x93),
+        ('\u{201d}', 0x94),
+        ('\u{2022}', 0x95),
+        ('\u{2013}', 0x96),
+        ('\u{2014}', 0x97),
+        ('\u{2dc}', 0x98),
+        ('\u{2122}', 0x99),
+        ('\u{161}', 0x9a),
+        ('\u{203a}', 0x9b),
+        ('\u{153}', 0x9c),
+        ('\u{17e}', 0x9e),
+        ('\u{178}', 0x9f),
+    ];
+    match c as u32 {
+        0xa0..=0xff => Some(c as u8),
+        _ => HIGH.iter().find(|(ch, _)| *ch == c).map(|(_, b)| *b),
+    }
+}
+
+/// Fold generated RTF at [`LEGACY_LINE_WIDTH`] columns. Raw CRLF is
+/// insignificant to RTF readers, so a break may go before a control word
+/// or between text characters - never inside a control word or before its
+/// delimiter space, where the newline would end the word early and turn
+/// the delimiter into body text.
+fn fold_legacy_lines(rtf: &str) -> String {
+    let bytes = rtf.as_bytes();
+    let mut out = String::with_capacity(rtf.len() + rtf.len() / LEGACY_LINE_WIDTH * 2);
+    let mut col = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        // One indivisible unit: a control word with parameter and
+        // delimiter, an escape, or a single character.
+        let start = i;
+        if bytes[i] == b'\\' && i + 1 < bytes.len() {
+            i += 1;
+            if bytes[i].is_ascii_alphabetic() {
+                while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+                if i < bytes.len() && (bytes[i] == b'-' || bytes[i].is_ascii_digit()) {
+                    i += 1;
+                    while i < bytes.len() && bytes[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+                if i < bytes.len() && bytes[i] == b' ' {
+                    i += 1;
+                }
+            } else if bytes[i] == b'\'' {
+                i = (i + 3).min(bytes.len());
+            } else {
+                i += 1;
+            }
+        } else {
+            // Legacy escaping leaves only ASCII, but stay boundary-safe.
+            i += utf8_len(bytes[i]);
+        }
+        let unit = &rtf[start..i.min(rtf.len())];
+        if unit == "\r" || unit == "\n" {
+            out.push_str(unit);
+            col = 0;
+            continue;
+        }
+        if col > 0 && col + unit.len() > LEGACY_LINE_WIDTH {
+            out.push_str("\r\n");
+            col = 0;
+        }
+        out.push_str(unit);
+        col += unit.len();
+    }
+    out
+}
+
+fn utf8_len(first: u8) -> usize {
+    match first {
+        0x00..=0x7f => 1,
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        _ => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::markdown_parser::MarkdownParser;
+
+    fn convert(md: &str) -> String {
+        let doc = MarkdownParser::new().parse(md).unwrap();
+        RtfGenerator::new().generate(&doc).unwrap()
+    }
+
+    #[test]
+    fn generates_rtf_header() {
+        let rtf = convert("Hello");
+        assert!(rtf.starts_with("{\\rtf1\\ansi\\deff0"));
+        assert!(rtf.ends_with('}'));
+        assert!(rtf.contains("\\fonttbl"));
+    }
+
+    #[test]
+    fn generates_bold_text() {
+        let rtf = convert("**bold** word");
+        assert!(rtf.contains("\\b bold\\b0"), "got: {rtf}");
+    }
+
+    #[test]
+    fn generates_heading_formatting() {
+        let rtf = convert("# Title");
+        assert!(rtf.contains("\\fs48 Title"), "got: {rtf}");
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape_rtf_text("a{b}c\\d"), "a\\{b\\}c\\\\d");
+    }
+
+    #[test]
+    fn escapes_unicode() {
+        assert_eq!(escape_rtf_text("α"), "\\u945?");
+    }
+
+    #[test]
+    fn generated_rtf_never_carries_raw_non_ascii() {
+        // Generated documents declare \ansi, so raw multibyte text in
+        // the body would be the mis-encoded pattern the tolerant reader
+        // exists to repair; both modes must always escape instead.
+        let doc = MarkdownParser::new()
+            .parse("Привет, мир! Café — “quotes”")
+            .unwrap();
+        for legacy in [false, true] {
+            let rtf = RtfGenerator::new()
+                .with_legacy_mode(legacy)
+                .generate(&doc)
+                .unwrap();
+            assert!(
+                rtf.is_ascii(),
+                "(legacy: {legacy}) raw non-ASCII in output: {rtf}"
+            );
+        }
+    }
+
+    #[test]
+    fn emits_rtlpar_and_run_direction_toggles() {
+        let doc = MarkdownParser::new()
+            .parse("---\ndirection: rtl\n---\n\u{645}\u{631}\u{62d}\u{628}\u{627}")
+            .unwrap();
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        assert!(rtf.contains("\\rtlpar"), "got: {rtf}");
+
+        // A Latin run inside an RTL paragraph round-trips its \ltrch toggle.
+        let src = "{\\rtf1 \\rtlpar \\u1605?\\u1585? {\\ltrch ABC-123} \\u1576?\\par}";
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(src).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        assert!(rtf.contains("\\ltrch ABC-123"), "got: {rtf}");
+    }
+
+    #[test]
+    fn legacy_mode_emits_rtf_15_for_old_readers() {
+        // é is in cp1252, α is not; the stylesheet must vanish in legacy
+        // output along with its \cs reference.
+        let src = "{\\rtf1{\\stylesheet{\\*\\cs16\\f1 Code;}}\
+                   caf\\u233? \\u945? {\\cs16 x}\\par}";
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(src).unwrap(),
+        )
+        .with_style_names(true)
+        .parse()
+        .unwrap();
+
+        let modern = RtfGenerator::new().generate(&doc).unwrap();
+        assert!(modern.contains("\\u233?"), "got: {modern}");
+        assert!(modern.contains("\\stylesheet"), "got: {modern}");
+
+        let legacy = RtfGenerator::new()
+            .with_legacy_mode(true)
+            .generate(&doc)
+            .unwrap();
+        assert!(legacy.contains("\\'e9"), "got: {legacy}");
+        assert!(!legacy.contains("\\u233"), "got: {legacy}");
+        assert!(legacy.contains("? "), "alpha degrades to '?': {legacy}");
+        assert!(!legacy.contains("\\stylesheet"), "got: {legacy}");
+        assert!(!legacy.contains("\\cs16"), "got: {legacy}");
+    }
+
+    /// Control words RTF 1.5 readers accept, out of what this generator
+    /// can emit; `'` stands for a `\'xx` escape.
+    const RTF15_ALLOWED: &[&str] = &[
+        "rtf", "ansi", "deff", "fonttbl", "f", "colortbl", "red", "green", "blue", "info",
+        "title", "pard", "rtlpar", "sb", "sa", "sl", "slmult", "b", "i", "ul", "ulnone",
+        "strike", "fs", "cf", "par", "line", "page", "bullet", "tab", "fi", "li", "trowd",
+        "cellx", "clmgf", "clmrg", "clvmgf", "clvmrg", "intbl", "ql", "qc", "qr", "cell", "row",
+        "rtlch", "ltrch", "field", "fldinst", "fldrslt", "lbimage", "lbimgsrc", "lbimgalt",
+        "lbimgtitle", "'",
+    ];
+
+    /// What RTF 1.9 output may use on top of [`RTF15_ALLOWED`].
+    const RTF19_EXTRA: &[&str] = &["u", "stylesheet", "cs", "outlinelevel"];
+
+    /// Every control word used by `rtf`, with `'` standing for a `\'xx`
+    /// escape; escaped specials (`\\`, `\{`, `\}`) are skipped.
+    fn control_words(rtf: &str) -> std::collections::BTreeSet<String> {
+        let mut words = std::collections::BTreeSet::new();
+        let mut chars = rtf.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                continue;
+            }
+            match chars.peek() {
+                Some('\'') => {
+                    words.insert("'".to_string());
+                    chars.next();
+                }
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let mut word = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if !c.is_ascii_alphabetic() {
+                            break;
+                        }
+                        word.push(c);
+                        chars.next();
+                    }
+                    words.insert(word);
+                }
+                _ => {
+                    chars.next();
+                }
+            }
+        }
+        words
+    }
+
+    #[test]
+    fn profiles_restrict_output_to_their_control_word_allowlist() {
+        use std::collections::BTreeSet;
+        let md = "# Café α\n\nIntro **bold** *italic* ~~old~~ text and a \
+                  [link](https://example.com \"Example\") and \
+                  ![logo](logo.png \"Logo\").\n\n- item\n\n\
+                  | A | B |\n| --- | --- |\n| 1 | 2 |\n";
+        let doc = MarkdownParser::new().parse(md).unwrap();
+        let allowed_15: BTreeSet<String> =
+            RTF15_ALLOWED.iter().map(|w| w.to_string()).collect();
+        let mut allowed_19 = allowed_15.clone();
+        allowed_19.extend(RTF19_EXTRA.iter().map(|w| w.to_string()));
+
+        let modern = RtfGenerator::new()
+            .with_profile(ConformanceProfile::Rtf19)
+            .generate(&doc)
+            .unwrap();
+        assert!(modern.contains("\\outlinelevel0"), "got: {modern}");
+        let outside: Vec<_> = control_words(&modern)
+            .difference(&allowed_19)
+            .cloned()
+            .collect();
+        assert!(outside.is_empty(), "outside RTF 1.9: {outside:?}");
+
+        let mut generator = RtfGenerator::new().with_profile(ConformanceProfile::Rtf15);
+        let restricted = generator.generate(&doc).unwrap();
+        let outside: Vec<_> = control_words(&restricted)
+            .difference(&allowed_15)
+            .cloned()
+            .collect();
+        assert!(outside.is_empty(), "outside RTF 1.5: {outside:?}");
+        // The dropped constructs are counted for warning surfaces: one
+        // heading and the two non-ASCII characters (é in cp1252, α not).
+        assert_eq!(generator.downgrades().get("outline_level"), Some(&1));
+        assert_eq!(generator.downgrades().get("unicode_text"), Some(&2));
+
+        // Legacy mode implies the 1.5 profile.
+        let legacy = RtfGenerator::new()
+            .with_legacy_mode(true)
+            .generate(&doc)
+            .unwrap();
+        let outside: Vec<_> = control_words(&legacy)
+            .difference(&allowed_15)
+            .cloned()
+            .collect();
+        assert!(outside.is_empty(), "legacy outside RTF 1.5: {outside:?}");
+    }
+
+    #[test]
+    fn downgrades_track_dropped_styles_and_reset_per_run() {
+        let src = "{\\rtf1{\\stylesheet{\\*\\cs16\\f1\\fs20 Code;}}\
+                   Call {\\cs16 parse_group()} next\\par}";
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(src).unwrap(),
+        )
+        .with_style_names(true)
+        .parse()
+        .unwrap();
+
+        let mut generator = RtfGenerator::new().with_profile(ConformanceProfile::Rtf15);
+        generator.generate(&doc).unwrap();
+        assert_eq!(generator.downgrades().get("character_styles"), Some(&1));
+
+        // A following RTF 1.9 run on the same generator reports nothing.
+        let mut generator = generator.with_profile(ConformanceProfile::Rtf19);
+        generator.generate(&doc).unwrap();
+        assert!(generator.downgrades().is_empty());
+    }
+
+    #[test]
+    fn legacy_mode_folds_long_lines_without_changing_content() {
+        let long = format!("{{\\rtf1 {}\\par}}", "word ".repeat(80));
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(&long).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let legacy = RtfGenerator::new()
+            .with_legacy_mode(true)
+            .generate(&doc)
+            .unwrap();
+        assert!(
+            legacy.lines().all(|l| l.len() <= LEGACY_LINE_WIDTH),
+            "got a {}-char line",
+            legacy.lines().map(str::len).max().unwrap_or(0)
+        );
+        // Raw CRLF is insignificant to readers: the text survives intact.
+        let reparsed = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(&legacy).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        assert_eq!(reparsed.plain_text().trim(), "word ".repeat(80).trim());
+    }
+
+    #[test]
+    fn round_trips_paragraph_spacing() {
+        let src = "{\\rtf1 \\sb240\\sa120\\sl276\\slmult1 spaced\\par \\pard plain\\par}";
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(src).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        assert!(
+            rtf.contains("\\sb240\\sa120\\sl276\\slmult1\\fs22 spaced"),
+            "got: {rtf}"
+        );
+        // The reset paragraph stays free of spacing words.
+        assert!(rtf.contains("\\pard\\fs22 plain"), "got: {rtf}");
+    }
+
+    #[test]
+    fn page_break_annotation_emits_pagebb_on_its_paragraph_only() {
+        let rtf = convert("First part\n\n<!-- page-break -->\n\nSecond part\n\nThird part");
+        assert!(rtf.contains("\\pard\\fs22 First part"), "got: {rtf}");
+        assert!(rtf.contains("\\pard\\pagebb\\fs22 Second part"), "got: {rtf}");
+        assert!(rtf.contains("\\pard\\fs22 Third part"), "got: {rtf}");
+        assert_eq!(rtf.matches("\\pagebb").count(), 1, "got: {rtf}");
+    }
+
+    #[test]
+    fn page_break_before_a_heading_keeps_the_house_spacing() {
+        let rtf = convert("Intro\n\n<!-- page-break -->\n\n# Appendix");
+        // The flag sits alongside, not instead of, the heading style.
+        assert!(
+            rtf.contains("\\pard\\pagebb\\sb240\\sa120\\outlinelevel0\\b\\fs48 Appendix"),
+            "got: {rtf}"
+        );
+    }
+
+    #[test]
+    fn keep_headings_emits_keepn_on_headings_only() {
+        let doc = MarkdownParser::new()
+            .parse("# Title\n\nBody text")
+            .unwrap();
+        let rtf = RtfGenerator::new()
+            .with_keep_headings(true)
+            .generate(&doc)
+            .unwrap();
+        assert!(
+            rtf.contains("\\pard\\keepn\\sb240\\sa120\\outlinelevel0\\b\\fs48 Title"),
+            "got: {rtf}"
+        );
+        assert_eq!(rtf.matches("\\keepn").count(), 1, "got: {rtf}");
+        // Off by default.
+        assert!(!convert("# Title\n\nBody text").contains("\\keepn"));
+    }
+
+    #[test]
+    fn keep_tables_marks_every_cell() {
+        let md = "Intro\n\n| a | b |\n| --- | --- |\n| c | d |";
+        let doc = MarkdownParser::new().parse(md).unwrap();
+        let rtf = RtfGenerator::new()
+            .with_keep_tables(true)
+            .generate(&doc)
+            .unwrap();
+        assert_eq!(rtf.matches("\\intbl\\keep ").count(), 4, "got: {rtf}");
+        assert!(!rtf.contains("\\keepn"), "got: {rtf}");
+        assert!(!convert(md).contains("\\keep"));
+    }
+
+    #[test]
+    fn explicit_language_emits_deflang_and_paragraph_lang_words() {
+        let doc = MarkdownParser::new()
+            .parse("# Hola\n\nBuenos dias")
+            .unwrap();
+        let rtf = RtfGenerator::new()
+            .with_language("es-ES")
+            .generate(&doc)
+            .unwrap();
+        assert!(rtf.contains("{\\rtf1\\ansi\\deff0\\deflang3082"), "got: {rtf}");
+        assert!(rtf.contains("\\b\\fs48\\lang3082 Hola"), "got: {rtf}");
+        assert!(rtf.contains("\\pard\\fs22\\lang3082 Buenos dias"), "got: {rtf}");
+        // No language, no language words.
+        let rtf = convert("# Hola\n\nBuenos dias");
+        assert!(!rtf.contains("\\deflang"), "got: {rtf}");
+        assert!(!rtf.contains("\\lang"), "got: {rtf}");
+    }
+
+    #[test]
+    fn unknown_language_tag_is_a_counted_downgrade() {
+        let doc = MarkdownParser::new().parse("Hello").unwrap();
+        let mut generator = RtfGenerator::new().with_language("tlh");
+        let rtf = generator.generate(&doc).unwrap();
+        assert!(!rtf.contains("\\deflang"), "got: {rtf}");
+        assert_eq!(generator.downgrades().get("unknown_language"), Some(&1));
+    }
+
+    #[test]
+    fn cross_references_become_bookmark_and_ref_pairs() {
+        let md = "# Section One\n\nSee [Section One](#section-one), \
+                  [elsewhere](#not-a-heading) and [the web](https://example.com).";
+        let doc = MarkdownParser::new().parse(md).unwrap();
+        let rtf = RtfGenerator::new()
+            .with_cross_references(true)
+            .generate(&doc)
+            .unwrap();
+        assert!(
+            rtf.contains("{\\*\\bkmkstart section-one}{\\*\\bkmkend section-one}Section One"),
+            "got: {rtf}"
+        );
+        assert!(
+            rtf.contains("{\\field{\\*\\fldinst{REF section-one \\\\h}}{\\fldrslt Section One}}"),
+            "got: {rtf}"
+        );
+        // Links into no heading keep the ordinary HYPERLINK emission.
+        assert!(rtf.contains("HYPERLINK \"#not-a-heading\""), "got: {rtf}");
+        assert!(rtf.contains("HYPERLINK \"https://example.com\""), "got: {rtf}");
+        // Off by default: no bookmarks, intra-document links stay plain.
+        let rtf = convert(md);
+        assert!(!rtf.contains("bkmkstart"), "got: {rtf}");
+        assert!(rtf.contains("HYPERLINK \"#section-one\""), "got: {rtf}");
+    }
+
+    #[test]
+    fn round_trips_character_styles() {
+        let src = "{\\rtf1{\\stylesheet{\\*\\cs16\\f1\\fs20 Code;}}\
+                   Call {\\cs16 parse_group()} next\\par}";
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(src).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        assert!(rtf.contains("{\\stylesheet{\\*\\cs16\\f1\\fs20 Code;}}"), "got: {rtf}");
+        assert!(rtf.contains("\\cs16 "), "got: {rtf}");
+
+        // Re-parsing the output keeps the style reference intact.
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(&rtf).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        assert!(doc.plain_text().contains("parse_group()"));
+        assert_eq!(doc.styles.len(), 1);
+    }
+
+    /// Identical inputs must produce byte-identical RTF, across repeated
+    /// runs and across threads. A multi-font document exercises the path
+    /// that used to leak HashMap iteration order into the fonttbl.
+    #[test]
+    fn output_is_byte_identical_across_runs_and_threads() {
+        let src = "{\\rtf1{\\fonttbl{\\f0 Arial;}{\\f1 Courier New;}{\\f2 Times New Roman;}}\
+                   {\\stylesheet{\\*\\cs16\\f1 Code;}}\
+                   Hello {\\f1 mono} and {\\f2 serif}\\par}";
+        let generate = || {
+            let doc = crate::conversion::rtf_parser::RtfParser::new(
+                crate::conversion::lexer::tokenize(src).unwrap(),
+            )
+            .parse()
+            .unwrap();
+            RtfGenerator::new().generate(&doc).unwrap()
+        };
+
+        let reference = generate();
+        for _ in 0..50 {
+            assert_eq!(generate(), reference);
+        }
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| std::thread::spawn(generate))
+            .collect();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), reference);
+        }
+    }
+
+    #[test]
+    fn fonttbl_is_emitted_in_index_order() {
+        let src = "{\\rtf1{\\fonttbl{\\f2 Zapf;}{\\f0 Arial;}{\\f1 Courier New;}}\
+                   A{\\f1 B}{\\f2 C}\\par}";
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(src).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        let arial = rtf.find("{\\f0 Arial;}").unwrap();
+        let courier = rtf.find("{\\f1 Courier New;}").unwrap();
+        let zapf = rtf.find("{\\f2 Zapf;}").unwrap();
+        assert!(arial < courier && courier < zapf, "got: {rtf}");
+    }
+
+    #[test]
+    fn round_trips_merged_table_cells() {
+        // A 2x-colspan header over a rowspan first column must survive
+        // generate -> parse with its merge roles intact.
+        let src = "{\\rtf1 \\trowd\\clmgf\\cellx2400\\clmrg\\cellx4800 \
+                   \\intbl Invoice\\cell\\cell\\row \
+                   \\trowd\\clvmgf\\cellx2400\\cellx4800 \\intbl Acme\\cell 10\\cell\\row \
+                   \\trowd\\clvmrg\\cellx2400\\cellx4800 \\intbl\\cell 20\\cell\\row}";
+        let doc = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(src).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        assert!(rtf.contains("\\clmgf\\cellx2400\\clmrg\\cellx4800"), "got: {rtf}");
+        assert!(rtf.contains("\\clvmgf\\cellx2400"), "got: {rtf}");
+        assert!(rtf.contains("\\clvmrg\\cellx2400"), "got: {rtf}");
+
+        let reparsed = crate::conversion::rtf_parser::RtfParser::new(
+            crate::conversion::lexer::tokenize(&rtf).unwrap(),
+        )
+        .parse()
+        .unwrap();
+        let RtfNode::Table(ref table) = reparsed.content[0] else {
+            panic!("expected table, got {:?}", reparsed.content);
+        };
+        assert_eq!(table.col_span(0, 0), 2);
+        assert_eq!(table.row_span(1, 0), 2);
+    }
+
+    #[test]
+    fn round_trip_preserves_text() {
+        let rtf = convert("# Title\n\nBody with **bold** text");
+        let md = crate::conversion::rtf_to_markdown(&rtf).unwrap();
+        assert!(md.contains("# Title"));
+        assert!(md.contains("**bold**"));
+    }
+
+    /// Calibrates the per-node cost factors: on every fixture the actual
+    /// output must land inside [min, max] and within 25% of `expected`.
+    #[test]
+    fn estimates_bracket_actual_output_within_tolerance() {
+        let fixtures = [
+            "Hello world",
+            "# Title\n\nBody with **bold** and *italic* text across a \
+             somewhat longer paragraph to give the model something to chew on.",
+            "# Report\n\n- first item\n- second item\n- third item\n\n\
+             | Item | Qty | Price |\n| --- | --- | --- |\n\
+             | Widget | 2 | 9.99 |\n| Gadget | 14 | 120.00 |",
+            "Café menü with accented text — dashes and “smart quotes” too.",
+            "Special \\ backslash and {braces} in text",
+            "A report with ![a chart](img/chart.png \"Q3\") inline",
+        ];
+        for (legacy, md) in fixtures
+            .iter()
+            .flat_map(|md| [(false, md), (true, md)])
+        {
+            let doc = MarkdownParser::new().parse(md).unwrap();
+            let mut generator = RtfGenerator::new().with_legacy_mode(legacy);
+            let estimate = generator.estimate(&doc);
+            let actual = generator.generate(&doc).unwrap().len();
+            assert!(
+                estimate.min <= actual && actual <= estimate.max,
+                "{md:?} (legacy: {legacy}): actual {actual} outside {estimate:?}"
+            );
+            let deviation = estimate.expected.abs_diff(actual);
+            assert!(
+                deviation * 4 <= actual,
+                "{md:?} (legacy: {legacy}): expected {} vs actual {actual}",
+                estimate.expected
+            );
+        }
+    }
+}
@@ -0,0 +1,3206 @@
+//! RTF parser.
+//!
+//! Consumes the token stream produced by [`lexer::tokenize`](super::lexer::tokenize)
+//! and builds an [`RtfDocument`] tree that the generators walk.
+
+use super::cancel::{self, CancellationToken};
+use super::color::{self, Color};
+use super::control_word_extensions::{ControlWordExtensions, CustomDestination, ExtensionRule};
+use super::cross_references;
+use super::features::FeatureUsage;
+use super::font_map::{self, FontEntry, FontMap, FontSubstitution};
+use super::forms::{self, FormField};
+use super::language;
+use super::lexer::RtfToken;
+use super::normalization::{self, NormalizationForm};
+use super::styles::{self, CharacterStyle};
+use super::unicode_hygiene;
+use crate::security::{InputValidator, SanitizationMode, SecurityLimits, UnicodeHygiene};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Text direction for a paragraph (`\rtlpar`) or a run (`\rtlch`/`\ltrch`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    LeftToRight,
+    RightToLeft,
+}
+
+/// Which side of a tracked change a run belongs to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RevisionKind {
+    /// `\revised` - text inserted by a reviewer.
+    #[default]
+    Inserted,
+    /// `\deleted` - text struck by a reviewer.
+    Deleted,
+}
+
+/// Track-changes marking on a run, with the author and timestamp resolved
+/// from the `\revtbl`/`\revauthN`/`\revdttmN` machinery where present.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Revision {
+    pub kind: RevisionKind,
+    /// Author name from the `\revtbl`, via `\revauthN`.
+    pub author: Option<String>,
+    /// Timestamp decoded from the packed `\revdttmN` value, as
+    /// `YYYY-MM-DD HH:MM`.
+    pub date: Option<String>,
+}
+
+/// Placeholder text standing in for equation (`\mmath`) and drawing
+/// object (`\do`) groups, which have no Markdown equivalent. The groups
+/// are skipped brace-balanced and tallied in the degradation report; the
+/// placeholder flows into the output where the content was.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaceholderPolicy {
+    /// Stands in for a `\mmath` equation group.
+    pub equation: String,
+    /// Stands in for a `\do` drawing object group.
+    pub drawing: String,
+    /// Preserve the skipped group's source in an HTML comment after the
+    /// placeholder, for later reprocessing.
+    pub keep_raw: bool,
+}
+
+impl Default for PlaceholderPolicy {
+    fn default() -> Self {
+        PlaceholderPolicy {
+            equation: "[equation omitted]".to_string(),
+            drawing: "[drawing omitted]".to_string(),
+            keep_raw: false,
+        }
+    }
+}
+
+/// A reviewer comment (`{\*\annotation ...}`) lifted out of the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// 1-based number in document order; inline `[note N]` markers use it.
+    pub index: usize,
+    /// Reviewer initials from `{\*\atnid ...}`.
+    pub initials: Option<String>,
+    /// Full reviewer name from `{\*\atnauthor ...}`, where the writer
+    /// emitted one.
+    pub author: Option<String>,
+    /// The commented-on text bracketed by `\atrfstart`/`\atrfend`, where
+    /// the comment anchors a range rather than a point.
+    pub anchor: Option<String>,
+    /// The comment body.
+    pub text: String,
+}
+
+impl Annotation {
+    /// Reviewer label for rendered notes: initials, falling back to the
+    /// full name.
+    pub fn label(&self) -> Option<&str> {
+        self.initials.as_deref().or(self.author.as_deref())
+    }
+}
+
+/// Character-level formatting attached to a run of content.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextFormat {
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    /// Explicit run direction (`\rtlch`/`\ltrch`); `None` inherits the
+    /// paragraph direction.
+    pub direction: Option<Direction>,
+    /// Font size in half-points, as RTF stores it (`\fsN`).
+    pub font_size: Option<i32>,
+    /// Index into the font table (`\fN`).
+    pub font_index: Option<i32>,
+    /// Index into the color table (`\cfN`).
+    pub color_index: Option<i32>,
+    /// Name of the character style (`\csN`) this run references, when the
+    /// parser is recording style names. Generators use it to preserve the
+    /// style's intent rather than just its raw toggles.
+    pub style_name: Option<String>,
+    /// Track-changes marking (`\revised`/`\deleted`); generators decide
+    /// whether to accept, reject or annotate it.
+    pub revision: Option<Revision>,
+}
+
+impl TextFormat {
+    pub fn is_plain(&self) -> bool {
+        *self == TextFormat::default()
+    }
+}
+
+/// Explicit paragraph spacing, in twips as RTF stores it (`\sbN`, `\saN`,
+/// `\slN`). `None` fields were left to the reader's defaults.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParagraphSpacing {
+    /// Space before the paragraph (`\sbN`), in twips.
+    pub space_before: Option<i32>,
+    /// Space after the paragraph (`\saN`), in twips.
+    pub space_after: Option<i32>,
+    /// Line spacing (`\slN`); an exact height in twips, or a multiple of
+    /// single spacing in 240ths when `line_spacing_multiple` is set.
+    pub line_spacing: Option<i32>,
+    /// `\slmult1` - `line_spacing` is a multiple rather than a height.
+    pub line_spacing_multiple: bool,
+    /// `\pagebb` - start the paragraph on a new page.
+    pub page_break_before: bool,
+    /// `\keepn` - keep the paragraph on the same page as the next one
+    /// (Word's "keep with next", typically set on headings).
+    pub keep_with_next: bool,
+}
+
+impl ParagraphSpacing {
+    /// True when no explicit spacing was given and neither break/keep
+    /// flag is set.
+    pub fn is_default(&self) -> bool {
+        *self == ParagraphSpacing::default()
+    }
+
+    /// True when any spacing value is set, ignoring the break/keep
+    /// flags; decides whether explicit spacing replaces a house style.
+    pub fn has_spacing(&self) -> bool {
+        self.space_before.is_some() || self.space_after.is_some() || self.line_spacing.is_some()
+    }
+}
+
+/// A node of the parsed document tree.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RtfNode {
+    /// A run of plain text.
+    Text(String),
+    /// Content carrying character formatting.
+    Formatted {
+        format: TextFormat,
+        content: Vec<RtfNode>,
+    },
+    /// A `HYPERLINK` field (`{\field{\*\fldinst ...}{\fldrslt ...}}`);
+    /// `title` is the tooltip from the instruction's `\o` switch.
+    Hyperlink {
+        url: String,
+        title: Option<String>,
+        content: Vec<RtfNode>,
+    },
+    /// An image reference (`![alt](src "title")` in Markdown). Pixel
+    /// payloads are never carried - `\pict` groups stay skipped - but the
+    /// reference and its alt text (our only accessibility artifact)
+    /// survive the round trip in a `{\*\lbimage ...}` destination of our
+    /// own; see the generator.
+    Image {
+        src: String,
+        alt: String,
+        title: Option<String>,
+    },
+    /// A raw RTF passthrough region (a ```` ```rtf-raw ```` fence in
+    /// Markdown). The content is RTF source emitted verbatim between
+    /// plain `{\*\lbrawstart}`/`{\*\lbrawend}` markers - not inside a
+    /// destination, because the reader must interpret it, not skip it.
+    /// Only the Markdown parser's explicit opt-in creates these; see
+    /// [`MarkdownParser::with_raw_rtf`](super::markdown_parser::MarkdownParser::with_raw_rtf).
+    RawRtf {
+        content: String,
+    },
+    /// A paragraph (`\par` terminated).
+    Paragraph {
+        direction: Direction,
+        spacing: ParagraphSpacing,
+        content: Vec<RtfNode>,
+    },
+    /// A heading derived from `\outlinelevelN` (level is 1-based).
+    Heading {
+        level: u8,
+        spacing: ParagraphSpacing,
+        content: Vec<RtfNode>,
+    },
+    /// A list item (bullet or numbered). `level` is 0-based nesting depth.
+    ListItem {
+        ordered: bool,
+        level: u8,
+        content: Vec<RtfNode>,
+    },
+    /// A table built from `\trowd`/`\cell`/`\row`.
+    Table(Table),
+    /// `\line` - a line break within a paragraph.
+    LineBreak,
+    /// `\page` - an explicit page break.
+    PageBreak,
+}
+
+impl Drop for RtfNode {
+    /// The compiler-generated drop glue recurses through `content` vectors,
+    /// so dropping an adversarially deep tree would overflow the native
+    /// stack. Flatten children onto a work list before they drop instead.
+    fn drop(&mut self) {
+        let mut work = Vec::new();
+        take_children(self, &mut work);
+        while let Some(mut node) = work.pop() {
+            take_children(&mut node, &mut work);
+        }
+    }
+}
+
+/// Move `node`'s children onto `work`, leaving it a leaf.
+fn take_children(node: &mut RtfNode, work: &mut Vec<RtfNode>) {
+    match node {
+        RtfNode::Formatted { content, .. }
+        | RtfNode::Hyperlink { content, .. }
+        | RtfNode::Paragraph { content, .. }
+        | RtfNode::Heading { content, .. }
+        | RtfNode::ListItem { content, .. } => work.append(content),
+        RtfNode::Table(table) => {
+            for row in &mut table.rows {
+                for cell in &mut row.cells {
+                    work.append(&mut cell.content);
+                }
+            }
+        }
+        RtfNode::Text(_)
+        | RtfNode::Image { .. }
+        | RtfNode::RawRtf { .. }
+        | RtfNode::LineBreak
+        | RtfNode::PageBreak => {}
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Table {
+    pub rows: Vec<TableRow>,
+}
+
+impl Table {
+    /// Column span of the cell at (`row`, `col`): 1 plus the `\clmrg`
+    /// continuation cells immediately to its right.
+    pub fn col_span(&self, row: usize, col: usize) -> usize {
+        let Some(row) = self.rows.get(row) else {
+            return 1;
+        };
+        1 + row.cells[col + 1..]
+            .iter()
+            .take_while(|c| c.h_merge == CellMerge::Merged)
+            .count()
+    }
+
+    /// Row span of the cell at (`row`, `col`): 1 plus the `\clvmrg`
+    /// continuation cells directly below it in later rows.
+    pub fn row_span(&self, row: usize, col: usize) -> usize {
+        1 + self.rows[row + 1..]
+            .iter()
+            .take_while(|r| {
+                r.cells
+                    .get(col)
+                    .is_some_and(|c| c.v_merge == CellMerge::Merged)
+            })
+            .count()
+    }
+
+    /// Whether any cell participates in a horizontal or vertical merge.
+    pub fn has_merges(&self) -> bool {
+        self.rows.iter().flat_map(|r| &r.cells).any(|c| {
+            c.h_merge != CellMerge::None || c.v_merge != CellMerge::None
+        })
+    }
+
+    /// Column boundaries for the table as a whole: the first row that
+    /// recorded `\cellx` positions. Empty when no row carried widths.
+    pub fn col_bounds(&self) -> &[i32] {
+        self.rows
+            .iter()
+            .find(|r| !r.col_bounds.is_empty())
+            .map(|r| r.col_bounds.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Per-column alignment, derived from the first cell in each column
+    /// with a non-default alignment (GFM alignment is per column, not
+    /// per cell).
+    pub fn column_alignments(&self) -> Vec<CellAlignment> {
+        let width = self.rows.iter().map(|r| r.cells.len()).max().unwrap_or(0);
+        (0..width)
+            .map(|col| {
+                self.rows
+                    .iter()
+                    .filter_map(|r| r.cells.get(col))
+                    .map(|c| c.alignment)
+                    .find(|a| *a != CellAlignment::Left)
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableRow {
+    pub cells: Vec<TableCell>,
+    /// Right boundary of each cell from `\cellxN`, in cumulative twips
+    /// from the left edge. Empty when the source carried no widths.
+    pub col_bounds: Vec<i32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TableCell {
+    pub content: Vec<RtfNode>,
+    /// Horizontal merge role from `\clmgf`/`\clmrg`.
+    pub h_merge: CellMerge,
+    /// Vertical merge role from `\clvmgf`/`\clvmrg`.
+    pub v_merge: CellMerge,
+    /// Content alignment from `\ql`/`\qc`/`\qr`.
+    pub alignment: CellAlignment,
+}
+
+/// A cell's role in a merged span. RTF marks the first cell of the span
+/// and keeps the absorbed cells in the row (empty) as continuations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellMerge {
+    /// Not part of a merged span.
+    #[default]
+    None,
+    /// First cell of a span (`\clmgf`/`\clvmgf`).
+    First,
+    /// Continuation absorbed into the preceding/above cell
+    /// (`\clmrg`/`\clvmrg`).
+    Merged,
+}
+
+/// Paragraph alignment of table cell content, mapped to and from the GFM
+/// colon syntax in the separator row (`:---:`, `---:`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CellAlignment {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// Document-level metadata harvested from the `\info` group.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DocumentMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub subject: Option<String>,
+    /// Font substitutions applied by the [`FontMap`] during parsing; the
+    /// original names are kept here for auditability.
+    pub font_substitutions: Vec<FontSubstitution>,
+    /// Unsupported constructs encountered during parsing - what this
+    /// document uses that won't fully survive conversion.
+    pub feature_usage: FeatureUsage,
+    /// Form fields lifted out of the document; only populated when the
+    /// parser runs with form extraction enabled.
+    pub form_fields: Vec<FormField>,
+    /// The [`NormalizationForm`](super::normalization::NormalizationForm)
+    /// the text nodes were brought into; `None` until the normalization
+    /// pass has run.
+    pub normalization: Option<super::normalization::NormalizationForm>,
+    /// Document language as a BCP 47 tag (e.g. `es-ES`), from `\deflang`
+    /// or a `lang:` front-matter key. Drives `\deflang`/`\lang` emission
+    /// and Word's spell-check dictionary choice.
+    pub language: Option<String>,
+    /// Reviewer comments lifted out of the document, in document order.
+    pub annotations: Vec<Annotation>,
+    /// Groups captured by name under a
+    /// [`ExtensionRule::CustomDestination`] alias, in document order.
+    pub custom_destinations: Vec<CustomDestination>,
+}
+
+/// The parsed representation of an RTF document.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RtfDocument {
+    pub metadata: DocumentMetadata,
+    /// The font table (post-substitution); [`TextFormat::font_index`]
+    /// values refer to [`FontEntry::index`].
+    pub fonts: Vec<FontEntry>,
+    /// The color table; [`TextFormat::color_index`] values index into it.
+    pub colors: Vec<Color>,
+    /// Character styles from the `\stylesheet`;
+    /// [`TextFormat::style_name`] values refer to [`CharacterStyle::name`].
+    pub styles: Vec<CharacterStyle>,
+    pub content: Vec<RtfNode>,
+}
+
+impl RtfDocument {
+    /// Concatenate all text nodes, ignoring formatting.
+    ///
+    /// Iterative (explicit work list) so adversarially deep trees cannot
+    /// exhaust the native stack.
+    pub fn plain_text(&self) -> String {
+        enum Step<'a> {
+            Node(&'a RtfNode),
+            Emit(&'static str),
+        }
+
+        let mut out = String::new();
+        let mut work: Vec<Step> = self.content.iter().rev().map(Step::Node).collect();
+        while let Some(step) = work.pop() {
+            let node = match step {
+                Step::Emit(s) => {
+                    out.push_str(s);
+                    continue;
+                }
+                Step::Node(node) => node,
+            };
+            match node {
+                RtfNode::Text(t) => out.push_str(t),
+                RtfNode::Formatted { content, .. }
+                | RtfNode::Hyperlink { content, .. } => {
+                    work.extend(content.iter().rev().map(Step::Node));
+                }
+                RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => {
+                    work.push(Step::Emit("\n"));
+                    work.extend(content.iter().rev().map(Step::Node));
+                }
+                RtfNode::Table(table) => {
+                    for row in table.rows.iter().rev() {
+                        work.push(Step::Emit("\n"));
+                        for cell in row.cells.iter().rev() {
+                            work.push(Step::Emit("\t"));
+                            work.extend(cell.content.iter().rev().map(Step::Node));
+                        }
+                    }
+                }
+                RtfNode::LineBreak => out.push('\n'),
+                RtfNode::Image { .. } | RtfNode::RawRtf { .. } | RtfNode::PageBreak => {}
+            }
+        }
+        out
+    }
+}
+
+/// Control words the parser converts (fully or with reduced fidelity):
+/// the `handle_control_word` match arms plus the destination groups
+/// parsed elsewhere. The support table's coverage test checks each entry
+/// is documented as `full` or `partial`, so a new match arm without a
+/// table row fails the build.
+#[cfg(test)]
+pub(crate) const HANDLED_CONTROL_WORDS: &[&str] = &[
+    "b", "i", "ul", "ulnone", "strike", "fs", "f", "cf", "cs", "plain", "outlinelevel", "pard",
+    "sb", "sa", "sl", "slmult", "deflang", "pagebb", "keepn", "keep", "rtlpar", "ltrpar", "rtlch", "ltrch",
+    "revised", "deleted",
+    "revauth", "revauthdel", "revdttm", "revdttmdel", "par", "line", "page", "sect", "trowd",
+    "intbl", "cell", "cellx", "clmgf", "clmrg", "clvmgf", "clvmrg", "row", "ql", "qc", "qr", "u", "tab", "bullet", "endash", "emdash", "lquote", "rquote",
+    "ldblquote", "rdblquote", "fonttbl", "colortbl", "stylesheet", "revtbl", "info", "title",
+    "author", "subject", "annotation", "atnid", "atnauthor", "atrfstart", "atrfend", "formfield",
+    "mmath", "do", "field", "fldinst", "fldrslt", "bkmkstart", "bkmkend", "lbimage", "lbimgsrc", "lbimgalt", "lbimgtitle",
+    "lbrawstart", "lbrawend",
+];
+
+/// Destination groups whose content is not document text.
+const SKIP_DESTINATIONS: &[&str] = &[
+    "listtable",
+    "listoverridetable",
+    "pict",
+    "object",
+    "header",
+    "footer",
+    "headerl",
+    "headerr",
+    "footerl",
+    "footerr",
+    "generator",
+    "themedata",
+];
+
+/// Parser state while walking the token stream.
+struct ParseState {
+    format: TextFormat,
+    outline_level: Option<u8>,
+    /// Paragraph direction from `\rtlpar`/`\ltrpar`.
+    direction: Direction,
+    /// Paragraph spacing from `\sb`/`\sa`/`\sl`/`\slmult`.
+    spacing: ParagraphSpacing,
+    in_table_row: bool,
+    /// Paragraph alignment from `\ql`/`\qc`/`\qr`; only kept on table
+    /// cells (Markdown has no block alignment outside tables).
+    alignment: CellAlignment,
+}
+
+/// One open group on the explicit parse stack: the formatting state scoped
+/// to the group and the inline content collected inside it so far.
+struct GroupFrame {
+    state: ParseState,
+    inline: Vec<RtfNode>,
+}
+
+pub struct RtfParser {
+    tokens: Vec<RtfToken>,
+    pos: usize,
+    metadata: DocumentMetadata,
+    /// Substitutions applied to fonts as the `\fonttbl` is parsed.
+    font_map: FontMap,
+    /// Alias rules for non-standard control words (default empty).
+    extensions: ControlWordExtensions,
+    /// Font table parsed from `\fonttbl` (post-substitution).
+    fonts: Vec<FontEntry>,
+    /// Color table parsed from `\colortbl`.
+    colors: Vec<Color>,
+    /// Character styles parsed from `\stylesheet`.
+    styles: Vec<CharacterStyle>,
+    /// Reviewer names parsed from `\revtbl`; `\revauthN` indexes into it.
+    revision_authors: Vec<String>,
+    /// Record style names on runs that reference `\csN` (default on).
+    record_style_names: bool,
+    /// Lift `\formfield` groups into structured data, replacing them in
+    /// the flow with their value text (default off).
+    extract_forms: bool,
+    /// Resolve `\*\bkmkstart` bookmarks and `REF`/`PAGEREF` fields into
+    /// intra-document heading links (default off).
+    cross_references: bool,
+    /// Bookmark names seen, each with the count of top-level blocks
+    /// flushed when its `\*\bkmkstart` appeared - the index of the block
+    /// it sits in once that block flushes.
+    bookmarks: Vec<(String, usize)>,
+    /// Leave `[note N]` markers where annotations were extracted
+    /// (default off: comments are stripped without a trace).
+    annotation_markers: bool,
+    /// Placeholder text for skipped equation and drawing groups.
+    placeholders: PlaceholderPolicy,
+    /// Initials/author pending from `{\*\atnid}`/`{\*\atnauthor}`,
+    /// consumed by the next `\annotation` group.
+    pending_annotation: (Option<String>, Option<String>),
+    /// Comment anchor ranges currently open: the `\atrfstart` id and the
+    /// text captured so far.
+    open_anchors: Vec<(i32, String)>,
+    /// Closed anchor ranges awaiting their `\annotation` group, by id.
+    closed_anchors: HashMap<i32, String>,
+    /// Cells collected for the table row currently being built.
+    pending_row: Vec<TableCell>,
+    /// Rows collected for the table currently being built.
+    pending_table: Vec<TableRow>,
+    /// Merge roles from the current row header, one entry per `\cellx`;
+    /// `\cell` number N takes entry N.
+    row_merges: Vec<(CellMerge, CellMerge)>,
+    /// Right cell boundaries from the current row header's `\cellxN`.
+    row_bounds: Vec<i32>,
+    /// Merge roles accumulated since the last `\cellx` (h, v).
+    current_merge: (CellMerge, CellMerge),
+    /// In tolerant mode structural surprises (stray `}`, EOF inside a
+    /// group) are recorded as warnings and parsing continues.
+    tolerant: bool,
+    warnings: Vec<String>,
+    /// Clamps out-of-range control word parameters.
+    validator: InputValidator,
+    /// Formatted runs created so far, checked against `max_format_runs`.
+    format_runs: usize,
+    max_format_runs: usize,
+    /// Per-object, total and image-count caps on embedded `\pict`/
+    /// `\object` payloads (defaults from [`SecurityLimits`]).
+    max_embedded_object_size: usize,
+    max_total_embedded_size: usize,
+    max_image_count: usize,
+    /// What to do with a payload that violates those caps.
+    sanitization: SanitizationMode,
+    /// Per-class policies for zero-width and bidi control characters,
+    /// applied to the finished document's text nodes.
+    hygiene: UnicodeHygiene,
+    /// Normalization form the finished document's text nodes are brought
+    /// into, after the hygiene scrub.
+    normalization: NormalizationForm,
+    /// Normalize `"Code"`-styled runs too (default: off; normalization
+    /// can change what source code means).
+    normalize_code: bool,
+    /// Which branch of `\upr` fallback pairs to keep: the `\*\ud`
+    /// Unicode branch (default) or the plain-ANSI branch.
+    prefer_unicode: bool,
+    /// Emit an empty paragraph node for each `\par` in a consecutive
+    /// run (default: drop them), so the pipeline's cleanup pass can
+    /// decide what the `\par\par\par` vertical-spacing idiom becomes.
+    keep_empty_paragraphs: bool,
+    /// Decoded embedded payload bytes and images seen so far.
+    embedded_total: usize,
+    image_count: usize,
+    /// Set when a formatting control word consumed its trailing delimiter
+    /// space: our VFP9 report writer uses that single space as the word
+    /// boundary between runs, so `push_text` re-inserts it when word
+    /// characters sit on both sides.
+    pending_delimiter_space: bool,
+    /// Checked periodically in the parse loop; a cancelled token aborts
+    /// with [`cancel::CANCELLED_MESSAGE`].
+    cancel: Option<CancellationToken>,
+}
+
+impl RtfParser {
+    pub fn new(tokens: Vec<RtfToken>) -> Self {
+        RtfParser {
+            tokens,
+            pos: 0,
+            metadata: DocumentMetadata::default(),
+            font_map: FontMap::with_defaults(),
+            extensions: ControlWordExtensions::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            revision_authors: Vec::new(),
+            record_style_names: true,
+            extract_forms: false,
+            cross_references: false,
+            bookmarks: Vec::new(),
+            annotation_markers: false,
+            placeholders: PlaceholderPolicy::default(),
+            pending_annotation: (None, None),
+            open_anchors: Vec::new(),
+            closed_anchors: HashMap::new(),
+            pending_row: Vec::new(),
+            pending_table: Vec::new(),
+            row_merges: Vec::new(),
+            row_bounds: Vec::new(),
+            current_merge: (CellMerge::None, CellMerge::None),
+            tolerant: false,
+            warnings: Vec::new(),
+            validator: InputValidator::with_defaults(),
+            format_runs: 0,
+            max_format_runs: SecurityLimits::default().max_format_runs,
+            max_embedded_object_size: SecurityLimits::default().max_embedded_object_size,
+            max_total_embedded_size: SecurityLimits::default().max_total_embedded_size,
+            max_image_count: SecurityLimits::default().max_image_count,
+            sanitization: SanitizationMode::default(),
+            hygiene: UnicodeHygiene::default(),
+            normalization: NormalizationForm::default(),
+            normalize_code: false,
+            prefer_unicode: true,
+            keep_empty_paragraphs: false,
+            embedded_total: 0,
+            image_count: 0,
+            pending_delimiter_space: false,
+            cancel: None,
+        }
+    }
+
+    /// Enable or disable tolerant parsing (default off).
+    pub fn with_tolerance(mut self, tolerant: bool) -> Self {
+        self.tolerant = tolerant;
+        self
+    }
+
+    /// Replace the default [`FontMap`] used for font substitution.
+    pub fn with_font_map(mut self, font_map: FontMap) -> Self {
+        self.font_map = font_map;
+        self
+    }
+
+    /// Replace the (empty) default [`ControlWordExtensions`] consulted for
+    /// control words the parser does not know.
+    pub fn with_extensions(mut self, extensions: ControlWordExtensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// Enable or disable recording style names on `\csN` runs (default on).
+    /// The resolved formatting is applied either way.
+    pub fn with_style_names(mut self, record: bool) -> Self {
+        self.record_style_names = record;
+        self
+    }
+
+    /// Enable or disable form field extraction (default off). When on,
+    /// `\field` groups carrying a `\formfield` destination land in
+    /// [`DocumentMetadata::form_fields`] and their value text takes their
+    /// place in the document flow.
+    pub fn with_form_extraction(mut self, extract: bool) -> Self {
+        self.extract_forms = extract;
+        self
+    }
+
+    /// Enable or disable cross-reference resolution (default off). When
+    /// on, `\*\bkmkstart`/`\*\bkmkend` pairs become named anchors and
+    /// `REF`/`PAGEREF` fields targeting them become intra-document links
+    /// to the slug of the bookmark's nearest enclosing heading - no other
+    /// field types gain meaning. References to bookmarks that do not
+    /// exist keep their result text and record a warning.
+    pub fn with_cross_references(mut self, resolve: bool) -> Self {
+        self.cross_references = resolve;
+        self
+    }
+
+    /// Replace the default [`PlaceholderPolicy`] for equation and drawing
+    /// groups.
+    pub fn with_placeholders(mut self, placeholders: PlaceholderPolicy) -> Self {
+        self.placeholders = placeholders;
+        self
+    }
+
+    /// Enable or disable `[note N]` markers at annotation anchors
+    /// (default off). The comments themselves land in
+    /// [`DocumentMetadata::annotations`] either way.
+    pub fn with_annotation_markers(mut self, markers: bool) -> Self {
+        self.annotation_markers = markers;
+        self
+    }
+
+    /// Override the maximum number of formatted runs (default from
+    /// [`SecurityLimits`]).
+    pub fn with_max_format_runs(mut self, max_format_runs: usize) -> Self {
+        self.max_format_runs = max_format_runs;
+        self
+    }
+
+    /// Override the embedded payload caps (defaults from
+    /// [`SecurityLimits`]).
+    pub fn with_embedded_limits(mut self, limits: &SecurityLimits) -> Self {
+        self.max_embedded_object_size = limits.max_embedded_object_size;
+        self.max_total_embedded_size = limits.max_total_embedded_size;
+        self.max_image_count = limits.max_image_count;
+        self
+    }
+
+    /// What to do with embedded payloads over the caps (default:
+    /// placeholder substitution).
+    pub fn with_sanitization_mode(mut self, mode: SanitizationMode) -> Self {
+        self.sanitization = mode;
+        self
+    }
+
+    /// Replace the default [`UnicodeHygiene`] policy applied to text
+    /// nodes (default from [`SecurityLimits`]: strip bidi overrides and
+    /// zero-width characters outside emoji sequences).
+    pub fn with_unicode_hygiene(mut self, hygiene: UnicodeHygiene) -> Self {
+        self.hygiene = hygiene;
+        self
+    }
+
+    /// Select the [`NormalizationForm`] text nodes are brought into
+    /// (default: NFC, so Mac-authored NFD sources and Windows NFC ones
+    /// produce byte-identical output for identical text).
+    pub fn with_normalization(mut self, form: NormalizationForm) -> Self {
+        self.normalization = form;
+        self
+    }
+
+    /// Normalize runs carrying the `"Code"` character style too
+    /// (default: off; normalization can change what quoted source code
+    /// means, so code is left exactly as written).
+    pub fn with_code_normalization(mut self, normalize: bool) -> Self {
+        self.normalize_code = normalize;
+        self
+    }
+
+    /// Keep the Unicode (`\*\ud`) branch of `\upr` fallback pairs (the
+    /// default) or the plain-ANSI branch, for legacy consumers.
+    pub fn with_unicode_preference(mut self, prefer_unicode: bool) -> Self {
+        self.prefer_unicode = prefer_unicode;
+        self
+    }
+
+    /// Keep consecutive `\par`s as empty paragraph nodes instead of
+    /// dropping them (default off). The pipeline enables this and lets
+    /// its cleanup pass collapse the runs per policy.
+    pub fn with_empty_paragraphs(mut self, keep: bool) -> Self {
+        self.keep_empty_paragraphs = keep;
+        self
+    }
+
+    /// Observe a [`CancellationToken`] while parsing: a cancelled token
+    /// aborts the parse at the next periodic check.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    pub fn parse(self) -> Result<RtfDocument, String> {
+        self.parse_with_warnings().map(|(document, _)| document)
+    }
+
+    /// Parse, returning the document together with any recovery warnings
+    /// recorded in tolerant mode.
+    pub fn parse_with_warnings(mut self) -> Result<(RtfDocument, Vec<String>), String> {
+        // Word writes `{\upr{ansi}{\*\ud {unicode}}}` pairs; emitting both
+        // branches would duplicate the text, so pick one up front.
+        if self
+            .tokens
+            .iter()
+            .any(|t| matches!(t, RtfToken::ControlWord { name, .. } if name == "upr"))
+        {
+            self.tokens = resolve_upr_alternates(&self.tokens, self.prefer_unicode);
+        }
+        let mut content = Vec::new();
+        loop {
+            let state = ParseState {
+                format: TextFormat::default(),
+                outline_level: None,
+                direction: Direction::default(),
+                spacing: ParagraphSpacing::default(),
+                in_table_row: false,
+                alignment: CellAlignment::default(),
+            };
+            self.parse_group(state, &mut content)?;
+            if self.pos >= self.tokens.len() {
+                break;
+            }
+            // parse_group returned on a GroupEnd with content still left:
+            // a stray `}` at (effective) top level.
+            if !self.tolerant {
+                return Err(format!(
+                    "unexpected group terminator at token {}",
+                    self.pos
+                ));
+            }
+            self.warnings.push(format!(
+                "stray group terminator at token {} ignored; continuing",
+                self.pos
+            ));
+        }
+        self.flush_table(&mut content);
+        super::thread_state::recycle_token_buffer(std::mem::take(&mut self.tokens));
+        let mut document = RtfDocument {
+            metadata: self.metadata,
+            fonts: self.fonts,
+            colors: self.colors,
+            styles: self.styles,
+            content,
+        };
+        unicode_hygiene::scrub(&mut document, &self.hygiene, &mut self.warnings)?;
+        normalization::normalize(&mut document, self.normalization, self.normalize_code);
+        if self.cross_references {
+            cross_references::resolve(&mut document, &self.bookmarks, &mut self.warnings);
+        }
+        Ok((document, self.warnings))
+    }
+
+    /// Parse tokens until the `GroupEnd` matching the outermost group (or
+    /// EOF at top level), appending finished block nodes to `out`.
+    ///
+    /// Group nesting is tracked on an explicit [`GroupFrame`] stack rather
+    /// than by recursion, so nesting depth is limited only by memory - not
+    /// by the native stack. Nested groups keep their inline content local
+    /// (formatting is scoped to the group) while block-level output reaches
+    /// `out` directly from any depth.
+    fn parse_group(&mut self, state: ParseState, out: &mut Vec<RtfNode>) -> Result<(), String> {
+        let mut stack = vec![GroupFrame {
+            state,
+            inline: Vec::new(),
+        }];
+
+        while self.pos < self.tokens.len() {
+            // Every 1024 tokens is frequent enough for prompt cancellation
+            // and cheap enough not to show up in profiles.
+            if self.pos & 0x3ff == 0
+                && self
+                    .cancel
+                    .as_ref()
+                    .is_some_and(CancellationToken::is_cancelled)
+            {
+                return Err(cancel::CANCELLED_MESSAGE.to_string());
+            }
+            let token = self.tokens[self.pos].clone();
+            self.pos += 1;
+            match token {
+                RtfToken::GroupStart => {
+                    if self.peek_is_fonttbl_group() {
+                        self.parse_font_group()?;
+                        continue;
+                    }
+                    if self.peek_is_colortbl_group() {
+                        self.parse_color_group()?;
+                        continue;
+                    }
+                    if self.peek_is_stylesheet_group() {
+                        self.parse_stylesheet_group()?;
+                        continue;
+                    }
+                    if self.peek_is_revtbl_group() {
+                        self.parse_revtbl_group()?;
+                        continue;
+                    }
+                    if let Some(destination) = self.peek_annotation_destination() {
+                        let start = self.pos;
+                        self.skip_group()?;
+                        if let Some(marker) = self.handle_annotation_group(destination, start) {
+                            let top = stack.last_mut().expect("group stack never empties");
+                            self.push_text(&mut top.inline, &top.state, marker)?;
+                        }
+                        continue;
+                    }
+                    if self.extract_forms && self.peek_is_form_field_group() {
+                        let start = self.pos;
+                        self.skip_group()?;
+                        if let Some(field) = forms::parse_field(&self.tokens[start..self.pos]) {
+                            let replacement = field.replacement_text();
+                            self.metadata.form_fields.push(field);
+                            let top = stack.last_mut().expect("group stack never empties");
+                            self.push_text(&mut top.inline, &top.state, replacement)?;
+                        }
+                        continue;
+                    }
+                    if let Some(placeholder) = self.peek_placeholder_group() {
+                        let start = self.pos;
+                        // skip_group is brace-balanced and tallies the
+                        // group's control words for the degradation report.
+                        self.skip_group()?;
+                        let mut text = placeholder;
+                        if self.placeholders.keep_raw {
+                            text.push_str(&format!(
+                                "<!-- {} -->",
+                                raw_rtf(&self.tokens[start..self.pos])
+                            ));
+                        }
+                        let top = stack.last_mut().expect("group stack never empties");
+                        self.push_text(&mut top.inline, &top.state, text)?;
+                        continue;
+                    }
+                    if let Some(is_image) = self.peek_embedded_payload() {
+                        let start = self.pos;
+                        self.skip_group()?;
+                        let size = embedded_payload_size(&self.tokens[start..self.pos]);
+                        if let Some(text) = self.enforce_embedded_limits(is_image, size)? {
+                            let top = stack.last_mut().expect("group stack never empties");
+                            self.push_text(&mut top.inline, &top.state, text)?;
+                        }
+                        continue;
+                    }
+                    if let Some((content_start, content_end, resume)) = self.peek_raw_passthrough() {
+                        let content = raw_tokens(&self.tokens[content_start..content_end]);
+                        self.pos = resume;
+                        let top = stack.last_mut().expect("group stack never empties");
+                        top.inline.push(RtfNode::RawRtf { content });
+                        // A non-text node between runs is its own boundary;
+                        // it never owes a repaired word-join space.
+                        self.pending_delimiter_space = false;
+                        continue;
+                    }
+                    if let Some(end) = self.peek_image_destination() {
+                        let node = image_from_destination(&self.tokens[self.pos..end]);
+                        self.pos = end;
+                        match node {
+                            Some(node) => {
+                                if let RtfNode::Image { src, alt, .. } = &node {
+                                    if alt.is_empty() {
+                                        self.warnings.push(format!(
+                                            "image '{src}' has no alt text; screen readers \
+                                             will announce nothing for it"
+                                        ));
+                                    }
+                                }
+                                let top = stack.last_mut().expect("group stack never empties");
+                                top.inline.push(node);
+                                self.pending_delimiter_space = false;
+                            }
+                            None => self
+                                .warnings
+                                .push("\\lbimage destination without a source; dropped".to_string()),
+                        }
+                        continue;
+                    }
+                    if self.cross_references {
+                        if let Some((name, is_start, end)) = self.peek_bookmark_destination() {
+                            self.pos = end;
+                            if is_start {
+                                self.bookmarks.push((name, out.len()));
+                            }
+                            continue;
+                        }
+                        if let Some((target, end)) = self.peek_cross_reference_field() {
+                            let text = field_result_text(&self.tokens[self.pos..end])
+                                .unwrap_or_else(|| target.clone());
+                            self.pos = end;
+                            let top = stack.last_mut().expect("group stack never empties");
+                            // A sentinel URL until the whole document is
+                            // parsed; resolution to a heading slug (or to
+                            // a warning) happens after.
+                            top.inline.push(RtfNode::Hyperlink {
+                                url: format!("#bkmk:{target}"),
+                                title: None,
+                                content: vec![RtfNode::Text(text)],
+                            });
+                            self.pending_delimiter_space = false;
+                            continue;
+                        }
+                    }
+                    if let Some(end) = self.peek_hyperlink_field() {
+                        let node = hyperlink_from_field(&self.tokens[self.pos..end]);
+                        let fallback = field_result_text(&self.tokens[self.pos..end]);
+                        self.pos = end;
+                        let top = stack.last_mut().expect("group stack never empties");
+                        match node {
+                            Some(node) => {
+                                top.inline.push(node);
+                                self.pending_delimiter_space = false;
+                            }
+                            None => {
+                                self.warnings.push(
+                                    "HYPERLINK field without a target; keeping its result text"
+                                        .to_string(),
+                                );
+                                if let Some(text) = fallback {
+                                    self.push_text(&mut top.inline, &top.state, text)?;
+                                }
+                            }
+                        }
+                        continue;
+                    }
+                    if self.peek_is_skip_destination() {
+                        self.skip_group()?;
+                        continue;
+                    }
+                    if self.peek_is_info_group() {
+                        self.parse_info_group()?;
+                        continue;
+                    }
+                    // Nested group: formatting changes are scoped to it.
+                    let top = &stack.last().expect("group stack never empties").state;
+                    let child_state = ParseState {
+                        format: top.format.clone(),
+                        outline_level: top.outline_level,
+                        direction: top.direction,
+                        spacing: top.spacing,
+                        in_table_row: top.in_table_row,
+                        alignment: top.alignment,
+                    };
+                    stack.push(GroupFrame {
+                        state: child_state,
+                        inline: Vec::new(),
+                    });
+                }
+                RtfToken::GroupEnd => {
+                    let mut frame = stack.pop().expect("group stack never empties");
+                    match stack.last_mut() {
+                        Some(parent) => parent.inline.append(&mut frame.inline),
+                        None => {
+                            self.flush_inline(&mut frame.inline, &mut frame.state, out);
+                            return Ok(());
+                        }
+                    }
+                }
+                RtfToken::ControlWord { name, parameter, space } => {
+                    // Group-scoped alias rules are dispatched here because
+                    // they close the containing group; run-scoped ones are
+                    // applied by handle_control_word.
+                    match self.extensions.rule(&name).cloned() {
+                        Some(ExtensionRule::IgnoreGroup) => {
+                            self.skip_rest_of_group()?;
+                            let mut frame = stack.pop().expect("group stack never empties");
+                            match stack.last_mut() {
+                                Some(parent) => parent.inline.append(&mut frame.inline),
+                                None => return Ok(()),
+                            }
+                        }
+                        Some(ExtensionRule::CustomDestination { name: destination }) => {
+                            let start = self.pos;
+                            self.skip_rest_of_group()?;
+                            self.metadata.custom_destinations.push(CustomDestination {
+                                name: destination,
+                                text: group_text(&self.tokens[start..self.pos])
+                                    .unwrap_or_default(),
+                            });
+                            let mut frame = stack.pop().expect("group stack never empties");
+                            match stack.last_mut() {
+                                Some(parent) => parent.inline.append(&mut frame.inline),
+                                None => return Ok(()),
+                            }
+                        }
+                        _ => {
+                            let top = stack.last_mut().expect("group stack never empties");
+                            self.handle_control_word(
+                                &name,
+                                parameter,
+                                &mut top.state,
+                                &mut top.inline,
+                                out,
+                            )?;
+                            // A consumed delimiter space after a formatting
+                            // word may have been a word boundary; arm the
+                            // repair in push_text when a word character
+                            // sits to its left in the same group, or carry
+                            // an armed boundary into a formatting group
+                            // that has no text of its own yet. Words that
+                            // emit content or end the run disarm it - their
+                            // space is plain syntax - and so does a later
+                            // space-less toggle, whose own delimiter bounds
+                            // the text instead (the generator leans on this
+                            // with its `{}` terminators).
+                            self.pending_delimiter_space = space
+                                && !emits_or_ends_run(&name)
+                                && (last_inline_char(&top.inline)
+                                    .is_some_and(char::is_alphanumeric)
+                                    || (self.pending_delimiter_space
+                                        && top.inline.is_empty()));
+                        }
+                    }
+                }
+                RtfToken::ControlSymbol(c) => {
+                    if c == '*' {
+                        // `\*` introduces an ignorable destination we don't know.
+                        self.skip_rest_of_group()?;
+                        let mut frame = stack.pop().expect("group stack never empties");
+                        match stack.last_mut() {
+                            Some(parent) => parent.inline.append(&mut frame.inline),
+                            None => return Ok(()),
+                        }
+                    }
+                }
+                RtfToken::Text(text) => {
+                    let top = stack.last_mut().expect("group stack never empties");
+                    self.push_text(&mut top.inline, &top.state, text)?;
+                }
+            }
+        }
+
+        // EOF with groups still open: fold each frame into its parent, then
+        // flush the top-level one.
+        while let Some(mut frame) = stack.pop() {
+            match stack.last_mut() {
+                Some(parent) => parent.inline.append(&mut frame.inline),
+                None => self.flush_inline(&mut frame.inline, &mut frame.state, out),
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_control_word(
+        &mut self,
+        name: &str,
+        parameter: Option<i32>,
+        state: &mut ParseState,
+        inline: &mut Vec<RtfNode>,
+        out: &mut Vec<RtfNode>,
+    ) -> Result<(), String> {
+        match name {
+            "b" => state.format.bold = parameter != Some(0),
+            "i" => state.format.italic = parameter != Some(0),
+            "ul" => state.format.underline = parameter != Some(0),
+            "ulnone" => state.format.underline = false,
+            "strike" => state.format.strikethrough = parameter != Some(0),
+            // Sizes and table indexes are clamped to spec-reasonable
+            // ranges; a clamp is recorded as a warning, not an error.
+            "fs" => state.format.font_size = parameter.map(|p| self.clamp_parameter("fs", p, 2, 3276)),
+            "f" => state.format.font_index = parameter.map(|p| self.clamp_parameter("f", p, 0, 4095)),
+            "cf" => state.format.color_index = parameter.map(|p| self.clamp_parameter("cf", p, 0, 4095)),
+            "cs" => {
+                state.format.style_name = None;
+                if let Some(style) = parameter
+                    .and_then(|p| self.styles.iter().find(|s| s.index == p))
+                {
+                    styles::apply_style(&mut state.format, &style.format);
+                    if self.record_style_names {
+                        state.format.style_name = Some(style.name.clone());
+                    }
+                }
+            }
+            "plain" => state.format = TextFormat::default(),
+            "outlinelevel" => {
+                state.outline_level = parameter.map(|p| (p.clamp(0, 5) + 1) as u8);
+            }
+            // Document default language; per-run `\langN` overrides are
+            // not modeled, so mixed-language documents keep the default.
+            "deflang" => match parameter.and_then(language::tag_for_lcid) {
+                Some(tag) => self.metadata.language = Some(tag.to_string()),
+                None => self.warnings.push(format!(
+                    "\\deflang{} is not a known LCID; no document language recorded",
+                    parameter.unwrap_or(0)
+                )),
+            },
+            "pard" => {
+                state.outline_level = None;
+                state.direction = Direction::default();
+                state.spacing = ParagraphSpacing::default();
+                state.in_table_row = false;
+                state.alignment = CellAlignment::default();
+            }
+            "ql" => state.alignment = CellAlignment::Left,
+            "qc" => state.alignment = CellAlignment::Center,
+            "qr" => state.alignment = CellAlignment::Right,
+            "sb" => {
+                state.spacing.space_before =
+                    parameter.map(|p| self.clamp_parameter("sb", p, 0, 32760));
+            }
+            "sa" => {
+                state.spacing.space_after =
+                    parameter.map(|p| self.clamp_parameter("sa", p, 0, 32760));
+            }
+            // Negative \sl means an exact line height; positive is "at
+            // least", or a multiple when \slmult1 follows.
+            "sl" => {
+                state.spacing.line_spacing =
+                    parameter.map(|p| self.clamp_parameter("sl", p, -32760, 32760));
+            }
+            "slmult" => state.spacing.line_spacing_multiple = parameter == Some(1),
+            // Toggle control words: no parameter or a nonzero one turns
+            // the property on, \pagebb0 turns it off.
+            "pagebb" => state.spacing.page_break_before = parameter != Some(0),
+            "keepn" => state.spacing.keep_with_next = parameter != Some(0),
+            // Keep-lines-together has no slot in the model; the generator
+            // re-emits it on table cells per its own configuration.
+            "keep" => {}
+            "rtlpar" => state.direction = Direction::RightToLeft,
+            "ltrpar" => state.direction = Direction::LeftToRight,
+            "rtlch" => state.format.direction = Some(Direction::RightToLeft),
+            "ltrch" => state.format.direction = Some(Direction::LeftToRight),
+            "revised" => {
+                state.format.revision = (parameter != Some(0)).then(Revision::default);
+            }
+            "deleted" => {
+                state.format.revision = (parameter != Some(0)).then(|| Revision {
+                    kind: RevisionKind::Deleted,
+                    ..Default::default()
+                });
+            }
+            // Author and timestamp follow the toggle in Word's output, so
+            // they amend the revision opened on this run.
+            "revauth" | "revauthdel" => {
+                if let (Some(p), Some(revision)) = (parameter, &mut state.format.revision) {
+                    revision.author = usize::try_from(p)
+                        .ok()
+                        .and_then(|i| self.revision_authors.get(i).cloned());
+                }
+            }
+            "revdttm" | "revdttmdel" => {
+                if let (Some(p), Some(revision)) = (parameter, &mut state.format.revision) {
+                    revision.date = decode_dttm(p);
+                }
+            }
+            "par" => {
+                // A \par with nothing pending continues a run of empty
+                // paragraphs; only the first \par of the run ended real
+                // content.
+                if inline.is_empty() && self.keep_empty_paragraphs && !state.in_table_row {
+                    self.flush_table(out);
+                    out.push(RtfNode::Paragraph {
+                        direction: state.direction,
+                        spacing: state.spacing,
+                        content: Vec::new(),
+                    });
+                } else {
+                    self.flush_inline(inline, state, out);
+                }
+            }
+            "line" => inline.push(RtfNode::LineBreak),
+            // A section boundary is a page boundary for our purposes.
+            "page" | "sect" => {
+                self.flush_inline(inline, state, out);
+                self.flush_table(out);
+                out.push(RtfNode::PageBreak);
+            }
+            "trowd" => {
+                state.in_table_row = true;
+                self.row_merges.clear();
+                self.row_bounds.clear();
+                self.current_merge = (CellMerge::None, CellMerge::None);
+            }
+            "intbl" => state.in_table_row = true,
+            // Merge roles sit in the row header, attached to the \cellx
+            // that closes each cell definition.
+            "clmgf" => self.current_merge.0 = CellMerge::First,
+            "clmrg" => self.current_merge.0 = CellMerge::Merged,
+            "clvmgf" => self.current_merge.1 = CellMerge::First,
+            "clvmrg" => self.current_merge.1 = CellMerge::Merged,
+            "cellx" => {
+                self.row_merges.push(self.current_merge);
+                self.current_merge = (CellMerge::None, CellMerge::None);
+                if let Some(p) = parameter {
+                    let bound = self.clamp_parameter("cellx", p, 0, 32760);
+                    self.row_bounds.push(bound);
+                }
+            }
+            "cell" => {
+                let content = std::mem::take(inline);
+                let (h_merge, v_merge) = self
+                    .row_merges
+                    .get(self.pending_row.len())
+                    .copied()
+                    .unwrap_or_default();
+                self.pending_row.push(TableCell {
+                    content,
+                    h_merge,
+                    v_merge,
+                    alignment: state.alignment,
+                });
+            }
+            "row" => {
+                let cells = std::mem::take(&mut self.pending_row);
+                // Cloned, not taken: a writer may reuse one row header
+                // for every row instead of re-declaring it.
+                self.pending_table.push(TableRow {
+                    cells,
+                    col_bounds: self.row_bounds.clone(),
+                });
+                state.in_table_row = false;
+            }
+            "u" => {
+                // \uN - Unicode code point; following fallback char is skipped
+                // by the lexer's delimiter handling in most writers.
+                if let Some(p) = parameter {
+                    let code = if p < 0 { (p + 65536) as u32 } else { p as u32 };
+                    if let Some(c) = char::from_u32(code) {
+                        self.push_text(inline, state, c.to_string())?;
+                        self.swallow_unicode_fallback(inline);
+                    }
+                }
+            }
+            "tab" => self.push_text(inline, state, "\t".to_string())?,
+            "bullet" => self.push_text(inline, state, "\u{2022}".to_string())?,
+            "endash" => self.push_text(inline, state, "\u{2013}".to_string())?,
+            "emdash" => self.push_text(inline, state, "\u{2014}".to_string())?,
+            "lquote" => self.push_text(inline, state, "\u{2018}".to_string())?,
+            "rquote" => self.push_text(inline, state, "\u{2019}".to_string())?,
+            "ldblquote" => self.push_text(inline, state, "\u{201c}".to_string())?,
+            "rdblquote" => self.push_text(inline, state, "\u{201d}".to_string())?,
+            _ => match self.extensions.rule(name).cloned() {
+                Some(ExtensionRule::BoldOn) => state.format.bold = true,
+                Some(ExtensionRule::BoldOff) => state.format.bold = false,
+                Some(ExtensionRule::ParagraphBreak) => self.flush_inline(inline, state, out),
+                // Group-scoped rules were dispatched by the parse loop.
+                _ => {
+                    // Unknown control words are ignored; their groups still
+                    // parse. Known-unsupported ones are tallied for the
+                    // degradation report.
+                    self.metadata.feature_usage.record(name);
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Clamp a control word parameter, recording a warning when it was out
+    /// of range.
+    fn clamp_parameter(&mut self, name: &str, value: i32, min: i32, max: i32) -> i32 {
+        let clamped = self.validator.validate_number(value, min, max);
+        if clamped != value {
+            self.warnings
+                .push(format!("\\{name}{value} out of range; clamped to {clamped}"));
+        }
+        clamped
+    }
+
+    /// After `\uN` the next text token usually starts with the legacy
+    /// fallback character, which must not be emitted twice.
+    fn swallow_unicode_fallback(&mut self, _inline: &mut [RtfNode]) {
+        if let Some(RtfToken::Text(t)) = self.tokens.get_mut(self.pos) {
+            if t.starts_with('?') {
+                t.remove(0);
+                if t.is_empty() {
+                    self.pos += 1;
+                }
+            }
+        }
+    }
+
+    fn push_text(
+        &mut self,
+        inline: &mut Vec<RtfNode>,
+        state: &ParseState,
+        mut text: String,
+    ) -> Result<(), String> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        // Word-join repair: a delimiter space consumed between two word
+        // characters was the only space the writer put between the runs,
+        // so put it back. Punctuation adjacency (`\b0 !`) stays tight -
+        // there the space really was just a delimiter.
+        if std::mem::take(&mut self.pending_delimiter_space)
+            && text.chars().next().is_some_and(char::is_alphanumeric)
+        {
+            text.insert(0, ' ');
+        }
+        for (_, captured) in &mut self.open_anchors {
+            captured.push_str(&text);
+        }
+        let node = RtfNode::Text(text);
+        if state.format.is_plain() {
+            inline.push(node);
+        } else {
+            // Merge into the previous run when the formatting is identical,
+            // so toggle floods (\b0\b...) coalesce instead of growing the
+            // tree one run per toggle.
+            if let Some(RtfNode::Formatted { format, content }) = inline.last_mut() {
+                if *format == state.format {
+                    content.push(node);
+                    return Ok(());
+                }
+            }
+            self.format_runs += 1;
+            if self.format_runs > self.max_format_runs {
+                return Err(format!(
+                    "format run limit exceeded ({} runs)",
+                    self.max_format_runs
+                ));
+            }
+            inline.push(RtfNode::Formatted {
+                format: state.format.clone(),
+                content: vec![node],
+            });
+        }
+        Ok(())
+    }
+
+    /// Turn accumulated inline content into a block node on `out`.
+    fn flush_inline(
+        &mut self,
+        inline: &mut Vec<RtfNode>,
+        state: &mut ParseState,
+        out: &mut Vec<RtfNode>,
+    ) {
+        if state.in_table_row {
+            // Cell content is flushed by \cell; a bare \par inside a cell
+            // becomes a line break in that cell.
+            if !inline.is_empty() {
+                inline.push(RtfNode::LineBreak);
+            }
+            return;
+        }
+        self.flush_table(out);
+        let content = std::mem::take(inline);
+        if content.is_empty() {
+            return;
+        }
+        match state.outline_level {
+            Some(level) => out.push(RtfNode::Heading {
+                level,
+                spacing: state.spacing,
+                content,
+            }),
+            None => out.push(RtfNode::Paragraph {
+                direction: state.direction,
+                spacing: state.spacing,
+                content,
+            }),
+        }
+    }
+
+    fn flush_table(&mut self, out: &mut Vec<RtfNode>) {
+        if !self.pending_table.is_empty() {
+            let rows = std::mem::take(&mut self.pending_table);
+            out.push(RtfNode::Table(Table { rows }));
+        }
+    }
+
+    /// A `{\field ...}` group whose instruction is a `HYPERLINK`, with the
+    /// `GroupStart` already consumed. Returns the token index just past
+    /// the group's matching `GroupEnd`. Other fields are left to normal
+    /// parsing, which keeps their `\fldrslt` result text in the flow.
+    fn peek_hyperlink_field(&self) -> Option<usize> {
+        match self.tokens.get(self.pos) {
+            Some(RtfToken::ControlWord { name, .. }) if name == "field" => {}
+            _ => return None,
+        }
+        let mut depth = 1usize;
+        let mut end = None;
+        for (offset, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(self.pos + offset + 1);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Unterminated field groups fall through to normal parsing, which
+        // already copes with truncation.
+        let end = end?;
+        let instruction = field_instruction(&self.tokens[self.pos..end])?;
+        instruction.trim_start().starts_with("HYPERLINK").then_some(end)
+    }
+
+    /// A `{\*\bkmkstart name}` or `{\*\bkmkend name}` destination (the
+    /// `GroupStart` already consumed): the bookmark name, whether it is
+    /// the range start, and the token index just past the matching
+    /// `GroupEnd`. Only consulted in cross-reference mode; otherwise the
+    /// groups skip as unknown `\*` destinations, as before.
+    fn peek_bookmark_destination(&self) -> Option<(String, bool, usize)> {
+        let is_start = match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+            (
+                Some(RtfToken::ControlSymbol('*')),
+                Some(RtfToken::ControlWord { name, .. }),
+            ) => match name.as_str() {
+                "bkmkstart" => true,
+                "bkmkend" => false,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        let mut depth = 1usize;
+        for (offset, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let end = self.pos + offset + 1;
+                        let name = subgroup_text(&self.tokens[self.pos..end])?;
+                        return Some((name, is_start, end));
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Unterminated destinations fall through to normal parsing.
+        None
+    }
+
+    /// A `{\field ...}` group whose instruction is an allowlisted
+    /// cross-reference (`REF`/`PAGEREF`), with the `GroupStart` already
+    /// consumed: the target bookmark name and the token index just past
+    /// the matching `GroupEnd`. Only consulted in cross-reference mode.
+    fn peek_cross_reference_field(&self) -> Option<(String, usize)> {
+        match self.tokens.get(self.pos) {
+            Some(RtfToken::ControlWord { name, .. }) if name == "field" => {}
+            _ => return None,
+        }
+        let mut depth = 1usize;
+        let mut end = None;
+        for (offset, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(self.pos + offset + 1);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let end = end?;
+        let instruction = field_instruction(&self.tokens[self.pos..end])?;
+        let target = cross_references::reference_target(&instruction)?;
+        Some((target, end))
+    }
+
+    /// A `{\*\lbimage ...}` image-reference destination (the `GroupStart`
+    /// already consumed). Returns the token index just past the group's
+    /// matching `GroupEnd`. Readers that predate the destination skip it
+    /// as an unknown `\*` group.
+    fn peek_image_destination(&self) -> Option<usize> {
+        match (self.tokens.get(self.pos), self.tokens.get(self.pos + 1)) {
+            (
+                Some(RtfToken::ControlSymbol('*')),
+                Some(RtfToken::ControlWord { name, .. }),
+            ) if name == "lbimage" => {}
+            _ => return None,
+        }
+        let mut depth = 1usize;
+        for (offset, token) in self.tokens[self.pos..].iter().enumerate() {
+            match token {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(self.pos + offset + 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Unterminated destinations fall through to normal parsing.
+        None
+    }
+
+    /// A `{\*\lbrawstart}` region marker (the `GroupStart` already
+    /// consumed), paired with a later `{\*\lbrawend}` at the same
+    /// nesting level. Returns `(content_start, content_end, resume)`
+    /// token indices: the span between the markers and the position just
+    /// past the end marker's `GroupEnd`. Markers without a partner fall
+    /// through to normal parsing, which skips them as unknown `\*`
+    /// groups.
+    fn peek_raw_passthrough(&self) -> Option<(usize, usize, usize)> {
+        match (
+            self.tokens.get(self.pos),
+            self.tokens.get(self.pos + 1),
+            self.tokens.get(self.pos + 2),
+        ) {
+            (
+                Some(RtfToken::ControlSymbol('*')),
+                Some(RtfToken::ControlWord { name, .. }),
+                Some(RtfToken::GroupEnd),
+            ) if name == "lbrawstart" => {}
+            _ => return None,
+        }
+        let content_start = self.pos + 3;
+        let mut depth = 0usize;
+        let mut i = content_start;
+        while i < self.tokens.len() {
+            match &self.tokens[i] {
+                RtfToken::GroupStart => {
+                    if depth == 0 {
+                        let is_end = matches!(
+                            (self.tokens.get(i + 1), self.tokens.get(i + 2), self.tokens.get(i + 3)),
+                            (
+                                Some(RtfToken::ControlSymbol('*')),
+                                Some(RtfToken::ControlWord { name, .. }),
+                                Some(RtfToken::GroupEnd),
+                            ) if name == "lbrawend"
+                        );
+                        if is_end {
+                            return Some((content_start, i, i + 4));
+                        }
+                    }
+                    depth += 1;
+                }
+                RtfToken::GroupEnd => {
+                    if depth == 0 {
+                        // The enclosing group closed before the end
+                        // marker; the region is unterminated.
+                        return None;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        None
+    }
+
+    fn peek_is_skip_destination(&self) -> bool {
+        let mut pos = self.pos;
+        // Allow `\*` before the destination word.
+        if let Some(RtfToken::ControlSymbol('*')) = self.tokens.get(pos) {
+            pos += 1;
+        }
+        match self.tokens.get(pos) {
+            Some(RtfToken::ControlWord { name, .. }) => {
+                SKIP_DESTINATIONS.contains(&name.as_str())
+            }
+            _ => false,
+        }
+    }
+
+    /// An embedded `\pict`/`\object` payload group, returning whether it
+    /// is an image. These are skip destinations too, but their payload
+    /// sizes are metered against the embedded-object limits.
+    fn peek_embedded_payload(&self) -> Option<bool> {
+        let mut pos = self.pos;
+        if let Some(RtfToken::ControlSymbol('*')) = self.tokens.get(pos) {
+            pos += 1;
+        }
+        match self.tokens.get(pos) {
+            Some(RtfToken::ControlWord { name, .. }) => match name.as_str() {
+                "pict" => Some(true),
+                "object" => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Apply the embedded payload caps to one skipped `\pict`/`\object`
+    /// group of `size` decoded bytes. Returns the placeholder text to
+    /// leave in the flow when the payload was dropped for a violation.
+    fn enforce_embedded_limits(
+        &mut self,
+        is_image: bool,
+        size: usize,
+    ) -> Result<Option<String>, String> {
+        if is_image {
+            self.image_count += 1;
+        }
+        let what = if is_image { "image" } else { "object" };
+        let violation = if size > self.max_embedded_object_size {
+            Some(format!(
+                "embedded {what} of {size} bytes exceeds the per-object limit of {} bytes",
+                self.max_embedded_object_size
+            ))
+        } else if self.embedded_total + size > self.max_total_embedded_size {
+            Some(format!(
+                "embedded payloads exceed the total limit of {} bytes",
+                self.max_total_embedded_size
+            ))
+        } else if is_image && self.image_count > self.max_image_count {
+            Some(format!(
+                "document exceeds the limit of {} embedded images",
+                self.max_image_count
+            ))
+        } else {
+            None
+        };
+        let Some(message) = violation else {
+            self.embedded_total += size;
+            return Ok(None);
+        };
+        match self.sanitization {
+            SanitizationMode::Reject => Err(message),
+            SanitizationMode::Placeholder => {
+                self.warnings.push(message);
+                Ok(Some(format!("[{what} omitted]")))
+            }
+        }
+    }
+
+    fn peek_is_fonttbl_group(&self) -> bool {
+        matches!(
+            self.tokens.get(self.pos),
+            Some(RtfToken::ControlWord { name, .. }) if name == "fonttbl"
+        )
+    }
+
+    /// Parse the `\fonttbl` destination, applying the [`FontMap`] and
+    /// recording any substitutions in the document metadata.
+    fn parse_font_group(&mut self) -> Result<(), String> {
+        let start = self.pos;
+        self.skip_group()?;
+        let mut fonts = font_map::parse_fonttbl(&self.tokens[start..self.pos]);
+        for entry in &mut fonts {
+            if let Some(replacement) = self.font_map.substitute(&entry.name, entry.family) {
+                self.metadata.font_substitutions.push(FontSubstitution {
+                    index: entry.index,
+                    original: std::mem::take(&mut entry.name),
+                    replacement: replacement.to_string(),
+                });
+                entry.name = replacement.to_string();
+            }
+        }
+        self.fonts = fonts;
+        Ok(())
+    }
+
+    fn peek_is_colortbl_group(&self) -> bool {
+        matches!(
+            self.tokens.get(self.pos),
+            Some(RtfToken::ControlWord { name, .. }) if name == "colortbl"
+        )
+    }
+
+    /// Parse the `\colortbl` destination into the document color table.
+    fn parse_color_group(&mut self) -> Result<(), String> {
+        let start = self.pos;
+        self.skip_group()?;
+        self.colors = color::parse_colortbl(&self.tokens[start..self.pos]);
+        Ok(())
+    }
+
+    fn peek_is_stylesheet_group(&self) -> bool {
+        matches!(
+            self.tokens.get(self.pos),
+            Some(RtfToken::ControlWord { name, .. }) if name == "stylesheet"
+        )
+    }
+
+    /// Parse the `\stylesheet` destination into the character style table.
+    fn parse_stylesheet_group(&mut self) -> Result<(), String> {
+        let start = self.pos;
+        self.skip_group()?;
+        self.styles = styles::parse_stylesheet(&self.tokens[start..self.pos]);
+        Ok(())
+    }
+
+    /// A `\field` group carrying a `\formfield` destination; ordinary
+    /// fields (hyperlinks, page numbers) are left to the normal walk.
+    fn peek_is_form_field_group(&self) -> bool {
+        let mut pos = self.pos;
+        if !matches!(
+            self.tokens.get(pos),
+            Some(RtfToken::ControlWord { name, .. }) if name == "field"
+        ) {
+            return false;
+        }
+        let mut depth = 1usize;
+        while let Some(token) = self.tokens.get(pos) {
+            match token {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return false;
+                    }
+                }
+                RtfToken::ControlWord { name, .. } if name == "formfield" => return true,
+                _ => {}
+            }
+            pos += 1;
+        }
+        false
+    }
+
+    fn peek_is_revtbl_group(&self) -> bool {
+        let mut pos = self.pos;
+        // Word writes the table as `{\*\revtbl ...}`.
+        if let Some(RtfToken::ControlSymbol('*')) = self.tokens.get(pos) {
+            pos += 1;
+        }
+        matches!(
+            self.tokens.get(pos),
+            Some(RtfToken::ControlWord { name, .. }) if name == "revtbl"
+        )
+    }
+
+    /// Parse the `\revtbl` destination into the reviewer name table. Each
+    /// nested group holds one `;`-terminated author name; `\revauthN`
+    /// indexes the table (entry 0 is Word's reserved "Unknown").
+    fn parse_revtbl_group(&mut self) -> Result<(), String> {
+        let start = self.pos;
+        self.skip_group()?;
+        for token in &self.tokens[start..self.pos] {
+            if let RtfToken::Text(text) = token {
+                let name = text.trim_end().trim_end_matches(';').trim();
+                if !name.is_empty() {
+                    self.revision_authors.push(name.to_string());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// One of the `\*`-prefixed annotation destinations handled inline:
+    /// the reviewer marks, anchor range delimiters and the comment body.
+    fn peek_annotation_destination(&self) -> Option<&'static str> {
+        let mut pos = self.pos;
+        if let Some(RtfToken::ControlSymbol('*')) = self.tokens.get(pos) {
+            pos += 1;
+        }
+        match self.tokens.get(pos) {
+            Some(RtfToken::ControlWord { name, .. }) => match name.as_str() {
+                "atnid" => Some("atnid"),
+                "atnauthor" => Some("atnauthor"),
+                "atrfstart" => Some("atrfstart"),
+                "atrfend" => Some("atrfend"),
+                "annotation" => Some("annotation"),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Fold one annotation destination group (already skipped over,
+    /// spanning `start..self.pos`) into the annotation state. Returns the
+    /// inline marker to leave at the anchor, when one is due.
+    fn handle_annotation_group(&mut self, destination: &str, start: usize) -> Option<String> {
+        match destination {
+            "atnid" => self.pending_annotation.0 = group_text(&self.tokens[start..self.pos]),
+            "atnauthor" => self.pending_annotation.1 = group_text(&self.tokens[start..self.pos]),
+            "atrfstart" => {
+                if let Some(id) = group_parameter(&self.tokens[start..self.pos], "atrfstart") {
+                    self.open_anchors.push((id, String::new()));
+                }
+            }
+            "atrfend" => {
+                if let Some(id) = group_parameter(&self.tokens[start..self.pos], "atrfend") {
+                    if let Some(i) = self.open_anchors.iter().position(|(open, _)| *open == id) {
+                        let (id, text) = self.open_anchors.remove(i);
+                        self.closed_anchors.insert(id, text);
+                    }
+                }
+            }
+            "annotation" => {
+                let text = group_text(&self.tokens[start..self.pos]).unwrap_or_default();
+                let anchor = group_parameter(&self.tokens[start..self.pos], "atnref")
+                    .and_then(|id| self.closed_anchors.remove(&id));
+                let (initials, author) = std::mem::take(&mut self.pending_annotation);
+                let index = self.metadata.annotations.len() + 1;
+                self.metadata.annotations.push(Annotation {
+                    index,
+                    initials,
+                    author,
+                    anchor,
+                    text,
+                });
+                return self.annotation_markers.then(|| format!("[note {index}]"));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// An equation or drawing object group due for a placeholder, returning
+    /// the placeholder text.
+    fn peek_placeholder_group(&self) -> Option<String> {
+        let mut pos = self.pos;
+        if let Some(RtfToken::ControlSymbol('*')) = self.tokens.get(pos) {
+            pos += 1;
+        }
+        match self.tokens.get(pos) {
+            Some(RtfToken::ControlWord { name, .. }) => match name.as_str() {
+                "mmath" => Some(self.placeholders.equation.clone()),
+                "do" => Some(self.placeholders.drawing.clone()),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn peek_is_info_group(&self) -> bool {
+        matches!(
+            self.tokens.get(self.pos),
+            Some(RtfToken::ControlWord { name, .. }) if name == "info"
+        )
+    }
+
+    /// Skip a group whose `GroupStart` has already been consumed. Skipped
+    /// destinations are exactly where unsupported features live, so their
+    /// control words are still tallied for the degradation report.
+    fn skip_group(&mut self) -> Result<(), String> {
+        let mut depth = 1usize;
+        while self.pos < self.tokens.len() {
+            match self.tokens[self.pos] {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                }
+                RtfToken::ControlWord { ref name, .. } => {
+                    self.metadata.feature_usage.record(name);
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        if self.tolerant {
+            self.warnings
+                .push("unterminated group at end of input; closing".to_string());
+            return Ok(());
+        }
+        Err("unterminated group".to_string())
+    }
+
+    /// Skip to the end of the current group (its `GroupStart` was consumed by
+    /// the caller).
+    fn skip_rest_of_group(&mut self) -> Result<(), String> {
+        let mut depth = 1usize;
+        while self.pos < self.tokens.len() {
+            match self.tokens[self.pos] {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                }
+                RtfToken::ControlWord { ref name, .. } => {
+                    self.metadata.feature_usage.record(name);
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+        Ok(())
+    }
+
+    /// Harvest `\title`/`\author`/`\subject` out of the `\info` group.
+    fn parse_info_group(&mut self) -> Result<(), String> {
+        let mut depth = 1usize;
+        let mut current: Option<&'static str> = None;
+        while self.pos < self.tokens.len() {
+            let token = self.tokens[self.pos].clone();
+            self.pos += 1;
+            match token {
+                RtfToken::GroupStart => depth += 1,
+                RtfToken::GroupEnd => {
+                    depth -= 1;
+                    current = None;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                RtfToken::ControlWord { name, .. } => {
+                    current = match name.as_str() {
+                        "title" => Some("title"),
+                        "author" => Some("author"),
+                        "subject" => Some("subject"),
+                        _ => None,
+                    };
+                }
+                RtfToken::Text(text) => match current {
+                    Some("title") => self.metadata.title = Some(text),
+                    Some("author") => self.metadata.author = Some(text),
+                    Some("subject") => self.metadata.subject = Some(text),
+                    _ => {}
+                },
+                RtfToken::ControlSymbol(_) => {}
+            }
+        }
+        if self.tolerant {
+            self.warnings
+                .push("unterminated info group at end of input".to_string());
+            return Ok(());
+        }
+        Err("unterminated info group".to_string())
+    }
+}
+
+/// Re-render a skipped group's tokens (its `GroupStart` already consumed)
+/// as RTF source, for `keep_raw` comments. Control words keep their
+/// consumed delimiter space, so the result is valid if not byte-identical.
+/// Rewrite `{\upr{ansi}{\*\ud {unicode}}}` fallback pairs down to the
+/// chosen branch, unwrapping the wrapper group so the kept tokens parse
+/// in place with their formatting intact. Readers must pick exactly one
+/// branch; Word writes the ANSI one for pre-Unicode readers and the
+/// `\*\ud` destination for everyone else. Malformed pairs (no `\ud`
+/// subgroup, unbalanced braces) are left untouched for the normal
+/// destination handling.
+fn resolve_upr_alternates(tokens: &[RtfToken], prefer_unicode: bool) -> Vec<RtfToken> {
+    let mut out = Vec::with_capacity(tokens.len());
+    let mut i = 0;
+    while i < tokens.len() {
+        if matches!(tokens[i], RtfToken::GroupStart) {
+            let mut word = i + 1;
+            if matches!(tokens.get(word), Some(RtfToken::ControlSymbol('*'))) {
+                word += 1;
+            }
+            let is_upr = matches!(
+                tokens.get(word),
+                Some(RtfToken::ControlWord { name, .. }) if name == "upr"
+            );
+            if is_upr {
+                if let Some(end) = matching_group_end(tokens, i) {
+                    let base = word + 1;
+                    if let Some((ud_start, ud_end)) = find_ud_subgroup(&tokens[base..end]) {
+                        if prefer_unicode {
+                            // Content of the `\*\ud` destination, minus
+                            // its wrapper braces and keyword.
+                            let mut body = base + ud_start + 1;
+                            if matches!(tokens.get(body), Some(RtfToken::ControlSymbol('*'))) {
+                                body += 1;
+                            }
+                            body += 1; // the `ud` control word itself
+                            out.extend(resolve_upr_alternates(
+                                &tokens[body..base + ud_end],
+                                prefer_unicode,
+                            ));
+                        } else {
+                            out.extend(resolve_upr_alternates(
+                                &tokens[base..base + ud_start],
+                                prefer_unicode,
+                            ));
+                            out.extend(resolve_upr_alternates(
+                                &tokens[base + ud_end + 1..end],
+                                prefer_unicode,
+                            ));
+                        }
+                        i = end + 1;
+                        continue;
+                    }
+                }
+            }
+        }
+        out.push(tokens[i].clone());
+        i += 1;
+    }
+    out
+}
+
+/// Index of the `GroupEnd` matching the `GroupStart` at `start`.
+fn matching_group_end(tokens: &[RtfToken], start: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, token) in tokens.iter().enumerate().skip(start) {
+        match token {
+            RtfToken::GroupStart => depth += 1,
+            RtfToken::GroupEnd => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// First immediate `{\*\ud ...}` subgroup in a `\upr` body, as
+/// (group start, group end) indices relative to the slice.
+fn find_ud_subgroup(tokens: &[RtfToken]) -> Option<(usize, usize)> {
+    let mut depth = 0usize;
+    for i in 0..tokens.len() {
+        match &tokens[i] {
+            RtfToken::GroupStart => {
+                if depth == 0 {
+                    let mut word = i + 1;
+                    if matches!(tokens.get(word), Some(RtfToken::ControlSymbol('*'))) {
+                        word += 1;
+                    }
+                    let is_ud = matches!(
+                        tokens.get(word),
+                        Some(RtfToken::ControlWord { name, .. }) if name == "ud"
+                    );
+                    if is_ud {
+                        return matching_group_end(tokens, i).map(|end| (i, end));
+                    }
+                }
+                depth += 1;
+            }
+            RtfToken::GroupEnd => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Decoded byte size of an embedded payload group: hex data contributes
+/// half a byte per digit and `\binN` its parameter directly.
+fn embedded_payload_size(tokens: &[RtfToken]) -> usize {
+    let mut hex_digits = 0usize;
+    let mut bin_bytes = 0usize;
+    for token in tokens {
+        match token {
+            RtfToken::Text(text) => {
+                hex_digits += text.chars().filter(|c| c.is_ascii_hexdigit()).count();
+            }
+            RtfToken::ControlWord { name, parameter, .. } if name == "bin" => {
+                bin_bytes += parameter.unwrap_or(0).max(0) as usize;
+            }
+            _ => {}
+        }
+    }
+    hex_digits / 2 + bin_bytes
+}
+
+fn raw_rtf(tokens: &[RtfToken]) -> String {
+    let mut out = String::from("{");
+    out.push_str(&raw_tokens(tokens));
+    out
+}
+
+/// Re-serialize a token span as RTF source. Control words re-emit the
+/// delimiter space the lexer consumed, so a second pass re-lexes to the
+/// same tokens; `Text` never holds `\{}` because the lexer splits on
+/// them.
+fn raw_tokens(tokens: &[RtfToken]) -> String {
+    let mut out = String::new();
+    for token in tokens {
+        match token {
+            RtfToken::GroupStart => out.push('{'),
+            RtfToken::GroupEnd => out.push('}'),
+            RtfToken::ControlWord { name, parameter, space } => {
+                out.push('\\');
+                out.push_str(name);
+                if let Some(p) = parameter {
+                    out.push_str(&p.to_string());
+                }
+                if *space {
+                    out.push(' ');
+                }
+            }
+            RtfToken::ControlSymbol(c) => {
+                out.push('\\');
+                out.push(*c);
+            }
+            RtfToken::Text(text) => out.push_str(text),
+        }
+    }
+    out
+}
+
+/// Concatenated direct text of a destination group (its `GroupStart`
+/// already consumed), trimmed; text inside nested sub-destinations is not
+/// part of it. `None` when the group carries no text.
+/// Build a hyperlink node from a field group's tokens (starting at the
+/// `\field` control word), or `None` when the instruction has no target.
+/// The `\fldrslt` result is flattened to its plain text; Word's link
+/// styling (`\ul\cf`) is presentation, not content.
+fn hyperlink_from_field(tokens: &[RtfToken]) -> Option<RtfNode> {
+    let instruction = field_instruction(tokens)?;
+    let (url, title) = parse_hyperlink_instruction(&instruction)?;
+    let text = field_result_text(tokens).unwrap_or_else(|| url.clone());
+    Some(RtfNode::Hyperlink {
+        url,
+        title,
+        content: vec![RtfNode::Text(text)],
+    })
+}
+
+/// Build an image node from a `{\*\lbimage ...}` destination's tokens
+/// (starting at the `\*`), or `None` when it has no source. The metadata
+/// lives in nested `\lbimgsrc`/`\lbimgalt`/`\lbimgtitle` subgroups rather
+/// than quoted arguments, so alt text with quotes needs no escaping
+/// beyond the ordinary RTF specials.
+fn image_from_destination(tokens: &[RtfToken]) -> Option<RtfNode> {
+    let subgroup = |name| {
+        let (start, end) = field_subgroup(tokens, name)?;
+        subgroup_text(&tokens[start..end])
+    };
+    Some(RtfNode::Image {
+        src: subgroup("lbimgsrc")?,
+        alt: subgroup("lbimgalt").unwrap_or_default(),
+        title: subgroup("lbimgtitle"),
+    })
+}
+
+/// Text of the `{\*\fldinst ...}` subgroup of a field group's tokens.
+/// Word wraps the instruction in a further group, so text is collected
+/// at any depth within the subgroup.
+fn field_instruction(tokens: &[RtfToken]) -> Option<String> {
+    let (start, end) = field_subgroup(tokens, "fldinst")?;
+    subgroup_text(&tokens[start..end])
+}
+
+/// Text of the `{\fldrslt ...}` subgroup of a field group's tokens,
+/// flattened across any formatting groups inside it.
+fn field_result_text(tokens: &[RtfToken]) -> Option<String> {
+    let (start, end) = field_subgroup(tokens, "fldrslt")?;
+    subgroup_text(&tokens[start..end])
+}
+
+/// All text in an already-bounded token range, regardless of nesting.
+fn subgroup_text(tokens: &[RtfToken]) -> Option<String> {
+    let mut text = String::new();
+    for token in tokens {
+        if let RtfToken::Text(t) = token {
+            text.push_str(t);
+        }
+    }
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Content token range of the first immediate subgroup introduced by
+/// `name` (allowing a leading `\*`), exclusive of its `GroupEnd`.
+fn field_subgroup(tokens: &[RtfToken], name: &str) -> Option<(usize, usize)> {
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            RtfToken::GroupStart => {
+                depth += 1;
+                if depth == 1 {
+                    let mut j = i + 1;
+                    if matches!(tokens.get(j), Some(RtfToken::ControlSymbol('*'))) {
+                        j += 1;
+                    }
+                    if matches!(
+                        tokens.get(j),
+                        Some(RtfToken::ControlWord { name: n, .. }) if n == name
+                    ) {
+                        let start = j + 1;
+                        let mut inner = 1usize;
+                        let mut k = start;
+                        while k < tokens.len() && inner > 0 {
+                            match tokens[k] {
+                                RtfToken::GroupStart => inner += 1,
+                                RtfToken::GroupEnd => inner -= 1,
+                                _ => {}
+                            }
+                            k += 1;
+                        }
+                        return Some((start, k.saturating_sub(1)));
+                    }
+                }
+            }
+            RtfToken::GroupEnd => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Target and `\o` tooltip of a `HYPERLINK` field instruction; arguments
+/// are quoted or bare, other switches are skipped with their argument.
+fn parse_hyperlink_instruction(instruction: &str) -> Option<(String, Option<String>)> {
+    let rest = instruction.trim_start().strip_prefix("HYPERLINK")?;
+    let mut url = None;
+    let mut title = None;
+    let mut pending_switch = None;
+    let mut chars = rest.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if c == '\\' {
+            chars.next();
+            pending_switch = chars.next();
+            continue;
+        }
+        let mut arg = String::new();
+        if c == '"' {
+            chars.next();
+            for c in chars.by_ref() {
+                if c == '"' {
+                    break;
+                }
+                arg.push(c);
+            }
+        } else {
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() {
+                    break;
+                }
+                arg.push(c);
+                chars.next();
+            }
+        }
+        match pending_switch.take() {
+            Some('o') => title = Some(arg),
+            Some(_) => {}
+            None if url.is_none() => url = Some(arg),
+            None => {}
+        }
+    }
+    url.filter(|u| !u.is_empty()).map(|u| (u, title))
+}
+
+fn group_text(tokens: &[RtfToken]) -> Option<String> {
+    let mut depth = 1usize;
+    let mut text = String::new();
+    for token in tokens {
+        match token {
+            RtfToken::GroupStart => depth += 1,
+            RtfToken::GroupEnd => depth = depth.saturating_sub(1),
+            RtfToken::Text(t) if depth == 1 => text.push_str(t),
+            _ => {}
+        }
+    }
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Parameter of the named control word within a group's tokens. Some
+/// writers separate the value with a space, making it text rather than a
+/// parameter; that shape is accepted too.
+fn group_parameter(tokens: &[RtfToken], word: &str) -> Option<i32> {
+    let mut found = false;
+    for token in tokens {
+        match token {
+            RtfToken::ControlWord { name, parameter, .. } if name == word => {
+                if parameter.is_some() {
+                    return *parameter;
+                }
+                found = true;
+            }
+            RtfToken::Text(text) if found => return text.trim().parse().ok(),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Decode a packed `\revdttmN` timestamp (DTTM bit fields: minute, hour,
+/// Control words that emit inline content or end the current text run.
+/// A delimiter space consumed after one of these is ordinary syntax, not
+/// a lost word boundary, so it never arms the word-join repair - and a
+/// run break disarms it.
+fn emits_or_ends_run(name: &str) -> bool {
+    matches!(
+        name,
+        "par" | "line" | "page" | "sect" | "cell" | "row" | "tab" | "u" | "bin" | "bullet"
+            | "endash" | "emdash" | "lquote" | "rquote" | "ldblquote" | "rdblquote"
+    )
+}
+
+/// Final character of accumulated inline content, looking through
+/// formatting runs; `None` when the last node carries no text.
+fn last_inline_char(inline: &[RtfNode]) -> Option<char> {
+    match inline.last()? {
+        RtfNode::Text(text) => text.chars().next_back(),
+        RtfNode::Formatted { content, .. } => last_inline_char(content),
+        _ => None,
+    }
+}
+
+/// day, month, year-1900) to `YYYY-MM-DD HH:MM`. Values with impossible
+/// fields - hosts have shipped garbage here - decode to `None`.
+fn decode_dttm(value: i32) -> Option<String> {
+    if value <= 0 {
+        return None;
+    }
+    let v = value as u32;
+    let minute = v & 0x3F;
+    let hour = (v >> 6) & 0x1F;
+    let day = (v >> 11) & 0x1F;
+    let month = (v >> 16) & 0xF;
+    let year = 1900 + ((v >> 20) & 0x1FF);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 {
+        return None;
+    }
+    Some(format!("{year:04}-{month:02}-{day:02} {hour:02}:{minute:02}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::lexer::tokenize;
+
+    fn parse(input: &str) -> RtfDocument {
+        RtfParser::new(tokenize(input).unwrap()).parse().unwrap()
+    }
+
+    #[test]
+    fn parses_plain_paragraph() {
+        let doc = parse("{\\rtf1 Hello World\\par}");
+        assert_eq!(
+            doc.content,
+            vec![RtfNode::Paragraph {
+                direction: Direction::LeftToRight,
+                spacing: ParagraphSpacing::default(),
+                content: vec![RtfNode::Text("Hello World".to_string())],
+            }]
+        );
+    }
+
+    #[test]
+    fn parses_bold_run() {
+        let doc = parse("{\\rtf1 Hello \\b World\\b0 !\\par}");
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(children[0], RtfNode::Text("Hello ".to_string()));
+        assert!(matches!(
+            children[1],
+            RtfNode::Formatted { ref format, .. } if format.bold
+        ));
+    }
+
+    #[test]
+    fn reinserts_word_boundaries_eaten_as_delimiter_spaces() {
+        // Real VFP9 report output separates runs with the single space the
+        // lexer consumes as a delimiter; without the repair the words join
+        // ("Quarterlysalesreporttotals").
+        let doc = parse("{\\rtf1 \\b Quarterly\\b0 sales\\i report\\i0 totals\\par}");
+        assert_eq!(doc.plain_text().trim(), "Quarterly sales report totals");
+
+        // Formatting groups between the runs keep the boundary too.
+        let doc = parse("{\\rtf1 Net\\b0 {\\i gain}\\par}");
+        assert_eq!(doc.plain_text().trim(), "Net gain");
+    }
+
+    #[test]
+    fn delimiter_spaces_stay_consumed_around_punctuation_and_breaks() {
+        // Punctuation hugging a run and text after \par/\line never gain
+        // a space; those delimiters were ordinary syntax.
+        let doc = parse("{\\rtf1 \\b Total\\b0 : 12\\par}");
+        assert_eq!(doc.plain_text().trim(), "Total: 12");
+
+        let doc = parse("{\\rtf1 First\\b0 \\par Second\\line Third\\par}");
+        assert_eq!(doc.plain_text().trim(), "First\nSecond\nThird");
+
+        // A space the writer did emit as text is not doubled.
+        let doc = parse("{\\rtf1 \\b Bold\\b0  and plain\\par}");
+        assert_eq!(doc.plain_text().trim(), "Bold and plain");
+    }
+
+    #[test]
+    fn hyperlink_fields_become_link_nodes() {
+        let doc = parse(
+            "{\\rtf1 Visit {\\field{\\*\\fldinst{HYPERLINK \"https://example.com\" \
+             \\\\o \"Example site\"}}{\\fldrslt Example}} today\\par}",
+        );
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(
+            children[1],
+            RtfNode::Hyperlink {
+                url: "https://example.com".to_string(),
+                title: Some("Example site".to_string()),
+                content: vec![RtfNode::Text("Example".to_string())],
+            }
+        );
+        assert_eq!(doc.plain_text().trim(), "Visit Example today");
+
+        // Non-HYPERLINK fields keep their cached result text, as before.
+        let doc = parse("{\\rtf1 Page {\\field{\\*\\fldinst{PAGE}}{\\fldrslt 1}}\\par}");
+        assert_eq!(doc.plain_text().trim(), "Page 1");
+    }
+
+    #[test]
+    fn image_destinations_become_image_nodes() {
+        let doc = parse(
+            "{\\rtf1 Logo {\\*\\lbimage{\\*\\lbimgsrc img/logo.png}\
+             {\\*\\lbimgalt The \"old\" logo}{\\*\\lbimgtitle Old}} here\\par}",
+        );
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert_eq!(
+            children[1],
+            RtfNode::Image {
+                src: "img/logo.png".to_string(),
+                alt: "The \"old\" logo".to_string(),
+                title: Some("Old".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn image_without_alt_text_warns() {
+        let tokens =
+            tokenize("{\\rtf1 {\\*\\lbimage{\\*\\lbimgsrc x.png}}\\par}").unwrap();
+        let (doc, warnings) = RtfParser::new(tokens).parse_with_warnings().unwrap();
+        assert!(
+            warnings.iter().any(|w| w.contains("alt text")),
+            "{warnings:?}"
+        );
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(&children[0], RtfNode::Image { alt, .. } if alt.is_empty()));
+    }
+
+    #[test]
+    fn hyperlink_field_without_target_warns_and_keeps_text() {
+        let tokens =
+            tokenize("{\\rtf1 {\\field{\\*\\fldinst{HYPERLINK}}{\\fldrslt broken}}\\par}")
+                .unwrap();
+        let (doc, warnings) = RtfParser::new(tokens).parse_with_warnings().unwrap();
+        assert!(
+            warnings.iter().any(|w| w.contains("HYPERLINK")),
+            "{warnings:?}"
+        );
+        assert_eq!(doc.plain_text().trim(), "broken");
+    }
+
+    /// Two bookmarks - one inside its heading, one in body text under the
+    /// second heading - plus a reference to a bookmark that does not exist.
+    const CROSS_REFERENCED_MANUAL: &str =
+        "{\\rtf1 \\outlinelevel0 {\\*\\bkmkstart sec_overview}Overview\
+         {\\*\\bkmkend sec_overview}\\par \
+         \\pard Intro\\par \
+         \\outlinelevel0 Details\\par \
+         \\pard {\\*\\bkmkstart sec_details}{\\*\\bkmkend sec_details}Body\\par \
+         \\pard See {\\field{\\*\\fldinst{REF sec_overview \\\\h}}{\\fldrslt Overview}} and \
+         {\\field{\\*\\fldinst{PAGEREF sec_details \\\\h}}{\\fldrslt page 9}} and \
+         {\\field{\\*\\fldinst{REF ghost \\\\h}}{\\fldrslt missing}}\\par}";
+
+    #[test]
+    fn resolves_cross_references_to_heading_links() {
+        let tokens = tokenize(CROSS_REFERENCED_MANUAL).unwrap();
+        let (doc, warnings) = RtfParser::new(tokens)
+            .with_cross_references(true)
+            .parse_with_warnings()
+            .unwrap();
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[4] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        let links: Vec<(&str, &str)> = children
+            .iter()
+            .filter_map(|node| match node {
+                RtfNode::Hyperlink { url, content, .. } => match content.as_slice() {
+                    [RtfNode::Text(text)] => Some((url.as_str(), text.as_str())),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .collect();
+        // The heading-range bookmark targets its own heading; the body
+        // one targets the closest heading above it. PAGEREF resolves like
+        // REF - the link is what the page reference was for.
+        assert_eq!(
+            links,
+            [("#overview", "Overview"), ("#details", "page 9")]
+        );
+        // The dangling reference keeps its result text and warns.
+        assert!(doc.plain_text().contains("missing"));
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("unknown bookmark 'ghost'"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn bookmarks_and_refs_are_inert_without_the_mode() {
+        let tokens = tokenize(CROSS_REFERENCED_MANUAL).unwrap();
+        let (doc, warnings) = RtfParser::new(tokens).parse_with_warnings().unwrap();
+        assert!(warnings.is_empty(), "{warnings:?}");
+        // Bookmarks skip as unknown `\*` destinations, fields keep their
+        // cached result text - the behavior before the mode existed.
+        let text = doc.plain_text();
+        assert!(!text.contains("sec_overview"), "{text}");
+        assert!(text.contains("See Overview and page 9 and missing"), "{text}");
+    }
+
+    #[test]
+    fn group_scoped_formatting() {
+        let doc = parse("{\\rtf1 {\\b bold} plain\\par}");
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph");
+        };
+        assert!(matches!(
+            children[0],
+            RtfNode::Formatted { ref format, .. } if format.bold
+        ));
+        assert_eq!(children[1], RtfNode::Text(" plain".to_string()));
+    }
+
+    #[test]
+    fn parses_heading_from_outline_level() {
+        let doc = parse("{\\rtf1 \\outlinelevel0 Title\\par \\pard Body\\par}");
+        assert!(matches!(doc.content[0], RtfNode::Heading { level: 1, .. }));
+        assert!(matches!(doc.content[1], RtfNode::Paragraph { .. }));
+    }
+
+    #[test]
+    fn parses_simple_table() {
+        let doc = parse("{\\rtf1 \\trowd\\intbl A\\cell B\\cell\\row \\pard after\\par}");
+        let RtfNode::Table(ref table) = doc.content[0] else {
+            panic!("expected table, got {:?}", doc.content);
+        };
+        assert_eq!(table.rows.len(), 1);
+        assert_eq!(table.rows[0].cells.len(), 2);
+    }
+
+    /// Invoice-style layout: a header spanning both columns, then a
+    /// vertically merged first column across the two value rows.
+    const MERGED_TABLE: &str =
+        "{\\rtf1 \\trowd\\clmgf\\cellx2400\\clmrg\\cellx4800 \\intbl Invoice\\cell\\cell\\row \
+         \\trowd\\clvmgf\\cellx2400\\cellx4800 \\intbl Acme\\cell 10\\cell\\row \
+         \\trowd\\clvmrg\\cellx2400\\cellx4800 \\intbl\\cell 20\\cell\\row}";
+
+    #[test]
+    fn merged_cells_record_their_spans() {
+        let doc = parse(MERGED_TABLE);
+        let RtfNode::Table(ref table) = doc.content[0] else {
+            panic!("expected table, got {:?}", doc.content);
+        };
+        assert!(table.has_merges());
+        assert_eq!(table.rows[0].cells[0].h_merge, CellMerge::First);
+        assert_eq!(table.rows[0].cells[1].h_merge, CellMerge::Merged);
+        assert_eq!(table.rows[1].cells[0].v_merge, CellMerge::First);
+        assert_eq!(table.rows[2].cells[0].v_merge, CellMerge::Merged);
+        assert_eq!(table.col_span(0, 0), 2);
+        assert_eq!(table.row_span(1, 0), 2);
+        // Unmerged cells span exactly themselves.
+        assert_eq!(table.col_span(1, 1), 1);
+        assert_eq!(table.row_span(1, 1), 1);
+    }
+
+    #[test]
+    fn records_column_bounds_and_cell_alignment() {
+        let doc = parse(
+            "{\\rtf1 \\trowd\\cellx2400\\cellx5000 \
+             \\intbl\\qr Amount\\cell \\pard\\intbl Qty\\cell\\row}",
+        );
+        let RtfNode::Table(ref table) = doc.content[0] else {
+            panic!("expected table, got {:?}", doc.content);
+        };
+        assert_eq!(table.rows[0].col_bounds, vec![2400, 5000]);
+        assert_eq!(table.col_bounds(), &[2400, 5000]);
+        assert_eq!(table.rows[0].cells[0].alignment, CellAlignment::Right);
+        assert_eq!(table.rows[0].cells[1].alignment, CellAlignment::Left);
+        assert_eq!(
+            table.column_alignments(),
+            vec![CellAlignment::Right, CellAlignment::Left]
+        );
+    }
+
+    #[test]
+    fn font_table_does_not_leak_into_text() {
+        let doc = parse("{\\rtf1{\\fonttbl{\\f0 Arial;}}Hello\\par}");
+        assert_eq!(doc.plain_text().trim(), "Hello");
+        assert_eq!(doc.fonts.len(), 1);
+        assert_eq!(doc.fonts[0].name, "Arial");
+    }
+
+    #[test]
+    fn substitutes_legacy_fonts_and_records_originals() {
+        let doc = parse("{\\rtf1{\\fonttbl{\\f0\\fswiss MS Sans Serif;}}Hello\\par}");
+        assert_eq!(doc.fonts[0].name, "Segoe UI");
+        assert_eq!(
+            doc.metadata.font_substitutions,
+            vec![FontSubstitution {
+                index: 0,
+                original: "MS Sans Serif".to_string(),
+                replacement: "Segoe UI".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn reads_info_metadata() {
+        let doc = parse("{\\rtf1{\\info{\\title My Doc}{\\author Jane}}Body\\par}");
+        assert_eq!(doc.metadata.title.as_deref(), Some("My Doc"));
+        assert_eq!(doc.metadata.author.as_deref(), Some("Jane"));
+    }
+
+    #[test]
+    fn reads_deflang_into_the_language_metadata() {
+        let doc = parse("{\\rtf1\\ansi\\deff0\\deflang3082 Hola\\par}");
+        assert_eq!(doc.metadata.language.as_deref(), Some("es-ES"));
+    }
+
+    #[test]
+    fn unknown_deflang_lcid_warns_and_records_no_language() {
+        let tokens = tokenize("{\\rtf1\\deflang9999 Body\\par}").unwrap();
+        let (doc, warnings) = RtfParser::new(tokens).parse_with_warnings().unwrap();
+        assert!(doc.metadata.language.is_none());
+        assert_eq!(warnings.len(), 1, "{warnings:?}");
+        assert!(warnings[0].contains("\\deflang9999"), "{warnings:?}");
+    }
+
+    #[test]
+    fn parses_colortbl_and_resolves_cf() {
+        let doc = parse(
+            "{\\rtf1{\\colortbl ;\\red255\\green0\\blue0;\\red0\\green255\\blue0;\
+             \\red0\\green0\\blue255;\\red128\\green0\\blue0;\\red0\\green0\\blue128;}\
+             \\cf5 navy text\\par}",
+        );
+        assert_eq!(doc.colors.len(), 6);
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        let RtfNode::Formatted { ref format, .. } = children[0] else {
+            panic!("expected formatted run, got {children:?}");
+        };
+        assert_eq!(format.color_index, Some(5));
+        assert_eq!(doc.colors[5], Color::new(0, 0, 128));
+    }
+
+    #[test]
+    fn tracks_paragraph_and_run_direction() {
+        let doc = parse(
+            "{\\rtf1 \\rtlpar \\u1588?\\u1601?\\u1585?\\u1577? {\\ltrch ABC-123} \\u1575?\\par \\pard after\\par}",
+        );
+        let RtfNode::Paragraph {
+            direction,
+            ref content,
+            ..
+        } = doc.content[0]
+        else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert_eq!(direction, Direction::RightToLeft);
+        assert!(content.iter().any(|n| matches!(
+            n,
+            RtfNode::Formatted { format, .. }
+                if format.direction == Some(Direction::LeftToRight)
+        )));
+        // \pard resets the direction for the next paragraph.
+        assert!(matches!(
+            doc.content[1],
+            RtfNode::Paragraph {
+                direction: Direction::LeftToRight,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parses_paragraph_spacing_and_pard_resets_it() {
+        let doc = parse("{\\rtf1 \\sb240\\sa120\\sl276\\slmult1 spaced\\par \\pard plain\\par}");
+        let RtfNode::Paragraph { spacing, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert_eq!(
+            spacing,
+            ParagraphSpacing {
+                space_before: Some(240),
+                space_after: Some(120),
+                line_spacing: Some(276),
+                line_spacing_multiple: true,
+                ..Default::default()
+            }
+        );
+        let RtfNode::Paragraph { spacing, .. } = doc.content[1] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert!(spacing.is_default(), "\\pard resets spacing");
+    }
+
+    #[test]
+    fn parses_page_break_before_and_keep_with_next_flags() {
+        let doc = parse("{\\rtf1 \\pagebb\\keepn Section\\par \\pard \\keepn0 body\\par}");
+        let RtfNode::Paragraph { spacing, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert!(spacing.page_break_before);
+        assert!(spacing.keep_with_next);
+        assert!(!spacing.has_spacing(), "flags are not spacing values");
+        let RtfNode::Paragraph { spacing, .. } = doc.content[1] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert!(spacing.is_default(), "\\pard resets and \\keepn0 stays off");
+    }
+
+    #[test]
+    fn resolves_character_styles_from_stylesheet() {
+        // Word-exported fixture with a custom "Code" character style.
+        let doc = parse(
+            "{\\rtf1{\\fonttbl{\\f0 Calibri;}{\\f1 Consolas;}}\
+             {\\stylesheet{\\s0 Normal;}{\\*\\cs16\\f1\\fs20 Code;}}\
+             Call {\\cs16 parse_group()} next\\par}",
+        );
+        assert_eq!(doc.styles.len(), 1);
+        assert_eq!(doc.plain_text().trim(), "Call parse_group() next");
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        let RtfNode::Formatted { ref format, .. } = children[1] else {
+            panic!("expected formatted run, got {children:?}");
+        };
+        assert_eq!(format.style_name.as_deref(), Some("Code"));
+        assert_eq!(format.font_index, Some(1));
+        assert_eq!(format.font_size, Some(20));
+    }
+
+    #[test]
+    fn style_names_can_be_suppressed() {
+        let tokens = tokenize(
+            "{\\rtf1{\\stylesheet{\\*\\cs17\\i Emphasis;}}plain {\\cs17 styled}\\par}",
+        )
+        .unwrap();
+        let doc = RtfParser::new(tokens)
+            .with_style_names(false)
+            .parse()
+            .unwrap();
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        // The resolved formatting still applies; only the name is dropped.
+        let RtfNode::Formatted { ref format, .. } = children[1] else {
+            panic!("expected formatted run, got {children:?}");
+        };
+        assert!(format.italic);
+        assert_eq!(format.style_name, None);
+    }
+
+    #[test]
+    fn toggle_floods_coalesce_instead_of_growing_the_tree() {
+        // A pathological alternating-toggle document; the node tree must
+        // stay proportional to the text, not the toggle count.
+        let rtf = format!("{{\\rtf1 {}text\\par}}", "\\b0\\b ".repeat(10_000));
+        let doc = parse(&rtf);
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert_eq!(children.len(), 1);
+
+        // Identical adjacent runs merge even with text between toggles.
+        let doc = parse("{\\rtf1 \\b x\\b0\\b y\\b0\\b z\\par}");
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert_eq!(children.len(), 1);
+    }
+
+    #[test]
+    fn format_run_limit_is_enforced() {
+        let tokens =
+            tokenize("{\\rtf1 \\b a\\b0 \\i b\\i0 \\ul c\\ulnone\\par}").unwrap();
+        let err = RtfParser::new(tokens)
+            .with_max_format_runs(2)
+            .parse()
+            .unwrap_err();
+        assert!(err.starts_with("format run limit"), "got: {err}");
+    }
+
+    #[test]
+    fn absurd_parameters_are_clamped_with_a_warning() {
+        let tokens = tokenize("{\\rtf1 \\fs50000 big\\par}").unwrap();
+        let (doc, warnings) = RtfParser::new(tokens).parse_with_warnings().unwrap();
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        let RtfNode::Formatted { ref format, .. } = children[0] else {
+            panic!("expected formatted run, got {children:?}");
+        };
+        assert_eq!(format.font_size, Some(3276));
+        assert!(
+            warnings.iter().any(|w| w.contains("\\fs50000")),
+            "got: {warnings:?}"
+        );
+    }
+
+    #[test]
+    fn resolves_revision_authors_and_dates() {
+        // Word tracked-changes export: revtbl entry 0 is the reserved
+        // "Unknown", real reviewers follow.
+        let doc = parse(
+            "{\\rtf1{\\*\\revtbl {Unknown;}{Alice Smith;}}\
+             Base {\\revised\\revauth1\\revdttm130235269 inserted}\
+             {\\deleted\\revauthdel1 removed} tail\\par}",
+        );
+        let RtfNode::Paragraph { content: ref children, .. } = doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        let RtfNode::Formatted { ref format, .. } = children[1] else {
+            panic!("expected formatted run, got {children:?}");
+        };
+        assert_eq!(
+            format.revision,
+            Some(Revision {
+                kind: RevisionKind::Inserted,
+                author: Some("Alice Smith".to_string()),
+                date: Some("2024-03-07 14:05".to_string()),
+            })
+        );
+        let RtfNode::Formatted { ref format, .. } = children[2] else {
+            panic!("expected formatted run, got {children:?}");
+        };
+        let revision = format.revision.as_ref().unwrap();
+        assert_eq!(revision.kind, RevisionKind::Deleted);
+        assert_eq!(revision.author.as_deref(), Some("Alice Smith"));
+
+        // The revtbl itself must not leak into the text.
+        assert_eq!(doc.plain_text().trim(), "Base insertedremoved tail");
+    }
+
+    #[test]
+    fn decodes_packed_revision_timestamps() {
+        assert_eq!(decode_dttm(130235269).as_deref(), Some("2024-03-07 14:05"));
+        assert_eq!(decode_dttm(0), None);
+        assert_eq!(decode_dttm(-1), None);
+        // Month 0 is not a date Word ever wrote.
+        assert_eq!(decode_dttm(5), None);
+    }
+
+    #[test]
+    fn unsupported_features_are_tallied_with_categories() {
+        use crate::conversion::features::{FeatureCategory, FeatureSeverity};
+
+        // Drawing objects and a comment icon in ignorable destinations,
+        // and a picture in a skipped destination.
+        let doc = parse(
+            "{\\rtf1 {\\*\\do\\dpline}{\\*\\do\\dprect} before\
+             {\\*\\atnicn2}{\\pict 0102} after\\par}",
+        );
+        let usage = &doc.metadata.feature_usage;
+        assert_eq!(usage.get("do").unwrap().count, 2);
+        assert_eq!(
+            usage.get("do").unwrap().category,
+            FeatureCategory::DrawingObjects
+        );
+        assert_eq!(usage.get("atnicn").unwrap().count, 1);
+        assert_eq!(
+            usage.get("atnicn").unwrap().severity,
+            FeatureSeverity::Ignorable
+        );
+        assert_eq!(usage.get("pict").unwrap().count, 1);
+        let categories = usage.category_counts();
+        assert_eq!(categories[&FeatureCategory::DrawingObjects], 4); // \do x2, \dpline, \dprect
+        assert_eq!(categories[&FeatureCategory::Annotations], 1);
+        assert_eq!(usage.worst_severity(), Some(FeatureSeverity::Lossy));
+
+        // The skipped destinations must not leak their content; the
+        // drawing groups stand in as placeholders.
+        assert_eq!(
+            doc.plain_text().trim(),
+            "[drawing omitted][drawing omitted] before after"
+        );
+    }
+
+    #[test]
+    fn equations_and_drawings_become_placeholders() {
+        let doc = parse(
+            "{\\rtf1 Result: {\\mmath x = y^2} and {\\*\\do\\dpline} done\\par}",
+        );
+        assert_eq!(
+            doc.plain_text().trim(),
+            "Result: [equation omitted] and [drawing omitted] done"
+        );
+        assert_eq!(doc.metadata.feature_usage.get("mmath").unwrap().count, 1);
+        assert_eq!(doc.metadata.feature_usage.get("do").unwrap().count, 1);
+    }
+
+    #[test]
+    fn keep_raw_preserves_group_source_in_a_comment() {
+        let tokens = tokenize("{\\rtf1 A {\\mmath x=1} B\\par}").unwrap();
+        let doc = RtfParser::new(tokens)
+            .with_placeholders(PlaceholderPolicy {
+                keep_raw: true,
+                ..Default::default()
+            })
+            .parse()
+            .unwrap();
+        assert_eq!(
+            doc.plain_text().trim(),
+            "A [equation omitted]<!-- {\\mmath x=1} --> B"
+        );
+    }
+
+    #[test]
+    fn clean_documents_report_no_feature_usage() {
+        let doc = parse("{\\rtf1 Hello \\b World\\b0\\par}");
+        assert!(doc.metadata.feature_usage.is_empty());
+    }
+
+    /// Two images, the first over the per-object cap (20 hex digits = 10
+    /// decoded bytes), the second under it.
+    const TWO_IMAGES: &str = "{\\rtf1 A\
+        {\\pict\\wmetafile8 0102030405060708090a}\
+        {\\pict\\wmetafile8 01020304} B\\par}";
+
+    #[test]
+    fn oversized_embedded_payloads_become_placeholders_with_a_warning() {
+        let limits = SecurityLimits {
+            max_embedded_object_size: 8,
+            ..Default::default()
+        };
+        let tokens = tokenize(TWO_IMAGES).unwrap();
+        let (doc, warnings) = RtfParser::new(tokens)
+            .with_embedded_limits(&limits)
+            .parse_with_warnings()
+            .unwrap();
+        assert_eq!(doc.plain_text().trim(), "A[image omitted] B");
+        assert_eq!(warnings.len(), 1, "got: {warnings:?}");
+        assert!(warnings[0].contains("per-object limit"), "got: {warnings:?}");
+    }
+
+    #[test]
+    fn reject_mode_fails_oversized_payloads() {
+        let limits = SecurityLimits {
+            max_embedded_object_size: 8,
+            ..Default::default()
+        };
+        let err = RtfParser::new(tokenize(TWO_IMAGES).unwrap())
+            .with_embedded_limits(&limits)
+            .with_sanitization_mode(SanitizationMode::Reject)
+            .parse()
+            .unwrap_err();
+        assert!(err.contains("per-object limit"), "got: {err}");
+    }
+
+    #[test]
+    fn image_count_limit_is_enforced() {
+        let limits = SecurityLimits {
+            max_image_count: 1,
+            ..Default::default()
+        };
+        let (doc, warnings) = RtfParser::new(tokenize(TWO_IMAGES).unwrap())
+            .with_embedded_limits(&limits)
+            .parse_with_warnings()
+            .unwrap();
+        assert_eq!(doc.plain_text().trim(), "A[image omitted] B");
+        assert!(
+            warnings.iter().any(|w| w.contains("embedded images")),
+            "got: {warnings:?}"
+        );
+    }
+
+    #[test]
+    fn extracts_form_fields_and_replaces_them_in_the_flow() {
+        let rtf = "{\\rtf1 Name: {\\field{\\*\\formfield{\\fftype0\
+                   {\\*\\ffname first_name}}}{\\fldrslt Jane}} City: \
+                   {\\field{\\*\\formfield{\\fftype0{\\*\\ffname city}}}{\\fldrslt }} \
+                   Subscribed: {\\field{\\*\\formfield{\\fftype1\\ffres1\
+                   {\\*\\ffname subscribed}}}}\\par}";
+        let tokens = tokenize(rtf).unwrap();
+        let doc = RtfParser::new(tokens)
+            .with_form_extraction(true)
+            .parse()
+            .unwrap();
+
+        let fields = &doc.metadata.form_fields;
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].name.as_deref(), Some("first_name"));
+        assert_eq!(fields[0].value, "Jane");
+        assert_eq!(fields[1].name.as_deref(), Some("city"));
+        assert_eq!(fields[1].value, "");
+        assert_eq!(fields[2].name.as_deref(), Some("subscribed"));
+        assert_eq!(fields[2].value, "true");
+        assert_eq!(
+            doc.plain_text().trim(),
+            "Name: Jane City: ____ Subscribed: [x]"
+        );
+    }
+
+    #[test]
+    fn form_extraction_is_off_by_default() {
+        let rtf = "{\\rtf1 {\\field{\\*\\formfield{\\fftype0}}{\\fldrslt Jane}}\\par}";
+        let doc = parse(rtf);
+        assert!(doc.metadata.form_fields.is_empty());
+    }
+
+    #[test]
+    fn extracts_annotations_with_authors_and_anchors() {
+        // Word-shaped comments: an anchored range, reviewer marks, the
+        // reference character and the comment body.
+        let rtf = "{\\rtf1 The {\\*\\atrfstart1}budget figure{\\*\\atrfend1}\
+                   {\\*\\atnid AB}{\\*\\atnauthor Alice Brown}\\chatn\
+                   {\\*\\annotation{\\*\\atnref1}Please verify} needs review.\
+                   {\\*\\atnid CD}{\\*\\atnauthor Carl Doe}\\chatn\
+                   {\\*\\annotation Looks wrong} Final copy.\
+                   {\\*\\atnid AB}\\chatn{\\*\\annotation Approved}\\par}";
+        let doc = parse(rtf);
+        let notes = &doc.metadata.annotations;
+        assert_eq!(notes.len(), 3);
+        assert_eq!(notes[0].initials.as_deref(), Some("AB"));
+        assert_eq!(notes[0].author.as_deref(), Some("Alice Brown"));
+        assert_eq!(notes[0].anchor.as_deref(), Some("budget figure"));
+        assert_eq!(notes[0].text, "Please verify");
+        assert_eq!(notes[1].initials.as_deref(), Some("CD"));
+        assert_eq!(notes[1].anchor, None);
+        assert_eq!(notes[2].initials.as_deref(), Some("AB"));
+        assert_eq!(notes[2].author, None);
+
+        // Comments are stripped from the flow by default.
+        assert_eq!(
+            doc.plain_text().trim(),
+            "The budget figure needs review. Final copy."
+        );
+        assert!(doc.metadata.feature_usage.is_empty());
+    }
+
+    #[test]
+    fn annotation_markers_are_opt_in() {
+        let rtf = "{\\rtf1 Body{\\*\\atnid AB}\\chatn{\\*\\annotation A note}\\par}";
+        assert_eq!(parse(rtf).plain_text().trim(), "Body");
+
+        let tokens = tokenize(rtf).unwrap();
+        let doc = RtfParser::new(tokens)
+            .with_annotation_markers(true)
+            .parse()
+            .unwrap();
+        assert_eq!(doc.plain_text().trim(), "Body[note 1]");
+    }
+
+    #[test]
+    fn unicode_escape() {
+        let doc = parse("{\\rtf1 \\u945?lpha\\par}");
+        assert_eq!(doc.plain_text().trim(), "αlpha");
+    }
+
+    #[test]
+    fn upr_fallback_pairs_keep_exactly_one_branch() {
+        // Word's Unicode fallback: a plain-ANSI approximation plus the
+        // real text in a \*\ud destination. Emitting both would repeat
+        // every non-ASCII run.
+        let rtf = "{\\rtf1 Title: {\\upr{Gei?}{\\*\\ud{\
+                   \\u915?\\u949?\\u953?\\u940?}}} end\\par}";
+        let doc = parse(rtf);
+        let text = doc.plain_text();
+        assert_eq!(text.trim(), "Title: Γειά end");
+        assert_eq!(text.matches("Gei").count(), 0, "{text}");
+
+        // A legacy preference keeps the ANSI branch instead.
+        let doc = RtfParser::new(tokenize(rtf).unwrap())
+            .with_unicode_preference(false)
+            .parse()
+            .unwrap();
+        assert_eq!(doc.plain_text().trim(), "Title: Gei? end");
+    }
+
+    #[test]
+    fn extension_aliases_change_unknown_word_handling() {
+        let rtf = "{\\rtf1 {\\fpxhdr Monthly Report}\
+                   \\fpxem emphasized\\par}";
+        // Without a map both words fall through to the degradation tally.
+        let doc = parse(rtf);
+        assert!(doc.plain_text().contains("Monthly Report"));
+
+        let extensions = ControlWordExtensions::from_json(
+            r#"{"fpxhdr": {"treat_as": "custom-destination", "name": "header"},
+                "fpxem": {"treat_as": "bold-on"}}"#,
+        )
+        .unwrap();
+        let doc = RtfParser::new(tokenize(rtf).unwrap())
+            .with_extensions(extensions)
+            .parse()
+            .unwrap();
+        // The header block left the flow and landed on the metadata.
+        assert_eq!(doc.plain_text().trim(), "emphasized");
+        assert_eq!(
+            doc.metadata.custom_destinations,
+            vec![CustomDestination {
+                name: "header".to_string(),
+                text: "Monthly Report".to_string(),
+            }]
+        );
+        let RtfNode::Paragraph { content, .. } = &doc.content[0] else {
+            panic!("expected paragraph, got {:?}", doc.content);
+        };
+        assert!(
+            matches!(&content[0], RtfNode::Formatted { format, .. } if format.bold),
+            "{content:?}"
+        );
+    }
+
+    #[test]
+    fn extension_ignore_group_drops_the_group() {
+        let extensions = ControlWordExtensions::from_json(
+            r#"{"fpxfoot": {"treat_as": "ignore-group"},
+                "fpxpar": {"treat_as": "paragraph-break"}}"#,
+        )
+        .unwrap();
+        let doc = RtfParser::new(
+            tokenize("{\\rtf1 one\\fpxpar two{\\fpxfoot page 1 of 9}\\par}").unwrap(),
+        )
+        .with_extensions(extensions)
+        .parse()
+        .unwrap();
+        assert_eq!(doc.plain_text().trim(), "one\ntwo");
+        assert_eq!(doc.content.len(), 2);
+    }
+}
@@ -0,0 +1,351 @@
+//! Incremental re-conversion for editor integration.
+//!
+//! A [`ConversionSession`] keeps the source split into top-level segments
+//! (child groups of the root group, plus the text between them), each with
+//! its own cached token run. Small edits re-tokenize only the segment they
+//! touch and splice the cached runs; edits that cross segment boundaries,
+//! change group nesting, or exceed a churn threshold fall back to a full
+//! rebuild. Output is regenerated lazily and cached until the next edit.
+
+use super::lexer::{tokenize, RtfToken};
+use super::markdown_generator::MarkdownGenerator;
+use super::pipeline::{ConversionError, ConversionResult};
+use super::rtf_parser::RtfParser;
+use std::ops::Range;
+
+/// Total replaced bytes tolerated before the next edit forces a full
+/// rebuild; beyond this the segment bookkeeping costs more than it saves.
+const CHURN_LIMIT: usize = 64 * 1024;
+
+/// One top-level segment: its byte range in the current source and the
+/// tokens produced from that slice.
+struct Segment {
+    range: Range<usize>,
+    tokens: Vec<RtfToken>,
+}
+
+pub struct ConversionSession {
+    rtf: String,
+    segments: Vec<Segment>,
+    /// Cached output; cleared by every edit.
+    markdown: Option<String>,
+    /// Bytes replaced since the last full rebuild.
+    churn: usize,
+    /// Set when the source cannot be tokenized (editors routinely pass
+    /// through transient invalid states); reported by `to_markdown`.
+    broken: Option<String>,
+}
+
+impl ConversionSession {
+    /// Parse `rtf` once and cache its token runs per top-level segment.
+    pub fn new(rtf: impl Into<String>) -> ConversionResult<Self> {
+        let mut session = ConversionSession {
+            rtf: rtf.into(),
+            segments: Vec::new(),
+            markdown: None,
+            churn: 0,
+            broken: None,
+        };
+        session.rebuild();
+        match session.broken.take() {
+            Some(message) => Err(ConversionError::parse(message)),
+            None => Ok(session),
+        }
+    }
+
+    /// The current source text.
+    pub fn source(&self) -> &str {
+        &self.rtf
+    }
+
+    /// Replace `range` (byte offsets into the current source) with
+    /// `replacement`, re-tokenizing as little as possible.
+    pub fn apply_edit(&mut self, range: Range<usize>, replacement: &str) -> ConversionResult<()> {
+        if range.start > range.end || range.end > self.rtf.len() {
+            return Err(ConversionError::validation(format!(
+                "edit range {}..{} is outside the document (len {})",
+                range.start,
+                range.end,
+                self.rtf.len()
+            )));
+        }
+        self.markdown = None;
+        self.churn += range.len().max(replacement.len());
+
+        let splice_target = if self.churn > CHURN_LIMIT || self.broken.is_some() {
+            None
+        } else {
+            self.segment_containing(&range)
+        };
+        self.rtf.replace_range(range.clone(), replacement);
+
+        let Some(index) = splice_target else {
+            self.rebuild();
+            return Ok(());
+        };
+        // The edit stayed inside one segment: its end moves by the size
+        // difference, everything after it just slides.
+        let delta = replacement.len() as isize - range.len() as isize;
+        let old_range = &self.segments[index].range;
+        let segment_range = old_range.start..(old_range.end as isize + delta).max(0) as usize;
+        let slice = &self.rtf[segment_range.clone()];
+        let Ok(tokens) = tokenize(slice) else {
+            // The edit broke the segment mid-token; start over.
+            self.rebuild();
+            return Ok(());
+        };
+        if group_balance(&tokens) != group_balance(&self.segments[index].tokens) {
+            // The edit changed group nesting, so segment boundaries moved.
+            self.rebuild();
+            return Ok(());
+        }
+        self.segments[index] = Segment {
+            range: segment_range,
+            tokens,
+        };
+        for segment in &mut self.segments[index + 1..] {
+            segment.range = offset_range(&segment.range, delta);
+        }
+        Ok(())
+    }
+
+    /// Convert the current source, reusing cached output when no edit
+    /// happened since the last call.
+    pub fn to_markdown(&mut self) -> ConversionResult<String> {
+        if let Some(message) = &self.broken {
+            return Err(ConversionError::parse(message.clone()));
+        }
+        if let Some(markdown) = &self.markdown {
+            return Ok(markdown.clone());
+        }
+        let token_count = self.segments.iter().map(|s| s.tokens.len()).sum();
+        let mut tokens = Vec::with_capacity(token_count);
+        for segment in &self.segments {
+            tokens.extend_from_slice(&segment.tokens);
+        }
+        let document = RtfParser::new(tokens)
+            .parse()
+            .map_err(ConversionError::parse)?;
+        let markdown = MarkdownGenerator::new().generate(&document);
+        self.markdown = Some(markdown.clone());
+        Ok(markdown)
+    }
+
+    /// Re-segment and re-tokenize the whole source. An untokenizable
+    /// source marks the session broken rather than failing, so further
+    /// edits can repair it.
+    fn rebuild(&mut self) {
+        self.broken = None;
+        let mut segments = Vec::new();
+        for range in segment_ranges(&self.rtf) {
+            match tokenize(&self.rtf[range.clone()]) {
+                Ok(tokens) => segments.push(Segment { range, tokens }),
+                Err(message) => {
+                    self.broken = Some(message);
+                    self.segments.clear();
+                    return;
+                }
+            }
+        }
+        self.segments = segments;
+        self.churn = 0;
+    }
+
+    /// Index of the segment that fully contains `range`, if any. Edits
+    /// spanning segments (i.e. crossing a top-level group boundary) get
+    /// `None` and take the rebuild path.
+    fn segment_containing(&self, range: &Range<usize>) -> Option<usize> {
+        self.segments
+            .iter()
+            .position(|s| s.range.start <= range.start && range.end <= s.range.end)
+    }
+}
+
+/// Split the source at top-level group boundaries: each child group of the
+/// root group is one segment, as is each stretch of root-level content
+/// between them. Splitting only at `{`/`}` keeps fragment tokenization
+/// identical to tokenizing the whole document.
+fn segment_ranges(rtf: &str) -> Vec<Range<usize>> {
+    let bytes = rtf.as_bytes();
+    let mut ranges = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => {
+                // Skip the escaped character so \{ and \} don't count.
+                i += 2;
+                continue;
+            }
+            b'{' => {
+                depth += 1;
+                if depth == 2 && i > start {
+                    ranges.push(start..i);
+                    start = i;
+                }
+            }
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 1 {
+                    ranges.push(start..i + 1);
+                    start = i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if start < bytes.len() {
+        ranges.push(start..bytes.len());
+    }
+    ranges
+}
+
+/// Net group nesting of a token run (opens minus closes).
+fn group_balance(tokens: &[RtfToken]) -> isize {
+    tokens
+        .iter()
+        .map(|t| match t {
+            RtfToken::GroupStart => 1,
+            RtfToken::GroupEnd => -1,
+            _ => 0,
+        })
+        .sum()
+}
+
+fn offset_range(range: &Range<usize>, delta: isize) -> Range<usize> {
+    let start = (range.start as isize + delta).max(0) as usize;
+    let end = (range.end as isize + delta).max(0) as usize;
+    start..end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Full reconversion over the same simple path the session uses.
+    fn full_convert(rtf: &str) -> String {
+        let document = RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap();
+        MarkdownGenerator::new().generate(&document)
+    }
+
+    #[test]
+    fn session_matches_full_conversion() {
+        let rtf = "{\\rtf1 Hello {\\b World}\\par}";
+        let mut session = ConversionSession::new(rtf).unwrap();
+        assert_eq!(session.to_markdown().unwrap(), full_convert(rtf));
+    }
+
+    #[test]
+    fn in_group_edit_splices_without_rebuild() {
+        let rtf = "{\\rtf1 Hello {\\b World}\\par}";
+        let mut session = ConversionSession::new(rtf).unwrap();
+        session.to_markdown().unwrap();
+
+        // Replace "World" inside the bold group.
+        let start = rtf.find("World").unwrap();
+        session.apply_edit(start..start + 5, "Editor").unwrap();
+        assert_eq!(session.source(), "{\\rtf1 Hello {\\b Editor}\\par}");
+        assert_eq!(
+            session.to_markdown().unwrap(),
+            full_convert(session.source())
+        );
+    }
+
+    #[test]
+    fn boundary_crossing_edit_falls_back_to_rebuild() {
+        let rtf = "{\\rtf1 Hello {\\b World}\\par}";
+        let mut session = ConversionSession::new(rtf).unwrap();
+
+        // Delete "o {\b W" - spans a group boundary.
+        let start = rtf.find("o {").unwrap();
+        session.apply_edit(start..start + 7, "").unwrap();
+        assert_eq!(
+            session.to_markdown().unwrap(),
+            full_convert(session.source())
+        );
+    }
+
+    #[test]
+    fn balanced_nested_group_edit_still_splices() {
+        let rtf = "{\\rtf1 Hello {\\b World}\\par}";
+        let mut session = ConversionSession::new(rtf).unwrap();
+
+        // A balanced nested group stays within the segment.
+        let start = rtf.find("World").unwrap();
+        session.apply_edit(start..start + 5, "{\\i X}").unwrap();
+        assert_eq!(
+            session.to_markdown().unwrap(),
+            full_convert(session.source())
+        );
+    }
+
+    #[test]
+    fn nesting_change_inside_a_segment_falls_back() {
+        let rtf = "{\\rtf1 Hello {\\b World}\\par}";
+        let mut session = ConversionSession::new(rtf).unwrap();
+
+        // An unbalanced open brace changes the segment's group balance;
+        // segment boundaries are stale, so the session must rebuild.
+        let start = rtf.find("World").unwrap();
+        session.apply_edit(start..start + 5, "X{\\i Y").unwrap();
+        assert_eq!(
+            session.to_markdown().unwrap(),
+            full_convert(session.source())
+        );
+    }
+
+    #[test]
+    fn out_of_bounds_edit_is_rejected() {
+        let mut session = ConversionSession::new("{\\rtf1 Hi\\par}").unwrap();
+        let err = session.apply_edit(5..999, "x").unwrap_err();
+        assert_eq!(err.category(), "validation");
+    }
+
+    /// Randomized edit sequences must stay equivalent to full reconversion.
+    /// A fixed-seed LCG keeps the test deterministic without a rand dep.
+    #[test]
+    fn randomized_edits_match_full_conversion() {
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move |bound: usize| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((state >> 33) as usize) % bound.max(1)
+        };
+        let words = ["alpha", "beta", "gamma", "delta", ""];
+
+        let rtf = "{\\rtf1 one {\\b two} three {\\i four} five\\par six\\par}";
+        let mut session = ConversionSession::new(rtf).unwrap();
+        for _ in 0..50 {
+            // Edit a random range inside the body, on char boundaries.
+            let len = session.source().len();
+            if len < 12 {
+                break;
+            }
+            let mut start = 7 + next(len - 8);
+            while !session.source().is_char_boundary(start) {
+                start -= 1;
+            }
+            let mut end = start + next(6).min(len - 1 - start);
+            while !session.source().is_char_boundary(end) {
+                end -= 1;
+            }
+            let replacement = words[next(words.len())];
+            session.apply_edit(start..end, replacement).unwrap();
+
+            let expected = match tokenize(session.source())
+                .ok()
+                .and_then(|t| RtfParser::new(t).parse().ok())
+            {
+                Some(document) => MarkdownGenerator::new().generate(&document),
+                // Random edits can corrupt the source beyond parsing;
+                // the session must fail the same way.
+                None => {
+                    assert!(session.to_markdown().is_err());
+                    continue;
+                }
+            };
+            assert_eq!(session.to_markdown().unwrap(), expected);
+        }
+    }
+}
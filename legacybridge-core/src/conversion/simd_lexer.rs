@@ -0,0 +1,355 @@
+//! SIMD-accelerated tokenizer.
+//!
+//! Uses vectorized byte scanning (via `memchr`, which selects SSE2/AVX2/NEON
+//! at runtime) to skip over plain-text runs in bulk, falling back to the
+//! scalar logic only at structural bytes. Output is token-for-token
+//! identical to [`tokenize`](super::lexer::tokenize); the fuzz targets
+//! enforce that equivalence.
+
+use super::lexer::{cp1252_to_char, RtfToken, SpannedToken};
+
+/// Which vector instruction set the byte scanner can use on this CPU.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimdLevel {
+    None,
+    Sse2,
+    Avx2,
+    Neon,
+}
+
+impl std::fmt::Display for SimdLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SimdLevel::None => "none",
+            SimdLevel::Sse2 => "sse2",
+            SimdLevel::Avx2 => "avx2",
+            SimdLevel::Neon => "neon",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CpuFeatures {
+    pub simd_level: SimdLevel,
+}
+
+impl CpuFeatures {
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if std::arch::is_x86_feature_detected!("avx2") {
+                return CpuFeatures {
+                    simd_level: SimdLevel::Avx2,
+                };
+            }
+            return CpuFeatures {
+                simd_level: SimdLevel::Sse2,
+            };
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            return CpuFeatures {
+                simd_level: SimdLevel::Neon,
+            };
+        }
+        #[allow(unreachable_code)]
+        CpuFeatures {
+            simd_level: SimdLevel::None,
+        }
+    }
+}
+
+/// Find the next structural byte (`\`, `{`, `}`, CR, LF) at or after `from`.
+#[inline]
+fn next_special(bytes: &[u8], from: usize) -> Option<usize> {
+    let a = memchr::memchr3(b'\\', b'{', b'}', &bytes[from..]);
+    let b = memchr::memchr2(b'\r', b'\n', &bytes[from..]);
+    match (a, b) {
+        (Some(x), Some(y)) => Some(from + x.min(y)),
+        (Some(x), None) => Some(from + x),
+        (None, Some(y)) => Some(from + y),
+        (None, None) => None,
+    }
+}
+
+/// Tokenize RTF input using the vectorized scanner. Produces exactly the
+/// same token stream as the scalar [`tokenize`](super::lexer::tokenize).
+pub fn tokenize_simd(input: &str) -> Result<Vec<RtfToken>, String> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+
+    macro_rules! flush_text {
+        () => {
+            if !text.is_empty() {
+                tokens.push(RtfToken::Text(std::mem::take(&mut text)));
+            }
+        };
+    }
+
+    while i < bytes.len() {
+        // Bulk-copy everything up to the next structural byte.
+        let special = next_special(bytes, i);
+        let stop = special.unwrap_or(bytes.len());
+        if stop > i {
+            // The slice boundary always falls on a structural ASCII byte or
+            // the end of input, so it cannot split a UTF-8 sequence.
+            text.push_str(&input[i..stop]);
+            i = stop;
+            continue;
+        }
+        match bytes[i] {
+            b'{' => {
+                flush_text!();
+                tokens.push(RtfToken::GroupStart);
+                i += 1;
+            }
+            b'}' => {
+                flush_text!();
+                tokens.push(RtfToken::GroupEnd);
+                i += 1;
+            }
+            b'\r' | b'\n' => i += 1,
+            b'\\' => {
+                i += 1;
+                if i >= bytes.len() {
+                    return Err("unexpected end of input after backslash".to_string());
+                }
+                let c = bytes[i];
+                if c.is_ascii_alphabetic() {
+                    flush_text!();
+                    let start = i;
+                    while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let name = input[start..i].to_string();
+                    let mut parameter = None;
+                    let num_start = i;
+                    if i < bytes.len() && (bytes[i] == b'-' || bytes[i].is_ascii_digit()) {
+                        i += 1;
+                        while i < bytes.len() && bytes[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        parameter = input[num_start..i].parse::<i32>().ok();
+                    }
+                    let space = i < bytes.len() && bytes[i] == b' ';
+                    if space {
+                        i += 1;
+                    }
+                    tokens.push(RtfToken::ControlWord { name, parameter, space });
+                } else if c == b'\'' {
+                    if i + 2 < bytes.len() {
+                        let hex = &input[i + 1..i + 3];
+                        if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                            text.push(cp1252_to_char(byte));
+                            i += 3;
+                        } else {
+                            i += 1;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                } else {
+                    match c {
+                        b'\\' | b'{' | b'}' => text.push(c as char),
+                        b'~' => text.push('\u{a0}'),
+                        b'-' => {}
+                        b'_' => text.push('-'),
+                        _ => {
+                            flush_text!();
+                            tokens.push(RtfToken::ControlSymbol(c as char));
+                        }
+                    }
+                    i += 1;
+                }
+            }
+            _ => unreachable!("next_special returned a non-structural byte"),
+        }
+    }
+    flush_text!();
+    Ok(tokens)
+}
+
+/// [`tokenize_simd`] annotating every token with its byte span, under the
+/// same tiling contract as the scalar
+/// [`tokenize_spanned`](super::lexer::tokenize_spanned): spans cover the
+/// input without gaps or overlaps, and stripping them reproduces the
+/// plain token stream.
+pub fn tokenize_simd_spanned(input: &str) -> Result<Vec<SpannedToken>, String> {
+    let bytes = input.as_bytes();
+    let mut tokens: Vec<SpannedToken> = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+    // End of the last pushed token; the next span starts here so skipped
+    // bytes in between stay covered.
+    let mut prev_end = 0;
+
+    macro_rules! push_token {
+        ($token:expr, $end:expr) => {{
+            tokens.push(SpannedToken {
+                token: $token,
+                start: prev_end,
+                end: $end,
+            });
+            prev_end = $end;
+        }};
+    }
+
+    macro_rules! flush_text {
+        ($end:expr) => {
+            if !text.is_empty() {
+                push_token!(RtfToken::Text(std::mem::take(&mut text)), $end);
+            }
+        };
+    }
+
+    while i < bytes.len() {
+        // Bulk-copy everything up to the next structural byte.
+        let special = next_special(bytes, i);
+        let stop = special.unwrap_or(bytes.len());
+        if stop > i {
+            text.push_str(&input[i..stop]);
+            i = stop;
+            continue;
+        }
+        match bytes[i] {
+            b'{' => {
+                flush_text!(i);
+                i += 1;
+                push_token!(RtfToken::GroupStart, i);
+            }
+            b'}' => {
+                flush_text!(i);
+                i += 1;
+                push_token!(RtfToken::GroupEnd, i);
+            }
+            b'\r' | b'\n' => i += 1,
+            b'\\' => {
+                // The backslash belongs to whatever token it introduces.
+                let escape_start = i;
+                i += 1;
+                if i >= bytes.len() {
+                    return Err("unexpected end of input after backslash".to_string());
+                }
+                let c = bytes[i];
+                if c.is_ascii_alphabetic() {
+                    flush_text!(escape_start);
+                    let start = i;
+                    while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                        i += 1;
+                    }
+                    let name = input[start..i].to_string();
+                    let mut parameter = None;
+                    let num_start = i;
+                    if i < bytes.len() && (bytes[i] == b'-' || bytes[i].is_ascii_digit()) {
+                        i += 1;
+                        while i < bytes.len() && bytes[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        parameter = input[num_start..i].parse::<i32>().ok();
+                    }
+                    let space = i < bytes.len() && bytes[i] == b' ';
+                    if space {
+                        i += 1;
+                    }
+                    push_token!(RtfToken::ControlWord { name, parameter, space }, i);
+                } else if c == b'\'' {
+                    if i + 2 < bytes.len() {
+                        let hex = &input[i + 1..i + 3];
+                        if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                            text.push(cp1252_to_char(byte));
+                            i += 3;
+                        } else {
+                            i += 1;
+                        }
+                    } else {
+                        i += 1;
+                    }
+                } else {
+                    match c {
+                        b'\\' | b'{' | b'}' => text.push(c as char),
+                        b'~' => text.push('\u{a0}'),
+                        b'-' => {}
+                        b'_' => text.push('-'),
+                        _ => {
+                            flush_text!(escape_start);
+                            push_token!(RtfToken::ControlSymbol(c as char), i + 1);
+                        }
+                    }
+                    i += 1;
+                }
+            }
+            _ => unreachable!("next_special returned a non-structural byte"),
+        }
+    }
+    if !text.is_empty() {
+        tokens.push(SpannedToken {
+            token: RtfToken::Text(text),
+            start: prev_end,
+            end: bytes.len(),
+        });
+    }
+    // Trailing skipped bytes (a final newline, say) extend the last span.
+    if let Some(last) = tokens.last_mut() {
+        last.end = bytes.len();
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::lexer::tokenize;
+
+    fn assert_equivalent(input: &str) {
+        assert_eq!(
+            tokenize(input),
+            tokenize_simd(input),
+            "scalar and SIMD lexers diverged on {input:?}"
+        );
+    }
+
+    #[test]
+    fn matches_scalar_on_basic_documents() {
+        assert_equivalent("{\\rtf1 Hello \\b World\\b0}");
+        assert_equivalent("{\\rtf1 caf\\'e9 \\u945?lpha}");
+        // The delimiter-space flag must agree byte for byte too.
+        assert_equivalent("{\\rtf1 \\b joined\\b0 next\\i0text\\par}");
+        assert_equivalent("\\{escaped\\} \\~ \\- \\_");
+        assert_equivalent("{\\rtf1 line1\r\nline2\n}");
+        assert_equivalent("");
+        assert_equivalent("plain text with no rtf at all");
+    }
+
+    #[test]
+    fn matches_scalar_on_trailing_backslash() {
+        assert_eq!(tokenize("abc\\"), tokenize_simd("abc\\"));
+    }
+
+    #[test]
+    fn spanned_matches_scalar_spanned() {
+        use crate::conversion::lexer::tokenize_spanned;
+        for input in [
+            "{\\rtf1 Hello \\b World\\b0}",
+            "caf\\'e9 \\{x\\} \\*\\li-720 done\r\n",
+            "",
+        ] {
+            assert_eq!(
+                tokenize_spanned(input),
+                tokenize_simd_spanned(input),
+                "scalar and SIMD spanned lexers diverged on {input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn detect_reports_a_level() {
+        // On any supported platform detection must not panic and must
+        // return a stable answer.
+        let a = CpuFeatures::detect().simd_level;
+        let b = CpuFeatures::detect().simd_level;
+        assert_eq!(a, b);
+    }
+}
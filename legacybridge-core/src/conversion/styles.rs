@@ -0,0 +1,158 @@
+//! Character style (`\cs`) definitions from the stylesheet.
+//!
+//! Word exports semantic intent ("Code", "Emphasis") as character styles;
+//! runs reference them with `\csN`. The parser resolves references into
+//! their effective [`TextFormat`] and can record the style name so the
+//! generators can preserve the intent rather than just the raw toggles.
+
+use super::lexer::RtfToken;
+use super::rtf_parser::{Direction, TextFormat};
+
+/// One `\cs` entry of the stylesheet.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CharacterStyle {
+    pub index: i32,
+    pub name: String,
+    /// The formatting the style definition carries.
+    pub format: TextFormat,
+}
+
+/// Parse the tokens of a `\stylesheet` destination, keeping only the
+/// character styles (`\cs` entries); paragraph styles (`\s`) are skipped.
+pub fn parse_stylesheet(tokens: &[RtfToken]) -> Vec<CharacterStyle> {
+    let mut styles = Vec::new();
+    let mut current: Option<CharacterStyle> = None;
+    for token in tokens {
+        match token {
+            RtfToken::GroupStart => current = None,
+            RtfToken::GroupEnd => current = None,
+            RtfToken::ControlWord { name, parameter, .. } => {
+                if name == "cs" {
+                    current = Some(CharacterStyle {
+                        index: parameter.unwrap_or(0),
+                        ..Default::default()
+                    });
+                    continue;
+                }
+                if let Some(style) = current.as_mut() {
+                    apply_format_word(&mut style.format, name, *parameter);
+                }
+            }
+            RtfToken::Text(text) => {
+                let Some(style) = current.as_mut() else {
+                    continue;
+                };
+                match text.split_once(';') {
+                    Some((head, _)) => {
+                        style.name.push_str(head);
+                        style.name = style.name.trim().to_string();
+                        styles.push(current.take().expect("entry is set"));
+                    }
+                    None => style.name.push_str(text),
+                }
+            }
+            RtfToken::ControlSymbol(_) => {}
+        }
+    }
+    styles
+}
+
+/// Overlay a style's formatting onto a run's format. Boolean toggles are
+/// additive; indexed attributes apply only where the run has none.
+pub fn apply_style(format: &mut TextFormat, style: &TextFormat) {
+    format.bold |= style.bold;
+    format.italic |= style.italic;
+    format.underline |= style.underline;
+    format.strikethrough |= style.strikethrough;
+    format.direction = format.direction.or(style.direction);
+    format.font_size = format.font_size.or(style.font_size);
+    format.font_index = format.font_index.or(style.font_index);
+    format.color_index = format.color_index.or(style.color_index);
+}
+
+/// Emit a style definition's formatting as RTF control words, in the form
+/// [`parse_stylesheet`] reads back.
+pub fn format_words(format: &TextFormat) -> String {
+    let mut out = String::new();
+    if format.bold {
+        out.push_str("\\b");
+    }
+    if format.italic {
+        out.push_str("\\i");
+    }
+    if format.underline {
+        out.push_str("\\ul");
+    }
+    if format.strikethrough {
+        out.push_str("\\strike");
+    }
+    if let Some(index) = format.font_index {
+        out.push_str(&format!("\\f{index}"));
+    }
+    if let Some(size) = format.font_size {
+        out.push_str(&format!("\\fs{size}"));
+    }
+    if let Some(index) = format.color_index {
+        out.push_str(&format!("\\cf{index}"));
+    }
+    match format.direction {
+        Some(Direction::RightToLeft) => out.push_str("\\rtlch"),
+        Some(Direction::LeftToRight) => out.push_str("\\ltrch"),
+        None => {}
+    }
+    out
+}
+
+fn apply_format_word(format: &mut TextFormat, name: &str, parameter: Option<i32>) {
+    match name {
+        "b" => format.bold = parameter != Some(0),
+        "i" => format.italic = parameter != Some(0),
+        "ul" => format.underline = parameter != Some(0),
+        "ulnone" => format.underline = false,
+        "strike" => format.strikethrough = parameter != Some(0),
+        "fs" => format.font_size = parameter,
+        "f" => format.font_index = parameter,
+        "cf" => format.color_index = parameter,
+        "rtlch" => format.direction = Some(Direction::RightToLeft),
+        "ltrch" => format.direction = Some(Direction::LeftToRight),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::lexer::tokenize;
+
+    #[test]
+    fn parses_character_styles_only() {
+        let tokens = tokenize(
+            "{\\stylesheet{\\s0 Normal;}{\\*\\cs16\\f1\\fs20 Code;}{\\*\\cs17\\i Emphasis;}}",
+        )
+        .unwrap();
+        let styles = parse_stylesheet(&tokens);
+        assert_eq!(styles.len(), 2);
+        assert_eq!(styles[0].name, "Code");
+        assert_eq!(styles[0].index, 16);
+        assert_eq!(styles[0].format.font_index, Some(1));
+        assert_eq!(styles[1].name, "Emphasis");
+        assert!(styles[1].format.italic);
+    }
+
+    #[test]
+    fn style_overlay_keeps_run_attributes() {
+        let mut format = TextFormat {
+            bold: true,
+            font_size: Some(24),
+            ..Default::default()
+        };
+        let style = TextFormat {
+            italic: true,
+            font_size: Some(20),
+            ..Default::default()
+        };
+        apply_style(&mut format, &style);
+        assert!(format.bold && format.italic);
+        assert_eq!(format.font_size, Some(24));
+    }
+}
@@ -0,0 +1,1885 @@
+//! Document templates for legacy office workflows.
+//!
+//! A [`Template`] bundles the formatting conventions of the VB6/VFP9
+//! application it replaces ([`LegacySettings`]), `{{variable}}`
+//! substitutions, and node [`Transformation`]s. Templates are registered
+//! with a [`TemplateSystem`], which validates their format strings at load
+//! time and applies them to a parsed [`RtfDocument`].
+
+use super::rtf_parser::{
+    Direction, ParagraphSpacing, RtfDocument, RtfNode, TableCell, TableRow, TextFormat,
+};
+use super::styles::CharacterStyle;
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+/// Kinds of template; every variant has a built-in except `Manual`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateType {
+    Memo,
+    Report,
+    Letter,
+    Invoice,
+    Contract,
+    Manual,
+}
+
+/// Formatting conventions inherited from the legacy application.
+#[derive(Debug, Clone)]
+pub struct LegacySettings {
+    /// VB6-style date pattern: `MM/DD/YYYY`, `DD.MM.YYYY`, `YYYY-MM-DD`, ...
+    pub date_format: String,
+    /// VB6-style numeric pattern: `#,##0.00`, `0.000`, `#,##0`, ...
+    pub number_format: String,
+}
+
+impl Default for LegacySettings {
+    fn default() -> Self {
+        LegacySettings {
+            date_format: "MM/DD/YYYY".to_string(),
+            number_format: "#,##0.00".to_string(),
+        }
+    }
+}
+
+/// Separator convention for [`Transformation::FormatNumbers`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberLocale {
+    /// `1,234.56`
+    #[default]
+    Us,
+    /// `1.234,56`
+    Eu,
+}
+
+/// Which nodes [`Transformation::FormatNumbers`] touches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberScope {
+    /// Every text node in the document.
+    #[default]
+    Document,
+    /// Table cells only, leaving prose (and resolved dates) alone.
+    Tables,
+}
+
+/// A document transformation applied by [`TemplateSystem::apply`].
+///
+/// Structural transformations run before variable resolution, so the
+/// blocks they insert can carry `{{variables}}`. Pattern replacements run
+/// on the resolved text, all in one tree walk, in template order per
+/// node. The numeric transformations run last, in template order.
+#[derive(Debug, Clone)]
+pub enum Transformation {
+    /// Reformat numeric literals according to the template's
+    /// `number_format`, with the locale's decimal and grouping separators.
+    FormatNumbers {
+        locale: NumberLocale,
+        scope: NumberScope,
+    },
+    /// Append a totals row to every table, summing the numeric values in
+    /// the last column and formatting the sum per `number_format`.
+    TotalsRow {
+        label: String,
+        locale: NumberLocale,
+    },
+    /// Prepend sender and addressee blocks and append a signature area.
+    /// Variables: `sender_name`, `sender_address`, `addressee_name`,
+    /// `addressee_address`, `signature_name`.
+    AddressBlocks,
+    /// Prepend a parties block. Variables: `party_a`, `party_b`; the
+    /// effective date comes from the built-in `{{date}}`.
+    PartiesBlock,
+    /// Prefix headings with hierarchical numbers (`1`, `1.1`, `1.2`, ...).
+    NumberHeadings,
+    /// Replace a pattern in every text node; see [`ReplacePattern`].
+    ReplacePattern(ReplacePattern),
+}
+
+/// One text replacement; [`find`](Self::find) is a literal by default or
+/// a regular expression when [`regex`](Self::regex) is set. Patterns are
+/// compiled once per application (with a size limit on the regex form)
+/// and applied to each node in template order within a single tree walk,
+/// so large pattern lists stay linear in document size.
+#[derive(Debug, Clone)]
+pub struct ReplacePattern {
+    pub find: String,
+    /// Replacement text; the regex form may reference capture groups
+    /// (`$1`, `${name}`).
+    pub replace: String,
+    /// Interpret `find` as a regular expression instead of a literal.
+    pub regex: bool,
+}
+
+impl Transformation {
+    /// One-line description shown in previews.
+    fn describe(&self) -> &'static str {
+        match self {
+            Transformation::FormatNumbers { .. } => "reformat numeric literals",
+            Transformation::TotalsRow { .. } => "append totals row to tables",
+            Transformation::AddressBlocks => "add address and signature blocks",
+            Transformation::PartiesBlock => "add parties block",
+            Transformation::NumberHeadings => "number headings",
+            Transformation::ReplacePattern(_) => "replace text pattern",
+        }
+    }
+
+    fn is_structural(&self) -> bool {
+        matches!(
+            self,
+            Transformation::AddressBlocks
+                | Transformation::PartiesBlock
+                | Transformation::NumberHeadings
+        )
+    }
+}
+
+/// Spacing conventions for one kind of block, in points; `None` fields
+/// leave the document's own spacing untouched. RTF stores spacing in
+/// twips (twentieths of a point), so `space_after: 12` becomes `\sa240`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SpacingSettings {
+    /// Space before each block, in points.
+    pub space_before: Option<i32>,
+    /// Space after each block, in points.
+    pub space_after: Option<i32>,
+    /// Line spacing as a multiple of single spacing (1.5 = one-and-a-half).
+    pub line_spacing: Option<f32>,
+    /// Start each block on a new page (`\pagebb`).
+    pub page_break_before: Option<bool>,
+    /// Keep each block on the same page as the next one (`\keepn`);
+    /// set on headings so they never strand at the bottom of a page.
+    pub keep_with_next: Option<bool>,
+}
+
+impl SpacingSettings {
+    fn is_default(&self) -> bool {
+        self.space_before.is_none()
+            && self.space_after.is_none()
+            && self.line_spacing.is_none()
+            && self.page_break_before.is_none()
+            && self.keep_with_next.is_none()
+    }
+}
+
+/// Paragraph spacing conventions a template imposes on the document.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ParagraphSettings {
+    /// Applied to body paragraphs.
+    pub body: SpacingSettings,
+    /// Applied to headings.
+    pub headings: SpacingSettings,
+}
+
+/// Header and footer paragraphs added around the document body; both may
+/// carry `{{variables}}`.
+#[derive(Debug, Clone, Default)]
+pub struct HeaderFooterConfig {
+    pub header: Option<String>,
+    pub footer: Option<String>,
+}
+
+/// A named template: legacy settings plus substitutions and transforms.
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub template_type: TemplateType,
+    pub settings: LegacySettings,
+    /// `{{key}}` substitutions. `{{date}}` and `{{time}}` are built in and
+    /// honor `settings.date_format`; explicit entries override them.
+    pub variables: HashMap<String, String>,
+    pub transformations: Vec<Transformation>,
+    pub header_footer: HeaderFooterConfig,
+    /// Spacing overlaid onto the document's blocks.
+    pub paragraph_settings: ParagraphSettings,
+    /// Character styles merged into the document's stylesheet (existing
+    /// styles with the same name win).
+    pub styles: Vec<CharacterStyle>,
+}
+
+impl Template {
+    /// A template with default settings and no substitutions.
+    pub fn new(name: impl Into<String>, template_type: TemplateType) -> Self {
+        Template {
+            name: name.into(),
+            template_type,
+            settings: LegacySettings::default(),
+            variables: HashMap::new(),
+            transformations: Vec::new(),
+            header_footer: HeaderFooterConfig::default(),
+            paragraph_settings: ParagraphSettings::default(),
+            styles: Vec::new(),
+        }
+    }
+}
+
+/// Registry of templates; starts with the built-ins.
+#[derive(Clone)]
+pub struct TemplateSystem {
+    templates: HashMap<String, Template>,
+    /// `extends` edges from loaded template files, child name to base
+    /// name, for enforcing [`MAX_INHERITANCE_DEPTH`] across loads.
+    parents: HashMap<String, String>,
+}
+
+impl Default for TemplateSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TemplateSystem {
+    pub fn new() -> Self {
+        let mut system = TemplateSystem {
+            templates: HashMap::new(),
+            parents: HashMap::new(),
+        };
+        for template in builtin_templates() {
+            system
+                .register(template)
+                .expect("built-in templates must validate");
+        }
+        system
+    }
+
+    /// Validate and register a template; replaces any previous template of
+    /// the same name. Invalid date or number format strings are rejected
+    /// here so they cannot silently leak raw patterns into output later.
+    pub fn register(&mut self, template: Template) -> Result<(), String> {
+        date_pattern_to_chrono(&template.settings.date_format)
+            .map_err(|e| format!("template '{}': {e}", template.name))?;
+        parse_number_format(&template.settings.number_format)
+            .map_err(|e| format!("template '{}': {e}", template.name))?;
+        for transformation in &template.transformations {
+            if let Transformation::ReplacePattern(pattern) = transformation {
+                compile_pattern(pattern)
+                    .map_err(|e| format!("template '{}': {e}", template.name))?;
+            }
+        }
+        // A programmatic registration supersedes any loaded file of the
+        // same name, inheritance edge included.
+        self.parents.remove(&template.name);
+        self.templates.insert(template.name.clone(), template);
+        Ok(())
+    }
+
+    /// Load one JSON template file (see [`TemplateFile`] for the format),
+    /// enforcing the template-file limits, and register it. Returns the
+    /// registered name. The size limit is checked against the file's
+    /// metadata, so an oversized file is rejected without reading it.
+    pub fn load_file(&mut self, path: &str) -> Result<String, String> {
+        let size = std::fs::metadata(path)
+            .map_err(|e| format!("failed to read template file {path}: {e}"))?
+            .len();
+        if size > MAX_TEMPLATE_FILE_BYTES {
+            return Err(format!(
+                "template file {path} is {size} bytes, over the \
+                 {MAX_TEMPLATE_FILE_BYTES}-byte template file size limit"
+            ));
+        }
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read template file {path}: {e}"))?;
+        self.load_json(&json)
+    }
+
+    /// [`load_file`](Self::load_file) on already-read JSON; the file size
+    /// limit applies to the string's length.
+    pub fn load_json(&mut self, json: &str) -> Result<String, String> {
+        if json.len() as u64 > MAX_TEMPLATE_FILE_BYTES {
+            return Err(format!(
+                "template JSON is {} bytes, over the \
+                 {MAX_TEMPLATE_FILE_BYTES}-byte template file size limit",
+                json.len()
+            ));
+        }
+        let file: TemplateFile =
+            serde_json::from_str(json).map_err(|e| format!("invalid template JSON: {e}"))?;
+        let name = file.name.clone();
+        let base = match &file.extends {
+            Some(base_name) => {
+                let depth = 1 + self.inheritance_depth(base_name);
+                if depth > MAX_INHERITANCE_DEPTH {
+                    return Err(format!(
+                        "template '{name}' would have inheritance depth {depth}, \
+                         over the limit of {MAX_INHERITANCE_DEPTH}"
+                    ));
+                }
+                Some(self.get(base_name).ok_or_else(|| {
+                    format!("template '{name}' extends unknown template '{base_name}'")
+                })?)
+            }
+            None => None,
+        };
+        let extends = file.extends.clone();
+        let template = file
+            .resolve(base)
+            .map_err(|e| format!("template '{name}': {e}"))?;
+        self.register(template)?;
+        if let Some(base_name) = extends {
+            self.parents.insert(name.clone(), base_name);
+        }
+        Ok(name)
+    }
+
+    /// Load every `.json` file in a directory, in file-name order so an
+    /// `extends` may reference templates from earlier files. Fails on the
+    /// first invalid file (templates from earlier files stay registered)
+    /// and on directories holding more than
+    /// [`MAX_TEMPLATES_PER_DIRECTORY`] template files. Returns the
+    /// registered names in load order.
+    pub fn load_directory(&mut self, dir: &str) -> Result<Vec<String>, String> {
+        let mut paths: Vec<std::path::PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| format!("failed to read template directory {dir}: {e}"))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        if paths.len() > MAX_TEMPLATES_PER_DIRECTORY {
+            return Err(format!(
+                "template directory {dir} holds {} template files, over the \
+                 limit of {MAX_TEMPLATES_PER_DIRECTORY}",
+                paths.len()
+            ));
+        }
+        paths.sort();
+        let mut names = Vec::with_capacity(paths.len());
+        for path in &paths {
+            names.push(self.load_file(&path.to_string_lossy())?);
+        }
+        Ok(names)
+    }
+
+    /// Length of the `extends` chain below `name`. Chains cannot cycle -
+    /// a base must already be registered, and re-registration severs the
+    /// old edge - so the walk terminates.
+    fn inheritance_depth(&self, name: &str) -> usize {
+        let mut depth = 0;
+        let mut current = name;
+        while let Some(parent) = self.parents.get(current) {
+            depth += 1;
+            current = parent;
+        }
+        depth
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Template> {
+        self.templates.get(name)
+    }
+
+    /// Registered template names, sorted for stable listings.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Apply a template to a document in place: resolve `{{variables}}` in
+    /// every text node, then run the template's transformations.
+    pub fn apply(&self, name: &str, document: &mut RtfDocument) -> Result<(), String> {
+        self.apply_at(name, document, chrono::Local::now().naive_local())
+    }
+
+    /// [`apply`](Self::apply) with an explicit timestamp for `{{date}}` and
+    /// `{{time}}`; the public entry point passes the current local time.
+    pub fn apply_at(
+        &self,
+        name: &str,
+        document: &mut RtfDocument,
+        now: NaiveDateTime,
+    ) -> Result<(), String> {
+        self.run(name, document, now, None)
+    }
+
+    /// Report what [`apply`](Self::apply) would do without touching the
+    /// document: per-transformation touch counts with sample excerpts,
+    /// resolved header/footer text, metadata changes and any `{{markers}}`
+    /// that would remain unresolved.
+    pub fn preview(&self, name: &str, document: &RtfDocument) -> Result<TemplateDiff, String> {
+        self.preview_at(name, document, chrono::Local::now().naive_local())
+    }
+
+    /// [`preview`](Self::preview) with an explicit timestamp.
+    pub fn preview_at(
+        &self,
+        name: &str,
+        document: &RtfDocument,
+        now: NaiveDateTime,
+    ) -> Result<TemplateDiff, String> {
+        // Run the real transformations against a throwaway clone with the
+        // recorder attached, so the preview can never drift from apply.
+        let mut scratch = document.clone();
+        let mut diff = TemplateDiff::default();
+        self.run(name, &mut scratch, now, Some(&mut diff))?;
+        diff.metadata_changes = metadata_changes(document, &scratch);
+        Ok(diff)
+    }
+
+    fn run(
+        &self,
+        name: &str,
+        document: &mut RtfDocument,
+        now: NaiveDateTime,
+        diff: Option<&mut TemplateDiff>,
+    ) -> Result<(), String> {
+        let template = self
+            .templates
+            .get(name)
+            .ok_or_else(|| format!("unknown template '{name}'"))?;
+
+        // Validated at registration, so these cannot fail here.
+        let chrono_pattern = date_pattern_to_chrono(&template.settings.date_format)?;
+        let number_format = parse_number_format(&template.settings.number_format)?;
+
+        let date = now.format(&chrono_pattern).to_string();
+        let time = now.format("%H:%M:%S").to_string();
+
+        let mut previews: Vec<TransformationPreview> = template
+            .transformations
+            .iter()
+            .map(|t| TransformationPreview {
+                description: t.describe().to_string(),
+                touched: 0,
+                samples: Vec::new(),
+            })
+            .collect();
+
+        for (preview, transformation) in previews.iter_mut().zip(&template.transformations) {
+            if transformation.is_structural() {
+                apply_structural(transformation, &mut document.content, preview);
+            }
+        }
+        if let Some(header) = &template.header_footer.header {
+            document.content.insert(0, paragraph(header));
+        }
+        if let Some(footer) = &template.header_footer.footer {
+            document.content.push(paragraph(footer));
+        }
+        for style in &template.styles {
+            if !document.styles.iter().any(|s| s.name == style.name) {
+                document.styles.push(style.clone());
+            }
+        }
+        // Spacing runs after the header/footer are in place, so inserted
+        // paragraphs pick up the convention too.
+        apply_paragraph_settings(&mut document.content, &template.paragraph_settings);
+
+        let mut unresolved = BTreeSet::new();
+        for_each_text_mut(&mut document.content, &mut |text| {
+            resolve_variables(text, template, &date, &time, &mut unresolved);
+        });
+
+        // Pattern replacements: compile each pattern once, then apply them
+        // all to every node in a single walk, in template order. One walk
+        // per pattern made large documents quadratic in pattern count.
+        let mut replacements = Vec::new();
+        for (index, transformation) in template.transformations.iter().enumerate() {
+            if let Transformation::ReplacePattern(pattern) = transformation {
+                // Validated at registration, so this cannot fail here.
+                replacements.push((index, compile_pattern(pattern)?, pattern.replace.as_str()));
+            }
+        }
+        if !replacements.is_empty() {
+            for_each_text_mut(&mut document.content, &mut |text| {
+                for (index, find, replace) in &replacements {
+                    if apply_pattern(find, replace, text) {
+                        previews[*index].touch(text);
+                    }
+                }
+            });
+        }
+
+        for (preview, transformation) in previews.iter_mut().zip(&template.transformations) {
+            match transformation {
+                Transformation::FormatNumbers { locale, scope } => {
+                    let mut reformat = |text: &mut String| {
+                        let formatted = reformat_numbers(text, &number_format, *locale);
+                        if formatted != *text {
+                            preview.touch(&formatted);
+                            *text = formatted;
+                        }
+                    };
+                    match scope {
+                        NumberScope::Document => {
+                            for_each_text_mut(&mut document.content, &mut reformat);
+                        }
+                        NumberScope::Tables => {
+                            for node in &mut document.content {
+                                if let RtfNode::Table(table) = node {
+                                    for row in &mut table.rows {
+                                        for cell in &mut row.cells {
+                                            for_each_text_mut(&mut cell.content, &mut reformat);
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                Transformation::TotalsRow { label, locale } => {
+                    append_totals_rows(
+                        &mut document.content,
+                        label,
+                        &number_format,
+                        *locale,
+                        preview,
+                    );
+                }
+                _ => {}
+            }
+        }
+
+        if let Some(diff) = diff {
+            // Structural samples were recorded before variable resolution;
+            // show them the way they would actually render.
+            for preview in &mut previews {
+                for sample in &mut preview.samples {
+                    resolve_variables(sample, template, &date, &time, &mut unresolved);
+                }
+            }
+            let mut resolve = |text: &String| {
+                let mut text = text.clone();
+                resolve_variables(&mut text, template, &date, &time, &mut unresolved);
+                text
+            };
+            diff.header = template.header_footer.header.as_ref().map(&mut resolve);
+            diff.footer = template.header_footer.footer.as_ref().map(&mut resolve);
+            diff.transformations = previews;
+            diff.unresolved_variables = unresolved.into_iter().collect();
+        }
+        Ok(())
+    }
+}
+
+// Limits applied when loading template files. Templates often live on
+// shared network drives the deployment does not control, so a corrupted
+// or malicious file must fail fast with an error naming the limit it
+// broke, instead of hanging or bloating the process.
+
+/// Maximum template file size in bytes, checked against the file's
+/// metadata before reading.
+const MAX_TEMPLATE_FILE_BYTES: u64 = 1024 * 1024;
+/// Maximum transformations per template file.
+const MAX_TEMPLATE_TRANSFORMATIONS: usize = 64;
+/// Maximum style definitions per template file.
+const MAX_TEMPLATE_STYLES: usize = 64;
+/// Maximum `{{variable}}` entries per template file.
+const MAX_TEMPLATE_VARIABLES: usize = 256;
+/// Maximum length of one variable key.
+const MAX_VARIABLE_KEY_LEN: usize = 64;
+/// Maximum length of one variable value; header and footer text shares it.
+const MAX_VARIABLE_VALUE_LEN: usize = 4096;
+/// Maximum `extends` chain length across loaded template files.
+const MAX_INHERITANCE_DEPTH: usize = 8;
+/// Maximum template files one [`TemplateSystem::load_directory`] accepts.
+const MAX_TEMPLATES_PER_DIRECTORY: usize = 100;
+
+/// On-disk template file format (JSON). Top-level fields mirror
+/// [`Template`]; transformations are objects with a `"type"` key naming
+/// the [`Transformation`] variant in snake case plus that type's
+/// parameters. Unknown top-level fields, unknown transformation types
+/// and unknown or missing transformation parameters are all rejected at
+/// load time, so a typo cannot surface as a mid-conversion failure.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFile {
+    name: String,
+    /// One of `memo`, `report`, `letter`, `invoice`, `contract` or
+    /// `manual`; defaults to the base's type or `manual`.
+    #[serde(default)]
+    template_type: Option<String>,
+    /// Name of an already-registered template to start from. This file's
+    /// fields replace the base's where present; variables merge per key.
+    #[serde(default)]
+    extends: Option<String>,
+    #[serde(default)]
+    date_format: Option<String>,
+    #[serde(default)]
+    number_format: Option<String>,
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    #[serde(default)]
+    transformations: Option<Vec<TransformationSpec>>,
+    #[serde(default)]
+    header: Option<String>,
+    #[serde(default)]
+    footer: Option<String>,
+    #[serde(default)]
+    styles: Option<Vec<StyleSpec>>,
+}
+
+impl TemplateFile {
+    /// Check the per-template limits and build the [`Template`],
+    /// overlaying this file onto its base when it extends one.
+    fn resolve(self, base: Option<&Template>) -> Result<Template, String> {
+        if self.variables.len() > MAX_TEMPLATE_VARIABLES {
+            return Err(format!(
+                "{} variables, over the limit of {MAX_TEMPLATE_VARIABLES}",
+                self.variables.len()
+            ));
+        }
+        for (key, value) in &self.variables {
+            if key.len() > MAX_VARIABLE_KEY_LEN {
+                return Err(format!(
+                    "variable key '{}...' is {} bytes, over the limit of {MAX_VARIABLE_KEY_LEN}",
+                    &key[..MAX_VARIABLE_KEY_LEN.min(key.len())],
+                    key.len()
+                ));
+            }
+            if value.len() > MAX_VARIABLE_VALUE_LEN {
+                return Err(format!(
+                    "value of variable '{key}' is {} bytes, over the \
+                     limit of {MAX_VARIABLE_VALUE_LEN}",
+                    value.len()
+                ));
+            }
+        }
+        if let Some(transformations) = &self.transformations {
+            if transformations.len() > MAX_TEMPLATE_TRANSFORMATIONS {
+                return Err(format!(
+                    "{} transformations, over the limit of {MAX_TEMPLATE_TRANSFORMATIONS}",
+                    transformations.len()
+                ));
+            }
+        }
+        if let Some(styles) = &self.styles {
+            if styles.len() > MAX_TEMPLATE_STYLES {
+                return Err(format!(
+                    "{} styles, over the limit of {MAX_TEMPLATE_STYLES}",
+                    styles.len()
+                ));
+            }
+        }
+        for (label, text) in [("header", &self.header), ("footer", &self.footer)] {
+            if let Some(text) = text {
+                if text.len() > MAX_VARIABLE_VALUE_LEN {
+                    return Err(format!(
+                        "{label} is {} bytes, over the limit of {MAX_VARIABLE_VALUE_LEN}",
+                        text.len()
+                    ));
+                }
+            }
+        }
+
+        let mut template = match base {
+            Some(base) => {
+                let mut template = base.clone();
+                template.name = self.name;
+                template
+            }
+            None => Template::new(self.name, TemplateType::Manual),
+        };
+        if let Some(kind) = self.template_type {
+            template.template_type = template_type_from_name(&kind)?;
+        }
+        if let Some(date_format) = self.date_format {
+            template.settings.date_format = date_format;
+        }
+        if let Some(number_format) = self.number_format {
+            template.settings.number_format = number_format;
+        }
+        template.variables.extend(self.variables);
+        if let Some(transformations) = self.transformations {
+            template.transformations = transformations
+                .into_iter()
+                .map(TransformationSpec::resolve)
+                .collect::<Result<_, _>>()?;
+        }
+        if let Some(header) = self.header {
+            template.header_footer.header = Some(header);
+        }
+        if let Some(footer) = self.footer {
+            template.header_footer.footer = Some(footer);
+        }
+        if let Some(styles) = self.styles {
+            template.styles = styles.into_iter().map(StyleSpec::resolve).collect();
+        }
+        Ok(template)
+    }
+}
+
+fn template_type_from_name(kind: &str) -> Result<TemplateType, String> {
+    Ok(match kind {
+        "memo" => TemplateType::Memo,
+        "report" => TemplateType::Report,
+        "letter" => TemplateType::Letter,
+        "invoice" => TemplateType::Invoice,
+        "contract" => TemplateType::Contract,
+        "manual" => TemplateType::Manual,
+        other => return Err(format!("unknown template type '{other}'")),
+    })
+}
+
+/// One transformation in a template file: its `"type"` plus, flattened
+/// alongside it, whatever parameters that type takes.
+#[derive(Deserialize)]
+struct TransformationSpec {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(flatten)]
+    params: HashMap<String, serde_json::Value>,
+}
+
+impl TransformationSpec {
+    /// Convert to a [`Transformation`], checking that every required
+    /// parameter is present and every present one is known for the type.
+    fn resolve(mut self) -> Result<Transformation, String> {
+        let kind = std::mem::take(&mut self.kind);
+        let transformation = match kind.as_str() {
+            "format_numbers" => Transformation::FormatNumbers {
+                locale: self.locale()?,
+                scope: self.scope()?,
+            },
+            "totals_row" => Transformation::TotalsRow {
+                label: self.required_str(&kind, "label")?,
+                locale: self.locale()?,
+            },
+            "address_blocks" => Transformation::AddressBlocks,
+            "parties_block" => Transformation::PartiesBlock,
+            "number_headings" => Transformation::NumberHeadings,
+            "replace_pattern" => Transformation::ReplacePattern(ReplacePattern {
+                find: self.required_str(&kind, "find")?,
+                replace: self.required_str(&kind, "replace")?,
+                regex: self.optional_bool("regex")?.unwrap_or(false),
+            }),
+            other => return Err(format!("unknown transformation type '{other}'")),
+        };
+        if let Some(extra) = self.params.keys().next() {
+            return Err(format!(
+                "transformation '{kind}' does not take a parameter '{extra}'"
+            ));
+        }
+        Ok(transformation)
+    }
+
+    fn required_str(&mut self, kind: &str, key: &str) -> Result<String, String> {
+        self.optional_str(key)?.ok_or_else(|| {
+            format!("transformation '{kind}' is missing its required parameter '{key}'")
+        })
+    }
+
+    fn optional_str(&mut self, key: &str) -> Result<Option<String>, String> {
+        match self.params.remove(key) {
+            None => Ok(None),
+            Some(serde_json::Value::String(value)) => Ok(Some(value)),
+            Some(other) => Err(format!("parameter '{key}' must be a string, got {other}")),
+        }
+    }
+
+    fn optional_bool(&mut self, key: &str) -> Result<Option<bool>, String> {
+        match self.params.remove(key) {
+            None => Ok(None),
+            Some(serde_json::Value::Bool(value)) => Ok(Some(value)),
+            Some(other) => Err(format!("parameter '{key}' must be a boolean, got {other}")),
+        }
+    }
+
+    fn locale(&mut self) -> Result<NumberLocale, String> {
+        Ok(match self.optional_str("locale")?.as_deref() {
+            None | Some("us") => NumberLocale::Us,
+            Some("eu") => NumberLocale::Eu,
+            Some(other) => {
+                return Err(format!("parameter 'locale' must be 'us' or 'eu', got '{other}'"))
+            }
+        })
+    }
+
+    fn scope(&mut self) -> Result<NumberScope, String> {
+        Ok(match self.optional_str("scope")?.as_deref() {
+            None | Some("document") => NumberScope::Document,
+            Some("tables") => NumberScope::Tables,
+            Some(other) => {
+                return Err(format!(
+                    "parameter 'scope' must be 'document' or 'tables', got '{other}'"
+                ))
+            }
+        })
+    }
+}
+
+/// One character style in a template file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StyleSpec {
+    index: i32,
+    name: String,
+    #[serde(default)]
+    bold: bool,
+    #[serde(default)]
+    italic: bool,
+    #[serde(default)]
+    underline: bool,
+    #[serde(default)]
+    strikethrough: bool,
+}
+
+impl StyleSpec {
+    fn resolve(self) -> CharacterStyle {
+        CharacterStyle {
+            index: self.index,
+            name: self.name,
+            format: TextFormat {
+                bold: self.bold,
+                italic: self.italic,
+                underline: self.underline,
+                strikethrough: self.strikethrough,
+                ..TextFormat::default()
+            },
+        }
+    }
+}
+
+/// What a template application would change; see [`TemplateSystem::preview`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TemplateDiff {
+    /// Names of metadata fields the template would change.
+    pub metadata_changes: Vec<String>,
+    /// One entry per transformation, in template order.
+    pub transformations: Vec<TransformationPreview>,
+    /// Header text after variable resolution, if the template has one.
+    pub header: Option<String>,
+    /// Footer text after variable resolution, if the template has one.
+    pub footer: Option<String>,
+    /// `{{markers}}` that would remain in the output, sorted.
+    pub unresolved_variables: Vec<String>,
+}
+
+/// Preview of a single transformation's effect.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformationPreview {
+    pub description: String,
+    /// Number of nodes the transformation would modify or insert.
+    pub touched: usize,
+    /// Up to three excerpts of affected content.
+    pub samples: Vec<String>,
+}
+
+impl TransformationPreview {
+    fn touch(&mut self, sample: &str) {
+        self.touched += 1;
+        if self.samples.len() < 3 {
+            self.samples.push(excerpt(sample));
+        }
+    }
+}
+
+/// Shorten a sample to a preview-friendly length, on a char boundary.
+fn excerpt(text: &str) -> String {
+    const LIMIT: usize = 48;
+    if text.len() <= LIMIT {
+        return text.to_string();
+    }
+    let mut end = LIMIT;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &text[..end])
+}
+
+/// Metadata fields that differ between the original and transformed
+/// document. Templates do not touch metadata yet, but previews report it
+/// so callers need no changes when one does.
+fn metadata_changes(before: &RtfDocument, after: &RtfDocument) -> Vec<String> {
+    let mut changes = Vec::new();
+    if before.metadata.title != after.metadata.title {
+        changes.push("title".to_string());
+    }
+    if before.metadata.author != after.metadata.author {
+        changes.push("author".to_string());
+    }
+    if before.metadata.subject != after.metadata.subject {
+        changes.push("subject".to_string());
+    }
+    changes
+}
+
+/// A plain left-to-right paragraph holding one text run.
+fn paragraph(text: &str) -> RtfNode {
+    RtfNode::Paragraph {
+        direction: Direction::LeftToRight,
+        spacing: ParagraphSpacing::default(),
+        content: vec![RtfNode::Text(text.to_string())],
+    }
+}
+
+/// Twips per point; RTF spacing units are twentieths of a point.
+const TWIPS_PER_POINT: i32 = 20;
+
+/// Convert template spacing (points) to node spacing (twips), overlaying
+/// only the fields the template sets so document spacing survives where
+/// the template is silent. Line spacing multiples are stored the way RTF
+/// writes them: `\slN\slmult1` with N in 240ths of single spacing.
+fn overlay_spacing(spacing: &mut ParagraphSpacing, settings: &SpacingSettings) {
+    if let Some(points) = settings.space_before {
+        spacing.space_before = Some(points * TWIPS_PER_POINT);
+    }
+    if let Some(points) = settings.space_after {
+        spacing.space_after = Some(points * TWIPS_PER_POINT);
+    }
+    if let Some(multiple) = settings.line_spacing {
+        spacing.line_spacing = Some((multiple * 240.0).round() as i32);
+        spacing.line_spacing_multiple = true;
+    }
+    if let Some(break_before) = settings.page_break_before {
+        spacing.page_break_before = break_before;
+    }
+    if let Some(keep) = settings.keep_with_next {
+        spacing.keep_with_next = keep;
+    }
+}
+
+/// Overlay the template's spacing conventions onto top-level blocks.
+fn apply_paragraph_settings(content: &mut [RtfNode], settings: &ParagraphSettings) {
+    for node in content {
+        match node {
+            RtfNode::Paragraph { spacing, .. } if !settings.body.is_default() => {
+                overlay_spacing(spacing, &settings.body);
+            }
+            RtfNode::Heading { spacing, .. } if !settings.headings.is_default() => {
+                overlay_spacing(spacing, &settings.headings);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn apply_structural(
+    transformation: &Transformation,
+    content: &mut Vec<RtfNode>,
+    preview: &mut TransformationPreview,
+) {
+    match transformation {
+        Transformation::AddressBlocks => {
+            preview.touch("{{sender_name}} / {{sender_address}}");
+            preview.touch("{{addressee_name}} / {{addressee_address}}");
+            preview.touch("Sincerely, {{signature_name}}");
+            content.insert(
+                0,
+                RtfNode::Paragraph {
+                    direction: Direction::LeftToRight,
+                    spacing: ParagraphSpacing::default(),
+                    content: vec![
+                        RtfNode::Text("{{sender_name}}".to_string()),
+                        RtfNode::LineBreak,
+                        RtfNode::Text("{{sender_address}}".to_string()),
+                    ],
+                },
+            );
+            content.insert(
+                1,
+                RtfNode::Paragraph {
+                    direction: Direction::LeftToRight,
+                    spacing: ParagraphSpacing::default(),
+                    content: vec![
+                        RtfNode::Text("{{addressee_name}}".to_string()),
+                        RtfNode::LineBreak,
+                        RtfNode::Text("{{addressee_address}}".to_string()),
+                    ],
+                },
+            );
+            content.push(RtfNode::Paragraph {
+                direction: Direction::LeftToRight,
+                spacing: ParagraphSpacing::default(),
+                content: vec![
+                    RtfNode::Text("Sincerely,".to_string()),
+                    RtfNode::LineBreak,
+                    RtfNode::Text("{{signature_name}}".to_string()),
+                ],
+            });
+        }
+        Transformation::PartiesBlock => {
+            let text = "This agreement is made between {{party_a}} and {{party_b}}, \
+                        effective {{date}}.";
+            preview.touch(text);
+            content.insert(0, paragraph(text));
+        }
+        Transformation::NumberHeadings => number_headings(content, preview),
+        Transformation::FormatNumbers { .. }
+        | Transformation::TotalsRow { .. }
+        | Transformation::ReplacePattern(_) => {}
+    }
+}
+
+/// Prefix headings with hierarchical numbers in document order: a level-1
+/// heading resets the level-2 counter, and so on.
+fn number_headings(content: &mut [RtfNode], preview: &mut TransformationPreview) {
+    let mut counters = [0usize; 9];
+    for node in content.iter_mut() {
+        if let RtfNode::Heading { level, content, .. } = node {
+            let level = (*level as usize).clamp(1, 9);
+            counters[level - 1] += 1;
+            for counter in &mut counters[level..] {
+                *counter = 0;
+            }
+            let label = counters[..level]
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(".");
+            content.insert(0, RtfNode::Text(format!("{label} ")));
+            preview.touch(&nodes_text(content));
+        }
+    }
+}
+
+/// Append a totals row to every top-level table, summing the numeric
+/// values of the last column.
+fn append_totals_rows(
+    content: &mut [RtfNode],
+    label: &str,
+    format: &NumberFormat,
+    locale: NumberLocale,
+    preview: &mut TransformationPreview,
+) {
+    for node in content.iter_mut() {
+        let RtfNode::Table(table) = node else {
+            continue;
+        };
+        let columns = table.rows.iter().map(|r| r.cells.len()).max().unwrap_or(0);
+        if columns == 0 {
+            continue;
+        }
+        let sum: f64 = table
+            .rows
+            .iter()
+            .filter_map(|row| row.cells.last())
+            .filter_map(|cell| {
+                nodes_text(&cell.content)
+                    .trim()
+                    .replace(',', "")
+                    .parse::<f64>()
+                    .ok()
+            })
+            .sum();
+        let total = format_number(sum, format, locale);
+        preview.touch(&format!("{label} {total}"));
+        let mut cells = vec![TableCell::default(); columns];
+        if columns > 1 {
+            cells[0].content.push(RtfNode::Text(label.to_string()));
+        }
+        cells[columns - 1].content = vec![RtfNode::Text(total)];
+        table.rows.push(TableRow {
+            cells,
+            ..TableRow::default()
+        });
+    }
+}
+
+/// Concatenated text of a node subtree, in document order.
+fn nodes_text(nodes: &[RtfNode]) -> String {
+    let mut out = String::new();
+    let mut stack: Vec<&RtfNode> = nodes.iter().rev().collect();
+    while let Some(node) = stack.pop() {
+        match node {
+            RtfNode::Text(text) => out.push_str(text),
+            RtfNode::Formatted { content, .. }
+            | RtfNode::Hyperlink { content, .. }
+            | RtfNode::Paragraph { content, .. }
+            | RtfNode::Heading { content, .. }
+            | RtfNode::ListItem { content, .. } => stack.extend(content.iter().rev()),
+            RtfNode::Table(table) => {
+                for row in table.rows.iter().rev() {
+                    for cell in row.cells.iter().rev() {
+                        stack.extend(cell.content.iter().rev());
+                    }
+                }
+            }
+            RtfNode::Image { .. } | RtfNode::RawRtf { .. } | RtfNode::LineBreak
+            | RtfNode::PageBreak => {}
+        }
+    }
+    out
+}
+
+/// Whether `name` is one of the built-in templates. The metrics label
+/// guard folds everything else into `"custom"`, so user-registered
+/// template names never widen label cardinality.
+pub(crate) fn is_builtin_name(name: &str) -> bool {
+    matches!(name, "memo" | "report" | "letter" | "invoice" | "contract")
+}
+
+/// The built-in template library.
+fn builtin_templates() -> Vec<Template> {
+    let memo = Template::new("memo", TemplateType::Memo);
+
+    let mut report = Template::new("report", TemplateType::Report);
+    report.transformations.push(Transformation::FormatNumbers {
+        locale: NumberLocale::Us,
+        scope: NumberScope::Document,
+    });
+    // Reports go to print: keep section headings with their first body
+    // paragraph so Word never strands one at the bottom of a page.
+    report.paragraph_settings.headings.keep_with_next = Some(true);
+
+    // Business letter: address blocks and a signature area filled from
+    // `sender_name`, `sender_address`, `addressee_name`,
+    // `addressee_address` and `signature_name`, dated via the header.
+    let mut letter = Template::new("letter", TemplateType::Letter);
+    letter.transformations.push(Transformation::AddressBlocks);
+    letter.header_footer.header = Some("{{date}}".to_string());
+    letter.styles.push(CharacterStyle {
+        index: 20,
+        name: "Emphasis".to_string(),
+        format: TextFormat {
+            italic: true,
+            ..TextFormat::default()
+        },
+    });
+
+    // Invoice: expects a table with amounts in the last column; appends a
+    // totals row and currency-formats the cells. Variables:
+    // `invoice_number`, plus the built-in `date`.
+    let mut invoice = Template::new("invoice", TemplateType::Invoice);
+    invoice.transformations.push(Transformation::TotalsRow {
+        label: "Total".to_string(),
+        locale: NumberLocale::Us,
+    });
+    invoice.transformations.push(Transformation::FormatNumbers {
+        locale: NumberLocale::Us,
+        scope: NumberScope::Tables,
+    });
+    invoice.header_footer.header = Some("INVOICE {{invoice_number}} - {{date}}".to_string());
+    invoice.header_footer.footer = Some("Thank you for your business.".to_string());
+    invoice.styles.push(CharacterStyle {
+        index: 21,
+        name: "Code".to_string(),
+        format: TextFormat {
+            font_index: Some(1),
+            ..TextFormat::default()
+        },
+    });
+
+    // Contract: numbered headings, a parties block filled from `party_a`
+    // and `party_b`, and a confidentiality footer.
+    let mut contract = Template::new("contract", TemplateType::Contract);
+    contract.transformations.push(Transformation::PartiesBlock);
+    contract.transformations.push(Transformation::NumberHeadings);
+    contract.header_footer.footer =
+        Some("CONFIDENTIAL - {{party_a}} / {{party_b}}".to_string());
+    contract.styles.push(CharacterStyle {
+        index: 20,
+        name: "Emphasis".to_string(),
+        format: TextFormat {
+            italic: true,
+            ..TextFormat::default()
+        },
+    });
+
+    vec![memo, report, letter, invoice, contract]
+}
+
+/// Run `f` over every text node, iteratively - template application must
+/// survive the same adversarially deep trees as the rest of the crate.
+fn for_each_text_mut(nodes: &mut [RtfNode], f: &mut impl FnMut(&mut String)) {
+    let mut stack: Vec<&mut RtfNode> = nodes.iter_mut().collect();
+    while let Some(node) = stack.pop() {
+        match node {
+            RtfNode::Text(text) => f(text),
+            RtfNode::Formatted { content, .. }
+            | RtfNode::Hyperlink { content, .. }
+            | RtfNode::Paragraph { content, .. }
+            | RtfNode::Heading { content, .. }
+            | RtfNode::ListItem { content, .. } => stack.extend(content.iter_mut()),
+            RtfNode::Table(table) => {
+                for row in &mut table.rows {
+                    for cell in &mut row.cells {
+                        stack.extend(cell.content.iter_mut());
+                    }
+                }
+            }
+            RtfNode::Image { .. } | RtfNode::RawRtf { .. } | RtfNode::LineBreak
+            | RtfNode::PageBreak => {}
+        }
+    }
+}
+
+/// A [`ReplacePattern`]'s `find` compiled for repeated use across nodes.
+enum CompiledFind {
+    /// memchr-backed substring searcher; probing it rejects non-matching
+    /// nodes without allocating. Boxed: the finder's prefilter tables
+    /// dwarf the regex variant.
+    Literal(Box<memchr::memmem::Finder<'static>>),
+    Regex(regex::Regex),
+}
+
+/// Compile a pattern's `find`, rejecting empty search text, invalid regex
+/// syntax and regexes whose compiled form exceeds 1MB. Called both to
+/// validate at registration and to build the searcher at apply time.
+fn compile_pattern(pattern: &ReplacePattern) -> Result<CompiledFind, String> {
+    if pattern.find.is_empty() {
+        return Err("replace pattern with empty search text".to_string());
+    }
+    if pattern.regex {
+        regex::RegexBuilder::new(&pattern.find)
+            .size_limit(1 << 20)
+            .build()
+            .map(CompiledFind::Regex)
+            .map_err(|e| format!("invalid replace pattern '{}': {e}", pattern.find))
+    } else {
+        Ok(CompiledFind::Literal(Box::new(
+            memchr::memmem::Finder::new(pattern.find.as_bytes()).into_owned(),
+        )))
+    }
+}
+
+/// Apply one compiled pattern to a node's text, allocating only when it
+/// matches; returns whether the text changed.
+fn apply_pattern(find: &CompiledFind, replace: &str, text: &mut String) -> bool {
+    match find {
+        CompiledFind::Literal(finder) => {
+            if finder.find(text.as_bytes()).is_none() {
+                return false;
+            }
+            let needle_len = finder.needle().len();
+            let mut out = String::with_capacity(text.len());
+            let mut last = 0;
+            for at in finder.find_iter(text.as_bytes()) {
+                out.push_str(&text[last..at]);
+                out.push_str(replace);
+                last = at + needle_len;
+            }
+            out.push_str(&text[last..]);
+            *text = out;
+            true
+        }
+        CompiledFind::Regex(re) => {
+            if !re.is_match(text) {
+                return false;
+            }
+            *text = re.replace_all(text, replace).into_owned();
+            true
+        }
+    }
+}
+
+/// Replace `{{key}}` markers in `text`. Explicit template variables win
+/// over the built-in `date`/`time`; unknown markers are left untouched and
+/// their keys collected into `unresolved`.
+fn resolve_variables(
+    text: &mut String,
+    template: &Template,
+    date: &str,
+    time: &str,
+    unresolved: &mut BTreeSet<String>,
+) {
+    if !text.contains("{{") {
+        return;
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    while let Some(open) = rest.find("{{") {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let Some(close) = after.find("}}") else {
+            out.push_str(&rest[open..]);
+            rest = "";
+            break;
+        };
+        let key = &after[..close];
+        match template.variables.get(key).map(String::as_str) {
+            Some(value) => out.push_str(value),
+            None if key == "date" => out.push_str(date),
+            None if key == "time" => out.push_str(time),
+            None => {
+                unresolved.insert(key.to_string());
+                out.push_str("{{");
+                out.push_str(key);
+                out.push_str("}}");
+            }
+        }
+        rest = &after[close + 2..];
+    }
+    out.push_str(rest);
+    *text = out;
+}
+
+/// Map a VB6-style date pattern to a chrono format string. Tokens:
+/// `YYYY`, `YY`, `MM`, `DD`, `HH`, `NN` (minutes, as in VB6), `SS`.
+/// Anything alphabetic outside those tokens is an error.
+fn date_pattern_to_chrono(pattern: &str) -> Result<String, String> {
+    const TOKENS: &[(&str, &str)] = &[
+        ("YYYY", "%Y"),
+        ("YY", "%y"),
+        ("MM", "%m"),
+        ("DD", "%d"),
+        ("HH", "%H"),
+        ("NN", "%M"),
+        ("SS", "%S"),
+    ];
+    let mut out = String::new();
+    let mut rest = pattern;
+    'outer: while !rest.is_empty() {
+        for (token, replacement) in TOKENS {
+            if rest.starts_with(token) {
+                out.push_str(replacement);
+                rest = &rest[token.len()..];
+                continue 'outer;
+            }
+        }
+        let c = rest.chars().next().unwrap();
+        if c.is_alphabetic() || c == '%' {
+            return Err(format!(
+                "unsupported token '{c}' in date format '{pattern}'"
+            ));
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    Ok(out)
+}
+
+/// A parsed VB6-style numeric pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NumberFormat {
+    /// Emit thousands separators (`#,##0` style).
+    grouping: bool,
+    /// Digits after the decimal separator.
+    decimals: usize,
+}
+
+/// Parse a VB6-style numeric pattern: `#` and `0` placeholders, one
+/// optional `,` for grouping and one optional `.` decimal section.
+fn parse_number_format(pattern: &str) -> Result<NumberFormat, String> {
+    if pattern.is_empty() || !pattern.chars().all(|c| matches!(c, '#' | '0' | ',' | '.')) {
+        return Err(format!("invalid number format '{pattern}'"));
+    }
+    let mut sections = pattern.split('.');
+    let integer = sections.next().unwrap_or_default();
+    let decimal = sections.next().unwrap_or_default();
+    if sections.next().is_some() || integer.is_empty() || decimal.contains(',') {
+        return Err(format!("invalid number format '{pattern}'"));
+    }
+    Ok(NumberFormat {
+        grouping: integer.contains(','),
+        decimals: decimal.len(),
+    })
+}
+
+/// Reformat every numeric literal in `text`. Source literals use US
+/// conventions (`.` decimal, optional `,` grouping); output follows the
+/// requested locale. Non-numeric text passes through untouched.
+fn reformat_numbers(text: &str, format: &NumberFormat, locale: NumberLocale) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(|c: char| c.is_ascii_digit()) {
+        out.push_str(&rest[..start]);
+        let candidate = &rest[start..];
+        let end = candidate
+            .find(|c: char| !c.is_ascii_digit() && c != ',' && c != '.')
+            .unwrap_or(candidate.len());
+        // Trailing separators belong to the sentence, not the number.
+        let literal = candidate[..end].trim_end_matches(['.', ',']);
+        match literal.replace(',', "").parse::<f64>() {
+            Ok(value) => out.push_str(&format_number(value, format, locale)),
+            Err(_) => out.push_str(literal),
+        }
+        rest = &candidate[literal.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn format_number(value: f64, format: &NumberFormat, locale: NumberLocale) -> String {
+    let (decimal_sep, grouping_sep) = match locale {
+        NumberLocale::Us => ('.', ','),
+        NumberLocale::Eu => (',', '.'),
+    };
+    let rendered = format!("{value:.*}", format.decimals);
+    let (integer, decimal) = match rendered.split_once('.') {
+        Some((i, d)) => (i, Some(d)),
+        None => (rendered.as_str(), None),
+    };
+    let (sign, digits) = match integer.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", integer),
+    };
+    let mut out = String::from(sign);
+    if format.grouping {
+        let len = digits.len();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (len - i) % 3 == 0 {
+                out.push(grouping_sep);
+            }
+            out.push(c);
+        }
+    } else {
+        out.push_str(digits);
+    }
+    if let Some(decimal) = decimal {
+        out.push(decimal_sep);
+        out.push_str(decimal);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::{lexer::tokenize, rtf_parser::RtfParser};
+    use chrono::NaiveDate;
+
+    fn parse(rtf: &str) -> RtfDocument {
+        RtfParser::new(tokenize(rtf).unwrap()).parse().unwrap()
+    }
+
+    fn fixed_now() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 3, 7)
+            .unwrap()
+            .and_hms_opt(14, 5, 9)
+            .unwrap()
+    }
+
+    #[test]
+    fn builtin_name_guard_matches_the_builtin_library() {
+        for template in builtin_templates() {
+            assert!(is_builtin_name(&template.name), "{}", template.name);
+        }
+        assert!(!is_builtin_name("bespoke"));
+    }
+
+    #[test]
+    fn date_patterns_map_to_chrono() {
+        assert_eq!(date_pattern_to_chrono("MM/DD/YYYY").unwrap(), "%m/%d/%Y");
+        assert_eq!(date_pattern_to_chrono("DD.MM.YYYY").unwrap(), "%d.%m.%Y");
+        assert_eq!(
+            date_pattern_to_chrono("YYYY-MM-DD HH:NN:SS").unwrap(),
+            "%Y-%m-%d %H:%M:%S"
+        );
+        assert!(date_pattern_to_chrono("MM/QQ/YYYY").is_err());
+    }
+
+    #[test]
+    fn invalid_formats_are_rejected_at_registration() {
+        let mut system = TemplateSystem::new();
+        let mut template = Template::new("bad-date", TemplateType::Memo);
+        template.settings.date_format = "Month DD".to_string();
+        assert!(system.register(template).unwrap_err().contains("bad-date"));
+
+        let mut template = Template::new("bad-number", TemplateType::Memo);
+        template.settings.number_format = "#,##0.0.0".to_string();
+        assert!(system
+            .register(template)
+            .unwrap_err()
+            .contains("invalid number format"));
+    }
+
+    #[test]
+    fn memo_template_resolves_date_with_us_format() {
+        let system = TemplateSystem::new();
+        // Literal braces are escaped in RTF source.
+        let mut document = parse("{\\rtf1 Date: \\{\\{date\\}\\} at \\{\\{time\\}\\}\\par}");
+        system.apply_at("memo", &mut document, fixed_now()).unwrap();
+        assert_eq!(
+            document.plain_text().trim(),
+            "Date: 03/07/2024 at 14:05:09"
+        );
+    }
+
+    #[test]
+    fn report_template_formats_numbers() {
+        let system = TemplateSystem::new();
+        let mut document = parse("{\\rtf1 Total 1234567.8 units\\par}");
+        system
+            .apply_at("report", &mut document, fixed_now())
+            .unwrap();
+        assert_eq!(document.plain_text().trim(), "Total 1,234,567.80 units");
+    }
+
+    #[test]
+    fn european_template_uses_comma_decimals_and_dotted_dates() {
+        let mut system = TemplateSystem::new();
+        let mut template = Template::new("de-report", TemplateType::Report);
+        template.settings.date_format = "DD.MM.YYYY".to_string();
+        template.transformations.push(Transformation::FormatNumbers {
+            locale: NumberLocale::Eu,
+            scope: NumberScope::Document,
+        });
+        system.register(template).unwrap();
+
+        let mut document = parse("{\\rtf1 Stand \\{\\{date\\}\\}: 1234.5 Einheiten\\par}");
+        system
+            .apply_at("de-report", &mut document, fixed_now())
+            .unwrap();
+        assert_eq!(
+            document.plain_text().trim(),
+            "Stand 07.03.2024: 1.234,50 Einheiten"
+        );
+    }
+
+    #[test]
+    fn explicit_variables_override_builtins_and_unknowns_survive() {
+        let mut system = TemplateSystem::new();
+        let mut template = Template::new("custom", TemplateType::Memo);
+        template
+            .variables
+            .insert("date".to_string(), "today".to_string());
+        system.register(template).unwrap();
+
+        let mut document = parse("{\\rtf1 \\{\\{date\\}\\} \\{\\{who\\}\\}\\par}");
+        system
+            .apply_at("custom", &mut document, fixed_now())
+            .unwrap();
+        assert_eq!(document.plain_text().trim(), "today {{who}}");
+    }
+
+    /// A built-in with caller-supplied variables, registered under the
+    /// same name the way GUI callers do.
+    fn with_variables(name: &str, variables: &[(&str, &str)]) -> TemplateSystem {
+        let mut system = TemplateSystem::new();
+        let mut template = system.get(name).unwrap().clone();
+        for (key, value) in variables {
+            template
+                .variables
+                .insert(key.to_string(), value.to_string());
+        }
+        system.register(template).unwrap();
+        system
+    }
+
+    #[test]
+    fn letter_template_scaffolds_address_blocks_and_signature() {
+        let system = with_variables(
+            "letter",
+            &[
+                ("sender_name", "A. Vendor"),
+                ("sender_address", "1 Supply Rd"),
+                ("addressee_name", "B. Customer"),
+                ("addressee_address", "2 Demand St"),
+                ("signature_name", "A. Vendor"),
+            ],
+        );
+        let mut document = parse("{\\rtf1 Please find our offer enclosed.\\par}");
+        system
+            .apply_at("letter", &mut document, fixed_now())
+            .unwrap();
+        let markdown = crate::conversion::markdown_generator::MarkdownGenerator::new()
+            .generate(&document);
+        let expected_order = [
+            "03/07/2024",
+            "A. Vendor",
+            "1 Supply Rd",
+            "B. Customer",
+            "2 Demand St",
+            "Please find our offer enclosed.",
+            "Sincerely,",
+        ];
+        let mut cursor = 0;
+        for needle in expected_order {
+            let at = markdown[cursor..]
+                .find(needle)
+                .unwrap_or_else(|| panic!("'{needle}' missing or out of order in: {markdown}"));
+            cursor += at + needle.len();
+        }
+        assert_eq!(document.styles.len(), 1, "letter adds its Emphasis style");
+    }
+
+    #[test]
+    fn loads_and_applies_a_template_file() {
+        let mut system = TemplateSystem::new();
+        let name = system
+            .load_json(
+                r#"{
+                    "name": "status",
+                    "template_type": "report",
+                    "variables": {"department": "Fulfillment"},
+                    "transformations": [
+                        {"type": "replace_pattern", "find": "TBD", "replace": "pending"}
+                    ],
+                    "header": "{{department}} status - {{date}}"
+                }"#,
+            )
+            .unwrap();
+        assert_eq!(name, "status");
+        let mut document = parse("{\\rtf1 Delivery date TBD\\par}");
+        system
+            .apply_at("status", &mut document, fixed_now())
+            .unwrap();
+        let text = document.plain_text();
+        assert!(text.contains("Fulfillment status - 03/07/2024"), "{text}");
+        assert!(text.contains("Delivery date pending"), "{text}");
+    }
+
+    #[test]
+    fn template_file_errors_name_the_broken_limit_or_parameter() {
+        let mut system = TemplateSystem::new();
+        let err = system
+            .load_json(r#"{"name": "t", "transformations": [{"type": "totals_row"}]}"#)
+            .unwrap_err();
+        assert!(err.contains("missing its required parameter 'label'"), "{err}");
+        let err = system
+            .load_json(
+                r#"{"name": "t", "transformations": [{"type": "number_headings", "label": "x"}]}"#,
+            )
+            .unwrap_err();
+        assert!(err.contains("does not take a parameter 'label'"), "{err}");
+        let err = system
+            .load_json(r#"{"name": "t", "transformations": [{"type": "shout"}]}"#)
+            .unwrap_err();
+        assert!(err.contains("unknown transformation type 'shout'"), "{err}");
+        let big = "x".repeat(MAX_VARIABLE_KEY_LEN + 1);
+        let err = system
+            .load_json(&format!(r#"{{"name": "t", "variables": {{"{big}": "v"}}}}"#))
+            .unwrap_err();
+        assert!(err.contains("over the limit of 64"), "{err}");
+    }
+
+    #[test]
+    fn oversized_template_file_is_rejected_without_reading_it() {
+        let dir = std::env::temp_dir().join(format!("lb-template-big-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("huge.json");
+        // A sparse 50MB file; the size check reads only the metadata.
+        let file = std::fs::File::create(&path).unwrap();
+        file.set_len(50 * 1024 * 1024).unwrap();
+        let started = std::time::Instant::now();
+        let err = TemplateSystem::new()
+            .load_file(&path.to_string_lossy())
+            .unwrap_err();
+        assert!(err.contains("template file size limit"), "{err}");
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn directory_templates_extend_in_file_name_order() {
+        let dir = std::env::temp_dir().join(format!("lb-templates-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("10-base.json"),
+            r#"{"name": "base", "variables": {"department": "Sales"},
+                "header": "{{department}} - {{date}}"}"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("20-child.json"),
+            r#"{"name": "child", "extends": "base",
+                "variables": {"department": "Support"}}"#,
+        )
+        .unwrap();
+        let mut system = TemplateSystem::new();
+        let names = system.load_directory(&dir.to_string_lossy()).unwrap();
+        assert_eq!(names, ["base", "child"]);
+        let mut document = parse("{\\rtf1 Body\\par}");
+        system
+            .apply_at("child", &mut document, fixed_now())
+            .unwrap();
+        let text = document.plain_text();
+        assert!(text.contains("Support - 03/07/2024"), "{text}");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn inheritance_depth_is_limited() {
+        let mut system = TemplateSystem::new();
+        system.load_json(r#"{"name": "d0"}"#).unwrap();
+        for depth in 1..=MAX_INHERITANCE_DEPTH {
+            system
+                .load_json(&format!(
+                    r#"{{"name": "d{depth}", "extends": "d{}"}}"#,
+                    depth - 1
+                ))
+                .unwrap();
+        }
+        let err = system
+            .load_json(&format!(
+                r#"{{"name": "d9", "extends": "d{MAX_INHERITANCE_DEPTH}"}}"#
+            ))
+            .unwrap_err();
+        assert!(err.contains("inheritance depth 9"), "{err}");
+    }
+
+    #[test]
+    fn invoice_template_appends_a_formatted_totals_row() {
+        let system = with_variables("invoice", &[("invoice_number", "INV-7")]);
+        let mut document = parse(
+            "{\\rtf1 \\trowd\\intbl Widget\\cell 1234.5\\cell\\row\
+             \\trowd\\intbl Gadget\\cell 100\\cell\\row}",
+        );
+        system
+            .apply_at("invoice", &mut document, fixed_now())
+            .unwrap();
+        let markdown = crate::conversion::markdown_generator::MarkdownGenerator::new()
+            .generate(&document);
+        assert!(markdown.contains("INVOICE INV-7 - 03/07/2024"), "{markdown}");
+        assert!(markdown.contains("1,234.50"), "{markdown}");
+        assert!(markdown.contains("Total"), "{markdown}");
+        assert!(markdown.contains("1,334.50"), "{markdown}");
+        assert!(markdown.contains("Thank you for your business."), "{markdown}");
+    }
+
+    #[test]
+    fn contract_template_numbers_headings_and_adds_footer() {
+        // Markdown source, RTF output: the other conversion direction.
+        let markdown = "# Scope\n\nWork.\n\n## Deliverables\n\nThings.\n\n# Payment\n\nNet 30.";
+        let mut document = crate::conversion::markdown_parser::MarkdownParser::new()
+            .parse(markdown)
+            .unwrap();
+        let system = with_variables("contract", &[("party_a", "Acme"), ("party_b", "Widgets Ltd")]);
+        system
+            .apply_at("contract", &mut document, fixed_now())
+            .unwrap();
+        let rtf = crate::conversion::rtf_generator::RtfGenerator::new()
+            .generate(&document)
+            .unwrap();
+        assert!(
+            rtf.contains("between Acme and Widgets Ltd, effective 03/07/2024"),
+            "{rtf}"
+        );
+        assert!(rtf.contains("1 Scope"), "{rtf}");
+        assert!(rtf.contains("1.1 Deliverables"), "{rtf}");
+        assert!(rtf.contains("2 Payment"), "{rtf}");
+        assert!(rtf.contains("CONFIDENTIAL - Acme / Widgets Ltd"), "{rtf}");
+    }
+
+    #[test]
+    fn report_template_keeps_headings_with_their_body() {
+        let markdown = "# Findings\n\nNumbers hold up.\n\n## Detail\n\nMore numbers.";
+        let mut document = crate::conversion::markdown_parser::MarkdownParser::new()
+            .parse(markdown)
+            .unwrap();
+        TemplateSystem::new()
+            .apply_at("report", &mut document, fixed_now())
+            .unwrap();
+        let rtf = crate::conversion::rtf_generator::RtfGenerator::new()
+            .generate(&document)
+            .unwrap();
+        // Both headings carry \keepn; body paragraphs do not.
+        assert_eq!(rtf.matches("\\keepn").count(), 2, "{rtf}");
+        assert!(rtf.contains("\\keepn\\sb240\\sa120\\outlinelevel0\\b\\fs48 Findings"), "{rtf}");
+        assert!(rtf.contains("\\pard\\fs22 Numbers hold up."), "{rtf}");
+    }
+
+    #[test]
+    fn spacing_settings_convert_points_to_twips_in_generated_rtf() {
+        let mut system = TemplateSystem::new();
+        let mut template = Template::new("spaced", TemplateType::Report);
+        template.paragraph_settings.headings.space_after = Some(12);
+        template.paragraph_settings.body.line_spacing = Some(1.5);
+        system.register(template).unwrap();
+
+        let mut document = parse("{\\rtf1 \\outlinelevel0 Title\\par \\pard Body\\par}");
+        system
+            .apply_at("spaced", &mut document, fixed_now())
+            .unwrap();
+        let rtf = crate::conversion::rtf_generator::RtfGenerator::new()
+            .generate(&document)
+            .unwrap();
+        // 12pt after headings = 240 twips; 1.5-line spacing = \sl360\slmult1.
+        assert!(rtf.contains("\\sa240"), "{rtf}");
+        assert!(rtf.contains("\\sl360\\slmult1"), "{rtf}");
+    }
+
+    #[test]
+    fn preview_reports_changes_without_touching_the_document() {
+        let system = TemplateSystem::new();
+        let document = parse(
+            "{\\rtf1 \\trowd\\intbl Widget\\cell 1234.5\\cell\\row\
+             \\trowd\\intbl Gadget\\cell 100\\cell\\row}",
+        );
+        let before = document.clone();
+        let diff = system
+            .preview_at("invoice", &document, fixed_now())
+            .unwrap();
+        assert_eq!(document, before, "preview must not mutate");
+
+        assert_eq!(diff.metadata_changes, Vec::<String>::new());
+        assert_eq!(
+            diff.header.as_deref(),
+            Some("INVOICE {{invoice_number}} - 03/07/2024")
+        );
+        assert_eq!(diff.footer.as_deref(), Some("Thank you for your business."));
+        assert_eq!(diff.unresolved_variables, vec!["invoice_number"]);
+
+        let totals = &diff.transformations[0];
+        assert_eq!(totals.description, "append totals row to tables");
+        assert_eq!(totals.touched, 1);
+        assert_eq!(totals.samples, vec!["Total 1,334.50"]);
+        let numbers = &diff.transformations[1];
+        assert_eq!(numbers.description, "reformat numeric literals");
+        assert!(numbers.touched >= 2, "both amount cells change");
+    }
+
+    fn with_patterns(patterns: &[(&str, &str, bool)]) -> TemplateSystem {
+        let mut system = TemplateSystem::new();
+        let mut template = Template::new("patterns", TemplateType::Manual);
+        for (find, replace, regex) in patterns {
+            template
+                .transformations
+                .push(Transformation::ReplacePattern(ReplacePattern {
+                    find: find.to_string(),
+                    replace: replace.to_string(),
+                    regex: *regex,
+                }));
+        }
+        system.register(template).unwrap();
+        system
+    }
+
+    #[test]
+    fn overlapping_replace_patterns_apply_in_declared_order() {
+        let system = with_patterns(&[("aa", "b", false), ("ab", "c", false)]);
+        let mut document = parse("{\\rtf1 aab\\par}");
+        system
+            .apply_at("patterns", &mut document, fixed_now())
+            .unwrap();
+        // "aa" -> "b" first, leaving "bb"; the overlapping "ab" can then
+        // never match. The reverse order would produce "ac".
+        assert_eq!(document.plain_text().trim(), "bb");
+
+        let system = with_patterns(&[("ab", "c", false), ("aa", "b", false)]);
+        let mut document = parse("{\\rtf1 aab\\par}");
+        system
+            .apply_at("patterns", &mut document, fixed_now())
+            .unwrap();
+        assert_eq!(document.plain_text().trim(), "ac");
+    }
+
+    #[test]
+    fn regex_replace_patterns_support_capture_groups() {
+        let system = with_patterns(&[(r"(\d+)-(\d+)", "$2/$1", true)]);
+        let mut document = parse("{\\rtf1 Range 3-17, plain dash - kept\\par}");
+        system
+            .apply_at("patterns", &mut document, fixed_now())
+            .unwrap();
+        assert_eq!(
+            document.plain_text().trim(),
+            "Range 17/3, plain dash - kept"
+        );
+    }
+
+    #[test]
+    fn bad_replace_patterns_are_rejected_at_registration() {
+        let mut system = TemplateSystem::new();
+        let mut template = Template::new("bad-regex", TemplateType::Manual);
+        template
+            .transformations
+            .push(Transformation::ReplacePattern(ReplacePattern {
+                find: "(unclosed".to_string(),
+                replace: String::new(),
+                regex: true,
+            }));
+        let err = system.register(template).unwrap_err();
+        assert!(err.contains("invalid replace pattern"), "{err}");
+
+        let mut template = Template::new("empty-find", TemplateType::Manual);
+        template
+            .transformations
+            .push(Transformation::ReplacePattern(ReplacePattern {
+                find: String::new(),
+                replace: "x".to_string(),
+                regex: false,
+            }));
+        let err = system.register(template).unwrap_err();
+        assert!(err.contains("empty search text"), "{err}");
+    }
+
+    #[test]
+    fn numbers_in_sentences_keep_their_punctuation() {
+        let format = NumberFormat {
+            grouping: true,
+            decimals: 2,
+        };
+        assert_eq!(
+            reformat_numbers("Pay 42. Then 9,999 more.", &format, NumberLocale::Us),
+            "Pay 42.00. Then 9,999.00 more."
+        );
+    }
+}
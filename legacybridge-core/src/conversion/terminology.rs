@@ -0,0 +1,674 @@
+//! Enterprise terminology enforcement on generated Markdown.
+//!
+//! Migrations are the one moment an organization touches every legacy
+//! document, so style owners use them to retire banned vocabulary. A
+//! [`TerminologyConfig`] carries an ordered list of rules - a term plus
+//! its preferred replacement, or no replacement for a banned phrase that
+//! should only be flagged - with per-rule case handling and word-boundary
+//! matching. The pipeline applies it to the generated Markdown under
+//! [`PipelineConfig::terminology`](super::pipeline::PipelineConfig::terminology),
+//! after output verification; code spans, fenced code blocks and URLs
+//! are exempt, since identifiers and links mean what they say.
+
+use serde::{Deserialize, Serialize};
+
+/// Most replacements and flagged matches listed individually in
+/// validation results; beyond this an overflow line carries the
+/// remaining count.
+pub const MAX_LISTED_MATCHES: usize = 20;
+
+/// Rule-count cap; every text position probes every rule until one
+/// matches, and a list beyond this is almost certainly generated wrong.
+const MAX_RULES: usize = 256;
+
+/// How a rule's pattern is matched against the text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaseHandling {
+    /// Match case-insensitively (ASCII folding) and shape the
+    /// replacement to the matched text: "login" -> "sign in",
+    /// "Login" -> "Sign in", "LOGIN" -> "SIGN IN".
+    #[default]
+    Preserve,
+    /// Match the pattern exactly as written; the replacement is used
+    /// verbatim.
+    Exact,
+}
+
+/// One terminology rule: a literal term or phrase (not a regex) plus
+/// what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminologyRule {
+    /// The term to find.
+    pub pattern: String,
+    /// Preferred replacement; `None` flags matches without changing
+    /// them, for banned phrases with no sanctioned substitute.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    #[serde(default)]
+    pub case: CaseHandling,
+    /// Match only whole words (default); `false` also matches inside
+    /// larger words.
+    #[serde(default = "default_word_boundary")]
+    pub word_boundary: bool,
+}
+
+fn default_word_boundary() -> bool {
+    true
+}
+
+impl Default for TerminologyRule {
+    fn default() -> Self {
+        TerminologyRule {
+            pattern: String::new(),
+            replacement: None,
+            case: CaseHandling::default(),
+            word_boundary: true,
+        }
+    }
+}
+
+/// Ordered terminology rules; at any text position the first matching
+/// rule wins.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminologyConfig {
+    #[serde(default)]
+    pub rules: Vec<TerminologyRule>,
+}
+
+impl TerminologyConfig {
+    /// Load a config from its JSON form:
+    /// `{"rules": [{"pattern": "login", "replacement": "sign in"}, {"pattern": "kill switch"}]}`.
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        serde_json::from_str(json).map_err(|e| format!("invalid terminology JSON: {e}"))
+    }
+
+    /// Load a config from CSV rows of
+    /// `pattern,replacement[,case[,boundary]]`: an empty replacement
+    /// means flag-only, `case` is `preserve` (default) or `exact`, and
+    /// `boundary` is `word` (default) or `anywhere`. Blank lines, `#`
+    /// comments and a `pattern,...` header row are skipped. Fields may
+    /// be double-quoted to carry commas.
+    pub fn from_csv(csv: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for (index, line) in csv.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields = split_csv_line(line);
+            if index == 0 && fields.first().map(String::as_str) == Some("pattern") {
+                continue;
+            }
+            if fields.len() > 4 {
+                return Err(format!(
+                    "terminology CSV line {}: expected at most 4 fields, got {}",
+                    index + 1,
+                    fields.len()
+                ));
+            }
+            let field = |i: usize| fields.get(i).map(String::as_str).unwrap_or("");
+            let case = match field(2) {
+                "" | "preserve" => CaseHandling::Preserve,
+                "exact" => CaseHandling::Exact,
+                other => {
+                    return Err(format!(
+                        "terminology CSV line {}: unknown case handling '{other}' \
+                         (expected 'preserve' or 'exact')",
+                        index + 1
+                    ));
+                }
+            };
+            let word_boundary = match field(3) {
+                "" | "word" => true,
+                "anywhere" => false,
+                other => {
+                    return Err(format!(
+                        "terminology CSV line {}: unknown boundary '{other}' \
+                         (expected 'word' or 'anywhere')",
+                        index + 1
+                    ));
+                }
+            };
+            rules.push(TerminologyRule {
+                pattern: field(0).to_string(),
+                replacement: (!field(1).is_empty()).then(|| field(1).to_string()),
+                case,
+                word_boundary,
+            });
+        }
+        Ok(TerminologyConfig { rules })
+    }
+
+    /// Load a config file, dispatching on extension: `.csv` takes the
+    /// [`from_csv`](Self::from_csv) form, everything else the JSON one.
+    pub fn from_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read terminology config {path}: {e}"))?;
+        if path.to_ascii_lowercase().ends_with(".csv") {
+            Self::from_csv(&content)
+        } else {
+            Self::from_json(&content)
+        }
+    }
+
+    /// Validate the rules for repeated use across documents.
+    pub fn compile(&self) -> Result<CompiledTerminology, String> {
+        if self.rules.len() > MAX_RULES {
+            return Err(format!(
+                "terminology config has {} rules; the limit is {MAX_RULES}",
+                self.rules.len()
+            ));
+        }
+        for rule in &self.rules {
+            if rule.pattern.is_empty() {
+                return Err("terminology rule with empty pattern".to_string());
+            }
+        }
+        Ok(CompiledTerminology {
+            rules: self.rules.clone(),
+        })
+    }
+}
+
+/// Split one CSV line into trimmed fields, honoring double quotes (with
+/// `""` as an escaped quote) so replacements can carry commas.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_string());
+    fields
+}
+
+/// A [`TerminologyConfig`] validated and ready to apply.
+#[derive(Debug)]
+pub struct CompiledTerminology {
+    rules: Vec<TerminologyRule>,
+}
+
+/// One match [`CompiledTerminology::apply`] acted on, for validation
+/// reporting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TerminologyMatch {
+    /// The text as it appeared in the document.
+    pub found: String,
+    /// What it became; `None` for flag-only rules.
+    pub replaced_with: Option<String>,
+    /// 1-based line of the match in the input.
+    pub line: usize,
+    /// 1-based column of the match, in characters.
+    pub column: usize,
+}
+
+/// What [`CompiledTerminology::apply`] did. The listing vectors cap at
+/// [`MAX_LISTED_MATCHES`]; the counts don't.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TerminologyOutcome {
+    /// Applied replacements, capped.
+    pub replacements: Vec<TerminologyMatch>,
+    /// Every replacement, including those past the listing cap.
+    pub replacement_count: usize,
+    /// Flag-only matches, capped.
+    pub flagged: Vec<TerminologyMatch>,
+    /// Every flag-only match, including those past the cap.
+    pub flagged_count: usize,
+}
+
+impl CompiledTerminology {
+    /// Enforce the rules on `markdown`, returning the rewritten text and
+    /// what happened. Inline code spans, fenced code blocks and URLs
+    /// (link destinations, autolinks, bare `scheme://` runs) are left
+    /// untouched and unreported.
+    pub fn apply(&self, markdown: &str) -> (String, TerminologyOutcome) {
+        let mut outcome = TerminologyOutcome::default();
+        if self.rules.is_empty() {
+            return (markdown.to_string(), outcome);
+        }
+        let protected = protected_ranges(markdown);
+        let mut out = String::with_capacity(markdown.len());
+        let mut i = 0;
+        let mut next_range = 0;
+        let mut line = 1;
+        let mut column = 1;
+
+        // Copy `text` to the output verbatim, keeping the line/column
+        // counters in step with the input position.
+        let copy = |text: &str, out: &mut String, line: &mut usize, column: &mut usize| {
+            out.push_str(text);
+            for c in text.chars() {
+                if c == '\n' {
+                    *line += 1;
+                    *column = 1;
+                } else {
+                    *column += 1;
+                }
+            }
+        };
+
+        while i < markdown.len() {
+            while next_range < protected.len() && protected[next_range].1 <= i {
+                next_range += 1;
+            }
+            if let Some(&(start, end)) = protected.get(next_range) {
+                if start <= i {
+                    copy(&markdown[i..end], &mut out, &mut line, &mut column);
+                    i = end;
+                    next_range += 1;
+                    continue;
+                }
+            }
+            // A match may not run into the next protected range.
+            let limit = protected
+                .get(next_range)
+                .map(|&(start, _)| start)
+                .unwrap_or(markdown.len());
+            if let Some((rule, found)) = self.match_at(markdown, i, limit) {
+                let matched = TerminologyMatch {
+                    found: found.to_string(),
+                    replaced_with: rule
+                        .replacement
+                        .as_ref()
+                        .map(|r| shaped(rule, found, r)),
+                    line,
+                    column,
+                };
+                match &matched.replaced_with {
+                    Some(replacement) => {
+                        out.push_str(replacement);
+                        outcome.replacement_count += 1;
+                        if outcome.replacements.len() < MAX_LISTED_MATCHES {
+                            outcome.replacements.push(matched.clone());
+                        }
+                        // Advance the counters over the consumed input.
+                        for c in found.chars() {
+                            if c == '\n' {
+                                line += 1;
+                                column = 1;
+                            } else {
+                                column += 1;
+                            }
+                        }
+                    }
+                    None => {
+                        outcome.flagged_count += 1;
+                        if outcome.flagged.len() < MAX_LISTED_MATCHES {
+                            outcome.flagged.push(matched.clone());
+                        }
+                        copy(found, &mut out, &mut line, &mut column);
+                    }
+                }
+                i += found.len();
+                continue;
+            }
+            let c = markdown[i..].chars().next().expect("in-bounds position");
+            let mut buffer = [0u8; 4];
+            copy(c.encode_utf8(&mut buffer), &mut out, &mut line, &mut column);
+            i += c.len_utf8();
+        }
+        (out, outcome)
+    }
+
+    /// The first rule matching at byte position `i`, with the matched
+    /// slice; matches may not extend past `limit`.
+    fn match_at<'a>(
+        &self,
+        text: &'a str,
+        i: usize,
+        limit: usize,
+    ) -> Option<(&TerminologyRule, &'a str)> {
+        for rule in &self.rules {
+            let end = i + rule.pattern.len();
+            if end > limit || !text.is_char_boundary(end) {
+                continue;
+            }
+            let candidate = &text[i..end];
+            let matches = match rule.case {
+                CaseHandling::Exact => candidate == rule.pattern,
+                CaseHandling::Preserve => candidate.eq_ignore_ascii_case(&rule.pattern),
+            };
+            if !matches {
+                continue;
+            }
+            if rule.word_boundary {
+                let before = text[..i].chars().next_back();
+                let after = text[end..].chars().next();
+                if before.is_some_and(char::is_alphanumeric)
+                    || after.is_some_and(char::is_alphanumeric)
+                {
+                    continue;
+                }
+            }
+            return Some((rule, candidate));
+        }
+        None
+    }
+}
+
+/// The replacement shaped to the matched text under
+/// [`CaseHandling::Preserve`]: all-caps matches uppercase it, a leading
+/// capital capitalizes it, anything else takes it as written.
+fn shaped(rule: &TerminologyRule, found: &str, replacement: &str) -> String {
+    if rule.case == CaseHandling::Exact {
+        return replacement.to_string();
+    }
+    let letters: Vec<char> = found.chars().filter(|c| c.is_alphabetic()).collect();
+    if letters.len() > 1 && letters.iter().all(|c| c.is_uppercase()) {
+        replacement.to_uppercase()
+    } else if letters.first().is_some_and(|c| c.is_uppercase()) {
+        let mut chars = replacement.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new(),
+        }
+    } else {
+        replacement.to_string()
+    }
+}
+
+/// Byte ranges of `markdown` terminology must not touch: fenced code
+/// blocks, inline code spans, and URLs. Sorted and non-overlapping.
+fn protected_ranges(markdown: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    // Fenced code blocks first, tracked line by line; inline constructs
+    // are then scanned only in the gaps between fences.
+    let mut fence: Option<(usize, char, usize)> = None;
+    let mut offset = 0;
+    let mut gap_start = 0;
+    for line in markdown.split_inclusive('\n') {
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        let fence_len = |c: char| trimmed.chars().take_while(|&x| x == c).count();
+        match fence {
+            None if indent <= 3 && (fence_len('`') >= 3 || fence_len('~') >= 3) => {
+                let c = if fence_len('`') >= 3 { '`' } else { '~' };
+                scan_inline(markdown, gap_start, offset, &mut ranges);
+                fence = Some((offset, c, fence_len(c)));
+            }
+            Some((start, c, len))
+                if indent <= 3
+                    && fence_len(c) >= len
+                    && trimmed.trim_end().chars().all(|x| x == c) =>
+            {
+                ranges.push((start, offset + line.len()));
+                fence = None;
+                gap_start = offset + line.len();
+            }
+            _ => {}
+        }
+        offset += line.len();
+    }
+    match fence {
+        // An unclosed fence runs to the end of input.
+        Some((start, ..)) => ranges.push((start, markdown.len())),
+        None => scan_inline(markdown, gap_start, markdown.len(), &mut ranges),
+    }
+    ranges.sort_unstable();
+    merge(ranges)
+}
+
+/// Find inline code spans and URLs in `markdown[from..to]`.
+fn scan_inline(markdown: &str, from: usize, to: usize, ranges: &mut Vec<(usize, usize)>) {
+    let bytes = markdown.as_bytes();
+    let mut i = from;
+    while i < to {
+        match bytes[i] {
+            b'`' => {
+                let run_start = i;
+                while i < to && bytes[i] == b'`' {
+                    i += 1;
+                }
+                let len = i - run_start;
+                if let Some(end) = closing_backtick_run(bytes, i, to, len) {
+                    ranges.push((run_start, end));
+                    i = end;
+                }
+            }
+            // Inline link destination: `](` up to the matching `)`. The
+            // display text before it stays subject to the rules.
+            b']' if i + 1 < to && bytes[i + 1] == b'(' => {
+                let dest = i + 2;
+                let mut depth = 1usize;
+                let mut j = dest;
+                while j < to {
+                    match bytes[j] {
+                        b'(' => depth += 1,
+                        b')' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        _ => {}
+                    }
+                    j += 1;
+                }
+                if j < to {
+                    ranges.push((dest, j));
+                    i = j + 1;
+                } else {
+                    i += 2;
+                }
+            }
+            // A bare URL (which also covers `<autolinks>`): extend the
+            // `://` left over the scheme and right to the end of the run.
+            b':' if markdown[i..to].starts_with("://") => {
+                let mut start = i;
+                while start > from && bytes[start - 1].is_ascii_alphanumeric() {
+                    start -= 1;
+                }
+                let mut end = i + 3;
+                while end < to && !matches!(bytes[end], b' ' | b'\t' | b'\n' | b')' | b'>' | b'"' | b'`')
+                {
+                    end += 1;
+                }
+                ranges.push((start, end));
+                i = end;
+            }
+            _ => i += 1,
+        }
+    }
+}
+
+/// End (exclusive) of the next backtick run of exactly `len` in
+/// `bytes[from..to]`, closing an inline code span.
+fn closing_backtick_run(bytes: &[u8], from: usize, to: usize, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < to {
+        if bytes[i] == b'`' {
+            let run_start = i;
+            while i < to && bytes[i] == b'`' {
+                i += 1;
+            }
+            if i - run_start == len {
+                return Some(i);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Merge overlapping or touching sorted ranges.
+fn merge(ranges: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    let mut merged: Vec<(usize, usize)> = Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = (*last_end).max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replace_rule(pattern: &str, replacement: &str) -> TerminologyRule {
+        TerminologyRule {
+            pattern: pattern.to_string(),
+            replacement: Some(replacement.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn apply(rules: Vec<TerminologyRule>, markdown: &str) -> (String, TerminologyOutcome) {
+        TerminologyConfig { rules }.compile().unwrap().apply(markdown)
+    }
+
+    #[test]
+    fn replacement_preserves_the_matched_case_shape() {
+        let rules = vec![replace_rule("login", "sign in")];
+        let (out, outcome) =
+            apply(rules, "Login here. To login, use the LOGIN page.\n");
+        assert_eq!(out, "Sign in here. To sign in, use the SIGN IN page.\n");
+        assert_eq!(outcome.replacement_count, 3);
+        assert_eq!(outcome.replacements[0].found, "Login");
+        assert_eq!(
+            outcome.replacements[0].replaced_with.as_deref(),
+            Some("Sign in")
+        );
+        assert_eq!((outcome.replacements[0].line, outcome.replacements[0].column), (1, 1));
+        assert_eq!((outcome.replacements[1].line, outcome.replacements[1].column), (1, 16));
+    }
+
+    #[test]
+    fn flag_only_rules_report_without_rewriting() {
+        let rules = vec![TerminologyRule {
+            pattern: "kill switch".to_string(),
+            ..Default::default()
+        }];
+        let (out, outcome) = apply(rules, "Flip the kill switch.\n");
+        assert_eq!(out, "Flip the kill switch.\n");
+        assert_eq!(outcome.flagged_count, 1);
+        assert_eq!(outcome.flagged[0].found, "kill switch");
+        assert_eq!(outcome.flagged[0].replaced_with, None);
+        assert_eq!((outcome.flagged[0].line, outcome.flagged[0].column), (1, 10));
+        assert!(outcome.replacements.is_empty());
+    }
+
+    #[test]
+    fn word_boundaries_hold_unless_a_rule_opts_out() {
+        let rules = vec![replace_rule("login", "sign in")];
+        let (out, outcome) = apply(rules.clone(), "The loginserver handles login.\n");
+        assert_eq!(out, "The loginserver handles sign in.\n");
+        assert_eq!(outcome.replacement_count, 1);
+
+        let anywhere = vec![TerminologyRule {
+            word_boundary: false,
+            ..replace_rule("login", "sign in")
+        }];
+        let (out, _) = apply(anywhere, "The loginserver.\n");
+        assert_eq!(out, "The sign inserver.\n");
+    }
+
+    #[test]
+    fn exact_case_rules_match_and_replace_verbatim() {
+        let rules = vec![TerminologyRule {
+            case: CaseHandling::Exact,
+            ..replace_rule("Master", "Primary")
+        }];
+        let (out, outcome) = apply(rules, "Master list, master copy.\n");
+        assert_eq!(out, "Primary list, master copy.\n");
+        assert_eq!(outcome.replacement_count, 1);
+    }
+
+    #[test]
+    fn code_and_urls_are_exempt() {
+        let rules = vec![replace_rule("login", "sign in")];
+        let markdown = "Use `login()` to login.\n\
+                        ```\nlogin --force\n```\n\
+                        See [the login page](https://example.com/login) or http://example.com/login?next=1.\n";
+        let (out, outcome) = apply(rules, markdown);
+        assert_eq!(
+            out,
+            "Use `login()` to sign in.\n\
+             ```\nlogin --force\n```\n\
+             See [the sign in page](https://example.com/login) or http://example.com/login?next=1.\n"
+        );
+        assert_eq!(outcome.replacement_count, 2);
+    }
+
+    #[test]
+    fn first_matching_rule_wins_and_matches_do_not_cascade() {
+        let rules = vec![
+            replace_rule("sign in", "log on"),
+            replace_rule("login", "sign in"),
+        ];
+        let (out, _) = apply(rules, "sign in then login\n");
+        // The replacement text is not re-scanned.
+        assert_eq!(out, "log on then sign in\n");
+    }
+
+    #[test]
+    fn listing_caps_at_the_limit_but_counts_keep_going() {
+        let rules = vec![replace_rule("old", "new")];
+        let markdown = "old ".repeat(MAX_LISTED_MATCHES + 5);
+        let (_, outcome) = apply(rules, &markdown);
+        assert_eq!(outcome.replacements.len(), MAX_LISTED_MATCHES);
+        assert_eq!(outcome.replacement_count, MAX_LISTED_MATCHES + 5);
+    }
+
+    #[test]
+    fn csv_rows_load_with_defaults_and_quoting() {
+        let config = TerminologyConfig::from_csv(
+            "pattern,replacement,case,boundary\n\
+             # retired vocabulary\n\
+             login,sign in\n\
+             \"whitelist, the\",\"allowlist, the\",exact,anywhere\n\
+             kill switch\n",
+        )
+        .unwrap();
+        assert_eq!(config.rules.len(), 3);
+        assert_eq!(config.rules[0], replace_rule("login", "sign in"));
+        assert_eq!(
+            config.rules[1],
+            TerminologyRule {
+                pattern: "whitelist, the".to_string(),
+                replacement: Some("allowlist, the".to_string()),
+                case: CaseHandling::Exact,
+                word_boundary: false,
+            }
+        );
+        assert_eq!(config.rules[2].replacement, None);
+
+        let err = TerminologyConfig::from_csv("login,sign in,loudly\n").unwrap_err();
+        assert!(err.contains("unknown case handling"), "{err}");
+    }
+
+    #[test]
+    fn json_form_defaults_case_and_boundary() {
+        let config = TerminologyConfig::from_json(
+            r#"{"rules": [{"pattern": "login", "replacement": "sign in"}, {"pattern": "kill switch"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.rules[0].case, CaseHandling::Preserve);
+        assert!(config.rules[0].word_boundary);
+        assert_eq!(config.rules[1].replacement, None);
+        assert!(TerminologyConfig::from_json("not json")
+            .unwrap_err()
+            .contains("invalid terminology JSON"));
+    }
+
+    #[test]
+    fn compile_rejects_empty_patterns() {
+        let config = TerminologyConfig {
+            rules: vec![TerminologyRule::default()],
+        };
+        assert!(config.compile().unwrap_err().contains("empty pattern"));
+    }
+}
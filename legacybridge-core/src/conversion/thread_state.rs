@@ -0,0 +1,130 @@
+//! Thread-local reusable conversion state.
+//!
+//! At batch rates the fixed setup cost of a conversion - allocating the
+//! token buffer, building the template registry with its validated
+//! built-ins - dominates tiny documents. This module keeps that state
+//! per thread and lends it to the entry points: the lexer starts from
+//! the recycled token buffer and the pipeline's template stage clones
+//! the cached registry instead of rebuilding it. Everything handed out
+//! is cleared (buffers) or cloned (the registry) so no state can leak
+//! between conversions; [`reset_thread_state`] drops it all for hosts
+//! that want a pristine thread, e.g. before returning one to a pool.
+
+use super::lexer::RtfToken;
+use super::template::TemplateSystem;
+use std::cell::RefCell;
+
+/// Recycled token buffers above this capacity are dropped instead of
+/// kept, so one pathological document does not pin its peak allocation
+/// for the life of the thread.
+const MAX_RECYCLED_TOKEN_CAPACITY: usize = 64 * 1024;
+
+#[derive(Default)]
+struct ThreadState {
+    /// Cleared token buffer whose capacity the next tokenization reuses.
+    token_buffer: Vec<RtfToken>,
+    /// Template registry with the built-ins registered and validated,
+    /// cloned per use so per-call registrations stay per-call.
+    template_base: Option<TemplateSystem>,
+}
+
+thread_local! {
+    static STATE: RefCell<ThreadState> = RefCell::new(ThreadState::default());
+}
+
+/// Take the recycled token buffer (empty, capacity preserved); the
+/// lexer uses this as its output vector.
+pub(crate) fn take_token_buffer() -> Vec<RtfToken> {
+    STATE.with(|state| std::mem::take(&mut state.borrow_mut().token_buffer))
+}
+
+/// Return a token buffer for the next conversion on this thread. The
+/// buffer is cleared here; oversized ones are dropped instead.
+pub(crate) fn recycle_token_buffer(mut buffer: Vec<RtfToken>) {
+    if buffer.capacity() > MAX_RECYCLED_TOKEN_CAPACITY {
+        return;
+    }
+    buffer.clear();
+    STATE.with(|state| {
+        let mut state = state.borrow_mut();
+        if buffer.capacity() > state.token_buffer.capacity() {
+            state.token_buffer = buffer;
+        }
+    });
+}
+
+/// A [`TemplateSystem`] with the built-ins registered: a clone of the
+/// thread's cached base, so callers may register and mutate freely.
+pub(crate) fn template_system() -> TemplateSystem {
+    STATE.with(|state| {
+        state
+            .borrow_mut()
+            .template_base
+            .get_or_insert_with(TemplateSystem::new)
+            .clone()
+    })
+}
+
+/// Drop every piece of cached per-thread conversion state. Conversions
+/// never need this for correctness - the cache is cleared or cloned on
+/// every use - but hosts that hand threads back to a pool can call it
+/// to release the held allocations.
+pub fn reset_thread_state() {
+    STATE.with(|state| *state.borrow_mut() = ThreadState::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::pipeline::{DocumentPipeline, PipelineConfig};
+
+    #[test]
+    fn template_state_does_not_leak_into_plain_conversions() {
+        let rtf = "{\\rtf1 Services rendered\\par}";
+        let templated = DocumentPipeline::new(PipelineConfig {
+            template: Some("invoice".to_string()),
+            ..Default::default()
+        })
+        .process(rtf)
+        .unwrap();
+        assert!(templated.markdown.contains("INVOICE"), "{}", templated.markdown);
+
+        // Same thread, plain configuration: nothing of the template run
+        // may remain.
+        let plain = DocumentPipeline::with_defaults().process(rtf).unwrap();
+        assert!(!plain.markdown.contains("INVOICE"), "{}", plain.markdown);
+        assert!(
+            !plain.markdown.contains("Thank you for your business"),
+            "{}",
+            plain.markdown
+        );
+    }
+
+    #[test]
+    fn recycling_keeps_capacity_but_never_content() {
+        reset_thread_state();
+        let mut buffer = take_token_buffer();
+        assert!(buffer.is_empty());
+        buffer.push(RtfToken::Text("leftover".to_string()));
+        buffer.reserve(100);
+        let capacity = buffer.capacity();
+        recycle_token_buffer(buffer);
+        let reused = take_token_buffer();
+        assert!(reused.is_empty());
+        assert_eq!(reused.capacity(), capacity);
+    }
+
+    #[test]
+    fn oversized_buffers_are_dropped_not_cached() {
+        reset_thread_state();
+        recycle_token_buffer(Vec::with_capacity(MAX_RECYCLED_TOKEN_CAPACITY + 1));
+        assert_eq!(take_token_buffer().capacity(), 0);
+    }
+
+    #[test]
+    fn reset_thread_state_releases_the_cache() {
+        recycle_token_buffer(Vec::with_capacity(64));
+        reset_thread_state();
+        assert_eq!(take_token_buffer().capacity(), 0);
+    }
+}
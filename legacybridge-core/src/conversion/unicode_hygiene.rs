@@ -0,0 +1,289 @@
+//! Unicode hygiene for invisible and directional control characters.
+//!
+//! Zero-width joiners, zero-width spaces and bidi override marks render
+//! as nothing but change how surrounding text displays, which makes
+//! converted output spoofable in downstream renderers: a U+202E turns
+//! `file\u{202E}cod.rtf` into what reads as an `.doc` file. This pass
+//! walks a parsed document's text nodes and applies the per-class
+//! [`UnicodeHygiene`] policy from
+//! [`SecurityLimits`](crate::security::SecurityLimits). Both parsers run
+//! it before handing the document to a generator, so both conversion
+//! directions are covered.
+
+use super::rtf_parser::{RtfDocument, RtfNode};
+use crate::security::{UnicodeHygiene, UnicodePolicy};
+use std::fmt::Write as _;
+
+/// Warnings from the pass carry this prefix, so the pipeline can report
+/// them under their own code instead of the generic parser-warning one.
+pub const WARNING_PREFIX: &str = "unicode hygiene:";
+
+/// Character classes the policy distinguishes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    BidiOverride,
+    ZeroWidthJoiner,
+    ZeroWidthSpace,
+}
+
+impl CharClass {
+    fn describe(self) -> &'static str {
+        match self {
+            CharClass::BidiOverride => "bidi override",
+            CharClass::ZeroWidthJoiner => "zero-width joiner",
+            CharClass::ZeroWidthSpace => "zero-width space",
+        }
+    }
+}
+
+fn classify(c: char) -> Option<CharClass> {
+    match c {
+        '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}' => Some(CharClass::BidiOverride),
+        '\u{200C}' | '\u{200D}' => Some(CharClass::ZeroWidthJoiner),
+        '\u{200B}' | '\u{2060}' | '\u{FEFF}' => Some(CharClass::ZeroWidthSpace),
+        _ => None,
+    }
+}
+
+/// Rough emoji detection for the ZWJ exemption: the blocks emoji
+/// sequences are built from, plus the VS16 presentation selector.
+/// Precision is not critical - a kept ZWJ between two symbols is
+/// harmless; the pass exists to break ZWJ abuse inside plain text.
+fn is_emoji_scalar(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1F000..=0x1FAFF | 0x2600..=0x27BF | 0x2B00..=0x2BFF | 0xFE0F
+    )
+}
+
+/// Whether the ZWJ at `chars[i]` glues an emoji sequence: its nearest
+/// non-selector neighbors on both sides are emoji scalars.
+fn in_emoji_sequence(chars: &[char], i: usize) -> bool {
+    let prev = chars[..i].iter().rev().find(|&&c| c != '\u{FE0F}');
+    let next = chars[i + 1..].iter().find(|&&c| c != '\u{FE0F}');
+    matches!((prev, next), (Some(&p), Some(&n)) if is_emoji_scalar(p) && is_emoji_scalar(n))
+}
+
+/// How many characters each class's policy acted on (stripped or
+/// escaped); classes set to [`UnicodePolicy::Keep`] count nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct ScrubCounts {
+    bidi_overrides: usize,
+    zero_width_joiners: usize,
+    zero_width_spaces: usize,
+}
+
+/// Apply the policy to every text node in `document`, appending one
+/// warning per affected character class to `warnings`. A class set to
+/// [`UnicodePolicy::Reject`] fails the pass on its first occurrence.
+pub fn scrub(
+    document: &mut RtfDocument,
+    policy: &UnicodeHygiene,
+    warnings: &mut Vec<String>,
+) -> Result<(), String> {
+    let mut counts = ScrubCounts::default();
+    let mut work: Vec<&mut RtfNode> = document.content.iter_mut().collect();
+    while let Some(node) = work.pop() {
+        match node {
+            RtfNode::Text(text) => {
+                if let Some(clean) = scrub_text(text, policy, &mut counts)? {
+                    *text = clean;
+                }
+            }
+            RtfNode::Formatted { content, .. }
+            | RtfNode::Hyperlink { content, .. }
+            | RtfNode::Paragraph { content, .. }
+            | RtfNode::Heading { content, .. }
+            | RtfNode::ListItem { content, .. } => work.extend(content.iter_mut()),
+            RtfNode::Table(table) => {
+                for row in &mut table.rows {
+                    for cell in &mut row.cells {
+                        work.extend(cell.content.iter_mut());
+                    }
+                }
+            }
+            RtfNode::Image { .. }
+            | RtfNode::RawRtf { .. }
+            | RtfNode::LineBreak
+            | RtfNode::PageBreak => {}
+        }
+    }
+    let mut report = |count: usize, class: CharClass, policy: UnicodePolicy| {
+        if count > 0 {
+            let verb = if policy == UnicodePolicy::Escape {
+                "escaped"
+            } else {
+                "stripped"
+            };
+            warnings.push(format!(
+                "{WARNING_PREFIX} {verb} {count} {} character(s)",
+                class.describe()
+            ));
+        }
+    };
+    report(
+        counts.bidi_overrides,
+        CharClass::BidiOverride,
+        policy.bidi_overrides,
+    );
+    report(
+        counts.zero_width_joiners,
+        CharClass::ZeroWidthJoiner,
+        policy.zero_width_joiners,
+    );
+    report(
+        counts.zero_width_spaces,
+        CharClass::ZeroWidthSpace,
+        policy.zero_width_spaces,
+    );
+    Ok(())
+}
+
+/// Apply the policy to one text run; `Ok(None)` means it was clean, so
+/// callers skip the reallocation on the overwhelmingly common path.
+fn scrub_text(
+    text: &str,
+    policy: &UnicodeHygiene,
+    counts: &mut ScrubCounts,
+) -> Result<Option<String>, String> {
+    if !text.chars().any(|c| classify(c).is_some()) {
+        return Ok(None);
+    }
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    for (i, &c) in chars.iter().enumerate() {
+        let Some(class) = classify(c) else {
+            out.push(c);
+            continue;
+        };
+        if c == '\u{200D}' && in_emoji_sequence(&chars, i) {
+            out.push(c);
+            continue;
+        }
+        let (applied, count) = match class {
+            CharClass::BidiOverride => (policy.bidi_overrides, &mut counts.bidi_overrides),
+            CharClass::ZeroWidthJoiner => {
+                (policy.zero_width_joiners, &mut counts.zero_width_joiners)
+            }
+            CharClass::ZeroWidthSpace => (policy.zero_width_spaces, &mut counts.zero_width_spaces),
+        };
+        match applied {
+            UnicodePolicy::Keep => out.push(c),
+            UnicodePolicy::Strip => *count += 1,
+            UnicodePolicy::Escape => {
+                *count += 1;
+                let _ = write!(out, "&#x{:X};", c as u32);
+            }
+            UnicodePolicy::Reject => {
+                return Err(format!(
+                    "{WARNING_PREFIX} {} U+{:04X} rejected by policy",
+                    class.describe(),
+                    c as u32
+                ));
+            }
+        }
+    }
+    Ok(Some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::rtf_parser::DocumentMetadata;
+
+    fn document_with(text: &str) -> RtfDocument {
+        RtfDocument {
+            metadata: DocumentMetadata::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content: vec![RtfNode::Paragraph {
+                direction: Default::default(),
+                spacing: Default::default(),
+                content: vec![RtfNode::Text(text.to_string())],
+            }],
+        }
+    }
+
+    fn first_text(document: &RtfDocument) -> &str {
+        let RtfNode::Paragraph { content, .. } = &document.content[0] else {
+            panic!("paragraph");
+        };
+        let RtfNode::Text(text) = &content[0] else {
+            panic!("text");
+        };
+        text
+    }
+
+    #[test]
+    fn bidi_override_is_stripped_by_default() {
+        let mut document = document_with("file\u{202E}cod.rtf");
+        let mut warnings = Vec::new();
+        scrub(&mut document, &UnicodeHygiene::default(), &mut warnings).unwrap();
+        assert_eq!(first_text(&document), "filecod.rtf");
+        assert_eq!(warnings.len(), 1);
+        assert!(
+            warnings[0].contains("stripped 1 bidi override"),
+            "{}",
+            warnings[0]
+        );
+    }
+
+    #[test]
+    fn emoji_family_survives_strip_mode() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let mut document = document_with(family);
+        let mut warnings = Vec::new();
+        scrub(&mut document, &UnicodeHygiene::default(), &mut warnings).unwrap();
+        assert_eq!(first_text(&document), family);
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn zwj_in_plain_text_is_stripped() {
+        let mut document = document_with("ab\u{200D}cd and a\u{200B}gap");
+        let mut warnings = Vec::new();
+        scrub(&mut document, &UnicodeHygiene::default(), &mut warnings).unwrap();
+        assert_eq!(first_text(&document), "abcd and agap");
+        assert_eq!(warnings.len(), 2);
+    }
+
+    #[test]
+    fn escape_policy_emits_entities() {
+        let policy = UnicodeHygiene {
+            bidi_overrides: UnicodePolicy::Escape,
+            ..Default::default()
+        };
+        let mut document = document_with("file\u{202E}cod.rtf");
+        let mut warnings = Vec::new();
+        scrub(&mut document, &policy, &mut warnings).unwrap();
+        assert_eq!(first_text(&document), "file&#x202E;cod.rtf");
+        assert!(warnings[0].contains("escaped 1"), "{}", warnings[0]);
+    }
+
+    #[test]
+    fn reject_policy_fails_with_the_offending_scalar() {
+        let policy = UnicodeHygiene {
+            bidi_overrides: UnicodePolicy::Reject,
+            ..Default::default()
+        };
+        let mut document = document_with("file\u{202E}cod.rtf");
+        let err = scrub(&mut document, &policy, &mut Vec::new()).unwrap_err();
+        assert!(err.contains("U+202E"), "{err}");
+    }
+
+    #[test]
+    fn keep_policy_passes_everything_through() {
+        let policy = UnicodeHygiene {
+            bidi_overrides: UnicodePolicy::Keep,
+            zero_width_joiners: UnicodePolicy::Keep,
+            zero_width_spaces: UnicodePolicy::Keep,
+        };
+        let text = "a\u{202E}b\u{200D}c\u{200B}d";
+        let mut document = document_with(text);
+        let mut warnings = Vec::new();
+        scrub(&mut document, &policy, &mut warnings).unwrap();
+        assert_eq!(first_text(&document), text);
+        assert!(warnings.is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! Stable error contract shared by every embedding surface.
+//!
+//! The Tauri commands, the C ABI DLL and the TypeScript frontend all
+//! describe failures with the one [`ErrorEnvelope`] JSON schema, so a
+//! given [`ConversionError`] produces the same envelope no matter which
+//! bridge reported it and the UI never parses loose error strings. The
+//! matching TypeScript declaration (`bindings/ErrorEnvelope.d.ts`) is
+//! generated from the Rust type by this module's tests - regenerate with
+//! `cargo test -p legacybridge-core export_bindings`.
+
+use crate::contracts;
+use crate::conversion::ConversionError;
+use serde::{Deserialize, Serialize};
+
+/// A conversion failure as every bridge reports it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(ts_rs::TS))]
+#[cfg_attr(test, ts(export, export_to = "ErrorEnvelope.d.ts"))]
+pub struct ErrorEnvelope {
+    /// Contract major of this payload; see [`contracts`](crate::contracts).
+    /// Envelopes recorded before contract versioning are version 1 and
+    /// omit the field.
+    #[serde(
+        default = "contracts::version_one",
+        skip_serializing_if = "contracts::is_version_one"
+    )]
+    pub v: u32,
+    /// Stable numeric code matching [`ConversionError::error_code`];
+    /// `-1` for bridge-level input errors (null pointer, bad JSON).
+    pub code: i32,
+    /// Stable category: `parse`, `validation`, `generation`,
+    /// `resource_limit`, `cancelled`, `contract`, or `input` for
+    /// bridge-level errors.
+    pub category: String,
+    pub message: String,
+    /// Validation code (`RTF003`, ...) when the error carries one.
+    pub validation_code: Option<String>,
+    /// Byte offset of the failure, when the failing stage reports one.
+    pub position: Option<usize>,
+    /// Actionable suggestions for the user, in display order.
+    pub hints: Vec<String>,
+    /// Whether retrying can succeed without editing the document:
+    /// cancellations and resource limits are transient, and parse errors
+    /// may yield to `auto_recovery`.
+    pub recoverable: bool,
+}
+
+impl ErrorEnvelope {
+    /// Envelope for bridge-level input errors (null pointer, invalid
+    /// UTF-8, malformed options JSON) that never reach the converter.
+    pub fn invalid_input(message: impl Into<String>) -> Self {
+        ErrorEnvelope {
+            v: contracts::CONTRACT_VERSION,
+            code: -1,
+            category: "input".to_string(),
+            message: message.into(),
+            validation_code: None,
+            position: None,
+            hints: Vec::new(),
+            recoverable: false,
+        }
+    }
+}
+
+impl From<&ConversionError> for ErrorEnvelope {
+    fn from(error: &ConversionError) -> Self {
+        let validation_code = match error {
+            ConversionError::ValidationError { code, .. } => Some(code.clone()),
+            _ => None,
+        };
+        let hints = match error {
+            ConversionError::ParseError(_) => {
+                vec!["enable auto_recovery to attempt structural repair".to_string()]
+            }
+            ConversionError::ValidationError { .. } => {
+                vec!["run deep validation for the full diagnostics".to_string()]
+            }
+            ConversionError::ResourceLimit(_) => {
+                vec!["raise the configured limits or split the document".to_string()]
+            }
+            ConversionError::UnsupportedContractVersion { .. } => {
+                vec![format!(
+                    "regenerate the payload with contract version {} or lower",
+                    contracts::CONTRACT_VERSION
+                )]
+            }
+            _ => Vec::new(),
+        };
+        ErrorEnvelope {
+            v: contracts::CONTRACT_VERSION,
+            code: error.error_code(),
+            category: error.category().to_string(),
+            message: error.to_string(),
+            validation_code,
+            position: None,
+            hints,
+            recoverable: matches!(
+                error,
+                ConversionError::ParseError(_)
+                    | ConversionError::ResourceLimit(_)
+                    | ConversionError::Cancelled
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelopes_carry_the_stable_code_and_category() {
+        let envelope = ErrorEnvelope::from(&ConversionError::validation_with_code(
+            "RTF003",
+            "not an RTF document",
+        ));
+        assert_eq!(envelope.code, -3);
+        assert_eq!(envelope.category, "validation");
+        assert_eq!(envelope.validation_code.as_deref(), Some("RTF003"));
+        assert!(!envelope.recoverable);
+        assert!(!envelope.hints.is_empty());
+
+        let envelope = ErrorEnvelope::from(&ConversionError::Cancelled);
+        assert_eq!(envelope.code, -6);
+        assert_eq!(envelope.category, "cancelled");
+        assert!(envelope.recoverable);
+        assert!(envelope.hints.is_empty());
+    }
+
+    #[test]
+    fn envelopes_round_trip_through_json() {
+        let envelope = ErrorEnvelope::from(&ConversionError::parse("unbalanced group"));
+        let json = serde_json::to_string(&envelope).unwrap();
+        assert!(json.contains("\"v\":"), "{json}");
+        assert_eq!(serde_json::from_str::<ErrorEnvelope>(&json).unwrap(), envelope);
+    }
+
+    #[test]
+    fn version_one_envelopes_still_parse() {
+        // The exact shape emitted before contract versioning existed.
+        let json = r#"{"code":-2,"category":"parse","message":"parse error: x",
+                       "validation_code":null,"position":null,"hints":[],"recoverable":true}"#;
+        let envelope: ErrorEnvelope = serde_json::from_str(json).unwrap();
+        assert_eq!(envelope.v, 1);
+        assert_eq!(envelope.code, -2);
+        // Re-serializing a version 1 envelope reproduces the old shape.
+        assert!(!serde_json::to_string(&envelope).unwrap().contains("\"v\":"));
+    }
+}
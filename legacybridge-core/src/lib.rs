@@ -0,0 +1,32 @@
+//! LegacyBridge conversion core.
+//!
+//! This crate holds everything shared between the Tauri desktop app and the
+//! VB6/VFP9-facing DLL: the RTF and Markdown parsers/generators, the
+//! conversion pipeline and the security validators. The consumers keep only
+//! their respective glue (Tauri commands, C ABI exports).
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod config;
+pub mod contracts;
+pub mod conversion;
+pub mod ffi_error_bridge;
+pub mod security;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod selftest;
+pub mod sync;
+pub mod test_support;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
+
+pub use conversion::{
+    extract_outline, extract_plain_text, markdown_to_rtf, markdown_to_rtf_legacy,
+    markdown_to_rtf_fragment, markdown_to_rtf_with_heading_offset, markdown_to_rtf_with_integrity,
+    markdown_to_rtf_with_profile,
+    markdown_to_rtf_with_raw_rtf, rtf_to_markdown,
+    ConversionError, ConversionResult,
+    DocumentPipeline, PipelineConfig,
+};
+pub use conversion::integrity::{verify_integrity, IntegrityBlock};
+pub use conversion::lexer::{tokenize_spanned, RtfToken, SpannedToken};
+pub use conversion::thread_state::reset_thread_state;
+pub use conversion::simd_lexer::tokenize_simd_spanned;
@@ -0,0 +1,296 @@
+//! Security limits and input validation shared by all conversion entry
+//! points.
+
+pub mod workspace;
+
+pub use workspace::TempWorkspace;
+
+use serde::{Deserialize, Serialize};
+
+/// Hard limits applied to untrusted input before and during conversion.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityLimits {
+    /// Maximum input document size in bytes.
+    pub max_input_size: usize,
+    /// Maximum length of a single Markdown line in bytes. A minified
+    /// export can put megabytes on one line; everything past this cap is
+    /// rejected with the offending line number rather than fed to the
+    /// inline parser.
+    #[serde(default = "default_max_line_length")]
+    pub max_line_length: usize,
+    /// Maximum group nesting depth the parser will follow.
+    pub max_nesting_depth: usize,
+    /// Maximum number of tokens produced by the lexer.
+    pub max_token_count: usize,
+    /// Maximum output size in bytes the generators may produce.
+    pub max_output_size: usize,
+    /// Maximum number of formatted runs the parser will create. Defends
+    /// against toggle-flood documents that stay under the input size cap
+    /// but explode the node tree.
+    pub max_format_runs: usize,
+    /// Maximum decoded size of a single embedded `\pict`/`\object`
+    /// payload, in bytes. A document under `max_input_size` can still be
+    /// one huge image; this cap treats it differently from text.
+    pub max_embedded_object_size: usize,
+    /// Maximum summed decoded size of all embedded payloads per document.
+    pub max_total_embedded_size: usize,
+    /// Maximum number of embedded images per document.
+    pub max_image_count: usize,
+    /// Per-class policies for zero-width and bidi control characters in
+    /// text content, applied by the Unicode hygiene pass (see
+    /// [`unicode_hygiene`](crate::conversion::unicode_hygiene)) in both
+    /// conversion directions.
+    #[serde(default)]
+    pub unicode_hygiene: UnicodeHygiene,
+}
+
+impl Default for SecurityLimits {
+    fn default() -> Self {
+        SecurityLimits {
+            max_input_size: 10 * 1024 * 1024,
+            max_line_length: default_max_line_length(),
+            max_nesting_depth: 64,
+            max_token_count: 5_000_000,
+            max_output_size: 50 * 1024 * 1024,
+            max_format_runs: 100_000,
+            max_embedded_object_size: 4 * 1024 * 1024,
+            max_total_embedded_size: 16 * 1024 * 1024,
+            max_image_count: 256,
+            unicode_hygiene: UnicodeHygiene::default(),
+        }
+    }
+}
+
+/// Serde default for [`SecurityLimits::max_line_length`], so limit
+/// documents saved before the field existed keep deserializing.
+fn default_max_line_length() -> usize {
+    4 * 1024 * 1024
+}
+
+/// What the Unicode hygiene pass does with one class of invisible or
+/// directional control characters; see [`UnicodeHygiene`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnicodePolicy {
+    /// Pass the characters through untouched.
+    Keep,
+    /// Remove them from the text.
+    #[default]
+    Strip,
+    /// Replace each with its `&#xNNNN;` numeric entity, so the document
+    /// stays auditable without the character staying active.
+    Escape,
+    /// Fail the conversion.
+    Reject,
+}
+
+/// Per-class policies for characters that render as nothing but change
+/// how surrounding text displays - the raw material of content spoofing
+/// (`file\u{202E}cod.rtf` renders its tail reversed). Applied to text
+/// nodes in both conversion directions; every class defaults to
+/// [`UnicodePolicy::Strip`]. Zero-width joiners between emoji scalars
+/// are presentation, not spoofing, and are kept under every policy.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnicodeHygiene {
+    /// Directional embedding, override and isolate marks:
+    /// U+202A-U+202E and U+2066-U+2069.
+    #[serde(default)]
+    pub bidi_overrides: UnicodePolicy,
+    /// Zero-width (non-)joiners U+200C and U+200D, except ZWJ inside
+    /// emoji sequences.
+    #[serde(default)]
+    pub zero_width_joiners: UnicodePolicy,
+    /// Zero-width space U+200B, word joiner U+2060 and mid-text U+FEFF.
+    #[serde(default)]
+    pub zero_width_spaces: UnicodePolicy,
+}
+
+impl UnicodeHygiene {
+    /// Every class set to [`UnicodePolicy::Keep`], for re-parsing output
+    /// that already went through the pass (verification must compare,
+    /// not scrub again).
+    pub fn keep_all() -> Self {
+        UnicodeHygiene {
+            bidi_overrides: UnicodePolicy::Keep,
+            zero_width_joiners: UnicodePolicy::Keep,
+            zero_width_spaces: UnicodePolicy::Keep,
+        }
+    }
+}
+
+/// What the parser does with an embedded payload that violates the
+/// embedded-object limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SanitizationMode {
+    /// Drop the payload, leave a placeholder in the text flow and record
+    /// a parser warning.
+    #[default]
+    Placeholder,
+    /// Fail the conversion.
+    Reject,
+}
+
+/// Validates raw input against [`SecurityLimits`] and RTF-specific
+/// deny rules before the lexer runs.
+pub struct InputValidator {
+    limits: SecurityLimits,
+    /// Accept `\formfield` despite the deny rule; set only by callers
+    /// running form extraction.
+    allow_form_fields: bool,
+}
+
+/// Control words that are rejected outright: they can trigger external
+/// content fetches or embed executable objects.
+const DENIED_CONTROL_WORDS: &[&str] = &["objdata", "objclass", "dde", "objautlink"];
+
+/// Denied unless form extraction is enabled: legacy form fields can carry
+/// macro actions we never want to evaluate as prose.
+const FORM_FIELD_CONTROL_WORD: &str = "formfield";
+
+impl InputValidator {
+    pub fn new(limits: SecurityLimits) -> Self {
+        InputValidator {
+            limits,
+            allow_form_fields: false,
+        }
+    }
+
+    pub fn with_defaults() -> Self {
+        Self::new(SecurityLimits::default())
+    }
+
+    /// Allow `\formfield` groups through validation, for form extraction.
+    pub fn with_form_fields_allowed(mut self, allow: bool) -> Self {
+        self.allow_form_fields = allow;
+        self
+    }
+
+    pub fn limits(&self) -> &SecurityLimits {
+        &self.limits
+    }
+
+    /// Check raw RTF input; returns a reason string on rejection.
+    pub fn validate_rtf_input(&self, input: &str) -> Result<(), String> {
+        if input.len() > self.limits.max_input_size {
+            return Err(format!(
+                "input exceeds maximum size ({} > {} bytes)",
+                input.len(),
+                self.limits.max_input_size
+            ));
+        }
+        let mut depth = 0usize;
+        let mut max_depth = 0usize;
+        let bytes = input.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\\' => i += 1,
+                b'{' => {
+                    depth += 1;
+                    max_depth = max_depth.max(depth);
+                }
+                b'}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            i += 1;
+        }
+        if max_depth > self.limits.max_nesting_depth {
+            return Err(format!(
+                "nesting depth {} exceeds limit {}",
+                max_depth, self.limits.max_nesting_depth
+            ));
+        }
+        for word in DENIED_CONTROL_WORDS {
+            if contains_control_word(input, word) {
+                return Err(format!("disallowed control word \\{word}"));
+            }
+        }
+        if !self.allow_form_fields && contains_control_word(input, FORM_FIELD_CONTROL_WORD) {
+            return Err(format!(
+                "disallowed control word \\{FORM_FIELD_CONTROL_WORD} (enable form extraction to process forms)"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Clamp a control word parameter into a spec-reasonable range.
+    pub fn validate_number(&self, value: i32, min: i32, max: i32) -> i32 {
+        value.clamp(min, max)
+    }
+}
+
+/// Check for `\word` followed by a non-alphabetic delimiter, so `\dde` does
+/// not fire on `\ddeword`.
+fn contains_control_word(input: &str, word: &str) -> bool {
+    let needle = format!("\\{word}");
+    let mut start = 0;
+    while let Some(pos) = input[start..].find(&needle) {
+        let end = start + pos + needle.len();
+        match input.as_bytes().get(end) {
+            Some(c) if c.is_ascii_alphabetic() => {}
+            _ => return true,
+        }
+        start = end;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_normal_document() {
+        assert!(InputValidator::with_defaults()
+            .validate_rtf_input("{\\rtf1 Hello}")
+            .is_ok());
+    }
+
+    #[test]
+    fn rejects_excessive_nesting() {
+        let limits = SecurityLimits {
+            max_nesting_depth: 4,
+            ..Default::default()
+        };
+        let deep = format!("{}text{}", "{".repeat(10), "}".repeat(10));
+        assert!(InputValidator::new(limits).validate_rtf_input(&deep).is_err());
+    }
+
+    #[test]
+    fn rejects_denied_control_words() {
+        let err = InputValidator::with_defaults()
+            .validate_rtf_input("{\\rtf1{\\object\\objdata 0102}}")
+            .unwrap_err();
+        assert!(err.contains("objdata"));
+    }
+
+    #[test]
+    fn form_fields_are_denied_unless_extraction_is_enabled() {
+        let rtf = "{\\rtf1{\\field{\\*\\formfield{\\fftype0}}{\\fldrslt x}}}";
+        let err = InputValidator::with_defaults()
+            .validate_rtf_input(rtf)
+            .unwrap_err();
+        assert!(err.contains("formfield"), "{err}");
+        assert!(InputValidator::with_defaults()
+            .with_form_fields_allowed(true)
+            .validate_rtf_input(rtf)
+            .is_ok());
+    }
+
+    #[test]
+    fn unsupported_but_safe_constructs_pass_validation() {
+        // Equations and drawing objects are unsupported, not dangerous:
+        // the parser replaces them with placeholders instead.
+        assert!(InputValidator::with_defaults()
+            .validate_rtf_input("{\\rtf1 {\\mmath x=1}{\\*\\do\\dpline} body}")
+            .is_ok());
+    }
+
+    #[test]
+    fn denied_word_must_be_delimited() {
+        assert!(InputValidator::with_defaults()
+            .validate_rtf_input("{\\rtf1 \\ddeword}")
+            .is_ok());
+    }
+}
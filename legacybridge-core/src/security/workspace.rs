@@ -0,0 +1,228 @@
+//! Scratch space for intermediate artifacts.
+//!
+//! Ad-hoc temp files risk leaking sensitive document content. A
+//! [`TempWorkspace`] is a per-operation directory under the platform temp
+//! (or a configured scratch dir) with restrictive permissions, cleaned up
+//! on drop - including during unwinding - and by a panic hook for the
+//! panicking thread. Every feature that writes intermediates must resolve
+//! its paths through [`TempWorkspace::resolve`], which confines them to
+//! the workspace.
+
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once, OnceLock};
+use std::thread::ThreadId;
+
+/// Live workspace directories by owning thread, so the panic hook can
+/// clean up the panicking thread's scratch space without touching
+/// workspaces other threads are still using.
+fn live_workspaces() -> &'static Mutex<Vec<(ThreadId, PathBuf)>> {
+    static LIVE: OnceLock<Mutex<Vec<(ThreadId, PathBuf)>>> = OnceLock::new();
+    LIVE.get_or_init(|| Mutex::new(Vec::new()))
+}
+
+fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            let thread = std::thread::current().id();
+            if let Ok(mut live) = live_workspaces().lock() {
+                live.retain(|(owner, path)| {
+                    if *owner == thread {
+                        let _ = std::fs::remove_dir_all(path);
+                        false
+                    } else {
+                        true
+                    }
+                });
+            }
+            previous(info);
+        }));
+    });
+}
+
+static NEXT_WORKSPACE_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A per-operation scratch directory, removed when the workspace drops.
+#[derive(Debug)]
+pub struct TempWorkspace {
+    root: PathBuf,
+    /// Overwrite file contents with zeros before unlinking, for regulated
+    /// deployments where deleted scratch files must not be recoverable.
+    secure_delete: bool,
+}
+
+impl TempWorkspace {
+    /// Create a workspace under the platform temp directory.
+    pub fn new() -> io::Result<Self> {
+        Self::in_dir(std::env::temp_dir())
+    }
+
+    /// Create a workspace under a configured scratch directory.
+    pub fn in_dir(scratch: impl AsRef<Path>) -> io::Result<Self> {
+        install_panic_hook();
+        let root = scratch.as_ref().join(format!(
+            "legacybridge-{}-{}",
+            std::process::id(),
+            NEXT_WORKSPACE_ID.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&root)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&root, std::fs::Permissions::from_mode(0o700))?;
+        }
+        live_workspaces()
+            .lock()
+            .unwrap()
+            .push((std::thread::current().id(), root.clone()));
+        Ok(TempWorkspace {
+            root,
+            secure_delete: false,
+        })
+    }
+
+    /// Enable overwriting file contents before unlink during cleanup.
+    pub fn with_secure_delete(mut self, secure_delete: bool) -> Self {
+        self.secure_delete = secure_delete;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Resolve a relative name to a path inside the workspace. Rejects
+    /// absolute paths and any `..`/root components, so a crafted name
+    /// (e.g. an image called `../../etc/passwd`) cannot escape.
+    pub fn resolve(&self, name: impl AsRef<Path>) -> io::Result<PathBuf> {
+        let name = name.as_ref();
+        if name.as_os_str().is_empty()
+            || !name.components().all(|c| matches!(c, Component::Normal(_)))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("path escapes the workspace: {}", name.display()),
+            ));
+        }
+        Ok(self.root.join(name))
+    }
+
+    /// Write an intermediate file inside the workspace, creating parent
+    /// directories as needed.
+    pub fn write(&self, name: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<PathBuf> {
+        let path = self.resolve(name)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&path, contents)?;
+        Ok(path)
+    }
+
+    fn cleanup(&self) -> io::Result<()> {
+        if self.secure_delete {
+            overwrite_tree(&self.root)?;
+        }
+        std::fs::remove_dir_all(&self.root)
+    }
+}
+
+impl Drop for TempWorkspace {
+    fn drop(&mut self) {
+        // Best effort: a failed cleanup must not turn into a panic (or a
+        // double panic during unwinding).
+        let _ = self.cleanup();
+        if let Ok(mut live) = live_workspaces().lock() {
+            live.retain(|(_, path)| *path != self.root);
+        }
+    }
+}
+
+/// Overwrite every file under `root` with zeros of the same length, so
+/// the contents are gone even if the unlink leaves recoverable blocks.
+fn overwrite_tree(root: &Path) -> io::Result<()> {
+    for entry in std::fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            overwrite_tree(&path)?;
+        } else {
+            let len = std::fs::metadata(&path)?.len() as usize;
+            std::fs::write(&path, vec![0u8; len])?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workspace_is_removed_on_drop() {
+        let workspace = TempWorkspace::new().unwrap();
+        let root = workspace.root.clone();
+        workspace.write("page1.png", b"bytes").unwrap();
+        assert!(root.join("page1.png").exists());
+        drop(workspace);
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn workspace_is_removed_when_the_operation_errors() {
+        fn failing_operation(root_out: &mut PathBuf) -> io::Result<()> {
+            let workspace = TempWorkspace::new()?;
+            *root_out = workspace.root.clone();
+            workspace.write("partial.bin", b"half-written")?;
+            Err(io::Error::other("downstream failure"))
+        }
+        let mut root = PathBuf::new();
+        assert!(failing_operation(&mut root).is_err());
+        assert!(!root.exists());
+    }
+
+    #[test]
+    fn workspace_is_removed_when_the_operation_panics() {
+        let root = std::sync::Arc::new(Mutex::new(PathBuf::new()));
+        let panicked_root = std::sync::Arc::clone(&root);
+        let result = std::panic::catch_unwind(move || {
+            let workspace = TempWorkspace::new().unwrap();
+            *panicked_root.lock().unwrap() = workspace.root.clone();
+            workspace.write("secret.bin", b"sensitive").unwrap();
+            panic!("operation blew up");
+        });
+        assert!(result.is_err());
+        assert!(!root.lock().unwrap().exists());
+    }
+
+    #[test]
+    fn crafted_names_cannot_escape_the_workspace() {
+        let workspace = TempWorkspace::new().unwrap();
+        assert!(workspace.resolve("../evil.png").is_err());
+        assert!(workspace.resolve("a/../../evil.png").is_err());
+        assert!(workspace.resolve("/etc/passwd").is_err());
+        assert!(workspace.resolve("").is_err());
+
+        let nested = workspace.resolve("images/page1.png").unwrap();
+        assert!(nested.starts_with(workspace.path()));
+    }
+
+    #[test]
+    fn secure_delete_still_removes_everything() {
+        let workspace = TempWorkspace::new().unwrap().with_secure_delete(true);
+        let root = workspace.root.clone();
+        workspace.write("regulated/export.zip", b"confidential").unwrap();
+        drop(workspace);
+        assert!(!root.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn workspace_directory_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let workspace = TempWorkspace::new().unwrap();
+        let mode = std::fs::metadata(workspace.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+}
@@ -0,0 +1,355 @@
+//! In-process deployment self-test.
+//!
+//! Field installs of the DLL fail for environmental reasons — antivirus
+//! software interfering with temp files, runtime behavior differences,
+//! CPU quirks — and a support call usually starts with no diagnostic at
+//! all. [`run_selftest`] runs a battery of checks against embedded
+//! fixtures and returns a machine-readable report with pass/fail per
+//! check plus basic environment information, so "send us the self-test
+//! output" replaces guesswork. The battery touches nothing outside a
+//! private directory under the system temp dir and is budgeted to finish
+//! well under two seconds; the DLL surface is
+//! `legacybridge_run_selftest`, the desktop app's is the `run_selftest`
+//! command.
+
+use crate::conversion::lexer::tokenize;
+use crate::conversion::pipeline::{ConversionStats, DocumentPipeline, PipelineConfig};
+use crate::conversion::rtf_parser::RtfParser;
+use crate::conversion::simd_lexer::{tokenize_simd, CpuFeatures};
+use crate::conversion::template::TemplateSystem;
+use crate::conversion::{markdown_to_rtf, rtf_to_markdown};
+use serde::{Deserialize, Serialize};
+use std::time::Instant;
+
+/// Version of the report schema; bumped on any shape change.
+/// Version 2 added the report-level `stats` field.
+pub const SELFTEST_VERSION: u32 = 2;
+
+/// Check-selection bits for [`run_selftest`]; `0` runs everything.
+pub const CHECK_ROUND_TRIP: u32 = 1 << 0;
+pub const CHECK_SIMD: u32 = 1 << 1;
+pub const CHECK_TEMP_FILES: u32 = 1 << 2;
+pub const CHECK_TEMPLATES: u32 = 1 << 3;
+pub const CHECK_THREAD_POOL: u32 = 1 << 4;
+pub const CHECK_TIMING: u32 = 1 << 5;
+pub const CHECK_STATS: u32 = 1 << 6;
+
+/// RTF fixture exercised by most checks: formatting, an accented
+/// character and a table, small enough to convert in microseconds.
+const RTF_FIXTURE: &str = "{\\rtf1\\ansi\\deff0{\\fonttbl{\\f0 Calibri;}}\
+\\b Self-test\\b0  caf\\'e9 fixture\\par\
+\\trowd\\cellx4000\\cellx8000\\intbl one\\cell two\\cell\\row}";
+
+const MARKDOWN_FIXTURE: &str = "# Self-test\n\nA **bold** word and a *quiet* one.\n";
+
+/// One check's outcome; `error` is set only on failure.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub name: String,
+    pub passed: bool,
+    pub duration_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Where the battery ran, for reproducing environment-specific failures.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentInfo {
+    /// OS name and version as reported by the system.
+    pub os: String,
+    pub arch: String,
+    pub logical_cpus: usize,
+    pub page_size: u64,
+    /// Vector instruction set the tokenizer's byte scanner selects here.
+    pub simd_level: String,
+    /// LegacyBridge core version.
+    pub version: String,
+}
+
+/// The whole battery, serializable for support tickets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelftestReport {
+    /// Schema version; see [`SELFTEST_VERSION`].
+    pub selftest_version: u32,
+    /// True when every selected check passed.
+    pub passed: bool,
+    /// Wall-clock time of the whole battery.
+    pub duration_ms: u64,
+    pub environment: EnvironmentInfo,
+    /// Selected checks, in battery order.
+    pub checks: Vec<CheckResult>,
+    /// Counters from a `collect_stats` conversion of the RTF fixture -
+    /// token and node counts, tokens per second, the SIMD level, where
+    /// the time went - so "this machine is slow" tickets arrive with
+    /// numbers. Only set when the stats check is selected. Absent in
+    /// pre-version-2 reports.
+    #[serde(default)]
+    pub stats: Option<ConversionStats>,
+}
+
+/// One entry of the battery: its selection bit, report name and body.
+type Check = (u32, &'static str, fn() -> Result<(), String>);
+
+/// Run the selected checks (`flags` is a bit set of the `CHECK_*`
+/// constants; `0` means all) and assemble the report.
+pub fn run_selftest(flags: u32) -> SelftestReport {
+    let started = Instant::now();
+    let all = flags == 0;
+    let mut checks = Vec::new();
+    let battery: [Check; 7] = [
+        (CHECK_ROUND_TRIP, "round_trip", check_round_trip),
+        (CHECK_SIMD, "simd_equivalence", check_simd),
+        (CHECK_STATS, "stats", check_stats),
+        (CHECK_TEMP_FILES, "temp_files", check_temp_files),
+        (CHECK_TEMPLATES, "templates", check_templates),
+        (CHECK_THREAD_POOL, "thread_pool", check_thread_pool),
+        (CHECK_TIMING, "timing", check_timing),
+    ];
+    for (bit, name, check) in battery {
+        if !all && flags & bit == 0 {
+            continue;
+        }
+        let check_started = Instant::now();
+        let outcome = check();
+        checks.push(CheckResult {
+            name: name.to_string(),
+            passed: outcome.is_ok(),
+            duration_ms: check_started.elapsed().as_millis() as u64,
+            error: outcome.err(),
+        });
+    }
+    let stats = if all || flags & CHECK_STATS != 0 {
+        fixture_stats().ok()
+    } else {
+        None
+    };
+    SelftestReport {
+        selftest_version: SELFTEST_VERSION,
+        passed: checks.iter().all(|c| c.passed),
+        duration_ms: started.elapsed().as_millis() as u64,
+        environment: environment(),
+        checks,
+        stats,
+    }
+}
+
+fn environment() -> EnvironmentInfo {
+    EnvironmentInfo {
+        os: sysinfo::System::long_os_version()
+            .unwrap_or_else(|| std::env::consts::OS.to_string()),
+        arch: std::env::consts::ARCH.to_string(),
+        logical_cpus: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        page_size: page_size::get() as u64,
+        simd_level: CpuFeatures::detect().simd_level.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+/// Convert the embedded fixtures both ways and verify recognizable
+/// content survives.
+fn check_round_trip() -> Result<(), String> {
+    let markdown = rtf_to_markdown(RTF_FIXTURE).map_err(|e| format!("RTF -> MD: {e}"))?;
+    for expected in ["**Self-test**", "café", "| one | two |"] {
+        if !markdown.contains(expected) {
+            return Err(format!("RTF -> MD output lost {expected:?}: {markdown:?}"));
+        }
+    }
+    let rtf = markdown_to_rtf(MARKDOWN_FIXTURE).map_err(|e| format!("MD -> RTF: {e}"))?;
+    if !rtf.starts_with("{\\rtf1") || !rtf.contains("\\b bold\\b0") {
+        return Err(format!("MD -> RTF output malformed: {rtf:?}"));
+    }
+    Ok(())
+}
+
+/// The vectorized tokenizer must match the scalar one on this CPU.
+fn check_simd() -> Result<(), String> {
+    let scalar = tokenize(RTF_FIXTURE).map_err(|e| format!("scalar tokenizer: {e}"))?;
+    let simd = tokenize_simd(RTF_FIXTURE).map_err(|e| format!("simd tokenizer: {e}"))?;
+    if scalar != simd {
+        return Err(format!(
+            "token streams diverge at simd level {}",
+            CpuFeatures::detect().simd_level
+        ));
+    }
+    Ok(())
+}
+
+/// Convert the RTF fixture with counter collection on and return the
+/// run's statistics, for both [`check_stats`] and the report's `stats`
+/// field.
+fn fixture_stats() -> Result<ConversionStats, String> {
+    let config = PipelineConfig {
+        collect_stats: true,
+        ..PipelineConfig::default()
+    };
+    let output = DocumentPipeline::new(config)
+        .process(RTF_FIXTURE)
+        .map_err(|e| format!("fixture conversion: {e}"))?;
+    output
+        .stats
+        .ok_or_else(|| "collect_stats run produced no statistics".to_string())
+}
+
+/// Conversion counters must be collectable and agree with a direct
+/// tokenizer run and with CPU detection.
+fn check_stats() -> Result<(), String> {
+    let stats = fixture_stats()?;
+    let tokens = tokenize(RTF_FIXTURE).map_err(|e| format!("tokenize fixture: {e}"))?;
+    if stats.token_count != tokens.len() {
+        return Err(format!(
+            "stats counted {} tokens; the tokenizer produced {}",
+            stats.token_count,
+            tokens.len()
+        ));
+    }
+    let detected = CpuFeatures::detect().simd_level.to_string();
+    if stats.simd_level != detected {
+        return Err(format!(
+            "stats report simd level {}; detection says {detected}",
+            stats.simd_level
+        ));
+    }
+    if stats.input_bytes != RTF_FIXTURE.len() {
+        return Err(format!(
+            "stats report {} input bytes; the fixture is {}",
+            stats.input_bytes,
+            RTF_FIXTURE.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Write, re-read and delete a file in a private temp workspace; this is
+/// the check that catches antivirus interference with staging files.
+fn check_temp_files() -> Result<(), String> {
+    let dir = std::env::temp_dir().join(format!("legacybridge-selftest-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("create {}: {e}", dir.display()))?;
+    let outcome = temp_file_probe(&dir);
+    // Best-effort teardown even on failure; a stale dir is itself a finding.
+    let removed = std::fs::remove_dir_all(&dir);
+    outcome?;
+    removed.map_err(|e| format!("remove {}: {e}", dir.display()))
+}
+
+fn temp_file_probe(dir: &std::path::Path) -> Result<(), String> {
+    use crate::conversion::encoding::{safe_write, OutputEncoding};
+    let path = dir.join("probe.md");
+    safe_write(&path, MARKDOWN_FIXTURE, &OutputEncoding::default())
+        .map_err(|e| format!("write {}: {e}", path.display()))?;
+    let back =
+        std::fs::read_to_string(&path).map_err(|e| format!("read {}: {e}", path.display()))?;
+    if back != MARKDOWN_FIXTURE {
+        return Err(format!("probe file came back altered: {back:?}"));
+    }
+    std::fs::remove_file(&path).map_err(|e| format!("delete {}: {e}", path.display()))
+}
+
+/// Built-in templates must validate and apply to a parsed fixture.
+fn check_templates() -> Result<(), String> {
+    let system = TemplateSystem::new();
+    let names = system.names();
+    let name = names
+        .first()
+        .ok_or_else(|| "no built-in templates registered".to_string())?;
+    let tokens = tokenize(RTF_FIXTURE).map_err(|e| format!("tokenize fixture: {e}"))?;
+    let mut document = RtfParser::new(tokens)
+        .parse()
+        .map_err(|e| format!("parse fixture: {e}"))?;
+    system
+        .apply(name, &mut document)
+        .map_err(|e| format!("apply '{name}': {e}"))
+}
+
+/// Spin up as many workers as folder conversion would and convert the
+/// fixture on each; results must agree across threads.
+fn check_thread_pool() -> Result<(), String> {
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(4);
+    let expected = rtf_to_markdown(RTF_FIXTURE).map_err(|e| e.to_string())?;
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| scope.spawn(|| rtf_to_markdown(RTF_FIXTURE)))
+            .collect();
+        for handle in handles {
+            match handle.join() {
+                Ok(Ok(markdown)) if markdown == expected => {}
+                Ok(Ok(markdown)) => {
+                    return Err(format!("worker output diverged: {markdown:?}"))
+                }
+                Ok(Err(e)) => return Err(format!("worker conversion failed: {e}")),
+                Err(_) => return Err("worker thread panicked".to_string()),
+            }
+        }
+        Ok(())
+    })
+}
+
+/// The monotonic clock must advance and a fixture conversion must finish
+/// inside a generous bound; catches grossly throttled or virtualized
+/// hosts where the 2-second battery budget cannot hold.
+fn check_timing() -> Result<(), String> {
+    let before = Instant::now();
+    std::thread::sleep(std::time::Duration::from_millis(1));
+    if before.elapsed().is_zero() {
+        return Err("monotonic clock did not advance across a 1ms sleep".to_string());
+    }
+    let started = Instant::now();
+    rtf_to_markdown(RTF_FIXTURE).map_err(|e| e.to_string())?;
+    let elapsed = started.elapsed();
+    if elapsed > std::time::Duration::from_millis(500) {
+        return Err(format!(
+            "fixture conversion took {}ms; expected well under 500ms",
+            elapsed.as_millis()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn full_battery_passes_here_and_stays_in_budget() {
+        let started = Instant::now();
+        let report = run_selftest(0);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        assert!(report.passed, "{report:#?}");
+        assert_eq!(report.selftest_version, SELFTEST_VERSION);
+        let names: Vec<&str> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "round_trip",
+                "simd_equivalence",
+                "stats",
+                "temp_files",
+                "templates",
+                "thread_pool",
+                "timing"
+            ]
+        );
+        assert!(report.checks.iter().all(|c| c.error.is_none()));
+        assert!(!report.environment.os.is_empty());
+        assert!(report.environment.page_size > 0);
+        let stats = report.stats.as_ref().expect("full battery carries stats");
+        assert_eq!(stats.simd_level, report.environment.simd_level);
+        assert!(stats.token_count > 0);
+
+        // Reports survive the serde round trip support tickets rely on.
+        let json = serde_json::to_string(&report).unwrap();
+        let parsed: SelftestReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, report);
+    }
+
+    #[test]
+    fn flags_select_a_subset_of_checks() {
+        let report = run_selftest(CHECK_SIMD | CHECK_TIMING);
+        let names: Vec<&str> = report.checks.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["simd_equivalence", "timing"]);
+        assert!(report.passed, "{report:#?}");
+        // Stats ride along only when their check is selected.
+        assert!(report.stats.is_none());
+        assert!(run_selftest(CHECK_STATS).stats.is_some());
+    }
+}
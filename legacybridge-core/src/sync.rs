@@ -0,0 +1,56 @@
+//! Poison-tolerant locking for process-wide state.
+//!
+//! The crates in this workspace keep long-lived shared slots (last-error
+//! strings, folder reports, metrics counters, job tables) behind
+//! [`std::sync::Mutex`]. A panic while such a lock is held poisons it,
+//! and `lock().unwrap()` then panics on every later access - one crashed
+//! conversion would take the error-reporting path down with it, forever.
+//! Every slot guarded this way holds a plain value that is replaced or
+//! mutated in place without multi-step invariants, so the data is still
+//! coherent after a poisoning and the right recovery is to keep using it.
+
+use std::sync::{
+    Mutex, MutexGuard, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+};
+
+/// Lock `mutex`, recovering the guard when a previous holder panicked.
+pub fn lock_unpoisoned<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Read-lock `lock`, recovering the guard when a previous holder
+/// panicked; same rationale as [`lock_unpoisoned`].
+pub fn read_unpoisoned<T>(lock: &RwLock<T>) -> RwLockReadGuard<'_, T> {
+    lock.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Write-lock `lock`, recovering the guard when a previous holder
+/// panicked; same rationale as [`lock_unpoisoned`].
+pub fn write_unpoisoned<T>(lock: &RwLock<T>) -> RwLockWriteGuard<'_, T> {
+    lock.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn poisoned_locks_recover_instead_of_panicking() {
+        let shared = Mutex::new(7);
+        // Poison the lock by panicking while holding it.
+        let result = std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let _guard = shared.lock().unwrap();
+                    panic!("poison the lock");
+                })
+                .join()
+        });
+        assert!(result.is_err());
+        assert!(shared.is_poisoned());
+        assert_eq!(*lock_unpoisoned(&shared), 7);
+        *lock_unpoisoned(&shared) = 8;
+        assert_eq!(*lock_unpoisoned(&shared), 8);
+    }
+}
@@ -0,0 +1,199 @@
+//! Structural comparison helpers shared by the property tests and the
+//! (future) fidelity-report feature.
+//!
+//! Round trips are compared on a *normalized* view of the document, because
+//! the generators are allowed a set of documented, meaning-preserving
+//! rewrites:
+//!
+//! - adjacent text runs with identical formatting merge;
+//! - formatting wrappers with no effective attributes unwrap;
+//! - whitespace runs collapse to a single space and block edges are trimmed;
+//! - heading content compares text-only (headings are implicitly bold and
+//!   generators may drop or add the bold flag);
+//! - underline renders as italic in Markdown, so underline normalizes to
+//!   italic.
+
+use crate::conversion::rtf_parser::{RtfDocument, RtfNode, TextFormat};
+
+/// A flattened, comparison-friendly run of text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedRun {
+    pub bold: bool,
+    pub italic: bool,
+    pub strikethrough: bool,
+    pub text: String,
+}
+
+/// A block in the normalized projection of a document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NormalizedBlock {
+    Heading { level: u8, text: String },
+    Paragraph(Vec<NormalizedRun>),
+    ListItem { ordered: bool, runs: Vec<NormalizedRun> },
+    PageBreak,
+}
+
+/// Project a document onto its normalized form.
+pub fn normalize(document: &RtfDocument) -> Vec<NormalizedBlock> {
+    let mut blocks = Vec::new();
+    for node in &document.content {
+        match node {
+            RtfNode::Heading { level, content, .. } => {
+                let text = collapse_ws(&collect_text(content));
+                if !text.is_empty() {
+                    blocks.push(NormalizedBlock::Heading {
+                        level: *level,
+                        text,
+                    });
+                }
+            }
+            RtfNode::Paragraph { content, .. } => {
+                let runs = flatten_runs(content);
+                if !runs.is_empty() {
+                    blocks.push(NormalizedBlock::Paragraph(runs));
+                }
+            }
+            RtfNode::ListItem {
+                ordered, content, ..
+            } => {
+                let runs = flatten_runs(content);
+                if !runs.is_empty() {
+                    blocks.push(NormalizedBlock::ListItem {
+                        ordered: *ordered,
+                        runs,
+                    });
+                }
+            }
+            RtfNode::PageBreak => blocks.push(NormalizedBlock::PageBreak),
+            // Tables and loose inline nodes are covered by fixture tests.
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// True when two documents are structurally equivalent modulo the
+/// normalizations documented on this module.
+pub fn structurally_equivalent(a: &RtfDocument, b: &RtfDocument) -> bool {
+    normalize(a) == normalize(b)
+}
+
+/// Concatenation of every `Text` node in document order.
+pub fn concatenated_text(document: &RtfDocument) -> String {
+    fn walk(nodes: &[RtfNode], out: &mut String) {
+        for node in nodes {
+            match node {
+                RtfNode::Text(t) => out.push_str(t),
+                RtfNode::Formatted { content, .. }
+                | RtfNode::Hyperlink { content, .. }
+                | RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => walk(content, out),
+                RtfNode::Table(table) => {
+                    for row in &table.rows {
+                        for cell in &row.cells {
+                            walk(&cell.content, out);
+                        }
+                    }
+                }
+                RtfNode::Image { .. } | RtfNode::RawRtf { .. } | RtfNode::LineBreak
+                | RtfNode::PageBreak => {}
+            }
+        }
+    }
+    let mut out = String::new();
+    walk(&document.content, &mut out);
+    out
+}
+
+fn collect_text(nodes: &[RtfNode]) -> String {
+    let mut out = String::new();
+    for run in flatten_runs(nodes) {
+        out.push_str(&run.text);
+        out.push(' ');
+    }
+    out
+}
+
+fn flatten_runs(nodes: &[RtfNode]) -> Vec<NormalizedRun> {
+    fn walk(nodes: &[RtfNode], inherited: &TextFormat, out: &mut Vec<NormalizedRun>) {
+        for node in nodes {
+            match node {
+                RtfNode::Text(text) => push_run(out, inherited, text),
+                RtfNode::Formatted { format, content } => {
+                    let merged = TextFormat {
+                        bold: inherited.bold || format.bold,
+                        italic: inherited.italic || format.italic,
+                        underline: inherited.underline || format.underline,
+                        strikethrough: inherited.strikethrough || format.strikethrough,
+                        ..inherited.clone()
+                    };
+                    walk(content, &merged, out);
+                }
+                RtfNode::LineBreak => push_run(out, inherited, " "),
+                RtfNode::Hyperlink { content, .. }
+                | RtfNode::Paragraph { content, .. }
+                | RtfNode::Heading { content, .. }
+                | RtfNode::ListItem { content, .. } => walk(content, inherited, out),
+                RtfNode::Image { .. } | RtfNode::RawRtf { .. } | RtfNode::Table(_)
+                | RtfNode::PageBreak => {}
+            }
+        }
+    }
+
+    let mut runs = Vec::new();
+    walk(nodes, &TextFormat::default(), &mut runs);
+    // Collapse whitespace and trim block edges after merging.
+    for run in &mut runs {
+        run.text = collapse_ws_keep_edges(&run.text);
+    }
+    if let Some(first) = runs.first_mut() {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = runs.last_mut() {
+        last.text = last.text.trim_end().to_string();
+    }
+    runs.retain(|r| !r.text.is_empty());
+    runs
+}
+
+fn push_run(out: &mut Vec<NormalizedRun>, format: &TextFormat, text: &str) {
+    // Underline normalizes to italic (its Markdown rendering).
+    let italic = format.italic || format.underline;
+    if let Some(last) = out.last_mut() {
+        if last.bold == format.bold
+            && last.italic == italic
+            && last.strikethrough == format.strikethrough
+        {
+            last.text.push_str(text);
+            return;
+        }
+    }
+    out.push(NormalizedRun {
+        bold: format.bold,
+        italic,
+        strikethrough: format.strikethrough,
+        text: text.to_string(),
+    });
+}
+
+fn collapse_ws(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn collapse_ws_keep_edges(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_ws = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_ws {
+                out.push(' ');
+            }
+            in_ws = true;
+        } else {
+            out.push(c);
+            in_ws = false;
+        }
+    }
+    out
+}
@@ -0,0 +1,78 @@
+//! wasm-bindgen facade for browser embedding.
+//!
+//! Compiled only for `wasm32-unknown-unknown`; native builds (app and DLL)
+//! never see these symbols or the wasm-bindgen dependency. Errors are
+//! returned as `JsValue`s containing a JSON object of the shape
+//! `{ "code": i32, "category": str, "message": str, "position": u32|null }`.
+
+use crate::conversion::{self, ConversionError};
+use crate::security::SecurityLimits;
+use wasm_bindgen::prelude::*;
+
+fn error_to_js(e: &ConversionError) -> JsValue {
+    let payload = serde_json::json!({
+        "code": e.error_code(),
+        "category": e.category(),
+        "message": e.to_string(),
+        "position": serde_json::Value::Null,
+    });
+    JsValue::from_str(&payload.to_string())
+}
+
+/// Convert RTF to Markdown with default security limits.
+#[wasm_bindgen]
+pub fn rtf_to_markdown(rtf: &str) -> Result<String, JsValue> {
+    conversion::secure_rtf_to_markdown(rtf, &SecurityLimits::default())
+        .map_err(|e| error_to_js(&e))
+}
+
+/// Convert Markdown to RTF with default security limits.
+#[wasm_bindgen]
+pub fn markdown_to_rtf(markdown: &str) -> Result<String, JsValue> {
+    conversion::secure_markdown_to_rtf(markdown, &SecurityLimits::default())
+        .map_err(|e| error_to_js(&e))
+}
+
+/// Validate a document. `format` is "rtf" or "markdown". Returns the
+/// validation results as a JSON array.
+#[wasm_bindgen]
+pub fn validate_document(content: &str, format: &str) -> Result<String, JsValue> {
+    let validator = crate::conversion::pipeline::Validator::new(false);
+    let results = match format {
+        "rtf" => validator.validate_rtf(content),
+        "markdown" | "md" => validator.validate_markdown(content),
+        other => {
+            return Err(error_to_js(&ConversionError::validation(format!(
+                "unknown format: {other}"
+            ))))
+        }
+    };
+    serde_json::to_string(&results).map_err(|e| error_to_js(&ConversionError::generation(e.to_string())))
+}
+
+/// Analyze an RTF document without converting it. Returns a JSON object
+/// with token/node counts, metadata, the unsupported-feature usage map,
+/// and any form fields and reviewer comments found.
+#[wasm_bindgen]
+pub fn analyze_rtf(content: &str) -> Result<String, JsValue> {
+    let tokens = crate::conversion::lexer::tokenize(content)
+        .map_err(|m| error_to_js(&ConversionError::parse(m)))?;
+    let token_count = tokens.len();
+    let document = crate::conversion::rtf_parser::RtfParser::new(tokens)
+        .with_form_extraction(true)
+        .parse()
+        .map_err(|m| error_to_js(&ConversionError::parse(m)))?;
+    let outline =
+        crate::conversion::markdown_generator::MarkdownGenerator::new().outline(&document);
+    let payload = serde_json::json!({
+        "token_count": token_count,
+        "node_count": document.content.len(),
+        "title": document.metadata.title,
+        "author": document.metadata.author,
+        "outline": outline,
+        "feature_usage": document.metadata.feature_usage,
+        "form_fields": document.metadata.form_fields,
+        "annotations": document.metadata.annotations,
+    });
+    Ok(payload.to_string())
+}
@@ -0,0 +1,234 @@
+//! Golden-file regression corpus.
+//!
+//! Every fixture under `tests/corpus/input/` is converted under each
+//! relevant configuration and diffed against the committed expectation in
+//! `tests/corpus/expected/`. Conversion errors are golden too (recorded as
+//! an `!! conversion error:` line), so error-message regressions are
+//! caught alongside output regressions.
+//!
+//! To bless new or intentionally changed output:
+//!
+//! ```text
+//! BLESS=1 cargo test -p legacybridge-core --test corpus
+//! ```
+//!
+//! then review and commit the regenerated files under `expected/` like any
+//! other diff.
+
+use legacybridge_core::conversion::pipeline::{DocumentPipeline, PipelineConfig};
+use legacybridge_core::conversion::{self, ConversionMode};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Per-file conversion budget; corpus documents are small, so anything
+/// near this is a performance regression, not a big document.
+const TIME_BUDGET: Duration = Duration::from_secs(5);
+
+fn corpus_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus")
+}
+
+type Convert = Box<dyn Fn(&str) -> Result<String, String>>;
+
+/// One conversion of one fixture: the configuration name keys the
+/// expectation file, the closure produces the output to compare.
+struct Run {
+    config: &'static str,
+    output_ext: &'static str,
+    convert: Convert,
+}
+
+fn rtf_runs() -> Vec<Run> {
+    let pipeline = |config: PipelineConfig| {
+        move |input: &str| {
+            DocumentPipeline::new(config.clone())
+                .process(input)
+                .map(|output| output.markdown)
+                .map_err(|e| e.to_string())
+        }
+    };
+    vec![
+        Run {
+            config: "pipeline_default",
+            output_ext: "md",
+            convert: Box::new(pipeline(PipelineConfig::default())),
+        },
+        Run {
+            config: "pipeline_no_preserve",
+            output_ext: "md",
+            convert: Box::new(pipeline(PipelineConfig {
+                preserve_formatting: false,
+                ..Default::default()
+            })),
+        },
+        Run {
+            config: "pipeline_legacy",
+            output_ext: "md",
+            convert: Box::new(pipeline(PipelineConfig {
+                legacy_mode: true,
+                ..Default::default()
+            })),
+        },
+        Run {
+            config: "simple",
+            output_ext: "md",
+            convert: Box::new(|input: &str| {
+                conversion::rtf_to_markdown_with_mode(input, ConversionMode::Simple)
+                    .map(|(markdown, _)| markdown)
+                    .map_err(|e| e.to_string())
+            }),
+        },
+    ]
+}
+
+fn md_runs() -> Vec<Run> {
+    vec![
+        Run {
+            config: "rtf_default",
+            output_ext: "rtf",
+            convert: Box::new(|input: &str| {
+                conversion::markdown_to_rtf(input).map_err(|e| e.to_string())
+            }),
+        },
+        Run {
+            config: "rtf_legacy",
+            output_ext: "rtf",
+            convert: Box::new(|input: &str| {
+                conversion::markdown_to_rtf_legacy(input).map_err(|e| e.to_string())
+            }),
+        },
+    ]
+}
+
+/// Convert within the panic and time budgets; any outcome becomes
+/// comparable text so it can be golden.
+fn run_one(run: &Run, input: &str, what: &str, failures: &mut Vec<String>) -> Option<String> {
+    let started = Instant::now();
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| (run.convert)(input)));
+    let elapsed = started.elapsed();
+    if elapsed > TIME_BUDGET {
+        failures.push(format!(
+            "{what}: took {elapsed:?}, over the {TIME_BUDGET:?} budget"
+        ));
+    }
+    match result {
+        Ok(Ok(output)) => Some(output),
+        Ok(Err(message)) => Some(format!("!! conversion error: {message}\n")),
+        Err(_) => {
+            failures.push(format!("{what}: conversion panicked"));
+            None
+        }
+    }
+}
+
+/// Minimal unified-style diff: common prefix/suffix lines are elided,
+/// the differing middle is shown as `-expected` / `+actual` with a line
+/// of context on each side.
+fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let mut start = 0;
+    while start < expected.len() && start < actual.len() && expected[start] == actual[start] {
+        start += 1;
+    }
+    let mut end = 0;
+    while end < expected.len() - start
+        && end < actual.len() - start
+        && expected[expected.len() - 1 - end] == actual[actual.len() - 1 - end]
+    {
+        end += 1;
+    }
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "@@ expected lines {}..{}, actual lines {}..{} @@",
+        start + 1,
+        expected.len() - end,
+        start + 1,
+        actual.len() - end
+    );
+    if start > 0 {
+        let _ = writeln!(out, "  {}", expected[start - 1]);
+    }
+    const MAX_SHOWN: usize = 40;
+    let mut emit = |sign: char, lines: &[&str]| {
+        for line in lines.iter().take(MAX_SHOWN) {
+            let _ = writeln!(out, "{sign} {line}");
+        }
+        if lines.len() > MAX_SHOWN {
+            let _ = writeln!(out, "{sign} ... ({} more)", lines.len() - MAX_SHOWN);
+        }
+    };
+    emit('-', &expected[start..expected.len() - end]);
+    emit('+', &actual[start..actual.len() - end]);
+    if end > 0 {
+        let _ = writeln!(out, "  {}", expected[expected.len() - end]);
+    }
+    out
+}
+
+#[test]
+fn corpus_outputs_match_the_blessed_expectations() {
+    let bless = std::env::var("BLESS").is_ok_and(|v| v == "1");
+    let input_dir = corpus_dir().join("input");
+    let expected_dir = corpus_dir().join("expected");
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(&input_dir)
+        .expect("tests/corpus/input must exist")
+        .map(|entry| entry.expect("readable corpus entry").path())
+        .collect();
+    entries.sort();
+    assert!(
+        entries.len() >= 25,
+        "corpus shrank to {} fixtures; it is meant to grow",
+        entries.len()
+    );
+
+    let mut failures = Vec::new();
+    let mut blessed = 0usize;
+    for path in &entries {
+        let stem = path.file_stem().and_then(|s| s.to_str()).expect("utf-8 stem");
+        let ext = path.extension().and_then(|s| s.to_str()).unwrap_or("");
+        let runs = match ext {
+            "rtf" => rtf_runs(),
+            "md" => md_runs(),
+            other => panic!("unexpected corpus input extension {other:?} on {path:?}"),
+        };
+        let input = std::fs::read_to_string(path).expect("readable corpus input");
+        for run in &runs {
+            let what = format!("{stem}.{ext} [{}]", run.config);
+            let Some(output) = run_one(run, &input, &what, &mut failures) else {
+                continue;
+            };
+            let expected_path =
+                expected_dir.join(format!("{stem}.{}.{}", run.config, run.output_ext));
+            if bless {
+                std::fs::create_dir_all(&expected_dir).expect("create expected dir");
+                std::fs::write(&expected_path, &output).expect("write expectation");
+                blessed += 1;
+                continue;
+            }
+            match std::fs::read_to_string(&expected_path) {
+                Ok(expected) if expected == output => {}
+                Ok(expected) => failures.push(format!(
+                    "{what}: output differs from {}:\n{}",
+                    expected_path.display(),
+                    diff(&expected, &output)
+                )),
+                Err(_) => failures.push(format!(
+                    "{what}: no expectation at {}; run with BLESS=1 and commit it",
+                    expected_path.display()
+                )),
+            }
+        }
+    }
+    if bless {
+        println!("blessed {blessed} expectation file(s)");
+    }
+    assert!(
+        failures.is_empty(),
+        "{} corpus failure(s):\n\n{}",
+        failures.len(),
+        failures.join("\n")
+    );
+}
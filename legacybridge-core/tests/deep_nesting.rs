@@ -0,0 +1,79 @@
+//! Adversarial nesting must not exhaust the native stack.
+//!
+//! These tests bypass the security validator (which caps nesting long before
+//! this depth) to prove the traversals themselves are safe when limits are
+//! raised for legitimate deep documents. They run in a child thread with a
+//! deliberately small stack so a regression to recursive traversal fails as
+//! a test failure instead of crashing the whole test binary.
+
+use legacybridge_core::conversion::lexer::tokenize;
+use legacybridge_core::conversion::markdown_generator::MarkdownGenerator;
+use legacybridge_core::conversion::rtf_generator::RtfGenerator;
+use legacybridge_core::conversion::rtf_parser::{
+    DocumentMetadata, Direction, ParagraphSpacing, RtfDocument, RtfNode, RtfParser, TextFormat,
+};
+
+const NESTING_DEPTH: usize = 10_000;
+/// Small enough that ~10k recursive frames would overflow it.
+const STACK_SIZE: usize = 256 * 1024;
+
+fn in_small_stack_thread(f: impl FnOnce() + Send + 'static) {
+    std::thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(f)
+        .expect("spawn small-stack thread")
+        .join()
+        .expect("deep-nesting thread panicked");
+}
+
+#[test]
+fn parses_10k_nested_groups_in_small_stack_thread() {
+    in_small_stack_thread(|| {
+        let mut rtf = String::from("{\\rtf1 ");
+        rtf.push_str(&"{".repeat(NESTING_DEPTH));
+        rtf.push_str("deep text");
+        rtf.push_str(&"}".repeat(NESTING_DEPTH));
+        rtf.push_str("\\par}");
+
+        let tokens = tokenize(&rtf).unwrap();
+        let doc = RtfParser::new(tokens).parse().unwrap();
+        assert!(doc.plain_text().contains("deep text"));
+
+        let md = MarkdownGenerator::new().generate(&doc);
+        assert!(md.contains("deep text"));
+    });
+}
+
+#[test]
+fn generates_from_10k_deep_tree_in_small_stack_thread() {
+    in_small_stack_thread(|| {
+        // The parser flattens plain nested groups, so build the deep tree
+        // directly to exercise the generator walks.
+        let mut node = RtfNode::Text("deep text".to_string());
+        for _ in 0..NESTING_DEPTH {
+            node = RtfNode::Formatted {
+                format: TextFormat::default(),
+                content: vec![node],
+            };
+        }
+        let doc = RtfDocument {
+            metadata: DocumentMetadata::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content: vec![RtfNode::Paragraph {
+                direction: Direction::LeftToRight,
+                spacing: ParagraphSpacing::default(),
+                content: vec![node],
+            }],
+        };
+
+        assert!(doc.plain_text().contains("deep text"));
+
+        let md = MarkdownGenerator::new().generate(&doc);
+        assert!(md.contains("deep text"));
+
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        assert!(rtf.contains("deep text"));
+    });
+}
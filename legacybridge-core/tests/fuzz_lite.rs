@@ -0,0 +1,51 @@
+//! Deterministic replay of fuzz-found regression inputs.
+//!
+//! CI runs on stable and cannot run cargo-fuzz; this test replays the
+//! committed corpus under `tests/fuzz-corpus/` so fixed crashes stay fixed.
+//! When a fuzz target finds a new crash, minimize it and add it here.
+
+use legacybridge_core::conversion::{
+    lexer, markdown_parser::MarkdownParser, rtf_parser::RtfParser, secure_rtf_to_markdown,
+    simd_lexer,
+};
+use legacybridge_core::security::SecurityLimits;
+use std::path::PathBuf;
+
+fn corpus_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fuzz-corpus")
+}
+
+#[test]
+fn replay_corpus_without_panics() {
+    let limits = SecurityLimits::default();
+    let mut seen = 0;
+    for entry in std::fs::read_dir(corpus_dir()).expect("corpus directory missing") {
+        let path = entry.unwrap().path();
+        let Ok(input) = std::fs::read_to_string(&path) else {
+            continue;
+        };
+        seen += 1;
+        let name = path.display();
+
+        if path.extension().is_some_and(|e| e == "md") {
+            let _ = MarkdownParser::new().parse(&input);
+            continue;
+        }
+
+        // Lexers must agree wherever both succeed.
+        let scalar = lexer::tokenize(&input);
+        let simd = simd_lexer::tokenize_simd(&input);
+        assert_eq!(scalar, simd, "lexer divergence on {name}");
+
+        if let Ok(tokens) = scalar {
+            let _ = RtfParser::new(tokens).parse();
+        }
+        if let Ok(markdown) = secure_rtf_to_markdown(&input, &limits) {
+            assert!(
+                markdown.len() <= limits.max_output_size,
+                "unbounded output on {name}"
+            );
+        }
+    }
+    assert!(seen >= 5, "corpus looks empty ({seen} files)");
+}
@@ -0,0 +1,170 @@
+//! Property-based round-trip invariants for the document model.
+
+use legacybridge_core::conversion::markdown_generator::MarkdownGenerator;
+use legacybridge_core::conversion::markdown_parser::MarkdownParser;
+use legacybridge_core::conversion::rtf_generator::RtfGenerator;
+use legacybridge_core::conversion::rtf_parser::{
+    Direction, DocumentMetadata, ParagraphSpacing, RtfDocument, RtfNode, RtfParser, TextFormat,
+};
+use legacybridge_core::conversion::lexer::{tokenize, tokenize_spanned, SpannedToken};
+use legacybridge_core::conversion::simd_lexer::tokenize_simd_spanned;
+use legacybridge_core::test_support::{concatenated_text, normalize, structurally_equivalent};
+use proptest::prelude::*;
+
+/// Words that cannot collide with Markdown or RTF syntax; the escaping
+/// machinery has its own dedicated tests.
+fn word() -> impl Strategy<Value = String> {
+    "[a-zA-Z][a-zA-Z0-9]{0,7}"
+}
+
+fn text_run() -> impl Strategy<Value = String> {
+    prop::collection::vec(word(), 1..4).prop_map(|words| words.join(" "))
+}
+
+fn format(allow_underline: bool) -> impl Strategy<Value = TextFormat> {
+    (any::<bool>(), any::<bool>(), any::<bool>(), any::<bool>()).prop_map(
+        move |(bold, italic, underline, strikethrough)| TextFormat {
+            bold,
+            italic,
+            underline: underline && allow_underline,
+            strikethrough,
+            ..Default::default()
+        },
+    )
+}
+
+fn inline_node(allow_underline: bool) -> impl Strategy<Value = RtfNode> {
+    prop_oneof![
+        3 => text_run().prop_map(RtfNode::Text),
+        2 => (format(allow_underline), text_run()).prop_map(|(format, text)| {
+            RtfNode::Formatted {
+                format,
+                content: vec![RtfNode::Text(text)],
+            }
+        }),
+    ]
+}
+
+fn block_node(allow_lists: bool, allow_underline: bool) -> impl Strategy<Value = RtfNode> {
+    let inline = || prop::collection::vec(inline_node(allow_underline), 1..4);
+    prop_oneof![
+        4 => inline().prop_map(|content| RtfNode::Paragraph {
+            direction: Direction::default(),
+            spacing: ParagraphSpacing::default(),
+            content,
+        }),
+        2 => (1u8..=6, text_run()).prop_map(|(level, text)| RtfNode::Heading {
+            level,
+            spacing: ParagraphSpacing::default(),
+            content: vec![RtfNode::Text(text)],
+        }),
+        if allow_lists { 2 } else { 0 } => (any::<bool>(), inline()).prop_map(|(ordered, content)| {
+            RtfNode::ListItem {
+                ordered,
+                level: 0,
+                content,
+            }
+        }),
+    ]
+}
+
+fn document(allow_lists: bool, allow_underline: bool) -> impl Strategy<Value = RtfDocument> {
+    prop::collection::vec(block_node(allow_lists, allow_underline), 1..6).prop_map(|content| {
+        RtfDocument {
+            metadata: DocumentMetadata::default(),
+            fonts: Vec::new(),
+            colors: Vec::new(),
+            styles: Vec::new(),
+            content,
+        }
+    })
+}
+
+/// Arbitrary source over the lexer's structural alphabet; not necessarily
+/// valid RTF, which the lexer tolerates by design.
+fn lexer_source() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9 {}\\\\'~_\r\n-]{0,64}"
+}
+
+/// The [`tokenize_spanned`] contract: spans tile the input (no gaps, no
+/// overlaps, full coverage) and stripping them reproduces [`tokenize`].
+fn assert_span_contract(input: &str, spanned: &[SpannedToken]) -> Result<(), TestCaseError> {
+    if let Some(first) = spanned.first() {
+        prop_assert_eq!(first.start, 0, "first span misses the input head");
+        prop_assert_eq!(
+            spanned.last().unwrap().end,
+            input.len(),
+            "last span misses the input tail"
+        );
+    }
+    for pair in spanned.windows(2) {
+        prop_assert_eq!(pair[0].end, pair[1].start, "gap or overlap between spans");
+    }
+    let stripped: Vec<_> = spanned.iter().map(|s| s.token.clone()).collect();
+    prop_assert_eq!(stripped, tokenize(input).unwrap());
+    Ok(())
+}
+
+proptest! {
+    /// Generated RTF parses back into a structurally equivalent tree.
+    #[test]
+    fn rtf_round_trip_is_structurally_equivalent(doc in document(false, true)) {
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        let reparsed = RtfParser::new(tokenize(&rtf).unwrap()).parse().unwrap();
+        prop_assert!(
+            structurally_equivalent(&doc, &reparsed),
+            "original: {:?}\nreparsed: {:?}\nrtf: {rtf}",
+            normalize(&doc),
+            normalize(&reparsed)
+        );
+    }
+
+    /// Generated Markdown parses back preserving text and heading/list
+    /// structure.
+    #[test]
+    fn markdown_round_trip_preserves_structure(doc in document(true, false)) {
+        let markdown = MarkdownGenerator::new().generate(&doc);
+        let reparsed = MarkdownParser::new().parse(&markdown).unwrap();
+        prop_assert!(
+            structurally_equivalent(&doc, &reparsed),
+            "original: {:?}\nreparsed: {:?}\nmarkdown: {markdown}",
+            normalize(&doc),
+            normalize(&reparsed)
+        );
+    }
+
+    /// On generated (hence lexable) RTF, spans tile the source and strip
+    /// back to the plain token stream, for both lexers.
+    #[test]
+    fn spans_tile_generated_rtf(doc in document(true, true)) {
+        let rtf = RtfGenerator::new().generate(&doc).unwrap();
+        let spanned = tokenize_spanned(&rtf).unwrap();
+        assert_span_contract(&rtf, &spanned)?;
+        prop_assert_eq!(tokenize_simd_spanned(&rtf).unwrap(), spanned);
+    }
+
+    /// On arbitrary structural soup the spanned lexers agree with
+    /// [`tokenize`] token-for-token and error-for-error.
+    #[test]
+    fn spans_tile_arbitrary_source(src in lexer_source()) {
+        match tokenize(&src) {
+            Ok(_) => {
+                let spanned = tokenize_spanned(&src).unwrap();
+                assert_span_contract(&src, &spanned)?;
+                prop_assert_eq!(tokenize_simd_spanned(&src).unwrap(), spanned);
+            }
+            Err(message) => {
+                prop_assert_eq!(tokenize_spanned(&src), Err(message.clone()));
+                prop_assert_eq!(tokenize_simd_spanned(&src), Err(message));
+            }
+        }
+    }
+
+    /// Plain-text extraction matches the concatenation of text nodes
+    /// (modulo the separators plain_text inserts between blocks).
+    #[test]
+    fn plain_text_matches_text_nodes(doc in document(true, true)) {
+        let strip = |s: String| s.chars().filter(|c| !c.is_whitespace()).collect::<String>();
+        prop_assert_eq!(strip(doc.plain_text()), strip(concatenated_text(&doc)));
+    }
+}
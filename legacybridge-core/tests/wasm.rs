@@ -0,0 +1,21 @@
+//! Headless browser tests for the wasm facade (`wasm-pack test --headless`).
+#![cfg(target_arch = "wasm32")]
+
+use legacybridge_core::wasm;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+#[wasm_bindgen_test]
+fn basic_conversion_works() {
+    let md = wasm::rtf_to_markdown("{\\rtf1 Hello \\b World\\b0\\par}").unwrap();
+    assert!(md.contains("Hello **World**"));
+}
+
+#[wasm_bindgen_test]
+fn error_path_returns_structured_error() {
+    let err = wasm::rtf_to_markdown("{\\rtf1{\\object\\objdata 0102}}").unwrap_err();
+    let text = err.as_string().unwrap();
+    assert!(text.contains("\"category\":\"validation\""));
+    assert!(text.contains("\"code\":-3"));
+}
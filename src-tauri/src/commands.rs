@@ -0,0 +1,2568 @@
+//! Tauri command handlers.
+//!
+//! Commands are plain functions so they stay testable without the GUI; the
+//! `gui` feature adds the `#[tauri::command]` attribute for IPC registration.
+
+use crate::conversion;
+use crate::conversion::builder::Conversion;
+use crate::conversion::cancel::CancellationToken;
+use crate::conversion::clock::ClockTimezone;
+use crate::conversion::{ConversionMode, ConversionPath};
+use crate::conversion::control_words;
+use crate::conversion::encoding::{
+    decode_input, safe_write, staging_path, writable_path, InputEncoding, OutputEncoding,
+    SUPPORTED_INPUT_ENCODINGS,
+};
+use crate::conversion::features::FeatureUsage;
+use crate::conversion::integrity::{self, IntegrityBlock};
+use crate::conversion::lexer::RtfToken;
+use crate::conversion::link_rewrite::LinkRewriteConfig;
+use crate::conversion::markdown_analysis::MarkdownAnalysis;
+use crate::conversion::markdown_generator::{OutlineEntry, RevisionMode};
+use crate::conversion::normalization::NormalizationForm;
+use crate::conversion::pipeline::{
+    self, AnnotationMode, Capabilities, CleanupPolicy, ConversionStats, DocumentPipeline,
+    PageRange, PipelineConfig, PipelineMetadata, PipelineOutput, RecoveryAction, Stage,
+    ValidationLevel, ValidationResult,
+};
+use crate::conversion::rtf_parser::{Annotation, PlaceholderPolicy};
+use crate::conversion::session::ConversionSession;
+use crate::conversion::simd_lexer;
+use crate::conversion::template::TemplateDiff;
+use crate::conversion::terminology::TerminologyConfig;
+use crate::security::{SanitizationMode, SecurityLimits};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine as _;
+use legacybridge_core::contracts;
+use legacybridge_core::ffi_error_bridge::ErrorEnvelope;
+use legacybridge_core::selftest::SelftestReport;
+use legacybridge_core::sync::lock_unpoisoned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionResponse {
+    pub success: bool,
+    pub content: Option<String>,
+    pub error: Option<String>,
+    /// Structured form of `error`, in the [`ErrorEnvelope`] schema shared
+    /// with the FFI bridge; the string stays for older frontends.
+    pub error_envelope: Option<ErrorEnvelope>,
+    /// Which execution path produced the content, for commands where
+    /// [`ConversionMode::Auto`] decides; `None` where it does not apply.
+    pub path: Option<ConversionPath>,
+}
+
+impl ConversionResponse {
+    fn ok(content: String) -> Self {
+        ConversionResponse {
+            success: true,
+            content: Some(content),
+            error: None,
+            error_envelope: None,
+            path: None,
+        }
+    }
+
+    fn err(message: impl std::fmt::Display) -> Self {
+        ConversionResponse {
+            success: false,
+            content: None,
+            error: Some(message.to_string()),
+            error_envelope: None,
+            path: None,
+        }
+    }
+
+    fn err_conversion(error: &pipeline::ConversionError) -> Self {
+        ConversionResponse {
+            error_envelope: Some(ErrorEnvelope::from(error)),
+            ..Self::err(error)
+        }
+    }
+}
+
+/// Response of the pipeline conversion command, carrying diagnostics and a
+/// categorized error so the UI can distinguish failure classes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineConversionResponse {
+    pub success: bool,
+    pub markdown: Option<String>,
+    pub validation_results: Vec<ValidationResult>,
+    pub metadata: Option<PipelineMetadata>,
+    pub error: Option<String>,
+    /// `parse` / `validation` / `generation` / `resource_limit`.
+    pub error_category: Option<String>,
+    /// Stable numeric code matching the FFI error codes (0 = success).
+    pub error_code: i32,
+    /// Structured form of `error`, in the [`ErrorEnvelope`] schema shared
+    /// with the FFI bridge; subsumes `error_category`/`error_code`, which
+    /// stay for older frontends.
+    pub error_envelope: Option<ErrorEnvelope>,
+    /// What the configured template would change; only set on dry runs.
+    pub template_diff: Option<TemplateDiff>,
+    /// Unsupported constructs the document uses, for the degradation
+    /// report panel; empty when the document converts faithfully.
+    pub feature_usage: FeatureUsage,
+    /// Reviewer comments extracted from the document, for the side panel;
+    /// populated regardless of the annotation mode.
+    pub annotations: Vec<Annotation>,
+    /// Structural repairs applied under auto-recovery, with before/after
+    /// source excerpts; empty when the input was well-formed.
+    pub recovery_actions: Vec<RecoveryAction>,
+    /// Results only a `strict_validation` run would have produced; only
+    /// populated when the request sets `compare_validation`.
+    pub strict_delta: Vec<ValidationResult>,
+    /// Performance counters for the run, for the diagnostics panel; only
+    /// set when the request enables `collect_stats`.
+    pub stats: Option<ConversionStats>,
+    /// Which execution path ran, when the request's `conversion_mode`
+    /// (or its `auto` default resolution) allowed a choice.
+    pub path: Option<ConversionPath>,
+}
+
+/// Pipeline settings accepted over IPC. Every field is optional; missing
+/// fields fall back to [`PipelineConfig::default`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PipelineConfigRequest {
+    /// Contract major of this request; missing means version 1. Requests
+    /// from a future major are rejected with a `contract` error by the
+    /// conversion commands; see [`contracts`].
+    pub v: Option<u32>,
+    /// Which execution path to use; defaults to the pipeline (this is
+    /// the pipeline command family). `simple` forces the direct path,
+    /// ignoring pipeline-only settings; `auto` applies the heuristic.
+    pub conversion_mode: Option<ConversionMode>,
+    pub strict_validation: Option<bool>,
+    pub auto_recovery: Option<bool>,
+    pub preserve_formatting: Option<bool>,
+    pub legacy_mode: Option<bool>,
+    pub font_map_path: Option<String>,
+    pub control_word_extensions_path: Option<String>,
+    pub page_range: Option<PageRange>,
+    pub template: Option<String>,
+    pub template_variables: Option<HashMap<String, String>>,
+    pub dry_run: Option<bool>,
+    pub revision_mode: Option<RevisionMode>,
+    pub extract_form_fields: Option<bool>,
+    pub annotation_mode: Option<AnnotationMode>,
+    pub placeholders: Option<PlaceholderPolicy>,
+    pub cleanup: Option<CleanupPolicy>,
+    pub wrap_width: Option<usize>,
+    pub spacing_comments: Option<bool>,
+    pub width_comments: Option<bool>,
+    pub max_cell_length: Option<usize>,
+    pub fallback_html_tables: Option<bool>,
+    pub output_encoding: Option<OutputEncoding>,
+    pub verify_output: Option<bool>,
+    pub sanitization_mode: Option<SanitizationMode>,
+    pub compare_validation: Option<bool>,
+    pub integrity: Option<bool>,
+    pub allow_fragment: Option<bool>,
+    pub heading_offset: Option<i8>,
+    pub normalization: Option<NormalizationForm>,
+    pub link_rewrite: Option<LinkRewriteConfig>,
+    pub link_rewrite_path: Option<String>,
+    pub terminology: Option<TerminologyConfig>,
+    pub terminology_path: Option<String>,
+    pub fixed_timestamp: Option<String>,
+    pub timezone: Option<ClockTimezone>,
+    pub collect_stats: Option<bool>,
+}
+
+impl PipelineConfigRequest {
+    fn into_config(self) -> PipelineConfig {
+        let defaults = PipelineConfig::default();
+        PipelineConfig {
+            strict_validation: self.strict_validation.unwrap_or(defaults.strict_validation),
+            auto_recovery: self.auto_recovery.unwrap_or(defaults.auto_recovery),
+            preserve_formatting: self
+                .preserve_formatting
+                .unwrap_or(defaults.preserve_formatting),
+            legacy_mode: self.legacy_mode.unwrap_or(defaults.legacy_mode),
+            font_map_path: self.font_map_path,
+            control_word_extensions_path: self.control_word_extensions_path,
+            page_range: self.page_range,
+            template: self.template,
+            template_variables: self.template_variables.unwrap_or_default(),
+            dry_run: self.dry_run.unwrap_or(defaults.dry_run),
+            revision_mode: self.revision_mode.unwrap_or(defaults.revision_mode),
+            extract_form_fields: self
+                .extract_form_fields
+                .unwrap_or(defaults.extract_form_fields),
+            annotation_mode: self.annotation_mode.unwrap_or(defaults.annotation_mode),
+            placeholders: self.placeholders.unwrap_or(defaults.placeholders),
+            cleanup: self.cleanup.unwrap_or(defaults.cleanup),
+            wrap_width: self.wrap_width.or(defaults.wrap_width),
+            spacing_comments: self.spacing_comments.unwrap_or(defaults.spacing_comments),
+            width_comments: self.width_comments.unwrap_or(defaults.width_comments),
+            max_cell_length: self.max_cell_length.or(defaults.max_cell_length),
+            fallback_html_tables: self
+                .fallback_html_tables
+                .unwrap_or(defaults.fallback_html_tables),
+            stop_after: defaults.stop_after,
+            output_encoding: self.output_encoding.unwrap_or(defaults.output_encoding),
+            verify_output: self.verify_output.unwrap_or(defaults.verify_output),
+            sanitization_mode: self
+                .sanitization_mode
+                .unwrap_or(defaults.sanitization_mode),
+            compare_validation: self
+                .compare_validation
+                .unwrap_or(defaults.compare_validation),
+            integrity: self.integrity.unwrap_or(defaults.integrity),
+            allow_fragment: self.allow_fragment.unwrap_or(defaults.allow_fragment),
+            heading_offset: self.heading_offset.unwrap_or(defaults.heading_offset),
+            normalization: self.normalization.unwrap_or(defaults.normalization),
+            link_rewrite: self.link_rewrite,
+            link_rewrite_path: self.link_rewrite_path,
+            terminology: self.terminology,
+            terminology_path: self.terminology_path,
+            // Not exposed over IPC: only the file-backed command raises
+            // the cap, for inputs that never cross the webview.
+            max_document_size: defaults.max_document_size,
+            fixed_timestamp: self.fixed_timestamp,
+            timezone: self.timezone.unwrap_or(defaults.timezone),
+            collect_stats: self.collect_stats.unwrap_or(defaults.collect_stats),
+        }
+    }
+}
+
+fn run_pipeline(content: &str, config: PipelineConfig) -> PipelineConversionResponse {
+    pipeline_response(Conversion::rtf_to_markdown(content).config(config).run_full())
+}
+
+fn pipeline_response(
+    result: Result<PipelineOutput, pipeline::ConversionError>,
+) -> PipelineConversionResponse {
+    match result {
+        Ok(output) => PipelineConversionResponse {
+            success: true,
+            markdown: Some(output.markdown),
+            validation_results: output.validation_results,
+            metadata: Some(output.metadata),
+            error: None,
+            error_category: None,
+            error_code: 0,
+            error_envelope: None,
+            template_diff: output.template_diff,
+            feature_usage: output.feature_usage,
+            annotations: output.annotations,
+            recovery_actions: output.recovery_actions,
+            strict_delta: output.strict_delta,
+            stats: output.stats,
+            path: Some(ConversionPath::Pipeline),
+        },
+        Err(e) => PipelineConversionResponse {
+            success: false,
+            markdown: None,
+            validation_results: Vec::new(),
+            metadata: None,
+            error: Some(e.to_string()),
+            error_category: Some(e.category().to_string()),
+            error_code: e.error_code(),
+            error_envelope: Some(ErrorEnvelope::from(&e)),
+            template_diff: None,
+            feature_usage: FeatureUsage::default(),
+            annotations: Vec::new(),
+            recovery_actions: Vec::new(),
+            strict_delta: Vec::new(),
+            stats: None,
+            path: None,
+        },
+    }
+}
+
+/// Convert RTF content through the full pipeline, returning diagnostics.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn rtf_to_markdown_pipeline(content: String) -> PipelineConversionResponse {
+    if let Some(e) = inline_size_guard(&content) {
+        return pipeline_response(Err(e));
+    }
+    run_pipeline(&content, PipelineConfig::default())
+}
+
+/// Convert RTF content through the pipeline with explicit settings. A
+/// `conversion_mode` of `simple` (or `auto` resolving to the simple path)
+/// bypasses the pipeline; the response's `path` reports what ran.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn rtf_to_markdown_pipeline_with_config(
+    content: String,
+    config: PipelineConfigRequest,
+) -> PipelineConversionResponse {
+    if let Err(e) = contracts::accept_version(config.v) {
+        return pipeline_response(Err(e));
+    }
+    if let Some(e) = inline_size_guard(&content) {
+        return pipeline_response(Err(e));
+    }
+    let mode = config.conversion_mode.unwrap_or(ConversionMode::Pipeline);
+    let path = match conversion::resolve_conversion_path(&content, mode) {
+        Ok(path) => path,
+        Err(e) => return pipeline_response(Err(e)),
+    };
+    match path {
+        ConversionPath::Pipeline => run_pipeline(&content, config.into_config()),
+        ConversionPath::Simple => {
+            match conversion::rtf_to_markdown_with_mode(&content, ConversionMode::Simple) {
+                Ok((markdown, _)) => PipelineConversionResponse {
+                    markdown: Some(markdown),
+                    path: Some(ConversionPath::Simple),
+                    ..pipeline_empty_success()
+                },
+                Err(e) => pipeline_response(Err(e)),
+            }
+        }
+    }
+}
+
+/// A successful [`PipelineConversionResponse`] with no diagnostics, for
+/// the simple path (which produces none).
+fn pipeline_empty_success() -> PipelineConversionResponse {
+    PipelineConversionResponse {
+        success: true,
+        markdown: None,
+        validation_results: Vec::new(),
+        metadata: None,
+        error: None,
+        error_category: None,
+        error_code: 0,
+        error_envelope: None,
+        template_diff: None,
+        feature_usage: FeatureUsage::default(),
+        annotations: Vec::new(),
+        recovery_actions: Vec::new(),
+        strict_delta: Vec::new(),
+        stats: None,
+        path: None,
+    }
+}
+
+/// Response of [`preview_rtf_page`]: one page of Markdown plus the total
+/// page count so the UI can page through the document.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewResponse {
+    pub success: bool,
+    pub markdown: Option<String>,
+    pub page: usize,
+    pub page_count: usize,
+    pub error: Option<String>,
+}
+
+/// Convert a single page (1-based) of an RTF document for preview.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn preview_rtf_page(content: String, page: usize) -> PreviewResponse {
+    let page_count = match pipeline::page_count(&content) {
+        Ok(count) => count,
+        Err(e) => {
+            return PreviewResponse {
+                success: false,
+                markdown: None,
+                page,
+                page_count: 0,
+                error: Some(e.to_string()),
+            }
+        }
+    };
+    let config = PipelineConfig {
+        page_range: Some(PageRange {
+            start: page,
+            end: page,
+        }),
+        ..Default::default()
+    };
+    match Conversion::rtf_to_markdown(content).config(config).run_full() {
+        Ok(output) => PreviewResponse {
+            success: true,
+            markdown: Some(output.markdown),
+            page,
+            page_count,
+            error: None,
+        },
+        Err(e) => PreviewResponse {
+            success: false,
+            markdown: None,
+            page,
+            page_count,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Response of [`preview_template`]: the diff a template would produce.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplatePreviewResponse {
+    pub success: bool,
+    pub diff: Option<TemplateDiff>,
+    pub error: Option<String>,
+}
+
+/// Report what applying a template would change, without converting.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn preview_template(
+    content: String,
+    template_name: String,
+    variables: Option<HashMap<String, String>>,
+) -> TemplatePreviewResponse {
+    let config = PipelineConfig {
+        template: Some(template_name),
+        template_variables: variables.unwrap_or_default(),
+        dry_run: true,
+        ..Default::default()
+    };
+    match Conversion::rtf_to_markdown(content).config(config).run_full() {
+        Ok(output) => TemplatePreviewResponse {
+            success: true,
+            diff: output.template_diff,
+            error: None,
+        },
+        Err(e) => TemplatePreviewResponse {
+            success: false,
+            diff: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Convert RTF content to Markdown. `mode` defaults to
+/// [`ConversionMode::Auto`]; the response reports which path ran.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn rtf_to_markdown(content: String, mode: Option<ConversionMode>) -> ConversionResponse {
+    if let Some(e) = inline_size_guard(&content) {
+        return ConversionResponse::err_conversion(&e);
+    }
+    match conversion::rtf_to_markdown_with_mode(&content, mode.unwrap_or_default()) {
+        Ok((markdown, path)) => ConversionResponse {
+            path: Some(path),
+            ..ConversionResponse::ok(markdown)
+        },
+        Err(e) => ConversionResponse::err_conversion(&e),
+    }
+}
+
+/// Convert Markdown content to RTF.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn markdown_to_rtf(content: String) -> ConversionResponse {
+    match conversion::markdown_to_rtf(&content) {
+        Ok(rtf) => ConversionResponse::ok(rtf),
+        Err(e) => ConversionResponse::err_conversion(&e),
+    }
+}
+
+/// [`markdown_to_rtf`] with an embedded `{\*\lbsig ...}` integrity
+/// signature; see [`verify_integrity`]. For the RTF->Markdown direction,
+/// enable `integrity` in [`rtf_to_markdown_pipeline_with_config`]'s
+/// configuration instead.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn markdown_to_rtf_with_integrity(content: String) -> ConversionResponse {
+    match conversion::markdown_to_rtf_with_integrity(&content) {
+        Ok(rtf) => ConversionResponse::ok(rtf),
+        Err(e) => ConversionResponse::err_conversion(&e),
+    }
+}
+
+/// The verdict on a signed document's integrity block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IntegrityResponse {
+    /// The block was found and the content still hashes to what it
+    /// records; `block` carries what was signed.
+    pub valid: bool,
+    pub block: Option<IntegrityBlock>,
+    pub error: Option<String>,
+}
+
+/// Check the integrity signature in converted content (Markdown trailer
+/// or RTF `{\*\lbsig ...}` block): re-hashes the content minus the block
+/// and compares against the recorded output hash. Comparing the returned
+/// input hash and options fingerprint against records is the caller's
+/// side of the audit.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn verify_integrity(content: String) -> IntegrityResponse {
+    match integrity::verify_integrity(&content) {
+        Ok(block) => IntegrityResponse {
+            valid: true,
+            block: Some(block),
+            error: None,
+        },
+        Err(e) => IntegrityResponse {
+            valid: false,
+            block: None,
+            error: Some(e),
+        },
+    }
+}
+
+/// Re-emit Markdown in our canonical form, optionally re-flowed at
+/// `wrap_width` columns for line-oriented review.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn normalize_markdown(content: String, wrap_width: Option<usize>) -> ConversionResponse {
+    use crate::conversion::markdown_generator::MarkdownGenerator;
+    use crate::conversion::markdown_parser::MarkdownParser;
+
+    match MarkdownParser::new().parse(&content) {
+        Ok(document) => ConversionResponse::ok(
+            MarkdownGenerator::new()
+                .with_wrap_width(wrap_width)
+                .generate(&document),
+        ),
+        Err(e) => ConversionResponse::err_conversion(&pipeline::ConversionError::parse(e)),
+    }
+}
+
+/// Outline of the converted document, for the UI's navigation panel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlineResponse {
+    pub success: bool,
+    /// Heading entries in document order; offsets index the Markdown the
+    /// default pipeline emits for the same content, so they line up with
+    /// [`rtf_to_markdown_pipeline`]'s output.
+    pub outline: Vec<OutlineEntry>,
+    pub error: Option<String>,
+}
+
+/// Extract the document outline without returning the Markdown, for the
+/// navigation panel. Replaces the frontend's Markdown scraping, which
+/// broke on `#` inside code spans.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn get_outline(content: String) -> OutlineResponse {
+    match Conversion::rtf_to_markdown(content).run_full() {
+        Ok(output) => OutlineResponse {
+            success: true,
+            outline: output.metadata.outline,
+            error: None,
+        },
+        Err(e) => OutlineResponse {
+            success: false,
+            outline: Vec::new(),
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Markdown pre-flight analysis, for the conversion preview panel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkdownAnalysisResponse {
+    pub success: bool,
+    pub analysis: Option<MarkdownAnalysis>,
+    pub error: Option<String>,
+}
+
+/// Analyze a Markdown file before MD->RTF conversion: outline, feature
+/// counts, predicted output size and which features have no native RTF
+/// mapping. The symmetrical RTF-direction analysis lives in the wasm
+/// bindings' `analyze_rtf`.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn analyze_markdown(content: String) -> MarkdownAnalysisResponse {
+    match conversion::markdown_analysis::analyze_markdown(&content) {
+        Ok(analysis) => MarkdownAnalysisResponse {
+            success: true,
+            analysis: Some(analysis),
+            error: None,
+        },
+        Err(e) => MarkdownAnalysisResponse {
+            success: false,
+            analysis: None,
+            error: Some(e.to_string()),
+        },
+    }
+}
+
+/// Report the output dialect a configuration produces (RTF spec level,
+/// escaping, Markdown syntax), so integrators can verify what a given
+/// `legacy_mode` setting actually gets them. No config means defaults.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn conversion_capabilities(config: Option<PipelineConfigRequest>) -> Capabilities {
+    config
+        .map(PipelineConfigRequest::into_config)
+        .unwrap_or_default()
+        .capabilities()
+}
+
+/// Look up support for an RTF control word (without the backslash), or
+/// list the whole support table when `word` is omitted. An empty list for
+/// a queried word means LegacyBridge does not recognize it.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn control_word_support(word: Option<String>) -> Vec<control_words::ControlWordInfo> {
+    match word {
+        Some(word) => control_words::control_word_info(&word).into_iter().collect(),
+        None => control_words::control_word_table().to_vec(),
+    }
+}
+
+/// Token spans for syntax highlighting, in a compact form the editor can
+/// index without per-token objects.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HighlightResponse {
+    pub success: bool,
+    /// `(kind, start, end)` triples in input order; `start`/`end` are byte
+    /// offsets into the submitted content, tiling it without overlaps.
+    /// Kinds: `group_start`, `group_end`, `control_word`, `control_symbol`,
+    /// `text`.
+    pub tokens: Vec<(String, usize, usize)>,
+    pub error: Option<String>,
+}
+
+/// Tokenize RTF content for the editor's syntax highlighter, without
+/// parsing or converting it. Uses the SIMD lexer, so large documents
+/// re-highlight at typing speed.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn tokenize_for_highlighting(content: String) -> HighlightResponse {
+    match simd_lexer::tokenize_simd_spanned(&content) {
+        Ok(spanned) => HighlightResponse {
+            success: true,
+            tokens: spanned
+                .into_iter()
+                .map(|s| {
+                    let kind = match s.token {
+                        RtfToken::GroupStart => "group_start",
+                        RtfToken::GroupEnd => "group_end",
+                        RtfToken::ControlWord { .. } => "control_word",
+                        RtfToken::ControlSymbol(_) => "control_symbol",
+                        RtfToken::Text(_) => "text",
+                    };
+                    (kind.to_string(), s.start, s.end)
+                })
+                .collect(),
+            error: None,
+        },
+        Err(e) => HighlightResponse {
+            success: false,
+            tokens: Vec::new(),
+            error: Some(e),
+        },
+    }
+}
+
+/// Run the deployment self-test battery, for the diagnostics panel: the
+/// same checks the DLL exports as `legacybridge_run_selftest`, so desktop
+/// and DLL installs produce comparable reports. `flags` is a bit set of
+/// the check-selection constants in `legacybridge_core::selftest`;
+/// omitted or 0 runs everything.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn run_selftest(flags: Option<u32>) -> SelftestReport {
+    legacybridge_core::selftest::run_selftest(flags.unwrap_or(0))
+}
+
+/// A background conversion started by [`start_conversion_job`].
+struct ConversionJob {
+    cancel: CancellationToken,
+    /// Filled by the worker thread when the conversion finishes.
+    result: Arc<Mutex<Option<PipelineConversionResponse>>>,
+}
+
+/// Background conversion jobs, keyed by the id returned from
+/// [`start_conversion_job`]. Finished jobs stay until the UI collects
+/// them via [`conversion_job_status`].
+fn jobs() -> &'static Mutex<HashMap<u64, ConversionJob>> {
+    static JOBS: OnceLock<Mutex<HashMap<u64, ConversionJob>>> = OnceLock::new();
+    JOBS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Response of the job commands: the job's id, its current status
+/// (`running`, `completed`, `cancelled` or `unknown`), and the pipeline
+/// response once it has one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobStatusResponse {
+    pub job_id: u64,
+    pub status: String,
+    pub response: Option<PipelineConversionResponse>,
+}
+
+fn job_status(job_id: u64, result: &Mutex<Option<PipelineConversionResponse>>) -> JobStatusResponse {
+    let result = lock_unpoisoned(result);
+    let status = match &*result {
+        None => "running",
+        Some(response) if response.error_category.as_deref() == Some("cancelled") => "cancelled",
+        Some(_) => "completed",
+    };
+    JobStatusResponse {
+        job_id,
+        status: status.to_string(),
+        response: result.clone(),
+    }
+}
+
+/// Start a conversion on a worker thread so a runaway document can be
+/// stopped from the UI: poll [`conversion_job_status`] for the
+/// completed/cancelled transition, and call [`cancel_conversion_job`] to
+/// stop it mid-flight.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn start_conversion_job(
+    content: String,
+    config: Option<PipelineConfigRequest>,
+) -> JobStatusResponse {
+    let job_id = NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed);
+    if let Err(e) = contracts::accept_version(config.as_ref().and_then(|c| c.v)) {
+        return JobStatusResponse {
+            job_id,
+            status: "completed".to_string(),
+            response: Some(pipeline_response(Err(e))),
+        };
+    }
+    if let Some(e) = inline_size_guard(&content) {
+        return JobStatusResponse {
+            job_id,
+            status: "completed".to_string(),
+            response: Some(pipeline_response(Err(e))),
+        };
+    }
+    let cancel = CancellationToken::new();
+    let result = Arc::new(Mutex::new(None));
+    lock_unpoisoned(jobs()).insert(
+        job_id,
+        ConversionJob {
+            cancel: cancel.clone(),
+            result: Arc::clone(&result),
+        },
+    );
+    let config = config.map(PipelineConfigRequest::into_config).unwrap_or_default();
+    std::thread::spawn(move || {
+        let response = pipeline_response(
+            Conversion::rtf_to_markdown(content)
+                .config(config)
+                .cancellation(cancel)
+                .run_full(),
+        );
+        *lock_unpoisoned(&result) = Some(response);
+    });
+    JobStatusResponse {
+        job_id,
+        status: "running".to_string(),
+        response: None,
+    }
+}
+
+/// Report a job's status, returning the conversion response once the job
+/// has completed or been cancelled.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn conversion_job_status(job_id: u64) -> JobStatusResponse {
+    match lock_unpoisoned(jobs()).get(&job_id) {
+        Some(job) => job_status(job_id, &job.result),
+        None => JobStatusResponse {
+            job_id,
+            status: "unknown".to_string(),
+            response: None,
+        },
+    }
+}
+
+/// Request cancellation of a running job. The worker observes the token
+/// inside the lexer/parser loops, so even a single large document stops
+/// promptly; the job's status then transitions to `cancelled`.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn cancel_conversion_job(job_id: u64) -> JobStatusResponse {
+    match lock_unpoisoned(jobs()).get(&job_id) {
+        Some(job) => {
+            job.cancel.cancel();
+            job_status(job_id, &job.result)
+        }
+        None => JobStatusResponse {
+            job_id,
+            status: "unknown".to_string(),
+            response: None,
+        },
+    }
+}
+
+/// One file's result from [`validate_folder`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileValidationReport {
+    pub file: String,
+    /// Whether a full conversion of this file would succeed.
+    pub valid: bool,
+    pub error: Option<String>,
+    pub validation_results: Vec<ValidationResult>,
+    pub recovery_actions: Vec<RecoveryAction>,
+    pub feature_usage: FeatureUsage,
+    /// Results only a strict run would have produced; populated when the
+    /// request sets `compare_validation`.
+    pub strict_delta: Vec<ValidationResult>,
+}
+
+/// Response of [`validate_folder`]: per-file reports plus summary counts
+/// for the pre-flight panel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolderValidationResponse {
+    pub success: bool,
+    pub total: usize,
+    /// Files a full conversion would accept.
+    pub valid: usize,
+    /// Valid files that still carry validation warnings.
+    pub with_warnings: usize,
+    /// Valid files that needed structural repairs to parse.
+    pub needing_recovery: usize,
+    /// Valid files that `strict_validation` would have rejected; only
+    /// counted when the request sets `compare_validation`.
+    pub would_fail_strict: usize,
+    pub files: Vec<FileValidationReport>,
+    /// Folder-level failure (unreadable directory); per-file failures land
+    /// in their reports instead.
+    pub error: Option<String>,
+}
+
+impl FolderValidationResponse {
+    fn err(message: impl std::fmt::Display) -> Self {
+        FolderValidationResponse {
+            success: false,
+            total: 0,
+            valid: 0,
+            with_warnings: 0,
+            needing_recovery: 0,
+            would_fail_strict: 0,
+            files: Vec::new(),
+            error: Some(message.to_string()),
+        }
+    }
+}
+
+/// Pre-flight every `.rtf` file in a directory without generating output:
+/// runs the pipeline's validation and parsing stages only (see
+/// [`Stage::Parse`]), so a large folder can be triaged at a fraction of
+/// conversion cost and nothing is written to disk.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn validate_folder(
+    dir: String,
+    config: Option<PipelineConfigRequest>,
+) -> FolderValidationResponse {
+    if let Err(e) = contracts::accept_version(config.as_ref().and_then(|c| c.v)) {
+        return FolderValidationResponse::err(e);
+    }
+    let config = PipelineConfig {
+        stop_after: Stage::Parse,
+        ..config.map(PipelineConfigRequest::into_config).unwrap_or_default()
+    };
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) => return FolderValidationResponse::err(format!("cannot read {dir}: {e}")),
+    };
+    let mut paths: Vec<std::path::PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("rtf"))
+        })
+        .collect();
+    paths.sort();
+
+    let pipeline = DocumentPipeline::new(config);
+    let mut files = Vec::with_capacity(paths.len());
+    for path in &paths {
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned();
+        let report = match std::fs::read_to_string(path) {
+            Err(e) => FileValidationReport {
+                file: name,
+                valid: false,
+                error: Some(format!("cannot read file: {e}")),
+                validation_results: Vec::new(),
+                recovery_actions: Vec::new(),
+                feature_usage: FeatureUsage::default(),
+                strict_delta: Vec::new(),
+            },
+            Ok(content) => match pipeline.process(&content) {
+                Ok(output) => FileValidationReport {
+                    file: name,
+                    valid: true,
+                    error: None,
+                    validation_results: output.validation_results,
+                    recovery_actions: output.recovery_actions,
+                    feature_usage: output.feature_usage,
+                    strict_delta: output.strict_delta,
+                },
+                Err(e) => FileValidationReport {
+                    file: name,
+                    valid: false,
+                    error: Some(e.to_string()),
+                    validation_results: Vec::new(),
+                    recovery_actions: Vec::new(),
+                    feature_usage: FeatureUsage::default(),
+                    strict_delta: Vec::new(),
+                },
+            },
+        };
+        files.push(report);
+    }
+
+    FolderValidationResponse {
+        success: true,
+        total: files.len(),
+        valid: files.iter().filter(|f| f.valid).count(),
+        with_warnings: files
+            .iter()
+            .filter(|f| {
+                f.valid
+                    && f.validation_results
+                        .iter()
+                        .any(|r| r.level == ValidationLevel::Warning)
+            })
+            .count(),
+        needing_recovery: files
+            .iter()
+            .filter(|f| f.valid && !f.recovery_actions.is_empty())
+            .count(),
+        would_fail_strict: files
+            .iter()
+            .filter(|f| {
+                f.valid
+                    && f.strict_delta
+                        .iter()
+                        .any(|r| r.level == ValidationLevel::Error)
+            })
+            .count(),
+        files,
+        error: None,
+    }
+}
+
+/// Open conversion sessions for the editor, keyed by the id returned from
+/// [`create_session`]. Sessions survive between IPC calls until closed.
+fn sessions() -> &'static Mutex<HashMap<u64, ConversionSession>> {
+    static SESSIONS: OnceLock<Mutex<HashMap<u64, ConversionSession>>> = OnceLock::new();
+    SESSIONS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResponse {
+    pub success: bool,
+    pub session_id: Option<u64>,
+    pub content: Option<String>,
+    pub error: Option<String>,
+}
+
+impl SessionResponse {
+    fn ok(session_id: u64, content: Option<String>) -> Self {
+        SessionResponse {
+            success: true,
+            session_id: Some(session_id),
+            content,
+            error: None,
+        }
+    }
+
+    fn err(message: impl std::fmt::Display) -> Self {
+        SessionResponse {
+            success: false,
+            session_id: None,
+            content: None,
+            error: Some(message.to_string()),
+        }
+    }
+}
+
+/// Start an incremental conversion session for `content`.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn create_session(content: String) -> SessionResponse {
+    match ConversionSession::new(content) {
+        Ok(session) => {
+            let id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+            lock_unpoisoned(sessions()).insert(id, session);
+            SessionResponse::ok(id, None)
+        }
+        Err(e) => SessionResponse::err(e),
+    }
+}
+
+/// Apply an edit (byte range plus replacement) to an open session.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn update_session(
+    session_id: u64,
+    start: usize,
+    end: usize,
+    replacement: String,
+) -> SessionResponse {
+    let mut sessions = lock_unpoisoned(sessions());
+    let Some(session) = sessions.get_mut(&session_id) else {
+        return SessionResponse::err(format!("no open session {session_id}"));
+    };
+    match session.apply_edit(start..end, &replacement) {
+        Ok(()) => SessionResponse::ok(session_id, None),
+        Err(e) => SessionResponse::err(e),
+    }
+}
+
+/// Get the (incrementally regenerated) Markdown output of a session.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn get_output(session_id: u64) -> SessionResponse {
+    let mut sessions = lock_unpoisoned(sessions());
+    let Some(session) = sessions.get_mut(&session_id) else {
+        return SessionResponse::err(format!("no open session {session_id}"));
+    };
+    match session.to_markdown() {
+        Ok(markdown) => SessionResponse::ok(session_id, Some(markdown)),
+        Err(e) => SessionResponse::err(e),
+    }
+}
+
+/// Close a session and release its caches, cancelling any preview
+/// conversion still running for it.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn close_session(session_id: u64) -> SessionResponse {
+    match lock_unpoisoned(sessions()).remove(&session_id) {
+        Some(_) => {
+            if let Some(slot) = lock_unpoisoned(previews()).remove(&session_id) {
+                if let Some(cancel) = &lock_unpoisoned(&slot).in_flight {
+                    cancel.cancel();
+                }
+            }
+            SessionResponse::ok(session_id, None)
+        }
+        None => SessionResponse::err(format!("no open session {session_id}")),
+    }
+}
+
+/// Preview state for one session: a coalescing single-slot queue. A new
+/// request replaces the queued one and cancels the in-flight conversion,
+/// so rapid typing runs at most one conversion at a time with at most one
+/// waiting, and results can never arrive out of order.
+struct PreviewSlot {
+    /// Sequence number handed to the most recent request.
+    latest_seq: u64,
+    /// The queued-but-not-started request; a newer one replaces it.
+    pending: Option<(u64, String)>,
+    /// Cancellation token of the conversion currently running.
+    in_flight: Option<CancellationToken>,
+    /// Whether this session's preview worker thread is alive.
+    worker_running: bool,
+    /// Most recent completed result, kept until a newer one lands.
+    ready: Option<(u64, PipelineConversionResponse)>,
+    /// Conversions actually started; superseded requests never run.
+    conversions_run: u64,
+}
+
+/// Preview queues by session id. Behind its own lock (not the session's)
+/// so polling never blocks on a running conversion.
+fn previews() -> &'static Mutex<HashMap<u64, Arc<Mutex<PreviewSlot>>>> {
+    static PREVIEWS: OnceLock<Mutex<HashMap<u64, Arc<Mutex<PreviewSlot>>>>> = OnceLock::new();
+    PREVIEWS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Response of the live-preview commands. `seq` is the accepted request's
+/// number from [`request_preview`], or the number of the carried result
+/// from [`latest_preview`]; the frontend discards anything older than the
+/// highest `seq` it has seen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LivePreviewResponse {
+    pub success: bool,
+    pub seq: u64,
+    /// Whether a newer request is still queued or converting.
+    pub busy: bool,
+    pub response: Option<PipelineConversionResponse>,
+    /// Conversions this session's queue has actually run.
+    pub conversions_run: u64,
+    pub error: Option<String>,
+}
+
+fn run_preview_worker(slot: Arc<Mutex<PreviewSlot>>) {
+    loop {
+        let (seq, content, cancel) = {
+            let mut slot = lock_unpoisoned(&slot);
+            let Some((seq, content)) = slot.pending.take() else {
+                slot.worker_running = false;
+                slot.in_flight = None;
+                return;
+            };
+            let cancel = CancellationToken::new();
+            slot.in_flight = Some(cancel.clone());
+            slot.conversions_run += 1;
+            (seq, content, cancel)
+        };
+        let response = pipeline_response(
+            Conversion::rtf_to_markdown(content)
+                .cancellation(cancel)
+                .run_full(),
+        );
+        let mut slot = lock_unpoisoned(&slot);
+        slot.in_flight = None;
+        // A cancelled conversion was superseded; publishing it would flash
+        // a stale preview, so only the newest completed result is kept.
+        if response.error_category.as_deref() != Some("cancelled")
+            && slot.ready.as_ref().is_none_or(|(s, _)| *s < seq)
+        {
+            slot.ready = Some((seq, response));
+        }
+    }
+}
+
+/// Queue a preview conversion of `content` for an open session. Requests
+/// coalesce: a queued-but-not-started request is replaced and an
+/// in-flight conversion is cancelled when a newer request arrives. Poll
+/// [`latest_preview`] for the result.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn request_preview(session_id: u64, content: String) -> LivePreviewResponse {
+    if !lock_unpoisoned(sessions()).contains_key(&session_id) {
+        return LivePreviewResponse {
+            success: false,
+            seq: 0,
+            busy: false,
+            response: None,
+            conversions_run: 0,
+            error: Some(format!("no open session {session_id}")),
+        };
+    }
+    let slot = Arc::clone(lock_unpoisoned(previews()).entry(session_id).or_insert_with(|| {
+        Arc::new(Mutex::new(PreviewSlot {
+            latest_seq: 0,
+            pending: None,
+            in_flight: None,
+            worker_running: false,
+            ready: None,
+            conversions_run: 0,
+        }))
+    }));
+    let mut locked = lock_unpoisoned(&slot);
+    locked.latest_seq += 1;
+    let seq = locked.latest_seq;
+    locked.pending = Some((seq, content));
+    if let Some(cancel) = &locked.in_flight {
+        cancel.cancel();
+    }
+    if !locked.worker_running {
+        locked.worker_running = true;
+        let worker_slot = Arc::clone(&slot);
+        std::thread::spawn(move || run_preview_worker(worker_slot));
+    }
+    let conversions_run = locked.conversions_run;
+    drop(locked);
+    LivePreviewResponse {
+        success: true,
+        seq,
+        busy: true,
+        response: None,
+        conversions_run,
+        error: None,
+    }
+}
+
+/// Report the newest completed preview for a session. The headless build
+/// has no event bus, so polling this command stands in for the
+/// `preview-ready` event: a result is ready when `busy` is false and
+/// `seq` matches the last accepted request.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn latest_preview(session_id: u64) -> LivePreviewResponse {
+    let slot = lock_unpoisoned(previews()).get(&session_id).cloned();
+    let Some(slot) = slot else {
+        return LivePreviewResponse {
+            success: lock_unpoisoned(sessions()).contains_key(&session_id),
+            seq: 0,
+            busy: false,
+            response: None,
+            conversions_run: 0,
+            error: None,
+        };
+    };
+    let slot = lock_unpoisoned(&slot);
+    let (seq, response) = match &slot.ready {
+        Some((seq, response)) => (*seq, Some(response.clone())),
+        None => (0, None),
+    };
+    LivePreviewResponse {
+        success: true,
+        seq,
+        busy: slot.pending.is_some() || slot.in_flight.is_some(),
+        response,
+        conversions_run: slot.conversions_run,
+        error: None,
+    }
+}
+
+/// Response of [`get_last_crash_report`]: the newest report from the
+/// crash directory, if any, plus the panic count for this process.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReportResponse {
+    pub found: bool,
+    /// Panics observed by this process since startup.
+    pub panics_total: u64,
+    pub report: Option<crate::crash::CrashReport>,
+}
+
+/// Offer the newest crash report left by a previous run, so the app can
+/// show "LegacyBridge crashed last time" with what it was doing (sizes
+/// and hashes only - never document content; see [`crate::crash`]).
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn get_last_crash_report() -> CrashReportResponse {
+    let report = crate::crash::last_report();
+    CrashReportResponse {
+        found: report.is_some(),
+        panics_total: crate::crash::panics_total(),
+        report,
+    }
+}
+
+/// Convert an RTF file on disk to a Markdown file. The optional output
+/// encoding controls the written file's line endings and BOM; the
+/// default is platform line endings without one. The optional input
+/// encoding overrides BOM/UTF-8 auto-detection when the operator knows
+/// better (see [`Capabilities::supported_input_encodings`]); an
+/// unsupported name fails before the file is read.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn convert_rtf_file_to_md(
+    input_path: String,
+    output_path: String,
+    encoding: Option<OutputEncoding>,
+    input_encoding: Option<String>,
+) -> ConversionResponse {
+    let forced = match &input_encoding {
+        Some(name) => match InputEncoding::from_name(name) {
+            Some(encoding) => Some(encoding),
+            None => {
+                return ConversionResponse::err(format!(
+                    "unsupported input encoding {name:?}; supported: {}",
+                    SUPPORTED_INPUT_ENCODINGS.join(", ")
+                ))
+            }
+        },
+        None => None,
+    };
+    let rtf = match std::fs::read(&input_path) {
+        Ok(bytes) => decode_input(&bytes, forced).0,
+        Err(e) => return ConversionResponse::err(format!("cannot read {input_path}: {e}")),
+    };
+    match conversion::rtf_to_markdown(&rtf) {
+        Ok(markdown) => match safe_write(&output_path, &markdown, &encoding.unwrap_or_default()) {
+            Ok(()) => ConversionResponse::ok(markdown),
+            Err(e) => ConversionResponse::err(format!("cannot write {output_path}: {e}")),
+        },
+        Err(e) => ConversionResponse::err_conversion(&e),
+    }
+}
+
+/// Size cap for [`convert_large_file`] unless the call raises it: ten
+/// times the inline
+/// [`MAX_DOCUMENT_SIZE`](pipeline::validation::MAX_DOCUMENT_SIZE).
+/// Deliberately separate from the inline limit, which stays small
+/// because every inline byte is copied through the webview.
+const DEFAULT_FILE_BACKED_LIMIT: usize = 100 * 1024 * 1024;
+
+/// One stage report from a running [`convert_large_file`] call, polled
+/// via [`large_file_progress`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LargeFileProgress {
+    /// `reading`, `converting`, `writing`, then `done`.
+    pub stage: String,
+    /// Input file size in bytes.
+    pub bytes_total: u64,
+}
+
+/// Response of [`convert_large_file`]. The Markdown goes to the output
+/// path and never crosses IPC; only sizes and diagnostics come back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargeFileResponse {
+    pub success: bool,
+    /// Size of the written output file.
+    pub bytes_written: Option<u64>,
+    pub validation_results: Vec<ValidationResult>,
+    /// Peak heap bytes the conversion allocated; `None` unless the
+    /// crate was built with the `memory-accounting` feature.
+    pub peak_memory_bytes: Option<usize>,
+    pub error: Option<String>,
+    pub error_envelope: Option<ErrorEnvelope>,
+}
+
+impl LargeFileResponse {
+    fn err(message: impl std::fmt::Display) -> Self {
+        LargeFileResponse {
+            success: false,
+            bytes_written: None,
+            validation_results: Vec::new(),
+            peak_memory_bytes: None,
+            error: Some(message.to_string()),
+            error_envelope: None,
+        }
+    }
+
+    fn err_conversion(error: &pipeline::ConversionError) -> Self {
+        LargeFileResponse {
+            error_envelope: Some(ErrorEnvelope::from(error)),
+            ..Self::err(error)
+        }
+    }
+}
+
+/// Latest [`LargeFileProgress`], for polling. A single slot like the
+/// live preview's: the UI runs one large conversion at a time, and a
+/// stale `done` from a previous run is harmless.
+fn large_file_progress_slot() -> &'static Mutex<Option<LargeFileProgress>> {
+    static SLOT: OnceLock<Mutex<Option<LargeFileProgress>>> = OnceLock::new();
+    SLOT.get_or_init(|| Mutex::new(None))
+}
+
+/// Pre-empt the pipeline's `RTF002` size error for content that arrived
+/// over IPC: the distinct `RTF121` code tells the frontend the document
+/// is not broken, merely too large for inline conversion, so it retries
+/// through [`convert_large_file`] instead of surfacing a failure.
+fn inline_size_guard(content: &str) -> Option<pipeline::ConversionError> {
+    let limit = pipeline::validation::MAX_DOCUMENT_SIZE;
+    (content.len() > limit).then(|| {
+        pipeline::ConversionError::validation_with_code(
+            "RTF121",
+            format!(
+                "document is {} bytes, above the {limit} byte inline limit; \
+                 convert it file-backed via convert_large_file",
+                content.len()
+            ),
+        )
+    })
+}
+
+/// Convert an RTF file on disk to a Markdown file without the content
+/// ever crossing IPC, for documents above the inline commands' limit
+/// (their `RTF121` error directs here). The cap is
+/// [`DEFAULT_FILE_BACKED_LIMIT`] unless `max_size` says otherwise. The
+/// input is read from disk in one buffer (the lexer has no streaming
+/// mode yet) and the output goes through [`safe_write`] under the
+/// config's output encoding. Poll [`large_file_progress`] for stage
+/// progress while this runs.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn convert_large_file(
+    input_path: String,
+    output_path: String,
+    config: Option<PipelineConfigRequest>,
+    max_size: Option<usize>,
+) -> LargeFileResponse {
+    convert_large_file_with_progress(&input_path, &output_path, config, max_size, |progress| {
+        *lock_unpoisoned(large_file_progress_slot()) = Some(progress);
+    })
+}
+
+/// Report the running (or last finished) [`convert_large_file`] stage.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn large_file_progress() -> Option<LargeFileProgress> {
+    lock_unpoisoned(large_file_progress_slot()).clone()
+}
+
+/// [`convert_large_file`] with the progress sink explicit, so tests can
+/// observe the stage sequence directly.
+fn convert_large_file_with_progress(
+    input_path: &str,
+    output_path: &str,
+    config: Option<PipelineConfigRequest>,
+    max_size: Option<usize>,
+    mut progress: impl FnMut(LargeFileProgress),
+) -> LargeFileResponse {
+    if let Err(e) = contracts::accept_version(config.as_ref().and_then(|c| c.v)) {
+        return LargeFileResponse::err_conversion(&e);
+    }
+    let limit = max_size.unwrap_or(DEFAULT_FILE_BACKED_LIMIT);
+    let bytes_total = match std::fs::metadata(input_path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return LargeFileResponse::err(format!("cannot read {input_path}: {e}")),
+    };
+    if bytes_total > limit as u64 {
+        return LargeFileResponse::err(format!(
+            "{input_path} is {bytes_total} bytes, above the {limit} byte file-backed limit"
+        ));
+    }
+    let mut report = |stage: &str| {
+        progress(LargeFileProgress {
+            stage: stage.to_string(),
+            bytes_total,
+        })
+    };
+
+    report("reading");
+    let rtf = match std::fs::read(input_path) {
+        Ok(bytes) => decode_input(&bytes, None).0,
+        Err(e) => return LargeFileResponse::err(format!("cannot read {input_path}: {e}")),
+    };
+
+    report("converting");
+    let mut pipeline_config = config
+        .map(PipelineConfigRequest::into_config)
+        .unwrap_or_default();
+    pipeline_config.max_document_size = Some(limit);
+    let encoding = pipeline_config.output_encoding;
+    let output = match Conversion::rtf_to_markdown(rtf).config(pipeline_config).run_full() {
+        Ok(output) => output,
+        Err(e) => return LargeFileResponse::err_conversion(&e),
+    };
+
+    report("writing");
+    if let Err(e) = safe_write(output_path, &output.markdown, &encoding) {
+        return LargeFileResponse::err(format!("cannot write {output_path}: {e}"));
+    }
+    report("done");
+    LargeFileResponse {
+        success: true,
+        bytes_written: std::fs::metadata(output_path).map(|m| m.len()).ok(),
+        validation_results: output.validation_results,
+        peak_memory_bytes: output.peak_memory_bytes,
+        error: None,
+        error_envelope: None,
+    }
+}
+
+/// Raw bytes of one transfer chunk, before base64 expansion. Fixed so
+/// the frontend can derive chunk counts and progress from the file size.
+const FILE_CHUNK_SIZE: u64 = 256 * 1024;
+
+/// Open transfer streams beyond this are refused until one closes or
+/// expires; a well-behaved frontend needs one or two per session.
+const MAX_OPEN_STREAMS: usize = 8;
+
+/// Streams untouched for this long are reclaimed by the next stream
+/// command, so an abandoned frontend tab cannot leak handles or staging
+/// files indefinitely.
+const STREAM_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// One open chunked transfer; see [`open_file_stream`] and
+/// [`begin_file_write`].
+enum FileStream {
+    /// Download: an open handle read in [`FILE_CHUNK_SIZE`] pieces.
+    Read { file: std::fs::File, size: u64 },
+    /// Upload: bytes appended to the target's staging file; the target
+    /// itself only changes when [`commit_file_write`] renames the
+    /// staging file into place.
+    Write {
+        /// `None` once the handle has been taken for the commit.
+        file: Option<std::fs::File>,
+        staging: PathBuf,
+        target: PathBuf,
+    },
+}
+
+/// A registered [`FileStream`] with the timestamp driving expiry.
+struct StreamEntry {
+    stream: FileStream,
+    /// Updated on every command that touches the stream.
+    last_used: Instant,
+}
+
+impl Drop for StreamEntry {
+    fn drop(&mut self) {
+        // An uncommitted write stream aborts: close the handle, then
+        // delete the staging file so the target is left untouched. A
+        // committed stream already renamed the staging file away,
+        // making the delete a no-op.
+        if let FileStream::Write { file, staging, .. } = &mut self.stream {
+            file.take();
+            let _ = std::fs::remove_file(&*staging);
+        }
+    }
+}
+
+/// Open chunked transfers by stream id, shared by the read and write
+/// command families below.
+fn streams() -> &'static Mutex<HashMap<u64, StreamEntry>> {
+    static STREAMS: OnceLock<Mutex<HashMap<u64, StreamEntry>>> = OnceLock::new();
+    STREAMS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static NEXT_STREAM_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Reclaim streams idle past [`STREAM_IDLE_TIMEOUT`]; called under the
+/// registry lock by every stream command.
+fn expire_idle_streams(streams: &mut HashMap<u64, StreamEntry>) {
+    streams.retain(|_, entry| entry.last_used.elapsed() < STREAM_IDLE_TIMEOUT);
+}
+
+/// Best-effort content type from the file extension, for the frontend's
+/// preview decision; unknown extensions report `application/octet-stream`.
+fn guess_mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("rtf") => "application/rtf",
+        Some("md" | "markdown") => "text/markdown",
+        Some("txt") => "text/plain",
+        Some("json") => "application/json",
+        Some("html" | "htm") => "text/html",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Response of the stream-management commands. The metadata fields are
+/// only set by [`open_file_stream`]; the other commands return just the
+/// id (or an error).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStreamResponse {
+    pub success: bool,
+    pub stream_id: Option<u64>,
+    /// File size in bytes.
+    pub size: Option<u64>,
+    /// Raw bytes per chunk, so the frontend need not hard-code it.
+    pub chunk_size: Option<u64>,
+    /// Chunks a full read takes (0 for an empty file).
+    pub chunk_count: Option<u64>,
+    /// Content type guessed from the file extension.
+    pub mime_type: Option<String>,
+    pub error: Option<String>,
+}
+
+impl FileStreamResponse {
+    fn ok(stream_id: u64) -> Self {
+        FileStreamResponse {
+            success: true,
+            stream_id: Some(stream_id),
+            size: None,
+            chunk_size: None,
+            chunk_count: None,
+            mime_type: None,
+            error: None,
+        }
+    }
+
+    fn err(message: impl std::fmt::Display) -> Self {
+        FileStreamResponse {
+            success: false,
+            stream_id: None,
+            size: None,
+            chunk_size: None,
+            chunk_count: None,
+            mime_type: None,
+            error: Some(message.to_string()),
+        }
+    }
+}
+
+/// Response of [`read_file_chunk`] and [`append_file_chunk`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChunkResponse {
+    pub success: bool,
+    /// Base64 chunk contents; only set by [`read_file_chunk`].
+    pub data: Option<String>,
+    /// Whether this chunk reaches the end of a read stream.
+    pub last: bool,
+    pub error: Option<String>,
+}
+
+impl FileChunkResponse {
+    fn err(message: impl std::fmt::Display) -> Self {
+        FileChunkResponse {
+            success: false,
+            data: None,
+            last: false,
+            error: Some(message.to_string()),
+        }
+    }
+}
+
+/// Open a file for chunked download. Returns a stream id plus the
+/// metadata the frontend needs to drive [`read_file_chunk`] and show
+/// progress, without ever holding the whole file in memory at once.
+/// Streams count against [`MAX_OPEN_STREAMS`] and expire after
+/// [`STREAM_IDLE_TIMEOUT`] without activity.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn open_file_stream(path: String) -> FileStreamResponse {
+    let file = match std::fs::File::open(&path) {
+        Ok(file) => file,
+        Err(e) => return FileStreamResponse::err(format!("cannot open {path}: {e}")),
+    };
+    let size = match file.metadata() {
+        Ok(metadata) => metadata.len(),
+        Err(e) => return FileStreamResponse::err(format!("cannot stat {path}: {e}")),
+    };
+    let max = SecurityLimits::default().max_input_size as u64;
+    if size > max {
+        return FileStreamResponse::err(format!(
+            "{path} is {size} bytes, over the {max} byte limit"
+        ));
+    }
+    let mut streams = lock_unpoisoned(streams());
+    expire_idle_streams(&mut streams);
+    if streams.len() >= MAX_OPEN_STREAMS {
+        return FileStreamResponse::err(format!(
+            "too many open streams (limit {MAX_OPEN_STREAMS})"
+        ));
+    }
+    let id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+    streams.insert(
+        id,
+        StreamEntry {
+            stream: FileStream::Read { file, size },
+            last_used: Instant::now(),
+        },
+    );
+    FileStreamResponse {
+        size: Some(size),
+        chunk_size: Some(FILE_CHUNK_SIZE),
+        chunk_count: Some(size.div_ceil(FILE_CHUNK_SIZE)),
+        mime_type: Some(guess_mime_type(Path::new(&path)).to_string()),
+        ..FileStreamResponse::ok(id)
+    }
+}
+
+/// Read one chunk of an open read stream as base64. Chunks may be
+/// fetched in any order and re-fetched; an index at or past the end
+/// returns an empty final chunk.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn read_file_chunk(stream_id: u64, chunk_index: u64) -> FileChunkResponse {
+    use std::io::{Read, Seek, SeekFrom};
+    let mut streams = lock_unpoisoned(streams());
+    expire_idle_streams(&mut streams);
+    let Some(entry) = streams.get_mut(&stream_id) else {
+        return FileChunkResponse::err(format!("no open stream {stream_id}"));
+    };
+    entry.last_used = Instant::now();
+    let FileStream::Read { file, size } = &mut entry.stream else {
+        return FileChunkResponse::err(format!("stream {stream_id} is a write stream"));
+    };
+    let start = chunk_index.saturating_mul(FILE_CHUNK_SIZE).min(*size);
+    let len = (*size - start).min(FILE_CHUNK_SIZE);
+    let mut bytes = vec![0u8; len as usize];
+    let read = file
+        .seek(SeekFrom::Start(start))
+        .and_then(|_| file.read_exact(&mut bytes));
+    match read {
+        Ok(()) => FileChunkResponse {
+            success: true,
+            data: Some(BASE64.encode(&bytes)),
+            last: start + len >= *size,
+            error: None,
+        },
+        Err(e) => FileChunkResponse::err(format!("cannot read stream {stream_id}: {e}")),
+    }
+}
+
+/// Start a chunked upload to `path`. Chunks accumulate in the same
+/// staging file [`safe_write`] uses; nothing appears at `path` itself
+/// until [`commit_file_write`] renames the staging file into place, so
+/// an upload that is closed or abandoned early is invisible.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn begin_file_write(path: String) -> FileStreamResponse {
+    let target = PathBuf::from(&path);
+    let staging = staging_path(&target);
+    let mut streams = lock_unpoisoned(streams());
+    expire_idle_streams(&mut streams);
+    if streams.len() >= MAX_OPEN_STREAMS {
+        return FileStreamResponse::err(format!(
+            "too many open streams (limit {MAX_OPEN_STREAMS})"
+        ));
+    }
+    let file = match std::fs::File::create(&staging) {
+        Ok(file) => file,
+        Err(e) => {
+            return FileStreamResponse::err(format!("cannot create {}: {e}", staging.display()))
+        }
+    };
+    let id = NEXT_STREAM_ID.fetch_add(1, Ordering::Relaxed);
+    streams.insert(
+        id,
+        StreamEntry {
+            stream: FileStream::Write {
+                file: Some(file),
+                staging,
+                target,
+            },
+            last_used: Instant::now(),
+        },
+    );
+    FileStreamResponse::ok(id)
+}
+
+/// Append one base64 chunk to an open write stream. Chunks are written
+/// in call order; a rejected chunk (bad base64, disk error) leaves the
+/// stream open so the frontend can retry or abort.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn append_file_chunk(stream_id: u64, data: String) -> FileChunkResponse {
+    use std::io::Write;
+    let bytes = match BASE64.decode(data.as_bytes()) {
+        Ok(bytes) => bytes,
+        Err(e) => return FileChunkResponse::err(format!("invalid base64 chunk: {e}")),
+    };
+    let mut streams = lock_unpoisoned(streams());
+    expire_idle_streams(&mut streams);
+    let Some(entry) = streams.get_mut(&stream_id) else {
+        return FileChunkResponse::err(format!("no open stream {stream_id}"));
+    };
+    entry.last_used = Instant::now();
+    let FileStream::Write {
+        file: Some(file), ..
+    } = &mut entry.stream
+    else {
+        return FileChunkResponse::err(format!("stream {stream_id} is a read stream"));
+    };
+    match file.write_all(&bytes) {
+        Ok(()) => FileChunkResponse {
+            success: true,
+            data: None,
+            last: false,
+            error: None,
+        },
+        Err(e) => FileChunkResponse::err(format!("cannot write stream {stream_id}: {e}")),
+    }
+}
+
+/// Finish a chunked upload: flush the staging file to disk and rename it
+/// over the target - the same commit [`safe_write`] performs. The target
+/// file appears (or changes) only when this returns success; on failure
+/// the staging file is deleted and the target is left untouched.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn commit_file_write(stream_id: u64) -> FileStreamResponse {
+    let mut streams = lock_unpoisoned(streams());
+    expire_idle_streams(&mut streams);
+    let Some(mut entry) = streams.remove(&stream_id) else {
+        return FileStreamResponse::err(format!("no open stream {stream_id}"));
+    };
+    match &mut entry.stream {
+        FileStream::Read { .. } => {
+            streams.insert(stream_id, entry);
+            FileStreamResponse::err(format!("stream {stream_id} is a read stream"))
+        }
+        FileStream::Write {
+            file,
+            staging,
+            target,
+        } => {
+            // The disk work happens with the entry out of the registry so
+            // other streams are not blocked behind a slow sync.
+            drop(streams);
+            let committed = match file.take() {
+                Some(file) => file.sync_all().map(|()| drop(file)),
+                None => Ok(()),
+            }
+            .and_then(|()| std::fs::rename(&*staging, writable_path(target)));
+            match committed {
+                Ok(()) => FileStreamResponse::ok(stream_id),
+                Err(e) => {
+                    FileStreamResponse::err(format!("cannot commit {}: {e}", target.display()))
+                }
+            }
+        }
+    }
+}
+
+/// Close a stream and release its resources. Closing an uncommitted
+/// write stream aborts it: the staging file is deleted and the target
+/// is left untouched.
+#[cfg_attr(feature = "gui", tauri::command)]
+pub fn close_file_stream(stream_id: u64) -> FileStreamResponse {
+    let mut streams = lock_unpoisoned(streams());
+    expire_idle_streams(&mut streams);
+    match streams.remove(&stream_id) {
+        Some(_) => FileStreamResponse::ok(stream_id),
+        None => FileStreamResponse::err(format!("no open stream {stream_id}")),
+    }
+}
+
+/// Invokes a macro with the full IPC command list, qualified from the
+/// crate root. `main.rs` feeds `tauri::generate_handler` through this,
+/// so the handler list lives next to the commands instead of drifting in
+/// a separate file: a new `#[tauri::command]` function is added here
+/// once and is thereby both registered and covered by the registry test
+/// below, which scans this module's source and fails when a command is
+/// missing from (or stale in) this list.
+#[macro_export]
+macro_rules! for_each_command {
+    ($action:ident) => {
+        $action![
+            $crate::commands::rtf_to_markdown_pipeline,
+            $crate::commands::rtf_to_markdown_pipeline_with_config,
+            $crate::commands::preview_rtf_page,
+            $crate::commands::preview_template,
+            $crate::commands::rtf_to_markdown,
+            $crate::commands::markdown_to_rtf,
+            $crate::commands::markdown_to_rtf_with_integrity,
+            $crate::commands::verify_integrity,
+            $crate::commands::normalize_markdown,
+            $crate::commands::get_outline,
+            $crate::commands::analyze_markdown,
+            $crate::commands::conversion_capabilities,
+            $crate::commands::control_word_support,
+            $crate::commands::tokenize_for_highlighting,
+            $crate::commands::run_selftest,
+            $crate::commands::start_conversion_job,
+            $crate::commands::conversion_job_status,
+            $crate::commands::cancel_conversion_job,
+            $crate::commands::validate_folder,
+            $crate::commands::create_session,
+            $crate::commands::update_session,
+            $crate::commands::get_output,
+            $crate::commands::close_session,
+            $crate::commands::request_preview,
+            $crate::commands::latest_preview,
+            $crate::commands::get_last_crash_report,
+            $crate::commands::convert_rtf_file_to_md,
+            $crate::commands::convert_large_file,
+            $crate::commands::large_file_progress,
+            $crate::commands::open_file_stream,
+            $crate::commands::read_file_chunk,
+            $crate::commands::begin_file_write,
+            $crate::commands::append_file_chunk,
+            $crate::commands::commit_file_write,
+            $crate::commands::close_file_stream,
+        ]
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects the registry as stringified paths, fed through
+    /// [`for_each_command!`](crate::for_each_command).
+    macro_rules! command_names {
+        ($($command:path),* $(,)?) => {
+            &[$(stringify!($command)),*]
+        };
+    }
+
+    const REGISTERED_COMMANDS: &[&str] = crate::for_each_command!(command_names);
+
+    /// The handler registry and the implemented commands must be equal
+    /// sets: a `#[tauri::command]` function missing from
+    /// `for_each_command!` would be "command not found" at runtime with
+    /// no compile-time signal, and a registered name without a function
+    /// is a stale entry.
+    #[test]
+    fn command_registry_matches_the_implemented_commands() {
+        let source = include_str!("commands.rs");
+        let mut implemented = Vec::new();
+        let mut pending_attr = false;
+        for line in source.lines() {
+            if line.contains("#[cfg_attr(feature = \"gui\", tauri::command)]") {
+                pending_attr = true;
+                continue;
+            }
+            if pending_attr {
+                if let Some(rest) = line.split("pub fn ").nth(1) {
+                    let name: String = rest
+                        .chars()
+                        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+                        .collect();
+                    implemented.push(name);
+                    pending_attr = false;
+                }
+            }
+        }
+        assert!(implemented.len() >= 33, "command scan broke: {implemented:?}");
+        let registered: Vec<&str> = REGISTERED_COMMANDS
+            .iter()
+            .map(|path| path.rsplit("::").next().unwrap().trim())
+            .collect();
+        for name in &implemented {
+            assert!(
+                registered.contains(&name.as_str()),
+                "command {name} is not registered in for_each_command!"
+            );
+        }
+        for name in &registered {
+            assert!(
+                implemented.iter().any(|i| i == name),
+                "registered command {name} no longer exists"
+            );
+        }
+        assert_eq!(registered.len(), implemented.len(), "duplicate entries");
+    }
+
+    #[test]
+    fn command_converts_rtf() {
+        let response = rtf_to_markdown("{\\rtf1 Hello\\par}".to_string(), None);
+        assert!(response.success);
+        assert_eq!(response.content.as_deref().map(str::trim), Some("Hello"));
+        assert_eq!(response.path, Some(ConversionPath::Simple));
+    }
+
+    #[test]
+    fn command_reports_errors() {
+        // A table document routes through the pipeline, whose validation
+        // rejects the missing RTF header.
+        let response = rtf_to_markdown("\\trowd no header".to_string(), None);
+        assert!(!response.success);
+        assert!(response.error.is_some());
+    }
+
+    #[test]
+    fn conversion_mode_forces_a_path_and_is_reported() {
+        let rtf = "{\\rtf1 \\trowd\\intbl A\\cell B\\cell\\row}";
+        let auto = rtf_to_markdown(rtf.to_string(), None);
+        assert_eq!(auto.path, Some(ConversionPath::Pipeline));
+        let simple = rtf_to_markdown(rtf.to_string(), Some(ConversionMode::Simple));
+        assert_eq!(simple.path, Some(ConversionPath::Simple));
+        assert_eq!(simple.content, auto.content);
+
+        let response = rtf_to_markdown_pipeline_with_config(
+            rtf.to_string(),
+            PipelineConfigRequest {
+                conversion_mode: Some(ConversionMode::Simple),
+                ..Default::default()
+            },
+        );
+        assert!(response.success);
+        assert_eq!(response.path, Some(ConversionPath::Simple));
+        assert_eq!(response.markdown, auto.content);
+    }
+
+    #[test]
+    fn markdown_to_rtf_command_works() {
+        let response = markdown_to_rtf("# Title".to_string());
+        assert!(response.success);
+        assert!(response.content.unwrap().starts_with("{\\rtf1"));
+    }
+
+    #[test]
+    fn pipeline_command_categorizes_validation_failures() {
+        let response = rtf_to_markdown_pipeline("not rtf at all".to_string());
+        assert!(!response.success);
+        assert_eq!(response.error_category.as_deref(), Some("validation"));
+        assert_eq!(response.error_code, -3);
+    }
+
+    #[test]
+    fn pipeline_command_categorizes_parse_failures() {
+        let response = rtf_to_markdown_pipeline("{\\rtf1 broken\\".to_string());
+        assert!(!response.success);
+        assert_eq!(response.error_category.as_deref(), Some("parse"));
+        assert_eq!(response.error_code, -2);
+    }
+
+    #[test]
+    fn failures_carry_the_shared_error_envelope() {
+        // The envelope must be exactly what the shared bridge builds for
+        // the same failure, so FFI and Tauri consumers see one contract.
+        let expected = ErrorEnvelope::from(
+            &DocumentPipeline::with_defaults()
+                .process("not rtf at all")
+                .unwrap_err(),
+        );
+        let response = rtf_to_markdown_pipeline("not rtf at all".to_string());
+        assert_eq!(response.error_envelope, Some(expected));
+
+        let response =
+            rtf_to_markdown("not rtf at all".to_string(), Some(ConversionMode::Pipeline));
+        let envelope = response.error_envelope.expect("envelope set");
+        assert_eq!(envelope.category, "validation");
+        assert_eq!(envelope.code, -3);
+        assert!(!envelope.recoverable);
+        // The loose string stays for older frontends.
+        assert_eq!(response.error.as_deref(), Some(envelope.message.as_str()));
+    }
+
+    #[test]
+    fn config_request_passes_page_range_through() {
+        let request = PipelineConfigRequest {
+            page_range: Some(PageRange { start: 2, end: 2 }),
+            ..Default::default()
+        };
+        let response = rtf_to_markdown_pipeline_with_config(
+            "{\\rtf1 P1\\par\\page P2\\par\\page P3\\par}".to_string(),
+            request,
+        );
+        assert!(response.success);
+        let markdown = response.markdown.unwrap();
+        assert!(markdown.contains("P2"), "{markdown}");
+        assert!(!markdown.contains("P1"), "{markdown}");
+    }
+
+    #[test]
+    fn future_contract_versions_in_config_requests_are_rejected() {
+        let request = PipelineConfigRequest {
+            v: Some(99),
+            ..Default::default()
+        };
+        let response =
+            rtf_to_markdown_pipeline_with_config("{\\rtf1 Hi\\par}".to_string(), request);
+        assert!(!response.success);
+        assert_eq!(response.error_code, -7);
+        let envelope = response.error_envelope.expect("envelope set");
+        assert_eq!(envelope.category, "contract");
+
+        // Missing `v` is contract version 1 and stays accepted.
+        let response = rtf_to_markdown_pipeline_with_config(
+            "{\\rtf1 Hi\\par}".to_string(),
+            PipelineConfigRequest::default(),
+        );
+        assert!(response.success);
+    }
+
+    #[test]
+    fn control_word_support_answers_single_words_and_the_full_table() {
+        let table = control_word_support(None);
+        assert!(table.len() > 50, "table suspiciously small: {}", table.len());
+
+        let hit = control_word_support(Some("pict".to_string()));
+        assert_eq!(hit.len(), 1);
+        assert_eq!(hit[0].name, "pict");
+
+        assert!(control_word_support(Some("nosuchword".to_string())).is_empty());
+    }
+
+    #[test]
+    fn highlighting_tokens_tile_the_input() {
+        let input = "{\\rtf1 Hello \\b World}";
+        let response = tokenize_for_highlighting(input.to_string());
+        assert!(response.success);
+        assert_eq!(response.tokens[0], ("group_start".to_string(), 0, 1));
+        let mut cursor = 0;
+        for (kind, start, end) in &response.tokens {
+            assert_eq!(*start, cursor, "gap before a {kind} token");
+            cursor = *end;
+        }
+        assert_eq!(cursor, input.len());
+
+        let response = tokenize_for_highlighting("broken\\".to_string());
+        assert!(!response.success);
+        assert!(response.tokens.is_empty());
+    }
+
+    #[test]
+    fn normalize_markdown_wraps_at_the_requested_column() {
+        let long = "This is a long sentence that certainly runs past the requested column width.";
+        let response = normalize_markdown(long.to_string(), Some(30));
+        assert!(response.success);
+        let content = response.content.unwrap();
+        assert!(content.lines().count() > 1, "{content}");
+        assert!(content.lines().all(|l| l.chars().count() <= 30), "{content}");
+
+        // Without a width the paragraph stays on one line.
+        let response = normalize_markdown(long.to_string(), None);
+        assert_eq!(response.content.unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn conversion_jobs_complete_and_report_their_response() {
+        let started = start_conversion_job("{\\rtf1 Hello\\par}".to_string(), None);
+        assert_eq!(started.status, "running");
+
+        let mut status = conversion_job_status(started.job_id);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while status.status == "running" {
+            assert!(std::time::Instant::now() < deadline, "job never finished");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            status = conversion_job_status(started.job_id);
+        }
+        assert_eq!(status.status, "completed");
+        let response = status.response.unwrap();
+        assert!(response.success);
+        assert!(response.markdown.unwrap().contains("Hello"));
+
+        assert_eq!(conversion_job_status(9_999_999).status, "unknown");
+    }
+
+    #[test]
+    fn conversion_jobs_can_be_cancelled_mid_flight() {
+        let rtf = format!(
+            "{{\\rtf1 {}}}",
+            "a long paragraph to keep the parser busy\\par ".repeat(200_000)
+        );
+        let started = start_conversion_job(rtf, None);
+        let cancelled = cancel_conversion_job(started.job_id);
+        assert!(cancelled.status == "running" || cancelled.status == "cancelled");
+
+        let mut status = conversion_job_status(started.job_id);
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while status.status == "running" {
+            assert!(std::time::Instant::now() < deadline, "job never stopped");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            status = conversion_job_status(started.job_id);
+        }
+        assert_eq!(status.status, "cancelled");
+        let response = status.response.unwrap();
+        assert!(!response.success);
+        assert_eq!(response.error_code, -6);
+    }
+
+    #[test]
+    fn validate_folder_aggregates_per_file_verdicts() {
+        let dir = std::env::temp_dir().join(format!("lb-validate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("clean.rtf"), "{\\rtf1 Hello\\par}").unwrap();
+        std::fs::write(dir.join("repaired.rtf"), "{\\rtf1 before}} after\\par}").unwrap();
+        std::fs::write(dir.join("broken.rtf"), "not rtf at all").unwrap();
+        std::fs::write(dir.join("ignored.txt"), "skipped").unwrap();
+
+        let response = validate_folder(dir.to_string_lossy().into_owned(), None);
+        assert!(response.success);
+        assert_eq!(response.total, 3);
+        assert_eq!(response.valid, 2);
+        assert_eq!(response.needing_recovery, 1);
+        // Reports follow file order and carry per-file diagnostics.
+        assert_eq!(response.files[0].file, "broken.rtf");
+        assert!(!response.files[0].valid);
+        assert!(response.files[0].error.is_some());
+        assert_eq!(response.files[2].file, "repaired.rtf");
+        assert!(!response.files[2].recovery_actions.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let response = validate_folder("/nonexistent/lb-validate".to_string(), None);
+        assert!(!response.success);
+        assert!(response.error.is_some());
+    }
+
+    #[test]
+    fn validate_folder_counts_files_that_would_fail_strict() {
+        let dir = std::env::temp_dir().join(format!("lb-strict-delta-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("clean.rtf"), "{\\rtf1 Hello\\par}").unwrap();
+        std::fs::write(dir.join("unclosed.rtf"), "{\\rtf1 left open\\par").unwrap();
+
+        let request = PipelineConfigRequest {
+            compare_validation: Some(true),
+            ..Default::default()
+        };
+        let response = validate_folder(dir.to_string_lossy().into_owned(), Some(request));
+        assert!(response.success);
+        assert_eq!(response.valid, 2);
+        assert_eq!(response.would_fail_strict, 1);
+        assert_eq!(response.files[1].file, "unclosed.rtf");
+        assert_eq!(response.files[1].strict_delta[0].code, "RTF004");
+
+        // Without the option the delta stays empty and nothing is counted.
+        let response = validate_folder(dir.to_string_lossy().into_owned(), None);
+        assert_eq!(response.would_fail_strict, 0);
+        assert!(response.files[1].strict_delta.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn file_conversion_writes_the_requested_encoding() {
+        use crate::conversion::encoding::LineEnding;
+
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("lb-enc-in-{}.rtf", std::process::id()));
+        let output = dir.join(format!("lb-enc-out-{}.md", std::process::id()));
+        std::fs::write(&input, "{\\rtf1 A\\par B\\par}").unwrap();
+
+        let response = convert_rtf_file_to_md(
+            input.to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+            Some(OutputEncoding {
+                line_ending: LineEnding::CrLf,
+                bom: true,
+                trailing_newline: true,
+            }),
+            None,
+        );
+        assert!(response.success, "{:?}", response.error);
+        let bytes = std::fs::read(&output).unwrap();
+        assert!(bytes.starts_with(&[0xEF, 0xBB, 0xBF]), "missing BOM");
+        assert!(bytes.ends_with(b"\r\n"), "missing trailing CRLF");
+        assert!(!bytes.windows(2).any(|w| w[1] == b'\n' && w[0] != b'\r'));
+
+        let response = convert_rtf_file_to_md(
+            input.to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+            Some(OutputEncoding {
+                line_ending: LineEnding::Lf,
+                bom: false,
+                trailing_newline: false,
+            }),
+            None,
+        );
+        assert!(response.success);
+        let bytes = std::fs::read(&output).unwrap();
+        assert!(!bytes.starts_with(&[0xEF, 0xBB, 0xBF]));
+        assert!(!bytes.contains(&b'\r'));
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn large_files_convert_through_the_file_backed_path() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("lb-large-in-{}.rtf", std::process::id()));
+        let output = dir.join(format!("lb-large-out-{}.md", std::process::id()));
+
+        // A ~30MB document: three times the inline limit.
+        let target = 30 * 1024 * 1024;
+        let mut rtf = String::with_capacity(target + 64);
+        rtf.push_str("{\\rtf1 ");
+        // Plain paragraphs: a run of formatting toggles this long would
+        // trip the RTF106 format-run cap, which is not what is under test.
+        while rtf.len() < target {
+            rtf.push_str("body text for the large file-backed fixture\\par ");
+        }
+        rtf.push('}');
+        std::fs::write(&input, &rtf).unwrap();
+
+        // Output verification re-parses the whole document; what this
+        // test measures is the file-backed path, not the verifier.
+        let config = PipelineConfigRequest {
+            verify_output: Some(false),
+            ..Default::default()
+        };
+        let mut stages = Vec::new();
+        let response = convert_large_file_with_progress(
+            &input.to_string_lossy(),
+            &output.to_string_lossy(),
+            Some(config),
+            None,
+            |progress| stages.push(progress.stage),
+        );
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(stages, ["reading", "converting", "writing", "done"]);
+        let written = std::fs::metadata(&output).unwrap().len();
+        assert!(written > 0);
+        assert_eq!(response.bytes_written, Some(written));
+
+        // Under the memory-accounting feature, hold the conversion to a
+        // small multiple of the document instead of an unbounded pile of
+        // intermediate copies.
+        if let Some(peak) = response.peak_memory_bytes {
+            assert!(
+                peak < 12 * rtf.len(),
+                "file-backed conversion peaked at {peak} bytes for a {} byte input",
+                rtf.len()
+            );
+        }
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn file_backed_conversion_enforces_its_own_limit() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("lb-large-cap-{}.rtf", std::process::id()));
+        let output = dir.join(format!("lb-large-cap-{}.md", std::process::id()));
+        std::fs::write(&input, "{\\rtf1 small but over the custom cap\\par}").unwrap();
+
+        let response = convert_large_file(
+            input.to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+            None,
+            Some(16),
+        );
+        assert!(!response.success);
+        assert!(
+            response.error.as_deref().unwrap().contains("file-backed limit"),
+            "{:?}",
+            response.error
+        );
+        assert!(!output.exists());
+
+        // A missing input fails before any stage runs.
+        let response = convert_large_file(
+            dir.join("lb-large-missing.rtf").to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+            None,
+            None,
+        );
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("cannot read"));
+
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn oversized_inline_content_is_directed_to_the_file_backed_path() {
+        let mut content = String::from("{\\rtf1 ");
+        content.push_str(&"x".repeat(pipeline::validation::MAX_DOCUMENT_SIZE));
+        content.push('}');
+
+        // The distinct code (not RTF002) tells the frontend to retry via
+        // convert_large_file instead of reporting a broken document.
+        let response = rtf_to_markdown(content.clone(), None);
+        assert!(!response.success);
+        let envelope = response.error_envelope.unwrap();
+        assert_eq!(envelope.validation_code.as_deref(), Some("RTF121"));
+        assert!(envelope.message.contains("convert_large_file"));
+
+        let response = rtf_to_markdown_pipeline_with_config(
+            content.clone(),
+            PipelineConfigRequest::default(),
+        );
+        assert_eq!(
+            response.error_envelope.unwrap().validation_code.as_deref(),
+            Some("RTF121")
+        );
+
+        let started = start_conversion_job(content, None);
+        assert_eq!(started.status, "completed");
+        let envelope = started.response.unwrap().error_envelope.unwrap();
+        assert_eq!(envelope.validation_code.as_deref(), Some("RTF121"));
+    }
+
+    #[test]
+    fn session_commands_round_trip_an_edit() {
+        let created = create_session("{\\rtf1 Hello {\\b World}\\par}".to_string());
+        assert!(created.success);
+        let id = created.session_id.unwrap();
+
+        let start = "{\\rtf1 Hello {\\b ".len();
+        let updated = update_session(id, start, start + 5, "Editor".to_string());
+        assert!(updated.success, "{:?}", updated.error);
+
+        let output = get_output(id);
+        assert!(output.success);
+        assert!(output.content.unwrap().contains("**Editor**"));
+
+        assert!(close_session(id).success);
+        assert!(!get_output(id).success);
+    }
+
+    #[test]
+    fn rapid_preview_requests_coalesce_to_at_most_two_conversions() {
+        let created = create_session("{\\rtf1 seed\\par}".to_string());
+        let id = created.session_id.unwrap();
+
+        // Large enough that one conversion far outlasts the enqueue loop;
+        // contents are pre-built so enqueueing is just a pointer swap.
+        let filler = "some plain paragraph text to chew through\\par ".repeat(20_000);
+        let contents: Vec<String> = (0..50)
+            .map(|i| format!("{{\\rtf1 {filler}edit {i}\\par}}"))
+            .collect();
+        let mut last_seq = 0;
+        for content in contents {
+            let accepted = request_preview(id, content);
+            assert!(accepted.success, "{:?}", accepted.error);
+            last_seq = accepted.seq;
+        }
+        assert_eq!(last_seq, 50);
+
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(30);
+        let settled = loop {
+            let polled = latest_preview(id);
+            if polled.seq == last_seq && !polled.busy {
+                break polled;
+            }
+            assert!(std::time::Instant::now() < deadline, "preview never settled");
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        };
+
+        // Superseded requests coalesced away: at most the one in flight
+        // when typing started plus the final conversion actually ran.
+        assert!(
+            settled.conversions_run <= 2,
+            "{} conversions ran",
+            settled.conversions_run
+        );
+        let response = settled.response.unwrap();
+        assert!(response.success, "{:?}", response.error);
+        assert!(response.markdown.unwrap().contains("edit 49"));
+
+        assert!(close_session(id).success);
+        assert!(!request_preview(id, "{\\rtf1 gone\\par}".to_string()).success);
+    }
+
+    #[test]
+    fn preview_template_reports_the_diff() {
+        let mut variables = HashMap::new();
+        variables.insert("party_a".to_string(), "Acme".to_string());
+        variables.insert("party_b".to_string(), "Widgets Ltd".to_string());
+        let response = preview_template(
+            "{\\rtf1 \\outlinelevel0 Scope\\par \\pard Work.\\par}".to_string(),
+            "contract".to_string(),
+            Some(variables),
+        );
+        assert!(response.success, "{:?}", response.error);
+        let diff = response.diff.unwrap();
+        assert!(diff
+            .footer
+            .as_deref()
+            .unwrap()
+            .contains("Acme / Widgets Ltd"));
+        assert!(diff.unresolved_variables.is_empty(), "{diff:?}");
+
+        let response = preview_template(
+            "{\\rtf1 Hi\\par}".to_string(),
+            "nonexistent".to_string(),
+            None,
+        );
+        assert!(!response.success);
+    }
+
+    #[test]
+    fn dry_run_flag_surfaces_the_diff_in_the_pipeline_response() {
+        let request = PipelineConfigRequest {
+            template: Some("report".to_string()),
+            dry_run: Some(true),
+            ..Default::default()
+        };
+        let response = rtf_to_markdown_pipeline_with_config(
+            "{\\rtf1 Total 1234.5\\par}".to_string(),
+            request,
+        );
+        assert!(response.success);
+        assert!(response.markdown.unwrap().contains("1234.5"));
+        assert!(response.template_diff.is_some());
+    }
+
+    #[test]
+    fn analyze_markdown_reports_required_extensions() {
+        let response =
+            analyze_markdown("# Doc\n\n![img](logo.png)\n\n<br>\n".to_string());
+        assert!(response.success);
+        let analysis = response.analysis.unwrap();
+        assert_eq!(analysis.images.relative, 1);
+        assert_eq!(
+            analysis.required_extensions,
+            vec!["images", "inline_html"]
+        );
+    }
+
+    #[test]
+    fn get_outline_returns_entries_with_offsets() {
+        let response = get_outline(
+            "{\\rtf1 \\outlinelevel0 Intro\\par \\pard Body\\par \
+             \\outlinelevel1 Intro\\par}"
+                .to_string(),
+        );
+        assert!(response.success, "{:?}", response.error);
+        assert_eq!(response.outline.len(), 2);
+        // Duplicate titles still get distinct slugs.
+        assert_eq!(response.outline[0].slug, "intro");
+        assert_eq!(response.outline[1].slug, "intro-1");
+        // Offsets line up with the pipeline's Markdown output.
+        let markdown = rtf_to_markdown_pipeline(
+            "{\\rtf1 \\outlinelevel0 Intro\\par \\pard Body\\par \
+             \\outlinelevel1 Intro\\par}"
+                .to_string(),
+        )
+        .markdown
+        .unwrap();
+        let offset = response.outline[1].char_offset;
+        assert_eq!(markdown.chars().nth(offset), Some('#'), "got: {markdown}");
+
+        assert!(!get_outline("not rtf".to_string()).success);
+    }
+
+    #[test]
+    fn preview_pages_through_a_document() {
+        let rtf = "{\\rtf1 P1\\par\\page P2\\par\\page P3\\par}".to_string();
+        let response = preview_rtf_page(rtf.clone(), 3);
+        assert!(response.success);
+        assert_eq!(response.page_count, 3);
+        assert!(response.markdown.unwrap().contains("P3"));
+
+        let response = preview_rtf_page(rtf, 4);
+        assert!(!response.success);
+        assert_eq!(response.page_count, 3);
+    }
+
+    #[test]
+    fn input_encoding_override_fixes_cp866_files() {
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("lb-cp866-in-{}.rtf", std::process::id()));
+        let output = dir.join(format!("lb-cp866-out-{}.md", std::process::id()));
+        // "Привет" in cp866, inside an otherwise ASCII RTF shell.
+        let mut rtf = b"{\\rtf1 ".to_vec();
+        rtf.extend_from_slice(&[0x8F, 0xE0, 0xA8, 0xA2, 0xA5, 0xE2]);
+        rtf.extend_from_slice(b"\\par}");
+        std::fs::write(&input, &rtf).unwrap();
+
+        // Auto-detection falls back to windows-1252 and garbles the text.
+        let response = convert_rtf_file_to_md(
+            input.to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+            None,
+            None,
+        );
+        assert!(response.success, "{:?}", response.error);
+        assert!(!std::fs::read_to_string(&output).unwrap().contains("Привет"));
+
+        // The override decodes it correctly.
+        let response = convert_rtf_file_to_md(
+            input.to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+            None,
+            Some("cp866".to_string()),
+        );
+        assert!(response.success, "{:?}", response.error);
+        assert!(std::fs::read_to_string(&output).unwrap().contains("Привет"));
+
+        // Unsupported names fail fast, before anything is read or written.
+        let response = convert_rtf_file_to_md(
+            input.to_string_lossy().into_owned(),
+            output.to_string_lossy().into_owned(),
+            None,
+            Some("ebcdic".to_string()),
+        );
+        assert!(!response.success);
+        assert!(response.error.unwrap().contains("unsupported input encoding"));
+
+        std::fs::remove_file(&input).unwrap();
+        std::fs::remove_file(&output).unwrap();
+    }
+
+    #[test]
+    fn capabilities_list_the_supported_input_encodings() {
+        let capabilities = conversion_capabilities(None);
+        assert!(capabilities
+            .supported_input_encodings
+            .contains(&"cp866".to_string()));
+    }
+
+    #[test]
+    fn selftest_command_runs_the_selected_checks() {
+        use legacybridge_core::selftest::{CHECK_ROUND_TRIP, CHECK_SIMD};
+        let report = run_selftest(Some(CHECK_ROUND_TRIP | CHECK_SIMD));
+        assert!(report.passed, "{report:#?}");
+        assert_eq!(report.checks.len(), 2);
+        assert_eq!(run_selftest(None).checks.len(), 7);
+    }
+
+    #[test]
+    fn chunked_read_reassembles_the_exact_bytes() {
+        // ~5MB patterned payload, deliberately not a multiple of the
+        // chunk size so the final chunk is short.
+        let bytes: Vec<u8> = (0..5 * 1024 * 1024 + 137)
+            .map(|i: u32| (i.wrapping_mul(31) % 251) as u8)
+            .collect();
+        let path = std::env::temp_dir().join(format!("lb-stream-read-{}.bin", std::process::id()));
+        std::fs::write(&path, &bytes).unwrap();
+
+        let opened = open_file_stream(path.to_string_lossy().into_owned());
+        assert!(opened.success, "{:?}", opened.error);
+        assert_eq!(opened.size, Some(bytes.len() as u64));
+        assert_eq!(opened.mime_type.as_deref(), Some("application/octet-stream"));
+        let stream_id = opened.stream_id.unwrap();
+        let chunk_count = opened.chunk_count.unwrap();
+        assert_eq!(
+            chunk_count,
+            (bytes.len() as u64).div_ceil(opened.chunk_size.unwrap())
+        );
+
+        let mut reassembled = Vec::new();
+        for index in 0..chunk_count {
+            let chunk = read_file_chunk(stream_id, index);
+            assert!(chunk.success, "{:?}", chunk.error);
+            assert_eq!(chunk.last, index == chunk_count - 1);
+            reassembled.extend(BASE64.decode(chunk.data.unwrap()).unwrap());
+        }
+        assert_eq!(reassembled, bytes);
+
+        assert!(close_file_stream(stream_id).success);
+        assert!(!read_file_chunk(stream_id, 0).success);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn chunked_write_commits_atomically_or_not_at_all() {
+        let target =
+            std::env::temp_dir().join(format!("lb-stream-write-{}.bin", std::process::id()));
+
+        // Abort: append, then close without committing - neither the
+        // target nor the staging file may exist afterwards.
+        let begun = begin_file_write(target.to_string_lossy().into_owned());
+        assert!(begun.success, "{:?}", begun.error);
+        let stream_id = begun.stream_id.unwrap();
+        assert!(append_file_chunk(stream_id, BASE64.encode(b"partial")).success);
+        assert!(!target.exists());
+        assert!(close_file_stream(stream_id).success);
+        assert!(!target.exists());
+        assert!(!staging_path(&target).exists());
+
+        // Commit: the reassembled bytes appear only at commit time.
+        let begun = begin_file_write(target.to_string_lossy().into_owned());
+        let stream_id = begun.stream_id.unwrap();
+        assert!(!append_file_chunk(stream_id, "not base64!!".to_string()).success);
+        for chunk in [&b"first "[..], b"second ", b"third"] {
+            assert!(append_file_chunk(stream_id, BASE64.encode(chunk)).success);
+        }
+        assert!(!target.exists());
+        assert!(commit_file_write(stream_id).success);
+        assert_eq!(std::fs::read(&target).unwrap(), b"first second third");
+        assert!(!staging_path(&target).exists());
+        // The stream is gone once committed.
+        assert!(!commit_file_write(stream_id).success);
+        std::fs::remove_file(&target).unwrap();
+    }
+}
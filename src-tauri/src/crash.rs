@@ -0,0 +1,168 @@
+//! Crash reporting for the desktop app.
+//!
+//! [`install`] chains a panic hook that writes a JSON crash report -
+//! timestamp, panic message, backtrace, crate version and the conversion
+//! breadcrumb from the panicking thread - to the app data directory, so a
+//! crash during a conversion leaves an actionable artifact. Document
+//! content is never included: the breadcrumb carries only a size, a hash
+//! and a stage name. The newest report is surfaced on the next start
+//! through the `get_last_crash_report` command in [`crate::commands`].
+
+use legacybridge_core::conversion::breadcrumb::{self, Breadcrumb};
+use legacybridge_core::sync::lock_unpoisoned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, Once};
+
+/// Where reports are written; `None` until [`install`].
+static REPORT_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Panics observed by this process since startup.
+static PANICS_TOTAL: AtomicU64 = AtomicU64::new(0);
+
+/// One crash, as written to disk and offered back on the next start.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrashReport {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub version: String,
+    pub thread: String,
+    /// Panic message with its source location.
+    pub message: String,
+    pub backtrace: String,
+    /// The conversion in flight on the panicking thread, if any: input
+    /// size, content hash and pipeline stage - never document content.
+    pub operation: Option<Breadcrumb>,
+}
+
+/// Default per-user data directory for crash reports.
+pub fn default_report_dir() -> PathBuf {
+    let data_dir = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+    data_dir
+        .unwrap_or_else(std::env::temp_dir)
+        .join("legacybridge")
+        .join("crashes")
+}
+
+/// Install the crash-reporting panic hook, writing reports under `dir`.
+/// The hook is installed once and chains whatever hook was already in
+/// place; later calls only redirect the report directory.
+pub fn install(dir: impl Into<PathBuf>) {
+    *lock_unpoisoned(&REPORT_DIR) = Some(dir.into());
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            write_report(info);
+            previous(info);
+        }));
+    });
+}
+
+/// Panics observed by this process since startup.
+pub fn panics_total() -> u64 {
+    PANICS_TOTAL.load(Ordering::SeqCst)
+}
+
+/// The newest crash report in the configured directory, if any.
+pub fn last_report() -> Option<CrashReport> {
+    let dir = lock_unpoisoned(&REPORT_DIR).clone()?;
+    let newest = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.starts_with("crash-") && name.ends_with(".json")
+        })
+        .max_by_key(|entry| entry.metadata().and_then(|m| m.modified()).ok())?;
+    let json = std::fs::read_to_string(newest.path()).ok()?;
+    serde_json::from_str(&json).ok()
+}
+
+/// Write one report. Best effort throughout: crash reporting must never
+/// panic itself, and a failed write only loses the artifact.
+fn write_report(info: &std::panic::PanicHookInfo<'_>) {
+    let sequence = PANICS_TOTAL.fetch_add(1, Ordering::SeqCst);
+    let Some(dir) = lock_unpoisoned(&REPORT_DIR).clone() else {
+        return;
+    };
+    let message = info
+        .payload()
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| info.payload().downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "panic".to_string());
+    let report = CrashReport {
+        timestamp: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        thread: std::thread::current().name().unwrap_or("<unnamed>").to_string(),
+        message: match info.location() {
+            Some(location) => format!("{message} at {location}"),
+            None => message,
+        },
+        backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+        operation: breadcrumb::active(),
+    };
+    let _ = std::fs::create_dir_all(&dir);
+    if let Ok(json) = serde_json::to_string_pretty(&report) {
+        let _ = std::fs::write(
+            dir.join(format!("crash-{}-{sequence}.json", report.timestamp)),
+            json,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use legacybridge_core::conversion::pipeline::DocumentPipeline;
+
+    #[test]
+    fn a_panicking_conversion_leaves_a_report_with_the_breadcrumb() {
+        let dir = std::env::temp_dir().join(format!("lb-crash-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        install(&dir);
+
+        let input = "{\\rtf1 crash me\\par}";
+        std::thread::Builder::new()
+            .name("crash-worker".to_string())
+            .spawn(move || {
+                let pipeline = DocumentPipeline::with_defaults()
+                    .register_pre_generate("exploder", |_, _| panic!("synthetic crash"));
+                // The pipeline isolates hook panics, but the panic hook
+                // still runs and must capture the report.
+                let _ = pipeline.process(input);
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+
+        let report = last_report().expect("report written");
+        assert!(report.message.contains("synthetic crash"), "{}", report.message);
+        assert_eq!(report.thread, "crash-worker");
+        assert_eq!(report.version, env!("CARGO_PKG_VERSION"));
+        assert!(!report.backtrace.is_empty());
+        // The report carries sizes and hashes only, never content.
+        assert!(!serde_json::to_string(&report).unwrap().contains("crash me"));
+        let operation = report.operation.expect("breadcrumb captured");
+        assert_eq!(operation.input_bytes, input.len());
+        assert_ne!(operation.input_hash, 0);
+        assert_eq!(operation.stage, "transform");
+        assert!(panics_total() >= 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,12 @@
+//! LegacyBridge - a lightweight RTF <-> Markdown converter designed for
+//! legacy systems (VB6, VFP9) and modern desktop use via Tauri.
+//!
+//! The conversion logic itself lives in the shared `legacybridge-core`
+//! crate; this crate adds the Tauri command layer and desktop glue.
+
+pub mod commands;
+pub mod crash;
+
+pub use legacybridge_core::conversion;
+pub use legacybridge_core::security;
+pub use legacybridge_core::{markdown_to_rtf, rtf_to_markdown, ConversionResult};
@@ -0,0 +1,20 @@
+#![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
+
+#[cfg(feature = "gui")]
+fn main() {
+    use tauri::generate_handler;
+
+    legacybridge_app::crash::install(legacybridge_app::crash::default_report_dir());
+    tauri::Builder::default()
+        // The command list lives next to the commands themselves;
+        // `for_each_command!` keeps this handler, the implementation and
+        // the registry test in lockstep.
+        .invoke_handler(legacybridge_app::for_each_command!(generate_handler))
+        .run(tauri::generate_context!())
+        .expect("error while running LegacyBridge");
+}
+
+#[cfg(not(feature = "gui"))]
+fn main() {
+    eprintln!("legacybridge was built without the `gui` feature; nothing to run");
+}